@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use std::sync::Arc;
+
+use agent_safe_spl::compiled::CompiledPolicy;
+use agent_safe_spl::evaluator::{eval_policy, fold_constants};
+use agent_safe_spl::parser::parse;
+use agent_safe_spl::types::{CallbackState, CryptoCallbacks, Env, Node};
+
+const FAMILY_GIFTS: &str = r#"(and
+  (= (get req "actor_pub") "K_ai")
+  (= (get req "action") "payments.create")
+  (<= (get req "amount") 50)
+  (member (get req "recipient") allowed_recipients)
+  (= (get req "purpose") "giftcard")
+  (<= (per-day-count "payments.create" (get req "day")) 1)
+  (get req "device_attested")
+  (dpop_ok?)
+  (merkle_ok? (tuple (get req "actor_pub") (get req "action") (get req "recipient") 50 "giftcard" (get req "day")))
+  (vrf_ok? (get req "day") (get req "amount"))
+)"#;
+
+const SIMPLE: &str = r#"(and (= (get req "action") "read") (<= (get req "amount") 100))"#;
+
+const CONSTANT_HEAVY: &str = r#"(and (<= 10 100) (= "a" "a") (not #f) (in-range (get req "amount") 0 75) (>= 5 1))"#;
+
+fn bench_env() -> Env {
+    let mut req = HashMap::new();
+    req.insert("actor_pub".into(), Node::Str("K_ai".into()));
+    req.insert("action".into(), Node::Str("payments.create".into()));
+    req.insert("recipient".into(), Node::Str("niece@example.com".into()));
+    req.insert("purpose".into(), Node::Str("giftcard".into()));
+    req.insert("amount".into(), Node::Number(50.0));
+    req.insert("day".into(), Node::Str("2025-09-29".into()));
+    req.insert("device_attested".into(), Node::Bool(true));
+
+    let mut vars = HashMap::new();
+    vars.insert(
+        "allowed_recipients".into(),
+        Node::List((0..32).map(|i| Node::Str(format!("user{i}@example.com"))).chain([Node::Str("niece@example.com".into())]).collect()),
+    );
+
+    Env {
+        req,
+        vars,
+        crypto: CryptoCallbacks {
+            dpop_ok: CallbackState::Configured(Arc::new(|| true)),
+            merkle_ok: Some(Arc::new(|_| true)),
+            vrf_ok: CallbackState::Configured(Arc::new(|_, _| true)),
+            thresh_ok: CallbackState::Configured(Arc::new(|| true)),
+        },
+        ..Env::default()
+    }
+}
+
+fn bench_parse(c: &mut Criterion) {
+    c.bench_function("parse/family_gifts", |b| b.iter(|| parse(black_box(FAMILY_GIFTS)).unwrap()));
+    c.bench_function("parse/simple", |b| b.iter(|| parse(black_box(SIMPLE)).unwrap()));
+}
+
+fn bench_eval(c: &mut Criterion) {
+    let env = bench_env();
+    for (name, src) in [("family_gifts", FAMILY_GIFTS), ("simple", SIMPLE), ("constant_heavy", CONSTANT_HEAVY)] {
+        let ast = parse(src).unwrap();
+        c.bench_function(&format!("eval/{name}"), |b| b.iter(|| eval_policy(black_box(&ast), &env).unwrap()));
+        let folded = fold_constants(&ast);
+        c.bench_function(&format!("eval_folded/{name}"), |b| b.iter(|| eval_policy(black_box(&folded), &env).unwrap()));
+        let compiled = CompiledPolicy::compile(&folded).unwrap();
+        c.bench_function(&format!("eval_compiled/{name}"), |b| b.iter(|| black_box(&compiled).eval(&env).unwrap()));
+        c.bench_function(&format!("parse_eval/{name}"), |b| {
+            b.iter(|| eval_policy(&parse(black_box(src)).unwrap(), &env).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, bench_parse, bench_eval);
+criterion_main!(benches);
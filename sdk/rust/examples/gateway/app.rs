@@ -0,0 +1,249 @@
+//! Reference payments gateway: token intake, PoP, policy evaluation with a
+//! usage store, obligations, receipts, and audit, composed the way a
+//! relying service is meant to compose them.
+//!
+//! `POST /payments` takes a JSON [`PaymentBody`] carrying the agent's token:
+//!
+//! 1. Without an `Agent-Safe-Presentation` header the gateway answers 401
+//!    with a fresh `Agent-Safe-Challenge`.
+//! 2. The agent signs the challenge with its PoP key and retries, setting
+//!    the header's `evd` to the SHA-256 of the body.
+//! 3. The gateway takes the challenge (each is answered once), checks the
+//!    body digest and issuer, and verifies the token against the payment
+//!    with its [`UsageLedger`] backing `cumulative-spend`.
+//! 4. An allow records the spend and the decision's obligations, appends
+//!    `payment.allowed` to the audit chain, and returns a [`Receipt`] naming
+//!    that entry and the request's hash. A deny appends `payment.denied` and
+//!    returns 403. Both entries commit to the request they decided.
+//!
+//! `POST /obligations/{token}/{kind}` discharges an obligation and
+//! `GET /audit` returns the chain for [`agent_safe_spl::audit::verify_chain`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use agent_safe_spl::http::{evidence_digest, token_ref, ChallengeHeader, PresentationHeader, CHALLENGE_HEADER, PRESENTATION_HEADER};
+use agent_safe_spl::issuers::{check_issuer, TrustedIssuers, TrustedKey};
+use agent_safe_spl::obligations::{Obligation, ObligationTracker};
+use agent_safe_spl::profile::{Verifier, VerifierProfile};
+use agent_safe_spl::request::request_hash;
+use agent_safe_spl::spend::SpendTracker;
+use agent_safe_spl::time::{format_rfc3339, Clock, FixedClock, SystemClock};
+use agent_safe_spl::token::Token;
+use agent_safe_spl::types::Node;
+use agent_safe_spl::usage::UsageLedger;
+
+/// How long an issued challenge may be answered, in seconds.
+pub const CHALLENGE_TTL_SECS: i64 = 300;
+
+/// Body of `POST /payments`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentBody {
+    pub token: Token,
+    pub recipient: String,
+    pub amount: f64,
+    /// ISO 4217 code.
+    pub currency: String,
+}
+
+/// Returned for an allowed payment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// [`token_ref`] of the token that authorized the payment.
+    pub token: String,
+    pub recipient: String,
+    pub amount: f64,
+    pub currency: String,
+    /// UTC day the spend counts against.
+    pub day: String,
+    /// [`request_hash`] of the request the policy allowed.
+    pub request_hash: String,
+    /// Obligations the gateway now tracks for this token.
+    pub obligations: Vec<Obligation>,
+    /// Sequence number and hash of the `payment.allowed` audit entry.
+    pub audit_seq: u64,
+    pub audit_hash: String,
+}
+
+/// Shared gateway state.
+pub struct Gateway {
+    /// Issuer keys whose tokens the gateway honours.
+    issuers: Arc<TrustedIssuers>,
+    audience: String,
+    clock: Box<dyn Clock + Send + Sync>,
+    challenges: Mutex<HashMap<String, ChallengeHeader>>,
+    usage: Arc<UsageLedger>,
+    obligations: ObligationTracker,
+}
+
+impl Gateway {
+    /// A gateway honouring tokens from the given Ed25519 issuer public keys (hex).
+    pub fn new(issuers: Vec<String>, audience: &str) -> Self {
+        Self::with_issuers(issuers.iter().map(|k| TrustedKey::new(k)).collect(), audience)
+    }
+
+    pub fn with_issuers(issuers: TrustedIssuers, audience: &str) -> Self {
+        Self {
+            issuers: Arc::new(issuers),
+            audience: audience.to_string(),
+            clock: Box::new(SystemClock),
+            challenges: Mutex::new(HashMap::new()),
+            usage: Arc::new(UsageLedger::new()),
+            obligations: ObligationTracker::new(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// The ledger of allowed spend, for reporting and persistence.
+    pub fn usage(&self) -> &UsageLedger {
+        &self.usage
+    }
+
+    pub fn obligations(&self) -> &ObligationTracker {
+        &self.obligations
+    }
+
+    /// Issue a challenge and return it as a 401.
+    fn challenge(&self, now: i64, message: &str) -> Response {
+        let challenge = ChallengeHeader::generate(Some(&self.audience), now, CHALLENGE_TTL_SECS);
+        let header = challenge.encode();
+        let mut challenges = self.challenges.lock().unwrap_or_else(|e| e.into_inner());
+        challenges.retain(|_, c| !c.is_expired(now));
+        challenges.insert(challenge.nonce.clone(), challenge);
+        (StatusCode::UNAUTHORIZED, [(CHALLENGE_HEADER, header)], Json(json!({ "error": message }))).into_response()
+    }
+
+    fn pay(&self, headers: &HeaderMap, body: &[u8]) -> Response {
+        let now = self.clock.now_unix();
+        let payment: PaymentBody = match serde_json::from_slice(body) {
+            Ok(payment) => payment,
+            Err(e) => return error(StatusCode::BAD_REQUEST, "malformed_body", &e.to_string()),
+        };
+        let token = &payment.token;
+        if let Err(e) = check_issuer(self.issuers.as_ref(), token, now) {
+            return error(StatusCode::FORBIDDEN, e.code.as_str(), &e.message);
+        }
+        if token.pop_key.is_none() {
+            return error(StatusCode::FORBIDDEN, "pop_required", "gateway accepts only PoP-bound tokens");
+        }
+
+        let Some(header) = headers.get(PRESENTATION_HEADER).and_then(|v| v.to_str().ok()) else {
+            return self.challenge(now, "presentation required");
+        };
+        let presented = match PresentationHeader::decode(header) {
+            Ok(presented) => presented,
+            Err(e) => return error(StatusCode::BAD_REQUEST, "malformed_presentation", &e.to_string()),
+        };
+        // Taking the challenge makes it single-use whatever the outcome.
+        let challenge = self.challenges.lock().unwrap_or_else(|e| e.into_inner()).remove(&presented.nonce);
+        let Some(challenge) = challenge else {
+            return self.challenge(now, "unknown or already answered challenge");
+        };
+        let presentation = match presented.answer_to(token, &challenge, now) {
+            Ok(presentation) => presentation,
+            Err(e) => return error(StatusCode::UNAUTHORIZED, e.code.as_str(), &e.message),
+        };
+        if presented.evidence_digest.as_deref() != Some(evidence_digest(body).as_str()) {
+            return error(StatusCode::BAD_REQUEST, "evidence_mismatch", "presentation does not cover this body");
+        }
+
+        let day = format_rfc3339(now)[..10].to_string();
+        let req: HashMap<String, Node> = [
+            ("action", Node::from("payments.create")),
+            ("recipient", Node::from(payment.recipient.as_str())),
+            ("amount", Node::Number(payment.amount)),
+            ("currency", Node::from(payment.currency.as_str())),
+            ("day", Node::from(day.as_str())),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        let tracker: Arc<dyn SpendTracker> = self.usage.clone();
+        let verifier = Verifier::new(VerifierProfile { max_presentation_age_secs: Some(CHALLENGE_TTL_SECS), ..Default::default() })
+            .with_clock(FixedClock(now))
+            .with_spend_tracker(tracker)
+            .with_trusted_issuers(self.issuers.clone());
+        let result = verifier.verify(token, req.clone(), HashMap::new(), Some(&presentation));
+
+        let token_id = token_ref(token);
+        let audit = self.obligations.audit();
+        let detail = format!("recipient={} amount={} currency={}", payment.recipient, payment.amount, payment.currency);
+        if !result.allow {
+            let code = result.code.map_or("policy_denied", |c| c.as_str());
+            audit.append_for_request(&token_id, "payment.denied", &req, &format!("{detail} code={code}"), now);
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": result.error.as_deref().unwrap_or("policy denied the payment"),
+                    "code": code,
+                    "reason_code": result.reason_code,
+                })),
+            )
+                .into_response();
+        }
+
+        let entry = audit.append_for_request(&token_id, "payment.allowed", &req, &detail, now);
+        self.obligations.record(&token_id, &result.obligations, now);
+        let receipt = Receipt {
+            token: token_id,
+            recipient: payment.recipient,
+            amount: payment.amount,
+            currency: payment.currency,
+            day,
+            request_hash: request_hash(&req),
+            obligations: result.obligations,
+            audit_seq: entry.seq,
+            audit_hash: entry.hash,
+        };
+        (StatusCode::OK, Json(receipt)).into_response()
+    }
+
+    fn discharge(&self, token: &str, kind: &str) -> Response {
+        match self.obligations.acknowledge(token, kind, self.clock.now_unix()) {
+            Ok(entry) => (StatusCode::OK, Json(entry)).into_response(),
+            Err(e) => error(StatusCode::NOT_FOUND, "no_obligation", &e.0),
+        }
+    }
+}
+
+fn error(status: StatusCode, code: &str, message: &str) -> Response {
+    (status, Json(json!({ "error": message, "code": code }))).into_response()
+}
+
+/// The gateway's routes over shared state.
+pub fn router(gateway: Arc<Gateway>) -> Router {
+    Router::new()
+        .route("/payments", post(create_payment))
+        .route("/obligations/{token}/{kind}", post(discharge_obligation))
+        .route("/audit", get(audit_log))
+        .with_state(gateway)
+}
+
+async fn create_payment(State(gateway): State<Arc<Gateway>>, headers: HeaderMap, body: Bytes) -> Response {
+    gateway.pay(&headers, &body)
+}
+
+async fn discharge_obligation(
+    State(gateway): State<Arc<Gateway>>,
+    Path((token, kind)): Path<(String, String)>,
+) -> Response {
+    gateway.discharge(&token, &kind)
+}
+
+async fn audit_log(State(gateway): State<Arc<Gateway>>) -> Response {
+    Json(gateway.obligations.audit().entries()).into_response()
+}
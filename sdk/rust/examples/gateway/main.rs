@@ -0,0 +1,34 @@
+//! Reference payments gateway; see `app.rs` for the request flow.
+//!
+//! ```text
+//! cargo run --example gateway --features gateway -- <issuer-public-key-hex> [addr]
+//! ```
+
+// Clock and store accessors are for embedding and tests/gateway.rs.
+#[allow(dead_code)]
+mod app;
+
+use std::env;
+use std::process;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: gateway <issuer-public-key-hex> [addr]");
+        process::exit(1);
+    }
+    let addr = args.get(2).map_or("127.0.0.1:8080", String::as_str);
+
+    let gateway = Arc::new(app::Gateway::new(vec![args[1].clone()], addr));
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap_or_else(|e| {
+        eprintln!("Error binding {addr}: {e}");
+        process::exit(1);
+    });
+    println!("gateway listening on {addr}");
+    if let Err(e) = axum::serve(listener, app::router(gateway)).await {
+        eprintln!("Server error: {e}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,11 @@
+use std::process;
+
+use agent_safe_spl::vectors::signing_vectors;
+
+fn main() {
+    let vectors = signing_vectors().unwrap_or_else(|e| {
+        eprintln!("Error generating vectors: {e}");
+        process::exit(1);
+    });
+    println!("{}", serde_json::to_string_pretty(&vectors).expect("vectors serialize"));
+}
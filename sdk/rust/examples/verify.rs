@@ -2,8 +2,9 @@ use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::process;
+use std::sync::Arc;
 
-use agent_safe_spl::types::{CryptoCallbacks, Env, Node};
+use agent_safe_spl::types::{map_from_json, CallbackState, CryptoCallbacks, Env, Node};
 use agent_safe_spl::parser::parse;
 use agent_safe_spl::verifier::verify;
 
@@ -34,12 +35,10 @@ fn main() {
         process::exit(1);
     });
 
-    let mut req = HashMap::new();
-    if let Some(obj) = json.as_object() {
-        for (k, v) in obj {
-            req.insert(k.clone(), json_to_node(v));
-        }
-    }
+    let req = map_from_json(&json).unwrap_or_else(|e| {
+        eprintln!("Request error: {e}");
+        process::exit(1);
+    });
 
     let mut vars = HashMap::new();
     vars.insert(
@@ -54,16 +53,13 @@ fn main() {
     let env = Env {
         req,
         vars,
-        per_day_count: Box::new(|_, _| 0),
         crypto: CryptoCallbacks {
-            dpop_ok: Box::new(|| true),
-            merkle_ok: Box::new(|_| true),
-            vrf_ok: Box::new(|_, _| true),
-            thresh_ok: Box::new(|| true),
+            dpop_ok: CallbackState::Configured(Arc::new(|| true)),
+            merkle_ok: Some(Arc::new(|_| true)),
+            vrf_ok: CallbackState::Configured(Arc::new(|_, _| true)),
+            thresh_ok: CallbackState::Configured(Arc::new(|| true)),
         },
-        max_gas: 10_000,
-        sealed: false,
-        strict: false,
+        ..Env::default()
     };
 
     match verify(&ast, &env) {
@@ -74,14 +70,3 @@ fn main() {
         }
     }
 }
-
-fn json_to_node(v: &serde_json::Value) -> Node {
-    match v {
-        serde_json::Value::Bool(b) => Node::Bool(*b),
-        serde_json::Value::Number(n) => Node::Number(n.as_f64().unwrap_or(0.0)),
-        serde_json::Value::String(s) => Node::Str(s.clone()),
-        serde_json::Value::Array(arr) => Node::List(arr.iter().map(json_to_node).collect()),
-        serde_json::Value::Null => Node::Nil,
-        serde_json::Value::Object(_) => Node::Nil,
-    }
-}
@@ -0,0 +1,1229 @@
+//! Static analysis over parsed SPL policies.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::caveat::{before_now, earlier};
+use crate::limits::{find_limits, PolicyLimits};
+use crate::money::{self, format_amount};
+use crate::ops::Op;
+use crate::evaluator::{bound_param, eval_policy};
+use crate::summary::{conjuncts, summarize_clause};
+use crate::token::named_clauses;
+use crate::types::{Env, Node, SplError};
+use crate::vars::StandardVar;
+
+/// A suspicious pattern found in a policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintWarning {
+    /// Stable machine-readable rule identifier.
+    pub code: String,
+    pub message: String,
+}
+
+/// Upper bound a policy places on a request field on every allowed path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExposureBound {
+    pub field: String,
+    pub max: f64,
+    /// Set when the bound is a `(money ...)` amount; `max` is then in major units.
+    pub currency: Option<String>,
+}
+
+/// How much reviewer attention a policy needs. See [`score`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplexityReport {
+    /// Nodes in the policy tree.
+    pub nodes: usize,
+    /// Extra ways to satisfy the policy: each `or` adds one per alternative
+    /// beyond the first.
+    pub branches: usize,
+    /// Actions the policy pins `req.action` to with `=` or `member`, sorted.
+    pub actions: Vec<String>,
+    /// Upper bounds from `<`/`<=`, `in-range` and `(limits ...)` that hold
+    /// whichever branch allows.
+    pub exposure: Vec<ExposureBound>,
+    /// Request fields compared numerically but without an upper bound on
+    /// every allowed path, sorted.
+    pub unbounded: Vec<String>,
+    /// Operators whose outcome the policy does not determine: host crypto
+    /// callbacks and operators this crate does not know, sorted.
+    pub escape_hatches: Vec<String>,
+    /// Weighted total: 2 per branch, 1 per action beyond the first, 5 per
+    /// unbounded field and escape hatch, and 1 per 25 nodes.
+    pub score: u32,
+}
+
+impl ComplexityReport {
+    /// A single straight-line rule over at most one action, with every
+    /// numeric field capped and nothing delegated: safe to auto-approve.
+    pub fn is_trivial(&self) -> bool {
+        self.branches == 0 && self.actions.len() <= 1 && self.unbounded.is_empty() && self.escape_hatches.is_empty()
+    }
+}
+
+/// Score a policy's complexity so review workflows can auto-approve
+/// trivial policies and route risky ones to a human.
+pub fn score(ast: &Node) -> ComplexityReport {
+    let mut nodes = 0;
+    let mut branches = 0;
+    let mut actions = BTreeSet::new();
+    let mut compared = BTreeSet::new();
+    let mut escape_hatches = BTreeSet::new();
+    walk(ast, &mut |node| {
+        nodes += 1;
+        let Node::List(items) = node else { return };
+        let Some((Node::Symbol(head), args)) = items.split_first() else { return };
+        match Op::from_name(head) {
+            Some(Op::Or) => branches += args.len().saturating_sub(1),
+            Some(Op::Eq) => {
+                if let [a, b] = args {
+                    match (a, b) {
+                        (get, Node::Str(s)) | (Node::Str(s), get) if is_req_get(get, "action") => {
+                            actions.insert(s.clone());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some(Op::Member) => {
+                if let [get, Node::List(list)] = args {
+                    if is_req_get(get, "action") && list.first() == Some(&Node::Symbol("tuple".into())) {
+                        actions.extend(list[1..].iter().filter_map(|n| match n {
+                            Node::Str(s) => Some(s.clone()),
+                            _ => None,
+                        }));
+                    }
+                }
+            }
+            Some(Op::Le | Op::Lt | Op::Ge | Op::Gt | Op::InRange) => {
+                compared.extend(args.iter().filter_map(req_field).map(str::to_string));
+            }
+            Some(Op::DpopOk | Op::MerkleOk | Op::VrfOk | Op::ThreshOk | Op::Attested) => {
+                escape_hatches.insert(head.clone());
+            }
+            Some(_) => {}
+            None => {
+                escape_hatches.insert(head.clone());
+            }
+        }
+    });
+
+    let mut bounds = upper_bounds(ast);
+    for range in find_limits(ast).ok().flatten().unwrap_or_default().ranges {
+        tighten(&mut bounds, &range.field, (range.max, None));
+    }
+    let unbounded: Vec<String> = compared.into_iter().filter(|f| !bounds.contains_key(f)).collect();
+    let exposure: Vec<ExposureBound> = bounds
+        .into_iter()
+        .map(|(field, (max, currency))| ExposureBound { field, max, currency })
+        .collect();
+
+    let score = 2 * branches
+        + actions.len().saturating_sub(1)
+        + 5 * (unbounded.len() + escape_hatches.len())
+        + nodes / 25;
+    ComplexityReport {
+        nodes,
+        branches,
+        actions: actions.into_iter().collect(),
+        exposure,
+        unbounded,
+        escape_hatches: escape_hatches.into_iter().collect(),
+        score: score as u32,
+    }
+}
+
+fn walk(node: &Node, f: &mut impl FnMut(&Node)) {
+    f(node);
+    if let Node::List(items) = node {
+        // A limits block is data, not expressions.
+        if items.first() == Some(&Node::Symbol("limits".into())) {
+            return;
+        }
+        for item in items {
+            walk(item, f);
+        }
+    }
+}
+
+type Bounds = BTreeMap<String, (f64, Option<String>)>;
+
+/// Upper bounds that hold whenever `node` is true: the tightest of an
+/// `and`'s conjuncts, and the loosest of an `or`'s alternatives for fields
+/// every alternative bounds in the same unit.
+fn upper_bounds(node: &Node) -> Bounds {
+    let mut bounds = Bounds::new();
+    let Node::List(items) = node else { return bounds };
+    let Some((Node::Symbol(head), args)) = items.split_first() else { return bounds };
+    match (head.as_str(), args) {
+        ("and", _) => {
+            for arg in args {
+                for (field, bound) in upper_bounds(arg) {
+                    tighten(&mut bounds, &field, bound);
+                }
+            }
+        }
+        ("or", _) => {
+            // Alternatives that never allow cannot widen the bound.
+            let mut alternatives = args.iter().filter(|a| !never_true(a));
+            let Some(first) = alternatives.next() else { return bounds };
+            bounds = upper_bounds(first);
+            for arg in alternatives {
+                let other = upper_bounds(arg);
+                bounds.retain(|field, (max, currency)| match other.get(field) {
+                    Some((m, c)) if c == currency => {
+                        *max = max.max(*m);
+                        true
+                    }
+                    _ => false,
+                });
+            }
+        }
+        ("<=" | "<", [a, b]) => {
+            if let (Some(f), Some(bound)) = (req_field(a), amount(b)) {
+                bounds.insert(f.to_string(), bound);
+            }
+        }
+        (">=" | ">", [a, b]) => {
+            if let (Some(bound), Some(f)) = (amount(a), req_field(b)) {
+                bounds.insert(f.to_string(), bound);
+            }
+        }
+        ("in-range", [x, _, hi]) => {
+            if let (Some(f), Some(bound)) = (req_field(x), amount(hi)) {
+                bounds.insert(f.to_string(), bound);
+            }
+        }
+        _ => {}
+    }
+    bounds
+}
+
+fn tighten(bounds: &mut Bounds, field: &str, bound: (f64, Option<String>)) {
+    match bounds.get_mut(field) {
+        Some(existing) if existing.1 == bound.1 => existing.0 = existing.0.min(bound.0),
+        Some(_) => {}
+        None => {
+            bounds.insert(field.to_string(), bound);
+        }
+    }
+}
+
+/// A literal amount: a number, or a money value as major units and currency.
+fn amount(node: &Node) -> Option<(f64, Option<String>)> {
+    if let Some(n) = node.as_number() {
+        return Some((n, None));
+    }
+    let money = match node {
+        Node::Money { .. } => node.clone(),
+        Node::List(items) if items.first() == Some(&Node::Symbol("money".into())) => {
+            match items.as_slice() {
+                [_, amount, Node::Str(currency)] => money::money(amount, currency).ok()?,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let Node::Money { minor_units, currency } = money else { return None };
+    let major = format_amount(minor_units, &currency).parse().ok()?;
+    Some((major, Some(currency)))
+}
+
+fn req_field(node: &Node) -> Option<&str> {
+    match node {
+        Node::List(items) => match items.as_slice() {
+            [Node::Symbol(op), Node::Symbol(obj), Node::Str(k)] if op == "get" && obj == "req" => Some(k),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// What a policy reads when it is evaluated. See [`referenced_fields`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferencedFields {
+    /// Request fields read by `(get req "...")`, `(limits ...)` and the
+    /// operators that consult the request implicitly, sorted.
+    pub req_keys: Vec<String>,
+    /// Free symbols the caller must bind in `vars`, sorted.
+    pub vars: Vec<String>,
+    /// Host crypto callbacks the policy calls, sorted.
+    pub ops_used: Vec<String>,
+}
+
+/// List the request fields, vars and crypto operators a policy can read,
+/// so a gateway can reject requests missing a field before evaluating.
+///
+/// Keys looked up with a computed `(get req ...)` key are not listed.
+pub fn referenced_fields(ast: &Node) -> ReferencedFields {
+    let mut req_keys = BTreeSet::new();
+    let mut vars = BTreeSet::new();
+    let mut ops_used = BTreeSet::new();
+    collect_references(ast, &mut req_keys, &mut vars, &mut ops_used);
+    ReferencedFields {
+        req_keys: req_keys.into_iter().collect(),
+        vars: vars.into_iter().collect(),
+        ops_used: ops_used.into_iter().collect(),
+    }
+}
+
+fn collect_references(
+    node: &Node,
+    req_keys: &mut BTreeSet<String>,
+    vars: &mut BTreeSet<String>,
+    ops_used: &mut BTreeSet<String>,
+) {
+    let args = match node {
+        Node::Symbol(s) if !matches!(s.as_str(), "req" | "#t" | "#f") => {
+            vars.insert(s.clone());
+            return;
+        }
+        Node::List(items) => match items.split_first() {
+            Some((Node::Symbol(head), args)) => {
+                match Op::from_name(head) {
+                    Some(Op::Limits) => {
+                        // A limits block is data, not expressions.
+                        if let Ok(limits) = PolicyLimits::from_entries(args) {
+                            req_keys.extend(limits.ranges.into_iter().map(|r| r.field));
+                            if limits.per_day.is_some() {
+                                req_keys.extend(["action", "day"].map(String::from));
+                            }
+                        }
+                        return;
+                    }
+                    Some(Op::Get) => {
+                        if let Some(key) = req_field(node) {
+                            req_keys.insert(key.to_string());
+                        }
+                    }
+                    Some(Op::PerDayCountSelf) => req_keys.extend(["action", "day"].map(String::from)),
+                    Some(Op::ActionIn) => {
+                        req_keys.insert("action".to_string());
+                    }
+                    Some(Op::CumulativeSpend) => req_keys.extend(["action", "amount", "day"].map(String::from)),
+                    Some(Op::Lambda | Op::All | Op::Any) => {
+                        // The parameter is bound, not a var, within its scope.
+                        if let Some((param, scope)) = bound_param(node) {
+                            let mut inner = BTreeSet::new();
+                            collect_references(&items[scope], req_keys, &mut inner, ops_used);
+                            inner.remove(param);
+                            vars.extend(inner);
+                            for arg in &items[2..scope] {
+                                collect_references(arg, req_keys, vars, ops_used);
+                            }
+                        }
+                        return;
+                    }
+                    Some(op @ (Op::DpopOk | Op::MerkleOk | Op::VrfOk | Op::ThreshOk)) => {
+                        ops_used.insert(op.name().to_string());
+                    }
+                    Some(Op::Attested) => {
+                        req_keys.insert("attestation".to_string());
+                        ops_used.insert(Op::Attested.name().to_string());
+                    }
+                    _ => {}
+                }
+                args
+            }
+            _ => items.as_slice(),
+        },
+        _ => return,
+    };
+    for arg in args {
+        collect_references(arg, req_keys, vars, ops_used);
+    }
+}
+
+/// Most requests [`implies`] evaluates before giving up on an exhaustive search.
+pub const MAX_IMPLICATION_CASES: usize = 20_000;
+
+/// Whether one policy allows only what another does. See [`implies`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Implication {
+    /// Every request the first policy allows, the second allows too.
+    Holds,
+    /// A request the first policy allows and the second denies.
+    Refuted(HashMap<String, Node>),
+    /// No counterexample was found, but the search could not cover every
+    /// case; the reason says why.
+    Unknown(String),
+}
+
+/// Check that policy `a` implies policy `b`: that `a` is no broader, as an
+/// attenuated policy must be before its parent is delegated.
+///
+/// Each request field either policy reads is split into the cases the
+/// policies can tell apart: missing, booleans, the string and numeric
+/// constants it is compared with, a string and numbers between and beyond
+/// them. Both policies are evaluated on every combination (sampled past
+/// [`MAX_IMPLICATION_CASES`]), with default host state, treating errors as
+/// denials. The answer is [`Implication::Holds`] only when the search was
+/// exhaustive and both policies stick to comparisons of request fields with
+/// constants under `and`/`or`/`not`; vars, host operators, money and
+/// computed lookups leave it [`Implication::Unknown`]. A counterexample is
+/// always a real one.
+pub fn implies(a: &Node, b: &Node) -> Implication {
+    let (refs_a, refs_b) = (referenced_fields(a), referenced_fields(b));
+    if !refs_a.vars.is_empty() || !refs_b.vars.is_empty() {
+        return Implication::Unknown("policies read vars, which vary independently of the request".into());
+    }
+    let mut constants = BTreeMap::new();
+    for field in refs_a.req_keys.iter().chain(&refs_b.req_keys) {
+        constants.entry(field.clone()).or_insert_with(Vec::new);
+    }
+    collect_constants(a, &mut constants);
+    collect_constants(b, &mut constants);
+    let fields: Vec<(String, Vec<Option<Node>>)> =
+        constants.into_iter().map(|(field, consts)| (field, field_cases(&consts))).collect();
+
+    let total = fields.iter().try_fold(1usize, |n, (_, cases)| n.checked_mul(cases.len()));
+    let exhaustive = total.is_some_and(|n| n <= MAX_IMPLICATION_CASES);
+    let mut rng = 0x9e37_79b9_7f4a_7c15u64;
+    for case in 0..total.unwrap_or(usize::MAX).min(MAX_IMPLICATION_CASES) {
+        let mut index = case;
+        let mut req = HashMap::new();
+        for (field, cases) in &fields {
+            let pick = if exhaustive {
+                let pick = index % cases.len();
+                index /= cases.len();
+                pick
+            } else {
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+                (rng % cases.len() as u64) as usize
+            };
+            if let Some(value) = &cases[pick] {
+                req.insert(field.clone(), value.clone());
+            }
+        }
+        let env = Env { req, ..Env::default() };
+        let allows = |policy| eval_policy(policy, &env).is_ok_and(|v| v.is_truthy());
+        if allows(a) && !allows(b) {
+            return Implication::Refuted(env.req);
+        }
+    }
+    if !exhaustive {
+        Implication::Unknown(format!("searched {MAX_IMPLICATION_CASES} of the cases without a counterexample"))
+    } else if !decidable(a) || !decidable(b) {
+        Implication::Unknown("policies use operators the search cannot cover".into())
+    } else {
+        Implication::Holds
+    }
+}
+
+/// Constants each request field is compared with, by `=`, ordering,
+/// `in-range`, `member` and `limits`.
+fn collect_constants(node: &Node, out: &mut BTreeMap<String, Vec<Node>>) {
+    let Node::List(items) = node else { return };
+    let Some((Node::Symbol(head), args)) = items.split_first() else { return };
+    match Op::from_name(head) {
+        Some(Op::Limits) => {
+            for range in PolicyLimits::from_entries(args).map(|l| l.ranges).unwrap_or_default() {
+                out.entry(range.field).or_default().extend([Node::Number(range.min), Node::Number(range.max)]);
+            }
+            return;
+        }
+        Some(Op::Eq | Op::Le | Op::Lt | Op::Ge | Op::Gt | Op::InRange | Op::Member) => {
+            if let Some(field) = args.iter().find_map(req_field) {
+                let consts = out.entry(field.to_string()).or_default();
+                for arg in args {
+                    match arg {
+                        Node::List(list) if list.first() == Some(&Node::Symbol("tuple".into())) => {
+                            consts.extend(list[1..].iter().filter(|n| is_constant(n)).cloned());
+                        }
+                        _ if is_constant(arg) => consts.push(arg.clone()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    for item in items {
+        collect_constants(item, out);
+    }
+}
+
+fn is_constant(node: &Node) -> bool {
+    matches!(node, Node::Bool(_) | Node::Int(_) | Node::Str(_)) || matches!(node, Node::Number(n) if n.is_finite())
+}
+
+/// One value per case the constants can tell apart; `None` is a missing field.
+fn field_cases(consts: &[Node]) -> Vec<Option<Node>> {
+    // Strings equal numbers and booleans that print the same, and read as
+    // 0 in comparisons, as do booleans and missing fields.
+    let mut strings: BTreeSet<String> = consts
+        .iter()
+        .filter_map(|c| match c {
+            Node::Str(s) => Some(s.clone()),
+            Node::Bool(b) => Some(b.to_string()),
+            Node::Int(i) => Some(i.to_string()),
+            Node::Number(n) => Some(format!("{n}")),
+            _ => None,
+        })
+        .collect();
+    let mut numbers: Vec<f64> = consts
+        .iter()
+        .filter_map(|c| c.as_number().or_else(|| c.as_str()?.parse().ok()))
+        .filter(|n: &f64| n.is_finite())
+        .chain([0.0])
+        .collect();
+    numbers.sort_by(f64::total_cmp);
+    numbers.dedup();
+    let mut other = String::from("~");
+    while strings.contains(&other) {
+        other.push('~');
+    }
+    strings.insert(other);
+
+    let number = |n: f64| match n {
+        n if n.fract() == 0.0 && n.abs() < 9.0e15 => Node::Int(n as i64),
+        n => Node::Number(n),
+    };
+    let mut cases = vec![None, Some(Node::Bool(true)), Some(Node::Bool(false))];
+    cases.extend(strings.into_iter().map(|s| Some(Node::Str(s))));
+    cases.push(Some(number(numbers[0] - 1.0)));
+    for pair in numbers.windows(2) {
+        cases.push(Some(number(pair[0])));
+        cases.push(Some(Node::Number((pair[0] + pair[1]) / 2.0)));
+    }
+    let last = numbers[numbers.len() - 1];
+    cases.extend([Some(number(last)), Some(number(last + 1.0))]);
+    cases
+}
+
+/// Whether every case of `node` is covered by [`field_cases`]: request
+/// fields compared with constants, combined with `and`/`or`/`not`.
+fn decidable(node: &Node) -> bool {
+    let operand = |n: &Node| is_constant(n) || req_field(n).is_some();
+    match node {
+        Node::Symbol(s) => s == "#t" || s == "#f",
+        Node::List(items) => {
+            let Some((Node::Symbol(head), args)) = items.split_first() else { return false };
+            match Op::from_name(head) {
+                Some(Op::And | Op::Or | Op::Not) => args.iter().all(decidable),
+                Some(Op::Eq | Op::Le | Op::Lt | Op::Ge | Op::Gt | Op::InRange) => {
+                    args.iter().all(operand) && args.iter().filter(|a| req_field(a).is_some()).count() <= 1
+                }
+                Some(Op::Member) => match args {
+                    [x, Node::List(list)] => {
+                        operand(x) && list.first() == Some(&Node::Symbol("tuple".into()))
+                            && list[1..].iter().all(is_constant)
+                    }
+                    _ => false,
+                },
+                Some(Op::Limits) => PolicyLimits::from_entries(args).is_ok_and(|l| l.per_day.is_none()),
+                Some(Op::Get) => req_field(node).is_some(),
+                _ => false,
+            }
+        }
+        _ => is_constant(node),
+    }
+}
+
+/// A check [`lint_with`] can run. Each warning's `code` is its rule's [`LintRule::code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintRule {
+    /// `per-day-count` counts an action the policy never constrains
+    /// `req["action"]` to, so a typo silently counts 0.
+    PerDayCountActionMismatch,
+    /// A comparison against a bare symbol that is not a known var. Unbound
+    /// symbols evaluate to their own name, so `(= (get req "role") admin_role)`
+    /// matches a request whose role is the string `"admin_role"`.
+    UnknownSymbol,
+    /// An `or` alternative lets a request field through without the limit
+    /// another alternative places on it, and nothing else bounds the field.
+    OrBypassesLimit,
+    /// The policy never checks the time. Off by default, since most tokens
+    /// carry an envelope `expires`.
+    MissingExpiry,
+    /// Expressions `and`/`or` can never reach, and clauses that can never allow.
+    Unreachable,
+    /// Operators kept only for compatibility.
+    DeprecatedOp,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 6] = [
+        LintRule::PerDayCountActionMismatch,
+        LintRule::UnknownSymbol,
+        LintRule::OrBypassesLimit,
+        LintRule::MissingExpiry,
+        LintRule::Unreachable,
+        LintRule::DeprecatedOp,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            LintRule::PerDayCountActionMismatch => "per-day-count-action-mismatch",
+            LintRule::UnknownSymbol => "unknown-symbol",
+            LintRule::OrBypassesLimit => "or-bypasses-limit",
+            LintRule::MissingExpiry => "missing-expiry",
+            LintRule::Unreachable => "unreachable",
+            LintRule::DeprecatedOp => "deprecated-op",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<LintRule> {
+        LintRule::ALL.into_iter().find(|r| r.code() == code)
+    }
+
+    fn warn(self, message: String) -> LintWarning {
+        LintWarning { code: self.code().into(), message }
+    }
+}
+
+/// Which rules [`lint_with`] runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintConfig {
+    pub rules: BTreeSet<LintRule>,
+    /// Vars the verifier supplies. When set, every comparison against a
+    /// symbol outside them (and the standard vars) is flagged; when unset,
+    /// only `=` against a request field is, where a typo silently matches.
+    pub known_vars: Option<BTreeSet<String>>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        let rules = LintRule::ALL.into_iter().filter(|r| *r != LintRule::MissingExpiry).collect();
+        Self { rules, known_vars: None }
+    }
+}
+
+impl LintConfig {
+    pub fn enable(mut self, rule: LintRule) -> Self {
+        self.rules.insert(rule);
+        self
+    }
+
+    pub fn disable(mut self, rule: LintRule) -> Self {
+        self.rules.remove(&rule);
+        self
+    }
+}
+
+/// Operators kept for compatibility, and what to write instead.
+const DEPRECATED_OPS: &[(&str, &str)] = &[("in", "member")];
+
+/// Check a policy for suspicious patterns with the default rules.
+pub fn lint(ast: &Node) -> Vec<LintWarning> {
+    lint_with(ast, &LintConfig::default())
+}
+
+/// Check a policy for suspicious patterns with the rules in `config`.
+/// Each distinct warning is reported once.
+pub fn lint_with(ast: &Node, config: &LintConfig) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let on = |rule| config.rules.contains(&rule);
+    if on(LintRule::PerDayCountActionMismatch) {
+        lint_per_day_count_actions(ast, &mut warnings);
+    }
+    if on(LintRule::UnknownSymbol) {
+        lint_unknown_symbols(ast, config.known_vars.as_ref(), &mut Vec::new(), &mut warnings);
+    }
+    if on(LintRule::OrBypassesLimit) {
+        lint_or_bypasses_limit(ast, &upper_bounds(ast), &mut warnings);
+    }
+    if on(LintRule::MissingExpiry) {
+        lint_missing_expiry(ast, &mut warnings);
+    }
+    if on(LintRule::Unreachable) {
+        lint_unreachable(ast, &mut warnings);
+    }
+    if on(LintRule::DeprecatedOp) {
+        walk(ast, &mut |node| {
+            let Node::List(items) = node else { return };
+            let Some(Node::Symbol(head)) = items.first() else { return };
+            if let Some((_, instead)) = DEPRECATED_OPS.iter().find(|(op, _)| op == head) {
+                warnings.push(LintRule::DeprecatedOp.warn(format!("`{head}` is deprecated; use `{instead}`")));
+            }
+        });
+    }
+    let mut seen = BTreeSet::new();
+    warnings.retain(|w| seen.insert((w.code.clone(), w.message.clone())));
+    warnings
+}
+
+/// `#f` and `deny-with`, which never evaluate truthy.
+fn never_true(node: &Node) -> bool {
+    match node {
+        Node::Bool(false) => true,
+        Node::List(items) => items.first() == Some(&Node::Symbol("deny-with".into())),
+        _ => false,
+    }
+}
+
+fn lint_unknown_symbols(
+    node: &Node,
+    known_vars: Option<&BTreeSet<String>>,
+    bound: &mut Vec<String>,
+    warnings: &mut Vec<LintWarning>,
+) {
+    let Node::List(items) = node else { return };
+    if let Some((param, body)) = bound_param(node) {
+        for (i, item) in items.iter().enumerate().skip(1) {
+            if i == body {
+                bound.push(param.to_string());
+                lint_unknown_symbols(item, known_vars, bound, warnings);
+                bound.pop();
+            } else if item != &Node::Symbol(param.into()) {
+                lint_unknown_symbols(item, known_vars, bound, warnings);
+            }
+        }
+        return;
+    }
+    let Some((Node::Symbol(head), args)) = items.split_first() else { return };
+    if head == "limits" {
+        return;
+    }
+    let compared: Vec<&str> = match (Op::from_name(head), args, known_vars) {
+        (Some(Op::Eq), [a, b], None) => match (a, b) {
+            (get, Node::Symbol(s)) | (Node::Symbol(s), get) if req_field(get).is_some() => vec![s.as_str()],
+            _ => Vec::new(),
+        },
+        (Some(Op::Eq | Op::Le | Op::Lt | Op::Ge | Op::Gt | Op::InRange | Op::Member | Op::CountryIn | Op::IpInCidr), _, Some(_)) => args
+            .iter()
+            .filter_map(|arg| match arg {
+                Node::Symbol(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    for symbol in compared {
+        let known = symbol == "req"
+            || bound.iter().any(|b| b == symbol)
+            || StandardVar::from_name(symbol).is_some()
+            || known_vars.is_some_and(|vars| vars.contains(symbol));
+        if !known {
+            warnings.push(LintRule::UnknownSymbol.warn(format!(
+                "`{symbol}` is not a known var; unbound, it evaluates to the string \"{symbol}\""
+            )));
+        }
+    }
+    for arg in args {
+        lint_unknown_symbols(arg, known_vars, bound, warnings);
+    }
+}
+
+/// Flag `or`s where some alternative bounds a field that another leaves
+/// open, unless `guaranteed` (the policy's own bounds) covers it anyway.
+fn lint_or_bypasses_limit(node: &Node, guaranteed: &Bounds, warnings: &mut Vec<LintWarning>) {
+    let Node::List(items) = node else { return };
+    let Some((Node::Symbol(head), args)) = items.split_first() else { return };
+    match head.as_str() {
+        // Bounds under negation say nothing about what is allowed.
+        "not" | "limits" => return,
+        "or" => {
+            let alternatives: Vec<Bounds> = args.iter().filter(|a| !never_true(a)).map(upper_bounds).collect();
+            let fields: BTreeSet<&String> = alternatives.iter().flat_map(|b| b.keys()).collect();
+            for field in fields {
+                if !guaranteed.contains_key(field) && alternatives.iter().any(|b| !b.contains_key(field)) {
+                    warnings.push(LintRule::OrBypassesLimit.warn(format!(
+                        "an `or` alternative allows any \"{field}\", bypassing the limit another alternative sets"
+                    )));
+                }
+            }
+        }
+        _ => {}
+    }
+    for arg in args {
+        lint_or_bypasses_limit(arg, guaranteed, warnings);
+    }
+}
+
+fn lint_missing_expiry(ast: &Node, warnings: &mut Vec<LintWarning>) {
+    let mut checks_time = false;
+    walk(ast, &mut |node| {
+        if let Node::List(items) = node {
+            checks_time |= matches!(items.first(), Some(Node::Symbol(h)) if h == "before" || h == "during");
+        }
+    });
+    if !checks_time {
+        warnings.push(LintRule::MissingExpiry.warn(
+            "policy never checks the time; unless the token sets expires it never lapses \
+             (compare now with before or during)"
+                .into(),
+        ));
+    }
+}
+
+fn lint_unreachable(node: &Node, warnings: &mut Vec<LintWarning>) {
+    let Node::List(items) = node else { return };
+    let Some((Node::Symbol(head), args)) = items.split_first() else { return };
+    match (head.as_str(), args) {
+        ("limits", _) => return,
+        ("policy", [Node::Str(name), body]) if never_true(body) => {
+            warnings.push(LintRule::Unreachable.warn(format!("clause \"{name}\" never allows")));
+        }
+        ("and", _) => {
+            if let Some(i) = args.iter().position(never_true) {
+                if i + 1 < args.len() {
+                    warnings.push(LintRule::Unreachable.warn(format!("`and` never evaluates what follows {}", args[i])));
+                }
+            }
+            let mut pinned: BTreeMap<&str, &Node> = BTreeMap::new();
+            for arg in args {
+                let Some((field, value)) = pinned_field(arg) else { continue };
+                match pinned.insert(field, value) {
+                    Some(other) if other != value => warnings.push(LintRule::Unreachable.warn(format!(
+                        "`and` never allows: \"{field}\" cannot be both {other} and {value}"
+                    ))),
+                    _ => {}
+                }
+            }
+        }
+        ("or", _) => {
+            if let Some(i) = args.iter().position(|a| a == &Node::Bool(true)) {
+                if i + 1 < args.len() {
+                    warnings.push(LintRule::Unreachable.warn("`or` never evaluates what follows #t".into()));
+                }
+            }
+        }
+        _ => {}
+    }
+    for arg in args {
+        lint_unreachable(arg, warnings);
+    }
+}
+
+/// The field and constant of `(= (get req "field") constant)`, either way round.
+fn pinned_field(node: &Node) -> Option<(&str, &Node)> {
+    let Node::List(items) = node else { return None };
+    match items.as_slice() {
+        [Node::Symbol(op), a, b] if op == "=" => match (req_field(a), req_field(b)) {
+            (Some(field), None) if is_constant(b) => Some((field, b)),
+            (None, Some(field)) if is_constant(a) => Some((field, a)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Flag `(per-day-count "action" ...)` whose literal action is not one the
+/// policy constrains `req["action"]` to, since a typo silently counts 0.
+fn lint_per_day_count_actions(ast: &Node, warnings: &mut Vec<LintWarning>) {
+    let mut actions = Vec::new();
+    collect_action_constraints(ast, &mut actions);
+    let mut counted = Vec::new();
+    collect_per_day_count_literals(ast, &mut counted);
+    for action in counted {
+        if !actions.contains(&action) {
+            warnings.push(LintRule::PerDayCountActionMismatch.warn(format!(
+                "per-day-count counts \"{action}\" but the policy never constrains req action to it; \
+                 consider (per-day-count-self)"
+            )));
+        }
+    }
+}
+
+fn is_req_get(node: &Node, key: &str) -> bool {
+    matches!(node, Node::List(items) if matches!(items.as_slice(),
+        [Node::Symbol(op), Node::Symbol(obj), Node::Str(k)] if op == "get" && obj == "req" && k == key))
+}
+
+/// Literal actions from `(= (get req "action") "x")` in either argument order.
+fn collect_action_constraints(node: &Node, out: &mut Vec<String>) {
+    if let Node::List(items) = node {
+        if let [Node::Symbol(op), a, b] = items.as_slice() {
+            if op == "=" {
+                match (a, b) {
+                    (get, Node::Str(s)) | (Node::Str(s), get) if is_req_get(get, "action") => {
+                        out.push(s.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for item in items {
+            collect_action_constraints(item, out);
+        }
+    }
+}
+
+fn collect_per_day_count_literals(node: &Node, out: &mut Vec<String>) {
+    if let Node::List(items) = node {
+        if let [Node::Symbol(op), Node::Str(action), ..] = items.as_slice() {
+            if op == "per-day-count" {
+                out.push(action.clone());
+            }
+        }
+        for item in items {
+            collect_per_day_count_literals(item, out);
+        }
+    }
+}
+
+/// A policy in plain language, for consent screens. See [`describe`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyDescription {
+    /// One entry for a single policy, or one per `(policy "name" ...)` clause.
+    pub clauses: Vec<ClauseDescription>,
+}
+
+/// What one policy or named clause grants.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClauseDescription {
+    /// Clause name; `None` for a single policy.
+    pub name: Option<String>,
+    /// Conditions every allowed request meets, one per top-level conjunct.
+    pub requirements: Vec<String>,
+    /// Follow-ups the service commits to on allow, from `obligate`.
+    pub obligations: Vec<String>,
+}
+
+impl PolicyDescription {
+    /// Describe parsed policy source, which may hold named clauses.
+    pub fn of(exprs: &[Node]) -> Result<Self, SplError> {
+        let clauses = match named_clauses(exprs)? {
+            Some(clauses) => clauses.iter().map(|(name, body)| describe_clause(Some(name.clone()), body)).collect(),
+            None => exprs.iter().map(|expr| describe_clause(None, expr)).collect(),
+        };
+        Ok(PolicyDescription { clauses })
+    }
+}
+
+impl fmt::Display for PolicyDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, clause) in self.clauses.iter().enumerate() {
+            let indent = match &clause.name {
+                Some(name) => {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    writeln!(f, "{name}:")?;
+                    "  "
+                }
+                None => "",
+            };
+            for requirement in &clause.requirements {
+                writeln!(f, "{indent}- {requirement}")?;
+            }
+            for obligation in &clause.obligations {
+                writeln!(f, "{indent}- afterwards, the service must {obligation}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Render a policy as bullet points, e.g. `- amount must be ≤ 50`. Forms
+/// without a phrasing of their own fall back to an infix rendering.
+pub fn describe(ast: &Node) -> String {
+    PolicyDescription { clauses: vec![describe_clause(None, ast)] }.to_string()
+}
+
+fn describe_clause(name: Option<String>, body: &Node) -> ClauseDescription {
+    let mut clause = ClauseDescription { name, ..ClauseDescription::default() };
+    describe_conjunct(body, &mut clause);
+    if clause.requirements.is_empty() && clause.obligations.is_empty() {
+        clause.requirements.push("any request is allowed".into());
+    }
+    clause
+}
+
+fn describe_conjunct(node: &Node, clause: &mut ClauseDescription) {
+    let Node::List(items) = node else {
+        if node != &Node::Bool(true) {
+            clause.requirements.push(requirement(node));
+        }
+        return;
+    };
+    match items.split_first() {
+        Some((Node::Symbol(op), args)) if op == "and" => {
+            for arg in args {
+                describe_conjunct(arg, clause);
+            }
+        }
+        Some((Node::Symbol(op), args)) if op == "obligate" => clause.obligations.push(obligation(args)),
+        Some((Node::Symbol(op), args)) if op == "limits" => match PolicyLimits::from_entries(args) {
+            Ok(limits) => clause.requirements.extend(limit_phrases(&limits)),
+            Err(_) => clause.requirements.push(requirement(node)),
+        },
+        _ => clause.requirements.push(requirement(node)),
+    }
+}
+
+fn limit_phrases(limits: &PolicyLimits) -> Vec<String> {
+    let mut phrases: Vec<String> = limits
+        .ranges
+        .iter()
+        .map(|r| format!("{} must be between {} and {}", r.field, number(r.min), number(r.max)))
+        .collect();
+    if let Some(n) = limits.per_day {
+        phrases.push(format!("at most {} requests per day", number(n)));
+    }
+    phrases
+}
+
+fn obligation(args: &[Node]) -> String {
+    match args {
+        [kind] => noun(kind),
+        [kind, secs] => format!("{} within {} seconds", noun(kind), noun(secs)),
+        _ => format!("obligate({})", args.iter().map(noun).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// One condition as a sentence.
+fn requirement(node: &Node) -> String {
+    let Node::List(items) = node else {
+        return match node {
+            Node::Bool(true) => "any request is allowed".into(),
+            Node::Bool(false) => "no request is allowed".into(),
+            other => format!("{} must hold", noun(other)),
+        };
+    };
+    let Some((Node::Symbol(op), args)) = items.split_first() else { return format!("{} must hold", noun(node)) };
+    match (op.as_str(), args) {
+        ("and", _) => args.iter().map(requirement).collect::<Vec<_>>().join(", and "),
+        ("or", _) => {
+            let alternatives: Vec<String> = args.iter().filter(|a| !never_true(a)).map(requirement).collect();
+            match alternatives.as_slice() {
+                [] => "no request is allowed".into(),
+                [only] => only.clone(),
+                _ => format!("either {}", alternatives.join(", or ")),
+            }
+        }
+        ("not", [inner]) => negated(inner),
+        ("deny-with", _) => "no request is allowed".into(),
+        ("obligate", _) => format!("the service must then {}", obligation(args)),
+        ("limits", entries) => match PolicyLimits::from_entries(entries) {
+            Ok(limits) => limit_phrases(&limits).join(", and "),
+            Err(_) => format!("{} must hold", noun(node)),
+        },
+        ("=", [a, b]) => {
+            let (subject, value, _) = ordered(a, b);
+            format!("{} must be {}", noun(subject), noun(value))
+        }
+        ("=hash", [value, _]) => format!("{} must match a hashed value (not shown)", noun(value)),
+        ("<=" | "<" | ">=" | ">", [a, b]) => {
+            let (subject, value, swapped) = ordered(a, b);
+            let symbol = match (op.as_str(), swapped) {
+                ("<=", false) | (">=", true) => "≤",
+                ("<", false) | (">", true) => "<",
+                (">=", false) | ("<=", true) => "≥",
+                _ => ">",
+            };
+            format!("{} must be {symbol} {}", noun(subject), noun(value))
+        }
+        ("in-range", [x, lo, hi]) => format!("{} must be between {} and {}", noun(x), noun(lo), noun(hi)),
+        ("member" | "in", [x, list]) => format!("{} must be one of: {}", noun(x), noun(list)),
+        ("ip-in-cidr", [ip, cidrs]) => format!("{} must be an address in {}", noun(ip), noun(cidrs)),
+        ("attested?", [claim]) => format!("the device must be attested as {}", noun(claim)),
+        ("country-in", [country, codes]) => format!("{} must be one of the countries: {}", noun(country), noun(codes)),
+        ("action-in", globs) => format!("action must match one of: {}", globs.iter().map(noun).collect::<Vec<_>>().join(", ")),
+        ("subset?", [a, b]) => format!("every {} must be one of: {}", noun(a), noun(b)),
+        ("disjoint?", [a, b]) => format!("none of {} may be one of: {}", noun(a), noun(b)),
+        ("before", [a, b]) if is_now(a) => format!("only before {}", noun(b)),
+        ("before", [a, b]) if is_now(b) => format!("only after {}", noun(a)),
+        ("before", [a, b]) => format!("{} must be before {}", noun(a), noun(b)),
+        ("during", [t, start, end]) if is_now(t) => format!("only between {} and {}", noun(start), noun(end)),
+        ("during", [t, start, end]) => format!("{} must be between {} and {}", noun(t), noun(start), noun(end)),
+        ("weekday?", [t]) if is_now(t) => "only on weekdays".into(),
+        ("weekday?", [t]) => format!("{} must fall on a weekday", noun(t)),
+        ("hour-between?", [t, start, end]) if is_now(t) => {
+            format!("only between {}:00 and {}:00", noun(start), noun(end))
+        }
+        ("hour-between?", [t, start, end]) => {
+            format!("{} must be between {}:00 and {}:00", noun(t), noun(start), noun(end))
+        }
+        ("all", [x, list, body]) => format!("for every {} in {}, {}", noun(x), noun(list), requirement(body)),
+        ("any", [x, list, body]) => format!("for at least one {} in {}, {}", noun(x), noun(list), requirement(body)),
+        _ => format!("{} must hold", noun(node)),
+    }
+}
+
+fn negated(node: &Node) -> String {
+    let Node::List(items) = node else { return format!("{} must not hold", noun(node)) };
+    match items.as_slice() {
+        [Node::Symbol(op), a, b] if op == "=" => {
+            let (subject, value, _) = ordered(a, b);
+            format!("{} must not be {}", noun(subject), noun(value))
+        }
+        [Node::Symbol(op), x, list] if op == "member" || op == "in" => {
+            format!("{} must not be one of: {}", noun(x), noun(list))
+        }
+        [Node::Symbol(op), inner] if op == "not" => requirement(inner),
+        _ => format!("it must not be that {}", requirement(node)),
+    }
+}
+
+/// The non-literal side first, and whether the operands were swapped.
+fn ordered<'a>(a: &'a Node, b: &'a Node) -> (&'a Node, &'a Node, bool) {
+    if is_constant(a) && !is_constant(b) {
+        (b, a, true)
+    } else {
+        (a, b, false)
+    }
+}
+
+fn is_now(node: &Node) -> bool {
+    node == &Node::Symbol("now".into())
+}
+
+/// A value or expression as a noun phrase.
+fn noun(node: &Node) -> String {
+    match node {
+        Node::Bool(b) => b.to_string(),
+        Node::Int(n) => n.to_string(),
+        Node::Number(n) => number(*n),
+        Node::Str(s) => s.clone(),
+        Node::Symbol(s) if s == "now" => "the current time".into(),
+        Node::Symbol(s) if s == "day" => "today".into(),
+        Node::Symbol(s) => s.clone(),
+        Node::Money { minor_units, currency } => format!("{} {currency}", format_amount(*minor_units, currency)),
+        Node::Nil => "nothing".into(),
+        Node::Map(map) => {
+            let entries: Vec<String> = map.iter().map(|(k, v)| format!("{k}: {}", noun(v))).collect();
+            entries.join(", ")
+        }
+        Node::List(items) => {
+            if let Some(field) = req_field(node) {
+                return field.to_string();
+            }
+            if let [Node::Symbol(op), value, Node::Str(currency)] = items.as_slice() {
+                if let (true, Ok(money)) = (op == "money", money::money(value, currency)) {
+                    return noun(&money);
+                }
+            }
+            let Some((Node::Symbol(op), args)) = items.split_first() else {
+                return items.iter().map(noun).collect::<Vec<_>>().join(", ");
+            };
+            match (op.as_str(), args) {
+                ("tuple", _) => args.iter().map(noun).collect::<Vec<_>>().join(", "),
+                ("get", [obj, Node::Str(key)]) => format!("{}.{key}", noun(obj)),
+                ("length", [list]) => format!("the number of {}", noun(list)),
+                ("sum", [list]) => format!("the total of {}", noun(list)),
+                ("sum", [list, key]) => format!("the total {} of {}", noun(key), noun(list)),
+                ("per-day-count-self", []) => "the number of earlier requests for this action today".into(),
+                ("per-day-count", [action, day]) => {
+                    format!("the number of earlier {} requests on {}", noun(action), noun(day))
+                }
+                ("usage-count", [key, period]) => {
+                    format!("the number of earlier requests counted by {} in {}", noun(key), noun(period))
+                }
+                ("convert", [amount, from, to]) => {
+                    format!("{} converted from {} to {}", noun(amount), noun(from), noun(to))
+                }
+                ("cumulative-spend", [action, Node::Str(period)]) if period == "day" => {
+                    format!("the total {} spend today", noun(action))
+                }
+                ("cumulative-spend", [action, period]) => {
+                    format!("the total {} spend this {}", noun(action), noun(period))
+                }
+                ("intersect", [a, b]) => format!("the items in both {} and {}", noun(a), noun(b)),
+                ("union", [a, b]) => format!("{} together with {}", noun(a), noun(b)),
+                ("difference", [a, b]) => format!("{} except {}", noun(a), noun(b)),
+                _ => format!("{op}({})", args.iter().map(noun).collect::<Vec<_>>().join(", ")),
+            }
+        }
+    }
+}
+
+/// Whole numbers without a trailing `.0`.
+fn number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+/// Render a compliance report for a policy, or one `(policy "name" ...)`
+/// clause, as Markdown: its requirements in plain language, a table of
+/// numeric bounds and pinned values, the request fields and vars it reads,
+/// the crypto checks it requires, and any expiry it sets.
+pub fn to_markdown(ast: &Node) -> String {
+    let (title, body) = match ast {
+        Node::List(items) => match items.as_slice() {
+            [Node::Symbol(op), Node::Str(name), body] if op == "policy" => (format!("Policy report: {name}"), body),
+            _ => ("Policy report".to_string(), ast),
+        },
+        _ => ("Policy report".to_string(), ast),
+    };
+    let mut out = format!("# {title}\n\n## Requirements\n\n");
+    out.push_str(&PolicyDescription { clauses: vec![describe_clause(None, body)] }.to_string());
+
+    out.push_str("\n## Constraints\n\n");
+    let summary = summarize_clause(None, body);
+    if summary.envelope.is_empty() && summary.claims.is_empty() {
+        out.push_str("No numeric bounds or pinned values.\n");
+    } else {
+        out.push_str("| Field | Minimum | Maximum | Required value |\n|---|---|---|---|\n");
+        let cell = |n: Option<f64>| n.map(number).unwrap_or_default();
+        for bound in &summary.envelope {
+            out.push_str(&table_row(&[format!("`{}`", table_cell(&bound.field)), cell(bound.min), cell(bound.max), String::new()]));
+        }
+        for (field, value) in &summary.claims {
+            out.push_str(&table_row(&[format!("`{}`", table_cell(field)), String::new(), String::new(), format!("`{}`", table_cell(value))]));
+        }
+    }
+
+    out.push_str("\n## Referenced fields\n\n");
+    let refs = referenced_fields(body);
+    let code = |names: &[String]| names.iter().map(|n| format!("`{n}`")).collect::<Vec<_>>().join(", ");
+    let (standard, vars): (Vec<String>, Vec<String>) =
+        refs.vars.iter().cloned().partition(|v| StandardVar::from_name(v).is_some());
+    if refs.req_keys.is_empty() && refs.vars.is_empty() {
+        out.push_str("None.\n");
+    }
+    for (label, names) in [("Request fields", &refs.req_keys), ("Caller vars", &vars), ("Verifier vars", &standard)] {
+        if !names.is_empty() {
+            out.push_str(&format!("- {label}: {}\n", code(names)));
+        }
+    }
+
+    out.push_str("\n## Crypto requirements\n\n");
+    let mut crypto: Vec<&str> = refs.ops_used.iter().map(|op| crypto_phrase(op)).collect();
+    if uses_op(body, "=hash") {
+        crypto.push("`=hash`: a request field must match a salted hash commitment");
+    }
+    if crypto.is_empty() {
+        out.push_str("None.\n");
+    }
+    for phrase in crypto {
+        out.push_str(&format!("- {phrase}\n"));
+    }
+
+    out.push_str("\n## Expiry\n\n");
+    let deadline = conjuncts(body).iter().filter_map(before_now).fold(None, |best: Option<&str>, t| match best {
+        Some(b) if !earlier(t, b) => Some(b),
+        _ => Some(t),
+    });
+    match deadline {
+        Some(t) => out.push_str(&format!("Requests are allowed only before {t}.\n")),
+        None => out.push_str("The policy sets no expiry; the token's `expires` field governs.\n"),
+    }
+    out
+}
+
+fn crypto_phrase(op: &str) -> &str {
+    match op {
+        "dpop_ok?" => "`dpop_ok?`: the caller must prove possession of its key (DPoP)",
+        "merkle_ok?" => "`merkle_ok?`: a value must carry a Merkle set-membership proof",
+        "vrf_ok?" => "`vrf_ok?`: a VRF proof must check out for the day and amount",
+        "thresh_ok?" => "`thresh_ok?`: a threshold of co-signers must approve",
+        "attested?" => "`attested?`: the device must present an attestation from a trusted root",
+        other => other,
+    }
+}
+
+fn uses_op(node: &Node, name: &str) -> bool {
+    match node {
+        Node::List(items) => items.first() == Some(&Node::Symbol(name.into())) || items.iter().any(|i| uses_op(i, name)),
+        _ => false,
+    }
+}
+
+fn table_row(cells: &[String]) -> String {
+    format!("| {} |\n", cells.join(" | "))
+}
+
+/// `text` safe inside a Markdown table cell.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,171 @@
+//! A JSON decision protocol shared by sidecars and language bindings.
+//!
+//! A caller sends one [`DecisionRequest`] document and gets one
+//! [`DecisionResponse`] back; [`decide_json`] does both ends as strings, so
+//! an HTTP sidecar, an FFI shim, or a WASM export only has to move bytes:
+//!
+//! ```json
+//! {"token": {...}, "request": {"action": "read"}, "vars": {}, "options": {}}
+//! {"allow": true, "reason_code": null, "obligations": [], "gas_used": 7,
+//!  "token_id": "...", "issuer_key": "...", "error": null}
+//! ```
+//!
+//! Every response field is always present. Failures are data, never panics:
+//! `error` carries a [`VerifyErrorCode`] name such as `invalid_signature`,
+//! or `invalid_request` when the document itself is malformed.
+//!
+//! Any caller can sign a token with a key of its own, so decisions need a
+//! [`Verifier`] configured with trusted issuers; without one every request
+//! is refused as `untrusted_issuer`. Time comes from the verifier's clock:
+//! a `now` in `vars` is rejected as `invalid_request`, since outside strict
+//! mode it would stand in for the clock.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::obligations::Obligation;
+use crate::profile::Verifier;
+use crate::token::{Presentation, Token, VerifyErrorCode, VerifyTokenResult};
+use crate::types::Node;
+
+/// A token and the request to decide with it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionRequest {
+    pub token: Token,
+    /// Request fields the policy reads as `(get req "...")`.
+    #[serde(default)]
+    pub request: HashMap<String, Node>,
+    /// Relying-party vars; the token's signed vars take precedence.
+    #[serde(default)]
+    pub vars: HashMap<String, Node>,
+    #[serde(default)]
+    pub options: DecisionOptions,
+}
+
+/// Per-request inputs beyond the token, request, and vars.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionOptions {
+    /// PoP presentation, for tokens bound to an agent key.
+    #[serde(default)]
+    pub presentation: Option<Presentation>,
+    /// Hash-chain preimage for this use of a use-limited token (see
+    /// [`Verifier::verify_use`]).
+    #[serde(default)]
+    pub use_preimage: Option<String>,
+}
+
+/// The outcome of a [`DecisionRequest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionResponse {
+    pub allow: bool,
+    /// The policy's `deny-with` code on a deny.
+    pub reason_code: Option<String>,
+    /// Obligations attached to an allow.
+    pub obligations: Vec<Obligation>,
+    /// Gas the policy evaluation consumed; 0 when rejected before evaluating.
+    pub gas_used: i64,
+    /// [`Token::id`] of the token decided with; `None` for malformed requests.
+    pub token_id: Option<String>,
+    /// The token's issuer `public_key`, trusted whenever `allow` is set.
+    pub issuer_key: Option<String>,
+    /// Why no decision could be made; `None` for allows and policy denies.
+    pub error: Option<DecisionError>,
+}
+
+/// A failure in the protocol's `{"code": ..., "message": ...}` form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionError {
+    pub code: DecisionErrorCode,
+    pub message: String,
+}
+
+/// An error code, serialized as a bare string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DecisionErrorCode {
+    /// The token was rejected, e.g. `invalid_signature`.
+    Verify(VerifyErrorCode),
+    /// The request document was at fault, e.g. `invalid_request`.
+    Protocol(ProtocolErrorCode),
+}
+
+/// Errors in the request document rather than the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolErrorCode {
+    /// Not JSON, not a [`DecisionRequest`], or setting `now` in `vars`.
+    InvalidRequest,
+}
+
+impl DecisionResponse {
+    fn error(code: DecisionErrorCode, message: String) -> Self {
+        Self {
+            allow: false,
+            reason_code: None,
+            obligations: Vec::new(),
+            gas_used: 0,
+            token_id: None,
+            issuer_key: None,
+            error: Some(DecisionError { code, message }),
+        }
+    }
+
+    fn from_result(result: VerifyTokenResult, token: &Token) -> Self {
+        let error = match (result.code, result.error) {
+            (Some(code), message) => Some(DecisionError {
+                code: DecisionErrorCode::Verify(code),
+                message: message.unwrap_or_default(),
+            }),
+            _ => None,
+        };
+        Self {
+            allow: result.allow,
+            reason_code: result.reason_code,
+            obligations: result.obligations,
+            gas_used: result.gas_used,
+            token_id: Some(result.token_id),
+            issuer_key: Some(token.public_key.clone()),
+            error,
+        }
+    }
+}
+
+/// Decide `request` with `verifier`, which must have trusted issuers.
+pub fn decide(verifier: &Verifier, request: DecisionRequest) -> DecisionResponse {
+    let DecisionRequest { token, request, vars, options } = request;
+    if vars.contains_key("now") {
+        return DecisionResponse::error(
+            DecisionErrorCode::Protocol(ProtocolErrorCode::InvalidRequest),
+            "vars may not set now; decisions use the verifier clock".into(),
+        );
+    }
+    if verifier.trusted_issuers.is_none() {
+        let mut response = DecisionResponse::error(
+            DecisionErrorCode::Verify(VerifyErrorCode::UntrustedIssuer),
+            "decisions require a verifier with trusted issuers".into(),
+        );
+        response.token_id = Some(token.id().into_owned());
+        response.issuer_key = Some(token.public_key);
+        return response;
+    }
+    let presentation = options.presentation.as_ref();
+    let result = match &options.use_preimage {
+        Some(preimage) => verifier.verify_use(&token, request, vars, presentation, preimage),
+        None => verifier.verify(&token, request, vars, presentation),
+    };
+    DecisionResponse::from_result(result, &token)
+}
+
+/// Decide a [`DecisionRequest`] document with `verifier`, returning a
+/// [`DecisionResponse`] document.
+pub fn decide_json(verifier: &Verifier, input: &str) -> String {
+    let response = match serde_json::from_str::<DecisionRequest>(input) {
+        Ok(request) => decide(verifier, request),
+        Err(e) => DecisionResponse::error(
+            DecisionErrorCode::Protocol(ProtocolErrorCode::InvalidRequest),
+            format!("invalid decision request: {e}"),
+        ),
+    };
+    serde_json::to_string(&response).expect("decision responses serialize")
+}
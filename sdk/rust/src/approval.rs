@@ -0,0 +1,194 @@
+//! Signed owner approvals for requests that need a second device.
+//!
+//! When a policy's decision waits on the owner, the verifier sends the
+//! request (or its [`request_hash`]) to the owner's phone. The phone answers
+//! with an [`Approval`]: the request hash, approve or deny, an expiry, and the
+//! owner's Ed25519 key, signed by that key. The agent re-presents the
+//! request with the approval and the verifier checks it with
+//! [`Approval::verify`].
+//!
+//! [`Approval::to_compact`] packs an approval into 137 bytes rendered as
+//! unpadded uppercase base32 behind an `ASA1:` prefix. Every character is
+//! in the QR alphanumeric set, so the code fits a version 10 QR symbol at
+//! error correction level M.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{key_from_hex, public_key_hex, sign_hex};
+use crate::crypto::{ct_eq_hex, verify_ed25519};
+use crate::replay::ReplayCache;
+pub use crate::request::request_hash;
+use crate::types::{Node, SplError};
+
+/// Prefix of the compact encoding, naming its layout version.
+pub const COMPACT_PREFIX: &str = "ASA1:";
+
+const COMPACT_LEN: usize = 1 + 8 + 32 + 32 + 64;
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The owner's answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalDecision {
+    Approve,
+    Deny,
+}
+
+/// An owner-signed decision on one request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// [`request_hash`] of the request being decided.
+    pub request_hash: String,
+    pub decision: ApprovalDecision,
+    /// Unix time (seconds) after which the approval is void.
+    pub expires: i64,
+    /// Owner's Ed25519 public key (hex).
+    pub owner_key: String,
+    /// Owner's signature over [`Approval::payload`] (hex).
+    pub signature: String,
+}
+
+impl Approval {
+    /// Sign `decision` on `req` with the owner's Ed25519 private key.
+    pub fn sign(
+        req: &HashMap<String, Node>,
+        decision: ApprovalDecision,
+        expires: i64,
+        owner_private_key_hex: &str,
+    ) -> Result<Self, SplError> {
+        let seed = key_from_hex(owner_private_key_hex, "owner private key")?;
+        let mut approval = Approval {
+            request_hash: request_hash(req),
+            decision,
+            expires,
+            owner_key: public_key_hex(&seed)?,
+            signature: String::new(),
+        };
+        approval.signature = sign_hex(&seed, &approval.payload())?;
+        Ok(approval)
+    }
+
+    /// Bytes the owner signs: a domain tag, then each field, `\0`-separated.
+    pub fn payload(&self) -> Vec<u8> {
+        let decision = match self.decision {
+            ApprovalDecision::Approve => "approve",
+            ApprovalDecision::Deny => "deny",
+        };
+        ["agent-safe-approval", &self.request_hash, decision, &self.expires.to_string(), &self.owner_key]
+            .join("\0")
+            .into_bytes()
+    }
+
+    /// Check that this approval was signed by `owner_key`, covers `req`, and
+    /// has not expired at `now`, and return the owner's decision. With a
+    /// replay cache, each approval is accepted once.
+    pub fn verify(
+        &self,
+        req: &HashMap<String, Node>,
+        owner_key: &str,
+        now: i64,
+        replay_cache: Option<&dyn ReplayCache>,
+    ) -> Result<ApprovalDecision, SplError> {
+        if !self.owner_key.eq_ignore_ascii_case(owner_key) {
+            return Err(SplError("approval is not from the expected owner".into()));
+        }
+        if !verify_ed25519(&self.payload(), &self.signature, &self.owner_key) {
+            return Err(SplError("invalid approval signature".into()));
+        }
+        if !ct_eq_hex(&self.request_hash, &request_hash(req)) {
+            return Err(SplError("approval is for a different request".into()));
+        }
+        if now > self.expires {
+            return Err(SplError("approval has expired".into()));
+        }
+        if let Some(cache) = replay_cache {
+            if !cache.check_and_record(&format!("approval:{}", self.signature)) {
+                return Err(SplError("approval already used".into()));
+            }
+        }
+        Ok(self.decision)
+    }
+
+    /// Compact, QR-friendly encoding; see the module docs.
+    pub fn to_compact(&self) -> Result<String, SplError> {
+        let mut bytes = Vec::with_capacity(COMPACT_LEN);
+        bytes.push(match self.decision {
+            ApprovalDecision::Approve => 1,
+            ApprovalDecision::Deny => 0,
+        });
+        bytes.extend_from_slice(&self.expires.to_be_bytes());
+        for (field, len) in [(&self.request_hash, 32), (&self.owner_key, 32), (&self.signature, 64)] {
+            let raw = hex::decode(field).map_err(|e| SplError(format!("invalid approval hex: {e}")))?;
+            if raw.len() != len {
+                return Err(SplError("approval field has the wrong length".into()));
+            }
+            bytes.extend_from_slice(&raw);
+        }
+        Ok(format!("{COMPACT_PREFIX}{}", base32_encode(&bytes)))
+    }
+
+    /// Decode [`Approval::to_compact`] output. The signature is not checked.
+    pub fn from_compact(s: &str) -> Result<Self, SplError> {
+        let body = s
+            .strip_prefix(COMPACT_PREFIX)
+            .ok_or_else(|| SplError(format!("compact approval must start with {COMPACT_PREFIX}")))?;
+        let bytes = base32_decode(body)?;
+        if bytes.len() != COMPACT_LEN {
+            return Err(SplError("compact approval has the wrong length".into()));
+        }
+        let decision = match bytes[0] {
+            1 => ApprovalDecision::Approve,
+            0 => ApprovalDecision::Deny,
+            other => return Err(SplError(format!("unknown approval decision byte {other}"))),
+        };
+        let expires = i64::from_be_bytes(bytes[1..9].try_into().unwrap_or_default());
+        Ok(Approval {
+            request_hash: hex::encode(&bytes[9..41]),
+            decision,
+            expires,
+            owner_key: hex::encode(&bytes[41..73]),
+            signature: hex::encode(&bytes[73..]),
+        })
+    }
+}
+
+/// Unpadded base32 (RFC 4648 §6).
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &b in data {
+        buffer = (buffer << 8) | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// Decode unpadded base32, rejecting non-canonical trailing bits.
+fn base32_decode(s: &str) -> Result<Vec<u8>, SplError> {
+    let err = || SplError("invalid base32".into());
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in s.bytes() {
+        let v = BASE32.iter().position(|&b| b == c).ok_or_else(err)? as u32;
+        buffer = (buffer << 5) | v;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits >= 5 || buffer != 0 {
+        return Err(err());
+    }
+    Ok(out)
+}
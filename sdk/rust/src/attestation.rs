@@ -0,0 +1,143 @@
+//! Signed device attestation statements.
+//!
+//! A request's `device_attested` field is whatever the caller says. An
+//! [`Attestation`] is evidence instead: an attestation root (the device
+//! vendor, or an enterprise MDM) signs a statement binding a device key and
+//! claims such as `secure-enclave` to a nonce and an expiry. The agent sends
+//! it as `req["attestation"]`; the verifier configures its trusted roots in
+//! an [`AttestationVerifier`] on [`crate::types::Env::attestation`], and
+//! `(attested? "secure-enclave")` holds only for a statement that verifies
+//! and carries that claim.
+//!
+//! The nonce stops a captured statement from being replayed: the verifier's
+//! nonce check should accept only challenges it issued and has not yet
+//! seen answered. The statement must also name the key that proved
+//! possession of the token ([`crate::types::Env::pop_key`]), so one
+//! device's statement cannot vouch for an agent holding another key.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::backend::{key_from_hex, public_key_hex, sign_hex};
+use crate::canonical::canonical_json;
+use crate::crypto::verify_ed25519;
+use crate::types::{Node, SplError};
+
+/// A root-signed statement about one device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    /// The verifier challenge this statement answers.
+    pub nonce: String,
+    /// The device's Ed25519 public key (hex).
+    pub device_key: String,
+    /// Properties the root vouches for, sorted and without duplicates.
+    pub claims: Vec<String>,
+    /// Unix time (seconds) after which the statement is void.
+    pub expires: i64,
+    /// The root's Ed25519 public key (hex).
+    pub root_key: String,
+    /// The root's signature over [`Attestation::payload`] (hex).
+    pub signature: String,
+}
+
+impl Attestation {
+    /// Sign a statement with the root's Ed25519 private key.
+    pub fn sign(
+        nonce: &str,
+        device_key: &str,
+        claims: &[&str],
+        expires: i64,
+        root_private_key_hex: &str,
+    ) -> Result<Self, SplError> {
+        let seed = key_from_hex(root_private_key_hex, "attestation root private key")?;
+        let mut claims: Vec<String> = claims.iter().map(|c| c.to_string()).collect();
+        claims.sort();
+        claims.dedup();
+        let mut attestation = Attestation {
+            nonce: nonce.to_string(),
+            device_key: device_key.to_string(),
+            claims,
+            expires,
+            root_key: public_key_hex(&seed)?,
+            signature: String::new(),
+        };
+        attestation.signature = sign_hex(&seed, &attestation.payload())?;
+        Ok(attestation)
+    }
+
+    /// Bytes the root signs: a domain tag, `\0`, then the canonical JSON of
+    /// every field but the signature.
+    pub fn payload(&self) -> Vec<u8> {
+        let fields = json!({
+            "nonce": self.nonce,
+            "device_key": self.device_key,
+            "claims": self.claims,
+            "expires": self.expires,
+            "root_key": self.root_key,
+        });
+        format!("agent-safe-attestation\0{}", canonical_json(&fields)).into_bytes()
+    }
+
+    /// Read a statement carried in a request field.
+    pub fn from_node(node: &Node) -> Result<Self, SplError> {
+        serde_json::from_value(node.to_json_value()).map_err(|e| SplError(format!("invalid attestation: {e}")))
+    }
+
+    /// The statement as a request field value.
+    pub fn to_node(&self) -> Node {
+        Node::from_json_value(&serde_json::to_value(self).unwrap_or_default())
+    }
+
+    pub fn has_claim(&self, claim: &str) -> bool {
+        self.claims.iter().any(|c| c == claim)
+    }
+}
+
+/// Accepts or rejects a statement's nonce.
+pub type NonceCheck = dyn Fn(&str) -> bool + Send + Sync;
+
+/// The attestation roots a verifier trusts, and its nonce check.
+#[derive(Clone)]
+pub struct AttestationVerifier {
+    roots: Vec<String>,
+    nonce_ok: Arc<NonceCheck>,
+}
+
+impl AttestationVerifier {
+    /// Trust statements signed by any of `roots` (hex Ed25519 public keys)
+    /// whose nonce `nonce_ok` accepts.
+    pub fn new(roots: impl IntoIterator<Item = String>, nonce_ok: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self { roots: roots.into_iter().map(|r| r.to_ascii_lowercase()).collect(), nonce_ok: Arc::new(nonce_ok) }
+    }
+
+    /// Check that `attestation` is signed by a trusted root, attests
+    /// `device_key` (hex), answers an accepted nonce, and has not expired
+    /// at `now`.
+    pub fn verify(&self, attestation: &Attestation, device_key: &str, now: i64) -> Result<(), SplError> {
+        if !self.roots.contains(&attestation.root_key.to_ascii_lowercase()) {
+            return Err(SplError("attestation is not from a trusted root".into()));
+        }
+        if !verify_ed25519(&attestation.payload(), &attestation.signature, &attestation.root_key) {
+            return Err(SplError("invalid attestation signature".into()));
+        }
+        if !attestation.device_key.eq_ignore_ascii_case(device_key) {
+            return Err(SplError("attestation is for a different device key".into()));
+        }
+        if now > attestation.expires {
+            return Err(SplError("attestation has expired".into()));
+        }
+        if !(self.nonce_ok)(&attestation.nonce) {
+            return Err(SplError("attestation nonce was not accepted".into()));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AttestationVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttestationVerifier").field("roots", &self.roots).finish_non_exhaustive()
+    }
+}
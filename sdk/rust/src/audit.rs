@@ -0,0 +1,132 @@
+//! Append-only, hash-linked audit log.
+//!
+//! Each entry commits to its predecessor's hash, so deleting, reordering, or
+//! editing any recorded entry breaks [`verify_chain`] from that point on.
+//! Entries recorded with [`AuditLog::append_for_request`] also commit to
+//! the [`request_hash`] of the request they decided.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::ct_eq_hex;
+use crate::request::request_hash;
+use crate::types::{Node, SplError};
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One recorded event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Unix seconds at which the event happened.
+    pub at: i64,
+    pub token_id: String,
+    /// Event name, e.g. `obligation.discharged`.
+    pub event: String,
+    pub detail: String,
+    /// [`request_hash`] of the request the event concerns, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_hash: Option<String>,
+    pub prev_hash: String,
+    /// Hex SHA-256 over [`entry_hash`]'s encoding of the fields above.
+    pub hash: String,
+}
+
+/// Hash linking an entry to `prev_hash`. A request hash is encoded after
+/// `prev_hash`, so entries without one hash as they always have.
+pub fn entry_hash(
+    seq: u64,
+    at: i64,
+    token_id: &str,
+    event: &str,
+    detail: &str,
+    request_hash: Option<&str>,
+    prev_hash: &str,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"agent-safe-audit-v1\0");
+    hasher.update(seq.to_be_bytes());
+    hasher.update(at.to_be_bytes());
+    for field in [token_id, event, detail, prev_hash].into_iter().chain(request_hash) {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Check that `entries` form an unbroken chain from [`GENESIS_HASH`].
+pub fn verify_chain(entries: &[AuditEntry]) -> Result<(), SplError> {
+    let mut prev = GENESIS_HASH;
+    for (i, e) in entries.iter().enumerate() {
+        if e.seq != i as u64 || !ct_eq_hex(&e.prev_hash, prev) {
+            return Err(SplError(format!("audit chain broken at entry {i}")));
+        }
+        if !ct_eq_hex(&e.hash, &entry_hash(e.seq, e.at, &e.token_id, &e.event, &e.detail, e.request_hash.as_deref(), &e.prev_hash)) {
+            return Err(SplError(format!("audit entry {i} hash mismatch")));
+        }
+        prev = &e.hash;
+    }
+    Ok(())
+}
+
+/// In-memory audit chain.
+#[derive(Default)]
+pub struct AuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an event and return the recorded entry.
+    pub fn append(&self, token_id: &str, event: &str, detail: &str, at: i64) -> AuditEntry {
+        self.push(token_id, event, detail, None, at)
+    }
+
+    /// Append an event about `req`, committing to its [`request_hash`].
+    pub fn append_for_request(
+        &self,
+        token_id: &str,
+        event: &str,
+        req: &HashMap<String, Node>,
+        detail: &str,
+        at: i64,
+    ) -> AuditEntry {
+        self.push(token_id, event, detail, Some(request_hash(req)), at)
+    }
+
+    fn push(&self, token_id: &str, event: &str, detail: &str, request_hash: Option<String>, at: i64) -> AuditEntry {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = entries.len() as u64;
+        let prev_hash = entries.last().map_or(GENESIS_HASH, |e| e.hash.as_str()).to_string();
+        let entry = AuditEntry {
+            seq,
+            at,
+            token_id: token_id.to_string(),
+            event: event.to_string(),
+            detail: detail.to_string(),
+            hash: entry_hash(seq, at, token_id, event, detail, request_hash.as_deref(), &prev_hash),
+            request_hash,
+            prev_hash,
+        };
+        entries.push(entry.clone());
+        entry
+    }
+
+    /// Snapshot of every entry, oldest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Hash of the latest entry, or [`GENESIS_HASH`] when empty.
+    pub fn head(&self) -> String {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.last().map_or(GENESIS_HASH, |e| e.hash.as_str()).to_string()
+    }
+}
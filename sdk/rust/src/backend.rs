@@ -0,0 +1,133 @@
+//! Pluggable Ed25519 implementation.
+//!
+//! Every Ed25519 operation in the crate goes through the process-wide
+//! [`CryptoBackend`]. The `dalek` feature (on by default) provides
+//! [`DalekBackend`]; embedders that already link another implementation
+//! (ring, aws-lc-rs, an HSM shim) build with `default-features = false` and
+//! call [`install_backend`] once at startup. Without either, signing fails
+//! and verification rejects.
+
+use std::sync::OnceLock;
+
+use crate::types::SplError;
+
+/// Ed25519 primitives over raw bytes. Keys are 32-byte RFC 8032 seeds.
+pub trait CryptoBackend: Send + Sync {
+    fn ed25519_public_key(&self, seed: &[u8; 32]) -> Result<[u8; 32], SplError>;
+    fn ed25519_sign(&self, seed: &[u8; 32], message: &[u8]) -> Result<[u8; 64], SplError>;
+    /// Strict verification: reject non-canonical signatures and small-order keys.
+    fn ed25519_verify(&self, public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool;
+    /// Whether every `(public_key, message, signature)` verifies. `false`
+    /// says only that some signature failed; callers find which one with
+    /// [`CryptoBackend::ed25519_verify`].
+    fn ed25519_verify_batch(&self, items: &[(&[u8; 32], &[u8], &[u8; 64])]) -> bool {
+        items.iter().all(|(key, message, signature)| self.ed25519_verify(key, message, signature))
+    }
+}
+
+static INSTALLED: OnceLock<&'static dyn CryptoBackend> = OnceLock::new();
+
+/// Install the process-wide backend. Fails if one is already installed or
+/// the default was already used.
+pub fn install_backend(backend: &'static dyn CryptoBackend) -> Result<(), SplError> {
+    INSTALLED
+        .set(backend)
+        .map_err(|_| SplError("crypto backend already installed".into()))
+}
+
+/// The active backend.
+pub fn backend() -> &'static dyn CryptoBackend {
+    *INSTALLED.get_or_init(|| {
+        #[cfg(feature = "dalek")]
+        {
+            &DalekBackend
+        }
+        #[cfg(not(feature = "dalek"))]
+        {
+            &NoBackend
+        }
+    })
+}
+
+#[cfg(feature = "dalek")]
+pub use dalek::DalekBackend;
+
+#[cfg(feature = "dalek")]
+mod dalek {
+    use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+    use super::CryptoBackend;
+    use crate::types::SplError;
+
+    /// `ed25519-dalek` backend (`dalek` feature, default).
+    pub struct DalekBackend;
+
+    impl CryptoBackend for DalekBackend {
+        fn ed25519_public_key(&self, seed: &[u8; 32]) -> Result<[u8; 32], SplError> {
+            Ok(SigningKey::from_bytes(seed).verifying_key().to_bytes())
+        }
+
+        fn ed25519_sign(&self, seed: &[u8; 32], message: &[u8]) -> Result<[u8; 64], SplError> {
+            Ok(SigningKey::from_bytes(seed).sign(message).to_bytes())
+        }
+
+        fn ed25519_verify(&self, public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+            let Ok(key) = VerifyingKey::from_bytes(public_key) else { return false };
+            key.verify_strict(message, &Signature::from_bytes(signature)).is_ok()
+        }
+
+        /// One multiscalar check for the whole batch (`batch` feature).
+        /// Batches with a small-order key fail, as strict verification
+        /// rejects those keys. The batch equation is cofactored, so it may
+        /// also accept a signature whose `R` has a small-order component,
+        /// which only the key's holder can produce.
+        #[cfg(feature = "batch")]
+        fn ed25519_verify_batch(&self, items: &[(&[u8; 32], &[u8], &[u8; 64])]) -> bool {
+            let mut keys = Vec::with_capacity(items.len());
+            for (key, _, _) in items {
+                match VerifyingKey::from_bytes(key) {
+                    Ok(key) if !key.is_weak() => keys.push(key),
+                    _ => return false,
+                }
+            }
+            let messages: Vec<&[u8]> = items.iter().map(|(_, message, _)| *message).collect();
+            let signatures: Vec<Signature> = items.iter().map(|(_, _, signature)| Signature::from_bytes(signature)).collect();
+            ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_ok()
+        }
+    }
+}
+
+/// Placeholder when no backend is compiled in or installed: fails closed.
+#[cfg(not(feature = "dalek"))]
+struct NoBackend;
+
+#[cfg(not(feature = "dalek"))]
+impl CryptoBackend for NoBackend {
+    fn ed25519_public_key(&self, _seed: &[u8; 32]) -> Result<[u8; 32], SplError> {
+        Err(SplError("no Ed25519 backend installed".into()))
+    }
+
+    fn ed25519_sign(&self, _seed: &[u8; 32], _message: &[u8]) -> Result<[u8; 64], SplError> {
+        Err(SplError("no Ed25519 backend installed".into()))
+    }
+
+    fn ed25519_verify(&self, _public_key: &[u8; 32], _message: &[u8], _signature: &[u8; 64]) -> bool {
+        false
+    }
+}
+
+/// Decode a 32-byte hex key, naming it in errors.
+pub(crate) fn key_from_hex(key_hex: &str, what: &str) -> Result<[u8; 32], SplError> {
+    hex::decode(key_hex)
+        .map_err(|e| SplError(format!("invalid {what} hex: {e}")))?
+        .try_into()
+        .map_err(|_| SplError(format!("{what} must be 32 bytes")))
+}
+
+pub(crate) fn public_key_hex(seed: &[u8; 32]) -> Result<String, SplError> {
+    backend().ed25519_public_key(seed).map(hex::encode)
+}
+
+pub(crate) fn sign_hex(seed: &[u8; 32], message: &[u8]) -> Result<String, SplError> {
+    backend().ed25519_sign(seed, message).map(hex::encode)
+}
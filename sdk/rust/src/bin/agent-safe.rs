@@ -0,0 +1,389 @@
+//! `agent-safe`: mint, verify, and inspect tokens from the command line.
+
+use std::collections::HashMap;
+use std::fs;
+use std::process;
+
+use agent_safe_spl::analysis::{lint_with, to_markdown, LintConfig, LintRule};
+use agent_safe_spl::caveat::{chain_links, ChainLink};
+use agent_safe_spl::conformance::run_dir;
+use agent_safe_spl::obligations::Obligation;
+use agent_safe_spl::parser::{format_policy, parse_all};
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::sandbox::{Sandbox, Scenario};
+use agent_safe_spl::signature::SignatureScheme;
+use agent_safe_spl::summary::TokenSummary;
+use agent_safe_spl::testing::{run_suite, PolicySuite};
+use agent_safe_spl::token::{mint, new_token_id, MintOptions, Presentation, Token};
+use agent_safe_spl::types::{map_from_json, Node};
+use agent_safe_spl::vectors::all_vectors;
+
+const USAGE: &str = "usage: agent-safe <command> [options]
+
+commands:
+  keygen  [--alg EdDSA|ES256|ES256K]
+  mint    --policy p.spl --key key.json [--expires T] [--issued-at T] [--sealed]
+          [--pop-key HEX] [--alg NAME] [--attenuable] [--vars v.json]
+          [--token-id ID|uuid] [--scope GLOBS]
+  verify  --token t.json --request r.json [--vars v.json] [--presentation p.json]
+  inspect --token t.json
+  chain   --token t.json [--json]
+  fmt     --policy p.spl [--check]
+  lint    --policy p.spl [--vars v.json] [--enable RULES] [--disable RULES]
+  report  --policy p.spl
+  sandbox --policy p.spl --scenario s.json
+  test    --policy p.spl --cases cases.json
+  gen-vectors [--out DIR]
+  conformance --dir DIR
+
+--key takes a keygen output file or a file holding the private key hex.
+--token-id uuid signs a fresh random UUID into the token.
+--scope takes comma-separated action globs such as payments.*.
+--token accepts token JSON or a compact JWS. Times are RFC 3339.
+lint takes comma-separated rule codes; --vars names the vars the verifier
+supplies, so comparisons against any other symbol are flagged.
+gen-vectors writes the cross-SDK test vectors (default: current directory).
+conformance prints a JSON report of the spec cases in DIR.
+chain prints the delegation chain as a tree, or as JSON with --json.
+report prints a Markdown compliance report, one per named clause.
+verify exits 0 on allow, 1 on deny; test and conformance exit 1 if any
+case fails; lint exits 1 if it warns; chain exits 1 if a signature fails;
+every command exits 2 on error.";
+
+/// Command-line failure, reported on stderr with exit status 2.
+struct CliError(String);
+
+impl<E: std::fmt::Display> From<E> for CliError {
+    fn from(e: E) -> Self {
+        CliError(e.to_string())
+    }
+}
+
+type CliResult = Result<i32, CliError>;
+
+struct Args {
+    values: HashMap<String, String>,
+    switches: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String], value_flags: &[&str], switch_flags: &[&str]) -> Result<Args, CliError> {
+        let mut parsed = Args { values: HashMap::new(), switches: Vec::new() };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| CliError(format!("unexpected argument: {arg}")))?;
+            if switch_flags.contains(&name) {
+                parsed.switches.push(name.to_string());
+            } else if value_flags.contains(&name) {
+                let value = iter.next().ok_or_else(|| CliError(format!("--{name} needs a value")))?;
+                parsed.values.insert(name.to_string(), value.clone());
+            } else {
+                return Err(CliError(format!("unknown option: --{name}")));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.get(name).ok_or_else(|| CliError(format!("--{name} is required")))
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+}
+
+fn read(path: &str) -> Result<String, CliError> {
+    fs::read_to_string(path).map_err(|e| CliError(format!("{path}: {e}")))
+}
+
+fn read_json(path: &str) -> Result<serde_json::Value, CliError> {
+    serde_json::from_str(&read(path)?).map_err(|e| CliError(format!("{path}: {e}")))
+}
+
+fn read_token(path: &str) -> Result<Token, CliError> {
+    let src = read(path)?;
+    let src = src.trim();
+    if src.starts_with('{') {
+        serde_json::from_str(src).map_err(|e| CliError(format!("{path}: {e}")))
+    } else {
+        Token::from_jws(src).map_err(|e| CliError(format!("{path}: {e}")))
+    }
+}
+
+fn alg(args: &Args) -> Result<SignatureScheme, CliError> {
+    match args.get("alg") {
+        None => Ok(SignatureScheme::Ed25519),
+        Some(name) => SignatureScheme::from_name(name).ok_or_else(|| CliError(format!("unknown alg: {name}"))),
+    }
+}
+
+fn print_json(value: &impl serde::Serialize) -> CliResult {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(0)
+}
+
+fn keygen(args: &Args) -> CliResult {
+    let alg = alg(args)?;
+    let (public_key, private_key) = alg.generate_keypair()?;
+    print_json(&serde_json::json!({ "alg": alg, "public_key": public_key, "private_key": private_key }))
+}
+
+fn mint_cmd(args: &Args) -> CliResult {
+    let policy = read(args.required("policy")?)?;
+    let key_src = read(args.required("key")?)?;
+    let private_key = match serde_json::from_str::<serde_json::Value>(&key_src) {
+        Ok(json) => json["private_key"]
+            .as_str()
+            .ok_or_else(|| CliError("key file has no private_key".into()))?
+            .to_string(),
+        Err(_) => key_src.trim().to_string(),
+    };
+    let opts = MintOptions {
+        expires: args.get("expires").map(Into::into),
+        issued_at: args.get("issued-at").map(Into::into),
+        sealed: args.switch("sealed"),
+        pop_key: args.get("pop-key").map(Into::into),
+        alg: alg(args)?,
+        attenuable: args.switch("attenuable"),
+        token_id: args.get("token-id").map(|id| if id == "uuid" { new_token_id() } else { id.into() }),
+        scope: args.get("scope").map(|s| s.split(',').map(|g| g.trim().to_string()).collect()).unwrap_or_default(),
+        vars: match args.get("vars") {
+            Some(path) => map_from_json(&read_json(path)?)?.into_iter().collect(),
+            None => Default::default(),
+        },
+        ..MintOptions::default()
+    };
+    print_json(&mint(&policy, &private_key, opts)?)
+}
+
+fn verify_cmd(args: &Args) -> CliResult {
+    let token = read_token(args.required("token")?)?;
+    let req = map_from_json(&read_json(args.required("request")?)?)?;
+    let vars: HashMap<String, Node> = match args.get("vars") {
+        Some(path) => map_from_json(&read_json(path)?)?,
+        None => HashMap::new(),
+    };
+    let presentation: Option<Presentation> = match args.get("presentation") {
+        Some(path) => Some(serde_json::from_value(read_json(path)?)?),
+        None => None,
+    };
+
+    let result = Verifier::default().verify(&token, req, vars, presentation.as_ref());
+    if result.allow {
+        println!("ALLOW");
+        print_obligations(&result.obligations);
+        return Ok(0);
+    }
+    match (result.code, result.error, result.reason_code) {
+        (Some(code), Some(error), _) => println!("DENY ({}): {error}", code.as_str()),
+        (_, _, Some(reason)) => println!("DENY: {reason}"),
+        _ => println!("DENY"),
+    }
+    Ok(1)
+}
+
+fn print_obligations(obligations: &[Obligation]) {
+    for obligation in obligations {
+        match obligation.deadline_secs {
+            Some(secs) => println!("obligation: {} within {secs}s", obligation.kind),
+            None => println!("obligation: {}", obligation.kind),
+        }
+    }
+}
+
+fn inspect(args: &Args) -> CliResult {
+    let token = read_token(args.required("token")?)?;
+    print_json(&TokenSummary::from(&token))
+}
+
+fn chain_cmd(args: &Args) -> CliResult {
+    let links = chain_links(&read_token(args.required("token")?)?);
+    let status = if links.iter().all(|l| l.signature_valid) { 0 } else { 1 };
+    if args.switch("json") {
+        print_json(&links)?;
+    } else {
+        links.iter().for_each(print_link);
+    }
+    Ok(status)
+}
+
+fn print_link(link: &ChainLink) {
+    let depth = link.depth as usize;
+    let (branch, indent) = match depth {
+        0 => (String::new(), "  ".to_string()),
+        _ => (format!("{}└── ", "    ".repeat(depth - 1)), "    ".repeat(depth) + "  "),
+    };
+    let status = match &link.error {
+        None => "signature ok".to_string(),
+        Some(error) => format!("INVALID: {error}"),
+    };
+    match &link.caveat {
+        None => println!("{branch}token by {} ({status})", link.issuer),
+        Some(_) => println!("{branch}caveat {} by {} ({status})", depth - 1, link.issuer),
+    }
+    println!("{indent}expires: {}", link.expires.as_deref().unwrap_or("never"));
+    if let Some(caveat) = &link.caveat {
+        println!("{indent}adds: {caveat}");
+    }
+    for clause in &link.clauses {
+        if let Some(name) = &clause.name {
+            println!("{indent}clause {name}:");
+        }
+        for line in &clause.explanation {
+            println!("{indent}- {line}");
+        }
+    }
+    for step in link.narrowing.iter().filter(|s| s.rule != "conjunction") {
+        println!("{indent}{}: {}", step.rule, step.detail);
+    }
+}
+
+fn lint_rules(list: Option<&str>) -> Result<Vec<LintRule>, CliError> {
+    list.into_iter()
+        .flat_map(|list| list.split(','))
+        .map(|code| LintRule::from_code(code.trim()).ok_or_else(|| CliError(format!("unknown lint rule: {code}"))))
+        .collect()
+}
+
+fn lint_cmd(args: &Args) -> CliResult {
+    let mut config = LintConfig::default();
+    for rule in lint_rules(args.get("enable"))? {
+        config = config.enable(rule);
+    }
+    for rule in lint_rules(args.get("disable"))? {
+        config = config.disable(rule);
+    }
+    if let Some(path) = args.get("vars") {
+        config.known_vars = Some(map_from_json(&read_json(path)?)?.into_keys().collect());
+    }
+    let mut warned = false;
+    for expr in parse_all(&read(args.required("policy")?)?)? {
+        for warning in lint_with(&expr, &config) {
+            println!("{}: {}", warning.code, warning.message);
+            warned = true;
+        }
+    }
+    Ok(if warned { 1 } else { 0 })
+}
+
+fn report_cmd(args: &Args) -> CliResult {
+    let reports: Vec<String> = parse_all(&read(args.required("policy")?)?)?.iter().map(to_markdown).collect();
+    print!("{}", reports.join("\n"));
+    Ok(0)
+}
+
+fn fmt_cmd(args: &Args) -> CliResult {
+    let src = read(args.required("policy")?)?;
+    let formatted = format_policy(&src)?;
+    if args.switch("check") {
+        if src != formatted {
+            eprintln!("policy is not formatted");
+            return Ok(1);
+        }
+        return Ok(0);
+    }
+    print!("{formatted}");
+    Ok(0)
+}
+
+fn sandbox_cmd(args: &Args) -> CliResult {
+    let policy = read(args.required("policy")?)?;
+    let scenario: Scenario = serde_json::from_value(read_json(args.required("scenario")?)?)?;
+    for result in Sandbox::run_scenario(&policy, &scenario)? {
+        let outcome = result.outcome;
+        match (outcome.allow, outcome.error) {
+            (true, _) => println!("{}: ALLOW", result.name),
+            (false, Some(error)) => println!("{}: DENY ({error})", result.name),
+            (false, None) => match &outcome.reason_code {
+                Some(reason) => println!("{}: DENY: {reason}", result.name),
+                None => println!("{}: DENY", result.name),
+            },
+        }
+        print_obligations(&outcome.obligations);
+    }
+    Ok(0)
+}
+
+fn test_cmd(args: &Args) -> CliResult {
+    let policy = read(args.required("policy")?)?;
+    let suite: PolicySuite = serde_json::from_value(read_json(args.required("cases")?)?)?;
+    let report = run_suite(&policy, &suite)?;
+    for name in &report.passed {
+        println!("ok    {name}");
+    }
+    for failure in &report.failed {
+        println!("FAIL  {}: expected {}, got {}", failure.name, failure.expected.name(), failure.actual.name());
+        if let Some(error) = &failure.error {
+            println!("      error: {error}");
+        }
+        for line in &failure.trace {
+            println!("      {line}");
+        }
+    }
+    println!("{} passed, {} failed", report.passed.len(), report.failed.len());
+    Ok(if report.is_success() { 0 } else { 1 })
+}
+
+fn gen_vectors(args: &Args) -> CliResult {
+    let dir = std::path::Path::new(args.get("out").unwrap_or("."));
+    fs::create_dir_all(dir)?;
+    for (name, vectors) in all_vectors()? {
+        let path = dir.join(name);
+        fs::write(&path, serde_json::to_string_pretty(&vectors)? + "\n")?;
+        println!("wrote {}", path.display());
+    }
+    Ok(0)
+}
+
+fn conformance_cmd(args: &Args) -> CliResult {
+    let report = run_dir(std::path::Path::new(args.required("dir")?))?;
+    print_json(&report)?;
+    Ok(if report.is_success() { 0 } else { 1 })
+}
+
+fn run(argv: &[String]) -> CliResult {
+    let Some((command, rest)) = argv.split_first() else {
+        return Err(CliError(USAGE.into()));
+    };
+    match command.as_str() {
+        "keygen" => keygen(&Args::parse(rest, &["alg"], &[])?),
+        "mint" => mint_cmd(&Args::parse(
+            rest,
+            &["policy", "key", "expires", "issued-at", "pop-key", "alg", "vars", "token-id", "scope"],
+            &["sealed", "attenuable"],
+        )?),
+        "verify" => verify_cmd(&Args::parse(rest, &["token", "request", "vars", "presentation"], &[])?),
+        "inspect" => inspect(&Args::parse(rest, &["token"], &[])?),
+        "chain" => chain_cmd(&Args::parse(rest, &["token"], &["json"])?),
+        "lint" => lint_cmd(&Args::parse(rest, &["policy", "vars", "enable", "disable"], &[])?),
+        "report" => report_cmd(&Args::parse(rest, &["policy"], &[])?),
+        "fmt" => fmt_cmd(&Args::parse(rest, &["policy"], &["check"])?),
+        "sandbox" => sandbox_cmd(&Args::parse(rest, &["policy", "scenario"], &[])?),
+        "test" => test_cmd(&Args::parse(rest, &["policy", "cases"], &[])?),
+        "gen-vectors" => gen_vectors(&Args::parse(rest, &["out"], &[])?),
+        "conformance" => conformance_cmd(&Args::parse(rest, &["dir"], &[])?),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(0)
+        }
+        other => Err(CliError(format!("unknown command: {other}\n\n{USAGE}"))),
+    }
+}
+
+fn main() {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    match run(&argv) {
+        Ok(status) => process::exit(status),
+        Err(CliError(message)) => {
+            eprintln!("{message}");
+            process::exit(2);
+        }
+    }
+}
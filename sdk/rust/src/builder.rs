@@ -0,0 +1,248 @@
+//! Fluent construction of requests and evaluation environments, so callers
+//! need not assemble `HashMap<String, Node>` and [`Env`] by hand.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::attestation::AttestationVerifier;
+use crate::denylist::DenyListProvider;
+use crate::money::RateProvider;
+use crate::ops::OpHandler;
+use crate::spend::SpendTracker;
+use crate::time::Clock;
+use crate::types::{CallbackState, CounterKey, CryptoCallbacks, Env, GasSchedule, Limits, Node, SplError, StringMatch};
+
+/// Builds the `req` map, with typed setters for the fields policies use most.
+#[derive(Debug, Clone, Default)]
+pub struct RequestBuilder {
+    fields: HashMap<String, Node>,
+}
+
+impl RequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn action(self, action: &str) -> Self {
+        self.field("action", action)
+    }
+
+    pub fn amount(self, amount: f64) -> Self {
+        self.field("amount", amount)
+    }
+
+    pub fn recipient(self, recipient: &str) -> Self {
+        self.field("recipient", recipient)
+    }
+
+    pub fn actor_pub(self, public_key_hex: &str) -> Self {
+        self.field("actor_pub", public_key_hex)
+    }
+
+    /// UTC day (`YYYY-MM-DD`) the request counts against.
+    pub fn day(self, day: &str) -> Self {
+        self.field("day", day)
+    }
+
+    /// Set any field, replacing an earlier value.
+    pub fn field(mut self, key: &str, value: impl Into<Node>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Validate and return the request. Numbers must be finite, `action`
+    /// a non-empty string, and `day` a `YYYY-MM-DD` string.
+    pub fn build(self) -> Result<HashMap<String, Node>, SplError> {
+        for (key, value) in &self.fields {
+            if let Node::Number(n) = value {
+                if !n.is_finite() {
+                    return Err(SplError(format!("request field {key} must be a finite number")));
+                }
+            }
+        }
+        match self.fields.get("action") {
+            None => {}
+            Some(Node::Str(a)) if !a.is_empty() => {}
+            Some(_) => return Err(SplError("request action must be a non-empty string".into())),
+        }
+        match self.fields.get("day") {
+            None => {}
+            Some(Node::Str(d)) if is_day(d) => {}
+            Some(_) => return Err(SplError("request day must be YYYY-MM-DD".into())),
+        }
+        Ok(self.fields)
+    }
+}
+
+fn is_day(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && b.iter().enumerate().all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit())
+}
+
+/// Builds an [`Env`], checking the gas budget before handing it out.
+#[derive(Default)]
+pub struct EnvBuilder {
+    env: Env,
+    ops: Vec<(String, OpHandler)>,
+}
+
+impl EnvBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(mut self, req: HashMap<String, Node>) -> Self {
+        self.env.req = req;
+        self
+    }
+
+    pub fn var(mut self, name: &str, value: impl Into<Node>) -> Self {
+        self.env.vars.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Bind `name` to a map of per-key limits for `limit-for` / `remaining-for`.
+    pub fn limits<I, K>(self, name: &str, limits: I) -> Self
+    where
+        I: IntoIterator<Item = (K, f64)>,
+        K: Into<String>,
+    {
+        let map: BTreeMap<String, Node> = limits.into_iter().map(|(k, v)| (k.into(), Node::Number(v))).collect();
+        self.var(name, Node::Map(map))
+    }
+
+    /// Count earlier uses under a [`CounterKey`] in a period, for
+    /// `usage-count`, `per-day-count`, and `per_day` limits.
+    pub fn usage_count(mut self, f: impl Fn(&CounterKey, &str) -> i64 + Send + Sync + 'static) -> Self {
+        self.env.usage_count = Arc::new(f);
+        self
+    }
+
+    /// Resolve `now` through `clock` rather than the system clock.
+    pub fn clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.env.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn spent_for(mut self, f: impl Fn(&str) -> f64 + Send + Sync + 'static) -> Self {
+        self.env.spent_for = Arc::new(f);
+        self
+    }
+
+    pub fn max_gas(mut self, max_gas: i64) -> Self {
+        self.env.max_gas = max_gas;
+        self
+    }
+
+    pub fn gas_schedule(mut self, gas: GasSchedule) -> Self {
+        self.env.gas = gas;
+        self
+    }
+
+    /// Depth, list, and string limits for evaluation (see [`Limits`]).
+    pub fn size_limits(mut self, limits: Limits) -> Self {
+        self.env.limits = limits;
+        self
+    }
+
+    pub fn crypto(mut self, crypto: CryptoCallbacks) -> Self {
+        self.env.crypto = crypto;
+        self
+    }
+
+    pub fn dpop_ok(mut self, f: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.env.crypto.dpop_ok = CallbackState::Configured(Arc::new(f));
+        self
+    }
+
+    pub fn merkle_ok(mut self, f: impl Fn(&[Node]) -> bool + Send + Sync + 'static) -> Self {
+        self.env.crypto.merkle_ok = Some(Arc::new(f));
+        self
+    }
+
+    pub fn vrf_ok(mut self, f: impl Fn(&str, f64) -> bool + Send + Sync + 'static) -> Self {
+        self.env.crypto.vrf_ok = CallbackState::Configured(Arc::new(f));
+        self
+    }
+
+    pub fn thresh_ok(mut self, f: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.env.crypto.thresh_ok = CallbackState::Configured(Arc::new(f));
+        self
+    }
+
+    pub fn denylists(mut self, provider: impl DenyListProvider + 'static, max_staleness_secs: u64) -> Self {
+        self.env.denylists = Some(Arc::new(provider));
+        self.env.denylist_max_staleness_secs = max_staleness_secs;
+        self
+    }
+
+    pub fn attestation(mut self, verifier: AttestationVerifier) -> Self {
+        self.env.attestation = Some(Arc::new(verifier));
+        self
+    }
+
+    /// The key that proved possession of the token; see [`Env::pop_key`].
+    pub fn pop_key(mut self, key: &str) -> Self {
+        self.env.pop_key = Some(key.to_string());
+        self
+    }
+
+    pub fn rates(mut self, provider: impl RateProvider + 'static) -> Self {
+        self.env.rates = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn spend_tracker(mut self, tracker: Arc<dyn SpendTracker>) -> Self {
+        self.env.spend_tracker = Some(tracker);
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.env.strict = strict;
+        self
+    }
+
+    /// Compare strings under `mode` (see [`StringMatch`]).
+    pub fn string_match(mut self, mode: StringMatch) -> Self {
+        self.env.string_match = mode;
+        self
+    }
+
+    /// Permit only these operators (see [`Env::allowed_ops`]).
+    pub fn allowed_ops<'a>(mut self, ops: impl IntoIterator<Item = &'a str>) -> Self {
+        self.env.allowed_ops = Some(ops.into_iter().map(str::to_string).collect());
+        self
+    }
+
+    /// Register a host operator when the environment is built (see
+    /// [`Env::register_op`]), so the strict-mode check sees the final setting.
+    pub fn op(mut self, name: &str, handler: OpHandler) -> Self {
+        self.ops.push((name.to_string(), handler));
+        self
+    }
+
+    pub fn sealed(mut self, sealed: bool) -> Self {
+        self.env.sealed = sealed;
+        self
+    }
+
+    /// Return the environment. The gas budget must be positive and every
+    /// schedule cost non-negative; a negative cost would refund gas.
+    pub fn build(self) -> Result<Env, SplError> {
+        let mut env = self.env;
+        if env.max_gas <= 0 {
+            return Err(SplError("max_gas must be positive".into()));
+        }
+        let g = &env.gas;
+        if [g.node, g.crypto, g.host_call, g.list_item, g.string_per_kb].iter().any(|c| *c < 0) {
+            return Err(SplError("gas schedule costs must be non-negative".into()));
+        }
+        for (name, handler) in self.ops {
+            env.register_op(&name, handler)?;
+        }
+        Ok(env)
+    }
+}
@@ -0,0 +1,184 @@
+//! Parsed policies shared across verifications.
+//!
+//! Every verification parses its token's policy. A verifier serving many
+//! requests under the same few tokens can hand [`crate::profile::Verifier`]
+//! a [`PolicyCache`], which keeps the most recently used parses keyed by
+//! [`policy_hash`] and the [`Limits`] and [`Syntax`] they were parsed under:
+//!
+//! ```
+//! use std::sync::Arc;
+//! use agent_safe_spl::cache::PolicyCache;
+//! use agent_safe_spl::profile::Verifier;
+//!
+//! let cache = Arc::new(PolicyCache::new(1024).with_max_policy_bytes(64 * 1024));
+//! let verifier = Verifier::default().with_policy_cache(cache.clone());
+//! // ... verify tokens ...
+//! assert_eq!(cache.stats().hits, 0);
+//! ```
+//!
+//! Policies that fail to parse are not cached, and neither are policies
+//! over the byte limit. An observer set with [`PolicyCache::with_observer`]
+//! sees each hit, miss, and eviction as it happens, for export to a metrics
+//! system; [`PolicyCache::stats`] reports the running totals.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::parser::{parse_all_with_syntax, Syntax};
+use crate::policy_store::policy_hash;
+use crate::token::{Parsed, PolicyParser};
+use crate::types::{Limits, Node, SplError};
+
+/// A cache lookup, reported to the observer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheEvent {
+    Hit,
+    /// The policy was parsed; it is cached if it parsed and fits.
+    Miss,
+    /// The least recently used entry was dropped to make room.
+    Evict,
+}
+
+/// Running totals since the cache was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Policies currently cached.
+    pub entries: usize,
+}
+
+type Observer = Arc<dyn Fn(CacheEvent) + Send + Sync>;
+
+/// A policy hash and the limits and syntax it was parsed under.
+type Key = (String, Limits, Syntax);
+
+/// Least-recently-used cache of parsed policies.
+pub struct PolicyCache {
+    capacity: usize,
+    max_policy_bytes: Option<usize>,
+    observer: Option<Observer>,
+    inner: Mutex<Lru>,
+}
+
+#[derive(Default)]
+struct Lru {
+    /// Policy hash and limits to the parse and its last-use tick.
+    entries: HashMap<Key, (Arc<Vec<Node>>, u64)>,
+    /// Last-use tick to key, oldest first.
+    order: BTreeMap<u64, Key>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl PolicyCache {
+    /// A cache holding at most `capacity` policies. A zero capacity caches
+    /// nothing but still counts misses.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, max_policy_bytes: None, observer: None, inner: Mutex::new(Lru::default()) }
+    }
+
+    /// Parse but do not cache policies longer than `bytes`.
+    pub fn with_max_policy_bytes(mut self, bytes: usize) -> Self {
+        self.max_policy_bytes = Some(bytes);
+        self
+    }
+
+    /// Call `observer` on every lookup and eviction. It runs with the cache
+    /// locked, so it must not use the cache itself.
+    pub fn with_observer(mut self, observer: impl Fn(CacheEvent) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Parse `policy`, reusing a cached parse of the same source.
+    pub fn parse(&self, policy: &str) -> Result<Arc<Vec<Node>>, SplError> {
+        self.parse_with_limits(policy, &Limits::default())
+    }
+
+    /// Parse `policy` under `limits`, reusing a parse of the same source
+    /// under the same limits. A parse under looser limits is never reused,
+    /// so verifiers with different limits can share a cache.
+    pub fn parse_with_limits(&self, policy: &str, limits: &Limits) -> Result<Arc<Vec<Node>>, SplError> {
+        self.parse_keyed(policy, limits, Syntax::Current)
+    }
+
+    fn parse_keyed(&self, policy: &str, limits: &Limits, syntax: Syntax) -> Result<Arc<Vec<Node>>, SplError> {
+        let key = (policy_hash(policy), limits.clone(), syntax);
+        let mut guard = self.lock();
+        let lru = &mut *guard;
+        lru.tick += 1;
+        let tick = lru.tick;
+        if let Some((exprs, last_used)) = lru.entries.get_mut(&key) {
+            let (exprs, previous) = (exprs.clone(), std::mem::replace(last_used, tick));
+            lru.order.remove(&previous);
+            lru.order.insert(tick, key);
+            lru.stats.hits += 1;
+            self.notify(CacheEvent::Hit);
+            return Ok(exprs);
+        }
+        lru.stats.misses += 1;
+        self.notify(CacheEvent::Miss);
+        // Parse unlocked so a large policy does not stall other lookups.
+        drop(guard);
+
+        let exprs = Arc::new(parse_all_with_syntax(policy, limits, syntax)?);
+        if self.capacity == 0 || self.max_policy_bytes.is_some_and(|max| policy.len() > max) {
+            return Ok(exprs);
+        }
+        let mut guard = self.lock();
+        let lru = &mut *guard;
+        if let Some((_, last_used)) = lru.entries.remove(&key) {
+            // Another thread cached it meanwhile.
+            lru.order.remove(&last_used);
+        }
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else { break };
+            lru.entries.remove(&oldest);
+            lru.stats.evictions += 1;
+            self.notify(CacheEvent::Evict);
+        }
+        lru.tick += 1;
+        let tick = lru.tick;
+        lru.order.insert(tick, key.clone());
+        lru.entries.insert(key, (exprs.clone(), tick));
+        Ok(exprs)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let lru = self.lock();
+        CacheStats { entries: lru.entries.len(), ..lru.stats }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every cached policy. Totals are kept.
+    pub fn clear(&self) {
+        let mut lru = self.lock();
+        lru.entries.clear();
+        lru.order.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn notify(&self, event: CacheEvent) {
+        if let Some(observer) = &self.observer {
+            observer(event);
+        }
+    }
+}
+
+impl PolicyParser for PolicyCache {
+    fn parse(&self, policy: &str, limits: &Limits, syntax: Syntax) -> Parsed {
+        self.parse_keyed(policy, limits, syntax)
+    }
+}
@@ -0,0 +1,457 @@
+//! Offline attenuation by appending caveats.
+//!
+//! An attenuable token carries a signed `delegation_key`; its holder keeps the
+//! matching private key as `caveat_proof`. [`Token::add_caveat`] signs the new
+//! caveat with the current proof key, names a fresh next key in it, and hands
+//! the new private key on as the proof. Verifiers walk the chain from
+//! `delegation_key` and require the proof to match the last key, so dropping
+//! a caveat leaves a chain whose proof nobody downstream holds.
+//!
+//! Each caveat also carries a signed [`AttenuationProof`]: the hash of the
+//! token it narrows, the appended constraint, and a transcript comparing the
+//! constraint's bounds with the parent's, so auditors can see how the child
+//! narrows its parent without analysing either policy.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::backend::{key_from_hex, public_key_hex, sign_hex};
+use crate::crypto::{ct_eq_hex, verify_ed25519};
+use crate::did::check_did_key;
+use crate::keys::key_id;
+use crate::parser::{parse_all_with_syntax, parse_with_syntax};
+use crate::summary::{conjuncts, summarize_clause, ClauseSummary, FieldBound, TokenSummary};
+use crate::time::parse_rfc3339;
+use crate::token::{envelope_payload, generate_keypair, named_clauses, Token};
+use crate::types::{Limits, Node, SplError};
+
+/// One attenuation step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Caveat {
+    /// SPL expression ANDed onto the token's policy.
+    pub policy: String,
+    /// Public key that must sign the following caveat.
+    pub next_key: String,
+    /// Signature by the previous link's key over [`caveat_payload`].
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<AttenuationProof>,
+}
+
+/// How a caveat narrows the token it was appended to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttenuationProof {
+    /// [`parent_hash`] of the token before this caveat.
+    pub parent_hash: String,
+    /// The appended constraint; equal to the caveat policy.
+    pub constraint: String,
+    /// Subsumption-check steps, starting with the conjunction rule.
+    pub transcript: Vec<ProofStep>,
+    /// Whether the constraint tightens or adds a requirement the parent
+    /// lacked, rather than only restating it.
+    pub strict: bool,
+}
+
+/// One line of an [`AttenuationProof`] transcript.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// `conjunction`, `tightened`, `added`, `unchanged`, `constraint`, or
+    /// `opaque-parent` when the parent policy is encrypted.
+    pub rule: String,
+    pub detail: String,
+}
+
+impl AttenuationProof {
+    fn digest(&self) -> String {
+        let encoded = serde_json::to_vec(self).expect("proof serializes");
+        hex::encode(Sha256::digest(encoded))
+    }
+}
+
+/// Bytes a caveat signature covers: the previous link's signature (the
+/// token signature for the first caveat), the caveat policy, the next key,
+/// and the digest of the caveat's proof when it has one.
+pub fn caveat_payload(previous_signature: &str, policy: &str, next_key: &str, proof_digest: Option<&str>) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"agent-safe-caveat-v1\0");
+    hasher.update(previous_signature.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(policy.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(next_key.as_bytes());
+    if let Some(digest) = proof_digest {
+        hasher.update(b"\0proof=");
+        hasher.update(digest.as_bytes());
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Hash identifying `token` as it stood with only its first `depth` caveats:
+/// the signed envelope, the token signature, and each caveat signature.
+pub fn parent_hash(token: &Token, depth: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"agent-safe-attenuation-v1\0");
+    hasher.update(envelope_payload(token));
+    hasher.update(b"\0");
+    hasher.update(token.signature.as_bytes());
+    for caveat in &token.caveats[..depth] {
+        hasher.update(b"\0");
+        hasher.update(caveat.signature.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+impl Token {
+    /// Return a copy of this token further restricted by `caveat`, which
+    /// must also hold for every request. Requires an attenuable token (see
+    /// `MintOptions::attenuable`) that still carries its `caveat_proof`.
+    pub fn add_caveat(&self, caveat: &str) -> Result<Token, SplError> {
+        let proof = self
+            .caveat_proof
+            .as_deref()
+            .ok_or_else(|| SplError("token is not attenuable: no caveat proof".into()))?;
+        let policy = caveat.trim();
+        parse_caveat(self, policy)?;
+
+        let attenuation = attenuation_proof(self, policy)?;
+        let previous_signature = self.caveats.last().map_or(&self.signature, |c| &c.signature);
+        let (next_key, next_proof) = generate_keypair();
+        let signature = sign_hex(
+            &key_from_hex(proof, "caveat proof")?,
+            &caveat_payload(previous_signature, policy, &next_key, Some(&attenuation.digest())),
+        )?;
+
+        let mut token = self.clone();
+        token.caveats.push(Caveat { policy: policy.to_string(), next_key, signature, proof: Some(attenuation) });
+        token.caveat_proof = Some(next_proof);
+        Ok(token)
+    }
+}
+
+/// Verifier-side bounds on a token's caveat chain. Caveats are appended
+/// outside the issuer's signature, so without them a holder could make
+/// verification arbitrarily expensive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainLimits {
+    /// Most delegation hops: caveats that hand the proof on to a new key.
+    pub max_chain_depth: usize,
+    /// Most caveats, including those added by one holder without handing on.
+    pub max_caveats: usize,
+    /// Largest combined size, in bytes, of the token policy and its caveat
+    /// policies.
+    pub max_total_policy_bytes: usize,
+}
+
+impl Default for ChainLimits {
+    fn default() -> Self {
+        Self { max_chain_depth: 8, max_caveats: 32, max_total_policy_bytes: 131_072 }
+    }
+}
+
+impl ChainLimits {
+    /// Check `token`'s chain against these limits, before any caveat
+    /// signature is verified.
+    pub fn check(&self, token: &Token) -> Result<(), SplError> {
+        let caveats = token.caveats.len();
+        if caveats > self.max_caveats {
+            return Err(SplError(format!("token has {caveats} caveats, maximum {}", self.max_caveats)));
+        }
+        let mut key = token.delegation_key.as_deref().unwrap_or_default();
+        let mut depth = 0;
+        for caveat in &token.caveats {
+            if caveat.next_key != key {
+                depth += 1;
+                key = &caveat.next_key;
+            }
+        }
+        if depth > self.max_chain_depth {
+            return Err(SplError(format!("delegation chain depth {depth} exceeds maximum {}", self.max_chain_depth)));
+        }
+        let bytes = token.policy.len() + token.caveats.iter().map(|c| c.policy.len()).sum::<usize>();
+        if bytes > self.max_total_policy_bytes {
+            return Err(SplError(format!(
+                "chain policies total {bytes} bytes, maximum {}",
+                self.max_total_policy_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a caveat on `token` with the token's [`Token::syntax`].
+fn parse_caveat(token: &Token, caveat: &str) -> Result<Node, SplError> {
+    parse_with_syntax(caveat, &Limits::default(), token.syntax())
+}
+
+/// Check the caveat chain and proof, returning the parsed caveat policies.
+pub fn verify_caveat_chain(token: &Token) -> Result<Vec<Node>, SplError> {
+    let Some(delegation_key) = &token.delegation_key else {
+        if token.caveats.is_empty() {
+            return Ok(Vec::new());
+        }
+        return Err(SplError("caveats on a token without a delegation key".into()));
+    };
+
+    let mut key = delegation_key;
+    let mut policies = Vec::with_capacity(token.caveats.len());
+    for (i, caveat) in token.caveats.iter().enumerate() {
+        check_caveat(token, i, key)?;
+        policies.push(parse_caveat(token, &caveat.policy)?);
+        key = &caveat.next_key;
+    }
+
+    let proof = token
+        .caveat_proof
+        .as_deref()
+        .ok_or_else(|| SplError("attenuable token presented without its caveat proof".into()))?;
+    if !ct_eq_hex(&public_key_hex(&key_from_hex(proof, "caveat proof")?)?, key) {
+        return Err(SplError("caveat proof does not match the last caveat key".into()));
+    }
+    Ok(policies)
+}
+
+/// Check caveat `i`'s signature by `key` and its attenuation proof.
+fn check_caveat(token: &Token, i: usize, key: &str) -> Result<(), SplError> {
+    let caveat = &token.caveats[i];
+    let previous_signature = match i {
+        0 => &token.signature,
+        _ => &token.caveats[i - 1].signature,
+    };
+    let digest = caveat.proof.as_ref().map(AttenuationProof::digest);
+    let payload = caveat_payload(previous_signature, &caveat.policy, &caveat.next_key, digest.as_deref());
+    if !verify_ed25519(&payload, &caveat.signature, key) {
+        return Err(SplError(format!("invalid signature on caveat {i}")));
+    }
+    if let Some(proof) = &caveat.proof {
+        if !ct_eq_hex(&proof.parent_hash, &parent_hash(token, i)) || proof.constraint != caveat.policy {
+            return Err(SplError(format!("attenuation proof on caveat {i} does not match its parent")));
+        }
+    }
+    Ok(())
+}
+
+/// One link of a token's delegation chain: the minted token, then each
+/// caveat appended to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainLink {
+    /// 0 for the minted token, then one per caveat.
+    pub depth: u32,
+    /// Who signed the link: the token's issuer DID or kid (else the key id
+    /// of its public key), or the key id of the delegation key that signed
+    /// the caveat.
+    pub issuer: String,
+    /// The caveat's constraint; `None` for the minted token.
+    pub caveat: Option<String>,
+    /// Summary of the link's policy; empty when the token policy is encrypted.
+    pub clauses: Vec<ClauseSummary>,
+    /// How the caveat narrows its parent, from its attenuation proof.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub narrowing: Vec<ProofStep>,
+    /// When requests under this link stop being allowed: the token expiry,
+    /// tightened by any `(before now "T")` the chain so far requires.
+    pub expires: Option<String>,
+    /// Whether the link's signature, and any attenuation proof, verify.
+    pub signature_valid: bool,
+    /// Why the link failed to verify.
+    pub error: Option<String>,
+}
+
+/// Walk `token`'s delegation chain for display, checking each link's
+/// signature independently so one bad link does not hide the rest. This
+/// is not verification: it consults no trust store, clock, or caveat
+/// proof, so verify the token before relying on any link.
+pub fn chain_links(token: &Token) -> Vec<ChainLink> {
+    let summary = TokenSummary::from(token);
+    let root_error = if !token.alg.verify(&envelope_payload(token), &token.signature, &token.public_key) {
+        Some("invalid signature".to_string())
+    } else {
+        check_did_key(token).err().map(|e| e.message)
+    };
+    let mut expires = token.expires.clone();
+    let mut links = vec![ChainLink {
+        depth: 0,
+        issuer: token.issuer.clone().or_else(|| token.kid.clone()).unwrap_or_else(|| key_id(&token.public_key)),
+        caveat: None,
+        clauses: summary.clauses,
+        narrowing: Vec::new(),
+        expires: expires.clone(),
+        signature_valid: root_error.is_none(),
+        error: root_error,
+    }];
+
+    let mut key = token.delegation_key.as_deref();
+    for (i, caveat) in token.caveats.iter().enumerate() {
+        let ast = parse_caveat(token, &caveat.policy);
+        if let Ok(ast) = &ast {
+            for deadline in conjuncts(ast).iter().filter_map(before_now) {
+                if expires.as_deref().is_none_or(|e| earlier(deadline, e)) {
+                    expires = Some(deadline.to_string());
+                }
+            }
+        }
+        let error = match (key, &ast) {
+            (None, _) => Some("caveat on a token without a delegation key".to_string()),
+            (_, Err(e)) => Some(e.to_string()),
+            (Some(key), Ok(_)) => check_caveat(token, i, key).err().map(|e| e.0),
+        };
+        links.push(ChainLink {
+            depth: i as u32 + 1,
+            issuer: key.map(key_id).unwrap_or_default(),
+            caveat: Some(caveat.policy.clone()),
+            clauses: ast.map(|ast| vec![summarize_clause(None, &ast)]).unwrap_or_default(),
+            narrowing: caveat.proof.as_ref().map(|p| p.transcript.clone()).unwrap_or_default(),
+            expires: expires.clone(),
+            signature_valid: error.is_none(),
+            error,
+        });
+        key = Some(&caveat.next_key);
+    }
+    links
+}
+
+/// The deadline in a `(before now "T")` requirement.
+pub(crate) fn before_now(node: &Node) -> Option<&str> {
+    match node {
+        Node::List(items) => match items.as_slice() {
+            [Node::Symbol(op), Node::Symbol(now), Node::Str(t)] if op == "before" && now == "now" => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether RFC 3339 time `a` is before `b`; `false` if either does not parse.
+pub(crate) fn earlier(a: &str, b: &str) -> bool {
+    match (parse_rfc3339(a), parse_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a < b,
+        _ => false,
+    }
+}
+
+/// Build the proof for appending `constraint` to `parent`.
+fn attenuation_proof(parent: &Token, constraint: &str) -> Result<AttenuationProof, SplError> {
+    let constraint_ast = parse_caveat(parent, constraint)?;
+    let mut transcript = vec![ProofStep {
+        rule: "conjunction".into(),
+        detail: "child allows only requests that satisfy parent and constraint".into(),
+    }];
+
+    // Bounds every parent clause enforces, tightened by earlier caveats.
+    let root = if parent.encrypted_policy.is_some() { None } else { parse_all_with_syntax(&parent.policy, &Limits::default(), parent.syntax()).ok() };
+    let (mut parent_bounds, mut parent_terms) = match &root {
+        Some(exprs) => match named_clauses(exprs)? {
+            Some(clauses) => {
+                let envelopes: Vec<_> = clauses.iter().map(|(_, body)| envelope(body)).collect();
+                (loosest(&envelopes), Vec::new())
+            }
+            None => {
+                let terms = exprs.iter().flat_map(|e| conjuncts(e).iter().map(Node::to_string)).collect();
+                (exprs.iter().flat_map(envelope).collect(), terms)
+            }
+        },
+        None => {
+            transcript.push(ProofStep {
+                rule: "opaque-parent".into(),
+                detail: "parent policy is encrypted; comparing against earlier caveats only".into(),
+            });
+            (Vec::new(), Vec::new())
+        }
+    };
+    for caveat in &parent.caveats {
+        let ast = parse_caveat(parent, &caveat.policy)?;
+        parent_bounds.extend(envelope(&ast));
+        parent_terms.extend(conjuncts(&ast).iter().map(Node::to_string));
+    }
+    let parent_bounds = tightest(&parent_bounds);
+
+    let mut strict = false;
+    let child_bounds = envelope(&constraint_ast);
+    for bound in &child_bounds {
+        let before = parent_bounds.iter().find(|b| b.field == bound.field);
+        let (rule, tighter) = match before {
+            None => ("added", true),
+            Some(p) => {
+                let tighter = narrower(bound.min, p.min, |a, b| a > b) || narrower(bound.max, p.max, |a, b| a < b);
+                (if tighter { "tightened" } else { "unchanged" }, tighter)
+            }
+        };
+        strict |= tighter;
+        let detail = match before {
+            Some(p) => format!("{} (parent {})", describe(bound), describe(p)),
+            None => describe(bound),
+        };
+        transcript.push(ProofStep { rule: rule.into(), detail });
+    }
+    let bounded: Vec<&str> = child_bounds.iter().map(|b| b.field.as_str()).collect();
+    for term in conjuncts(&constraint_ast) {
+        let text = term.to_string();
+        let is_bound = summarize_clause(None, term).envelope.iter().any(|b| bounded.contains(&b.field.as_str()));
+        if is_bound {
+            continue;
+        }
+        let new = !parent_terms.contains(&text);
+        strict |= new && root.is_some();
+        transcript.push(ProofStep { rule: if new { "constraint" } else { "unchanged" }.into(), detail: text });
+    }
+
+    Ok(AttenuationProof {
+        parent_hash: parent_hash(parent, parent.caveats.len()),
+        constraint: constraint.to_string(),
+        transcript,
+        strict: strict && root.is_some(),
+    })
+}
+
+fn envelope(ast: &Node) -> Vec<FieldBound> {
+    summarize_clause(None, ast).envelope
+}
+
+/// Combine bounds on the same field, keeping the tightest of each side.
+fn tightest(bounds: &[FieldBound]) -> Vec<FieldBound> {
+    let mut out: Vec<FieldBound> = Vec::new();
+    for b in bounds {
+        match out.iter_mut().find(|o| o.field == b.field) {
+            Some(o) => {
+                o.min = match (o.min, b.min) { (Some(x), Some(y)) => Some(x.max(y)), (x, y) => x.or(y) };
+                o.max = match (o.max, b.max) { (Some(x), Some(y)) => Some(x.min(y)), (x, y) => x.or(y) };
+            }
+            None => out.push(b.clone()),
+        }
+    }
+    out
+}
+
+/// Bounds that hold whichever clause applies: a side is bounded only if
+/// every clause bounds it, at the loosest clause's value.
+fn loosest(envelopes: &[Vec<FieldBound>]) -> Vec<FieldBound> {
+    let Some((first, rest)) = envelopes.split_first() else { return Vec::new() };
+    first
+        .iter()
+        .filter_map(|b| {
+            let mut out = b.clone();
+            for env in rest {
+                let other = env.iter().find(|o| o.field == b.field)?;
+                out.min = out.min.zip(other.min).map(|(x, y)| x.min(y));
+                out.max = out.max.zip(other.max).map(|(x, y)| x.max(y));
+            }
+            (out.min.is_some() || out.max.is_some()).then_some(out)
+        })
+        .collect()
+}
+
+fn narrower(child: Option<f64>, parent: Option<f64>, tighter: impl Fn(f64, f64) -> bool) -> bool {
+    match (child, parent) {
+        (Some(c), Some(p)) => tighter(c, p),
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+fn describe(bound: &FieldBound) -> String {
+    match (bound.min, bound.max) {
+        (Some(lo), Some(hi)) => format!("{lo} <= {} <= {hi}", bound.field),
+        (Some(lo), None) => format!("{} >= {lo}", bound.field),
+        (None, Some(hi)) => format!("{} <= {hi}", bound.field),
+        (None, None) => bound.field.clone(),
+    }
+}
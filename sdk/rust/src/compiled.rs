@@ -0,0 +1,209 @@
+//! Policies compiled once and evaluated many times.
+//!
+//! [`CompiledPolicy::compile`] resolves every operator to an [`Op`], checks
+//! its argument count against [`Op::arity`], interns symbols, parses
+//! `(limits ...)` blocks, and turns `(get req "field")` into a direct
+//! request lookup. Evaluation then skips the name matching an AST walk
+//! repeats on every request, while sharing the evaluator's operators, so a
+//! compiled policy decides, charges gas, and reports obligations exactly as
+//! [`crate::evaluator::eval_policy_detailed`] does on its source.
+//!
+//! Compilation surfaces structural errors eagerly: an unknown operator or a
+//! wrong argument count fails `compile` even on a branch the interpreter
+//! would never reach. Run [`crate::evaluator::fold_constants`] first to
+//! also drop constant subtrees.
+//!
+//! Host operators are only known to [`CompiledPolicy::compile_with_ops`],
+//! which checks their arity against the registry and looks up their
+//! implementation in the evaluation [`Env`].
+//!
+//! ```
+//! use agent_safe_spl::builder::{EnvBuilder, RequestBuilder};
+//! use agent_safe_spl::compiled::CompiledPolicy;
+//! use agent_safe_spl::parse;
+//!
+//! let policy = CompiledPolicy::compile(&parse(r#"(<= (get req "amount") 50)"#)?)?;
+//! for (amount, allowed) in [(20.0, true), (80.0, false)] {
+//!     let env = EnvBuilder::new().request(RequestBuilder::new().amount(amount).build()?).build()?;
+//!     assert_eq!(policy.eval(&env)?.is_truthy(), allowed);
+//! }
+//! # Ok::<(), agent_safe_spl::types::SplError>(())
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::evaluator::{charge, enter, eval_call, eval_op, lambda_parts, lookup, resolve_symbol, run, EvalOutcome, EvalResult, EvalState, Operand};
+use crate::limits::PolicyLimits;
+use crate::ops::{Op, OpImpl, OpRegistry};
+use crate::types::{Env, Limits, Node, SplError, SplResult};
+
+/// A policy with operators resolved and arity checked, ready to evaluate
+/// against many environments. Cheap to share: it is immutable, `Send`, and
+/// `Sync`.
+#[derive(Debug, Clone)]
+pub struct CompiledPolicy {
+    root: Expr,
+}
+
+/// Shorthand for [`CompiledPolicy`], read as `Policy::compile(&ast)`.
+pub type Policy = CompiledPolicy;
+
+#[derive(Debug, Clone)]
+enum Expr {
+    /// A self-evaluating literal.
+    Lit(Node),
+    /// An interned symbol, resolved against `env.vars` at evaluation time.
+    Sym(Arc<str>),
+    /// `(get req "field")`.
+    ReqField(String),
+    Call(Op, Box<[Expr]>),
+    /// A host operator, resolved by name in `env.ops` at evaluation time.
+    Host(Arc<str>, Box<[Expr]>),
+    /// The parsed entries of a `(limits ...)` form, its only argument.
+    Limits(PolicyLimits),
+}
+
+impl CompiledPolicy {
+    /// Compile a parsed policy, rejecting nesting past the default
+    /// [`Limits::max_depth`].
+    pub fn compile(ast: &Node) -> Result<Self, SplError> {
+        Self::compile_with_limits(ast, &Limits::default())
+    }
+
+    /// Compile a parsed policy, rejecting nesting past `limits.max_depth`.
+    /// Evaluation still enforces the limits of its [`Env`].
+    pub fn compile_with_limits(ast: &Node, limits: &Limits) -> Result<Self, SplError> {
+        Self::compile_with_ops(ast, limits, &OpRegistry::default())
+    }
+
+    /// Compile a parsed policy that may call the host operators in `ops`.
+    /// Evaluate it with an [`Env`] whose [`Env::ops`] registers the same names.
+    pub fn compile_with_ops(ast: &Node, limits: &Limits, ops: &OpRegistry) -> Result<Self, SplError> {
+        let mut compiler = Compiler { symbols: HashMap::new(), max_depth: limits.max_depth, ops };
+        Ok(Self { root: compiler.expr(ast, 1)? })
+    }
+
+    /// Evaluate within an environment. Returns the result Node.
+    pub fn eval(&self, env: &Env) -> SplResult {
+        self.eval_detailed(env).map(|outcome| outcome.value)
+    }
+
+    /// Evaluate, also reporting the external state consulted.
+    pub fn eval_detailed(&self, env: &Env) -> Result<EvalOutcome, SplError> {
+        run(&self.root, env)
+    }
+}
+
+struct Compiler<'r> {
+    symbols: HashMap<String, Arc<str>>,
+    max_depth: usize,
+    ops: &'r OpRegistry,
+}
+
+impl Compiler<'_> {
+    fn expr(&mut self, node: &Node, depth: usize) -> Result<Expr, SplError> {
+        if depth > self.max_depth {
+            return Err(SplError("max nesting depth exceeded".into()));
+        }
+        let items = match node {
+            Node::Symbol(s) => return Ok(Expr::Sym(self.intern(s))),
+            Node::List(items) if !items.is_empty() => items,
+            Node::List(_) => return Ok(Expr::Lit(Node::Nil)),
+            _ => return Ok(Expr::Lit(node.clone())),
+        };
+        let name = match &items[0] {
+            Node::Symbol(s) => s.as_str(),
+            _ => return Err(SplError("operator must be a symbol".into())),
+        };
+        let handler = self.ops.get(name).ok_or_else(|| SplError(format!("Unknown op: {name}")))?;
+        let args = &items[1..];
+        handler.check_arity(name, args.len())?;
+        let op = match handler.handler {
+            OpImpl::Builtin(op) => op,
+            OpImpl::Host(_) => {
+                let args = args.iter().map(|a| self.expr(a, depth + 1)).collect::<Result<_, _>>()?;
+                return Ok(Expr::Host(self.intern(name), args));
+            }
+        };
+        match (op, args) {
+            (Op::Limits, entries) => {
+                Ok(Expr::Call(op, Box::new([Expr::Limits(PolicyLimits::from_entries(entries)?)])))
+            }
+            (Op::Lambda, _) => {
+                let (param, body) = lambda_parts(node)
+                    .ok_or_else(|| SplError("lambda expects one parameter: (lambda (x) body)".into()))?;
+                Ok(Expr::Call(op, Box::new([Expr::Sym(self.intern(param)), self.expr(body, depth + 1)?])))
+            }
+            (Op::Get, [Node::Symbol(obj), Node::Str(field)]) if obj == "req" => {
+                if depth == self.max_depth {
+                    return Err(SplError("max nesting depth exceeded".into()));
+                }
+                Ok(Expr::ReqField(field.clone()))
+            }
+            _ => {
+                let args = args.iter().map(|a| self.expr(a, depth + 1)).collect::<Result<_, _>>()?;
+                Ok(Expr::Call(op, args))
+            }
+        }
+    }
+
+    fn intern(&mut self, name: &str) -> Arc<str> {
+        self.symbols.entry(name.to_string()).or_insert_with(|| name.into()).clone()
+    }
+}
+
+impl Operand for Expr {
+    fn eval_inner<'a>(&'a self, env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+        match self {
+            Expr::Lit(node) => Ok(Cow::Borrowed(node)),
+            Expr::Sym(name) => resolve_symbol(name, env, st),
+            Expr::ReqField(field) => {
+                // Charged as the interpreter charges the field literal.
+                charge(st, env.gas.node)?;
+                enter(st)?;
+                st.depth -= 1;
+                lookup(env.req.get(field.as_str()))
+            }
+            Expr::Call(op, args) => eval_op(*op, args, env, st),
+            Expr::Host(name, args) => {
+                let handler = env.ops.get(name).ok_or_else(|| SplError(format!("Unknown op: {name}")))?;
+                eval_call(name, &handler, args, env, st)
+            }
+            Expr::Limits(_) => Err(SplError("limits entries are not an expression".into())),
+        }
+    }
+
+    fn lambda(&self) -> Option<(&str, &Expr)> {
+        match self {
+            Expr::Call(Op::Lambda, args) => match &args[..] {
+                [Expr::Sym(param), body] => Some((param, body)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn as_symbol(&self) -> Option<&str> {
+        match self {
+            Expr::Sym(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Expr::Sym(s) => Some(s),
+            Expr::Lit(Node::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn limits(args: &[Expr]) -> Result<Cow<'_, PolicyLimits>, SplError> {
+        match args {
+            [Expr::Limits(limits)] => Ok(Cow::Borrowed(limits)),
+            _ => Err(SplError("limits entries are not an expression".into())),
+        }
+    }
+}
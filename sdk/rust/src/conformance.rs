@@ -0,0 +1,191 @@
+//! Cross-SDK conformance runner.
+//!
+//! A conformance directory holds JSON files of policy cases, each with the
+//! request, vars, and the outcome every implementation must produce. Errors
+//! are compared by [`ErrorClass`] rather than message text, so ports can
+//! word their errors freely. `examples/conformance/` is the shared suite;
+//! `agent-safe conformance` runs it and prints a JSON [`ConformanceReport`].
+//!
+//! ```text
+//! { "cases": [
+//!     { "name": "and_short_circuits", "policy": "(and #f (get req \"missing\"))",
+//!       "expect": { "allow": false } },
+//!     { "name": "unbalanced", "policy": "(and #t", "expect": { "error": "parse" } } ] }
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::parse;
+use crate::types::{Env, Node, SplError};
+use crate::verifier::verify;
+
+/// Implementation-independent category of a policy failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorClass {
+    /// The policy text is not a well-formed expression.
+    Parse,
+    /// A size or nesting limit was exceeded.
+    Limit,
+    /// Evaluation ran out of gas.
+    Gas,
+    /// Any other evaluation error: unknown operator, bad arity, type error.
+    Eval,
+}
+
+impl ErrorClass {
+    fn classify(error: &SplError, parsing: bool) -> Self {
+        let message = error.0.as_str();
+        if message.contains("exceeds maximum") || message.contains("nesting depth") || message.contains("nests deeper") {
+            ErrorClass::Limit
+        } else if message.contains("gas budget") {
+            ErrorClass::Gas
+        } else if parsing {
+            ErrorClass::Parse
+        } else {
+            ErrorClass::Eval
+        }
+    }
+}
+
+/// Result of running a policy. In an expectation, unset fields are not
+/// checked; `error` is always compared, so a case that omits it must not
+/// fail.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcome {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+}
+
+impl Outcome {
+    fn satisfies(&self, expected: &Outcome) -> bool {
+        self.error == expected.error
+            && expected.allow.is_none_or(|allow| self.allow == Some(allow))
+            && expected.reason_code.as_ref().is_none_or(|code| self.reason_code.as_ref() == Some(code))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceCase {
+    pub name: String,
+    pub policy: String,
+    #[serde(default)]
+    pub req: HashMap<String, Node>,
+    #[serde(default)]
+    pub vars: HashMap<String, Node>,
+    /// Gas budget; the [`Env`] default when omitted.
+    #[serde(default)]
+    pub max_gas: Option<i64>,
+    pub expect: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceSuite {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub cases: Vec<ConformanceCase>,
+}
+
+/// One case's result, with enough detail to debug a mismatch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseResult {
+    /// File the case came from, relative to the suite directory.
+    pub file: String,
+    pub name: String,
+    pub passed: bool,
+    pub expected: Outcome,
+    pub actual: Outcome,
+    /// This implementation's error message, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub cases: Vec<CaseResult>,
+}
+
+impl ConformanceReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+
+    fn push(&mut self, result: CaseResult) {
+        if result.passed {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+        }
+        self.cases.push(result);
+    }
+}
+
+/// Run one case, returning its outcome and any error message.
+pub fn run_case(case: &ConformanceCase) -> (Outcome, Option<String>) {
+    let failed = |e: SplError, parsing| {
+        let outcome = Outcome { error: Some(ErrorClass::classify(&e, parsing)), ..Outcome::default() };
+        (outcome, Some(e.0))
+    };
+    let ast = match parse(&case.policy) {
+        Ok(ast) => ast,
+        Err(e) => return failed(e, true),
+    };
+    let mut env = Env { req: case.req.clone(), vars: case.vars.clone(), ..Env::default() };
+    if let Some(max_gas) = case.max_gas {
+        env.max_gas = max_gas;
+    }
+    match verify(&ast, &env) {
+        Ok(result) => (Outcome { allow: Some(result.allow), error: None, reason_code: result.reason_code }, None),
+        Err(e) => failed(e, false),
+    }
+}
+
+/// Run every case of `suite`, labelling results with `file`.
+pub fn run_suite(file: &str, suite: &ConformanceSuite) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+    for case in &suite.cases {
+        let (actual, message) = run_case(case);
+        report.push(CaseResult {
+            file: file.to_string(),
+            name: case.name.clone(),
+            passed: actual.satisfies(&case.expect),
+            expected: case.expect.clone(),
+            actual,
+            message,
+        });
+    }
+    report
+}
+
+/// Run every `*.json` suite in `dir`, in file name order.
+pub fn run_dir(dir: &Path) -> Result<ConformanceReport, SplError> {
+    let io = |e: std::io::Error| SplError(format!("{}: {e}", dir.display()));
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .map_err(io)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()
+        .map_err(io)?;
+    files.retain(|p| p.extension().is_some_and(|ext| ext == "json"));
+    files.sort();
+
+    let mut report = ConformanceReport::default();
+    for path in files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let text = fs::read_to_string(&path).map_err(|e| SplError(format!("{name}: {e}")))?;
+        let suite: ConformanceSuite = serde_json::from_str(&text).map_err(|e| SplError(format!("{name}: {e}")))?;
+        for result in run_suite(&name, &suite).cases {
+            report.push(result);
+        }
+    }
+    Ok(report)
+}
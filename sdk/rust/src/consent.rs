@@ -0,0 +1,64 @@
+//! Consent bundles: a token together with the description its owner saw.
+//!
+//! A consent screen shows [`describe`](crate::analysis::describe)'s bullet
+//! points before the owner approves a token. [`mint_with_consent`] mints the
+//! token and records that description with the hash of the signed policy and
+//! the signed `issued_at`. Anyone holding the bundle can later run
+//! [`verify_consent_bundle`] to show that what the owner saw is what was
+//! signed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::PolicyDescription;
+use crate::crypto::ct_eq_hex;
+use crate::parser::parse_all;
+use crate::policy_store::policy_hash;
+use crate::time::{format_rfc3339, Clock, SystemClock};
+use crate::token::{envelope_payload, mint, policy_source, MintOptions, Token};
+use crate::types::SplError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsentBundle {
+    pub token: Token,
+    /// The policy as shown to the owner, rendered by [`PolicyDescription`].
+    pub policy_description: String,
+    /// [`policy_hash`] of the token's policy source.
+    pub policy_hash: String,
+    /// RFC 3339; equal to the token's signed `issued_at`.
+    pub minted_at: String,
+}
+
+/// Mint a token with the description to show its owner. `opts.issued_at`
+/// defaults to now, so the mint time is covered by the signature.
+pub fn mint_with_consent(policy: &str, private_key_hex: &str, mut opts: MintOptions) -> Result<ConsentBundle, SplError> {
+    let policy_description = description(policy)?;
+    let minted_at = opts.issued_at.get_or_insert_with(|| format_rfc3339(SystemClock.now_unix())).clone();
+    let token = mint(policy, private_key_hex, opts)?;
+    Ok(ConsentBundle { token, policy_description, policy_hash: policy_hash(policy), minted_at })
+}
+
+/// Check that the bundle's token is validly signed, that its policy hashes
+/// to `policy_hash`, and that `policy_description` and `minted_at` are what
+/// that policy and the token say. Tokens with an encrypted policy need the
+/// recipient's `decryption_key`.
+pub fn verify_consent_bundle(bundle: &ConsentBundle, decryption_key: Option<&str>) -> Result<(), SplError> {
+    let token = &bundle.token;
+    if !token.alg.verify(&envelope_payload(token), &token.signature, &token.public_key) {
+        return Err(SplError("invalid signature".into()));
+    }
+    let policy = policy_source(token, decryption_key)?;
+    if !ct_eq_hex(&policy_hash(&policy), &bundle.policy_hash) {
+        return Err(SplError("policy hash does not match the signed policy".into()));
+    }
+    if description(&policy)? != bundle.policy_description {
+        return Err(SplError("policy description does not match the signed policy".into()));
+    }
+    if token.issued_at.as_deref() != Some(bundle.minted_at.as_str()) {
+        return Err(SplError("minted_at does not match the token's issued_at".into()));
+    }
+    Ok(())
+}
+
+fn description(policy: &str) -> Result<String, SplError> {
+    Ok(PolicyDescription::of(&parse_all(policy)?)?.to_string())
+}
@@ -0,0 +1,133 @@
+//! COSE_Sign1 (RFC 9052) export and import of tokens, as a CWT (RFC 8392)
+//! for enforcement points that speak CBOR.
+//!
+//! Registered claims mirror the token envelope as in [`crate::jws`]: `iss`
+//! is the issuer public key (hex), `exp`/`iat`/`cti` come from
+//! `expires`/`issued_at`/`token_id`, `aud` is optional, and PoP-bound
+//! tokens carry `cnf` with an OKP `COSE_Key`. The protected header names
+//! `EdDSA` and the issuer key's `kid`. The complete native token rides in
+//! the private claim [`SPL_CLAIM`], so import is lossless and the native
+//! signature still governs verification. Carrying the whole token alongside
+//! the registered claims makes a CWT larger than the same token's JWS, so
+//! CBOR saves nothing on the wire here. Only Ed25519 tokens are exported.
+
+use coset::cbor::Value;
+use coset::cwt::{ClaimsSet, ClaimsSetBuilder, Timestamp};
+use coset::{iana, Algorithm, CborSerializable, CoseSign1, CoseSign1Builder, HeaderBuilder, TaggedCborSerializable};
+
+use crate::signature::SignatureScheme;
+use crate::time::parse_rfc3339;
+use crate::token::Token;
+use crate::types::SplError;
+
+/// Private-use CWT claim key holding the native token.
+pub const SPL_CLAIM: i64 = -65_537;
+
+fn cose_err(e: coset::CoseError) -> SplError {
+    SplError(format!("invalid COSE: {e}"))
+}
+
+fn timestamp(field: &str, value: &Option<String>) -> Result<Option<Timestamp>, SplError> {
+    value
+        .as_deref()
+        .map(|v| parse_rfc3339(v).map(Timestamp::WholeSeconds).map_err(|e| SplError(format!("{field}: {e}"))))
+        .transpose()
+}
+
+fn registered_claims(token: &Token) -> Result<ClaimsSet, SplError> {
+    let mut claims = ClaimsSetBuilder::new().issuer(token.public_key.clone());
+    if let Some(exp) = timestamp("expires", &token.expires)? {
+        claims = claims.expiration_time(exp);
+    }
+    if let Some(iat) = timestamp("issued_at", &token.issued_at)? {
+        claims = claims.issued_at(iat);
+    }
+    if let Some(cti) = &token.token_id {
+        claims = claims.cwt_id(cti.clone().into_bytes());
+    }
+    if let Some(pop_key) = &token.pop_key {
+        let x = hex::decode(pop_key).map_err(|e| SplError(format!("invalid pop_key hex: {e}")))?;
+        let key = Value::Map(vec![
+            (Value::from(iana::KeyParameter::Kty as i64), Value::from(iana::KeyType::OKP as i64)),
+            (Value::from(iana::OkpKeyParameter::Crv as i64), Value::from(iana::EllipticCurve::Ed25519 as i64)),
+            (Value::from(iana::OkpKeyParameter::X as i64), Value::Bytes(x)),
+        ]);
+        // cnf: { COSE_Key: key } (RFC 8747)
+        claims = claims.claim(iana::CwtClaimName::Cnf, Value::Map(vec![(Value::from(1), key)]));
+    }
+    Ok(claims.build())
+}
+
+impl Token {
+    /// Export as a tagged COSE_Sign1 signed by the issuer key.
+    /// `private_key_hex` must belong to `self.public_key`.
+    pub fn to_cose(&self, private_key_hex: &str, audience: Option<&str>) -> Result<Vec<u8>, SplError> {
+        if self.alg != SignatureScheme::Ed25519 {
+            return Err(SplError(format!("COSE export supports Ed25519 tokens only, not {}", self.alg.name())));
+        }
+        if self.alg.public_key(private_key_hex)? != self.public_key {
+            return Err(SplError("private key does not match token public_key".into()));
+        }
+        let mut claims = registered_claims(self)?;
+        claims.audience = audience.map(str::to_string);
+        let spl = Value::serialized(self).map_err(|e| SplError(format!("cannot encode token: {e}")))?;
+        claims.rest.push((coset::cwt::ClaimName::PrivateUse(SPL_CLAIM), spl));
+
+        let mut protected = HeaderBuilder::new().algorithm(iana::Algorithm::EdDSA);
+        if let Some(kid) = &self.kid {
+            protected = protected.key_id(kid.clone().into_bytes());
+        }
+        let sign1 = CoseSign1Builder::new()
+            .protected(protected.build())
+            .payload(claims.to_vec().map_err(cose_err)?)
+            .try_create_signature(&[], |data| {
+                hex::decode(self.alg.sign(private_key_hex, data)?).map_err(|e| SplError(e.to_string()))
+            })?
+            .build();
+        sign1.to_tagged_vec().map_err(cose_err)
+    }
+
+    /// Import a COSE_Sign1 produced by [`Token::to_cose`]. Checks the COSE
+    /// signature, that its `aud` is exactly `audience` (`None` accepts only
+    /// CWTs without one), and that the registered claims agree with the
+    /// embedded token; the token itself still needs
+    /// [`crate::token::verify_token`].
+    pub fn from_cose(bytes: &[u8], audience: Option<&str>) -> Result<Token, SplError> {
+        let sign1 = CoseSign1::from_tagged_slice(bytes).map_err(cose_err)?;
+        let payload = sign1.payload.as_deref().ok_or_else(|| SplError("COSE_Sign1 has no payload".into()))?;
+        let mut claims = ClaimsSet::from_slice(payload).map_err(cose_err)?;
+
+        let position = claims
+            .rest
+            .iter()
+            .position(|(name, _)| *name == coset::cwt::ClaimName::PrivateUse(SPL_CLAIM))
+            .ok_or_else(|| SplError("CWT has no spl claim".into()))?;
+        let (_, spl) = claims.rest.remove(position);
+        let token: Token = spl.deserialized().map_err(|e| SplError(format!("invalid spl claim: {e}")))?;
+
+        if token.alg != SignatureScheme::Ed25519
+            || sign1.protected.header.alg != Some(Algorithm::Assigned(iana::Algorithm::EdDSA))
+        {
+            return Err(SplError("COSE alg must be EdDSA for an Ed25519 token".into()));
+        }
+        let kid = token.kid.as_ref().map(|k| k.as_bytes()).unwrap_or_default();
+        if sign1.protected.header.key_id != kid {
+            return Err(SplError("COSE kid does not match token kid".into()));
+        }
+        sign1.verify_signature(&[], |signature, data| {
+            if token.alg.verify(data, &hex::encode(signature), &token.public_key) {
+                Ok(())
+            } else {
+                Err(SplError("invalid COSE signature".into()))
+            }
+        })?;
+
+        if claims.audience.take().as_deref() != audience {
+            return Err(SplError("CWT audience does not match".into()));
+        }
+        if claims != registered_claims(&token)? {
+            return Err(SplError("CWT registered claims do not match the spl claim".into()));
+        }
+        Ok(token)
+    }
+}
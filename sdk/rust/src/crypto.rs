@@ -1,4 +1,4 @@
-use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 /// Verify an Ed25519 signature over a message.
@@ -6,11 +6,10 @@ pub fn verify_ed25519(message: &[u8], signature_hex: &str, public_key_hex: &str)
     let Ok(sig_bytes) = hex::decode(signature_hex) else { return false };
     let Ok(pub_bytes) = hex::decode(public_key_hex) else { return false };
 
-    let Ok(sig) = Signature::from_slice(&sig_bytes) else { return false };
+    let Ok(sig): Result<[u8; 64], _> = sig_bytes.try_into() else { return false };
     let Ok(pub_arr): Result<[u8; 32], _> = pub_bytes.try_into() else { return false };
-    let Ok(key) = VerifyingKey::from_bytes(&pub_arr) else { return false };
 
-    key.verify_strict(message, &sig).is_ok()
+    crate::backend::backend().ed25519_verify(&pub_arr, message, &sig)
 }
 
 /// SHA-256 hash of data.
@@ -25,7 +24,33 @@ pub fn sha256_hex(data: &[u8]) -> String {
     hex::encode(sha256(data))
 }
 
+/// Constant-time equality: the running time depends only on the lengths,
+/// not on where `a` and `b` first differ.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// Constant-time equality of two hex strings, ignoring case. Strings that
+/// are not hex never match.
+pub fn ct_eq_hex(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let (mut diff, mut valid) = (0u8, true);
+    for (x, y) in a.bytes().zip(b.bytes()) {
+        // Setting 0x20 lowercases A-F and leaves digits alone.
+        diff |= (x | 0x20) ^ (y | 0x20);
+        valid &= x.is_ascii_hexdigit() & y.is_ascii_hexdigit();
+    }
+    std::hint::black_box(diff) == 0 && valid
+}
+
 /// A step in a Merkle proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MerkleProofStep {
     pub hash: String,
     pub position: String, // "left" or "right"
@@ -48,7 +73,87 @@ pub fn verify_merkle_proof(leaf_data: &str, proof: &[MerkleProofStep], root_hex:
         current = hasher.finalize().to_vec();
     }
 
-    hex::encode(&current) == root_hex
+    ct_eq_hex(&hex::encode(&current), root_hex)
+}
+
+fn hash_pair(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+/// SHA-256 binary Merkle tree over UTF-8 leaves, compatible with
+/// [`verify_merkle_proof`].
+///
+/// Leaves are hashed as `SHA-256(leaf)` and paired left to right. A node
+/// without a sibling is promoted to the next level unchanged rather than
+/// duplicated. Leaf and interior hashes are not domain-separated, so a
+/// leaf whose bytes are two concatenated child hashes proves membership
+/// like that subtree; callers should not treat the root as unique to one
+/// leaf set.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    leaves: Vec<String>,
+    /// `levels[0]` holds leaf hashes; the last level holds only the root.
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleTree {
+    /// Build a tree over `leaves` in the given order.
+    pub fn build<S: AsRef<str>>(leaves: &[S]) -> Result<Self, crate::types::SplError> {
+        if leaves.is_empty() {
+            return Err(crate::types::SplError("Merkle tree requires at least one leaf".into()));
+        }
+        let leaves: Vec<String> = leaves.iter().map(|l| l.as_ref().to_string()).collect();
+        let mut levels = vec![leaves.iter().map(|l| sha256(l.as_bytes())).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!("chunks(2) yields one or two nodes"),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Ok(Self { leaves, levels })
+    }
+
+    /// Root hash as hex, for `Token.merkle_root`.
+    pub fn root_hex(&self) -> String {
+        hex::encode(&self.levels[self.levels.len() - 1][0])
+    }
+
+    /// Proof for the first occurrence of `leaf`, or `None` if absent.
+    pub fn proof_for(&self, leaf: &str) -> Option<Vec<MerkleProofStep>> {
+        let index = self.leaves.iter().position(|l| l == leaf)?;
+        self.proof_for_index(index)
+    }
+
+    /// Proof for the leaf at `index`, or `None` if out of range.
+    pub fn proof_for_index(&self, mut index: usize) -> Option<Vec<MerkleProofStep>> {
+        if index >= self.leaves.len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                proof.push(MerkleProofStep {
+                    hash: hex::encode(hash),
+                    position: if sibling > index { "right" } else { "left" }.to_string(),
+                });
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    pub fn leaves(&self) -> &[String] {
+        &self.leaves
+    }
 }
 
 /// HMAC-SHA256 (used internally for HKDF).
@@ -104,12 +209,7 @@ pub fn derive_service_key(master_key_hex: &str, service_domain: &str) -> Result<
     let seed: [u8; 32] = seed_bytes.try_into()
         .map_err(|_| crate::types::SplError("HKDF output size mismatch".into()))?;
 
-    let signing_key = SigningKey::from_bytes(&seed);
-    let verifying_key = signing_key.verifying_key();
-    Ok((
-        hex::encode(verifying_key.as_bytes()),
-        hex::encode(signing_key.as_bytes()),
-    ))
+    Ok((crate::backend::public_key_hex(&seed)?, hex::encode(seed)))
 }
 
 /// Verify a hash chain receipt.
@@ -120,11 +220,163 @@ pub fn verify_hash_chain(
     chain_length: usize,
 ) -> bool {
     let Ok(mut current) = hex::decode(preimage_hex) else { return false };
-    let steps = chain_length - index;
+    let Some(steps) = chain_length.checked_sub(index) else { return false };
 
     for _ in 0..steps {
         current = sha256(&current);
     }
 
-    hex::encode(&current) == commitment
+    ct_eq_hex(&hex::encode(&current), commitment)
+}
+
+/// A per-use hash-chain receipt: the preimage revealed at `index`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashChainReceipt {
+    pub index: usize,
+    pub preimage: String,
+}
+
+/// Issuer-side hash chain for offline budgets.
+///
+/// `chain[0] = seed` and `chain[i] = SHA-256(chain[i-1])`; the commitment is
+/// `chain[length]`. Uses are spent from the top down: the first receipt
+/// reveals `chain[length - 1]`, the last reveals the seed.
+#[derive(Debug, Clone)]
+pub struct HashChain {
+    chain: Vec<Vec<u8>>,
+}
+
+impl HashChain {
+    /// Build a chain of `length` uses from a hex seed.
+    pub fn generate(seed_hex: &str, length: usize) -> Result<Self, crate::types::SplError> {
+        let seed = hex::decode(seed_hex)
+            .map_err(|e| crate::types::SplError(format!("invalid seed hex: {e}")))?;
+        if length == 0 {
+            return Err(crate::types::SplError("hash chain length must be at least 1".into()));
+        }
+        let mut chain = Vec::with_capacity(length + 1);
+        chain.push(seed);
+        for i in 0..length {
+            let next = sha256(&chain[i]);
+            chain.push(next);
+        }
+        Ok(Self { chain })
+    }
+
+    /// Build a chain from a fresh 32-byte random seed.
+    pub fn generate_random(length: usize) -> Result<Self, crate::types::SplError> {
+        let mut seed = [0u8; 32];
+        getrandom::fill(&mut seed)
+            .map_err(|e| crate::types::SplError(format!("OS RNG failed: {e}")))?;
+        Self::generate(&hex::encode(seed), length)
+    }
+
+    /// Commitment hex for `Token.hash_chain_commitment`.
+    pub fn commitment(&self) -> String {
+        hex::encode(&self.chain[self.chain.len() - 1])
+    }
+
+    /// Number of uses the chain authorizes.
+    pub fn len(&self) -> usize {
+        self.chain.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Receipt revealing `chain[index]`, verifiable with [`verify_hash_chain`].
+    pub fn receipt(&self, index: usize) -> Option<HashChainReceipt> {
+        self.chain.get(index).map(|h| HashChainReceipt {
+            index,
+            preimage: hex::encode(h),
+        })
+    }
+
+    /// Preimage the agent presents for use `k` of a use-limited token (see
+    /// [`crate::uses`]): `chain[len - 1 - k]`, or `None` past the last use.
+    pub fn for_use(&self, k: usize) -> Option<String> {
+        let index = self.len().checked_sub(k + 1)?;
+        self.receipt(index).map(|r| r.preimage)
+    }
+
+    /// Receipts in spending order: index `len - 1` down to 0.
+    pub fn preimages(&self) -> impl Iterator<Item = HashChainReceipt> + '_ {
+        (0..self.len()).rev().filter_map(|i| self.receipt(i))
+    }
+}
+
+#[cfg(feature = "encryption")]
+pub use sealed::{generate_x25519_keypair, open_sealed, seal};
+
+/// Sealed-box encryption to an X25519 public key (`encryption` feature).
+///
+/// Each message uses a fresh ephemeral X25519 key. The shared secret is
+/// expanded with HKDF-SHA256 into an XChaCha20-Poly1305 key and nonce, so
+/// only the recipient can open it and the sender stays anonymous. Output is
+/// hex of `ephemeral_public (32) || ciphertext`.
+#[cfg(feature = "encryption")]
+mod sealed {
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    use super::hkdf_sha256;
+    use crate::types::SplError;
+
+    const INFO: &[u8] = b"agent-safe-sealed-box-v1";
+
+    fn key32(hex_str: &str, what: &str) -> Result<[u8; 32], SplError> {
+        hex::decode(hex_str)
+            .map_err(|e| SplError(format!("invalid {what} hex: {e}")))?
+            .try_into()
+            .map_err(|_| SplError(format!("{what} must be 32 bytes")))
+    }
+
+    fn cipher(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> (XChaCha20Poly1305, [u8; 24]) {
+        let salt = [ephemeral.as_bytes().as_slice(), recipient.as_bytes()].concat();
+        let okm = hkdf_sha256(shared, &salt, INFO, 56);
+        let nonce: [u8; 24] = okm[32..].try_into().expect("HKDF output is 56 bytes");
+        (XChaCha20Poly1305::new(okm[..32].into()), nonce)
+    }
+
+    /// Generate an X25519 keypair. Returns (public_key_hex, private_key_hex).
+    pub fn generate_x25519_keypair() -> (String, String) {
+        let mut seed = [0u8; 32];
+        getrandom::fill(&mut seed).expect("OS RNG failed");
+        let secret = StaticSecret::from(seed);
+        (hex::encode(PublicKey::from(&secret).as_bytes()), hex::encode(secret.to_bytes()))
+    }
+
+    /// Encrypt `plaintext` so only the holder of `recipient_public_hex` can read it.
+    pub fn seal(recipient_public_hex: &str, plaintext: &[u8]) -> Result<String, SplError> {
+        let recipient = PublicKey::from(key32(recipient_public_hex, "recipient public key")?);
+        let mut seed = [0u8; 32];
+        getrandom::fill(&mut seed).expect("OS RNG failed");
+        let ephemeral_secret = StaticSecret::from(seed);
+        let ephemeral = PublicKey::from(&ephemeral_secret);
+        let shared = ephemeral_secret.diffie_hellman(&recipient);
+        let (cipher, nonce) = cipher(shared.as_bytes(), &ephemeral, &recipient);
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad: INFO })
+            .map_err(|_| SplError("sealed-box encryption failed".into()))?;
+        Ok(hex::encode([ephemeral.as_bytes().as_slice(), &ciphertext].concat()))
+    }
+
+    /// Decrypt a [`seal`] output with the recipient's X25519 private key.
+    pub fn open_sealed(recipient_private_hex: &str, sealed_hex: &str) -> Result<Vec<u8>, SplError> {
+        let secret = StaticSecret::from(key32(recipient_private_hex, "recipient private key")?);
+        let recipient = PublicKey::from(&secret);
+        let sealed = hex::decode(sealed_hex).map_err(|e| SplError(format!("invalid sealed box hex: {e}")))?;
+        if sealed.len() < 32 {
+            return Err(SplError("sealed box too short".into()));
+        }
+        let (ephemeral, ciphertext) = sealed.split_at(32);
+        let ephemeral = PublicKey::from(<[u8; 32]>::try_from(ephemeral).expect("split at 32"));
+        let shared = secret.diffie_hellman(&ephemeral);
+        let (cipher, nonce) = cipher(shared.as_bytes(), &ephemeral, &recipient);
+        cipher
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: ciphertext, aad: INFO })
+            .map_err(|_| SplError("sealed box does not open with this key".into()))
+    }
 }
@@ -0,0 +1,83 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// Default oldest deny-list snapshot, in seconds, a decision may rely on.
+pub const DEFAULT_MAX_STALENESS_SECS: u64 = 3600;
+
+/// Version and age of a deny list as currently held by the verifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenyListStatus {
+    pub version: String,
+    /// Seconds since the list was last refreshed from its source.
+    pub age_secs: u64,
+}
+
+/// The deny-list version a decision consulted, reported for audit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DenyListVersion {
+    pub list: String,
+    pub version: String,
+}
+
+/// Verifier-side source of deny lists (blocked merchants, recipients, ...)
+/// backing the `denylist-absent?` operator.
+///
+/// Deny lists change faster than tokens, so the evaluator refuses to decide
+/// against a list older than `Env::denylist_max_staleness_secs`.
+pub trait DenyListProvider: Send + Sync {
+    /// Version and age of the named list, or `None` if it is unknown.
+    fn status(&self, list: &str) -> Option<DenyListStatus>;
+    /// Whether `value` appears on the named list. Under a normalizing
+    /// [`crate::types::StringMatch`] mode `value` arrives normalized, so
+    /// store entries in the same form.
+    fn contains(&self, list: &str, value: &str) -> bool;
+}
+
+struct LoadedList {
+    version: String,
+    loaded_at: Instant,
+    entries: HashSet<String>,
+}
+
+/// In-memory deny lists, aged from the moment each list was loaded.
+#[derive(Default)]
+pub struct InMemoryDenyLists {
+    lists: HashMap<String, LoadedList>,
+}
+
+impl InMemoryDenyLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load or replace a list, resetting its age.
+    pub fn load<I, S>(&mut self, list: &str, version: &str, entries: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.lists.insert(
+            list.to_string(),
+            LoadedList {
+                version: version.to_string(),
+                loaded_at: Instant::now(),
+                entries: entries.into_iter().map(Into::into).collect(),
+            },
+        );
+    }
+}
+
+impl DenyListProvider for InMemoryDenyLists {
+    fn status(&self, list: &str) -> Option<DenyListStatus> {
+        self.lists.get(list).map(|l| DenyListStatus {
+            version: l.version.clone(),
+            age_secs: l.loaded_at.elapsed().as_secs(),
+        })
+    }
+
+    fn contains(&self, list: &str, value: &str) -> bool {
+        self.lists.get(list).is_some_and(|l| l.entries.contains(value))
+    }
+}
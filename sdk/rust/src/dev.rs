@@ -0,0 +1,231 @@
+//! In-memory providers for local development and tests (`dev` feature).
+//!
+//! Every mock is a cheap handle over shared state: clone one into a
+//! [`Verifier`] or [`crate::types::Env`] and keep the other to script it — move the clock,
+//! preload counters and spend, revoke a value, or mark a nonce as used.
+//! [`Verifier::for_development`] wires the clock and replay cache in one call.
+//!
+//! Key and policy custody already have in-memory implementations in
+//! [`crate::keys::InMemoryKeyStore`] and [`crate::policy_store::InMemoryPolicyStore`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::builder::EnvBuilder;
+use crate::denylist::{DenyListProvider, DenyListStatus, DEFAULT_MAX_STALENESS_SECS};
+use crate::profile::{Verifier, VerifierProfile};
+use crate::replay::ReplayCache;
+use crate::time::{format_rfc3339, parse_rfc3339, Clock};
+use crate::types::{Node, SplError};
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicI64>,
+}
+
+impl ManualClock {
+    pub fn new(now_unix: i64) -> Self {
+        Self { now: Arc::new(AtomicI64::new(now_unix)) }
+    }
+
+    /// A clock stopped at an RFC 3339 time.
+    pub fn at(rfc3339: &str) -> Result<Self, SplError> {
+        parse_rfc3339(rfc3339).map(Self::new)
+    }
+
+    pub fn set(&self, now_unix: i64) {
+        self.now.store(now_unix, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_unix(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Replay cache whose seen nonces can be preloaded and inspected.
+#[derive(Debug, Clone, Default)]
+pub struct MockReplayCache {
+    seen: Arc<Mutex<HashSet<String>>>,
+}
+
+impl MockReplayCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat `nonce` as already used, so presenting it is a replay.
+    pub fn preload(&self, nonce: &str) {
+        lock(&self.seen).insert(nonce.to_string());
+    }
+
+    /// Nonces recorded so far, sorted.
+    pub fn seen(&self) -> Vec<String> {
+        let mut seen: Vec<String> = lock(&self.seen).iter().cloned().collect();
+        seen.sort();
+        seen
+    }
+}
+
+impl ReplayCache for MockReplayCache {
+    fn check_and_record(&self, nonce: &str) -> bool {
+        lock(&self.seen).insert(nonce.to_string())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    counts: HashMap<(String, String), i64>,
+    spent: HashMap<String, f64>,
+}
+
+/// Scripted `per-day-count` and `spent-for` values.
+#[derive(Debug, Clone, Default)]
+pub struct MockUsage {
+    usage: Arc<Mutex<Usage>>,
+}
+
+impl MockUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_count(&self, action: &str, day: &str, count: i64) {
+        lock(&self.usage).counts.insert((action.to_string(), day.to_string()), count);
+    }
+
+    /// Count one more `action` on `day`, as a host would after an allow.
+    pub fn record(&self, action: &str, day: &str) {
+        *lock(&self.usage).counts.entry((action.to_string(), day.to_string())).or_default() += 1;
+    }
+
+    pub fn set_spent(&self, key: &str, amount: f64) {
+        lock(&self.usage).spent.insert(key.to_string(), amount);
+    }
+
+    pub fn add_spent(&self, key: &str, amount: f64) {
+        *lock(&self.usage).spent.entry(key.to_string()).or_default() += amount;
+    }
+
+    pub fn count(&self, action: &str, day: &str) -> i64 {
+        lock(&self.usage).counts.get(&(action.to_string(), day.to_string())).copied().unwrap_or(0)
+    }
+
+    pub fn spent(&self, key: &str) -> f64 {
+        lock(&self.usage).spent.get(key).copied().unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug)]
+struct MockList {
+    version: String,
+    age_secs: u64,
+    entries: HashSet<String>,
+}
+
+/// Deny lists with canned contents and ages, e.g. revoked recipients or
+/// merchants. Unlike [`crate::denylist::InMemoryDenyLists`], a list's age is
+/// whatever was last set rather than wall-clock time since loading.
+#[derive(Debug, Clone, Default)]
+pub struct MockDenyLists {
+    lists: Arc<Mutex<HashMap<String, MockList>>>,
+}
+
+impl MockDenyLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load or replace a list with age zero.
+    pub fn load<I, S>(&self, list: &str, version: &str, entries: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        lock(&self.lists).insert(
+            list.to_string(),
+            MockList {
+                version: version.to_string(),
+                age_secs: 0,
+                entries: entries.into_iter().map(Into::into).collect(),
+            },
+        );
+    }
+
+    /// Add `value` to `list`, creating the list at version `"dev"` if needed.
+    pub fn revoke(&self, list: &str, value: &str) {
+        lock(&self.lists)
+            .entry(list.to_string())
+            .or_insert_with(|| MockList { version: "dev".into(), age_secs: 0, entries: HashSet::new() })
+            .entries
+            .insert(value.to_string());
+    }
+
+    /// Report `list` as last refreshed `age_secs` ago, to exercise staleness.
+    pub fn set_age(&self, list: &str, age_secs: u64) {
+        if let Some(l) = lock(&self.lists).get_mut(list) {
+            l.age_secs = age_secs;
+        }
+    }
+}
+
+impl DenyListProvider for MockDenyLists {
+    fn status(&self, list: &str) -> Option<DenyListStatus> {
+        lock(&self.lists).get(list).map(|l| DenyListStatus { version: l.version.clone(), age_secs: l.age_secs })
+    }
+
+    fn contains(&self, list: &str, value: &str) -> bool {
+        lock(&self.lists).get(list).is_some_and(|l| l.entries.contains(value))
+    }
+}
+
+/// One of each mock, sharing state with everything built from it.
+#[derive(Debug, Clone, Default)]
+pub struct DevProviders {
+    pub clock: ManualClock,
+    pub replay: MockReplayCache,
+    pub usage: MockUsage,
+    pub denylists: MockDenyLists,
+}
+
+impl DevProviders {
+    /// Providers with the clock stopped at `now_unix`.
+    pub fn new(now_unix: i64) -> Self {
+        Self { clock: ManualClock::new(now_unix), ..Self::default() }
+    }
+
+    /// A verifier under the default profile reading this clock, replay
+    /// cache, and deny lists (with a one-hour staleness bound).
+    pub fn verifier(&self) -> Verifier {
+        Verifier::new(VerifierProfile::default())
+            .with_clock(self.clock.clone())
+            .with_replay_cache(self.replay.clone())
+            .with_denylists(Arc::new(self.denylists.clone()), DEFAULT_MAX_STALENESS_SECS)
+    }
+
+    /// An [`EnvBuilder`] for `req` reading this usage and these deny lists
+    /// (with a one-hour staleness bound), with `now` and `day` vars from the
+    /// clock.
+    pub fn env_builder(&self, req: HashMap<String, Node>) -> EnvBuilder {
+        let now = format_rfc3339(self.clock.now_unix());
+        let (counts, spent) = (self.usage.clone(), self.usage.clone());
+        EnvBuilder::new()
+            .request(req)
+            .var("day", &now[..10])
+            .clock(self.clock.clone())
+            .usage_count(move |key, day| counts.count(key.as_str(), day))
+            .spent_for(move |key| spent.spent(key))
+            .denylists(self.denylists.clone(), DEFAULT_MAX_STALENESS_SECS)
+    }
+}
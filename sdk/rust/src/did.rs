@@ -0,0 +1,194 @@
+//! Decentralized identifiers (DIDs) for token issuers.
+//!
+//! A token may sign an `issuer` DID into its envelope. `did:key` DIDs
+//! encode the Ed25519 key itself, so every verifier checks that such an
+//! issuer is the key that signed. Other methods, such as `did:web`, need a
+//! lookup: implement [`Resolver`] and trust the DID with
+//! [`TrustedKey::from_did`], which resolves it once when the trust set is
+//! built. Tokens naming the DID as `issuer` then match that trusted key.
+//!
+//! ```text
+//! did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp
+//!         └ base58btc(0xed 0x01 ‖ 32-byte Ed25519 public key)
+//! ```
+
+use crate::issuers::TrustedKey;
+use crate::signature::SignatureScheme;
+use crate::token::{Token, VerifyError, VerifyErrorCode};
+use crate::types::SplError;
+
+pub const DID_KEY_PREFIX: &str = "did:key:";
+
+/// Multicodec prefix of an Ed25519 public key (`ed25519-pub`, varint 0xed).
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// Most base58 digits a multicodec-prefixed Ed25519 key (34 bytes) takes.
+/// Longer input is rejected before decoding, which is quadratic in its length.
+const ED25519_BASE58_LEN: usize = 47;
+
+/// Base58btc, the Bitcoin alphabet.
+pub fn base58_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    // Little-endian base-58 digits of the big-endian input.
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for byte in &data[zeros..] {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut out = "1".repeat(zeros);
+    out.extend(digits.iter().rev().map(|d| BASE58[*d as usize] as char));
+    out
+}
+
+pub fn base58_decode(s: &str) -> Result<Vec<u8>, SplError> {
+    let zeros = s.bytes().take_while(|b| *b == b'1').count();
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    for c in s.bytes().skip(zeros) {
+        let mut carry = BASE58
+            .iter()
+            .position(|b| *b == c)
+            .ok_or_else(|| SplError(format!("invalid base58 character {:?}", c as char)))? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+/// The `did:key` DID of an Ed25519 public key.
+pub fn did_key(public_key_hex: &str) -> Result<String, SplError> {
+    let key = hex::decode(public_key_hex).map_err(|e| SplError(format!("invalid public key hex: {e}")))?;
+    if key.len() != 32 {
+        return Err(SplError("Ed25519 public key must be 32 bytes".into()));
+    }
+    Ok(format!("{DID_KEY_PREFIX}z{}", base58_encode(&[&ED25519_MULTICODEC[..], &key].concat())))
+}
+
+/// The hex Ed25519 public key a `did:key` DID, or one of its verification
+/// method URLs (`did:key:z6Mk…#z6Mk…`), encodes.
+pub fn did_key_public_key(did: &str) -> Result<String, SplError> {
+    let did = did.split_once('#').map_or(did, |(did, _)| did);
+    let encoded = did
+        .strip_prefix(DID_KEY_PREFIX)
+        .ok_or_else(|| SplError(format!("not a did:key DID: {did}")))?;
+    public_key_from_multibase(encoded)
+}
+
+/// An Ed25519 key in multibase form: `z` then base58btc of the
+/// multicodec-prefixed key.
+fn public_key_from_multibase(encoded: &str) -> Result<String, SplError> {
+    let data = encoded
+        .strip_prefix('z')
+        .ok_or_else(|| SplError("only base58btc (z) multibase keys are supported".into()))?;
+    if data.len() > ED25519_BASE58_LEN {
+        return Err(SplError("multibase key is not an Ed25519 public key".into()));
+    }
+    let bytes = base58_decode(data)?;
+    match bytes.strip_prefix(&ED25519_MULTICODEC[..]) {
+        Some(key) if key.len() == 32 => Ok(hex::encode(key)),
+        _ => Err(SplError("multibase key is not an Ed25519 public key".into())),
+    }
+}
+
+/// Resolves a DID to the Ed25519 public key (hex) it asserts tokens with.
+pub trait Resolver: Send + Sync {
+    fn resolve(&self, did: &str) -> Result<String, SplError>;
+}
+
+/// Resolves `did:key` DIDs, which need no lookup.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DidKeyResolver;
+
+impl Resolver for DidKeyResolver {
+    fn resolve(&self, did: &str) -> Result<String, SplError> {
+        did_key_public_key(did)
+    }
+}
+
+/// Where a `did:web` DID document is published: `did:web:example.com` is
+/// `https://example.com/.well-known/did.json`, and
+/// `did:web:example.com:agents:7` is `https://example.com/agents/7/did.json`.
+pub fn did_web_url(did: &str) -> Result<String, SplError> {
+    let id = did.strip_prefix("did:web:").ok_or_else(|| SplError(format!("not a did:web DID: {did}")))?;
+    let mut parts = id.split(':');
+    let host = parts.next().filter(|h| !h.is_empty()).ok_or_else(|| SplError("did:web has no host".into()))?;
+    let path: Vec<&str> = parts.collect();
+    let host = host.replace("%3A", ":").replace("%3a", ":");
+    Ok(if path.is_empty() {
+        format!("https://{host}/.well-known/did.json")
+    } else {
+        format!("https://{host}/{}/did.json", path.join("/"))
+    })
+}
+
+/// The Ed25519 key a DID document asserts with: its first
+/// `assertionMethod` (or, lacking one, `verificationMethod`) entry carrying
+/// `publicKeyMultibase` or an OKP `publicKeyJwk`. The document's `id` must
+/// be `did`. Use it to implement [`Resolver`] for `did:web`.
+#[cfg(feature = "jws")]
+pub fn document_key(document: &serde_json::Value, did: &str) -> Result<String, SplError> {
+    use serde_json::Value;
+
+    if document.get("id").and_then(Value::as_str) != Some(did) {
+        return Err(SplError(format!("DID document is not for {did}")));
+    }
+    let methods = document.get("verificationMethod").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+    let candidates: Vec<&Value> = match document.get("assertionMethod").and_then(Value::as_array) {
+        Some(references) => references
+            .iter()
+            .filter_map(|reference| match reference {
+                Value::String(id) => methods.iter().find(|m| m.get("id").and_then(Value::as_str) == Some(id.as_str())),
+                embedded => Some(embedded),
+            })
+            .collect(),
+        None => methods.iter().collect(),
+    };
+    for method in candidates {
+        if let Some(multibase) = method.get("publicKeyMultibase").and_then(Value::as_str) {
+            return public_key_from_multibase(multibase);
+        }
+        if let Some(jwk) = method.get("publicKeyJwk") {
+            if jwk.get("kty").and_then(Value::as_str) == Some("OKP") && jwk.get("crv").and_then(Value::as_str) == Some("Ed25519") {
+                let x = jwk.get("x").and_then(Value::as_str).ok_or_else(|| SplError("JWK has no x".into()))?;
+                return Ok(hex::encode(crate::jws::base64url_decode(x)?));
+            }
+        }
+    }
+    Err(SplError(format!("DID document for {did} has no Ed25519 assertion key")))
+}
+
+impl TrustedKey {
+    /// Trust the key `did` resolves to, for tokens naming `did` as issuer.
+    pub fn from_did(did: &str, resolver: &dyn Resolver) -> Result<TrustedKey, SplError> {
+        Ok(TrustedKey::new(&resolver.resolve(did)?).kid(did))
+    }
+}
+
+/// A `did:key` issuer must be the key that signed the token.
+pub(crate) fn check_did_key(token: &Token) -> Result<(), VerifyError> {
+    let Some(issuer) = token.issuer.as_deref().filter(|i| i.starts_with(DID_KEY_PREFIX)) else {
+        return Ok(());
+    };
+    let untrusted = |message: String| VerifyError::new(VerifyErrorCode::UntrustedIssuer, message);
+    let key = did_key_public_key(issuer).map_err(|e| untrusted(format!("invalid issuer DID: {e}")))?;
+    if token.alg != SignatureScheme::Ed25519 || !key.eq_ignore_ascii_case(&token.public_key) {
+        return Err(untrusted("issuer DID does not match the signing key".into()));
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! Selective disclosure of request fields through salted hash commitments.
+//!
+//! A policy can test a sensitive field without the verifier seeing it:
+//! the issuer mints `(=hash (get req "diagnosis") "<commitment>")` with a
+//! [`commit`]ment to the expected value, and at request time the agent
+//! replaces the field with its own commitment under the same salt
+//! ([`disclose_request`]), so the verifier compares commitments only.
+//!
+//! A commitment is `<salt>:<digest>`: a hex salt of at least 16 bytes and
+//! the SHA-256 hex of the tag `agent-safe-disclose-v1\0`, the salt bytes,
+//! and the value in [`crate::request`] encoding. Types are part of the
+//! encoding, so `5` and `5.0` commit differently. The salt keeps
+//! low-entropy values from being recovered by hashing guesses, as long as
+//! it stays between issuer, agent, and verifier.
+
+use std::collections::HashMap;
+
+use crate::crypto::{ct_eq_hex, sha256_hex};
+use crate::request::encode_value;
+use crate::types::{Node, SplError};
+
+const TAG: &[u8] = b"agent-safe-disclose-v1\0";
+const MIN_SALT_BYTES: usize = 16;
+
+/// A random 16-byte hex salt.
+pub fn new_salt() -> String {
+    let mut salt = [0u8; MIN_SALT_BYTES];
+    getrandom::fill(&mut salt).expect("OS RNG failed");
+    hex::encode(salt)
+}
+
+/// The commitment to `value` under `salt_hex`.
+pub fn disclose(value: &Node, salt_hex: &str) -> Result<String, SplError> {
+    let salt = hex::decode(salt_hex).map_err(|e| SplError(format!("invalid salt hex: {e}")))?;
+    if salt.len() < MIN_SALT_BYTES {
+        return Err(SplError(format!("salt must be at least {MIN_SALT_BYTES} bytes")));
+    }
+    let mut data = [TAG, &salt].concat();
+    encode_value(&mut data, value);
+    Ok(format!("{}:{}", salt_hex.to_ascii_lowercase(), sha256_hex(&data)))
+}
+
+/// A commitment to `value` under a fresh salt, for minting `=hash` policies.
+pub fn commit(value: &Node) -> String {
+    disclose(value, &new_salt()).expect("fresh salt is valid")
+}
+
+/// The salt of a commitment, checking its form.
+pub fn commitment_salt(commitment: &str) -> Result<&str, SplError> {
+    let invalid = || SplError(format!("invalid commitment: {commitment:?}"));
+    let (salt, digest) = commitment.split_once(':').ok_or_else(invalid)?;
+    let is_hex = |s: &str| s.bytes().all(|b| b.is_ascii_hexdigit());
+    if salt.len() < 2 * MIN_SALT_BYTES || salt.len() % 2 != 0 || !is_hex(salt) || digest.len() != 64 || !is_hex(digest) {
+        return Err(invalid());
+    }
+    Ok(salt)
+}
+
+/// Whether `value` matches `commitment`: either it is the commitment
+/// itself, as disclosed by the agent, or a clear value that commits to it.
+/// A missing (`nil`) value never matches.
+pub fn hash_matches(value: &Node, commitment: &str) -> Result<bool, SplError> {
+    let salt = commitment_salt(commitment)?;
+    Ok(match value {
+        Node::Nil => false,
+        Node::Str(s) if commitment_salt(s).is_ok() => same_commitment(s, commitment),
+        clear => same_commitment(&disclose(clear, salt)?, commitment),
+    })
+}
+
+/// Compare well-formed commitments in constant time.
+fn same_commitment(a: &str, b: &str) -> bool {
+    match (a.split_once(':'), b.split_once(':')) {
+        (Some((salt_a, digest_a)), Some((salt_b, digest_b))) => ct_eq_hex(salt_a, salt_b) & ct_eq_hex(digest_a, digest_b),
+        _ => false,
+    }
+}
+
+/// `req` with every field that `ast` tests with `(=hash (get req "field")
+/// "<commitment>")` replaced by its commitment under that salt. Fields the
+/// request lacks are left out; a field tested under two salts is an error,
+/// since one disclosed value cannot match both.
+pub fn disclose_request(ast: &Node, req: &HashMap<String, Node>) -> Result<HashMap<String, Node>, SplError> {
+    let mut salts: HashMap<&str, &str> = HashMap::new();
+    collect_salts(ast, &mut salts)?;
+    let mut disclosed = req.clone();
+    for (field, salt) in salts {
+        if let Some(value) = disclosed.get_mut(field) {
+            *value = Node::Str(disclose(value, salt)?);
+        }
+    }
+    Ok(disclosed)
+}
+
+fn collect_salts<'a>(node: &'a Node, salts: &mut HashMap<&'a str, &'a str>) -> Result<(), SplError> {
+    let Node::List(items) = node else { return Ok(()) };
+    if let [Node::Symbol(head), Node::List(get), Node::Str(commitment)] = items.as_slice() {
+        if let ("=hash", [Node::Symbol(g), Node::Symbol(obj), Node::Str(field)]) = (head.as_str(), get.as_slice()) {
+            if g == "get" && obj == "req" {
+                let salt = commitment_salt(commitment)?;
+                if salts.insert(field, salt).is_some_and(|prev| !prev.eq_ignore_ascii_case(salt)) {
+                    return Err(SplError(format!("field {field:?} is committed under more than one salt")));
+                }
+            }
+        }
+    }
+    items.iter().try_for_each(|item| collect_salts(item, salts))
+}
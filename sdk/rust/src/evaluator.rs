@@ -1,218 +1,1256 @@
-use crate::types::{Env, Node, SplError, SplResult};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 
-const MAX_DEPTH: i64 = 64;
+use crate::attestation::Attestation;
+use crate::crypto::{verify_merkle_proof, MerkleProofStep};
+use crate::disclosure::hash_matches;
+use crate::geo::{country_code, Cidr};
+use crate::denylist::DenyListVersion;
+use crate::limits::PolicyLimits;
+use std::cmp::Ordering;
 
-struct EvalState {
+use crate::money;
+use crate::obligations::Obligation;
+use crate::ops::{HostOp, Op, OpHandler, OpImpl};
+use crate::scope::glob_matches;
+use crate::time::{format_rfc3339, is_date, local_time, parse_rfc3339, period_bounds};
+use crate::types::{CallbackState, CounterKey, Env, Node, SplError, SplResult, StringMatch};
+
+pub(crate) struct EvalState {
     gas: i64,
-    depth: i64,
+    pub(crate) depth: usize,
+    max_depth: usize,
+    /// Parameters of the `count-if` predicates being applied, innermost last.
+    bindings: Vec<(String, Node)>,
+    denylists: Vec<DenyListVersion>,
+    obligations: Vec<Obligation>,
+    reason_code: Option<String>,
+    /// `req["attestation"]` once checked, so a single-use nonce is consumed once.
+    attestation: Option<Option<Attestation>>,
+}
+
+/// Result of an evaluation together with what it consulted.
+#[derive(Debug, Clone)]
+pub struct EvalOutcome {
+    pub value: Node,
+    /// Gas consumed under `env.gas`.
+    pub gas_used: i64,
+    /// Deny-list versions read by `denylist-absent?`, in evaluation order.
+    pub denylists: Vec<DenyListVersion>,
+    /// Obligations attached by `obligate` forms that were reached.
+    pub obligations: Vec<Obligation>,
+    /// Code from the first `deny-with` form reached.
+    pub reason_code: Option<String>,
+}
+
+/// Intermediate evaluation value. Literals, vars, and request fields are
+/// borrowed from the AST or environment; only computed values are owned.
+type Value<'a> = Cow<'a, Node>;
+pub(crate) type EvalResult<'a> = Result<Value<'a>, SplError>;
+
+/// An expression the evaluator can walk: a parsed [`Node`] or a compiled
+/// [`crate::compiled::CompiledPolicy`] expression. Operators are shared, so
+/// both forms decide and charge gas identically.
+pub(crate) trait Operand: Sized {
+    /// Evaluate this expression; [`eval`] adds the per-node charge and depth check.
+    fn eval_inner<'a>(&'a self, env: &'a Env, st: &mut EvalState) -> EvalResult<'a>;
+    /// The symbol this expression is, if any.
+    fn as_symbol(&self) -> Option<&str>;
+    /// The symbol or string literal this expression is, if any.
+    fn as_name(&self) -> Option<&str>;
+    /// The entries of a `(limits ...)` form, which are data rather than expressions.
+    fn limits(args: &[Self]) -> Result<Cow<'_, PolicyLimits>, SplError>;
+    /// The parameter and body of a `(lambda (x) body)` form.
+    fn lambda(&self) -> Option<(&str, &Self)>;
+}
+
+impl Operand for Node {
+    fn eval_inner<'a>(&'a self, env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+        match self {
+            Node::List(items) if items.is_empty() => Ok(Cow::Owned(Node::Nil)),
+            Node::List(items) => {
+                let name = match &items[0] {
+                    Node::Symbol(s) => s.as_str(),
+                    _ => return Err(SplError("operator must be a symbol".into())),
+                };
+                let handler = env.ops.get(name).ok_or_else(|| SplError(format!("Unknown op: {name}")))?;
+                eval_call(name, &handler, &items[1..], env, st)
+            }
+            Node::Symbol(s) => resolve_symbol(s, env, st),
+            Node::Bool(_) | Node::Int(_) | Node::Number(_) | Node::Str(_) | Node::Map(_) | Node::Money { .. } | Node::Nil => {
+                Ok(Cow::Borrowed(self))
+            }
+        }
+    }
+
+    fn as_symbol(&self) -> Option<&str> {
+        match self {
+            Node::Symbol(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Node::Symbol(s) | Node::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn limits(args: &[Node]) -> Result<Cow<'_, PolicyLimits>, SplError> {
+        PolicyLimits::from_entries(args).map(Cow::Owned)
+    }
+
+    fn lambda(&self) -> Option<(&str, &Node)> {
+        lambda_parts(self)
+    }
+}
+
+/// The parameter and body of `(lambda (x) body)`. The parameter may not be
+/// `req`, `#t`, or `#f`.
+pub(crate) fn lambda_parts(node: &Node) -> Option<(&str, &Node)> {
+    let Node::List(items) = node else { return None };
+    match items.as_slice() {
+        [Node::Symbol(head), Node::List(params), body] if head == "lambda" => match params.as_slice() {
+            [Node::Symbol(param)] => bindable(param).map(|param| (param, body)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The parameter a `lambda`, `all`, or `any` form binds, and the index in
+/// the form of the expression it is bound in.
+pub(crate) fn bound_param(node: &Node) -> Option<(&str, usize)> {
+    let Node::List(items) = node else { return None };
+    match items.as_slice() {
+        [Node::Symbol(head), _, _] if head == "lambda" => lambda_parts(node).map(|(param, _)| (param, 2)),
+        [Node::Symbol(head), Node::Symbol(param), _, _] if head == "all" || head == "any" => {
+            bindable(param).map(|param| (param, 3))
+        }
+        _ => None,
+    }
+}
+
+fn bindable(name: &str) -> Option<&str> {
+    (!matches!(name, "req" | "#t" | "#f")).then_some(name)
 }
 
 /// Evaluate an SPL AST within an environment. Returns the result Node.
 pub fn eval_policy(ast: &Node, env: &Env) -> SplResult {
+    eval_policy_detailed(ast, env).map(|outcome| outcome.value)
+}
+
+/// Evaluate an SPL AST, also reporting the external state it consulted.
+pub fn eval_policy_detailed(ast: &Node, env: &Env) -> Result<EvalOutcome, SplError> {
+    run(ast, env)
+}
+
+pub(crate) fn run<A: Operand>(root: &A, env: &Env) -> Result<EvalOutcome, SplError> {
+    env.string_match.check()?;
+    // Checked once up front, so field and var reads stay O(1).
+    env.req.values().chain(env.vars.values()).try_for_each(|v| env.limits.check_value(v))?;
     let mut state = EvalState {
         gas: env.max_gas,
         depth: 0,
+        max_depth: env.limits.max_depth,
+        bindings: Vec::new(),
+        denylists: Vec::new(),
+        obligations: Vec::new(),
+        reason_code: None,
+        attestation: None,
+    };
+    let value = eval(root, env, &mut state)?.into_owned();
+    Ok(EvalOutcome {
+        value,
+        gas_used: env.max_gas - state.gas,
+        denylists: state.denylists,
+        obligations: state.obligations,
+        reason_code: state.reason_code,
+    })
+}
+
+/// Pre-resolve constant subtrees, e.g. `(<= 5 10)` becomes `#t`.
+///
+/// Only pure operators (see [`Op::is_pure`]) whose arguments are all scalar
+/// literals are folded, plus `and`/`or` short-circuits on a leading constant.
+/// Subtrees that would error are left intact so the error still surfaces at
+/// evaluation time. The residual policy evaluates identically but cheaper.
+pub fn fold_constants(ast: &Node) -> Node {
+    let Node::List(items) = ast else {
+        return ast.clone();
+    };
+    let Some(Node::Symbol(name)) = items.first() else {
+        return ast.clone();
+    };
+    // `limits` entries are data, not expressions.
+    if Op::from_name(name) == Some(Op::Limits) {
+        return ast.clone();
+    }
+    let folded: Vec<Node> = std::iter::once(items[0].clone())
+        .chain(items[1..].iter().map(fold_constants))
+        .collect();
+    let Some(op) = Op::from_name(name) else {
+        return Node::List(folded);
+    };
+
+    let args = &folded[1..];
+    if matches!(op, Op::And | Op::Or) {
+        if let Some(first) = args.first().filter(|a| is_scalar(a)) {
+            let short_circuit = if op == Op::And { !first.is_truthy() } else { first.is_truthy() };
+            if short_circuit {
+                return Node::Bool(op == Op::Or);
+            }
+        }
+    }
+    if op.is_pure() && args.iter().all(is_scalar) && !depends_on_string_match(op, args) {
+        let candidate = Node::List(folded);
+        return match eval_policy(&candidate, &Env::default()) {
+            Ok(value) if is_scalar(&value) => value,
+            _ => candidate,
+        };
+    }
+    Node::List(folded)
+}
+
+/// Values fixed ahead of evaluation, for [`partial_eval`].
+#[derive(Debug, Clone, Default)]
+pub struct KnownBindings {
+    /// Request fields every request the residual policy admits will carry.
+    pub req: HashMap<String, Node>,
+    /// Vars bound now instead of at verification.
+    pub vars: HashMap<String, Node>,
+}
+
+/// Specialize a policy by folding in values known ahead of time, e.g. a
+/// recipient fixed at mint time, and return the simplified residual.
+///
+/// Known vars and `(get req "...")` lookups of known fields are replaced by
+/// their values, constants are folded as by [`fold_constants`], and `and` /
+/// `or` drop operands that cannot change the outcome. Request fields that
+/// were substituted are pinned with a leading `(= (get req "...") value)`,
+/// so the residual admits exactly the requests the original policy admits
+/// that also carry those values. `limits` blocks and host operators are
+/// left for evaluation time.
+pub fn partial_eval(ast: &Node, known: &KnownBindings) -> Node {
+    let mut pinned = BTreeMap::new();
+    let residual = simplify(substitute(ast, known, &mut pinned));
+    if pinned.is_empty() || residual == Node::Bool(false) {
+        return residual;
+    }
+    let mut conjuncts: Vec<Node> = pinned
+        .into_iter()
+        .map(|(key, value)| Node::List(vec![Node::Symbol("=".into()), req_get(key), value]))
+        .collect();
+    match residual {
+        Node::Bool(true) => {}
+        Node::List(items) if items.first() == Some(&Node::Symbol("and".into())) => {
+            conjuncts.extend(items.into_iter().skip(1));
+        }
+        other => conjuncts.push(other),
+    }
+    if conjuncts.len() == 1 {
+        return conjuncts.remove(0);
+    }
+    Node::List(std::iter::once(Node::Symbol("and".into())).chain(conjuncts).collect())
+}
+
+fn req_get(key: String) -> Node {
+    Node::List(vec![Node::Symbol("get".into()), Node::Symbol("req".into()), Node::Str(key)])
+}
+
+/// Replace known vars and request lookups, recording the request fields used.
+fn substitute(node: &Node, known: &KnownBindings, pinned: &mut BTreeMap<String, Node>) -> Node {
+    match node {
+        Node::Symbol(name) if !matches!(name.as_str(), "req" | "#t" | "#f") => {
+            known.vars.get(name).and_then(|v| literal(v, true)).unwrap_or_else(|| node.clone())
+        }
+        Node::List(items) => {
+            let Some((Node::Symbol(head), args)) = items.split_first() else {
+                return node.clone();
+            };
+            match (Op::from_name(head), args) {
+                (Some(Op::Limits), _) => node.clone(),
+                // A bound parameter shadows a var of the same name in its scope.
+                (Some(Op::Lambda | Op::All | Op::Any), _) => {
+                    let Some((param, scope)) = bound_param(node) else { return node.clone() };
+                    let mut inner = known.clone();
+                    inner.vars.remove(param);
+                    let args = items.iter().enumerate().map(|(i, a)| match i {
+                        0 | 1 => a.clone(),
+                        _ if i == scope => substitute(a, &inner, pinned),
+                        _ => substitute(a, known, pinned),
+                    });
+                    Node::List(args.collect())
+                }
+                (Some(Op::Get), [Node::Symbol(obj), Node::Str(key)]) if obj == "req" => {
+                    match known.req.get(key).and_then(|v| literal(v, false)) {
+                        Some(value) => {
+                            pinned.insert(key.clone(), value.clone());
+                            value
+                        }
+                        None => node.clone(),
+                    }
+                }
+                _ => {
+                    // merkle_ok? names its proof rather than evaluating it.
+                    let by_name = Op::from_name(head) == Some(Op::MerkleOk) && args.len() == 3;
+                    let args = args.iter().enumerate().map(|(i, a)| {
+                        if by_name && i == 1 { a.clone() } else { substitute(a, known, pinned) }
+                    });
+                    Node::List(std::iter::once(items[0].clone()).chain(args).collect())
+                }
+            }
+        }
+        _ => node.clone(),
+    }
+}
+
+/// `value` as an expression that evaluates to it, if it has one. Lists
+/// become `(tuple ...)`; `nil` and non-finite numbers have no literal.
+fn literal(value: &Node, compound: bool) -> Option<Node> {
+    match value {
+        Node::Number(n) if !n.is_finite() => None,
+        Node::Nil | Node::Symbol(_) => None,
+        Node::List(items) if compound => std::iter::once(Some(Node::Symbol("tuple".into())))
+            .chain(items.iter().map(|item| literal(item, true)))
+            .collect::<Option<Vec<_>>>()
+            .map(Node::List),
+        Node::List(_) | Node::Map(_) if !compound => None,
+        _ => Some(value.clone()),
+    }
+}
+
+/// Fold bottom-up, also dropping `and` / `or` operands that cannot change
+/// the outcome and reading known keys out of literal maps.
+fn simplify(node: Node) -> Node {
+    let Node::List(items) = node else { return node };
+    let op = match items.first() {
+        Some(Node::Symbol(name)) => Op::from_name(name),
+        _ => return Node::List(items),
     };
-    eval(ast, env, &mut state)
+    if op == Some(Op::Limits) {
+        return Node::List(items);
+    }
+    let mut items: Vec<Node> = items.into_iter().map(simplify).collect();
+    match op {
+        Some(op @ (Op::And | Op::Or)) => {
+            // Truthy operands never stop an `and`, and falsy ones never
+            // stop an `or`; the first operand that does ends it.
+            let stops = |n: &Node| n.is_truthy() == (op == Op::Or);
+            let mut args = Vec::new();
+            for arg in items.drain(1..) {
+                if !is_scalar(&arg) {
+                    args.push(arg);
+                } else if stops(&arg) {
+                    if args.is_empty() {
+                        return Node::Bool(op == Op::Or);
+                    }
+                    args.push(Node::Bool(op == Op::Or));
+                    break;
+                }
+            }
+            match args.len() {
+                0 => Node::Bool(op == Op::And),
+                1 if is_boolean(&args[0]) => args.remove(0),
+                _ => Node::List(items.into_iter().chain(args).collect()),
+            }
+        }
+        Some(Op::Get) => match &items[1..] {
+            [Node::Map(entries), Node::Str(key)] => match entries.get(key).and_then(|v| literal(v, true)) {
+                Some(value) => value,
+                None => Node::List(items),
+            },
+            _ => Node::List(items),
+        },
+        // Membership in a literal tuple is decided, though not scalar-only.
+        Some(op @ (Op::Member | Op::Subset | Op::Disjoint))
+            if items[1..].iter().all(is_constant) && !depends_on_string_match(op, &items[1..]) =>
+        {
+            let candidate = Node::List(items);
+            match eval_policy(&candidate, &Env::default()) {
+                Ok(value) if is_scalar(&value) => value,
+                _ => candidate,
+            }
+        }
+        _ => fold_constants(&Node::List(items)),
+    }
 }
 
-fn eval(node: &Node, env: &Env, st: &mut EvalState) -> SplResult {
-    st.gas -= 1;
+/// Whether `op` compares strings among `args` that [`StringMatch`] modes
+/// could disagree on, so folding must wait for the evaluation `Env`. Plain
+/// ASCII without capitals compares the same under every mode.
+fn depends_on_string_match(op: Op, args: &[Node]) -> bool {
+    fn sensitive(node: &Node) -> bool {
+        match node {
+            Node::Str(s) => !s.bytes().all(|b| b.is_ascii() && !b.is_ascii_uppercase()),
+            Node::List(items) => items.iter().any(sensitive),
+            _ => false,
+        }
+    }
+    matches!(op, Op::Eq | Op::Member | Op::Subset | Op::Intersect | Op::Union | Op::Difference | Op::Disjoint)
+        && args.iter().any(sensitive)
+}
+
+/// A scalar literal, or a `(tuple ...)` of them.
+fn is_constant(node: &Node) -> bool {
+    match node {
+        Node::List(items) => {
+            items.first() == Some(&Node::Symbol("tuple".into())) && items[1..].iter().all(is_constant)
+        }
+        _ => is_scalar(node),
+    }
+}
+
+/// Whether `node` evaluates to a boolean, so `(and node)` can become `node`.
+fn is_boolean(node: &Node) -> bool {
+    match node {
+        Node::Bool(_) => true,
+        Node::List(items) => match items.first() {
+            Some(Node::Symbol(name)) => !matches!(
+                Op::from_name(name),
+                None | Some(
+                    Op::Get | Op::LimitFor | Op::SpentFor | Op::CumulativeSpend | Op::RemainingFor | Op::Tuple
+                        | Op::PerDayCount | Op::UsageCount | Op::PerDayCountSelf | Op::Money | Op::Convert | Op::Length | Op::Sum | Op::CountIf
+                        | Op::Lambda | Op::Intersect | Op::Union | Op::Difference
+                )
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_scalar(node: &Node) -> bool {
+    matches!(node, Node::Bool(_) | Node::Int(_) | Node::Number(_) | Node::Str(_) | Node::Money { .. } | Node::Nil)
+}
+
+pub(crate) fn charge(st: &mut EvalState, cost: i64) -> Result<(), SplError> {
+    st.gas -= cost;
     if st.gas < 0 {
         return Err(SplError("gas budget exceeded".into()));
     }
+    Ok(())
+}
+
+fn list_len(node: &Node) -> i64 {
+    match node {
+        Node::List(items) => items.len() as i64,
+        _ => 0,
+    }
+}
+
+fn string_cost(env: &Env, a: &Node, b: &Node) -> i64 {
+    let bytes = [a, b].iter().map(|n| match n {
+        Node::Str(s) | Node::Symbol(s) => s.len() as i64,
+        _ => 0,
+    }).sum::<i64>();
+    (bytes * env.gas.string_per_kb + 1023) / 1024
+}
+
+fn eval<'a, A: Operand>(node: &'a A, env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    charge(st, env.gas.node)?;
+    enter(st)?;
+    let result = node.eval_inner(env, st);
+    st.depth -= 1;
+    result
+}
+
+/// Descend one nesting level; callers decrement `st.depth` on the way out.
+pub(crate) fn enter(st: &mut EvalState) -> Result<(), SplError> {
     st.depth += 1;
-    if st.depth > MAX_DEPTH {
+    if st.depth > st.max_depth {
         st.depth -= 1;
         return Err(SplError("max nesting depth exceeded".into()));
     }
-    let result = eval_inner(node, env, st);
-    st.depth -= 1;
-    result
+    Ok(())
 }
 
-fn eval_inner(node: &Node, env: &Env, st: &mut EvalState) -> SplResult {
-    match node {
-        Node::List(items) if items.is_empty() => Ok(Node::Nil),
-        Node::List(items) => {
-            let op = match &items[0] {
-                Node::Symbol(s) => s.as_str(),
-                _ => return Err(SplError("operator must be a symbol".into())),
-            };
-            let args = &items[1..];
-            eval_op(op, args, env, st)
+fn boolean<'a>(b: bool) -> EvalResult<'a> {
+    Ok(Cow::Owned(Node::Bool(b)))
+}
+
+/// The host's callback for a crypto predicate; `None` means the predicate
+/// is `#f`. Strict mode refuses to evaluate an unconfigured predicate.
+fn crypto_callback<'a, F: ?Sized>(callback: &'a CallbackState<F>, op: Op, env: &Env) -> Result<Option<&'a F>, SplError> {
+    match callback {
+        CallbackState::Configured(f) => Ok(Some(f)),
+        CallbackState::Unconfigured if env.strict => {
+            Err(SplError(format!("{} callback is not configured", op.name())))
+        }
+        CallbackState::Unconfigured => Ok(None),
+    }
+}
+
+/// `(convert amount from to)`. Kept out of `eval_op` so its frame stays
+/// small for deeply nested policies.
+fn convert<'a, A: Operand>(args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let amount = eval(arg(args, 0, Op::Convert)?, env, st)?;
+    let from = eval(arg(args, 1, Op::Convert)?, env, st)?;
+    let to = eval(arg(args, 2, Op::Convert)?, env, st)?;
+    charge(st, env.gas.host_call)?;
+    let (from, to) = (node_str(&from), node_str(&to));
+    // An error in every mode: a NaN result would deny `(<= ...)` but allow
+    // `(not (> ...))`.
+    match money::convert(&amount, &from, &to, env.rates.as_deref())? {
+        Some(converted) => Ok(Cow::Owned(converted)),
+        None => Err(SplError(format!("no exchange rate from {from} to {to}"))),
+    }
+}
+
+/// Evaluate a call resolved through [`crate::ops::OpRegistry`], checking
+/// its arity first.
+pub(crate) fn eval_call<'a, A: Operand>(name: &str, handler: &OpHandler, args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    handler.check_arity(name, args.len())?;
+    match &handler.handler {
+        OpImpl::Builtin(op) => eval_op(*op, args, env, st),
+        OpImpl::Host(f) => host_op(name, handler.gas, f.as_ref(), args, env, st),
+    }
+}
+
+/// A host operator: arguments are evaluated in order, then its declared
+/// gas is charged before the host function runs.
+fn host_op<'a, A: Operand>(name: &str, gas: i64, f: &HostOp, args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    if env.allowed_ops.as_ref().is_some_and(|allowed| !allowed.contains(name)) {
+        return Err(SplError(format!("operator not permitted: {name}")));
+    }
+    if env.strict && Op::from_name(name).is_some() {
+        return Err(SplError(format!("cannot override built-in operator {name} in strict mode")));
+    }
+    let mut evaluated = Vec::with_capacity(args.len());
+    for a in args {
+        evaluated.push(eval(a, env, st)?.into_owned());
+    }
+    charge(st, gas)?;
+    let result = f(&evaluated)?;
+    env.limits.check_value(&result)?;
+    Ok(Cow::Owned(result))
+}
+
+/// `(ip-in-cidr ip block-or-blocks)`. A request address that does not
+/// parse is outside every block; a malformed block is an error.
+fn ip_in_cidr<'a, A: Operand>(args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let ip = eval(arg(args, 0, Op::IpInCidr)?, env, st)?;
+    let blocks = eval(arg(args, 1, Op::IpInCidr)?, env, st)?;
+    let ip = ip.as_str().and_then(|s| s.parse::<std::net::IpAddr>().ok());
+    let mut matched = false;
+    for block in one_or_many(&blocks) {
+        charge(st, env.gas.list_item)?;
+        let Node::Str(block) = block else {
+            return Err(SplError(format!("ip-in-cidr expects CIDR blocks, got {block}")));
+        };
+        let cidr = Cidr::parse(block)?;
+        matched |= ip.is_some_and(|ip| cidr.contains(ip));
+    }
+    boolean(matched)
+}
+
+/// `(country-in country codes)`, matching ISO 3166-1 alpha-2 codes
+/// without regard to case.
+fn country_in<'a, A: Operand>(args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let country = eval(arg(args, 0, Op::CountryIn)?, env, st)?;
+    let codes = eval(arg(args, 1, Op::CountryIn)?, env, st)?;
+    let country = country.as_str().and_then(country_code);
+    let mut matched = false;
+    for code in one_or_many(&codes) {
+        charge(st, env.gas.list_item)?;
+        let Some(code) = code.as_str().and_then(country_code) else {
+            return Err(SplError(format!("country-in expects ISO 3166-1 alpha-2 codes, got {code}")));
+        };
+        matched |= country.as_deref() == Some(code.as_str());
+    }
+    boolean(matched)
+}
+
+/// `(attested? claim)`: whether `req["attestation"]` verifies against the
+/// configured roots, attests [`Env::pop_key`], and carries `claim`. A
+/// missing or invalid statement, or an unset `pop_key`, is `#f`.
+fn attested<'a, A: Operand>(args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let claim = eval(arg(args, 0, Op::Attested)?, env, st)?;
+    charge(st, env.gas.crypto)?;
+    let Some(verifier) = &env.attestation else {
+        if env.strict {
+            return Err(SplError("attested? requires an attestation verifier".into()));
         }
-        Node::Symbol(s) => resolve_symbol(s, env),
-        Node::Bool(_) | Node::Number(_) | Node::Str(_) | Node::Nil => Ok(node.clone()),
+        return boolean(false);
+    };
+    let verified = st.attestation.get_or_insert_with(|| {
+        let device_key = env.pop_key.as_deref()?;
+        let attestation = Attestation::from_node(env.req.get("attestation")?).ok()?;
+        verifier.verify(&attestation, device_key, env.clock.now_unix()).ok().map(|_| attestation)
+    });
+    boolean(verified.as_ref().is_some_and(|a| a.has_claim(&node_str(&claim))))
+}
+
+/// The items of a list argument, or a lone value as a one-item list.
+fn one_or_many(node: &Node) -> &[Node] {
+    match node {
+        Node::List(items) => items,
+        single => std::slice::from_ref(single),
     }
 }
 
-fn eval_op(op: &str, args: &[Node], env: &Env, st: &mut EvalState) -> SplResult {
+pub(crate) fn eval_op<'a, A: Operand>(op: Op, args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    if let Some(allowed) = &env.allowed_ops {
+        if !allowed.contains(op.name()) {
+            return Err(SplError(format!("operator not permitted: {}", op.name())));
+        }
+    }
     match op {
-        "and" => {
+        Op::And => {
             for a in args {
                 let val = eval(a, env, st)?;
                 if !val.is_truthy() {
-                    return Ok(Node::Bool(false));
+                    return boolean(false);
                 }
             }
-            Ok(Node::Bool(true))
+            boolean(true)
         }
-        "or" => {
+        Op::Or => {
             for a in args {
                 let val = eval(a, env, st)?;
                 if val.is_truthy() {
-                    return Ok(Node::Bool(true));
+                    return boolean(true);
+                }
+            }
+            boolean(false)
+        }
+        Op::Not => {
+            let val = eval(arg(args, 0, op)?, env, st)?;
+            boolean(!val.is_truthy())
+        }
+        Op::Eq => {
+            let a = eval(arg(args, 0, op)?, env, st)?;
+            let b = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, string_cost(env, &a, &b))?;
+            if let (Node::Money { currency: x, .. }, Node::Money { currency: y, .. }) = (a.as_ref(), b.as_ref()) {
+                if x != y {
+                    return Err(SplError(format!("cannot compare {x} with {y}")));
                 }
             }
-            Ok(Node::Bool(false))
-        }
-        "not" => {
-            let val = eval(&args[0], env, st)?;
-            Ok(Node::Bool(!val.is_truthy()))
-        }
-        "=" => {
-            let a = eval(&args[0], env, st)?;
-            let b = eval(&args[1], env, st)?;
-            Ok(Node::Bool(node_eq(&a, &b)))
-        }
-        "<=" | "<" | ">=" | ">" => {
-            let a = eval(&args[0], env, st)?.as_f64();
-            let b = eval(&args[1], env, st)?.as_f64();
-            let result = match op {
-                "<=" => a <= b,
-                "<" => a < b,
-                ">=" => a >= b,
-                ">" => a > b,
-                _ => false,
-            };
-            Ok(Node::Bool(result))
-        }
-        "member" | "in" => {
-            let val = eval(&args[0], env, st)?;
-            let lst = eval(&args[1], env, st)?;
-            if let Node::List(items) = lst {
-                Ok(Node::Bool(items.iter().any(|item| node_eq(item, &val))))
+            boolean(node_eq(&a, &b, env.string_match))
+        }
+        Op::EqHash => {
+            let value = eval(arg(args, 0, op)?, env, st)?;
+            let commitment = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, env.gas.crypto + string_cost(env, &value, &commitment))?;
+            let Node::Str(commitment) = commitment.as_ref() else {
+                return Err(SplError(format!("=hash expects a commitment string, got {commitment}")));
+            };
+            boolean(hash_matches(&value, commitment)?)
+        }
+        Op::Le | Op::Lt | Op::Ge | Op::Gt => {
+            let a = eval(arg(args, 0, op)?, env, st)?;
+            let b = eval(arg(args, 1, op)?, env, st)?;
+            // NaN orders against nothing, so every comparison with it is false.
+            let result = match (op, compare(&a, &b)?) {
+                (_, None) => false,
+                (Op::Le, Some(o)) => o.is_le(),
+                (Op::Lt, Some(o)) => o.is_lt(),
+                (Op::Ge, Some(o)) => o.is_ge(),
+                (_, Some(o)) => o.is_gt(),
+            };
+            boolean(result)
+        }
+        Op::InRange => {
+            let x = eval(arg(args, 0, op)?, env, st)?;
+            let lo = eval(arg(args, 1, op)?, env, st)?;
+            let hi = eval(arg(args, 2, op)?, env, st)?;
+            let above = compare(&lo, &x)?.is_some_and(|o| o.is_le());
+            let below = compare(&x, &hi)?.is_some_and(|o| o.is_le());
+            boolean(above && below)
+        }
+        Op::Limits => eval_limits(A::limits(args)?.as_ref(), env, st),
+        Op::Member => {
+            let val = eval(arg(args, 0, op)?, env, st)?;
+            let lst = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, env.gas.list_item * list_len(&lst))?;
+            if let Node::List(items) = lst.as_ref() {
+                boolean(items.iter().any(|item| node_eq(item, &val, env.string_match)))
             } else {
-                Ok(Node::Bool(false))
+                boolean(false)
             }
         }
-        "subset?" => {
-            let a = eval(&args[0], env, st)?;
-            let b = eval(&args[1], env, st)?;
-            match (a, b) {
+        Op::Subset => {
+            let a = eval(arg(args, 0, op)?, env, st)?;
+            let b = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, env.gas.list_item * list_len(&a) * list_len(&b))?;
+            match (a.as_ref(), b.as_ref()) {
                 (Node::List(a_items), Node::List(b_items)) => {
                     let all_in = a_items.iter().all(|item| {
-                        b_items.iter().any(|candidate| node_eq(item, candidate))
+                        b_items.iter().any(|candidate| node_eq(item, candidate, env.string_match))
                     });
-                    Ok(Node::Bool(all_in))
+                    boolean(all_in)
                 }
-                _ => Ok(Node::Bool(false)),
+                _ => boolean(false),
             }
         }
-        "before" => {
-            let a = eval(&args[0], env, st)?;
-            let b = eval(&args[1], env, st)?;
-            let a_str = node_to_string(&a);
-            let b_str = node_to_string(&b);
-            Ok(Node::Bool(a_str < b_str))
+        Op::Length => {
+            let len = match eval(arg(args, 0, op)?, env, st)?.as_ref() {
+                Node::List(items) => items.len(),
+                Node::Map(entries) => entries.len(),
+                Node::Nil => 0,
+                other => return Err(SplError(format!("length expects a list, got {other}"))),
+            };
+            Ok(Cow::Owned(Node::Int(len as i64)))
+        }
+        Op::Sum => sum(args, env, st),
+        Op::CountIf => count_if(args, env, st),
+        Op::Lambda => Err(SplError("lambda is only valid as the predicate of count-if".into())),
+        Op::All | Op::Any => quantify(op, args, env, st),
+        Op::Intersect | Op::Union | Op::Difference | Op::Disjoint => set_op(op, args, env, st),
+        Op::Before => {
+            let a = eval(arg(args, 0, op)?, env, st)?;
+            let b = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, string_cost(env, &a, &b))?;
+            boolean(node_str(&a) < node_str(&b))
+        }
+        Op::Money => {
+            let amount = eval(arg(args, 0, op)?, env, st)?;
+            let currency = eval(arg(args, 1, op)?, env, st)?;
+            let Node::Str(currency) = currency.as_ref() else {
+                return Err(SplError(format!("money expects a currency code string, got {currency}")));
+            };
+            Ok(Cow::Owned(money::money(&amount, currency)?))
+        }
+        Op::Convert => convert(args, env, st),
+        Op::IpInCidr => ip_in_cidr(args, env, st),
+        Op::Attested => attested(args, env, st),
+        Op::CountryIn => country_in(args, env, st),
+        Op::Weekday => {
+            let t = eval(arg(args, 0, op)?, env, st)?;
+            boolean(local_time(timestamp_arg(&t, op)?)?.weekday <= 5)
         }
-        "get" => {
-            let key = eval(&args[1], env, st)?;
-            let key_str = match &key {
+        Op::HourBetween => {
+            let t = eval(arg(args, 0, op)?, env, st)?;
+            let start = hour_arg(eval(arg(args, 1, op)?, env, st)?.as_ref())?;
+            let end = hour_arg(eval(arg(args, 2, op)?, env, st)?.as_ref())?;
+            let local = local_time(timestamp_arg(&t, op)?)?;
+            let hour = local.hour as f64 + local.minute as f64 / 60.0;
+            // A window whose start is after its end wraps past midnight.
+            boolean(if start <= end { start <= hour && hour < end } else { hour >= start || hour < end })
+        }
+        Op::During => {
+            let t = eval(arg(args, 0, op)?, env, st)?;
+            let start = eval(arg(args, 1, op)?, env, st)?;
+            let end = eval(arg(args, 2, op)?, env, st)?;
+            let (t, start, end) = (timestamp_arg(&t, op)?, timestamp_arg(&start, op)?, timestamp_arg(&end, op)?);
+            if is_date(start) && is_date(end) {
+                let date = local_time(t)?.date();
+                boolean(start <= date.as_str() && date.as_str() <= end)
+            } else {
+                let t = parse_rfc3339(t)?;
+                boolean(parse_rfc3339(start)? <= t && t < parse_rfc3339(end)?)
+            }
+        }
+        Op::Get => {
+            let key = eval(arg(args, 1, op)?, env, st)?;
+            let key_str = match key.as_ref() {
                 Node::Str(s) => s.as_str(),
-                _ => return Ok(Node::Nil),
+                _ => return Ok(Cow::Owned(Node::Nil)),
             };
             // Check if first arg is symbol "req" — look up in env.req
-            if let Node::Symbol(s) = &args[0] {
-                if s == "req" {
-                    return Ok(env.req.get(key_str).cloned().unwrap_or(Node::Nil));
+            if arg(args, 0, op)?.as_symbol() == Some("req") {
+                return lookup(env.req.get(key_str));
+            }
+            // Otherwise evaluate the object and try map access
+            match eval(arg(args, 0, op)?, env, st)? {
+                Cow::Borrowed(Node::Map(entries)) => lookup(entries.get(key_str)),
+                Cow::Owned(Node::Map(mut entries)) => {
+                    Ok(Cow::Owned(entries.remove(key_str).unwrap_or(Node::Nil)))
                 }
+                _ => Ok(Cow::Owned(Node::Nil)),
+            }
+        }
+        Op::LimitFor => {
+            let key = eval(arg(args, 0, op)?, env, st)?;
+            let limits = eval(arg(args, 1, op)?, env, st)?;
+            let Node::Map(entries) = limits.as_ref() else {
+                return Err(SplError("limit-for expects a map of limits".into()));
+            };
+            // Unknown keys yield nil (or the default), which compares as 0,
+            // so a counterparty without a limit cannot receive a positive amount.
+            match entries.get(node_str(&key).as_ref()) {
+                Some(limit) => Ok(Cow::Owned(limit.clone())),
+                None if args.len() == 3 => eval(arg(args, 2, op)?, env, st),
+                None => Ok(Cow::Owned(Node::Nil)),
             }
-            // Otherwise resolve symbol and try map-like access on vars
-            let obj_name = match &args[0] {
-                Node::Symbol(s) => s.as_str(),
-                _ => return Ok(Node::Nil),
+        }
+        Op::SpentFor => {
+            let key = eval(arg(args, 0, op)?, env, st)?;
+            charge(st, env.gas.host_call)?;
+            Ok(Cow::Owned(Node::Number((env.spent_for)(&node_str(&key)))))
+        }
+        Op::CumulativeSpend => {
+            let action = eval(arg(args, 0, op)?, env, st)?;
+            let action = node_str(&action);
+            let period = eval(arg(args, 1, op)?, env, st)?;
+            let Some(tracker) = &env.spend_tracker else {
+                return Err(SplError("cumulative-spend requires a spend tracker".into()));
+            };
+            let Some(Node::Str(day)) = env.req.get("day") else {
+                return Err(SplError("cumulative-spend requires req[\"day\"]".into()));
             };
-            // For non-req symbols, resolve them
-            if let Some(Node::List(_)) = env.vars.get(obj_name) {
-                // Can't get from a list by string key
-                return Ok(Node::Nil);
+            let (from, to) = period_bounds(day, &node_str(&period))?;
+            charge(st, env.gas.host_call)?;
+            // Count this request too, so the result is the total if it is allowed.
+            let pending = match env.req.get("action") {
+                Some(Node::Str(a)) if *a == action => env.req.get("amount").and_then(Node::as_number).unwrap_or(0.0),
+                _ => 0.0,
+            };
+            Ok(Cow::Owned(Node::Number(tracker.spent_between(&action, &from, &to)? + pending)))
+        }
+        Op::RemainingFor => {
+            let key = eval(arg(args, 0, op)?, env, st)?;
+            let key = node_str(&key);
+            let limits = eval(arg(args, 1, op)?, env, st)?;
+            let Node::Map(entries) = limits.as_ref() else {
+                return Err(SplError("remaining-for expects a map of limits".into()));
+            };
+            charge(st, env.gas.host_call)?;
+            match entries.get(key.as_ref()).and_then(Node::as_number) {
+                Some(limit) => Ok(Cow::Owned(Node::Number(limit - (env.spent_for)(&key)))),
+                _ => Ok(Cow::Owned(Node::Nil)),
             }
-            Ok(Node::Nil)
         }
-        "tuple" => {
-            let mut result = Vec::new();
+        Op::Tuple => {
+            env.limits.check_list(args.len())?;
+            charge(st, env.gas.list_item * args.len() as i64)?;
+            let mut result = Vec::with_capacity(args.len());
             for a in args {
-                result.push(eval(a, env, st)?);
+                result.push(eval(a, env, st)?.into_owned());
             }
-            Ok(Node::List(result))
+            Ok(Cow::Owned(Node::List(result)))
+        }
+        Op::PerDayCount => {
+            let action = eval(arg(args, 0, op)?, env, st)?;
+            let day = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, env.gas.host_call)?;
+            let count = (env.usage_count)(&CounterKey::action(&node_str(&action)), &node_str(&day));
+            Ok(Cow::Owned(Node::Int(count)))
         }
-        "per-day-count" => {
-            let action = eval(&args[0], env, st)?;
-            let day = eval(&args[1], env, st)?;
-            let a = node_to_string(&action);
-            let d = node_to_string(&day);
-            let count = (env.per_day_count)(&a, &d);
-            Ok(Node::Number(count as f64))
+        Op::UsageCount => {
+            let key = eval(arg(args, 0, op)?, env, st)?;
+            let period = eval(arg(args, 1, op)?, env, st)?;
+            let key = match key.as_ref() {
+                Node::List(parts) => {
+                    charge(st, env.gas.list_item * parts.len() as i64)?;
+                    CounterKey::new(&parts.iter().map(node_str).collect::<Vec<_>>())
+                }
+                part => CounterKey::new(&[node_str(part)]),
+            };
+            charge(st, env.gas.host_call)?;
+            Ok(Cow::Owned(Node::Int((env.usage_count)(&key, &node_str(&period)))))
         }
-        "dpop_ok?" => Ok(Node::Bool((env.crypto.dpop_ok)())),
-        "merkle_ok?" => {
-            let mut evaluated = Vec::new();
+        Op::ActionIn => {
+            let action = match env.req.get("action") {
+                Some(Node::Str(action)) => Some(env.string_match.normalize(action)?),
+                _ => None,
+            };
+            let mut matched = false;
             for a in args {
-                evaluated.push(eval(a, env, st)?);
+                let globs = eval(a, env, st)?;
+                let globs = match globs.as_ref() {
+                    Node::List(items) => items.as_slice(),
+                    single => std::slice::from_ref(single),
+                };
+                for glob in globs {
+                    charge(st, env.gas.list_item)?;
+                    let Node::Str(glob) = glob else {
+                        return Err(SplError(format!("action-in expects action globs, got {glob}")));
+                    };
+                    let glob = env.string_match.normalize(glob)?;
+                    matched |= action.as_deref().is_some_and(|action| glob_matches(&glob, action));
+                }
+            }
+            boolean(matched)
+        }
+        Op::PerDayCountSelf => {
+            // Bound to the verified request so a mistyped action literal
+            // cannot silently read some other counter.
+            let field = |key: &str| match env.req.get(key) {
+                Some(Node::Str(s)) => Ok(s.as_str()),
+                _ => Err(SplError(format!("per-day-count-self requires req[\"{key}\"]"))),
+            };
+            let (action, day) = (field("action")?, field("day")?);
+            charge(st, env.gas.host_call)?;
+            Ok(Cow::Owned(Node::Int((env.usage_count)(&CounterKey::action(action), day))))
+        }
+        Op::DpopOk => {
+            charge(st, env.gas.crypto)?;
+            boolean(crypto_callback(&env.crypto.dpop_ok, op, env)?.is_some_and(|f| f()))
+        }
+        Op::MerkleOk => {
+            if let Some(hook) = &env.crypto.merkle_ok {
+                let mut evaluated = Vec::with_capacity(args.len());
+                for a in args {
+                    evaluated.push(eval(a, env, st)?.into_owned());
+                }
+                let items: i64 = evaluated.iter().map(|n| 1 + list_len(n)).sum();
+                charge(st, env.gas.crypto + env.gas.list_item * items)?;
+                return boolean(hook(&evaluated));
             }
-            Ok(Node::Bool((env.crypto.merkle_ok)(&evaluated)))
+            // Built-in form: (merkle_ok? leaf proof_var root)
+            if args.len() != 3 {
+                charge(st, env.gas.crypto)?;
+                return boolean(false);
+            }
+            let leaf = eval(&args[0], env, st)?;
+            let root = eval(&args[2], env, st)?;
+            let proof = args[1].as_name().and_then(|name| env.vars.get(name).or_else(|| env.req.get(name)));
+            charge(st, env.gas.crypto + env.gas.list_item * proof.map_or(0, list_len))?;
+            match proof.and_then(merkle_proof_steps) {
+                Some(steps) => boolean(verify_merkle_proof(&node_str(&leaf), &steps, &node_str(&root))),
+                None => boolean(false),
+            }
+        }
+        Op::VrfOk => {
+            let day = eval(arg(args, 0, op)?, env, st)?;
+            let amount = eval(arg(args, 1, op)?, env, st)?;
+            charge(st, env.gas.crypto)?;
+            boolean(crypto_callback(&env.crypto.vrf_ok, op, env)?.is_some_and(|f| f(&node_str(&day), amount.as_f64())))
         }
-        "vrf_ok?" => {
-            let day = eval(&args[0], env, st)?;
-            let amount = eval(&args[1], env, st)?;
-            let d = node_to_string(&day);
-            let a = amount.as_f64();
-            Ok(Node::Bool((env.crypto.vrf_ok)(&d, a)))
+        Op::ThreshOk => {
+            charge(st, env.gas.crypto)?;
+            boolean(crypto_callback(&env.crypto.thresh_ok, op, env)?.is_some_and(|f| f()))
+        }
+        Op::DenylistAbsent => {
+            let value = eval(arg(args, 0, op)?, env, st)?;
+            let list = eval(arg(args, 1, op)?, env, st)?;
+            let list = node_str(&list);
+            charge(st, env.gas.host_call)?;
+            let provider = env.denylists.as_ref()
+                .ok_or_else(|| SplError("no deny-list provider configured".into()))?;
+            let status = provider.status(&list)
+                .ok_or_else(|| SplError(format!("unknown deny list: {list}")))?;
+            if status.age_secs > env.denylist_max_staleness_secs {
+                return Err(SplError(format!(
+                    "deny list {list} is stale ({}s old, max {}s)",
+                    status.age_secs, env.denylist_max_staleness_secs
+                )));
+            }
+            let version = DenyListVersion { list: list.to_string(), version: status.version };
+            if !st.denylists.contains(&version) {
+                st.denylists.push(version);
+            }
+            let value = node_str(&value);
+            boolean(!provider.contains(&list, &env.string_match.normalize(&value)?))
+        }
+        Op::Obligate => {
+            let kind = eval(&args[0], env, st)?;
+            let deadline_secs = match args.get(1) {
+                Some(a) => match eval(a, env, st)?.as_ref() {
+                    Node::Int(n) if *n >= 0 => Some(*n),
+                    Node::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as i64),
+                    _ => return Err(SplError("obligate deadline must be a non-negative integer".into())),
+                },
+                None => None,
+            };
+            st.obligations.push(Obligation { kind: node_str(&kind).into_owned(), deadline_secs });
+            boolean(true)
+        }
+        Op::DenyWith => {
+            let code = eval(&args[0], env, st)?;
+            let code = node_str(&code);
+            let valid = (1..=64).contains(&code.len())
+                && code.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'));
+            if !valid {
+                return Err(SplError(format!("invalid deny-with code: {code:?}")));
+            }
+            st.reason_code.get_or_insert_with(|| code.into_owned());
+            boolean(false)
         }
-        "thresh_ok?" => Ok(Node::Bool((env.crypto.thresh_ok)())),
-        _ => Err(SplError(format!("Unknown op: {op}"))),
     }
 }
 
-fn resolve_symbol(name: &str, env: &Env) -> SplResult {
-    match name {
-        "#t" => Ok(Node::Bool(true)),
-        "#f" => Ok(Node::Bool(false)),
-        "req" => {
-            Ok(Node::Str("__req__".into()))
+fn eval_limits<'a>(limits: &PolicyLimits, env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    for range in &limits.ranges {
+        // A missing or non-numeric field fails the limit rather than reading as 0.
+        let within = match env.req.get(&range.field) {
+            Some(x) if x.as_number().is_some() => (range.min..=range.max).contains(&x.as_f64()),
+            _ => false,
+        };
+        if !within {
+            return boolean(false);
         }
-        "now" => {
-            if let Some(v) = env.vars.get("now") {
-                Ok(v.clone())
-            } else if env.strict {
-                Err(SplError(format!("Unresolved symbol: {name}")))
-            } else {
-                Ok(Node::Symbol(name.into()))
+    }
+    if let Some(max) = limits.per_day {
+        charge(st, env.gas.host_call)?;
+        let action = env.req.get("action").map(node_to_string).unwrap_or_default();
+        let day = env.req.get("day").map(node_to_string).unwrap_or_default();
+        if (env.usage_count)(&CounterKey::action(&action), &day) as f64 > max {
+            return boolean(false);
+        }
+    }
+    boolean(true)
+}
+
+/// A timestamp operand's RFC 3339 text, or an error naming `op`.
+fn timestamp_arg(node: &Node, op: Op) -> Result<&str, SplError> {
+    node.as_str()
+        .ok_or_else(|| SplError(format!("{} expects an RFC 3339 timestamp, got {node}", op.name())))
+}
+
+fn hour_arg(node: &Node) -> Result<f64, SplError> {
+    match node {
+        Node::Int(h) if (0..=24).contains(h) => Ok(*h as f64),
+        Node::Number(h) if (0.0..=24.0).contains(h) => Ok(*h),
+        _ => Err(SplError(format!("hour-between? expects hours from 0 to 24, got {node}"))),
+    }
+}
+
+fn sum<'a, A: Operand>(args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let lst = eval(arg(args, 0, Op::Sum)?, env, st)?;
+    let field = match args.get(1) {
+        Some(a) => match eval(a, env, st)?.into_owned() {
+            Node::Str(field) => Some(field),
+            other => return Err(SplError(format!("sum expects a field name string, got {other}"))),
+        },
+        None => None,
+    };
+    let items = list_arg(&lst, Op::Sum)?;
+    charge(st, env.gas.list_item * items.len() as i64)?;
+    let mut total: Option<Node> = None;
+    for item in items {
+        let value = match (&field, item) {
+            (None, value) => value,
+            (Some(field), Node::Map(entries)) => entries.get(field).unwrap_or(&Node::Nil),
+            (Some(field), other) => return Err(SplError(format!("sum cannot read field {field:?} of {other}"))),
+        };
+        total = Some(match total {
+            None if matches!(value, Node::Int(_) | Node::Number(_) | Node::Money { .. }) => value.clone(),
+            None => return Err(SplError(format!("sum expects numbers, got {value}"))),
+            Some(total) => add(&total, value)?,
+        });
+    }
+    Ok(Cow::Owned(total.unwrap_or(Node::Int(0))))
+}
+
+/// Apply a `(lambda (x) body)` predicate to each element, binding `x`.
+fn count_if<'a, A: Operand>(args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let (param, body) = arg(args, 0, Op::CountIf)?
+        .lambda()
+        .ok_or_else(|| SplError("count-if expects a (lambda (x) ...) predicate".into()))?;
+    let lst = eval(arg(args, 1, Op::CountIf)?, env, st)?;
+    let items = list_arg(&lst, Op::CountIf)?;
+    charge(st, env.gas.list_item * items.len() as i64)?;
+    let mut count = 0;
+    for item in items {
+        st.bindings.push((param.to_string(), item.clone()));
+        let matched = eval(body, env, st).map(|v| v.is_truthy());
+        st.bindings.pop();
+        count += i64::from(matched?);
+    }
+    Ok(Cow::Owned(Node::Int(count)))
+}
+
+/// `intersect`, `union`, `difference`, and `disjoint?`, comparing elements
+/// as `member` does. List results keep first-seen order without duplicates.
+fn set_op<'a, A: Operand>(op: Op, args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let a = eval(arg(args, 0, op)?, env, st)?;
+    let b = eval(arg(args, 1, op)?, env, st)?;
+    let (a, b) = (list_arg(&a, op)?, list_arg(&b, op)?);
+    charge(st, env.gas.list_item * a.len() as i64 * b.len() as i64)?;
+    let in_b = |item: &Node| b.iter().any(|candidate| node_eq(item, candidate, env.string_match));
+    if op == Op::Disjoint {
+        return boolean(!a.iter().any(in_b));
+    }
+    let candidates: Vec<&Node> = match op {
+        Op::Intersect => a.iter().filter(|item| in_b(item)).collect(),
+        Op::Difference => a.iter().filter(|item| !in_b(item)).collect(),
+        _ => a.iter().chain(b).collect(),
+    };
+    // Dropping duplicates compares each candidate with those kept.
+    charge(st, env.gas.list_item * (candidates.len() as i64).pow(2))?;
+    let mut result: Vec<Node> = Vec::new();
+    for item in candidates {
+        if !result.iter().any(|seen| node_eq(seen, item, env.string_match)) {
+            result.push(item.clone());
+        }
+    }
+    env.limits.check_list(result.len())?;
+    Ok(Cow::Owned(Node::List(result)))
+}
+
+/// `(all x list expr)` / `(any x list expr)`: evaluate `expr` with `x`
+/// bound to each element, stopping at the first that decides the result.
+fn quantify<'a, A: Operand>(op: Op, args: &'a [A], env: &'a Env, st: &mut EvalState) -> EvalResult<'a> {
+    let param = arg(args, 0, op)?
+        .as_symbol()
+        .and_then(bindable)
+        .ok_or_else(|| SplError(format!("{} expects a symbol to bind", op.name())))?;
+    let lst = eval(arg(args, 1, op)?, env, st)?;
+    let body = arg(args, 2, op)?;
+    let items = list_arg(&lst, op)?;
+    charge(st, env.gas.list_item * items.len() as i64)?;
+    let want = op == Op::Any;
+    for item in items {
+        st.bindings.push((param.to_string(), item.clone()));
+        let value = eval(body, env, st).map(|v| v.is_truthy());
+        st.bindings.pop();
+        if value? == want {
+            return boolean(want);
+        }
+    }
+    boolean(!want)
+}
+
+/// The elements of a list argument; `nil`, a missing field, has none.
+fn list_arg(node: &Node, op: Op) -> Result<&[Node], SplError> {
+    match node {
+        Node::List(items) => Ok(items),
+        Node::Nil => Ok(&[]),
+        other => Err(SplError(format!("{} expects a list, got {other}", op.name()))),
+    }
+}
+
+/// `a + b` for `sum`: Ints stay exact until a Number joins them, and money
+/// adds only to money in the same currency.
+fn add(a: &Node, b: &Node) -> Result<Node, SplError> {
+    let overflow = || SplError("sum overflowed".into());
+    match (a, b) {
+        (Node::Int(x), Node::Int(y)) => x.checked_add(*y).map(Node::Int).ok_or_else(overflow),
+        (Node::Money { minor_units: x, currency: cx }, Node::Money { minor_units: y, currency: cy }) => {
+            if cx != cy {
+                return Err(SplError(format!("cannot add {cx} to {cy}")));
+            }
+            let minor_units = x.checked_add(*y).ok_or_else(overflow)?;
+            Ok(Node::Money { minor_units, currency: cx.clone() })
+        }
+        (Node::Money { currency, .. }, other) | (other, Node::Money { currency, .. }) => {
+            Err(SplError(format!("cannot add {currency} money to {other}")))
+        }
+        (Node::Int(_) | Node::Number(_), Node::Int(_) | Node::Number(_)) => Ok(Node::Number(a.as_f64() + b.as_f64())),
+        (_, other) => Err(SplError(format!("sum expects numbers, got {other}"))),
+    }
+}
+
+/// Positional argument access that reports a missing argument instead of panicking.
+fn arg<A>(args: &[A], i: usize, op: Op) -> Result<&A, SplError> {
+    args.get(i)
+        .ok_or_else(|| SplError(format!("{} expects at least {} arguments", op.name(), i + 1)))
+}
+
+/// Decode a proof given as a list of `{"hash" h "position" p}` maps or
+/// `(h p)` pairs. Any malformed step rejects the whole proof.
+fn merkle_proof_steps(node: &Node) -> Option<Vec<MerkleProofStep>> {
+    let Node::List(items) = node else { return None };
+    items
+        .iter()
+        .map(|step| {
+            let (hash, position) = match step {
+                Node::Map(m) => (m.get("hash")?, m.get("position")?),
+                Node::List(pair) if pair.len() == 2 => (&pair[0], &pair[1]),
+                _ => return None,
+            };
+            match (hash, position) {
+                (Node::Str(h), Node::Str(p)) if p == "left" || p == "right" => {
+                    Some(MerkleProofStep { hash: h.clone(), position: p.clone() })
+                }
+                _ => None,
             }
+        })
+        .collect()
+}
+
+/// A request or map field, or `nil`. Values were checked against
+/// `env.limits` when they entered the evaluation.
+pub(crate) fn lookup(found: Option<&Node>) -> EvalResult<'_> {
+    Ok(found.map_or(Cow::Owned(Node::Nil), Cow::Borrowed))
+}
+
+pub(crate) fn resolve_symbol<'a>(name: &'a str, env: &'a Env, st: &EvalState) -> EvalResult<'a> {
+    if let Some((_, value)) = st.bindings.iter().rev().find(|(param, _)| param == name) {
+        return Ok(Cow::Owned(value.clone()));
+    }
+    match name {
+        "#t" => boolean(true),
+        "#f" => boolean(false),
+        "req" => {
+            Ok(Cow::Owned(Node::Str("__req__".into())))
         }
+        "now" => match env.vars.get(name) {
+            Some(_) if env.strict => Err(SplError("now must come from the evaluation clock, not vars".into())),
+            Some(v) => Ok(Cow::Borrowed(v)),
+            None => Ok(Cow::Owned(Node::Str(format_rfc3339(env.clock.now_unix())))),
+        },
         _ => {
             if let Some(v) = env.vars.get(name) {
-                Ok(v.clone())
+                Ok(Cow::Borrowed(v))
             } else if env.strict {
                 Err(SplError(format!("Unresolved symbol: {name}")))
             } else {
-                Ok(Node::Symbol(name.into()))
+                Ok(Cow::Owned(Node::Symbol(name.into())))
             }
         }
     }
 }
 
-fn node_eq(a: &Node, b: &Node) -> bool {
+fn node_eq(a: &Node, b: &Node, strings: StringMatch) -> bool {
     match (a, b) {
         (Node::Bool(x), Node::Bool(y)) => x == y,
         (Node::Number(x), Node::Number(y)) => x == y,
-        (Node::Str(x), Node::Str(y)) => x == y,
+        (Node::Int(_) | Node::Number(_), Node::Int(_) | Node::Number(_)) => compare_numbers(a, b) == Some(Ordering::Equal),
+        (Node::Str(x), Node::Str(y)) => strings.eq(x, y),
         (Node::Symbol(x), Node::Symbol(y)) => x == y,
-        (Node::Str(x), Node::Symbol(y)) | (Node::Symbol(x), Node::Str(y)) => x == y,
+        (Node::Str(x), Node::Symbol(y)) | (Node::Symbol(x), Node::Str(y)) => strings.eq(x, y),
+        (Node::Map(x), Node::Map(y)) => x == y,
+        (Node::Money { minor_units: x, currency: cx }, Node::Money { minor_units: y, currency: cy }) => x == y && cx == cy,
         (Node::Nil, Node::Nil) => true,
-        _ => node_to_string(a) == node_to_string(b),
+        _ => node_str(a) == node_str(b),
+    }
+}
+
+/// Order two values for `<`/`<=`/`>`/`>=`/`in-range`. Money compares only
+/// with money in the same currency; anything else compares as numbers, with
+/// non-numbers as 0.
+fn compare(a: &Node, b: &Node) -> Result<Option<Ordering>, SplError> {
+    match (a, b) {
+        (Node::Money { minor_units: x, currency: cx }, Node::Money { minor_units: y, currency: cy }) => {
+            if cx != cy {
+                return Err(SplError(format!("cannot compare {cx} with {cy}")));
+            }
+            Ok(Some(x.cmp(y)))
+        }
+        (Node::Money { currency, .. }, other) | (other, Node::Money { currency, .. }) => {
+            Err(SplError(format!("cannot compare {currency} money with {other}; wrap amounts in (money ...)")))
+        }
+        _ => Ok(compare_numbers(a, b)),
+    }
+}
+
+/// Numeric order without rounding Ints through f64, so ids and counters
+/// beyond 2^53 compare exactly. `None` when either side is NaN.
+fn compare_numbers(a: &Node, b: &Node) -> Option<Ordering> {
+    match (a, b) {
+        (Node::Int(x), Node::Int(y)) => Some(x.cmp(y)),
+        (Node::Int(x), other) => compare_int_float(*x, other.as_f64()),
+        (other, Node::Int(y)) => compare_int_float(*y, other.as_f64()).map(Ordering::reverse),
+        _ => a.as_f64().partial_cmp(&b.as_f64()),
+    }
+}
+
+fn compare_int_float(i: i64, f: f64) -> Option<Ordering> {
+    const TWO_63: f64 = 9_223_372_036_854_775_808.0;
+    if f.is_nan() {
+        return None;
+    }
+    if f >= TWO_63 {
+        return Some(Ordering::Less);
+    }
+    if f < -TWO_63 {
+        return Some(Ordering::Greater);
+    }
+    // Here f.trunc() fits in i64; break ties on the fractional part.
+    let whole = f.trunc() as i64;
+    Some(i.cmp(&whole).then_with(|| 0.0.partial_cmp(&f.fract()).unwrap_or(Ordering::Equal)))
+}
+
+/// String view of a node, borrowing for strings and symbols.
+fn node_str(node: &Node) -> Cow<'_, str> {
+    match node {
+        Node::Str(s) | Node::Symbol(s) => Cow::Borrowed(s),
+        _ => Cow::Owned(node_to_string(node)),
     }
 }
 
@@ -220,10 +1258,11 @@ fn node_to_string(node: &Node) -> String {
     match node {
         Node::Bool(true) => "true".into(),
         Node::Bool(false) => "false".into(),
+        Node::Int(i) => i.to_string(),
         Node::Number(n) => format!("{n}"),
         Node::Str(s) => s.clone(),
         Node::Symbol(s) => s.clone(),
         Node::Nil => "nil".into(),
-        Node::List(_) => format!("{node}"),
+        Node::List(_) | Node::Map(_) | Node::Money { .. } => format!("{node}"),
     }
 }
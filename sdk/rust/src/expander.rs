@@ -0,0 +1,212 @@
+//! Authoring macros that expand to core SPL.
+//!
+//! A [`MacroRegistry`] maps short forms such as `(amount<= 50)` to SPL
+//! templates and rewrites a policy until no macro call remains. Expansion
+//! happens before minting, so tokens carry core SPL and every verifier
+//! evaluates them without knowing the macros.
+//!
+//! Hygiene rules:
+//! - A macro may not take the name of an operator or reserved symbol
+//!   (`policy`, `limits`, `lambda`, `req`, `#t`, `#f`).
+//! - Arguments are substituted for parameters once; symbols inside an
+//!   argument are never mistaken for the template's parameters.
+//! - An argument may not mention a symbol the template binds with
+//!   `lambda`, `all`, or `any`, since the binding would capture it.
+//! - `(limits ...)` blocks are data and are left alone.
+//! - Expansion stops with an error after [`MAX_EXPANSION_DEPTH`] nested
+//!   rewrites, so recursive macros cannot loop, and once it has built more
+//!   nodes than the registry's [`Limits`] allow a policy to hold, so macros
+//!   that duplicate their arguments cannot grow a policy exponentially.
+//! - The expanded policy must itself fit the registry's [`Limits`].
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::evaluator::bound_param;
+use crate::ops::Op;
+use crate::parser;
+use crate::types::{Limits, Node, SplError};
+
+/// Most nested macro rewrites in one expansion.
+pub const MAX_EXPANSION_DEPTH: usize = 32;
+
+const RESERVED: &[&str] = &["policy", "limits", "lambda", "req", "#t", "#f"];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Macro {
+    params: Vec<String>,
+    template: Node,
+    /// Symbols the template binds, which arguments may not mention.
+    binds: BTreeSet<String>,
+}
+
+/// Named macros and their templates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MacroRegistry {
+    macros: HashMap<String, Macro>,
+    limits: Limits,
+}
+
+impl MacroRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bound parsing and expansion by `limits` instead of the defaults.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The built-in idioms:
+    ///
+    /// - `(amount<= n)` → `(<= (get req "amount") n)`
+    /// - `(recipient-in list)` → `(member (get req "recipient") list)`
+    /// - `(max-uses-per-day n)` → `(< (per-day-count-self) n)`
+    /// - `(expires-before "T")` → `(before now "T")`
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        for (name, param, template) in [
+            ("amount<=", "n", r#"(<= (get req "amount") n)"#),
+            ("recipient-in", "list", r#"(member (get req "recipient") list)"#),
+            ("max-uses-per-day", "n", "(< (per-day-count-self) n)"),
+            ("expires-before", "t", "(before now t)"),
+        ] {
+            registry.define(name, &[param], template).expect("standard macros are valid");
+        }
+        registry
+    }
+
+    /// Define or replace the macro `name`, whose calls take one argument
+    /// per parameter and expand to `template` with the arguments substituted.
+    pub fn define(&mut self, name: &str, params: &[&str], template: &str) -> Result<(), SplError> {
+        if !matches!(parser::parse(name)?, Node::Symbol(ref s) if s == name) {
+            return Err(SplError(format!("invalid macro name: {name:?}")));
+        }
+        if Op::from_name(name).is_some() || RESERVED.contains(&name) {
+            return Err(SplError(format!("macro {name} would shadow a built-in")));
+        }
+        let mut seen = BTreeSet::new();
+        for param in params {
+            if !matches!(parser::parse(param)?, Node::Symbol(ref s) if s == param) || RESERVED.contains(param) {
+                return Err(SplError(format!("invalid parameter {param:?} for macro {name}")));
+            }
+            if !seen.insert(*param) {
+                return Err(SplError(format!("duplicate parameter {param} for macro {name}")));
+            }
+        }
+        let template = parser::parse(template)?;
+        let mut binds = BTreeSet::new();
+        collect_bindings(&template, &mut binds);
+        let params = params.iter().map(|p| p.to_string()).collect();
+        self.macros.insert(name.to_string(), Macro { params, template, binds });
+        Ok(())
+    }
+
+    /// Whether `name` is a defined macro.
+    pub fn contains(&self, name: &str) -> bool {
+        self.macros.contains_key(name)
+    }
+
+    /// Rewrite every macro call in `ast` into core SPL.
+    pub fn expand(&self, ast: &Node) -> Result<Node, SplError> {
+        // Every node prints as at least one byte, so a policy with more
+        // nodes than `max_policy_bytes` could never be accepted.
+        let mut budget = self.limits.max_policy_bytes;
+        let expanded = self.expand_at(ast, 0, &mut budget)?;
+        self.limits.check_value(&expanded)?;
+        if expanded.to_string().len() > self.limits.max_policy_bytes {
+            return Err(SplError(format!(
+                "expanded policy exceeds maximum size of {} bytes",
+                self.limits.max_policy_bytes
+            )));
+        }
+        Ok(expanded)
+    }
+
+    /// Parse one expression and expand it.
+    pub fn parse(&self, src: &str) -> Result<Node, SplError> {
+        self.expand(&parser::parse_with_limits(src, &self.limits)?)
+    }
+
+    /// Parse every top-level expression, e.g. named clauses, and expand each.
+    pub fn parse_all(&self, src: &str) -> Result<Vec<Node>, SplError> {
+        parser::parse_all_with_limits(src, &self.limits)?.iter().map(|expr| self.expand(expr)).collect()
+    }
+
+    fn expand_at(&self, node: &Node, depth: usize, budget: &mut usize) -> Result<Node, SplError> {
+        let Node::List(items) = node else { return Ok(node.clone()) };
+        match items.split_first() {
+            Some((Node::Symbol(head), _)) if head == "limits" => Ok(node.clone()),
+            Some((Node::Symbol(head), args)) if self.macros.contains_key(head) => {
+                if depth >= MAX_EXPANSION_DEPTH {
+                    return Err(SplError(format!("macro expansion deeper than {MAX_EXPANSION_DEPTH} levels")));
+                }
+                let expanded = self.apply(head, args)?;
+                *budget = budget.checked_sub(node_count(&expanded)).ok_or_else(|| {
+                    SplError(format!(
+                        "macro expansion exceeds {} nodes",
+                        self.limits.max_policy_bytes
+                    ))
+                })?;
+                self.expand_at(&expanded, depth + 1, budget)
+            }
+            _ => Ok(Node::List(
+                items.iter().map(|item| self.expand_at(item, depth, budget)).collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+
+    fn apply(&self, name: &str, args: &[Node]) -> Result<Node, SplError> {
+        let m = &self.macros[name];
+        if args.len() != m.params.len() {
+            return Err(SplError(format!("{name} expects {} argument(s), got {}", m.params.len(), args.len())));
+        }
+        for arg in args {
+            let mut symbols = BTreeSet::new();
+            collect_symbols(arg, &mut symbols);
+            if let Some(captured) = symbols.intersection(&m.binds).next() {
+                return Err(SplError(format!("argument to {name} would be captured by its binding of {captured}")));
+            }
+        }
+        let bindings: HashMap<&str, &Node> = m.params.iter().map(String::as_str).zip(args).collect();
+        Ok(substitute(&m.template, &bindings))
+    }
+}
+
+fn substitute(node: &Node, bindings: &HashMap<&str, &Node>) -> Node {
+    match node {
+        Node::Symbol(s) => bindings.get(s.as_str()).map_or_else(|| node.clone(), |arg| (*arg).clone()),
+        Node::List(items) => Node::List(items.iter().map(|item| substitute(item, bindings)).collect()),
+        _ => node.clone(),
+    }
+}
+
+fn node_count(node: &Node) -> usize {
+    match node {
+        Node::List(items) => 1 + items.iter().map(node_count).sum::<usize>(),
+        Node::Map(entries) => 1 + entries.values().map(node_count).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+fn collect_bindings(node: &Node, out: &mut BTreeSet<String>) {
+    if let Some((param, _)) = bound_param(node) {
+        out.insert(param.to_string());
+    }
+    if let Node::List(items) = node {
+        for item in items {
+            collect_bindings(item, out);
+        }
+    }
+}
+
+fn collect_symbols(node: &Node, out: &mut BTreeSet<String>) {
+    match node {
+        Node::Symbol(s) => {
+            out.insert(s.clone());
+        }
+        Node::List(items) => items.iter().for_each(|item| collect_symbols(item, out)),
+        _ => {}
+    }
+}
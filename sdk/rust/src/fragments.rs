@@ -0,0 +1,100 @@
+//! Reusable policy fragments pulled in with `(include "name")`.
+//!
+//! A [`FragmentResolver`] maps fragment names such as
+//! `"standard-kyc-checks"` to SPL source. [`expand`] splices each fragment in
+//! place of its include, recursively, rejecting cycles and nesting deeper
+//! than [`MAX_INCLUDE_DEPTH`].
+//!
+//! A token's signature covers only the `(include ...)` text, so tokens
+//! minted with [`crate::token::mint_with_fragments`] also sign the
+//! [`resolved_hash`] of the expanded policy. Verification expands again with
+//! the verifier's resolver and rejects the token if any fragment changed.
+
+use std::collections::HashMap;
+
+use crate::crypto::sha256_hex;
+use crate::parser::parse;
+use crate::types::{Node, SplError};
+
+/// Deepest chain of fragments including fragments.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Most includes expanded for one policy, bounding the size of diamonds
+/// where fragments share sub-fragments.
+pub const MAX_INCLUDES: usize = 64;
+
+/// Looks up fragment source by name.
+pub trait FragmentResolver {
+    /// Source of the fragment called `name`, or `None` if there is none.
+    fn resolve(&self, name: &str) -> Result<Option<String>, SplError>;
+}
+
+impl FragmentResolver for HashMap<String, String> {
+    fn resolve(&self, name: &str) -> Result<Option<String>, SplError> {
+        Ok(self.get(name).cloned())
+    }
+}
+
+/// Parse `src` and expand its includes.
+pub fn parse_with_fragments(src: &str, resolver: &dyn FragmentResolver) -> Result<Node, SplError> {
+    expand(&parse(src)?, resolver)
+}
+
+/// Replace every `(include "name")` in `ast` with the named fragment,
+/// itself expanded.
+pub fn expand(ast: &Node, resolver: &dyn FragmentResolver) -> Result<Node, SplError> {
+    Expander { resolver, stack: Vec::new(), expansions: 0 }.expand(ast)
+}
+
+/// Whether `ast` contains an `(include ...)` form.
+pub fn has_includes(ast: &Node) -> bool {
+    match ast {
+        Node::List(items) => items.first() == Some(&Node::Symbol("include".into())) || items.iter().any(has_includes),
+        _ => false,
+    }
+}
+
+/// SHA-256 hex of expanded policy expressions in canonical form.
+pub fn resolved_hash(exprs: &[Node]) -> String {
+    let canonical: Vec<String> = exprs.iter().map(Node::to_string).collect();
+    sha256_hex(canonical.join("\n").as_bytes())
+}
+
+struct Expander<'a> {
+    resolver: &'a dyn FragmentResolver,
+    /// Fragments being expanded, outermost first.
+    stack: Vec<String>,
+    expansions: usize,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, node: &Node) -> Result<Node, SplError> {
+        let Node::List(items) = node else { return Ok(node.clone()) };
+        if items.first() == Some(&Node::Symbol("include".into())) {
+            let [_, Node::Str(name)] = items.as_slice() else {
+                return Err(SplError(format!("include expects one fragment name, got {node}")));
+            };
+            return self.include(name);
+        }
+        items.iter().map(|item| self.expand(item)).collect::<Result<_, _>>().map(Node::List)
+    }
+
+    fn include(&mut self, name: &str) -> Result<Node, SplError> {
+        if self.stack.iter().any(|n| n == name) {
+            return Err(SplError(format!("include cycle: {} -> {name}", self.stack.join(" -> "))));
+        }
+        if self.stack.len() >= MAX_INCLUDE_DEPTH {
+            return Err(SplError(format!("includes nested deeper than {MAX_INCLUDE_DEPTH}")));
+        }
+        self.expansions += 1;
+        if self.expansions > MAX_INCLUDES {
+            return Err(SplError(format!("policy expands more than {MAX_INCLUDES} includes")));
+        }
+        let src = self.resolver.resolve(name)?.ok_or_else(|| SplError(format!("unknown fragment: {name:?}")))?;
+        let ast = parse(&src).map_err(|e| SplError(format!("fragment {name:?}: {e}")))?;
+        self.stack.push(name.to_string());
+        let expanded = self.expand(&ast);
+        self.stack.pop();
+        expanded
+    }
+}
@@ -0,0 +1,228 @@
+//! Fuzzing entrypoints for the parser and evaluator.
+//!
+//! [`check_parse`] and [`check_eval`] assert the invariants every input must
+//! satisfy and panic on a violation, so `cargo fuzz` targets (see `fuzz/`)
+//! and property tests share one definition of "correct". [`AstGen`] turns
+//! arbitrary bytes into well-formed policy ASTs over the built-in operators,
+//! reaching the evaluator far more often than raw source does.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::compiled::CompiledPolicy;
+use crate::evaluator::{eval_policy_detailed, fold_constants, partial_eval, KnownBindings};
+use crate::parser::{format_policy, parse, parse_all};
+use crate::types::{Env, Node};
+
+/// Every operator name, plus `policy` and an unknown symbol.
+const HEADS: &[&str] = &[
+    "and", "or", "not", "=", "=hash", "<=", "<", ">=", ">", "in-range", "limits", "member", "in", "subset?",
+    "intersect", "union", "difference", "disjoint?",
+    "before", "weekday?", "hour-between?", "during", "get", "limit-for", "spent-for", "cumulative-spend", "remaining-for", "tuple", "length", "sum",
+    "count-if", "lambda", "all", "any", "per-day-count", "usage-count",
+    "per-day-count-self", "dpop_ok?", "merkle_ok?", "vrf_ok?", "thresh_ok?", "denylist-absent?",
+    "obligate", "deny-with", "money", "convert", "action-in", "ip-in-cidr", "country-in", "attested?", "policy", "no-such-op",
+];
+
+const FIELDS: &[&str] = &["action", "amount", "recipient", "day", "actor_pub", "missing"];
+const SYMBOLS: &[&str] = &["req", "now", "allowed_recipients", "limits", "undefined_var"];
+const STRINGS: &[&str] = &[
+    "", "read", "payments.create", "alice", "2026-01-01", "2026-01-01T00:00:00Z", "2026-01-03T22:30:00+09:00", "USD", "JPY", "19.99",
+];
+const NUMBERS: &[f64] = &[0.0, -1.0, 1.0, 50.0, 100.0, 1e9, -0.5, f64::MAX];
+
+/// Deterministic generator of policy ASTs from a byte string. Running out of
+/// bytes ends generation with `#f`, so every input yields a finite tree.
+pub struct AstGen<'a> {
+    data: &'a [u8],
+    pos: usize,
+    max_depth: usize,
+}
+
+impl<'a> AstGen<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, max_depth: 8 }
+    }
+
+    /// Limit nesting; deeper positions only produce atoms.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let b = self.data.get(self.pos).copied();
+        self.pos += 1;
+        b
+    }
+
+    fn pick<T: Copy>(&mut self, choices: &[T]) -> Option<T> {
+        self.byte().map(|b| choices[b as usize % choices.len()])
+    }
+
+    /// The next expression.
+    pub fn node(&mut self) -> Node {
+        self.expr(0)
+    }
+
+    fn expr(&mut self, depth: usize) -> Node {
+        let Some(kind) = self.byte() else { return Node::Bool(false) };
+        if depth >= self.max_depth {
+            return self.atom(kind);
+        }
+        match kind % 8 {
+            0..=3 => self.call(depth),
+            4 => match self.pick(FIELDS) {
+                Some(field) => Node::List(vec![Node::Symbol("get".into()), Node::Symbol("req".into()), Node::Str(field.into())]),
+                None => Node::Bool(false),
+            },
+            _ => self.atom(kind),
+        }
+    }
+
+    fn call(&mut self, depth: usize) -> Node {
+        let Some(head) = self.pick(HEADS) else { return Node::Bool(true) };
+        // Arity 0..=4 regardless of the operator, so wrong arities are hit too.
+        let arity = self.byte().map_or(0, |b| b % 5);
+        let mut items = vec![Node::Symbol(head.into())];
+        items.extend((0..arity).map(|_| self.expr(depth + 1)));
+        Node::List(items)
+    }
+
+    fn atom(&mut self, kind: u8) -> Node {
+        match kind % 6 {
+            0 => Node::Bool(kind & 0x80 != 0),
+            1 => self.pick(NUMBERS).map_or(Node::Bool(false), Node::Number),
+            2 => self.byte().map_or(Node::Bool(false), |b| Node::Int(i64::from(b as i8))),
+            3 => self.pick(STRINGS).map_or(Node::Bool(false), |s| Node::Str(s.into())),
+            4 => self.pick(SYMBOLS).map_or(Node::Bool(false), |s| Node::Symbol(s.into())),
+            _ => Node::List(Vec::new()),
+        }
+    }
+}
+
+/// Request, vars, and host callbacks the generated ASTs refer to.
+pub fn fuzz_env(max_gas: i64) -> Env {
+    let mut req = HashMap::new();
+    req.insert("action".into(), Node::Str("payments.create".into()));
+    req.insert("amount".into(), Node::Number(50.0));
+    req.insert("recipient".into(), Node::Str("alice".into()));
+    req.insert("day".into(), Node::Str("2026-01-01".into()));
+
+    let mut vars = HashMap::new();
+    vars.insert("now".into(), Node::Str("2026-01-01T00:00:00Z".into()));
+    vars.insert(
+        "allowed_recipients".into(),
+        Node::List(vec![Node::Str("alice".into()), Node::Str("bob".into())]),
+    );
+    vars.insert("limits".into(), Node::Map(BTreeMap::from([("alice".to_string(), Node::Number(75.0))])));
+
+    Env {
+        req,
+        vars,
+        usage_count: Arc::new(|_, _| 3),
+        spent_for: Arc::new(|_| 20.0),
+        max_gas,
+        ..Env::default()
+    }
+}
+
+/// Parse arbitrary source. Must not panic; whatever parses must survive
+/// formatting with an unchanged AST.
+pub fn check_parse(src: &str) {
+    let single = parse(src);
+    let Ok(exprs) = parse_all(src) else {
+        assert!(single.is_err(), "parse accepted what parse_all rejected: {src:?}");
+        return;
+    };
+    if let Ok(ast) = &single {
+        assert_eq!(exprs.len(), 1, "parse accepted several expressions: {src:?}");
+        assert_eq!(format!("{ast:?}"), format!("{:?}", exprs[0]));
+    }
+    // Some values do not print back to source, so only compare ASTs that
+    // Display can represent.
+    if exprs.iter().all(printable) {
+        let formatted = format_policy(src).expect("parsed source formats");
+        let reparsed = parse_all(&formatted).expect("formatted source parses");
+        assert_eq!(format!("{reparsed:?}"), format!("{exprs:?}"), "format changed {src:?}");
+    }
+}
+
+fn printable(node: &Node) -> bool {
+    match node {
+        Node::Str(_) => true,
+        Node::Number(n) => n.is_finite(),
+        Node::Int(_) => true,
+        Node::Symbol(s) => !s.is_empty() && !s.starts_with('"'),
+        Node::List(items) => items.iter().all(printable),
+        Node::Bool(_) => true,
+        // `nil` reads back as a symbol.
+        Node::Map(_) | Node::Money { .. } | Node::Nil => false,
+    }
+}
+
+/// Evaluate `ast` under [`fuzz_env`] with `max_gas`. Must not panic, must
+/// never report more gas than the budget, must give the same result on a
+/// second run, must agree with its constant-folded and partially
+/// evaluated forms, and, when it compiles, must give exactly the same
+/// outcome compiled.
+pub fn check_eval(ast: &Node, max_gas: i64) {
+    let env = fuzz_env(max_gas);
+    let first = eval_policy_detailed(ast, &env);
+    let second = eval_policy_detailed(ast, &env);
+    assert_eq!(summary(&first), summary(&second), "evaluation is not deterministic: {ast}");
+    if let Ok(compiled) = CompiledPolicy::compile(ast) {
+        assert_eq!(summary(&compiled.eval_detailed(&env)), summary(&first), "compiled evaluation differs: {ast}");
+    }
+
+    if let Ok(outcome) = &first {
+        assert!(
+            (0..=max_gas).contains(&outcome.gas_used),
+            "gas_used {} outside budget {max_gas}: {ast}",
+            outcome.gas_used
+        );
+        // The exact budget that succeeded must still succeed.
+        let exact = eval_policy_detailed(ast, &fuzz_env(outcome.gas_used));
+        assert_eq!(summary(&exact), summary(&first), "result depends on unused gas: {ast}");
+        // Folding may only make a successful evaluation cheaper.
+        let folded = eval_policy_detailed(&fold_constants(ast), &env);
+        assert_eq!(
+            folded.as_ref().map(|o| o.value.is_truthy()).ok(),
+            Some(outcome.value.is_truthy()),
+            "constant folding changed the decision: {ast}"
+        );
+        // Specializing on the request and vars it is evaluated with keeps the
+        // decision; the pinned request fields may cost extra gas.
+        let known = KnownBindings { req: env.req.clone(), vars: env.vars.clone() };
+        let specialized = eval_policy_detailed(&partial_eval(ast, &known), &fuzz_env(max_gas + 10_000));
+        assert_eq!(
+            specialized.as_ref().map(|o| o.value.is_truthy()).ok(),
+            Some(outcome.value.is_truthy()),
+            "partial evaluation changed the decision: {ast}"
+        );
+    }
+}
+
+fn summary(result: &Result<crate::evaluator::EvalOutcome, crate::types::SplError>) -> String {
+    match result {
+        Ok(outcome) => format!("ok {:?} gas={} obligations={:?}", outcome.value, outcome.gas_used, outcome.obligations),
+        Err(e) => format!("err {}", e.0),
+    }
+}
+
+/// Fuzz-target body: treat `data` as source when it is UTF-8, and always as
+/// a generator seed for an AST to evaluate.
+pub fn fuzz_one(data: &[u8]) {
+    if let Ok(src) = std::str::from_utf8(data) {
+        check_parse(src);
+        if let Ok(ast) = parse(src) {
+            check_eval(&ast, 10_000);
+        }
+    }
+    let ast = AstGen::new(data).node();
+    check_eval(&ast, 10_000);
+    if printable(&ast) {
+        let reparsed = parse(&ast.to_string()).expect("generated AST prints to valid source");
+        assert_eq!(format!("{reparsed:?}"), format!("{ast:?}"));
+    }
+}
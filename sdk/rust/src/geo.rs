@@ -0,0 +1,72 @@
+//! Network and location checks behind `ip-in-cidr` and `country-in`.
+//!
+//! Policy literals are validated strictly, so a typo such as `10.1.0.0/8`
+//! (host bits set) or `"USA"` is an error rather than a rule that never
+//! matches. Request values are not: an address or country that does not
+//! parse simply fails the check.
+
+use std::net::IpAddr;
+
+use crate::types::SplError;
+
+/// An IPv4 or IPv6 CIDR block such as `10.0.0.0/8` or `2001:db8::/32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parse `address/prefix`. The prefix must fit the address family and
+    /// the address must have no bits set past it.
+    pub fn parse(s: &str) -> Result<Self, SplError> {
+        let invalid = || SplError(format!("invalid CIDR block: {s:?}"));
+        let (address, prefix) = s.split_once('/').ok_or_else(invalid)?;
+        if prefix.is_empty() || prefix.len() > 3 || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let network: IpAddr = address.parse().map_err(|_| invalid())?;
+        let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+        let cidr = Cidr { network, prefix };
+        if prefix > cidr.bits() {
+            return Err(invalid());
+        }
+        if cidr.masked(network) != Some(network) {
+            return Err(SplError(format!("CIDR block {s:?} has host bits set")));
+        }
+        Ok(cidr)
+    }
+
+    /// Whether `ip` falls in the block. IPv4-mapped IPv6 addresses
+    /// (`::ffff:10.0.0.1`) are compared as IPv4.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.masked(ip.to_canonical()) == Some(self.network)
+    }
+
+    fn bits(&self) -> u8 {
+        match self.network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// `ip` with the bits past the prefix cleared, if it is of the block's family.
+    fn masked(&self, ip: IpAddr) -> Option<IpAddr> {
+        let host_bits = u32::from(self.bits() - self.prefix);
+        match (self.network, ip) {
+            (IpAddr::V4(_), IpAddr::V4(ip)) => {
+                Some(IpAddr::V4((u32::from(ip) & u32::MAX.checked_shl(host_bits).unwrap_or(0)).into()))
+            }
+            (IpAddr::V6(_), IpAddr::V6(ip)) => {
+                Some(IpAddr::V6((u128::from(ip) & u128::MAX.checked_shl(host_bits).unwrap_or(0)).into()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `s` as an ISO 3166-1 alpha-2 country code, uppercased, if it is two
+/// ASCII letters.
+pub fn country_code(s: &str) -> Option<String> {
+    (s.len() == 2 && s.bytes().all(|b| b.is_ascii_alphabetic())).then(|| s.to_ascii_uppercase())
+}
@@ -0,0 +1,96 @@
+//! tonic interceptor that verifies an agent's token before an RPC reaches
+//! the service.
+//!
+//! [`AgentSafeInterceptor`] reads the token from `authorization` metadata,
+//! as [`crate::middleware::AgentSafeLayer::grpc`] does, and verifies it
+//! against a `req` built from metadata and fixed fields. Allowed calls
+//! reach the service with the [`crate::token::VerifyTokenResult`] in the
+//! request extensions; rejections are `UNAUTHENTICATED` or
+//! `PERMISSION_DENIED` statuses carrying any `deny-with` code in
+//! [`crate::middleware::REASON_CODE_METADATA`] metadata:
+//!
+//! ```text
+//! let interceptor = AgentSafeInterceptor::new(Verifier::default)
+//!     .set("action", "payments.Payments")
+//!     .metadata("merchant", "x-merchant");
+//! Server::builder().add_service(PaymentsServer::with_interceptor(payments, interceptor))
+//! ```
+//!
+//! tonic does not show interceptors the RPC method or the message, so
+//! `req["action"]` is whatever [`AgentSafeInterceptor::set`] fixes for the
+//! wrapped service. Policies that tell methods apart need
+//! [`crate::middleware::AgentSafeLayer::grpc`], which sees the request path.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::middleware::{token_from, Rejection};
+use crate::profile::Verifier;
+use crate::types::Node;
+
+type MakeVerifier = dyn Fn() -> Verifier + Send + Sync;
+
+/// Interceptor verifying each call's token.
+///
+/// Like [`crate::middleware::AgentSafeLayer`], it builds a [`Verifier`] per
+/// call with `make_verifier`; share stores and trusted issuers by capturing
+/// `Arc`s in the closure.
+#[derive(Clone)]
+pub struct AgentSafeInterceptor {
+    make_verifier: Arc<MakeVerifier>,
+    fixed: HashMap<String, Node>,
+    fields: Vec<(String, String)>,
+    vars: HashMap<String, Node>,
+}
+
+impl AgentSafeInterceptor {
+    pub fn new(make_verifier: impl Fn() -> Verifier + Send + Sync + 'static) -> Self {
+        Self { make_verifier: Arc::new(make_verifier), fixed: HashMap::new(), fields: Vec::new(), vars: HashMap::new() }
+    }
+
+    /// Set `req` field `name` to `value` for every call.
+    pub fn set(mut self, name: &str, value: impl Into<Node>) -> Self {
+        self.fixed.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Set `req` field `name` from the ASCII metadata entry `key`; calls
+    /// without it leave the field unset. Later calls for the same name win.
+    pub fn metadata(mut self, name: &str, key: &str) -> Self {
+        self.fields.retain(|(n, _)| n != name);
+        self.fields.push((name.to_string(), key.to_ascii_lowercase()));
+        self
+    }
+
+    /// Vars passed to every verification.
+    pub fn vars(mut self, vars: HashMap<String, Node>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    fn request<T>(&self, request: &Request<T>) -> HashMap<String, Node> {
+        let mut req = self.fixed.clone();
+        for (name, key) in &self.fields {
+            if let Some(value) = request.metadata().get(key.as_str()).and_then(|v| v.to_str().ok()) {
+                req.insert(name.clone(), Node::from(value));
+            }
+        }
+        req
+    }
+}
+
+impl Interceptor for AgentSafeInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let token = token_from(request.metadata().as_ref()).map_err(Rejection::into_status)?;
+        let req = self.request(&request);
+        let result = (self.make_verifier)().verify(&token, req, self.vars.clone(), None);
+        if !result.allow {
+            return Err(Rejection::denied(&result).into_status());
+        }
+        request.extensions_mut().insert(result);
+        Ok(request)
+    }
+}
@@ -0,0 +1,238 @@
+//! HTTP header codecs for PoP challenges and presentations.
+//!
+//! A verifier answers an unauthenticated request with an
+//! `Agent-Safe-Challenge` response header; the agent signs it and retries
+//! with an `Agent-Safe-Presentation` request header. Both values are
+//! comma-separated `key=value` parameters: strings quoted with `\"` and `\\`
+//! escapes, integers bare, unknown keys ignored.
+//!
+//! ```text
+//! Agent-Safe-Challenge: nonce="3f9c…", aud="payments.example", exp=1760000300
+//! Agent-Safe-Presentation: tok="9a1b…", nonce="3f9c…", ts=1760000012, sig="c0de…", evd="e3b0…"
+//! ```
+
+use sha2::{Digest, Sha256};
+
+use crate::crypto::ct_eq;
+use crate::token::{Challenge, Presentation, Token, VerifyError, VerifyErrorCode};
+use crate::types::SplError;
+
+pub const CHALLENGE_HEADER: &str = "Agent-Safe-Challenge";
+pub const PRESENTATION_HEADER: &str = "Agent-Safe-Presentation";
+
+/// A challenge issued by a verifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengeHeader {
+    pub nonce: String,
+    /// Service the presentation is for, when the verifier names one.
+    pub audience: Option<String>,
+    /// Unix seconds after which the challenge is no longer answerable.
+    pub expires: i64,
+}
+
+impl ChallengeHeader {
+    /// A fresh challenge with a random 128-bit nonce, valid for `ttl_secs`.
+    pub fn generate(audience: Option<&str>, now: i64, ttl_secs: i64) -> Self {
+        let mut nonce = [0u8; 16];
+        getrandom::fill(&mut nonce).expect("OS RNG failed");
+        Self { nonce: hex::encode(nonce), audience: audience.map(Into::into), expires: now + ttl_secs }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now > self.expires
+    }
+
+    pub fn encode(&self) -> String {
+        let mut out = format!("nonce={}", quote(&self.nonce));
+        if let Some(aud) = &self.audience {
+            out.push_str(&format!(", aud={}", quote(aud)));
+        }
+        out.push_str(&format!(", exp={}", self.expires));
+        out
+    }
+
+    pub fn decode(value: &str) -> Result<Self, SplError> {
+        let params = Params::parse(value)?;
+        Ok(Self {
+            nonce: params.string("nonce")?,
+            audience: params.optional_string("aud")?,
+            expires: params.integer("exp")?,
+        })
+    }
+}
+
+/// A presentation sent by an agent in answer to a [`ChallengeHeader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationHeader {
+    /// [`token_ref`] of the presented token, which travels separately.
+    pub token_ref: String,
+    pub nonce: String,
+    /// Unix seconds at which the agent answered.
+    pub timestamp: i64,
+    /// Hex presentation signature (see [`crate::token::create_presentation_signature`]).
+    pub signature: String,
+    /// Hex SHA-256 of evidence sent with the request, such as the body or
+    /// the [`crate::request::request_hash`] of the decoded request: the
+    /// signed [`Challenge::evidence`]. The verifier recomputes and compares
+    /// it, and the presentation signature fails if it was swapped.
+    pub evidence_digest: Option<String>,
+}
+
+impl PresentationHeader {
+    pub fn new(token: &Token, presentation: &Presentation) -> Self {
+        Self {
+            token_ref: token_ref(token),
+            nonce: presentation.challenge.nonce.clone(),
+            timestamp: presentation.challenge.timestamp,
+            signature: presentation.signature.clone(),
+            evidence_digest: presentation.challenge.evidence.clone(),
+        }
+    }
+
+    pub fn presentation(&self) -> Presentation {
+        Presentation {
+            signature: self.signature.clone(),
+            challenge: Challenge {
+                nonce: self.nonce.clone(),
+                timestamp: self.timestamp,
+                evidence: self.evidence_digest.clone(),
+            },
+        }
+    }
+
+    /// Check this header against the token it claims and the challenge the
+    /// verifier issued, returning the presentation to verify the token with.
+    pub fn answer_to(&self, token: &Token, challenge: &ChallengeHeader, now: i64) -> Result<Presentation, VerifyError> {
+        if !ct_eq(self.token_ref.as_bytes(), token_ref(token).as_bytes()) {
+            return Err(VerifyError::new(VerifyErrorCode::InvalidPresentation, "presentation is for a different token"));
+        }
+        if !ct_eq(self.nonce.as_bytes(), challenge.nonce.as_bytes()) {
+            return Err(VerifyError::new(VerifyErrorCode::InvalidPresentation, "presentation answers a different challenge"));
+        }
+        if challenge.is_expired(now) || self.timestamp > challenge.expires {
+            return Err(VerifyError::new(VerifyErrorCode::PresentationExpired, "challenge has expired"));
+        }
+        Ok(self.presentation())
+    }
+
+    pub fn encode(&self) -> String {
+        let mut out = format!(
+            "tok={}, nonce={}, ts={}, sig={}",
+            quote(&self.token_ref),
+            quote(&self.nonce),
+            self.timestamp,
+            quote(&self.signature),
+        );
+        if let Some(evd) = &self.evidence_digest {
+            out.push_str(&format!(", evd={}", quote(evd)));
+        }
+        out
+    }
+
+    pub fn decode(value: &str) -> Result<Self, SplError> {
+        let params = Params::parse(value)?;
+        Ok(Self {
+            token_ref: params.string("tok")?,
+            nonce: params.string("nonce")?,
+            timestamp: params.integer("ts")?,
+            signature: params.string("sig")?,
+            evidence_digest: params.optional_string("evd")?,
+        })
+    }
+}
+
+/// Short stable reference to a token: its [`Token::id`].
+pub fn token_ref(token: &Token) -> String {
+    token.id().into_owned()
+}
+
+/// Hex SHA-256 for [`PresentationHeader::evidence_digest`].
+pub fn evidence_digest(evidence: &[u8]) -> String {
+    hex::encode(Sha256::digest(evidence))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+enum Param {
+    Str(String),
+    Int(i64),
+}
+
+struct Params(Vec<(String, Param)>);
+
+impl Params {
+    fn parse(value: &str) -> Result<Self, SplError> {
+        let err = |msg: &str| SplError(format!("malformed header: {msg}"));
+        let mut params = Vec::new();
+        let mut chars = value.chars().peekable();
+        loop {
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+            let mut key = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-') {
+                key.push(c.to_ascii_lowercase());
+            }
+            if key.is_empty() || chars.next() != Some('=') {
+                return Err(err("expected key="));
+            }
+            let param = if chars.next_if_eq(&'"').is_some() {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => s.push(chars.next().ok_or_else(|| err("dangling escape"))?),
+                        Some(c) => s.push(c),
+                        None => return Err(err("unterminated string")),
+                    }
+                }
+                Param::Str(s)
+            } else {
+                let mut digits = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '-') {
+                    digits.push(c);
+                }
+                Param::Int(digits.parse().map_err(|_| err(&format!("invalid value for {key}")))?)
+            };
+            if params.iter().any(|(k, _)| *k == key) {
+                return Err(err(&format!("duplicate {key}")));
+            }
+            params.push((key, param));
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            match chars.next() {
+                None => break,
+                Some(',') => {}
+                Some(_) => return Err(err("expected ,")),
+            }
+        }
+        Ok(Params(params))
+    }
+
+    fn get(&self, key: &str) -> Option<&Param> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn optional_string(&self, key: &str) -> Result<Option<String>, SplError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Param::Str(s)) => Ok(Some(s.clone())),
+            Some(Param::Int(_)) => Err(SplError(format!("header parameter {key} must be a string"))),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<String, SplError> {
+        self.optional_string(key)?
+            .ok_or_else(|| SplError(format!("header is missing {key}")))
+    }
+
+    fn integer(&self, key: &str) -> Result<i64, SplError> {
+        match self.get(key) {
+            Some(Param::Int(n)) => Ok(*n),
+            Some(Param::Str(_)) => Err(SplError(format!("header parameter {key} must be an integer"))),
+            None => Err(SplError(format!("header is missing {key}"))),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Issuer keys a verifier trusts.
+//!
+//! A token names its own `public_key`, so a valid signature only proves
+//! that whoever minted the token held that key. A verifier configured with
+//! [`crate::profile::Verifier::with_trusted_issuers`] (or calling
+//! [`crate::token::verify_token`]) also requires the key to be one
+//! it trusts, looked up by the token's signed `kid`, then its signed
+//! `issuer` DID (see [`crate::did`]), and by public key otherwise.
+//!
+//! Each [`TrustedKey`] carries the window in which it may sign. Rotation
+//! adds the new key alongside the old one and closes the old key's window,
+//! so tokens minted before the cutover keep verifying while both are
+//! listed. Tokens that sign `issued_at` are judged by when they were
+//! issued; others by the time of verification. A retired key's holder can
+//! backdate `issued_at`, so remove a compromised key rather than closing
+//! its window.
+
+use serde::{Deserialize, Serialize};
+
+use crate::keys::{key_id, KeyStore};
+use crate::signature::SignatureScheme;
+use crate::time::parse_rfc3339;
+use crate::token::{Token, VerifyError, VerifyErrorCode};
+use crate::types::SplError;
+
+/// An issuer public key and when it may sign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedKey {
+    /// Key id tokens name in `kid`; [`key_id`] of the public key by default.
+    pub kid: String,
+    /// Hex public key, as in [`Token::public_key`].
+    pub public_key: String,
+    #[serde(default)]
+    pub alg: SignatureScheme,
+    /// Unix seconds before which the key did not sign.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<i64>,
+    /// Unix seconds after which the key no longer signs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<i64>,
+}
+
+impl TrustedKey {
+    /// An Ed25519 key trusted without a window.
+    pub fn new(public_key: &str) -> Self {
+        Self::with_alg(public_key, SignatureScheme::Ed25519)
+    }
+
+    pub fn with_alg(public_key: &str, alg: SignatureScheme) -> Self {
+        Self { kid: key_id(public_key), public_key: public_key.to_string(), alg, not_before: None, not_after: None }
+    }
+
+    pub fn kid(mut self, kid: &str) -> Self {
+        self.kid = kid.to_string();
+        self
+    }
+
+    /// Trust the key for tokens issued in `not_before..=not_after` (Unix seconds).
+    pub fn valid(mut self, not_before: Option<i64>, not_after: Option<i64>) -> Self {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    pub fn is_valid_at(&self, at: i64) -> bool {
+        self.not_before.is_none_or(|t| at >= t) && self.not_after.is_none_or(|t| at <= t)
+    }
+}
+
+/// Where a verifier looks up trusted issuer keys.
+pub trait IssuerKeys: Send + Sync {
+    /// The trusted key with id `kid`, or, for tokens without a `kid`, the
+    /// one with `public_key`.
+    fn find(&self, kid: Option<&str>, public_key: &str) -> Result<Option<TrustedKey>, SplError>;
+}
+
+/// A fixed set of trusted issuer keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedIssuers {
+    keys: Vec<TrustedKey>,
+}
+
+impl TrustedIssuers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, key: TrustedKey) -> Self {
+        self.insert(key);
+        self
+    }
+
+    /// Add `key`, replacing any key with the same `kid`.
+    pub fn insert(&mut self, key: TrustedKey) {
+        self.keys.retain(|k| k.kid != key.kid);
+        self.keys.push(key);
+    }
+
+    pub fn remove(&mut self, kid: &str) -> Option<TrustedKey> {
+        let i = self.keys.iter().position(|k| k.kid == kid)?;
+        Some(self.keys.remove(i))
+    }
+
+    pub fn keys(&self) -> &[TrustedKey] {
+        &self.keys
+    }
+
+    /// Trust every key in an issuer's store for the span it was active:
+    /// from creation until it was rotated out.
+    pub fn from_key_store(store: &dyn KeyStore) -> Result<Self, SplError> {
+        let keys = store.keys()?.into_iter().map(|info| TrustedKey {
+            kid: info.id,
+            public_key: info.public_key,
+            alg: SignatureScheme::Ed25519,
+            not_before: Some(info.created_at),
+            not_after: info.retired_at,
+        });
+        Ok(Self { keys: keys.collect() })
+    }
+}
+
+impl FromIterator<TrustedKey> for TrustedIssuers {
+    fn from_iter<I: IntoIterator<Item = TrustedKey>>(iter: I) -> Self {
+        let mut issuers = Self::new();
+        for key in iter {
+            issuers.insert(key);
+        }
+        issuers
+    }
+}
+
+impl IssuerKeys for TrustedIssuers {
+    fn find(&self, kid: Option<&str>, public_key: &str) -> Result<Option<TrustedKey>, SplError> {
+        let found = match kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid == kid),
+            None => self.keys.iter().find(|k| k.public_key.eq_ignore_ascii_case(public_key)),
+        };
+        Ok(found.cloned())
+    }
+}
+
+/// Check that `token` was signed by a key `issuers` trusts for its
+/// issuance (or, without `issued_at`, for `now`).
+pub fn check_issuer(issuers: &dyn IssuerKeys, token: &Token, now: i64) -> Result<TrustedKey, VerifyError> {
+    let untrusted = |message: String| VerifyError::new(VerifyErrorCode::UntrustedIssuer, message);
+    let kid = token.kid.as_deref().or(token.issuer.as_deref());
+    let key = issuers
+        .find(kid, &token.public_key)
+        .map_err(|e| untrusted(format!("issuer key lookup failed: {e}")))?;
+    let Some(key) = key else {
+        return Err(match kid {
+            Some(kid) => untrusted(format!("unknown issuer key id: {kid}")),
+            None => untrusted(format!("untrusted issuer key {}", key_id(&token.public_key))),
+        });
+    };
+    if !key.public_key.eq_ignore_ascii_case(&token.public_key) || key.alg != token.alg {
+        return Err(untrusted(format!("token does not match trusted key {}", key.kid)));
+    }
+    check_valid_at_issuance(&key, token, now)?;
+    Ok(key)
+}
+
+/// Check that the co-signer `public_key` of a multi-signature `token` is
+/// an Ed25519 key `issuers` trusts for the token's issuance. Without this,
+/// the lead signer could declare keys it generated itself as co-signers.
+pub fn check_cosigner(issuers: &dyn IssuerKeys, token: &Token, public_key: &str, now: i64) -> Result<TrustedKey, VerifyError> {
+    let untrusted = |message: String| VerifyError::new(VerifyErrorCode::UntrustedIssuer, message);
+    let key = issuers
+        .find(None, public_key)
+        .map_err(|e| untrusted(format!("issuer key lookup failed: {e}")))?
+        .filter(|key| key.public_key.eq_ignore_ascii_case(public_key) && key.alg.is_ed25519())
+        .ok_or_else(|| untrusted(format!("untrusted co-signer key {}", key_id(public_key))))?;
+    check_valid_at_issuance(&key, token, now)?;
+    Ok(key)
+}
+
+fn check_valid_at_issuance(key: &TrustedKey, token: &Token, now: i64) -> Result<(), VerifyError> {
+    let at = match &token.issued_at {
+        Some(issued_at) => parse_rfc3339(issued_at)
+            .map_err(|e| VerifyError::new(VerifyErrorCode::MalformedToken, e.to_string()))?,
+        None => now,
+    };
+    if !key.is_valid_at(at) {
+        return Err(VerifyError::new(
+            VerifyErrorCode::UntrustedIssuer,
+            format!("issuer key {} is not valid at {at}", key.kid),
+        ));
+    }
+    Ok(())
+}
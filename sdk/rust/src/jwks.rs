@@ -0,0 +1,145 @@
+//! Trusted issuer keys from a JWKS (RFC 7517) document.
+//!
+//! [`parse_jwks`] converts a key set's Ed25519 signing keys (`"kty": "OKP"`,
+//! `"crv": "Ed25519"`) into [`TrustedIssuers`], keyed by each JWK's `kid`.
+//! Other key types, and keys marked for a `use` other than `"sig"`, are
+//! skipped. Optional numeric `nbf` and `exp` members bound the window in
+//! which a key may sign (see [`crate::issuers`]).
+//!
+//! [`JwksKeySet`] keeps a key set current for a verifier: it refetches the
+//! document through a [`Fetch`] once the cached copy expires, and early when
+//! a token names a `kid` it has not seen, as happens right after the issuer
+//! rotates. Fetching over HTTP is left to the host's [`Fetch`];
+//! [`JwksFile`] reads a local file.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use agent_safe_spl::jwks::{JwksFile, JwksKeySet};
+//! use agent_safe_spl::profile::Verifier;
+//! use agent_safe_spl::remote::RemoteConfig;
+//!
+//! let keys = JwksKeySet::new(JwksFile::new("/etc/agent-safe/issuers.jwks.json"), RemoteConfig::default());
+//! let verifier = Verifier::default().with_trusted_issuers(Arc::new(keys));
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::issuers::{IssuerKeys, TrustedIssuers, TrustedKey};
+use crate::jws::base64url_decode;
+use crate::keys::key_id;
+use crate::remote::{Fetch, Fetched, RemoteConfig, RemoteResource};
+use crate::signature::SignatureScheme;
+use crate::time::Clock;
+use crate::types::SplError;
+
+/// Least age, in seconds, before an unknown `kid` triggers an early refetch,
+/// so tokens naming made-up keys cannot drive a fetch per request.
+pub const MIN_REFETCH_SECS: i64 = 60;
+
+/// Parse a JWKS document into trusted keys.
+pub fn parse_jwks(json: &[u8]) -> Result<TrustedIssuers, SplError> {
+    let doc: Value = serde_json::from_slice(json).map_err(|e| SplError(format!("invalid JWKS: {e}")))?;
+    let keys = doc
+        .get("keys")
+        .and_then(Value::as_array)
+        .ok_or_else(|| SplError("JWKS has no keys array".into()))?;
+    let mut issuers = TrustedIssuers::new();
+    for jwk in keys {
+        if let Some(key) = trusted_key(jwk)? {
+            issuers.insert(key);
+        }
+    }
+    Ok(issuers)
+}
+
+/// Read and parse a JWKS file.
+pub fn load_jwks_file(path: impl AsRef<Path>) -> Result<TrustedIssuers, SplError> {
+    let path = path.as_ref();
+    let json = fs::read(path).map_err(|e| SplError(format!("reading {}: {e}", path.display())))?;
+    parse_jwks(&json)
+}
+
+/// The Ed25519 signing key a JWK describes, or `None` for other keys.
+fn trusted_key(jwk: &Value) -> Result<Option<TrustedKey>, SplError> {
+    let member = |name: &str| jwk.get(name).and_then(Value::as_str);
+    if member("kty") != Some("OKP") || member("crv") != Some("Ed25519") || member("use").is_some_and(|u| u != "sig") {
+        return Ok(None);
+    }
+    let kid = member("kid");
+    let invalid = |what: &str| SplError(format!("JWK {}: {what}", kid.unwrap_or("without kid")));
+    if member("alg").is_some_and(|alg| alg != "EdDSA" && alg != "Ed25519") {
+        return Err(invalid("alg must be EdDSA"));
+    }
+    let x = base64url_decode(member("x").ok_or_else(|| invalid("missing x"))?)?;
+    if x.len() != 32 {
+        return Err(invalid("x must be 32 bytes"));
+    }
+    let window = |name: &str| match jwk.get(name) {
+        None => Ok(None),
+        Some(v) => v.as_i64().map(Some).ok_or_else(|| invalid(&format!("{name} must be Unix seconds"))),
+    };
+    let public_key = hex::encode(x);
+    Ok(Some(TrustedKey {
+        kid: kid.map_or_else(|| key_id(&public_key), str::to_string),
+        public_key,
+        alg: SignatureScheme::Ed25519,
+        not_before: window("nbf")?,
+        not_after: window("exp")?,
+    }))
+}
+
+/// A JWKS document on disk, read on every fetch.
+#[derive(Debug, Clone)]
+pub struct JwksFile {
+    path: PathBuf,
+}
+
+impl JwksFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Fetch for JwksFile {
+    fn fetch(&self) -> Result<Fetched, SplError> {
+        let body = fs::read(&self.path).map_err(|e| SplError(format!("reading {}: {e}", self.path.display())))?;
+        Ok(Fetched { body, signature: None })
+    }
+}
+
+/// Trusted issuer keys from a JWKS document, cached per [`RemoteConfig`].
+pub struct JwksKeySet {
+    keys: RemoteResource<TrustedIssuers>,
+}
+
+impl JwksKeySet {
+    pub fn new(fetcher: impl Fetch + 'static, config: RemoteConfig) -> Self {
+        Self { keys: RemoteResource::new(fetcher, parse_jwks, config) }
+    }
+
+    /// Replace the clock judging cache freshness.
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.keys = self.keys.with_clock(clock);
+        self
+    }
+
+    /// The current key set, fetching if nothing servable is cached.
+    pub fn keys(&self) -> Result<Arc<TrustedIssuers>, SplError> {
+        self.keys.get()
+    }
+}
+
+impl IssuerKeys for JwksKeySet {
+    fn find(&self, kid: Option<&str>, public_key: &str) -> Result<Option<TrustedKey>, SplError> {
+        let found = self.keys.get()?.find(kid, public_key)?;
+        let settled = self.keys.status().age_secs.is_some_and(|age| age >= MIN_REFETCH_SECS);
+        if found.is_some() || kid.is_none() || !settled {
+            return Ok(found);
+        }
+        self.keys.refresh()?.find(kid, public_key)
+    }
+}
@@ -0,0 +1,142 @@
+//! JWS (compact serialization) export and import of tokens.
+//!
+//! Registered claims mirror the token envelope so JWT middleware can check
+//! it: `iss` is the issuer public key (hex), `exp`/`iat`/`jti` come from
+//! `expires`/`issued_at`/`token_id`, `aud` is optional, and PoP-bound
+//! tokens carry `cnf.jwk`. The header names the issuer key's `kid`. The
+//! complete native token rides in the custom `spl` claim, so import is
+//! lossless and the native signature still governs verification.
+
+use serde_json::{json, Map, Value};
+
+use crate::time::parse_rfc3339;
+use crate::token::Token;
+use crate::types::SplError;
+
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Unpadded base64url (RFC 4648 §5).
+pub fn base64url_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..=chunk.len() {
+            out.push(B64[(n >> (18 - 6 * i)) as usize & 63] as char);
+        }
+    }
+    out
+}
+
+/// Decode unpadded base64url, rejecting padding and non-canonical input.
+pub fn base64url_decode(s: &str) -> Result<Vec<u8>, SplError> {
+    let err = || SplError("invalid base64url".into());
+    if s.len() % 4 == 1 {
+        return Err(err());
+    }
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let v = B64.iter().position(|b| b == c).ok_or_else(err)? as u32;
+            n |= v << (18 - 6 * i);
+        }
+        let bytes = chunk.len() - 1;
+        if n & ((1 << (24 - 8 * bytes)) - 1) != 0 {
+            return Err(err());
+        }
+        out.extend((0..bytes).map(|i| (n >> (16 - 8 * i)) as u8));
+    }
+    Ok(out)
+}
+
+fn unix(field: &str, value: &Option<String>) -> Result<Option<i64>, SplError> {
+    value
+        .as_deref()
+        .map(|v| parse_rfc3339(v).map_err(|e| SplError(format!("{field}: {e}"))))
+        .transpose()
+}
+
+fn registered_claims(token: &Token) -> Result<Map<String, Value>, SplError> {
+    let mut claims = Map::new();
+    claims.insert("iss".into(), json!(token.public_key));
+    if let Some(exp) = unix("expires", &token.expires)? {
+        claims.insert("exp".into(), json!(exp));
+    }
+    if let Some(iat) = unix("issued_at", &token.issued_at)? {
+        claims.insert("iat".into(), json!(iat));
+    }
+    if let Some(jti) = &token.token_id {
+        claims.insert("jti".into(), json!(jti));
+    }
+    if let Some(pop_key) = &token.pop_key {
+        let x = hex::decode(pop_key).map_err(|e| SplError(format!("invalid pop_key hex: {e}")))?;
+        claims.insert("cnf".into(), json!({ "jwk": { "kty": "OKP", "crv": "Ed25519", "x": base64url_encode(&x) } }));
+    }
+    Ok(claims)
+}
+
+impl Token {
+    /// Export as a compact JWS signed by the issuer key. `private_key_hex`
+    /// must belong to `self.public_key` under `self.alg`.
+    pub fn to_jws(&self, private_key_hex: &str, audience: Option<&str>) -> Result<String, SplError> {
+        if self.alg.public_key(private_key_hex)? != self.public_key {
+            return Err(SplError("private key does not match token public_key".into()));
+        }
+        let mut header = json!({ "alg": self.alg.name(), "typ": "JWT" });
+        if let Some(kid) = &self.kid {
+            header["kid"] = json!(kid);
+        }
+        let mut claims = registered_claims(self)?;
+        if let Some(aud) = audience {
+            claims.insert("aud".into(), json!(aud));
+        }
+        claims.insert("spl".into(), serde_json::to_value(self).map_err(|e| SplError(e.to_string()))?);
+
+        let signing_input = format!(
+            "{}.{}",
+            base64url_encode(header.to_string().as_bytes()),
+            base64url_encode(Value::Object(claims).to_string().as_bytes()),
+        );
+        let signature = hex::decode(self.alg.sign(private_key_hex, signing_input.as_bytes())?)
+            .map_err(|e| SplError(e.to_string()))?;
+        Ok(format!("{signing_input}.{}", base64url_encode(&signature)))
+    }
+
+    /// Import a JWS produced by [`Token::to_jws`]. Checks the JWS signature
+    /// and that the registered claims agree with the embedded token; the
+    /// token itself still needs [`crate::token::verify_token`].
+    pub fn from_jws(jws: &str) -> Result<Token, SplError> {
+        let parts: Vec<&str> = jws.split('.').collect();
+        let [header_b64, claims_b64, signature_b64] = parts[..] else {
+            return Err(SplError("JWS must have three parts".into()));
+        };
+        let parse = |b64: &str, what: &str| -> Result<Value, SplError> {
+            serde_json::from_slice(&base64url_decode(b64)?).map_err(|e| SplError(format!("invalid JWS {what}: {e}")))
+        };
+        let header = parse(header_b64, "header")?;
+        let Value::Object(mut claims) = parse(claims_b64, "claims")? else {
+            return Err(SplError("JWS claims must be an object".into()));
+        };
+
+        let spl = claims.remove("spl").ok_or_else(|| SplError("JWS has no spl claim".into()))?;
+        let token: Token = serde_json::from_value(spl).map_err(|e| SplError(format!("invalid spl claim: {e}")))?;
+
+        if header.get("alg").and_then(Value::as_str) != Some(token.alg.name()) {
+            return Err(SplError("JWS alg does not match token alg".into()));
+        }
+        if header.get("kid").and_then(Value::as_str) != token.kid.as_deref() {
+            return Err(SplError("JWS kid does not match token kid".into()));
+        }
+        let signature = hex::encode(base64url_decode(signature_b64)?);
+        let signing_input = &jws[..header_b64.len() + 1 + claims_b64.len()];
+        if !token.alg.verify(signing_input.as_bytes(), &signature, &token.public_key) {
+            return Err(SplError("invalid JWS signature".into()));
+        }
+
+        claims.remove("aud");
+        if claims != registered_claims(&token)? {
+            return Err(SplError("JWS registered claims do not match the spl claim".into()));
+        }
+        Ok(token)
+    }
+}
@@ -0,0 +1,356 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{backend, key_from_hex, public_key_hex, sign_hex};
+use crate::crypto::sha256_hex;
+use crate::signature::SignatureScheme;
+use crate::time::{Clock, SystemClock};
+use crate::types::SplError;
+
+/// Public description of a signing key held by a [`KeyStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyInfo {
+    /// First 16 hex characters of SHA-256 over the public key bytes.
+    pub id: String,
+    pub public_key: String,
+    /// Unix seconds at which the key was generated.
+    pub created_at: i64,
+    /// Unix seconds at which the key was rotated out. Retired keys no longer
+    /// sign but remain listed so verifiers can still check older tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_at: Option<i64>,
+    /// Id of the key that replaced this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub successor: Option<String>,
+}
+
+/// Key id for a hex-encoded Ed25519 public key.
+pub fn key_id(public_key_hex: &str) -> String {
+    let bytes = hex::decode(public_key_hex).unwrap_or_default();
+    sha256_hex(&bytes)[..16].to_string()
+}
+
+/// Custody of issuer signing keys. Private key bytes stay inside the store;
+/// callers get signatures back from [`KeyStore::sign`].
+pub trait KeyStore {
+    /// Generate a new active key, retiring the current one if any.
+    fn rotate(&self) -> Result<KeyInfo, SplError>;
+    /// The key currently used for signing.
+    fn active(&self) -> Result<Option<KeyInfo>, SplError>;
+    /// Every key in the store, oldest first.
+    fn keys(&self) -> Result<Vec<KeyInfo>, SplError>;
+    /// Sign `message` with `key_id`. Returns the hex Ed25519 signature.
+    fn sign(&self, key_id: &str, message: &[u8]) -> Result<String, SplError>;
+}
+
+/// A single issuer key that signs without handing out its private half,
+/// such as an HSM or cloud KMS key. Mint with
+/// [`crate::token::mint_with_signer`].
+pub trait Signer {
+    /// Hex-encoded public key, as carried in minted tokens.
+    fn public_key(&self) -> String;
+    /// Raw signature over `message`.
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SplError>;
+    /// Scheme the key signs with; minted tokens carry it as `alg`.
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+}
+
+/// In-memory Ed25519 [`Signer`], for tests and for issuers that hold the
+/// key themselves.
+pub struct Ed25519Signer {
+    seed: [u8; 32],
+    public_key: String,
+}
+
+impl Ed25519Signer {
+    pub fn from_hex(private_key_hex: &str) -> Result<Self, SplError> {
+        let seed = key_from_hex(private_key_hex, "private key")?;
+        Ok(Self { seed, public_key: public_key_hex(&seed)? })
+    }
+}
+
+impl Signer for Ed25519Signer {
+    fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SplError> {
+        backend().ed25519_sign(&self.seed, message).map(Vec::from)
+    }
+}
+
+fn new_key(clock: &dyn Clock) -> Result<(KeyInfo, [u8; 32]), SplError> {
+    let mut seed = [0u8; 32];
+    getrandom::fill(&mut seed).expect("OS RNG failed");
+    let public_key = public_key_hex(&seed)?;
+    let info = KeyInfo {
+        id: key_id(&public_key),
+        public_key,
+        created_at: clock.now_unix(),
+        retired_at: None,
+        successor: None,
+    };
+    Ok((info, seed))
+}
+
+fn retire(info: &mut KeyInfo, successor: &str, now: i64) {
+    if info.retired_at.is_none() {
+        info.retired_at = Some(now);
+        info.successor = Some(successor.to_string());
+    }
+}
+
+fn unknown_key(key_id: &str) -> SplError {
+    SplError(format!("unknown key: {key_id}"))
+}
+
+fn retired_key(key_id: &str) -> SplError {
+    SplError(format!("key {key_id} is retired"))
+}
+
+/// In-memory key store. Keys are lost when the store is dropped.
+#[derive(Default)]
+pub struct InMemoryKeyStore {
+    keys: Mutex<Vec<(KeyInfo, [u8; 32])>>,
+}
+
+impl InMemoryKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(KeyInfo, [u8; 32])>> {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl KeyStore for InMemoryKeyStore {
+    fn rotate(&self) -> Result<KeyInfo, SplError> {
+        let (info, seed) = new_key(&SystemClock)?;
+        let mut keys = self.lock();
+        for (old, _) in keys.iter_mut() {
+            retire(old, &info.id, info.created_at);
+        }
+        keys.push((info.clone(), seed));
+        Ok(info)
+    }
+
+    fn active(&self) -> Result<Option<KeyInfo>, SplError> {
+        Ok(self.lock().iter().map(|(i, _)| i).find(|i| i.retired_at.is_none()).cloned())
+    }
+
+    fn keys(&self) -> Result<Vec<KeyInfo>, SplError> {
+        Ok(self.lock().iter().map(|(i, _)| i.clone()).collect())
+    }
+
+    fn sign(&self, key_id: &str, message: &[u8]) -> Result<String, SplError> {
+        let keys = self.lock();
+        let (info, seed) = keys.iter().find(|(i, _)| i.id == key_id).ok_or_else(|| unknown_key(key_id))?;
+        if info.retired_at.is_some() {
+            return Err(retired_key(key_id));
+        }
+        sign_hex(seed, message)
+    }
+}
+
+#[cfg(feature = "keystore")]
+pub use argon2::Params as Argon2Params;
+#[cfg(feature = "keystore")]
+pub use file::FileKeyStore;
+
+#[cfg(feature = "keystore")]
+mod file {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use argon2::{Algorithm, Argon2, Params, Version};
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+    use serde::{Deserialize, Serialize};
+    use zeroize::Zeroizing;
+
+    use super::{new_key, public_key_hex, retire, retired_key, sign_hex, unknown_key, KeyInfo, KeyStore};
+    use crate::time::SystemClock;
+    use crate::types::SplError;
+
+    /// Argon2id cost parameters recorded with each key so they can be raised
+    /// for new keys without breaking old ones.
+    #[derive(Serialize, Deserialize)]
+    struct KdfParams {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+        salt: String,
+    }
+
+    /// On-disk key file: public metadata plus the XChaCha20-Poly1305 sealed
+    /// seed. The key id and public key are bound in as associated data.
+    #[derive(Serialize, Deserialize)]
+    struct KeyFile {
+        /// Generation order within the store; `created_at` can tie.
+        seq: u64,
+        info: KeyInfo,
+        kdf: KdfParams,
+        nonce: String,
+        ciphertext: String,
+    }
+
+    fn io_err(e: std::io::Error) -> SplError {
+        SplError(format!("key store I/O error: {e}"))
+    }
+
+    fn random<const N: usize>() -> [u8; N] {
+        let mut bytes = [0u8; N];
+        getrandom::fill(&mut bytes).expect("OS RNG failed");
+        bytes
+    }
+
+    fn aad(info: &KeyInfo) -> Vec<u8> {
+        format!("agent-safe-key\0{}\0{}", info.id, info.public_key).into_bytes()
+    }
+
+    /// Passphrase-encrypted key store, one `<key id>.json` file per key.
+    ///
+    /// Each seed is sealed with XChaCha20-Poly1305 under a key derived from
+    /// the passphrase by Argon2id with a per-file salt. Rotation metadata
+    /// (`retired_at`, `successor`) is stored in the clear.
+    pub struct FileKeyStore {
+        root: PathBuf,
+        passphrase: Zeroizing<String>,
+        params: Params,
+    }
+
+    impl FileKeyStore {
+        /// Open (creating if needed) a store rooted at `root`.
+        pub fn open(root: impl AsRef<Path>, passphrase: &str) -> Result<Self, SplError> {
+            Self::open_with_params(root, passphrase, Params::default())
+        }
+
+        /// Open with explicit Argon2id parameters for newly generated keys.
+        pub fn open_with_params(root: impl AsRef<Path>, passphrase: &str, params: Params) -> Result<Self, SplError> {
+            let root = root.as_ref().to_path_buf();
+            fs::create_dir_all(&root).map_err(io_err)?;
+            Ok(Self { root, passphrase: Zeroizing::new(passphrase.to_string()), params })
+        }
+
+        fn path(&self, key_id: &str) -> PathBuf {
+            self.root.join(format!("{key_id}.json"))
+        }
+
+        fn cipher(&self, kdf: &KdfParams) -> Result<XChaCha20Poly1305, SplError> {
+            let salt = hex::decode(&kdf.salt).map_err(|e| SplError(format!("invalid key file salt: {e}")))?;
+            let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+                .map_err(|e| SplError(format!("invalid key file KDF parameters: {e}")))?;
+            let mut key = Zeroizing::new([0u8; 32]);
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(self.passphrase.as_bytes(), &salt, key.as_mut())
+                .map_err(|e| SplError(format!("key derivation failed: {e}")))?;
+            Ok(XChaCha20Poly1305::new(key.as_ref().into()))
+        }
+
+        fn read(&self, key_id: &str) -> Result<KeyFile, SplError> {
+            if !key_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(unknown_key(key_id));
+            }
+            let data = match fs::read(self.path(key_id)) {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(unknown_key(key_id)),
+                Err(e) => return Err(io_err(e)),
+            };
+            serde_json::from_slice(&data).map_err(|e| SplError(format!("invalid key file {key_id}: {e}")))
+        }
+
+        fn write(&self, file: &KeyFile) -> Result<(), SplError> {
+            let path = self.path(&file.info.id);
+            let json = serde_json::to_vec_pretty(file).map_err(|e| SplError(e.to_string()))?;
+            // Write then rename so readers never observe a partial file.
+            let tmp = path.with_extension("tmp");
+            let mut opts = fs::OpenOptions::new();
+            opts.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+            std::io::Write::write_all(&mut opts.open(&tmp).map_err(io_err)?, &json).map_err(io_err)?;
+            fs::rename(&tmp, &path).map_err(io_err)
+        }
+
+        fn files(&self) -> Result<Vec<KeyFile>, SplError> {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(&self.root).map_err(io_err)? {
+                let name = entry.map_err(io_err)?.file_name().to_string_lossy().into_owned();
+                if let Some(id) = name.strip_suffix(".json") {
+                    files.push(self.read(id)?);
+                }
+            }
+            files.sort_by_key(|f| f.seq);
+            Ok(files)
+        }
+    }
+
+    impl KeyStore for FileKeyStore {
+        fn rotate(&self) -> Result<KeyInfo, SplError> {
+            let existing = self.files()?;
+            let seq = existing.last().map_or(0, |f| f.seq + 1);
+            let (info, seed) = new_key(&SystemClock)?;
+            let kdf = KdfParams {
+                m_cost: self.params.m_cost(),
+                t_cost: self.params.t_cost(),
+                p_cost: self.params.p_cost(),
+                salt: hex::encode(random::<16>()),
+            };
+            let nonce = random::<24>();
+            let seed = Zeroizing::new(seed);
+            let ciphertext = self
+                .cipher(&kdf)?
+                .encrypt(XNonce::from_slice(&nonce), Payload { msg: seed.as_ref(), aad: &aad(&info) })
+                .map_err(|_| SplError("key encryption failed".into()))?;
+            self.write(&KeyFile {
+                seq,
+                info: info.clone(),
+                kdf,
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+            })?;
+
+            for mut old in existing {
+                if old.info.retired_at.is_none() {
+                    retire(&mut old.info, &info.id, info.created_at);
+                    self.write(&old)?;
+                }
+            }
+            Ok(info)
+        }
+
+        fn active(&self) -> Result<Option<KeyInfo>, SplError> {
+            Ok(self.files()?.into_iter().map(|f| f.info).rfind(|i| i.retired_at.is_none()))
+        }
+
+        fn keys(&self) -> Result<Vec<KeyInfo>, SplError> {
+            Ok(self.files()?.into_iter().map(|f| f.info).collect())
+        }
+
+        fn sign(&self, key_id: &str, message: &[u8]) -> Result<String, SplError> {
+            let file = self.read(key_id)?;
+            if file.info.retired_at.is_some() {
+                return Err(retired_key(key_id));
+            }
+            let nonce = hex::decode(&file.nonce).ok().filter(|n| n.len() == 24);
+            let ciphertext = hex::decode(&file.ciphertext).ok();
+            let (Some(nonce), Some(ciphertext)) = (nonce, ciphertext) else {
+                return Err(SplError(format!("invalid key file {key_id}")));
+            };
+            let seed = Zeroizing::new(
+                self.cipher(&file.kdf)?
+                    .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad(&file.info) })
+                    .map_err(|_| SplError("wrong passphrase or corrupt key file".into()))?,
+            );
+            let seed: &[u8; 32] = seed.as_slice().try_into().map_err(|_| SplError(format!("invalid key file {key_id}")))?;
+            if public_key_hex(seed)? != file.info.public_key {
+                return Err(SplError(format!("key file {key_id} does not match its public key")));
+            }
+            sign_hex(seed, message)
+        }
+    }
+}
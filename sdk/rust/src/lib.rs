@@ -1,11 +1,85 @@
 pub mod types;
 pub mod parser;
 pub mod evaluator;
+pub mod compiled;
+pub mod ops;
 pub mod verifier;
 pub mod crypto;
 pub mod token;
+pub mod replay;
+pub mod uses;
+pub mod limits;
+pub mod denylist;
+pub mod spend;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "tooling")]
+pub mod vectors;
+pub mod policy_store;
+#[cfg(feature = "analysis")]
+pub mod analysis;
+#[cfg(feature = "analysis")]
+pub mod consent;
+pub mod time;
+pub mod geo;
+pub mod attestation;
+pub mod profile;
+pub mod api;
+pub mod keys;
+pub mod issuers;
+pub mod did;
+pub mod summary;
+pub mod vars;
+pub mod signature;
+pub mod backend;
+#[cfg(feature = "jws")]
+pub mod jws;
+#[cfg(feature = "cose")]
+pub mod cose;
+pub mod caveat;
+pub mod canonical;
+pub mod fragments;
+pub mod audit;
+pub mod obligations;
+pub mod builder;
+pub mod request;
+pub mod scope;
+pub mod disclosure;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "jwks")]
+pub mod jwks;
+#[cfg(feature = "tower")]
+pub mod middleware;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "tooling")]
+pub mod sandbox;
+#[cfg(feature = "tooling")]
+pub mod testing;
+#[cfg(feature = "tooling")]
+pub mod conformance;
+#[cfg(feature = "tooling")]
+pub mod translate;
+#[cfg(feature = "tooling")]
+pub mod expander;
+pub mod money;
+#[cfg(feature = "usage")]
+pub mod usage;
+#[cfg(feature = "presets")]
+pub mod presets;
+#[cfg(feature = "approval")]
+pub mod approval;
+#[cfg(feature = "tooling")]
+pub mod fuzz;
+#[cfg(feature = "dev")]
+pub mod dev;
 
 pub use parser::parse;
 pub use verifier::verify;
-pub use types::{Node, Env, CryptoCallbacks};
-pub use token::{Token, mint, verify_token, generate_keypair};
+pub use types::{Node, Env, CallbackState, CounterKey, CryptoCallbacks};
+pub use token::{Token, mint, mint_multisig, verify_token, verify_token_untrusted, generate_keypair};
+pub use replay::{ReplayCache, InMemoryReplayCache};
+pub use profile::{Verifier, VerifierProfile};
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Node, SplError};
+
+/// Inclusive numeric bound on a request field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RangeLimit {
+    pub field: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Declarative `(limits ...)` block.
+///
+/// ```text
+/// (limits (amount 0 75) (per_day 3))
+/// ```
+///
+/// Each `(field min max)` entry bounds `(get req "field")` inclusively;
+/// `(per_day n)` caps `(per-day-count (get req "action") (get req "day"))`.
+/// The evaluator enforces the block and tooling reads it back through
+/// [`find_limits`] without interpreting arbitrary comparisons.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyLimits {
+    pub ranges: Vec<RangeLimit>,
+    pub per_day: Option<f64>,
+}
+
+impl PolicyLimits {
+    /// Parse the entries of a `(limits ...)` form (everything after the head symbol).
+    pub fn from_entries(entries: &[Node]) -> Result<Self, SplError> {
+        let mut limits = PolicyLimits::default();
+        for entry in entries {
+            let Node::List(items) = entry else {
+                return Err(SplError(format!("invalid limits entry: {entry}")));
+            };
+            match items.as_slice() {
+                [Node::Symbol(name), n] if name == "per_day" && n.as_number().is_some() => {
+                    limits.per_day = n.as_number();
+                }
+                [Node::Symbol(field), min, max] if min.as_number().is_some() && max.as_number().is_some() => {
+                    limits.ranges.push(RangeLimit {
+                        field: field.clone(),
+                        min: min.as_f64(),
+                        max: max.as_f64(),
+                    });
+                }
+                _ => return Err(SplError(format!("invalid limits entry: {entry}"))),
+            }
+        }
+        Ok(limits)
+    }
+
+    /// Upper bound declared for `field`, if any.
+    pub fn max_for(&self, field: &str) -> Option<f64> {
+        self.ranges.iter().find(|r| r.field == field).map(|r| r.max)
+    }
+}
+
+/// Locate the `(limits ...)` block of a policy: either the policy itself or
+/// a direct conjunct of a top-level `(and ...)`.
+pub fn find_limits(ast: &Node) -> Result<Option<PolicyLimits>, SplError> {
+    if let Some(entries) = limits_entries(ast) {
+        return PolicyLimits::from_entries(entries).map(Some);
+    }
+    if let Node::List(items) = ast {
+        if items.first() == Some(&Node::Symbol("and".into())) {
+            for item in &items[1..] {
+                if let Some(entries) = limits_entries(item) {
+                    return PolicyLimits::from_entries(entries).map(Some);
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn limits_entries(node: &Node) -> Option<&[Node]> {
+    match node {
+        Node::List(items) if items.first() == Some(&Node::Symbol("limits".into())) => {
+            Some(&items[1..])
+        }
+        _ => None,
+    }
+}
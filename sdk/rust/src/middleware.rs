@@ -0,0 +1,354 @@
+//! Tower middleware that verifies an agent's token before a request reaches
+//! the service.
+//!
+//! [`AgentSafeLayer`] reads a compact-JWS token from the `Authorization`
+//! header, builds `req` from the HTTP request with the configured
+//! [`Extract`]ors, and verifies the token against it. Allowed requests reach
+//! the inner service with the [`VerifyTokenResult`] in their extensions, so
+//! handlers can act on its obligations. Denied requests get 403 with the
+//! deny reason, and a missing or unreadable token gets 401:
+//!
+//! ```text
+//! Authorization: AgentSafe eyJhbGciOiJFZERTQSJ9.eyJ…
+//!
+//! 403 {"error": "policy denied the request", "code": "policy_denied", "reason_code": "AMOUNT_TOO_HIGH"}
+//! ```
+//!
+//! [`AgentSafeLayer::grpc`] serves gRPC, including tonic servers (which
+//! take tower layers through `Server::builder().layer`): the token travels
+//! in `authorization` metadata, `req["action"]` is the RPC method such as
+//! `payments.Payments/Create`, and rejections are gRPC statuses
+//! (`UNAUTHENTICATED`, `PERMISSION_DENIED`) with the reason in
+//! `grpc-message` and any `deny-with` code in `agent-safe-reason-code`.
+//!
+//! The layer sends no PoP challenge, so PoP-bound tokens are denied; the
+//! challenge round trip is shown in `examples/gateway`. The body is buffered
+//! only when an [`Extract::Body`] field is configured, so streaming RPCs
+//! pass through untouched otherwise. Buffered bodies are capped at
+//! [`AgentSafeLayer::max_body_bytes`]; larger ones get 413
+//! (`RESOURCE_EXHAUSTED` over gRPC).
+//!
+//! Services that wire authorization through tonic interceptors rather than
+//! tower layers can use [`crate::grpc::AgentSafeInterceptor`] (feature
+//! `grpc`) instead.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use http::request::Parts;
+use http::{HeaderMap, HeaderValue, Request, Response, StatusCode};
+use http_body::Body;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use serde_json::{json, Value};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::profile::Verifier;
+use crate::token::{Token, VerifyTokenResult};
+use crate::types::Node;
+
+/// `Authorization` scheme for agent tokens, matched case-insensitively.
+pub const AUTH_SCHEME: &str = "AgentSafe";
+
+/// gRPC response metadata carrying the policy's `deny-with` code.
+pub const REASON_CODE_METADATA: &str = "agent-safe-reason-code";
+
+/// Default cap on a buffered request body, in bytes.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1 << 20;
+
+/// Where a `req` field is taken from. A source that yields nothing leaves
+/// the field unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Extract {
+    /// The HTTP method, e.g. `"POST"`.
+    Method,
+    /// The URI path, without the query.
+    Path,
+    /// The gRPC method, `package.Service/Method`, taken from the path.
+    RpcMethod,
+    /// A header value; absent or non-UTF-8 values are skipped.
+    Header(String),
+    /// A field of a JSON body, as an RFC 6901 pointer such as `/amount`.
+    Body(String),
+}
+
+type MakeVerifier = dyn Fn() -> Verifier + Send + Sync;
+
+/// How rejections are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Http,
+    Grpc,
+}
+
+struct Config {
+    make_verifier: Arc<MakeVerifier>,
+    fields: Vec<(String, Extract)>,
+    vars: HashMap<String, Node>,
+    protocol: Protocol,
+    max_body_bytes: usize,
+}
+
+impl Config {
+    fn reads_body(&self) -> bool {
+        self.fields.iter().any(|(_, from)| matches!(from, Extract::Body(_)))
+    }
+
+    fn request(&self, parts: &Parts, body: &[u8]) -> HashMap<String, Node> {
+        let mut json = None;
+        let mut req = HashMap::new();
+        for (name, from) in &self.fields {
+            let value = match from {
+                Extract::Method => Some(Node::from(parts.method.as_str())),
+                Extract::Path => Some(Node::from(parts.uri.path())),
+                Extract::RpcMethod => Some(Node::from(parts.uri.path().trim_start_matches('/'))),
+                Extract::Header(header) => parts.headers.get(header).and_then(|v| v.to_str().ok()).map(Node::from),
+                Extract::Body(pointer) => json
+                    .get_or_insert_with(|| serde_json::from_slice::<Value>(body).ok())
+                    .as_ref()
+                    .and_then(|body| body.pointer(pointer))
+                    .map(Node::from_json_value),
+            };
+            if let Some(value) = value {
+                req.insert(name.clone(), value);
+            }
+        }
+        req
+    }
+}
+
+/// Layer adding [`AgentSafeService`] in front of a service.
+///
+/// A [`Verifier`] owns non-`Send` state such as its clock, so the layer
+/// builds one per request with `make_verifier`; share stores and trusted
+/// issuers by capturing `Arc`s in the closure.
+#[derive(Clone)]
+pub struct AgentSafeLayer {
+    make_verifier: Arc<MakeVerifier>,
+    fields: Vec<(String, Extract)>,
+    vars: HashMap<String, Node>,
+    protocol: Protocol,
+    max_body_bytes: usize,
+}
+
+impl AgentSafeLayer {
+    pub fn new(make_verifier: impl Fn() -> Verifier + Send + Sync + 'static) -> Self {
+        Self {
+            make_verifier: Arc::new(make_verifier),
+            fields: Vec::new(),
+            vars: HashMap::new(),
+            protocol: Protocol::Http,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+
+    /// A layer for gRPC services, with `req["action"]` set to the RPC method.
+    pub fn grpc(make_verifier: impl Fn() -> Verifier + Send + Sync + 'static) -> Self {
+        let layer = Self { protocol: Protocol::Grpc, ..Self::new(make_verifier) };
+        layer.extract("action", Extract::RpcMethod)
+    }
+
+    /// Set `req` field `name` from `from`. Later calls for the same name win.
+    pub fn extract(mut self, name: &str, from: Extract) -> Self {
+        self.fields.retain(|(n, _)| n != name);
+        self.fields.push((name.to_string(), from));
+        self
+    }
+
+    /// Vars passed to every verification.
+    pub fn vars(mut self, vars: HashMap<String, Node>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Largest body buffered for [`Extract::Body`] fields, by default
+    /// [`DEFAULT_MAX_BODY_BYTES`]. Longer bodies are rejected with 413.
+    pub fn max_body_bytes(mut self, max: usize) -> Self {
+        self.max_body_bytes = max;
+        self
+    }
+}
+
+impl<S> Layer<S> for AgentSafeLayer {
+    type Service = AgentSafeService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let config = Config {
+            make_verifier: self.make_verifier.clone(),
+            fields: self.fields.clone(),
+            vars: self.vars.clone(),
+            protocol: self.protocol,
+            max_body_bytes: self.max_body_bytes,
+        };
+        AgentSafeService { inner, config: Arc::new(config) }
+    }
+}
+
+/// Service verifying each request's token before passing it on.
+#[derive(Clone)]
+pub struct AgentSafeService<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+impl<S, B, R> Service<Request<B>> for AgentSafeService<S>
+where
+    S: Service<Request<B>, Response = Response<R>> + Clone + Send + 'static,
+    S::Future: Send,
+    B: Body<Data = Bytes> + From<Bytes> + Send + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    R: From<Bytes>,
+{
+    type Response = Response<R>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response<R>, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // Keep the instance that was polled ready for this request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            let token = match token_from(&parts.headers) {
+                Ok(token) => token,
+                Err(rejection) => return Ok(rejection.into_response(config.protocol)),
+            };
+            let (bytes, body) = if config.reads_body() {
+                match Limited::new(body, config.max_body_bytes).collect().await {
+                    Ok(collected) => {
+                        let bytes = collected.to_bytes();
+                        (bytes.clone(), B::from(bytes))
+                    }
+                    Err(e) if e.is::<LengthLimitError>() => {
+                        let message = format!("body exceeds {} bytes", config.max_body_bytes);
+                        let rejection = Rejection::new(StatusCode::PAYLOAD_TOO_LARGE, "body_too_large", message);
+                        return Ok(rejection.into_response(config.protocol));
+                    }
+                    Err(e) => {
+                        let rejection = Rejection::new(StatusCode::BAD_REQUEST, "unreadable_body", e.to_string());
+                        return Ok(rejection.into_response(config.protocol));
+                    }
+                }
+            } else {
+                (Bytes::new(), body)
+            };
+            let req = config.request(&parts, &bytes);
+            let result = (config.make_verifier)().verify(&token, req, config.vars.clone(), None);
+            if !result.allow {
+                return Ok(Rejection::denied(&result).into_response(config.protocol));
+            }
+            parts.extensions.insert(result);
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+/// The token in `Authorization`.
+pub(crate) fn token_from(headers: &HeaderMap) -> Result<Token, Rejection> {
+    let missing = || Rejection::new(StatusCode::UNAUTHORIZED, "missing_token", "missing AgentSafe authorization".into());
+    let value = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok()).ok_or_else(missing)?;
+    match value.split_once(' ') {
+        Some((scheme, credentials)) if scheme.eq_ignore_ascii_case(AUTH_SCHEME) => Token::from_jws(credentials.trim())
+            .map_err(|e| Rejection::new(StatusCode::UNAUTHORIZED, "malformed_token", e.0)),
+        _ => Err(missing()),
+    }
+}
+
+/// Why a request was turned away.
+pub(crate) struct Rejection {
+    status: StatusCode,
+    code: String,
+    error: String,
+    reason_code: Option<String>,
+}
+
+impl Rejection {
+    fn new(status: StatusCode, code: &str, error: String) -> Self {
+        Self { status, code: code.to_string(), error, reason_code: None }
+    }
+
+    pub(crate) fn denied(result: &VerifyTokenResult) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            code: result.code.map_or("policy_denied", |c| c.as_str()).to_string(),
+            error: result.error.clone().unwrap_or_else(|| "policy denied the request".into()),
+            reason_code: result.reason_code.clone(),
+        }
+    }
+
+    /// gRPC status code for the HTTP status.
+    fn grpc_status(&self) -> &'static str {
+        match self.status {
+            StatusCode::UNAUTHORIZED => "16",
+            StatusCode::BAD_REQUEST => "3",
+            StatusCode::PAYLOAD_TOO_LARGE => "8",
+            _ => "7",
+        }
+    }
+
+    /// The rejection as a tonic status, with the same code and message as
+    /// [`Protocol::Grpc`] responses.
+    #[cfg(feature = "grpc")]
+    pub(crate) fn into_status(self) -> tonic::Status {
+        let code = tonic::Code::from_bytes(self.grpc_status().as_bytes());
+        let mut status = tonic::Status::new(code, format!("{}: {}", self.code, self.error));
+        if let Some(value) = self.reason_code.and_then(|c| c.parse().ok()) {
+            status.metadata_mut().insert(REASON_CODE_METADATA, value);
+        }
+        status
+    }
+
+    fn into_response<R: From<Bytes>>(self, protocol: Protocol) -> Response<R> {
+        match protocol {
+            Protocol::Http => {
+                let body = json!({ "error": self.error, "code": self.code, "reason_code": self.reason_code });
+                let mut response = Response::new(R::from(Bytes::from(body.to_string())));
+                *response.status_mut() = self.status;
+                let headers = response.headers_mut();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                if self.status == StatusCode::UNAUTHORIZED {
+                    headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static(AUTH_SCHEME));
+                }
+                response
+            }
+            Protocol::Grpc => {
+                // A trailers-only response: the status rides in the headers.
+                let mut response = Response::new(R::from(Bytes::new()));
+                let headers = response.headers_mut();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+                headers.insert("grpc-status", HeaderValue::from_static(self.grpc_status()));
+                let message = grpc_message(&format!("{}: {}", self.code, self.error));
+                if let Ok(value) = HeaderValue::from_str(&message) {
+                    headers.insert("grpc-message", value);
+                }
+                if let Some(value) = self.reason_code.and_then(|c| HeaderValue::from_str(&c).ok()) {
+                    headers.insert(REASON_CODE_METADATA, value);
+                }
+                response
+            }
+        }
+    }
+}
+
+/// Percent-encode a `grpc-message`: bytes outside printable ASCII, and `%`.
+fn grpc_message(message: &str) -> String {
+    let mut out = String::with_capacity(message.len());
+    for byte in message.bytes() {
+        if (0x20..0x7f).contains(&byte) && byte != b'%' {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
@@ -0,0 +1,158 @@
+//! Currency amounts held as integer minor units.
+//!
+//! `(money 19.99 "USD")` builds a [`Node::Money`] of 1999 cents. Comparison
+//! operators compare money exactly and refuse to compare amounts in
+//! different currencies, or money with a bare number, instead of silently
+//! comparing floats.
+//!
+//! `(convert amount "EUR" "USD")` normalizes an amount through the host's
+//! [`RateProvider`] so a cap written in one currency can bound requests in
+//! another.
+
+use crate::types::{Node, SplError};
+
+/// ISO 4217 currencies whose minor unit is not 1/100.
+const EXPONENTS: &[(&str, u32)] = &[
+    ("BIF", 0), ("CLP", 0), ("DJF", 0), ("GNF", 0), ("ISK", 0), ("JPY", 0), ("KMF", 0), ("KRW", 0),
+    ("PYG", 0), ("RWF", 0), ("UGX", 0), ("VND", 0), ("VUV", 0), ("XAF", 0), ("XOF", 0), ("XPF", 0),
+    ("BHD", 3), ("IQD", 3), ("JOD", 3), ("KWD", 3), ("LYD", 3), ("OMR", 3), ("TND", 3),
+];
+
+/// Decimal places in `currency`'s minor unit: 0 for JPY, 3 for KWD, else 2.
+pub fn minor_exponent(currency: &str) -> u32 {
+    EXPONENTS.iter().find(|(c, _)| *c == currency).map_or(2, |(_, e)| *e)
+}
+
+/// Whether `code` looks like an ISO 4217 code: three ASCII uppercase letters.
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+fn check_currency(currency: &str) -> Result<(), SplError> {
+    if is_currency_code(currency) {
+        Ok(())
+    } else {
+        Err(SplError(format!("invalid currency code: {currency:?}")))
+    }
+}
+
+/// Minor units in a decimal string such as `"19.99"` or `"-5"`. More
+/// decimal places than the currency has is an error, not a rounding.
+pub fn minor_units_from_decimal(amount: &str, currency: &str) -> Result<i64, SplError> {
+    check_currency(currency)?;
+    let err = || SplError(format!("invalid {currency} amount: {amount:?}"));
+    let (negative, digits) = match amount.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, amount),
+    };
+    let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    let exponent = minor_exponent(currency) as usize;
+    if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) || !frac.bytes().all(|b| b.is_ascii_digit())
+        || (digits.contains('.') && frac.is_empty())
+    {
+        return Err(err());
+    }
+    if frac.len() > exponent {
+        return Err(SplError(format!("{currency} amount {amount} has more than {exponent} decimal places")));
+    }
+    let text = format!("{whole}{frac:0<exponent$}");
+    let units: i64 = text.parse().map_err(|_| err())?;
+    Ok(if negative { -units } else { units })
+}
+
+/// Minor units in a float amount. The amount must land on a whole minor
+/// unit, so `19.99` is 1999 cents but `19.999` is an error.
+pub fn minor_units_from_f64(amount: f64, currency: &str) -> Result<i64, SplError> {
+    check_currency(currency)?;
+    let scaled = amount * 10f64.powi(minor_exponent(currency) as i32);
+    let units = scaled.round();
+    // Beyond 2^53 a float no longer holds every integer exactly.
+    if !units.is_finite() || units.abs() > 9_007_199_254_740_992.0 {
+        return Err(SplError(format!("{currency} amount {amount} is out of range")));
+    }
+    if (scaled - units).abs() > 1e-6 {
+        return Err(SplError(format!(
+            "{currency} amount {amount} has more than {} decimal places",
+            minor_exponent(currency)
+        )));
+    }
+    Ok(units as i64)
+}
+
+/// Render minor units as a decimal string, e.g. `1999` USD as `"19.99"`.
+pub fn format_amount(minor_units: i64, currency: &str) -> String {
+    let exponent = minor_exponent(currency);
+    let sign = if minor_units < 0 { "-" } else { "" };
+    let abs = minor_units.unsigned_abs();
+    if exponent == 0 {
+        return format!("{sign}{abs}");
+    }
+    let scale = 10u64.pow(exponent);
+    format!("{sign}{}.{:0width$}", abs / scale, abs % scale, width = exponent as usize)
+}
+
+/// Build a money node from a number or decimal-string amount.
+pub fn money(amount: &Node, currency: &str) -> Result<Node, SplError> {
+    let minor_units = match amount {
+        Node::Int(i) => {
+            check_currency(currency)?;
+            i.checked_mul(10i64.pow(minor_exponent(currency)))
+                .ok_or_else(|| SplError(format!("{currency} amount {i} is out of range")))?
+        }
+        Node::Number(n) => minor_units_from_f64(*n, currency)?,
+        Node::Str(s) => minor_units_from_decimal(s, currency)?,
+        other => return Err(SplError(format!("money expects a number or decimal string amount, got {other}"))),
+    };
+    Ok(Node::Money { minor_units, currency: currency.to_string() })
+}
+
+/// Host-side source of exchange rates backing the `convert` operator.
+/// Closures `Fn(&str, &str) -> Option<f64>` implement it directly.
+pub trait RateProvider: Send + Sync {
+    /// Units of `to` per unit of `from`, or `None` if the pair is unknown.
+    fn rate(&self, from: &str, to: &str) -> Option<f64>;
+}
+
+impl<F: Fn(&str, &str) -> Option<f64> + Send + Sync> RateProvider for F {
+    fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        self(from, to)
+    }
+}
+
+/// Convert `amount` from one currency to another. Money must be in `from`
+/// and converts to money in `to`, rounded to its nearest minor unit; a bare
+/// number converts to a number. Converting a currency to itself needs no
+/// rate. Returns `Ok(None)` when no usable rate is available.
+pub fn convert(amount: &Node, from: &str, to: &str, rates: Option<&dyn RateProvider>) -> Result<Option<Node>, SplError> {
+    check_currency(from)?;
+    check_currency(to)?;
+    let major = match amount {
+        Node::Money { minor_units, currency } if currency == from => {
+            *minor_units as f64 / 10f64.powi(minor_exponent(from) as i32)
+        }
+        Node::Money { currency, .. } => {
+            return Err(SplError(format!("cannot convert {currency} money from {from}")));
+        }
+        Node::Int(_) | Node::Number(_) => amount.as_f64(),
+        other => return Err(SplError(format!("convert expects a number or money amount, got {other}"))),
+    };
+    let rate = if from == to {
+        1.0
+    } else {
+        match rates.and_then(|r| r.rate(from, to)) {
+            Some(rate) if rate.is_finite() && rate > 0.0 => rate,
+            _ => return Ok(None),
+        }
+    };
+    let converted = major * rate;
+    Ok(Some(match amount {
+        Node::Money { .. } => {
+            let units = (converted * 10f64.powi(minor_exponent(to) as i32)).round();
+            if !units.is_finite() || units.abs() > 9_007_199_254_740_992.0 {
+                return Err(SplError(format!("{to} amount {converted} is out of range")));
+            }
+            Node::Money { minor_units: units as i64, currency: to.to_string() }
+        }
+        _ => Node::Number(converted),
+    }))
+}
@@ -0,0 +1,115 @@
+//! Obligations attached to allowed decisions and their discharge.
+//!
+//! A policy attaches an obligation with `(obligate "kind" deadline_secs)`;
+//! it reaches the caller through `VerifyResult::obligations` and
+//! `VerifyTokenResult::obligations`. Relying services hand those to an
+//! [`ObligationTracker`] and acknowledge each one once discharged, which
+//! records the discharge, on time or late, in the tracker's [`AuditLog`].
+//!
+//! Token ids are whatever stable identifier the service already keys tokens
+//! by; the token signature works.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditEntry, AuditLog};
+use crate::types::SplError;
+
+/// Something the relying service must do after acting on an allow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Obligation {
+    /// Service-defined name, e.g. `notify-owner`.
+    pub kind: String,
+    /// Seconds after the decision by which it must be discharged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_secs: Option<i64>,
+}
+
+/// An obligation issued for a token and not yet acknowledged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingObligation {
+    pub obligation: Obligation,
+    /// Unix seconds of the decision that issued it.
+    pub issued_at: i64,
+    /// Unix seconds by which it is due, if it has a deadline.
+    pub due_at: Option<i64>,
+}
+
+impl PendingObligation {
+    pub fn is_overdue(&self, now: i64) -> bool {
+        self.due_at.is_some_and(|due| now > due)
+    }
+}
+
+/// Tracks outstanding obligations per token and audits their discharge.
+#[derive(Default)]
+pub struct ObligationTracker {
+    pending: Mutex<HashMap<String, Vec<PendingObligation>>>,
+    audit: AuditLog,
+}
+
+impl ObligationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the obligations of a decision made at `issued_at`.
+    pub fn record(&self, token_id: &str, obligations: &[Obligation], issued_at: i64) {
+        if obligations.is_empty() {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let entries = pending.entry(token_id.to_string()).or_default();
+        for obligation in obligations {
+            self.audit.append(token_id, "obligation.issued", &describe(obligation), issued_at);
+            entries.push(PendingObligation {
+                obligation: obligation.clone(),
+                issued_at,
+                due_at: obligation.deadline_secs.map(|d| issued_at.saturating_add(d)),
+            });
+        }
+    }
+
+    /// Discharge the oldest outstanding obligation of `kind` on `token_id`,
+    /// returning the audit entry that records it. Late discharges are still
+    /// accepted and recorded as late.
+    pub fn acknowledge(&self, token_id: &str, kind: &str, at: i64) -> Result<AuditEntry, SplError> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let missing = || SplError(format!("no outstanding {kind} obligation for token {token_id}"));
+        let entries = pending.get_mut(token_id).ok_or_else(missing)?;
+        let index = entries.iter().position(|p| p.obligation.kind == kind).ok_or_else(missing)?;
+        let discharged = entries.remove(index);
+        if entries.is_empty() {
+            pending.remove(token_id);
+        }
+
+        let status = if discharged.is_overdue(at) { "late" } else { "on_time" };
+        let detail = format!("{} issued_at={} status={status}", describe(&discharged.obligation), discharged.issued_at);
+        Ok(self.audit.append(token_id, "obligation.discharged", &detail, at))
+    }
+
+    /// Obligations issued for `token_id` and not yet acknowledged, oldest first.
+    pub fn outstanding(&self, token_id: &str) -> Vec<PendingObligation> {
+        let pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.get(token_id).cloned().unwrap_or_default()
+    }
+
+    /// Outstanding obligations for `token_id` already past their deadline.
+    pub fn overdue(&self, token_id: &str, now: i64) -> Vec<PendingObligation> {
+        self.outstanding(token_id).into_iter().filter(|p| p.is_overdue(now)).collect()
+    }
+
+    /// The audit chain of issued and discharged obligations.
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+}
+
+fn describe(obligation: &Obligation) -> String {
+    match obligation.deadline_secs {
+        Some(d) => format!("kind={} deadline_secs={d}", obligation.kind),
+        None => format!("kind={}", obligation.kind),
+    }
+}
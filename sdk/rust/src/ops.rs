@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{Node, SplError};
+
+/// Built-in SPL operators. The interpreter resolves each call's symbol
+/// with [`Op::from_name`] every time it evaluates the call, then dispatches
+/// on the enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+    Eq,
+    EqHash,
+    Le,
+    Lt,
+    Ge,
+    Gt,
+    InRange,
+    Limits,
+    Member,
+    Subset,
+    Intersect,
+    Union,
+    Difference,
+    Disjoint,
+    Before,
+    Weekday,
+    HourBetween,
+    During,
+    Get,
+    LimitFor,
+    SpentFor,
+    CumulativeSpend,
+    RemainingFor,
+    Tuple,
+    Length,
+    Sum,
+    CountIf,
+    Lambda,
+    All,
+    Any,
+    PerDayCount,
+    UsageCount,
+    PerDayCountSelf,
+    DpopOk,
+    MerkleOk,
+    VrfOk,
+    ThreshOk,
+    DenylistAbsent,
+    Obligate,
+    DenyWith,
+    Money,
+    Convert,
+    ActionIn,
+    IpInCidr,
+    CountryIn,
+    Attested,
+}
+
+impl Op {
+    /// Look up an operator by its SPL name. `in` is an alias for `member`.
+    pub fn from_name(name: &str) -> Option<Op> {
+        let op = match name {
+            "and" => Op::And,
+            "or" => Op::Or,
+            "not" => Op::Not,
+            "=" => Op::Eq,
+            "=hash" => Op::EqHash,
+            "<=" => Op::Le,
+            "<" => Op::Lt,
+            ">=" => Op::Ge,
+            ">" => Op::Gt,
+            "in-range" => Op::InRange,
+            "limits" => Op::Limits,
+            "member" | "in" => Op::Member,
+            "subset?" => Op::Subset,
+            "intersect" => Op::Intersect,
+            "union" => Op::Union,
+            "difference" => Op::Difference,
+            "disjoint?" => Op::Disjoint,
+            "before" => Op::Before,
+            "weekday?" => Op::Weekday,
+            "hour-between?" => Op::HourBetween,
+            "during" => Op::During,
+            "get" => Op::Get,
+            "limit-for" => Op::LimitFor,
+            "spent-for" => Op::SpentFor,
+            "cumulative-spend" => Op::CumulativeSpend,
+            "remaining-for" => Op::RemainingFor,
+            "tuple" => Op::Tuple,
+            "length" => Op::Length,
+            "sum" => Op::Sum,
+            "count-if" => Op::CountIf,
+            "lambda" => Op::Lambda,
+            "all" => Op::All,
+            "any" => Op::Any,
+            "per-day-count" => Op::PerDayCount,
+            "usage-count" => Op::UsageCount,
+            "per-day-count-self" => Op::PerDayCountSelf,
+            "dpop_ok?" => Op::DpopOk,
+            "merkle_ok?" => Op::MerkleOk,
+            "vrf_ok?" => Op::VrfOk,
+            "thresh_ok?" => Op::ThreshOk,
+            "denylist-absent?" => Op::DenylistAbsent,
+            "obligate" => Op::Obligate,
+            "deny-with" => Op::DenyWith,
+            "money" => Op::Money,
+            "convert" => Op::Convert,
+            "action-in" => Op::ActionIn,
+            "ip-in-cidr" => Op::IpInCidr,
+            "country-in" => Op::CountryIn,
+            "attested?" => Op::Attested,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Canonical SPL name of the operator.
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::And => "and",
+            Op::Or => "or",
+            Op::Not => "not",
+            Op::Eq => "=",
+            Op::EqHash => "=hash",
+            Op::Le => "<=",
+            Op::Lt => "<",
+            Op::Ge => ">=",
+            Op::Gt => ">",
+            Op::InRange => "in-range",
+            Op::Limits => "limits",
+            Op::Member => "member",
+            Op::Subset => "subset?",
+            Op::Intersect => "intersect",
+            Op::Union => "union",
+            Op::Difference => "difference",
+            Op::Disjoint => "disjoint?",
+            Op::Before => "before",
+            Op::Weekday => "weekday?",
+            Op::HourBetween => "hour-between?",
+            Op::During => "during",
+            Op::Get => "get",
+            Op::LimitFor => "limit-for",
+            Op::SpentFor => "spent-for",
+            Op::CumulativeSpend => "cumulative-spend",
+            Op::RemainingFor => "remaining-for",
+            Op::Tuple => "tuple",
+            Op::Length => "length",
+            Op::Sum => "sum",
+            Op::CountIf => "count-if",
+            Op::Lambda => "lambda",
+            Op::All => "all",
+            Op::Any => "any",
+            Op::PerDayCount => "per-day-count",
+            Op::UsageCount => "usage-count",
+            Op::PerDayCountSelf => "per-day-count-self",
+            Op::DpopOk => "dpop_ok?",
+            Op::MerkleOk => "merkle_ok?",
+            Op::VrfOk => "vrf_ok?",
+            Op::ThreshOk => "thresh_ok?",
+            Op::DenylistAbsent => "denylist-absent?",
+            Op::Obligate => "obligate",
+            Op::DenyWith => "deny-with",
+            Op::Money => "money",
+            Op::Convert => "convert",
+            Op::ActionIn => "action-in",
+            Op::IpInCidr => "ip-in-cidr",
+            Op::CountryIn => "country-in",
+            Op::Attested => "attested?",
+        }
+    }
+
+    /// Fewest and most arguments the operator takes; `None` is unbounded.
+    pub fn arity(self) -> (usize, Option<usize>) {
+        match self {
+            Op::And | Op::Or | Op::Tuple | Op::Limits | Op::MerkleOk => (0, None),
+            Op::PerDayCountSelf | Op::DpopOk | Op::ThreshOk => (0, Some(0)),
+            Op::Not | Op::Weekday | Op::SpentFor | Op::DenyWith | Op::Length | Op::Attested => (1, Some(1)),
+            Op::Obligate | Op::Sum => (1, Some(2)),
+            Op::ActionIn => (1, None),
+            Op::LimitFor => (2, Some(3)),
+            Op::InRange | Op::HourBetween | Op::During | Op::All | Op::Any | Op::Convert => (3, Some(3)),
+            Op::Eq | Op::EqHash | Op::Le | Op::Lt | Op::Ge | Op::Gt | Op::Member | Op::Subset | Op::Intersect | Op::Union
+            | Op::Difference | Op::Disjoint | Op::Before | Op::Get
+            | Op::RemainingFor | Op::CumulativeSpend | Op::PerDayCount | Op::UsageCount | Op::VrfOk | Op::DenylistAbsent
+            | Op::Money | Op::CountIf | Op::Lambda | Op::IpInCidr | Op::CountryIn => (2, Some(2)),
+        }
+    }
+
+    /// Whether the operator depends only on its arguments (no request,
+    /// vars, or host callbacks), making constant arguments foldable.
+    pub fn is_pure(self) -> bool {
+        matches!(
+            self,
+            Op::And | Op::Or | Op::Not | Op::Eq | Op::EqHash | Op::Le | Op::Lt | Op::Ge | Op::Gt
+                | Op::InRange | Op::Before | Op::Weekday | Op::HourBetween | Op::During | Op::Money
+                | Op::Length | Op::Sum | Op::IpInCidr | Op::CountryIn
+        )
+    }
+}
+
+/// A host function backing a custom operator. It receives the evaluated
+/// arguments, already checked against the declared arity.
+pub type HostOp = dyn Fn(&[Node]) -> Result<Node, SplError> + Send + Sync;
+
+/// How an operator name is evaluated.
+#[derive(Clone)]
+pub enum OpImpl {
+    Builtin(Op),
+    Host(Arc<HostOp>),
+}
+
+/// An operator's argument bounds, gas cost, and implementation.
+#[derive(Clone)]
+pub struct OpHandler {
+    pub min_arity: usize,
+    /// `None` is unbounded.
+    pub max_arity: Option<usize>,
+    /// Gas charged per call, on top of evaluating the arguments. Built-ins
+    /// charge from [`crate::types::GasSchedule`] instead and declare 0.
+    pub gas: i64,
+    pub handler: OpImpl,
+}
+
+impl OpHandler {
+    /// A host operator taking `min..=max` arguments and costing `gas` per call.
+    pub fn host(
+        min_arity: usize,
+        max_arity: Option<usize>,
+        gas: i64,
+        handler: impl Fn(&[Node]) -> Result<Node, SplError> + Send + Sync + 'static,
+    ) -> Self {
+        Self { min_arity, max_arity, gas, handler: OpImpl::Host(Arc::new(handler)) }
+    }
+
+    fn builtin(op: Op) -> Self {
+        let (min_arity, max_arity) = op.arity();
+        Self { min_arity, max_arity, gas: 0, handler: OpImpl::Builtin(op) }
+    }
+
+    /// Fail unless `n` arguments fit the declared arity of operator `name`.
+    pub fn check_arity(&self, name: &str, n: usize) -> Result<(), SplError> {
+        let expected = match (self.min_arity, self.max_arity) {
+            (min, Some(max)) if min == max && n != min => format!("{min}"),
+            (min, _) if n < min => format!("at least {min}"),
+            (_, Some(max)) if n > max => format!("at most {max}"),
+            _ => return Ok(()),
+        };
+        let plural = if expected.ends_with(" 1") || expected == "1" { "" } else { "s" };
+        Err(SplError(format!("{name} expects {expected} argument{plural}, got {n}")))
+    }
+}
+
+impl fmt::Debug for OpHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let handler = match &self.handler {
+            OpImpl::Builtin(op) => op.name(),
+            OpImpl::Host(_) => "<host>",
+        };
+        f.debug_struct("OpHandler")
+            .field("min_arity", &self.min_arity)
+            .field("max_arity", &self.max_arity)
+            .field("gas", &self.gas)
+            .field("handler", &handler)
+            .finish()
+    }
+}
+
+/// Operator names an [`crate::types::Env`] evaluates: every built-in, plus
+/// the custom operators a host registers. A custom operator named after a
+/// built-in overrides it, which strict mode forbids.
+#[derive(Debug, Clone, Default)]
+pub struct OpRegistry {
+    custom: HashMap<String, OpHandler>,
+}
+
+impl OpRegistry {
+    /// Register a custom operator, replacing any earlier one of that name.
+    /// Names must be symbols that the parser reads back unchanged; in
+    /// `strict` mode, built-in names are refused.
+    pub fn register(&mut self, name: &str, handler: OpHandler, strict: bool) -> Result<(), SplError> {
+        if !matches!(crate::parser::parse(name), Ok(Node::Symbol(ref s)) if s == name) || name == "req" {
+            return Err(SplError(format!("invalid operator name: {name:?}")));
+        }
+        if strict && Op::from_name(name).is_some() {
+            return Err(SplError(format!("cannot override built-in operator {name} in strict mode")));
+        }
+        if handler.max_arity.is_some_and(|max| max < handler.min_arity) || handler.gas < 0 {
+            return Err(SplError(format!("invalid arity or gas for operator {name}")));
+        }
+        self.custom.insert(name.to_string(), handler);
+        Ok(())
+    }
+
+    /// The handler `name` resolves to: a custom operator first, then a built-in.
+    pub fn get(&self, name: &str) -> Option<OpHandler> {
+        match self.custom.get(name) {
+            Some(handler) => Some(handler.clone()),
+            None => Op::from_name(name).map(OpHandler::builtin),
+        }
+    }
+
+    /// Names of the registered custom operators, sorted.
+    pub fn custom_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.custom.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Custom operators that replace a built-in of the same name, sorted.
+    pub fn overridden_builtins(&self) -> Vec<&str> {
+        self.custom_names().into_iter().filter(|name| Op::from_name(name).is_some()).collect()
+    }
+}
+
+/// Operators used in `ast` that `allowed` does not name, in first-use order.
+/// Aliases are checked by their canonical [`Op::name`], so `in` needs `member`.
+pub fn forbidden_ops(ast: &Node, allowed: &HashSet<String>) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    collect_forbidden(ast, allowed, &mut found);
+    found
+}
+
+fn collect_forbidden(node: &Node, allowed: &HashSet<String>, found: &mut Vec<&'static str>) {
+    match node {
+        Node::List(items) => {
+            if let Some(Node::Symbol(head)) = items.first() {
+                if let Some(name) = Op::from_name(head).map(Op::name) {
+                    if !allowed.contains(name) && !found.contains(&name) {
+                        found.push(name);
+                    }
+                }
+            }
+            items.iter().for_each(|n| collect_forbidden(n, allowed, found));
+        }
+        Node::Map(entries) => entries.values().for_each(|n| collect_forbidden(n, allowed, found)),
+        _ => {}
+    }
+}
@@ -1,32 +1,137 @@
-use crate::types::{Node, SplError};
+use crate::types::{Limits, Node, SplError};
 
-const MAX_POLICY_BYTES: usize = 65536; // 64 KB
+/// Deepest list nesting accepted unless [`Limits::max_depth`] is higher,
+/// well above the evaluator's default limit, so hostile input cannot
+/// exhaust the stack of the recursive parser.
+const MAX_PARSE_DEPTH: usize = 256;
 
-/// Parse an SPL S-expression string into an AST Node.
+/// Which literal syntax a policy is read with.
+///
+/// String escapes and quoted lists arrived with envelope version 0.3 (see
+/// [`crate::token::EnvelopeVersion::syntax`]). Tokens signed under earlier
+/// versions keep reading their policies as they did when minted, and as
+/// SDKs without the newer syntax still read them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Syntax {
+    /// Strings end at the first `"`, and `'` is an ordinary symbol character.
+    Legacy,
+    /// String escapes (see [`escape_string`]) and `'(...)` quoted lists.
+    #[default]
+    Current,
+}
+
+/// Parse an SPL S-expression string into an AST Node, under the default
+/// [`Limits`].
 pub fn parse(src: &str) -> Result<Node, SplError> {
-    if src.len() > MAX_POLICY_BYTES {
-        return Err(SplError(format!("policy exceeds maximum size of {MAX_POLICY_BYTES} bytes")));
-    }
-    let tokens = tokenize(src.trim());
-    if tokens.is_empty() {
-        return Err(SplError("unexpected EOF".into()));
-    }
+    parse_with_limits(src, &Limits::default())
+}
+
+/// Parse a single expression, rejecting sources over `limits.max_policy_bytes`
+/// and literals or lists over its string and list limits.
+pub fn parse_with_limits(src: &str, limits: &Limits) -> Result<Node, SplError> {
+    parse_with_syntax(src, limits, Syntax::Current)
+}
+
+/// [`parse_with_limits`], reading literals with `syntax`.
+pub fn parse_with_syntax(src: &str, limits: &Limits, syntax: Syntax) -> Result<Node, SplError> {
+    let tokens = tokens(src, limits, syntax)?;
     let mut pos = 0;
-    let result = parse_expr(&tokens, &mut pos)?;
+    let result = parse_expr(&tokens, &mut pos, 0, limits, syntax)?;
     if pos != tokens.len() {
         return Err(SplError("extra tokens".into()));
     }
     Ok(result)
 }
 
-fn parse_expr(tokens: &[String], pos: &mut usize) -> Result<Node, SplError> {
+/// Parse a source containing one or more top-level S-expressions,
+/// e.g. a sequence of `(policy "name" ...)` clauses.
+pub fn parse_all(src: &str) -> Result<Vec<Node>, SplError> {
+    parse_all_with_limits(src, &Limits::default())
+}
+
+/// [`parse_all`] under `limits`, as for [`parse_with_limits`].
+pub fn parse_all_with_limits(src: &str, limits: &Limits) -> Result<Vec<Node>, SplError> {
+    parse_all_with_syntax(src, limits, Syntax::Current)
+}
+
+/// [`parse_all_with_limits`], reading literals with `syntax`.
+pub fn parse_all_with_syntax(src: &str, limits: &Limits, syntax: Syntax) -> Result<Vec<Node>, SplError> {
+    let tokens = tokens(src, limits, syntax)?;
+    let mut pos = 0;
+    let mut exprs = Vec::new();
+    while pos < tokens.len() {
+        exprs.push(parse_expr(&tokens, &mut pos, 0, limits, syntax)?);
+    }
+    Ok(exprs)
+}
+
+fn tokens<'a>(src: &'a str, limits: &Limits, syntax: Syntax) -> Result<Vec<&'a str>, SplError> {
+    if src.len() > limits.max_policy_bytes {
+        return Err(SplError(format!("policy exceeds maximum size of {} bytes", limits.max_policy_bytes)));
+    }
+    let tokens = tokenize(src.trim(), syntax);
+    if tokens.is_empty() {
+        return Err(SplError("unexpected EOF".into()));
+    }
+    Ok(tokens)
+}
+
+/// Re-indent policy source: each top-level expression on its own
+/// paragraph, lists broken one argument per line once they pass 80 columns.
+pub fn format_policy(src: &str) -> Result<String, SplError> {
+    let exprs = parse_all(src)?;
+    let mut out = String::new();
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            out.push_str("\n\n");
+        }
+        format_node(expr, 0, &mut out);
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+const FORMAT_WIDTH: usize = 80;
+
+fn format_node(node: &Node, indent: usize, out: &mut String) {
+    let flat = node.to_string();
+    let items = match node {
+        Node::List(items) if indent + flat.len() > FORMAT_WIDTH && items.len() > 1 => items,
+        _ => {
+            out.push_str(&flat);
+            return;
+        }
+    };
+    // Keep the operator, and a clause's name, on the opening line.
+    let head = if items[0] == Node::Symbol("policy".into()) { 2 } else { 1 };
+    out.push('(');
+    for (i, item) in items.iter().enumerate() {
+        if i < head {
+            if i > 0 {
+                out.push(' ');
+            }
+            out.push_str(&item.to_string());
+        } else {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent + 2));
+            format_node(item, indent + 2, out);
+        }
+    }
+    out.push(')');
+}
+
+fn parse_expr(tokens: &[&str], pos: &mut usize, depth: usize, limits: &Limits, syntax: Syntax) -> Result<Node, SplError> {
     if *pos >= tokens.len() {
         return Err(SplError("unexpected EOF".into()));
     }
-    let tok = &tokens[*pos];
+    let tok = tokens[*pos];
     *pos += 1;
 
     if tok == "(" {
+        let max_depth = MAX_PARSE_DEPTH.max(limits.max_depth);
+        if depth >= max_depth {
+            return Err(SplError(format!("policy nests deeper than {max_depth} lists")));
+        }
         let mut items = Vec::new();
         loop {
             if *pos >= tokens.len() {
@@ -36,94 +141,195 @@ fn parse_expr(tokens: &[String], pos: &mut usize) -> Result<Node, SplError> {
                 *pos += 1;
                 break;
             }
-            items.push(parse_expr(tokens, pos)?);
+            items.push(parse_expr(tokens, pos, depth + 1, limits, syntax)?);
+            limits.check_list(items.len())?;
         }
         Ok(Node::List(items))
     } else if tok == ")" {
         Err(SplError("unexpected )".into()))
+    } else if tok == "'" && syntax == Syntax::Current {
+        if tokens.get(*pos) != Some(&"(") {
+            return Err(SplError("' must be followed by a list".into()));
+        }
+        quoted(parse_expr(tokens, pos, depth, limits, syntax)?)
     } else {
-        Ok(parse_atom(tok))
+        let atom = parse_atom(tok, syntax)?;
+        limits.check_value(&atom)?;
+        Ok(atom)
+    }
+}
+
+/// A quoted list as the `tuple` call that evaluates to it, with nested
+/// lists quoted too. Only literals may be quoted, so the data is fixed in
+/// the policy text.
+fn quoted(node: Node) -> Result<Node, SplError> {
+    match node {
+        Node::List(items) => std::iter::once(Ok(Node::Symbol("tuple".into())))
+            .chain(items.into_iter().map(quoted))
+            .collect::<Result<_, _>>()
+            .map(Node::List),
+        Node::Symbol(s) => Err(SplError(format!("quoted list holds symbol {s}; only literals can be quoted"))),
+        atom => Ok(atom),
     }
 }
 
-fn parse_atom(tok: &str) -> Node {
-    match tok {
+fn parse_atom(tok: &str, syntax: Syntax) -> Result<Node, SplError> {
+    Ok(match tok {
         "#t" => Node::Bool(true),
         "#f" => Node::Bool(false),
         _ => {
-            // Try number
+            // Integers stay exact; anything else numeric is a float
+            if let Ok(i) = tok.parse::<i64>() {
+                return Ok(Node::Int(i));
+            }
             if let Ok(n) = tok.parse::<f64>() {
-                return Node::Number(n);
+                return Ok(Node::Number(n));
             }
-            // Quoted string
-            if tok.starts_with('"') && tok.ends_with('"') && tok.len() >= 2 {
-                let inner = &tok[1..tok.len() - 1];
-                return Node::Str(inner.replace("\\\"", "\""));
+            match syntax {
+                Syntax::Current => {
+                    if let Some(body) = tok.strip_prefix('"') {
+                        return unescape(body).map(Node::Str);
+                    }
+                }
+                Syntax::Legacy => {
+                    if tok.starts_with('"') && tok.ends_with('"') && tok.len() >= 2 {
+                        return Ok(Node::Str(tok[1..tok.len() - 1].replace("\\\"", "\"")));
+                    }
+                }
             }
             // Symbol
             Node::Symbol(tok.to_string())
         }
+    })
+}
+
+/// The string a literal's body (after the opening quote) denotes.
+/// Supports `\"`, `\\`, `\n`, `\t`, and `\u{hex}`; the body must end at
+/// the first unescaped quote.
+fn unescape(body: &str) -> Result<String, SplError> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().is_empty() => return Ok(out),
+            '\\' => match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('u') => {
+                    let invalid = || SplError("invalid \\u{...} escape in string".into());
+                    let (hex, rest) = chars.as_str().strip_prefix('{').and_then(|r| r.split_once('}')).ok_or_else(invalid)?;
+                    if !(1..=6).contains(&hex.len()) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        return Err(invalid());
+                    }
+                    let code = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32).ok_or_else(invalid)?;
+                    out.push(code);
+                    chars = rest.chars();
+                }
+                Some(other) => return Err(SplError(format!("unknown escape \\{other} in string"))),
+                None => break,
+            },
+            c => out.push(c),
+        }
     }
+    Err(SplError("unterminated string".into()))
 }
 
-fn tokenize(src: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut buf = String::new();
-    let mut in_str = false;
-
-    for ch in src.chars() {
-        if in_str {
-            buf.push(ch);
-            if ch == '"' {
-                in_str = false;
-                tokens.push(buf.clone());
-                buf.clear();
-            }
-            continue;
+/// `s` as a string literal that [`parse`] reads back as `s`.
+pub fn escape_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
         }
+    }
+    out.push('"');
+    out
+}
 
+/// Split source into tokens, borrowing slices of `src`.
+fn tokenize(src: &str, syntax: Syntax) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    let current = syntax == Syntax::Current;
+
+    while let Some((start, ch)) = chars.next() {
         match ch {
+            '(' | ')' => tokens.push(&src[start..start + 1]),
+            '\'' if current => tokens.push(&src[start..start + 1]),
             '"' => {
-                flush_buf(&mut buf, &mut tokens);
-                buf.push('"');
-                in_str = true;
-            }
-            '(' | ')' => {
-                flush_buf(&mut buf, &mut tokens);
-                tokens.push(ch.to_string());
-            }
-            ' ' | '\n' | '\t' | '\r' => {
-                flush_buf(&mut buf, &mut tokens);
+                let mut end = src.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' if current => {
+                            chars.next();
+                        }
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                tokens.push(&src[start..end]);
             }
+            c if c.is_whitespace() => {}
             _ => {
-                buf.push(ch);
+                let mut end = src.len();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c == '(' || c == ')' || c == '"' || c.is_whitespace() {
+                        end = i;
+                        break;
+                    }
+                    chars.next();
+                }
+                tokens.push(&src[start..end]);
             }
         }
     }
-    flush_buf(&mut buf, &mut tokens);
     tokens
 }
 
-fn flush_buf(buf: &mut String, tokens: &mut Vec<String>) {
-    let trimmed = buf.trim().to_string();
-    if !trimmed.is_empty() {
-        for word in trimmed.split_whitespace() {
-            tokens.push(word.to_string());
-        }
-    }
-    buf.clear();
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn format_policy_breaks_long_lists() {
+        let src = r#"(policy "payments" (and (= (get req "action") "payments.create") (<= (get req "amount") 50) (member (get req "recipient") allowed_recipients)))  (policy "read" #t)"#;
+        let formatted = format_policy(src).unwrap();
+        assert_eq!(
+            formatted,
+            "(policy \"payments\"\n  (and\n    (= (get req \"action\") \"payments.create\")\n    (<= (get req \"amount\") 50)\n    (member (get req \"recipient\") allowed_recipients)))\n\n(policy \"read\" #t)\n"
+        );
+        assert_eq!(parse_all(&formatted).unwrap(), parse_all(src).unwrap());
+        assert_eq!(format_policy(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn parse_rejects_deep_nesting() {
+        let deep = "(".repeat(20_000) + &")".repeat(20_000);
+        assert!(parse(&deep).unwrap_err().0.contains("nests deeper"));
+        let ok = "(not ".repeat(200) + "#t" + &")".repeat(200);
+        assert!(parse(&ok).is_ok());
+    }
+
     #[test]
     fn parse_integer() {
-        assert_eq!(parse("42").unwrap(), Node::Number(42.0));
+        assert_eq!(parse("42").unwrap(), Node::Int(42));
+        assert_eq!(parse("9007199254740993").unwrap(), Node::Int(9_007_199_254_740_993));
+        assert_eq!(parse("42.0").unwrap(), Node::Number(42.0));
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn parse_negative_float() {
         assert_eq!(parse("-3.14").unwrap(), Node::Number(-3.14));
     }
@@ -200,6 +406,60 @@ mod tests {
         assert!(parse("#t #f").is_err());
     }
 
+    #[test]
+    fn parse_all_multiple() {
+        let exprs = parse_all("(a 1) (b 2) #t").unwrap();
+        assert_eq!(exprs.len(), 3);
+        assert_eq!(exprs[2], Node::Bool(true));
+    }
+
+    #[test]
+    fn parse_string_escapes() {
+        assert_eq!(parse(r#""say \"hi\"""#).unwrap(), Node::Str("say \"hi\"".into()));
+        assert_eq!(parse(r#""a\\b\nc\td""#).unwrap(), Node::Str("a\\b\nc\td".into()));
+        assert_eq!(parse(r#""\u{e9}\u{1F600}""#).unwrap(), Node::Str("é😀".into()));
+        assert_eq!(parse(r#"(= "\"" x)"#).unwrap(), Node::List(vec![
+            Node::Symbol("=".into()),
+            Node::Str("\"".into()),
+            Node::Symbol("x".into()),
+        ]));
+        for bad in [r#""\q""#, r#""\u{}""#, r#""\u{d800}""#, r#""\u{+41}""#, r#""\u{41""#, r#""abc"#, r#""abc\""#] {
+            assert!(parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parse_legacy_syntax() {
+        let legacy = |src| parse_with_syntax(src, &Limits::default(), Syntax::Legacy);
+        assert_eq!(legacy(r#""C:\""#).unwrap(), Node::Str("C:\\".into()));
+        assert_eq!(legacy(r#""a\nb""#).unwrap(), Node::Str("a\\nb".into()));
+        assert_eq!(legacy("'x").unwrap(), Node::Symbol("'x".into()));
+        assert!(parse(r#""C:\""#).is_err());
+    }
+
+    #[test]
+    fn format_round_trips_escaped_strings() {
+        let src = r#"(policy "quotes" (member (get req "note") '("say \"hi\"" "back\\slash" "line\nbreak" "bell\u{7}")))"#;
+        let formatted = format_policy(src).unwrap();
+        assert_eq!(parse_all(&formatted).unwrap(), parse_all(src).unwrap());
+        assert_eq!(format_policy(&formatted).unwrap(), formatted);
+        let s = "\"\\\n\t\u{1b}é";
+        assert_eq!(parse(&escape_string(s)).unwrap(), Node::Str(s.into()));
+    }
+
+    #[test]
+    fn parse_quoted_list() {
+        assert_eq!(parse(r#"'("a" 1 #t)"#).unwrap(), parse(r#"(tuple "a" 1 #t)"#).unwrap());
+        assert_eq!(parse(r#"'(("a" 1) ())"#).unwrap(), parse(r#"(tuple (tuple "a" 1) (tuple))"#).unwrap());
+        assert_eq!(parse("(member x '(1 2))").unwrap(), parse("(member x (tuple 1 2))").unwrap());
+        assert!(parse("'(a)").unwrap_err().0.contains("only literals"));
+        assert!(parse("'(1 '(2))").is_err());
+        assert!(parse("'1").is_err());
+        assert!(parse("'").is_err());
+        let limits = Limits { max_list_len: 2, ..Limits::default() };
+        assert!(parse_with_limits("'(1 2 3)", &limits).unwrap_err().0.contains("maximum length"));
+    }
+
     #[test]
     fn parse_empty() {
         assert!(parse("").is_err());
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::crypto::{ct_eq_hex, sha256_hex};
+use crate::types::SplError;
+
+/// Content address of a policy: SHA-256 hex of the trimmed source.
+pub fn policy_hash(policy: &str) -> String {
+    sha256_hex(policy.trim().as_bytes())
+}
+
+/// Content-addressed storage for policy blobs with reference tracking.
+///
+/// Blobs are keyed by [`policy_hash`]. Active tokens hold references via
+/// `retain`; `gc` removes every blob with no remaining holders. A freshly
+/// `put` blob is unreferenced, so retain it before the next `gc`.
+pub trait PolicyStore {
+    /// Store a policy and return its hash. Storing the same policy twice is a no-op.
+    fn put(&self, policy: &str) -> Result<String, SplError>;
+    /// Fetch a policy by hash. Implementations verify content against the hash.
+    fn get(&self, hash: &str) -> Result<Option<String>, SplError>;
+    /// Record that `holder` (typically a token id) references `hash`.
+    fn retain(&self, hash: &str, holder: &str) -> Result<(), SplError>;
+    /// Drop `holder`'s reference to `hash`.
+    fn release(&self, hash: &str, holder: &str) -> Result<(), SplError>;
+    /// Remove unreferenced blobs. Returns how many were removed.
+    fn gc(&self) -> Result<usize, SplError>;
+}
+
+#[derive(Default)]
+struct MemoryEntry {
+    policy: String,
+    holders: HashSet<String>,
+}
+
+/// In-memory policy store.
+#[derive(Default)]
+pub struct InMemoryPolicyStore {
+    entries: Mutex<HashMap<String, MemoryEntry>>,
+}
+
+impl InMemoryPolicyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, MemoryEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl PolicyStore for InMemoryPolicyStore {
+    fn put(&self, policy: &str) -> Result<String, SplError> {
+        let hash = policy_hash(policy);
+        self.lock().entry(hash.clone()).or_insert_with(|| MemoryEntry {
+            policy: policy.trim().to_string(),
+            holders: HashSet::new(),
+        });
+        Ok(hash)
+    }
+
+    fn get(&self, hash: &str) -> Result<Option<String>, SplError> {
+        Ok(self.lock().get(hash).map(|e| e.policy.clone()))
+    }
+
+    fn retain(&self, hash: &str, holder: &str) -> Result<(), SplError> {
+        match self.lock().get_mut(hash) {
+            Some(entry) => {
+                entry.holders.insert(holder.to_string());
+                Ok(())
+            }
+            None => Err(SplError(format!("unknown policy: {hash}"))),
+        }
+    }
+
+    fn release(&self, hash: &str, holder: &str) -> Result<(), SplError> {
+        if let Some(entry) = self.lock().get_mut(hash) {
+            entry.holders.remove(holder);
+        }
+        Ok(())
+    }
+
+    fn gc(&self) -> Result<usize, SplError> {
+        let mut entries = self.lock();
+        let before = entries.len();
+        entries.retain(|_, e| !e.holders.is_empty());
+        Ok(before - entries.len())
+    }
+}
+
+/// Filesystem policy store.
+///
+/// Layout under the root directory:
+/// - `objects/<hash>` — policy source
+/// - `refs/<hash>/<sha256(holder)>` — one empty marker file per holder
+pub struct FsPolicyStore {
+    root: PathBuf,
+}
+
+fn io_err(e: std::io::Error) -> SplError {
+    SplError(format!("policy store I/O error: {e}"))
+}
+
+/// Accept only the lowercase hex [`policy_hash`] emits, so one policy has
+/// one file name even on case-insensitive filesystems.
+fn check_hash(hash: &str) -> Result<(), SplError> {
+    if hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        Ok(())
+    } else {
+        Err(SplError(format!("invalid policy hash: {hash}")))
+    }
+}
+
+impl FsPolicyStore {
+    /// Open (creating if needed) a store rooted at `root`.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, SplError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join("objects")).map_err(io_err)?;
+        fs::create_dir_all(root.join("refs")).map_err(io_err)?;
+        Ok(Self { root })
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(hash)
+    }
+
+    fn refs_dir(&self, hash: &str) -> PathBuf {
+        self.root.join("refs").join(hash)
+    }
+
+    fn has_refs(&self, hash: &str) -> Result<bool, SplError> {
+        match fs::read_dir(self.refs_dir(hash)) {
+            Ok(mut entries) => Ok(entries.next().is_some()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(io_err(e)),
+        }
+    }
+}
+
+impl PolicyStore for FsPolicyStore {
+    fn put(&self, policy: &str) -> Result<String, SplError> {
+        let hash = policy_hash(policy);
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            // Write then rename so readers never observe a partial blob.
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, policy.trim()).map_err(io_err)?;
+            fs::rename(&tmp, &path).map_err(io_err)?;
+        }
+        Ok(hash)
+    }
+
+    fn get(&self, hash: &str) -> Result<Option<String>, SplError> {
+        check_hash(hash)?;
+        match fs::read_to_string(self.object_path(hash)) {
+            Ok(policy) if ct_eq_hex(&policy_hash(&policy), hash) => Ok(Some(policy)),
+            Ok(_) => Err(SplError(format!("policy blob {hash} is corrupt"))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_err(e)),
+        }
+    }
+
+    fn retain(&self, hash: &str, holder: &str) -> Result<(), SplError> {
+        check_hash(hash)?;
+        if !self.object_path(hash).exists() {
+            return Err(SplError(format!("unknown policy: {hash}")));
+        }
+        let dir = self.refs_dir(hash);
+        fs::create_dir_all(&dir).map_err(io_err)?;
+        fs::write(dir.join(sha256_hex(holder.as_bytes())), b"").map_err(io_err)
+    }
+
+    fn release(&self, hash: &str, holder: &str) -> Result<(), SplError> {
+        check_hash(hash)?;
+        match fs::remove_file(self.refs_dir(hash).join(sha256_hex(holder.as_bytes()))) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_err(e)),
+        }
+    }
+
+    fn gc(&self) -> Result<usize, SplError> {
+        let mut removed = 0;
+        for entry in fs::read_dir(self.root.join("objects")).map_err(io_err)? {
+            let entry = entry.map_err(io_err)?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if check_hash(&name).is_err() || self.has_refs(&name)? {
+                continue;
+            }
+            fs::remove_file(entry.path()).map_err(io_err)?;
+            let _ = fs::remove_dir(self.refs_dir(&name));
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqlitePolicyStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use rusqlite::{params, Connection, OptionalExtension};
+
+    use super::{policy_hash, PolicyStore};
+    use crate::crypto::ct_eq_hex;
+    use crate::types::SplError;
+
+    fn db_err(e: rusqlite::Error) -> SplError {
+        SplError(format!("policy store database error: {e}"))
+    }
+
+    /// SQLite policy store (`sqlite` feature).
+    pub struct SqlitePolicyStore {
+        conn: Mutex<Connection>,
+    }
+
+    impl SqlitePolicyStore {
+        /// Open (creating if needed) a store at `path`.
+        pub fn open(path: impl AsRef<Path>) -> Result<Self, SplError> {
+            Self::init(Connection::open(path).map_err(db_err)?)
+        }
+
+        /// Open a transient in-memory database.
+        pub fn open_in_memory() -> Result<Self, SplError> {
+            Self::init(Connection::open_in_memory().map_err(db_err)?)
+        }
+
+        fn init(conn: Connection) -> Result<Self, SplError> {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS policies (hash TEXT PRIMARY KEY, body TEXT NOT NULL);
+                 CREATE TABLE IF NOT EXISTS policy_refs (
+                     hash TEXT NOT NULL REFERENCES policies(hash),
+                     holder TEXT NOT NULL,
+                     PRIMARY KEY (hash, holder)
+                 );",
+            )
+            .map_err(db_err)?;
+            Ok(Self { conn: Mutex::new(conn) })
+        }
+
+        fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+            self.conn.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    impl PolicyStore for SqlitePolicyStore {
+        fn put(&self, policy: &str) -> Result<String, SplError> {
+            let hash = policy_hash(policy);
+            self.conn()
+                .execute(
+                    "INSERT OR IGNORE INTO policies (hash, body) VALUES (?1, ?2)",
+                    params![hash, policy.trim()],
+                )
+                .map_err(db_err)?;
+            Ok(hash)
+        }
+
+        fn get(&self, hash: &str) -> Result<Option<String>, SplError> {
+            let body: Option<String> = self
+                .conn()
+                .query_row("SELECT body FROM policies WHERE hash = ?1", params![hash], |r| r.get(0))
+                .optional()
+                .map_err(db_err)?;
+            match body {
+                Some(b) if !ct_eq_hex(&policy_hash(&b), hash) => {
+                    Err(SplError(format!("policy blob {hash} is corrupt")))
+                }
+                other => Ok(other),
+            }
+        }
+
+        fn retain(&self, hash: &str, holder: &str) -> Result<(), SplError> {
+            let conn = self.conn();
+            let known: Option<i64> = conn
+                .query_row("SELECT 1 FROM policies WHERE hash = ?1", params![hash], |r| r.get(0))
+                .optional()
+                .map_err(db_err)?;
+            if known.is_none() {
+                return Err(SplError(format!("unknown policy: {hash}")));
+            }
+            conn.execute(
+                "INSERT OR IGNORE INTO policy_refs (hash, holder) VALUES (?1, ?2)",
+                params![hash, holder],
+            )
+            .map_err(db_err)?;
+            Ok(())
+        }
+
+        fn release(&self, hash: &str, holder: &str) -> Result<(), SplError> {
+            self.conn()
+                .execute(
+                    "DELETE FROM policy_refs WHERE hash = ?1 AND holder = ?2",
+                    params![hash, holder],
+                )
+                .map_err(db_err)?;
+            Ok(())
+        }
+
+        fn gc(&self) -> Result<usize, SplError> {
+            self.conn()
+                .execute(
+                    "DELETE FROM policies WHERE hash NOT IN (SELECT DISTINCT hash FROM policy_refs)",
+                    [],
+                )
+                .map_err(db_err)
+        }
+    }
+}
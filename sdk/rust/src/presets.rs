@@ -0,0 +1,219 @@
+//! Vetted policy templates for common consumer delegations.
+//!
+//! Each preset is a typed option struct that renders a reviewed policy
+//! shape and mints it, so apps can grant "buy gifts for these people, up to
+//! $50 a time and twice a day" without writing SPL:
+//!
+//! ```no_run
+//! use agent_safe_spl::presets::{GiftPolicy, Preset};
+//! # let issuer_private_key = "";
+//! let token = GiftPolicy {
+//!     recipients: vec!["niece@example.com".into()],
+//!     per_txn_limit: 50.0,
+//!     per_day: 2,
+//!     currency: Some("USD".into()),
+//! }
+//! .mint(issuer_private_key, Default::default())?;
+//! # Ok::<(), agent_safe_spl::types::SplError>(())
+//! ```
+//!
+//! Request fields each preset reads are listed on its struct.
+
+use crate::money::is_currency_code;
+use crate::token::{mint, MintOptions, Token};
+use crate::types::{Node, SplError};
+
+/// A parameterized policy template.
+pub trait Preset {
+    /// The policy this preset grants. Fails on options that would grant
+    /// nothing or could not be expressed safely, such as an empty list.
+    fn policy(&self) -> Result<Node, SplError>;
+
+    /// Mint a token granting this preset.
+    fn mint(&self, private_key_hex: &str, opts: MintOptions) -> Result<Token, SplError> {
+        mint(&self.policy()?.to_string(), private_key_hex, opts)
+    }
+}
+
+/// Gift purchases for a fixed set of recipients.
+///
+/// Reads `action` (`"payments.create"`), `purpose` (`"gift"`), `recipient`,
+/// `amount`, `day`, and `currency` when one is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GiftPolicy {
+    pub recipients: Vec<String>,
+    /// Largest single purchase.
+    pub per_txn_limit: f64,
+    /// Daily purchase cap, enforced as `(limits (per_day n))`.
+    pub per_day: u32,
+    /// Require `req.currency` to be this ISO 4217 code.
+    pub currency: Option<String>,
+}
+
+impl Preset for GiftPolicy {
+    fn policy(&self) -> Result<Node, SplError> {
+        let mut clauses = vec![
+            req_eq("action", "payments.create"),
+            req_eq("purpose", "gift"),
+            req_member("recipient", "recipients", &self.recipients)?,
+            limits(&[("amount", 0.0, amount(self.per_txn_limit)?)], Some(self.per_day)),
+        ];
+        if let Some(currency) = &self.currency {
+            if !is_currency_code(currency) {
+                return Err(SplError(format!("invalid currency code: {currency:?}")));
+            }
+            clauses.push(req_eq("currency", currency));
+        }
+        Ok(and(clauses))
+    }
+}
+
+/// What an agent may do to a subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionAction {
+    Subscribe,
+    Renew,
+    Pause,
+    Cancel,
+}
+
+impl SubscriptionAction {
+    /// The `req.action` value for this action, e.g. `"subscriptions.cancel"`.
+    pub fn action(self) -> &'static str {
+        match self {
+            SubscriptionAction::Subscribe => "subscriptions.subscribe",
+            SubscriptionAction::Renew => "subscriptions.renew",
+            SubscriptionAction::Pause => "subscriptions.pause",
+            SubscriptionAction::Cancel => "subscriptions.cancel",
+        }
+    }
+}
+
+/// Subscription management with named services.
+///
+/// Reads `action`, `service`, and `monthly_price` when a price cap is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionPolicy {
+    pub services: Vec<String>,
+    pub actions: Vec<SubscriptionAction>,
+    /// Cap on `req.monthly_price` for subscribing and renewing.
+    pub max_monthly_price: Option<f64>,
+}
+
+impl Preset for SubscriptionPolicy {
+    fn policy(&self) -> Result<Node, SplError> {
+        let actions: Vec<String> = self.actions.iter().map(|a| a.action().to_string()).collect();
+        let mut clauses = vec![req_member("action", "actions", &actions)?, req_member("service", "services", &self.services)?];
+        if let Some(max) = self.max_monthly_price {
+            clauses.push(list(vec![sym("<="), req_get("monthly_price"), Node::Number(amount(max)?)]));
+        }
+        Ok(and(clauses))
+    }
+}
+
+/// Booking meetings on given calendars within working hours.
+///
+/// Reads `action` (`"calendar.book"`), `calendar`, `start` (RFC 3339, read
+/// in its own offset), and `duration_mins`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarBookingPolicy {
+    pub calendars: Vec<String>,
+    /// Local hours `[start, end)` a meeting may start in, e.g. `(9, 17)`.
+    pub hours: (u8, u8),
+    pub weekdays_only: bool,
+    pub max_duration_mins: u32,
+}
+
+impl Preset for CalendarBookingPolicy {
+    fn policy(&self) -> Result<Node, SplError> {
+        let (start, end) = self.hours;
+        if start > 24 || end > 24 || start == end {
+            return Err(SplError(format!("invalid booking hours: {start}..{end}")));
+        }
+        if self.max_duration_mins == 0 {
+            return Err(SplError("max_duration_mins must be positive".into()));
+        }
+        let mut clauses = vec![
+            req_eq("action", "calendar.book"),
+            req_member("calendar", "calendars", &self.calendars)?,
+            list(vec![sym("hour-between?"), req_get("start"), Node::Int(start.into()), Node::Int(end.into())]),
+        ];
+        if self.weekdays_only {
+            clauses.push(list(vec![sym("weekday?"), req_get("start")]));
+        }
+        clauses.push(limits(&[("duration_mins", 1.0, f64::from(self.max_duration_mins))], None));
+        Ok(and(clauses))
+    }
+}
+
+/// Sending email to fixed recipients.
+///
+/// Reads `action` (`"email.send"`), `recipient`, and `day`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailPolicy {
+    pub recipients: Vec<String>,
+    /// Daily message cap, enforced as `(limits (per_day n))`.
+    pub per_day: u32,
+}
+
+impl Preset for EmailPolicy {
+    fn policy(&self) -> Result<Node, SplError> {
+        Ok(and(vec![
+            req_eq("action", "email.send"),
+            req_member("recipient", "recipients", &self.recipients)?,
+            limits(&[], Some(self.per_day)),
+        ]))
+    }
+}
+
+fn sym(s: &str) -> Node {
+    Node::Symbol(s.to_string())
+}
+
+fn list(items: Vec<Node>) -> Node {
+    Node::List(items)
+}
+
+fn and(clauses: Vec<Node>) -> Node {
+    list(std::iter::once(sym("and")).chain(clauses).collect())
+}
+
+fn req_get(field: &str) -> Node {
+    list(vec![sym("get"), sym("req"), Node::Str(field.to_string())])
+}
+
+fn req_eq(field: &str, value: &str) -> Node {
+    list(vec![sym("="), req_get(field), Node::Str(value.to_string())])
+}
+
+/// `(member (get req "field") (tuple ...))` over a non-empty list of values
+/// the parser can read back.
+fn req_member(field: &str, what: &str, values: &[String]) -> Result<Node, SplError> {
+    if values.is_empty() {
+        return Err(SplError(format!("{what} must not be empty")));
+    }
+    if let Some(bad) = values.iter().find(|v| v.contains('"') || v.contains('\\')) {
+        return Err(SplError(format!("{what} entry {bad:?} contains a quote or backslash")));
+    }
+    let tuple = std::iter::once(sym("tuple")).chain(values.iter().map(|v| Node::Str(v.clone()))).collect();
+    Ok(list(vec![sym("member"), req_get(field), list(tuple)]))
+}
+
+fn limits(ranges: &[(&str, f64, f64)], per_day: Option<u32>) -> Node {
+    let mut entries = vec![sym("limits")];
+    for (field, min, max) in ranges {
+        entries.push(list(vec![sym(field), Node::Number(*min), Node::Number(*max)]));
+    }
+    if let Some(per_day) = per_day {
+        entries.push(list(vec![sym("per_day"), Node::Int(per_day.into())]));
+    }
+    list(entries)
+}
+
+fn amount(n: f64) -> Result<f64, SplError> {
+    if n.is_finite() && n >= 0.0 {
+        Ok(n)
+    } else {
+        Err(SplError(format!("invalid amount limit: {n}")))
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[cfg(feature = "cache")]
+use crate::cache::PolicyCache;
+use crate::attestation::AttestationVerifier;
+use crate::caveat::ChainLimits;
+use crate::denylist::{DenyListProvider, DEFAULT_MAX_STALENESS_SECS};
+use crate::fragments::FragmentResolver;
+use crate::issuers::IssuerKeys;
+use crate::money::RateProvider;
+use crate::replay::ReplayCache;
+use crate::signature::SignatureScheme;
+use crate::spend::SpendTracker;
+use crate::time::{parse_rfc3339, Clock, SystemClock};
+use crate::token::{
+    verify_any_at, verify_batch_at, verify_token_at, Challenge, PolicyParser, Presentation, Token, VerifyAnyResult, VerifyContext,
+    VerifyError, VerifyErrorCode, VerifyTokenResult,
+};
+use crate::types::{Limits, Node, StringMatch};
+use crate::uses::UsageStore;
+use crate::vars::StandardVar;
+
+/// Deployment-wide acceptance rules applied to every token after its
+/// signature verifies and before its policy is evaluated.
+#[derive(Debug, Clone)]
+pub struct VerifierProfile {
+    /// Reject tokens without an `expires`.
+    pub require_expiry: bool,
+    /// Maximum accepted `expires - issued_at`, in seconds. When set, tokens
+    /// missing either field are rejected.
+    pub max_token_lifetime_secs: Option<i64>,
+    /// Maximum age of a PoP presentation's challenge timestamp, in seconds.
+    pub max_presentation_age_secs: Option<i64>,
+    /// Tolerated disagreement between issuer, agent, and verifier clocks.
+    pub max_clock_skew_secs: i64,
+    /// Standard vars this deployment does not inject.
+    pub disabled_vars: Vec<StandardVar>,
+    /// Evaluate in strict mode: unresolved symbols, references to disabled
+    /// standard vars, and crypto predicates with no callback configured are
+    /// errors rather than `nil` or `#f`.
+    pub strict: bool,
+    /// Issuer signature schemes this deployment accepts; `None` accepts
+    /// every scheme compiled in.
+    pub accepted_algs: Option<Vec<SignatureScheme>>,
+    /// Size limits for parsing and evaluating token policies.
+    pub limits: Limits,
+    /// Bounds on caveat count, delegation depth, and total policy size.
+    pub chain_limits: ChainLimits,
+    /// Operators token policies may use, by canonical name; a policy using
+    /// any other is rejected before evaluation. `None` permits all.
+    pub allowed_ops: Option<HashSet<String>>,
+    /// Most [`Token::refresh`] steps between a token and its original
+    /// grant; `None` is unlimited, `Some(0)` rejects refreshed tokens.
+    pub max_refresh_depth: Option<u32>,
+    /// Reject tokens minted in an earlier [`Token::epoch`]; tokens without
+    /// one count as epoch 0.
+    pub min_issuer_epoch: Option<u64>,
+    /// How policies compare request strings; see [`StringMatch`].
+    pub string_match: StringMatch,
+}
+
+impl Default for VerifierProfile {
+    fn default() -> Self {
+        Self {
+            require_expiry: false,
+            max_token_lifetime_secs: None,
+            max_presentation_age_secs: None,
+            max_clock_skew_secs: 60,
+            disabled_vars: Vec::new(),
+            strict: false,
+            accepted_algs: None,
+            limits: Limits::default(),
+            chain_limits: ChainLimits::default(),
+            allowed_ops: None,
+            max_refresh_depth: None,
+            min_issuer_epoch: None,
+            string_match: StringMatch::Exact,
+        }
+    }
+}
+
+fn timestamp(value: &str) -> Result<i64, VerifyError> {
+    parse_rfc3339(value).map_err(|e| VerifyError::new(VerifyErrorCode::MalformedToken, e.to_string()))
+}
+
+impl VerifierProfile {
+    /// Whether tokens signed with `alg` may be verified under this profile.
+    pub fn accepts_alg(&self, alg: SignatureScheme) -> bool {
+        self.accepted_algs.as_ref().is_none_or(|algs| algs.contains(&alg))
+    }
+
+    /// Check the token envelope and caveat chain against this profile.
+    pub fn check_token(&self, token: &Token) -> Result<(), VerifyError> {
+        let lifetime_err = |msg: String| VerifyError::new(VerifyErrorCode::TokenLifetime, msg);
+        let needs_expiry = self.require_expiry || self.max_token_lifetime_secs.is_some();
+        let expires = match &token.expires {
+            Some(e) => Some(timestamp(e)?),
+            None if needs_expiry => return Err(lifetime_err("token has no expiry".into())),
+            None => None,
+        };
+
+        if let Some(min) = self.min_issuer_epoch {
+            let epoch = token.epoch.unwrap_or(0);
+            if epoch < min {
+                return Err(VerifyError::new(
+                    VerifyErrorCode::RevokedEpoch,
+                    format!("token epoch {epoch} is below minimum issuer epoch {min}"),
+                ));
+            }
+        }
+
+        self.chain_limits
+            .check(token)
+            .map_err(|e| VerifyError::new(VerifyErrorCode::ChainLimit, e.0))?;
+        if let Some(max) = self.max_refresh_depth {
+            if token.refresh_depth > max {
+                return Err(VerifyError::new(
+                    VerifyErrorCode::ChainLimit,
+                    format!("token refresh depth {} exceeds maximum {max}", token.refresh_depth),
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_token_lifetime_secs {
+            let issued_at = token
+                .issued_at
+                .as_deref()
+                .ok_or_else(|| lifetime_err("token has no issued_at".into()))?;
+            let issued_at = timestamp(issued_at)?;
+            let lifetime = expires.unwrap_or(issued_at) - issued_at;
+            if lifetime < 0 {
+                return Err(lifetime_err("token expires before it was issued".into()));
+            }
+            if lifetime > max {
+                return Err(lifetime_err(format!("token lifetime {lifetime}s exceeds maximum {max}s")));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject tokens used before their `issued_at` or `not_before` and,
+    /// when a challenge is given, presentations older than
+    /// `max_presentation_age_secs` or timestamped before the token was issued.
+    pub fn check_freshness(&self, token: &Token, challenge: Option<&Challenge>, now: i64) -> Result<(), VerifyError> {
+        let skew = self.max_clock_skew_secs;
+        if let Some(issued_at) = token.issued_at.as_deref() {
+            let issued_at = timestamp(issued_at)?;
+            let presented = challenge.map_or(now, |c| c.timestamp.min(now));
+            if presented + skew < issued_at {
+                return Err(VerifyError::new(
+                    VerifyErrorCode::PresentedBeforeIssuance,
+                    "token presented before issuance",
+                ));
+            }
+        }
+        if let Some(not_before) = token.not_before.as_deref() {
+            if now + skew < timestamp(not_before)? {
+                return Err(VerifyError::new(
+                    VerifyErrorCode::NotYetValid,
+                    format!("token is not valid before {not_before}"),
+                ));
+            }
+        }
+        if let (Some(max_age), Some(challenge)) = (self.max_presentation_age_secs, challenge) {
+            let age = now - challenge.timestamp;
+            if age > max_age {
+                return Err(VerifyError::new(
+                    VerifyErrorCode::PresentationExpired,
+                    format!("presentation is {age}s old, maximum {max_age}s"),
+                ));
+            }
+            if age < -skew {
+                return Err(VerifyError::new(
+                    VerifyErrorCode::PresentationExpired,
+                    "presentation timestamp is in the future",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A configured token verifier: a [`VerifierProfile`], the clock it is
+/// judged against, and optional stores such as a nonce replay cache and
+/// the issuer keys it trusts.
+pub struct Verifier {
+    pub profile: VerifierProfile,
+    pub clock: Box<dyn Clock>,
+    pub replay_cache: Option<Box<dyn ReplayCache>>,
+    /// X25519 private key (hex) for tokens with an `encrypted_policy`.
+    pub decryption_key: Option<String>,
+    /// Resolves `(include ...)` fragments in token policies.
+    pub fragments: Option<Box<dyn FragmentResolver>>,
+    /// Backs `cumulative-spend`; each allow is recorded here.
+    pub spend_tracker: Option<Arc<dyn SpendTracker>>,
+    /// Issuer keys to accept; `None` trusts each token's own key.
+    pub trusted_issuers: Option<Arc<dyn IssuerKeys>>,
+    /// Counts uses of use-limited tokens; `None` leaves them unlimited.
+    pub usage_store: Option<Arc<dyn UsageStore>>,
+    /// Trusted device attestation roots, backing `attested?` in token policies.
+    pub attestation: Option<Arc<AttestationVerifier>>,
+    /// Deny lists backing `denylist-absent?` in token policies.
+    pub denylists: Option<Arc<dyn DenyListProvider>>,
+    /// Oldest deny-list snapshot (in seconds) a decision may rely on.
+    pub denylist_max_staleness_secs: u64,
+    /// Exchange rates backing `convert` in token policies.
+    pub rates: Option<Arc<dyn RateProvider>>,
+    /// Parsed policies reused across calls.
+    #[cfg(feature = "cache")]
+    pub policy_cache: Option<Arc<PolicyCache>>,
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self::new(VerifierProfile::default())
+    }
+}
+
+impl Verifier {
+    /// A verifier using the system clock and no replay cache.
+    pub fn new(profile: VerifierProfile) -> Self {
+        Self {
+            profile,
+            clock: Box::new(SystemClock),
+            replay_cache: None,
+            decryption_key: None,
+            fragments: None,
+            spend_tracker: None,
+            trusted_issuers: None,
+            usage_store: None,
+            attestation: None,
+            denylists: None,
+            denylist_max_staleness_secs: DEFAULT_MAX_STALENESS_SECS,
+            rates: None,
+            #[cfg(feature = "cache")]
+            policy_cache: None,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn with_replay_cache(mut self, cache: impl ReplayCache + 'static) -> Self {
+        self.replay_cache = Some(Box::new(cache));
+        self
+    }
+
+    /// A verifier for trying the crate locally: default profile, a
+    /// [`crate::dev::ManualClock`] stopped at the current time, and a
+    /// [`crate::dev::MockReplayCache`]. Use [`crate::dev::DevProviders`] to
+    /// keep handles for scripting them.
+    #[cfg(feature = "dev")]
+    pub fn for_development() -> Self {
+        crate::dev::DevProviders::new(SystemClock.now_unix()).verifier()
+    }
+
+    pub fn with_fragments(mut self, resolver: impl FragmentResolver + 'static) -> Self {
+        self.fragments = Some(Box::new(resolver));
+        self
+    }
+
+    /// Share `tracker` with other verifiers or the host, which can keep a
+    /// clone of the `Arc` to read totals back.
+    pub fn with_spend_tracker(mut self, tracker: Arc<dyn SpendTracker>) -> Self {
+        self.spend_tracker = Some(tracker);
+        self
+    }
+
+    /// Accept only tokens signed by one of `issuers` (see [`crate::issuers`]).
+    pub fn with_trusted_issuers(mut self, issuers: Arc<dyn IssuerKeys>) -> Self {
+        self.trusted_issuers = Some(issuers);
+        self
+    }
+
+    /// Enforce the use limits of tokens with a `hash_chain_commitment`
+    /// (see [`crate::uses`]). Such tokens then verify only through
+    /// [`Verifier::verify_use`].
+    pub fn with_usage_store(mut self, store: Arc<dyn UsageStore>) -> Self {
+        self.usage_store = Some(store);
+        self
+    }
+
+    /// Check device attestations for `attested?` against `verifier`'s roots.
+    /// The statement must attest the token's `pop_key`.
+    pub fn with_attestation(mut self, verifier: Arc<AttestationVerifier>) -> Self {
+        self.attestation = Some(verifier);
+        self
+    }
+
+    /// Back `denylist-absent?` with `provider`, refusing to decide against a
+    /// list older than `max_staleness_secs`. Decisions report the list
+    /// versions they consulted in [`VerifyTokenResult::denylists`].
+    pub fn with_denylists(mut self, provider: Arc<dyn DenyListProvider>, max_staleness_secs: u64) -> Self {
+        self.denylists = Some(provider);
+        self.denylist_max_staleness_secs = max_staleness_secs;
+        self
+    }
+
+    /// Look up exchange rates for `convert` in `rates`.
+    pub fn with_rates(mut self, rates: Arc<dyn RateProvider>) -> Self {
+        self.rates = Some(rates);
+        self
+    }
+
+    /// Reuse parsed policies from `cache`, which other verifiers may share.
+    #[cfg(feature = "cache")]
+    pub fn with_policy_cache(mut self, cache: Arc<PolicyCache>) -> Self {
+        self.policy_cache = Some(cache);
+        self
+    }
+
+    pub fn with_decryption_key(mut self, recipient_private_key_hex: &str) -> Self {
+        self.decryption_key = Some(recipient_private_key_hex.to_string());
+        self
+    }
+
+    /// Verify a token and evaluate its policy under this verifier's profile.
+    pub fn verify(
+        &self,
+        token: &Token,
+        req: HashMap<String, Node>,
+        vars: HashMap<String, Node>,
+        presentation: Option<&Presentation>,
+    ) -> VerifyTokenResult {
+        verify_token_at(token, req, vars, &self.context(presentation, None))
+    }
+
+    /// Verify use `preimage_hex` of a use-limited token, recording the use
+    /// in this verifier's [`UsageStore`] when the policy allows.
+    pub fn verify_use(
+        &self,
+        token: &Token,
+        req: HashMap<String, Node>,
+        vars: HashMap<String, Node>,
+        presentation: Option<&Presentation>,
+        preimage_hex: &str,
+    ) -> VerifyTokenResult {
+        verify_token_at(token, req, vars, &self.context(presentation, Some(preimage_hex)))
+    }
+
+    /// Check whether any of `tokens` allows the request; see
+    /// [`crate::token::verify_any`] for ordering.
+    pub fn verify_any(
+        &self,
+        tokens: &[Token],
+        req: HashMap<String, Node>,
+        vars: HashMap<String, Node>,
+    ) -> VerifyAnyResult {
+        verify_any_at(tokens, &req, &vars, &self.context(None, None))
+    }
+
+    /// Verify many tokens, each against its own request, with shared
+    /// `vars`; see [`crate::token::verify_tokens_batch`].
+    pub fn verify_batch(&self, items: &[(Token, HashMap<String, Node>)], vars: HashMap<String, Node>) -> Vec<VerifyTokenResult> {
+        verify_batch_at(items, &vars, &self.context(None, None))
+    }
+
+    fn context<'a>(&'a self, presentation: Option<&'a Presentation>, use_preimage: Option<&'a str>) -> VerifyContext<'a> {
+        VerifyContext {
+            presentation,
+            replay_cache: self.replay_cache.as_deref(),
+            profile: &self.profile,
+            now: self.clock.now_unix(),
+            decryption_key: self.decryption_key.as_deref(),
+            parse_cache: self.parse_cache(),
+            fragments: self.fragments.as_deref(),
+            spend_tracker: self.spend_tracker.as_ref(),
+            issuers: self.trusted_issuers.as_deref(),
+            usage_store: self.usage_store.as_deref(),
+            attestation: self.attestation.as_ref(),
+            denylists: self.denylists.as_ref(),
+            denylist_max_staleness_secs: self.denylist_max_staleness_secs,
+            rates: self.rates.as_ref(),
+            use_preimage,
+            signature_verified: false,
+        }
+    }
+
+    #[cfg(feature = "cache")]
+    fn parse_cache(&self) -> Option<&dyn PolicyParser> {
+        self.policy_cache.as_deref().map(|c| c as &dyn PolicyParser)
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn parse_cache(&self) -> Option<&dyn PolicyParser> {
+        None
+    }
+}
@@ -0,0 +1,239 @@
+//! Cached remote resources (key sets, revocation lists, preludes, catalogs).
+//!
+//! [`RemoteResource`] serves a cached value while it is fresh. Once the
+//! jittered refresh time passes it keeps serving the stale value for up to
+//! `stale_for_secs` while one background thread revalidates. Fetched bodies
+//! must carry a valid signature when a signer key is configured, and a
+//! failed or invalid fetch never replaces a good value. After
+//! `failure_threshold` consecutive failures the circuit opens and no fetch
+//! is attempted for `open_for_secs`.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::crypto::verify_ed25519;
+use crate::time::{Clock, SystemClock};
+use crate::types::SplError;
+
+/// A fetched body and, when the source signs it, its signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    pub body: Vec<u8>,
+    /// Hex Ed25519 signature over `body`.
+    pub signature: Option<String>,
+}
+
+/// Transport for a remote resource (HTTP client, object store, file).
+pub trait Fetch: Send + Sync {
+    fn fetch(&self) -> Result<Fetched, SplError>;
+}
+
+/// Refresh and failure policy for a [`RemoteResource`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteConfig {
+    /// How long a fetched value is served without revalidating.
+    pub fresh_for_secs: i64,
+    /// How long past freshness a value may still be served while revalidating.
+    pub stale_for_secs: i64,
+    /// Refresh up to this fraction of `fresh_for_secs` early, at random, so
+    /// verifiers sharing a source do not refetch in lockstep.
+    pub jitter: f64,
+    /// Consecutive failures that open the circuit.
+    pub failure_threshold: u32,
+    /// How long an open circuit blocks fetching.
+    pub open_for_secs: i64,
+    /// Ed25519 public key (hex) that must sign every fetched body.
+    pub signer_public_key: Option<String>,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            fresh_for_secs: 300,
+            stale_for_secs: 3600,
+            jitter: 0.1,
+            failure_threshold: 3,
+            open_for_secs: 60,
+            signer_public_key: None,
+        }
+    }
+}
+
+/// Point-in-time view of a resource's cache and circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceStatus {
+    /// Seconds since the cached value was fetched, if there is one.
+    pub age_secs: Option<i64>,
+    pub stale: bool,
+    pub circuit_open: bool,
+    pub consecutive_failures: u32,
+}
+
+struct State<T> {
+    value: Option<Arc<T>>,
+    fetched_at: i64,
+    refresh_at: i64,
+    failures: u32,
+    open_until: Option<i64>,
+    revalidating: bool,
+}
+
+type Parser<T> = Box<dyn Fn(&[u8]) -> Result<T, SplError> + Send + Sync>;
+
+struct Inner<T> {
+    fetcher: Box<dyn Fetch>,
+    parse: Parser<T>,
+    config: RemoteConfig,
+    clock: Box<dyn Clock + Send + Sync>,
+    state: Mutex<State<T>>,
+}
+
+/// A remote value cached with stale-while-revalidate and circuit breaking.
+pub struct RemoteResource<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T: Send + Sync + 'static> RemoteResource<T> {
+    pub fn new(
+        fetcher: impl Fetch + 'static,
+        parse: impl Fn(&[u8]) -> Result<T, SplError> + Send + Sync + 'static,
+        config: RemoteConfig,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                fetcher: Box::new(fetcher),
+                parse: Box::new(parse),
+                config,
+                clock: Box::new(SystemClock),
+                state: Mutex::new(State {
+                    value: None,
+                    fetched_at: 0,
+                    refresh_at: 0,
+                    failures: 0,
+                    open_until: None,
+                    revalidating: false,
+                }),
+            }),
+        }
+    }
+
+    /// Replace the clock. Call before the first [`RemoteResource::get`].
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("with_clock called after the resource was shared")
+            .clock = Box::new(clock);
+        self
+    }
+
+    /// The current value. Fetches synchronously only when nothing servable
+    /// is cached; a stale value is returned at once and revalidated in the
+    /// background.
+    pub fn get(&self) -> Result<Arc<T>, SplError> {
+        let inner = &self.inner;
+        let now = inner.clock.now_unix();
+        let mut state = inner.lock();
+        if let Some(value) = state.value.clone() {
+            if now < state.refresh_at {
+                return Ok(value);
+            }
+            if now < state.fetched_at.saturating_add(inner.config.fresh_for_secs + inner.config.stale_for_secs) {
+                if !state.revalidating && !inner.circuit_open(&state, now) {
+                    state.revalidating = true;
+                    let inner = Arc::clone(inner);
+                    thread::spawn(move || {
+                        let _ = inner.refresh();
+                    });
+                }
+                return Ok(value);
+            }
+        }
+        drop(state);
+        inner.refresh()
+    }
+
+    /// Fetch now, bypassing freshness but not an open circuit.
+    pub fn refresh(&self) -> Result<Arc<T>, SplError> {
+        self.inner.refresh()
+    }
+
+    pub fn status(&self) -> ResourceStatus {
+        let now = self.inner.clock.now_unix();
+        let state = self.inner.lock();
+        ResourceStatus {
+            age_secs: state.value.as_ref().map(|_| now - state.fetched_at),
+            stale: state.value.is_some() && now >= state.refresh_at,
+            circuit_open: self.inner.circuit_open(&state, now),
+            consecutive_failures: state.failures,
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn circuit_open(&self, state: &State<T>, now: i64) -> bool {
+        state.open_until.is_some_and(|until| now < until)
+    }
+
+    fn refresh(&self) -> Result<Arc<T>, SplError> {
+        let now = self.clock.now_unix();
+        {
+            let mut state = self.lock();
+            if self.circuit_open(&state, now) {
+                state.revalidating = false;
+                return Err(SplError("remote resource circuit is open".into()));
+            }
+        }
+
+        let fetched = self.fetch_validated();
+
+        let mut state = self.lock();
+        state.revalidating = false;
+        match fetched {
+            Ok(value) => {
+                let value = Arc::new(value);
+                state.value = Some(Arc::clone(&value));
+                state.fetched_at = now;
+                state.refresh_at = now + self.jittered_freshness();
+                state.failures = 0;
+                state.open_until = None;
+                Ok(value)
+            }
+            Err(e) => {
+                state.failures += 1;
+                if state.failures >= self.config.failure_threshold {
+                    state.open_until = Some(now + self.config.open_for_secs);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn fetch_validated(&self) -> Result<T, SplError> {
+        let fetched = self.fetcher.fetch()?;
+        if let Some(signer) = &self.config.signer_public_key {
+            let signature = fetched
+                .signature
+                .as_deref()
+                .ok_or_else(|| SplError("remote resource is unsigned".into()))?;
+            if !verify_ed25519(&fetched.body, signature, signer) {
+                return Err(SplError("remote resource signature is invalid".into()));
+            }
+        }
+        (self.parse)(&fetched.body)
+    }
+
+    fn jittered_freshness(&self) -> i64 {
+        let fresh = self.config.fresh_for_secs;
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return fresh;
+        }
+        let mut bytes = [0u8; 4];
+        getrandom::fill(&mut bytes).expect("OS RNG failed");
+        let unit = f64::from(u32::from_le_bytes(bytes)) / f64::from(u32::MAX);
+        fresh - (fresh as f64 * jitter * unit) as i64
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Tracks presentation nonces a verifier has already accepted.
+///
+/// `verify_token_with_pop` calls `check_and_record` once the presentation
+/// signature has been verified; a `false` return rejects the presentation
+/// as a replay.
+pub trait ReplayCache {
+    /// Record `nonce` as used. Returns `false` if it was already seen.
+    fn check_and_record(&self, nonce: &str) -> bool;
+}
+
+/// Unbounded in-memory replay cache. Suitable for tests and single-process
+/// verifiers; production deployments should expire nonces alongside the
+/// challenge lifetime.
+#[derive(Default)]
+pub struct InMemoryReplayCache {
+    seen: Mutex<HashSet<String>>,
+}
+
+impl InMemoryReplayCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReplayCache for InMemoryReplayCache {
+    fn check_and_record(&self, nonce: &str) -> bool {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.insert(nonce.to_string())
+    }
+}
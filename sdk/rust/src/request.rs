@@ -0,0 +1,99 @@
+//! Canonical request encoding, so receipts, audit entries, approvals and
+//! PoP evidence can commit to the exact request a decision was made on.
+//!
+//! [`canonical_request`] encodes a request as the tag
+//! `agent-safe-request-v1\0`, then the number of fields, then each field in
+//! byte order of its key as the key followed by the value. Counts and
+//! lengths are 8-byte big-endian; strings are a length and UTF-8 bytes.
+//! A value is one type byte and its payload:
+//!
+//! | Type | Byte | Payload |
+//! |------|------|---------|
+//! | `Bool` | `b` | `0` or `1` |
+//! | `Int` | `i` | 8-byte big-endian two's complement |
+//! | `Number` | `f` | 8-byte big-endian IEEE 754 bits; `-0.0` as `0.0`, NaN as `0x7ff8000000000000` |
+//! | `Str` | `s` | string |
+//! | `Symbol` | `y` | string |
+//! | `List` | `l` | count, then each value |
+//! | `Map` | `m` | count, then each key string and value, in key order |
+//! | `Money` | `$` | minor units as for `Int`, then the currency string |
+//! | `Nil` | `n` | nothing |
+//!
+//! Types are part of the encoding: `5` and `5.0`, or `"x"` and the symbol
+//! `x`, hash differently, so build requests the same way on both sides
+//! (e.g. with [`crate::builder::RequestBuilder`]). [`request_hash`] is the
+//! SHA-256 hex of the encoding.
+
+use std::collections::HashMap;
+
+use crate::crypto::sha256_hex;
+use crate::types::Node;
+
+const TAG: &[u8] = b"agent-safe-request-v1\0";
+
+/// The canonical encoding of `req`.
+pub fn canonical_request(req: &HashMap<String, Node>) -> Vec<u8> {
+    let mut fields: Vec<(&String, &Node)> = req.iter().collect();
+    fields.sort_unstable_by_key(|(key, _)| key.as_bytes());
+    let mut out = TAG.to_vec();
+    out.extend((fields.len() as u64).to_be_bytes());
+    for (key, value) in fields {
+        encode_str(&mut out, key);
+        encode_value(&mut out, value);
+    }
+    out
+}
+
+/// SHA-256 hex of [`canonical_request`].
+pub fn request_hash(req: &HashMap<String, Node>) -> String {
+    sha256_hex(&canonical_request(req))
+}
+
+fn encode_str(out: &mut Vec<u8>, s: &str) {
+    out.extend((s.len() as u64).to_be_bytes());
+    out.extend(s.as_bytes());
+}
+
+pub(crate) fn encode_value(out: &mut Vec<u8>, value: &Node) {
+    match value {
+        Node::Bool(b) => out.extend([b'b', u8::from(*b)]),
+        Node::Int(i) => {
+            out.push(b'i');
+            out.extend(i.to_be_bytes());
+        }
+        Node::Number(n) => {
+            let n = if n.is_nan() { f64::NAN } else if *n == 0.0 { 0.0 } else { *n };
+            out.push(b'f');
+            out.extend(n.to_bits().to_be_bytes());
+        }
+        Node::Str(s) => {
+            out.push(b's');
+            encode_str(out, s);
+        }
+        Node::Symbol(s) => {
+            out.push(b'y');
+            encode_str(out, s);
+        }
+        Node::List(items) => {
+            out.push(b'l');
+            out.extend((items.len() as u64).to_be_bytes());
+            for item in items {
+                encode_value(out, item);
+            }
+        }
+        Node::Map(entries) => {
+            out.push(b'm');
+            out.extend((entries.len() as u64).to_be_bytes());
+            for (key, value) in entries {
+                encode_str(out, key);
+                encode_value(out, value);
+            }
+        }
+        Node::Money { minor_units, currency } => {
+            out.push(b'$');
+            out.extend(minor_units.to_be_bytes());
+            encode_str(out, currency);
+        }
+        Node::Nil => out.push(b'n'),
+    }
+}
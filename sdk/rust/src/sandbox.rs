@@ -0,0 +1,210 @@
+//! Side-effect-free policy preview.
+//!
+//! A [`Sandbox`] evaluates policy source against a virtual clock and
+//! scripted counter and spend values, with every crypto predicate
+//! satisfied, so owner-facing apps can show what a policy would do without
+//! touching real stores. A [`Scenario`] captures the same setup plus a
+//! sequence of requests as JSON for `agent-safe sandbox`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::evaluator::eval_policy_detailed;
+use crate::obligations::Obligation;
+use crate::parser::parse_all;
+use crate::time::{format_rfc3339, parse_rfc3339, FixedClock};
+use crate::token::select_clause;
+use crate::types::{CallbackState, CounterKey, CryptoCallbacks, Env, Node, SplError};
+
+/// Outcome of one sandboxed evaluation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxOutcome {
+    pub allow: bool,
+    pub obligations: Vec<Obligation>,
+    pub gas_used: i64,
+    /// Why evaluation failed; such requests are denied.
+    pub error: Option<String>,
+    /// Code from the policy's `deny-with` when it denied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+}
+
+/// Virtual environment for previewing policies.
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    /// Virtual time (Unix seconds), exposed as the `now` and `day` vars.
+    pub now: i64,
+    pub vars: HashMap<String, Node>,
+    /// Usage counts by [`CounterKey`] (the action, for `per-day-count`),
+    /// then period.
+    pub counters: HashMap<String, HashMap<String, i64>>,
+    /// `spent-for` values by key.
+    pub spent: HashMap<String, f64>,
+    /// Answer every crypto predicate (`dpop_ok?`, `merkle_ok?`, ...) with
+    /// this value instead of checking evidence.
+    pub evidence: bool,
+    /// After an allow, count the request against its action and day and add
+    /// its `amount` to its `recipient`'s spend, so scenarios can play out a
+    /// sequence of requests.
+    pub record_allowed: bool,
+    pub strict: bool,
+}
+
+impl Sandbox {
+    /// A sandbox at `now` with evidence available.
+    pub fn new(now: i64) -> Self {
+        Self { now, evidence: true, ..Self::default() }
+    }
+
+    pub fn advance(&mut self, secs: i64) {
+        self.now += secs;
+    }
+
+    /// Set the count under `key` for `period`; build multi-part keys with
+    /// [`CounterKey::new`].
+    pub fn set_count(&mut self, key: impl Into<CounterKey>, period: &str, count: i64) {
+        self.counters.entry(key.into().as_str().to_string()).or_default().insert(period.to_string(), count);
+    }
+
+    pub fn set_spent(&mut self, key: &str, amount: f64) {
+        self.spent.insert(key.to_string(), amount);
+    }
+
+    fn day(&self) -> String {
+        format_rfc3339(self.now)[..10].to_string()
+    }
+
+    /// The evaluation environment for `req`: scripted values, the virtual
+    /// clock for `now`, a `day` var unless set explicitly, and `req["day"]`
+    /// filled from the virtual clock when absent.
+    pub fn env(&self, mut req: HashMap<String, Node>) -> Env {
+        let mut vars = self.vars.clone();
+        vars.entry("day".into()).or_insert_with(|| Node::Str(self.day()));
+        req.entry("day".into()).or_insert_with(|| Node::Str(self.day()));
+
+        let counters = self.counters.clone();
+        let spent = self.spent.clone();
+        let evidence = self.evidence;
+        Env {
+            req,
+            vars,
+            usage_count: Arc::new(move |key, day| {
+                counters.get(key.as_str()).and_then(|days| days.get(day)).copied().unwrap_or(0)
+            }),
+            spent_for: Arc::new(move |key| spent.get(key).copied().unwrap_or(0.0)),
+            crypto: CryptoCallbacks {
+                dpop_ok: CallbackState::Configured(Arc::new(move || evidence)),
+                merkle_ok: Some(Arc::new(move |_| evidence)),
+                vrf_ok: CallbackState::Configured(Arc::new(move |_, _| evidence)),
+                thresh_ok: CallbackState::Configured(Arc::new(move || evidence)),
+            },
+            clock: Arc::new(FixedClock(self.now)),
+            strict: self.strict,
+            ..Env::default()
+        }
+    }
+
+    /// Evaluate policy source (one expression or named clauses) against `req`.
+    pub fn evaluate(&mut self, policy: &str, req: HashMap<String, Node>) -> SandboxOutcome {
+        let env = self.env(req);
+        let result = parse_all(policy)
+            .and_then(|exprs| select_clause(&exprs, &env.req))
+            .and_then(|ast| eval_policy_detailed(&ast, &env));
+        let outcome = match result {
+            Ok(outcome) => {
+                let allow = outcome.value.is_truthy();
+                SandboxOutcome {
+                    allow,
+                    obligations: if allow { outcome.obligations } else { Vec::new() },
+                    gas_used: outcome.gas_used,
+                    error: None,
+                    reason_code: if allow { None } else { outcome.reason_code },
+                }
+            }
+            Err(e) => SandboxOutcome {
+                allow: false,
+                obligations: Vec::new(),
+                gas_used: 0,
+                error: Some(e.0),
+                reason_code: None,
+            },
+        };
+        if outcome.allow && self.record_allowed {
+            self.record(&env.req);
+        }
+        outcome
+    }
+
+    fn record(&mut self, req: &HashMap<String, Node>) {
+        let field = |key: &str| req.get(key).and_then(Node::as_str).map(str::to_string);
+        if let (Some(action), Some(day)) = (field("action"), field("day")) {
+            *self.counters.entry(CounterKey::action(&action).as_str().to_string()).or_default().entry(day).or_default() += 1;
+        }
+        if let (Some(recipient), Some(amount)) = (field("recipient"), req.get("amount").and_then(Node::as_number)) {
+            *self.spent.entry(recipient).or_default() += amount;
+        }
+    }
+
+    /// Run every request of `scenario` in order from its starting state.
+    pub fn run_scenario(policy: &str, scenario: &Scenario) -> Result<Vec<ScenarioResult>, SplError> {
+        let mut sandbox = Sandbox {
+            now: parse_rfc3339(&scenario.now)?,
+            vars: scenario.vars.clone(),
+            counters: scenario.counters.clone(),
+            spent: scenario.spent.clone(),
+            evidence: scenario.evidence,
+            record_allowed: scenario.record_allowed,
+            strict: scenario.strict,
+        };
+        Ok(scenario
+            .requests
+            .iter()
+            .map(|step| {
+                sandbox.advance(step.advance_secs);
+                ScenarioResult { name: step.name.clone(), outcome: sandbox.evaluate(policy, step.request.clone()) }
+            })
+            .collect())
+    }
+}
+
+/// A preview scenario: starting state and the requests to play through it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// RFC 3339 virtual start time.
+    pub now: String,
+    #[serde(default)]
+    pub vars: HashMap<String, Node>,
+    #[serde(default)]
+    pub counters: HashMap<String, HashMap<String, i64>>,
+    #[serde(default)]
+    pub spent: HashMap<String, f64>,
+    #[serde(default = "evidence_default")]
+    pub evidence: bool,
+    #[serde(default)]
+    pub record_allowed: bool,
+    #[serde(default)]
+    pub strict: bool,
+    pub requests: Vec<ScenarioStep>,
+}
+
+fn evidence_default() -> bool {
+    true
+}
+
+/// One request in a [`Scenario`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioStep {
+    pub name: String,
+    pub request: HashMap<String, Node>,
+    /// Seconds to advance the virtual clock before this request.
+    #[serde(default)]
+    pub advance_secs: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub outcome: SandboxOutcome,
+}
@@ -0,0 +1,67 @@
+//! Action scopes: globs over `req.action` that bound what a token covers.
+//!
+//! A token minted with [`crate::token::MintOptions::scope`] signs a list of
+//! action globs; verifiers reject a request whose action matches none of
+//! them before parsing the policy. `*` matches any run of characters, so
+//! `payments.*` covers `payments.create` and `payments.refund.partial` but
+//! not `payments`. Policies test the same globs with `(action-in "payments.*")`.
+
+use std::collections::HashMap;
+
+use crate::token::{Token, VerifyError, VerifyErrorCode};
+use crate::types::{Node, SplError, StringMatch};
+
+/// Most globs one token may carry.
+pub const MAX_SCOPE_GLOBS: usize = 64;
+
+/// Whether `action` matches `glob`.
+pub fn glob_matches(glob: &str, action: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = action.strip_prefix(first) else { return false };
+    let mut parts: Vec<&str> = parts.collect();
+    // Without a `*` the glob is the whole action.
+    let Some(last) = parts.pop() else { return rest.is_empty() };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Whether `action` matches any of `globs`.
+pub fn in_scope<S: AsRef<str>>(globs: &[S], action: &str) -> bool {
+    globs.iter().any(|glob| glob_matches(glob.as_ref(), action))
+}
+
+/// Scopes are bounded lists of non-empty globs free of control characters.
+pub(crate) fn check_scope_globs(scope: &[String]) -> Result<(), SplError> {
+    if scope.len() > MAX_SCOPE_GLOBS {
+        return Err(SplError(format!("scope has {} globs, maximum {MAX_SCOPE_GLOBS}", scope.len())));
+    }
+    if let Some(glob) = scope.iter().find(|g| !(1..=128).contains(&g.len()) || g.chars().any(char::is_control)) {
+        return Err(SplError(format!("invalid scope glob: {glob:?}")));
+    }
+    Ok(())
+}
+
+/// Reject `req` if `token` is scoped and the request's action is outside
+/// it, comparing the action and globs under `strings`.
+pub fn check_scope(token: &Token, req: &HashMap<String, Node>, strings: StringMatch) -> Result<(), VerifyError> {
+    if token.scope.is_empty() {
+        return Ok(());
+    }
+    let normalize = |s| strings.normalize(s).map_err(|e| VerifyError::new(VerifyErrorCode::Evaluation, e.0));
+    match req.get("action") {
+        Some(Node::Str(action)) => {
+            let globs = token.scope.iter().map(|glob| normalize(glob)).collect::<Result<Vec<_>, _>>()?;
+            if in_scope(&globs, &normalize(action)?) {
+                return Ok(());
+            }
+            Err(VerifyError::new(VerifyErrorCode::OutOfScope, format!("action {action:?} is outside the token's scope")))
+        }
+        _ => Err(VerifyError::new(VerifyErrorCode::OutOfScope, "scoped token requires a request action")),
+    }
+}
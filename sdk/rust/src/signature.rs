@@ -0,0 +1,173 @@
+//! Issuer signature schemes.
+//!
+//! Ed25519 is always available. ECDSA over P-256 (`p256` feature) and
+//! secp256k1 (`secp256k1` feature) serve verifiers whose HSMs only speak
+//! ECDSA. ECDSA keys are hex SEC1 compressed points (public) and 32-byte
+//! scalars (private); signatures are hex `r || s`, SHA-256 prehashed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{key_from_hex, public_key_hex, sign_hex};
+use crate::crypto::verify_ed25519;
+use crate::types::SplError;
+
+/// Algorithm of a token's issuer signature, named as in JOSE.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    #[default]
+    #[serde(rename = "EdDSA")]
+    Ed25519,
+    #[serde(rename = "ES256")]
+    P256,
+    #[serde(rename = "ES256K")]
+    Secp256k1,
+}
+
+impl SignatureScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            SignatureScheme::Ed25519 => "EdDSA",
+            SignatureScheme::P256 => "ES256",
+            SignatureScheme::Secp256k1 => "ES256K",
+        }
+    }
+
+    /// Look up a scheme by its JOSE name.
+    pub fn from_name(name: &str) -> Option<SignatureScheme> {
+        [SignatureScheme::Ed25519, SignatureScheme::P256, SignatureScheme::Secp256k1]
+            .into_iter()
+            .find(|s| s.name() == name)
+    }
+
+    pub fn is_ed25519(&self) -> bool {
+        *self == SignatureScheme::Ed25519
+    }
+
+    /// Whether this build can sign and verify with the scheme.
+    pub fn is_supported(self) -> bool {
+        match self {
+            SignatureScheme::Ed25519 => true,
+            SignatureScheme::P256 => cfg!(feature = "p256"),
+            SignatureScheme::Secp256k1 => cfg!(feature = "secp256k1"),
+        }
+    }
+
+    /// Every scheme compiled into this build.
+    pub fn supported() -> Vec<SignatureScheme> {
+        [SignatureScheme::Ed25519, SignatureScheme::P256, SignatureScheme::Secp256k1]
+            .into_iter()
+            .filter(|s| s.is_supported())
+            .collect()
+    }
+
+    fn unsupported(self) -> SplError {
+        SplError(format!("signature scheme {} is not enabled in this build", self.name()))
+    }
+
+    /// Generate a keypair. Returns (public_key_hex, private_key_hex).
+    pub fn generate_keypair(self) -> Result<(String, String), SplError> {
+        match self {
+            SignatureScheme::Ed25519 => Ok(crate::token::generate_keypair()),
+            #[cfg(feature = "p256")]
+            SignatureScheme::P256 => Ok(ecdsa_p256::generate_keypair()),
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1 => Ok(ecdsa_k256::generate_keypair()),
+            #[allow(unreachable_patterns)]
+            other => Err(other.unsupported()),
+        }
+    }
+
+    /// Public key for a private key.
+    pub fn public_key(self, private_key_hex: &str) -> Result<String, SplError> {
+        match self {
+            SignatureScheme::Ed25519 => public_key_hex(&key_from_hex(private_key_hex, "private key")?),
+            #[cfg(feature = "p256")]
+            SignatureScheme::P256 => ecdsa_p256::public_key(private_key_hex),
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1 => ecdsa_k256::public_key(private_key_hex),
+            #[allow(unreachable_patterns)]
+            other => Err(other.unsupported()),
+        }
+    }
+
+    /// Sign `message`, returning the hex signature.
+    pub fn sign(self, private_key_hex: &str, message: &[u8]) -> Result<String, SplError> {
+        match self {
+            SignatureScheme::Ed25519 => sign_hex(&key_from_hex(private_key_hex, "private key")?, message),
+            #[cfg(feature = "p256")]
+            SignatureScheme::P256 => ecdsa_p256::sign(private_key_hex, message),
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1 => ecdsa_k256::sign(private_key_hex, message),
+            #[allow(unreachable_patterns)]
+            other => Err(other.unsupported()),
+        }
+    }
+
+    /// Verify a hex signature. Unsupported schemes never verify.
+    pub fn verify(self, message: &[u8], signature_hex: &str, public_key_hex: &str) -> bool {
+        match self {
+            SignatureScheme::Ed25519 => verify_ed25519(message, signature_hex, public_key_hex),
+            #[cfg(feature = "p256")]
+            SignatureScheme::P256 => ecdsa_p256::verify(message, signature_hex, public_key_hex),
+            #[cfg(feature = "secp256k1")]
+            SignatureScheme::Secp256k1 => ecdsa_k256::verify(message, signature_hex, public_key_hex),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+/// ECDSA over a RustCrypto curve; instantiated once per enabled curve.
+#[cfg(any(feature = "p256", feature = "secp256k1"))]
+macro_rules! ecdsa_scheme {
+    ($module:ident, $curve:ident) => {
+        mod $module {
+            use $curve::ecdsa::signature::{Signer, Verifier};
+            use $curve::ecdsa::{Signature, SigningKey, VerifyingKey};
+
+            use crate::types::SplError;
+
+            fn signing_key(private_key_hex: &str) -> Result<SigningKey, SplError> {
+                let bytes = hex::decode(private_key_hex).map_err(|e| SplError(format!("invalid private key hex: {e}")))?;
+                SigningKey::from_slice(&bytes).map_err(|_| SplError("invalid ECDSA private key".into()))
+            }
+
+            fn encode_public(key: &SigningKey) -> String {
+                hex::encode(key.verifying_key().to_encoded_point(true).as_bytes())
+            }
+
+            pub fn generate_keypair() -> (String, String) {
+                loop {
+                    let mut seed = [0u8; 32];
+                    getrandom::fill(&mut seed).expect("OS RNG failed");
+                    // Retry the negligible fraction of seeds outside the scalar field.
+                    if let Ok(key) = SigningKey::from_slice(&seed) {
+                        return (encode_public(&key), hex::encode(key.to_bytes()));
+                    }
+                }
+            }
+
+            pub fn public_key(private_key_hex: &str) -> Result<String, SplError> {
+                Ok(encode_public(&signing_key(private_key_hex)?))
+            }
+
+            pub fn sign(private_key_hex: &str, message: &[u8]) -> Result<String, SplError> {
+                let signature: Signature = signing_key(private_key_hex)?.sign(message);
+                Ok(hex::encode(signature.to_bytes()))
+            }
+
+            pub fn verify(message: &[u8], signature_hex: &str, public_key_hex: &str) -> bool {
+                let Ok(sig_bytes) = hex::decode(signature_hex) else { return false };
+                let Ok(pub_bytes) = hex::decode(public_key_hex) else { return false };
+                let Ok(signature) = Signature::from_slice(&sig_bytes) else { return false };
+                let Ok(key) = VerifyingKey::from_sec1_bytes(&pub_bytes) else { return false };
+                key.verify(message, &signature).is_ok()
+            }
+        }
+    };
+}
+
+#[cfg(feature = "p256")]
+ecdsa_scheme!(ecdsa_p256, p256);
+#[cfg(feature = "secp256k1")]
+ecdsa_scheme!(ecdsa_k256, k256);
@@ -0,0 +1,69 @@
+//! Cumulative spend per action, backing `cumulative-spend`.
+//!
+//! A [`SpendTracker`] in [`crate::types::Env`] answers how much has been
+//! spent on an action over a range of days, so a policy can cap a running
+//! total rather than a single request:
+//!
+//! ```text
+//! (<= (cumulative-spend "payments.create" "week") 200)
+//! ```
+//!
+//! The period (`"day"`, `"week"`, or `"month"`) is the one containing
+//! `req.day`. Verifiers call [`record_allowed`] after each allow, so the
+//! tracker sees every spend it approved. [`InMemorySpendTracker`] suits a
+//! single process; hosts with many verifiers implement the trait over
+//! shared storage.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::time::is_date;
+use crate::types::{Node, SplError};
+
+/// Store of allowed spend, keyed by action and day.
+pub trait SpendTracker: Send + Sync {
+    /// Total recorded against `action` on days `from..=to` (`YYYY-MM-DD`).
+    fn spent_between(&self, action: &str, from: &str, to: &str) -> Result<f64, SplError>;
+    /// Add `amount` to the spend on `action` for `day`.
+    fn record(&self, action: &str, day: &str, amount: f64) -> Result<(), SplError>;
+}
+
+/// In-memory spend totals.
+#[derive(Debug, Default)]
+pub struct InMemorySpendTracker {
+    totals: Mutex<BTreeMap<(String, String), f64>>,
+}
+
+impl InMemorySpendTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(String, String), f64>> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SpendTracker for InMemorySpendTracker {
+    fn spent_between(&self, action: &str, from: &str, to: &str) -> Result<f64, SplError> {
+        let range = (action.to_string(), from.to_string())..=(action.to_string(), to.to_string());
+        Ok(self.lock().range(range).map(|(_, amount)| amount).sum())
+    }
+
+    fn record(&self, action: &str, day: &str, amount: f64) -> Result<(), SplError> {
+        if !is_date(day) {
+            return Err(SplError(format!("expected a YYYY-MM-DD day, got {day:?}")));
+        }
+        *self.lock().entry((action.to_string(), day.to_string())).or_default() += amount;
+        Ok(())
+    }
+}
+
+/// Record an allowed request's `amount` against its `action` and `day`.
+/// Requests without all three spend nothing trackable and are skipped.
+pub fn record_allowed(tracker: &dyn SpendTracker, req: &HashMap<String, Node>) -> Result<(), SplError> {
+    match (req.get("action"), req.get("day"), req.get("amount").and_then(Node::as_number)) {
+        (Some(Node::Str(action)), Some(Node::Str(day)), Some(amount)) => tracker.record(action, day, amount),
+        _ => Ok(()),
+    }
+}
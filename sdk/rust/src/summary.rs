@@ -0,0 +1,194 @@
+//! Stable decoded view of a token for CLIs and dashboards.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::keys::key_id;
+use crate::limits::find_limits;
+use crate::parser::parse_all_with_syntax;
+use crate::token::{named_clauses, Token};
+use crate::types::{Limits, Node};
+
+/// Numeric bounds a clause places on a request field. Either side may be
+/// open when the policy only constrains one direction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldBound {
+    pub field: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Summary of one policy, or one `(policy "name" ...)` clause.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClauseSummary {
+    /// Clause name; `None` for a single-policy token.
+    pub name: Option<String>,
+    /// One line per top-level requirement, in policy order.
+    pub explanation: Vec<String>,
+    /// Numeric bounds from top-level comparisons and the `(limits ...)` block.
+    pub envelope: Vec<FieldBound>,
+    pub per_day: Option<f64>,
+    /// Request fields the clause pins to a literal with `=`.
+    pub claims: BTreeMap<String, String>,
+}
+
+/// Decoded, serializable view of a token. Built without verifying the
+/// signature, so callers must verify before trusting any of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenSummary {
+    pub version: String,
+    /// [`Token::id`].
+    pub token_id: String,
+    /// Key id of the issuer public key (see [`crate::keys::key_id`]).
+    pub issuer_fingerprint: String,
+    /// Signed issuer key id, if any.
+    pub kid: Option<String>,
+    /// Signed action globs; empty when the token covers every action.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scope: Vec<String>,
+    pub issued_at: Option<String>,
+    pub expires: Option<String>,
+    pub sealed: bool,
+    /// Whether presentations must be signed by `pop_key`.
+    pub pop_bound: bool,
+    /// Caveats appended since minting.
+    pub chain_depth: u32,
+    /// The policy is sealed to the verifier; `clauses` is then empty.
+    pub policy_encrypted: bool,
+    pub clauses: Vec<ClauseSummary>,
+    /// Set when the policy does not parse; `clauses` is then empty.
+    pub policy_error: Option<String>,
+}
+
+impl From<&Token> for TokenSummary {
+    fn from(token: &Token) -> Self {
+        let clauses = parse_all_with_syntax(&token.policy, &Limits::default(), token.syntax()).and_then(|exprs| {
+            Ok(match named_clauses(&exprs)? {
+                Some(named) => named.into_iter().map(|(name, body)| summarize_clause(Some(name), &body)).collect(),
+                None => exprs.iter().map(|body| summarize_clause(None, body)).collect(),
+            })
+        });
+        let (clauses, policy_error) = match clauses {
+            _ if token.encrypted_policy.is_some() => (Vec::new(), None),
+            Ok(clauses) => (clauses, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        TokenSummary {
+            version: token.version.clone(),
+            token_id: token.id().into_owned(),
+            issuer_fingerprint: key_id(&token.public_key),
+            kid: token.kid.clone(),
+            scope: token.scope.clone(),
+            issued_at: token.issued_at.clone(),
+            expires: token.expires.clone(),
+            sealed: token.sealed,
+            pop_bound: token.pop_key.is_some(),
+            chain_depth: token.caveats.len() as u32,
+            policy_encrypted: token.encrypted_policy.is_some(),
+            clauses,
+            policy_error,
+        }
+    }
+}
+
+pub(crate) fn conjuncts(ast: &Node) -> &[Node] {
+    match ast {
+        Node::List(items) if items.first() == Some(&Node::Symbol("and".into())) => &items[1..],
+        _ => std::slice::from_ref(ast),
+    }
+}
+
+pub(crate) fn summarize_clause(name: Option<String>, ast: &Node) -> ClauseSummary {
+    let mut envelope: Vec<FieldBound> = Vec::new();
+    let mut claims = BTreeMap::new();
+    let mut bound = |field: &str, min: Option<f64>, max: Option<f64>| {
+        let entry = match envelope.iter_mut().find(|b| b.field == field) {
+            Some(entry) => entry,
+            None => {
+                envelope.push(FieldBound { field: field.to_string(), min: None, max: None });
+                envelope.last_mut().unwrap()
+            }
+        };
+        if let Some(min) = min {
+            entry.min = Some(entry.min.map_or(min, |m| m.max(min)));
+        }
+        if let Some(max) = max {
+            entry.max = Some(entry.max.map_or(max, |m| m.min(max)));
+        }
+    };
+
+    let limits = find_limits(ast).ok().flatten().unwrap_or_default();
+    for range in &limits.ranges {
+        bound(&range.field, Some(range.min), Some(range.max));
+    }
+    for conjunct in conjuncts(ast) {
+        let Node::List(items) = conjunct else { continue };
+        match items.as_slice() {
+            [Node::Symbol(op), a, b] => match (op.as_str(), req_field(a), req_field(b), a, b) {
+                ("<=" | "<", Some(f), None, _, n) if n.as_number().is_some() => bound(f, None, n.as_number()),
+                (">=" | ">", Some(f), None, _, n) if n.as_number().is_some() => bound(f, n.as_number(), None),
+                ("<=" | "<", None, Some(f), n, _) if n.as_number().is_some() => bound(f, n.as_number(), None),
+                (">=" | ">", None, Some(f), n, _) if n.as_number().is_some() => bound(f, None, n.as_number()),
+                ("=", Some(f), None, _, lit) | ("=", None, Some(f), lit, _) if is_literal(lit) => {
+                    claims.insert(f.to_string(), literal_string(lit));
+                }
+                _ => {}
+            },
+            [Node::Symbol(op), field, lo, hi] if op == "in-range" => {
+                if let (Some(f), Some(lo), Some(hi)) = (req_field(field), lo.as_number(), hi.as_number()) {
+                    bound(f, Some(lo), Some(hi));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ClauseSummary {
+        name,
+        explanation: conjuncts(ast).iter().map(explain).collect(),
+        envelope,
+        per_day: limits.per_day,
+        claims,
+    }
+}
+
+fn req_field(node: &Node) -> Option<&str> {
+    match node {
+        Node::List(items) => match items.as_slice() {
+            [Node::Symbol(op), Node::Symbol(obj), Node::Str(k)] if op == "get" && obj == "req" => Some(k),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_literal(node: &Node) -> bool {
+    matches!(node, Node::Str(_) | Node::Int(_) | Node::Number(_) | Node::Bool(_))
+}
+
+fn literal_string(node: &Node) -> String {
+    match node {
+        Node::Str(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Render an expression in infix form, with `(get req "x")` as `req.x`.
+fn explain(node: &Node) -> String {
+    let Node::List(items) = node else { return node.to_string() };
+    if let Some(field) = req_field(node) {
+        return format!("req.{field}");
+    }
+    let Some((Node::Symbol(op), args)) = items.split_first() else { return node.to_string() };
+    let join = |sep: &str| args.iter().map(explain).collect::<Vec<_>>().join(sep);
+    match (op.as_str(), args) {
+        ("and", _) => format!("({})", join(" and ")),
+        ("or", _) => format!("({})", join(" or ")),
+        ("not", [a]) => format!("not {}", explain(a)),
+        ("=" | "<=" | "<" | ">=" | ">", [a, b]) => format!("{} {op} {}", explain(a), explain(b)),
+        ("member" | "in", [a, b]) => format!("{} in {}", explain(a), explain(b)),
+        ("in-range", [x, lo, hi]) => format!("{} <= {} <= {}", explain(lo), explain(x), explain(hi)),
+        _ => format!("{op}({})", join(", ")),
+    }
+}
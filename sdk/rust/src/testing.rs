@@ -0,0 +1,148 @@
+//! Table-driven policy tests.
+//!
+//! A [`PolicySuite`] is a JSON document of named requests with the decision
+//! each should get. Cases run in a [`Sandbox`], so suites are deterministic
+//! and need no stores; `agent-safe test` runs them from the command line.
+//!
+//! ```text
+//! { "now": "2026-03-01T12:00:00Z",
+//!   "vars": { "allowed": ["mom@example.com"] },
+//!   "cases": [
+//!     { "name": "mom", "expect": "allow", "request": { "recipient": "mom@example.com" } },
+//!     { "name": "stranger", "expect": "deny", "request": { "recipient": "x@example.com" } } ] }
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::evaluator::eval_policy;
+use crate::parser::parse_all;
+use crate::sandbox::Sandbox;
+use crate::summary::conjuncts;
+use crate::time::parse_rfc3339;
+use crate::token::select_clause;
+use crate::types::{Env, Node, SplError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expectation {
+    #[serde(alias = "ALLOW")]
+    Allow,
+    #[serde(alias = "DENY")]
+    Deny,
+}
+
+impl Expectation {
+    fn of(allow: bool) -> Self {
+        if allow { Expectation::Allow } else { Expectation::Deny }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Expectation::Allow => "ALLOW",
+            Expectation::Deny => "DENY",
+        }
+    }
+}
+
+/// One named request and its expected decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyCase {
+    pub name: String,
+    pub request: HashMap<String, Node>,
+    pub expect: Expectation,
+    /// Vars for this case only, overriding the suite's.
+    #[serde(default)]
+    pub vars: HashMap<String, Node>,
+}
+
+/// Shared sandbox setup plus the cases to run against it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicySuite {
+    /// RFC 3339 virtual time; the Unix epoch when omitted.
+    #[serde(default)]
+    pub now: Option<String>,
+    #[serde(default)]
+    pub vars: HashMap<String, Node>,
+    #[serde(default)]
+    pub counters: HashMap<String, HashMap<String, i64>>,
+    #[serde(default)]
+    pub spent: HashMap<String, f64>,
+    #[serde(default = "evidence_default")]
+    pub evidence: bool,
+    pub cases: Vec<PolicyCase>,
+}
+
+fn evidence_default() -> bool {
+    true
+}
+
+/// A case whose decision differed from its expectation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseFailure {
+    pub name: String,
+    pub expected: Expectation,
+    pub actual: Expectation,
+    pub error: Option<String>,
+    /// Value of each top-level conjunct of the governing clause, e.g.
+    /// `#f  (<= (get req "amount") 50)`.
+    pub trace: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestReport {
+    pub passed: Vec<String>,
+    pub failed: Vec<CaseFailure>,
+}
+
+impl TestReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Run every case of `suite` against policy source.
+pub fn run_suite(policy: &str, suite: &PolicySuite) -> Result<TestReport, SplError> {
+    let base = Sandbox {
+        now: suite.now.as_deref().map(parse_rfc3339).transpose()?.unwrap_or(0),
+        vars: suite.vars.clone(),
+        counters: suite.counters.clone(),
+        spent: suite.spent.clone(),
+        evidence: suite.evidence,
+        ..Sandbox::default()
+    };
+
+    let mut report = TestReport::default();
+    for case in &suite.cases {
+        let mut sandbox = base.clone();
+        sandbox.vars.extend(case.vars.clone());
+        let outcome = sandbox.evaluate(policy, case.request.clone());
+        let actual = Expectation::of(outcome.allow);
+        if actual == case.expect {
+            report.passed.push(case.name.clone());
+            continue;
+        }
+        report.failed.push(CaseFailure {
+            name: case.name.clone(),
+            expected: case.expect,
+            actual,
+            error: outcome.error,
+            trace: trace(policy, &sandbox.env(case.request.clone())),
+        });
+    }
+    Ok(report)
+}
+
+fn trace(policy: &str, env: &Env) -> Vec<String> {
+    let Ok(ast) = parse_all(policy).and_then(|exprs| select_clause(&exprs, &env.req)) else {
+        return Vec::new();
+    };
+    conjuncts(&ast)
+        .iter()
+        .map(|term| match eval_policy(term, env) {
+            Ok(value) => format!("{}  {term}", if value.is_truthy() { "#t" } else { "#f" }),
+            Err(e) => format!("error: {e}  {term}"),
+        })
+        .collect()
+}
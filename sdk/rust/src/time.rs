@@ -0,0 +1,264 @@
+//! RFC 3339 timestamp handling for token envelopes.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::types::SplError;
+
+/// Source of the verifier's current time, in Unix seconds.
+pub trait Clock {
+    fn now_unix(&self) -> i64;
+}
+
+/// The host's wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64)
+    }
+}
+
+/// A clock stopped at a fixed instant, for tests and replaying decisions.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now_unix(&self) -> i64 {
+        self.0
+    }
+}
+
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    // Howard Hinnant's days_from_civil, valid for the proleptic Gregorian calendar.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ if (y % 4 == 0 && y % 100 != 0) || y % 400 == 0 => 29,
+        _ => 28,
+    }
+}
+
+fn digits(s: &str, range: std::ops::Range<usize>) -> Option<i64> {
+    let part = s.get(range)?;
+    if part.bytes().all(|b| b.is_ascii_digit()) {
+        part.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parse an RFC 3339 timestamp (`2026-04-01T00:00:00Z`, optional fractional
+/// seconds, `Z` or `±HH:MM` offset) into Unix seconds. Fractions are truncated.
+pub fn parse_rfc3339(s: &str) -> Result<i64, SplError> {
+    parse_rfc3339_with_offset(s).map(|(unix, _)| unix)
+}
+
+/// Wall-clock reading of a timestamp in the UTC offset it was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    /// ISO weekday: 1 is Monday, 7 is Sunday.
+    pub weekday: i64,
+}
+
+impl LocalTime {
+    /// The local calendar date as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Read an RFC 3339 timestamp as local time in its own offset, so
+/// `2026-03-06T23:30:00-05:00` is a Friday at 23:30 even though it is
+/// Saturday in UTC.
+pub fn local_time(s: &str) -> Result<LocalTime, SplError> {
+    let (unix, offset) = parse_rfc3339_with_offset(s)?;
+    let local = unix + offset;
+    let days = local.div_euclid(86_400);
+    let secs = local.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    Ok(LocalTime {
+        year,
+        month,
+        day,
+        hour: secs / 3600,
+        minute: (secs % 3600) / 60,
+        // 1970-01-01 was a Thursday.
+        weekday: (days + 3).rem_euclid(7) + 1,
+    })
+}
+
+/// Parse an RFC 3339 timestamp into Unix seconds and its UTC offset in seconds.
+fn parse_rfc3339_with_offset(s: &str) -> Result<(i64, i64), SplError> {
+    let err = || SplError(format!("invalid RFC 3339 timestamp: {s}"));
+    let b = s.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ')
+        || b[13] != b':' || b[16] != b':'
+    {
+        return Err(err());
+    }
+    let year = digits(s, 0..4).ok_or_else(err)?;
+    let month = digits(s, 5..7).ok_or_else(err)?;
+    let day = digits(s, 8..10).ok_or_else(err)?;
+    let hour = digits(s, 11..13).ok_or_else(err)?;
+    let minute = digits(s, 14..16).ok_or_else(err)?;
+    let second = digits(s, 17..19).ok_or_else(err)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month)
+        || hour > 23 || minute > 59 || second > 60
+    {
+        return Err(err());
+    }
+
+    let mut rest = &s[19..];
+    if let Some(frac) = rest.strip_prefix('.') {
+        let len = frac.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return Err(err());
+        }
+        rest = &frac[len..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && matches!(rest.as_bytes()[0], b'+' | b'-') && rest.as_bytes()[3] == b':' => {
+            let oh = digits(rest, 1..3).ok_or_else(err)?;
+            let om = digits(rest, 4..6).ok_or_else(err)?;
+            if oh > 23 || om > 59 {
+                return Err(err());
+            }
+            let secs = oh * 3600 + om * 60;
+            if rest.starts_with('-') { -secs } else { secs }
+        }
+        _ => return Err(err()),
+    };
+
+    let days = days_from_civil(year, month, day);
+    Ok((days * 86_400 + hour * 3600 + minute * 60 + second.min(59) - offset, offset))
+}
+
+/// Whether `s` is a valid `YYYY-MM-DD` calendar date.
+pub fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && matches!(
+            (digits(s, 0..4), digits(s, 5..7), digits(s, 8..10)),
+            (Some(y), Some(m), Some(d)) if (1..=12).contains(&m) && d >= 1 && d <= days_in_month(y, m)
+        )
+}
+
+/// First and last day (`YYYY-MM-DD`) of the `"day"`, `"week"` (ISO,
+/// Monday to Sunday), or `"month"` containing `day`.
+pub fn period_bounds(day: &str, period: &str) -> Result<(String, String), SplError> {
+    if !is_date(day) {
+        return Err(SplError(format!("expected a YYYY-MM-DD day, got {day:?}")));
+    }
+    let (y, m, d) = (digits(day, 0..4).unwrap_or(0), digits(day, 5..7).unwrap_or(0), digits(day, 8..10).unwrap_or(0));
+    let (first, last) = match period {
+        "day" => (days_from_civil(y, m, d), days_from_civil(y, m, d)),
+        "week" => {
+            let days = days_from_civil(y, m, d);
+            // 1970-01-01 was a Thursday, three days after a Monday.
+            let monday = days - (days + 3).rem_euclid(7);
+            (monday, monday + 6)
+        }
+        "month" => (days_from_civil(y, m, 1), days_from_civil(y, m, days_in_month(y, m))),
+        _ => return Err(SplError(format!("unknown period {period:?}; expected day, week, or month"))),
+    };
+    let format = |days: i64| {
+        let (y, m, d) = civil_from_days(days);
+        format!("{y:04}-{m:02}-{d:02}")
+    };
+    Ok((format(first), format(last)))
+}
+
+/// Format Unix seconds as an RFC 3339 UTC timestamp (`YYYY-MM-DDTHH:MM:SSZ`).
+pub fn format_rfc3339(unix_secs: i64) -> String {
+    let days = unix_secs.div_euclid(86_400);
+    let secs = unix_secs.rem_euclid(86_400);
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{y:04}-{m:02}-{d:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_utc() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(parse_rfc3339("2026-04-01T00:00:00Z").unwrap(), 1_775_001_600);
+    }
+
+    #[test]
+    fn parse_offset_and_fraction() {
+        assert_eq!(
+            parse_rfc3339("2026-04-01T02:00:00.123+02:00").unwrap(),
+            parse_rfc3339("2026-04-01T00:00:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_rfc3339("2026-03-31T19:00:00-05:00").unwrap(),
+            parse_rfc3339("2026-04-01T00:00:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_rejects_malformed() {
+        for bad in ["2026-04-01", "2026-13-01T00:00:00Z", "2025-02-29T00:00:00Z", "2026-04-01T00:00:00", "2026-04-01T00:00:00.Z", "2026-04-01T24:00:00Z"] {
+            assert!(parse_rfc3339(bad).is_err(), "{bad}");
+        }
+        assert!(parse_rfc3339("2024-02-29T00:00:00Z").is_ok());
+    }
+
+    #[test]
+    fn local_time_keeps_offset() {
+        let t = local_time("2026-03-06T23:30:00-05:00").unwrap();
+        assert_eq!((t.date().as_str(), t.hour, t.minute, t.weekday), ("2026-03-06", 23, 30, 5));
+        let utc = local_time("2026-03-07T04:30:00Z").unwrap();
+        assert_eq!((utc.date().as_str(), utc.hour, utc.weekday), ("2026-03-07", 4, 6));
+        assert_eq!(local_time("1970-01-01T00:00:00Z").unwrap().weekday, 4);
+        assert_eq!(local_time("1969-12-28T12:00:00Z").unwrap().weekday, 7);
+        assert!(is_date("2024-02-29") && !is_date("2025-02-29") && !is_date("2025-12-1"));
+    }
+
+    #[test]
+    fn format_roundtrip() {
+        for ts in [0, 1_775_001_600, 951_782_400, -86_400] {
+            assert_eq!(parse_rfc3339(&format_rfc3339(ts)).unwrap(), ts);
+        }
+        assert_eq!(format_rfc3339(1_775_001_600), "2026-04-01T00:00:00Z");
+    }
+}
@@ -1,17 +1,43 @@
-use ed25519_dalek::{SigningKey, Signer};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
-use crate::crypto::verify_ed25519;
-use crate::evaluator::eval_policy;
-use crate::parser::parse;
-use crate::types::{CryptoCallbacks, Env, Node, SplError};
+use crate::attestation::AttestationVerifier;
+use crate::backend::{key_from_hex, public_key_hex, sign_hex};
+use crate::canonical::canonical_json;
+use crate::caveat::{verify_caveat_chain, Caveat};
+use crate::crypto::{ct_eq_hex, verify_ed25519};
+use crate::denylist::{DenyListProvider, DenyListVersion, DEFAULT_MAX_STALENESS_SECS};
+use crate::evaluator::eval_policy_detailed;
+use crate::fragments::{expand, has_includes, resolved_hash, FragmentResolver};
+use crate::did::check_did_key;
+use crate::issuers::{check_cosigner, check_issuer, IssuerKeys};
+use crate::scope::{check_scope, check_scope_globs};
+use crate::keys::{KeyStore, Signer};
+use crate::money::RateProvider;
+use crate::obligations::Obligation;
+use crate::ops::forbidden_ops;
+use crate::parser::{parse_all, parse_all_with_syntax, Syntax};
+use crate::profile::VerifierProfile;
+use crate::replay::ReplayCache;
+use crate::signature::SignatureScheme;
+use crate::spend::{record_allowed, SpendTracker};
+use crate::time::{format_rfc3339, parse_rfc3339, Clock, FixedClock, SystemClock};
+use crate::types::{Env, Limits, Node, SplError};
+use crate::uses::{check_use, UsageStore};
+use crate::vars::{disabled_references, inject_standard_vars, StandardVar};
 
 /// A signed Agent-Safe capability token.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
+    /// Envelope format, `MAJOR.MINOR.PATCH`; see [`EnvelopeVersion`].
     pub version: String,
+    /// Issuer signature scheme. Omitted from JSON for Ed25519.
+    #[serde(default, skip_serializing_if = "SignatureScheme::is_ed25519")]
+    pub alg: SignatureScheme,
     pub policy: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub merkle_root: Option<String>,
@@ -20,10 +46,245 @@ pub struct Token {
     pub sealed: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires: Option<String>,
+    /// RFC 3339 issuance time, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<String>,
     pub public_key: String,
     pub signature: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pop_key: Option<String>,
+    /// Policy sealed to the verifier's X25519 key (see [`crate::crypto::seal`]).
+    /// When set, `policy` is empty and the verifier decrypts before parsing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_policy: Option<String>,
+    /// Root key of the caveat chain, covered by the signature. Present only
+    /// on attenuable tokens (see [`crate::caveat`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegation_key: Option<String>,
+    /// Attenuations appended after minting; each must also pass.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caveats: Vec<Caveat>,
+    /// Private key for the last link of the caveat chain. Whoever holds it
+    /// can attenuate further; verifiers require it to match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caveat_proof: Option<String>,
+    /// Issuer-supplied policy vars, covered by the signature. Verifiers
+    /// merge them into `vars`; caller vars may add names but not override these.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, Node>,
+    /// [`crate::fragments::resolved_hash`] of the policy with its
+    /// `(include ...)` forms expanded, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_policy_hash: Option<String>,
+    /// Issuer-assigned unique identifier, covered by the signature. See
+    /// [`Token::id`] for tokens minted without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    /// Id of the issuer key that signed, covered by the signature, so
+    /// verifiers holding several keys (see [`crate::issuers`]) look it up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// Issuer DID, covered by the signature. A `did:key` issuer must be
+    /// `public_key`; see [`crate::did`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// Action globs the token covers, covered by the signature. Empty
+    /// covers every action; see [`crate::scope`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scope: Vec<String>,
+    /// Issuer keys of a multi-signature token, covered by the signature.
+    /// `public_key` must be one of them; see [`mint_multisig`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signers: Vec<String>,
+    /// How many distinct `signers` must have signed, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<u32>,
+    /// Signatures over the same payload by `signers` other than `public_key`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cosignatures: Vec<CoSignature>,
+    /// [`Token::id`] of the token this one refreshed, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refreshed_from: Option<String>,
+    /// Refreshes between the original grant and this token, covered by the
+    /// signature. Verifiers may cap it with [`VerifierProfile::max_refresh_depth`].
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub refresh_depth: u32,
+    /// RFC 3339 time before which verifiers reject the token, covered by
+    /// the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<String>,
+    /// Issuer epoch the token was minted in, covered by the signature.
+    /// After a key compromise the issuer moves to a new epoch and verifiers
+    /// raise [`VerifierProfile::min_issuer_epoch`], revoking every earlier token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<u64>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// One co-signer's signature on a multi-signature token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Token envelope format, read from [`Token::version`]. The version
+/// decides which fields the issuer signature covers:
+///
+/// - `0.1.x`: the five-field [`signing_payload`] only. The format predates
+///   extension fields, so a 0.1 token carrying one is malformed.
+/// - `0.2.x`: the five fields, then `\0name=value` for each extension field
+///   present (see [`envelope_payload`]). A token without extension fields
+///   signs the same bytes as under 0.1.
+/// - `0.3.x`: as 0.2, and `pop_key` is signed too. Under 0.1 and 0.2 it is
+///   not, so a holder can strip it and present the token without a key.
+///   The extension fields from `issuer` on (see [`envelope_payload`]) are
+///   0.3 only.
+///
+/// Below 1.0 the minor version is breaking, as the major is afterwards;
+/// the patch version never changes the payload. Verifiers reject versions
+/// they do not know rather than guess at their payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EnvelopeVersion {
+    V0_1,
+    V0_2,
+    V0_3,
+}
+
+impl EnvelopeVersion {
+    /// The version [`mint`] writes.
+    pub const CURRENT: EnvelopeVersion = EnvelopeVersion::V0_3;
+
+    /// Read a `MAJOR.MINOR.PATCH` version string.
+    pub fn parse(version: &str) -> Result<EnvelopeVersion, SplError> {
+        let parts: Vec<&str> = version.split('.').collect();
+        let numbers = parts.iter().map(|p| p.parse::<u32>()).collect::<Result<Vec<_>, _>>();
+        match (parts.len(), numbers.as_deref()) {
+            (3, Ok([0, 1, _])) => Ok(EnvelopeVersion::V0_1),
+            (3, Ok([0, 2, _])) => Ok(EnvelopeVersion::V0_2),
+            (3, Ok([0, 3, _])) => Ok(EnvelopeVersion::V0_3),
+            (3, Ok(_)) => Err(SplError(format!("unsupported token version {version}"))),
+            _ => Err(SplError(format!("malformed token version {version:?}"))),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EnvelopeVersion::V0_1 => "0.1.0",
+            EnvelopeVersion::V0_2 => "0.2.0",
+            EnvelopeVersion::V0_3 => "0.3.0",
+        }
+    }
+
+    /// How policies and caveats under this version are read. String
+    /// escapes and quoted lists start at 0.3, so earlier tokens keep the
+    /// meaning they were signed with.
+    pub fn syntax(self) -> Syntax {
+        match self {
+            EnvelopeVersion::V0_1 | EnvelopeVersion::V0_2 => Syntax::Legacy,
+            EnvelopeVersion::V0_3 => Syntax::Current,
+        }
+    }
+}
+
+impl Token {
+    /// The token's [`EnvelopeVersion`].
+    pub fn envelope_version(&self) -> Result<EnvelopeVersion, SplError> {
+        EnvelopeVersion::parse(&self.version)
+    }
+
+    /// How this token's policy and caveats are read: per its envelope
+    /// version, or [`Syntax::Current`] if the version is unknown.
+    pub fn syntax(&self) -> Syntax {
+        self.envelope_version().map_or(Syntax::Current, EnvelopeVersion::syntax)
+    }
+
+    /// This token relabelled with [`EnvelopeVersion::CURRENT`]. Migration
+    /// never re-signs, so it succeeds only when the token's signed payload
+    /// is the same under the current version; otherwise the issuer must
+    /// mint a new token.
+    pub fn migrate(&self) -> Result<Token, SplError> {
+        check_envelope(self)?;
+        let migrated = Token { version: EnvelopeVersion::CURRENT.as_str().to_string(), ..self.clone() };
+        if envelope_payload(&migrated) != envelope_payload(self) {
+            return Err(SplError(format!("version {} token signs a different payload; mint a new token", self.version)));
+        }
+        Ok(migrated)
+    }
+
+    /// Stable identifier for revocation lists, audit logs, and replay
+    /// caches: the signed `token_id`, or for tokens minted without one, hex
+    /// SHA-256 of the signed envelope and signature. Unsigned fields such as
+    /// caveats do not change it, so attenuated copies share their parent's id.
+    pub fn id(&self) -> Cow<'_, str> {
+        if let Some(id) = &self.token_id {
+            return Cow::Borrowed(id);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(envelope_payload(self));
+        hasher.update(b"\0");
+        hasher.update(self.signature.as_bytes());
+        Cow::Owned(hex::encode(hasher.finalize()))
+    }
+
+    /// Re-mint this token with the same policy and signed fields, a fresh
+    /// `issued_at` and `token_id`, and `new_expiry` (RFC 3339), which may
+    /// not be later than the current expiry. The result links back through
+    /// [`Token::refreshed_from`], so a long-lived grant can hand out
+    /// short-lived tokens. Only the issuer key can refresh, and tokens with
+    /// caveats or several signers cannot be refreshed, since the copy would
+    /// drop them.
+    pub fn refresh(&self, signer: &dyn Signer, new_expiry: &str) -> Result<Token, SplError> {
+        check_envelope(self)?;
+        if signer.scheme() != self.alg || !signer.public_key().eq_ignore_ascii_case(&self.public_key) {
+            return Err(SplError("only the issuer key can refresh a token".into()));
+        }
+        if !self.caveats.is_empty() || self.threshold.is_some() {
+            return Err(SplError("tokens with caveats or several signers cannot be refreshed".into()));
+        }
+        let now = SystemClock.now_unix();
+        let expiry = parse_rfc3339(new_expiry)?;
+        if expiry <= now {
+            return Err(SplError("refreshed expiry is already past".into()));
+        }
+        if let Some(current) = &self.expires {
+            if expiry > parse_rfc3339(current)? {
+                return Err(SplError("a refresh cannot extend the token's expiry".into()));
+            }
+        }
+        let (delegation_key, caveat_proof) = match self.delegation_key {
+            Some(_) => {
+                let (public, private) = generate_keypair();
+                (Some(public), Some(private))
+            }
+            None => (None, None),
+        };
+        let token = Token {
+            version: EnvelopeVersion::CURRENT.as_str().to_string(),
+            expires: Some(new_expiry.to_string()),
+            issued_at: Some(format_rfc3339(now)),
+            signature: String::new(),
+            delegation_key,
+            caveat_proof,
+            token_id: self.token_id.as_ref().map(|_| new_token_id()),
+            refreshed_from: Some(self.id().into_owned()),
+            refresh_depth: self.refresh_depth.checked_add(1).ok_or_else(|| SplError("refresh depth overflow".into()))?,
+            ..self.clone()
+        };
+        let unsigned = UnsignedToken { token };
+        let signature = signer.sign(&unsigned.payload())?;
+        unsigned.attach_signature(&hex::encode(signature), &signer.public_key())
+    }
+
+    /// This token's JSON in RFC 8785 canonical form (see [`crate::canonical`]),
+    /// for hashes that agree across SDKs. It covers unsigned fields too, so
+    /// it is not an identifier; use [`Token::id`] for that.
+    pub fn to_canonical_json(&self) -> String {
+        canonical_json(&serde_json::to_value(self).expect("tokens serialize to JSON"))
+    }
 }
 
 /// Options for minting a token.
@@ -33,20 +294,68 @@ pub struct MintOptions {
     pub hash_chain_commitment: Option<String>,
     pub sealed: bool,
     pub expires: Option<String>,
+    /// RFC 3339 issuance time; see [`crate::time::format_rfc3339`].
+    pub issued_at: Option<String>,
     pub pop_key: Option<String>,
+    /// Seal the policy to this X25519 public key (hex). Requires the
+    /// `encryption` feature.
+    pub encrypt_policy_to: Option<String>,
+    /// Issuer signature scheme; `private_key_hex` must be a key for it.
+    pub alg: SignatureScheme,
+    /// Mint with a delegation key so holders can [`Token::add_caveat`].
+    pub attenuable: bool,
+    /// Signed vars such as `allowed_recipients`, so the policy's data comes
+    /// from the issuer rather than the relying party.
+    pub vars: BTreeMap<String, Node>,
+    /// Unique id to sign into the token, e.g. [`new_token_id`]. Up to 128
+    /// ASCII letters, digits, and `.`, `_`, `:`, `-`.
+    pub token_id: Option<String>,
+    /// Issuer key id to sign into the token. [`mint_with_key_store`] uses
+    /// the store's key id when unset.
+    pub kid: Option<String>,
+    /// Issuer DID to sign into the token, e.g. [`crate::did::did_key`] of
+    /// the issuer key.
+    pub issuer: Option<String>,
+    /// Action globs such as `payments.*` to limit the token to.
+    pub scope: Vec<String>,
+    /// Issuer keys of a multi-signature token, when more may sign than
+    /// [`mint_multisig`] is given; defaults to the keys of its signers.
+    pub signers: Vec<String>,
+    /// RFC 3339 time the token becomes valid; no later than `expires`.
+    pub not_before: Option<String>,
+    /// The issuer's current epoch; see [`Token::epoch`].
+    pub epoch: Option<u64>,
 }
 
 /// Generate an Ed25519 keypair.
 /// Returns (public_key_hex, private_key_hex).
+///
+/// # Panics
+///
+/// If the OS RNG fails, or no Ed25519 backend is available: built without
+/// the `dalek` feature and before [`crate::backend::install_backend`].
 pub fn generate_keypair() -> (String, String) {
     let mut seed = [0u8; 32];
     getrandom::fill(&mut seed).expect("OS RNG failed");
-    let signing_key = SigningKey::from_bytes(&seed);
-    let verifying_key = signing_key.verifying_key();
-    (
-        hex::encode(verifying_key.as_bytes()),
-        hex::encode(signing_key.as_bytes()),
-    )
+    let public_key = public_key_hex(&seed).expect("Ed25519 backend unavailable");
+    (public_key, hex::encode(seed))
+}
+
+/// A random RFC 4122 version 4 UUID, for [`MintOptions::token_id`].
+pub fn new_token_id() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("OS RNG failed");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let h = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..])
+}
+
+/// Derive the Ed25519 keypair for a 32-byte hex seed.
+/// Returns (public_key_hex, private_key_hex); the private key is the seed itself.
+pub fn keypair_from_seed(seed_hex: &str) -> Result<(String, String), SplError> {
+    let seed = key_from_hex(seed_hex, "seed")?;
+    Ok((public_key_hex(&seed)?, hex::encode(seed)))
 }
 
 /// Build the canonical signing payload for a token.
@@ -69,158 +378,1220 @@ pub fn signing_payload(
     parts.join("\0").into_bytes()
 }
 
+/// Build the full signing payload for a token envelope: the five-field
+/// [`signing_payload`] followed by `\0name=value` for each extension field
+/// that is present. Tokens without extension fields keep the original payload.
+pub fn envelope_payload(token: &Token) -> Vec<u8> {
+    let mut payload = signing_payload(
+        &token.policy, &token.merkle_root, &token.hash_chain_commitment,
+        token.sealed, &token.expires,
+    );
+    for (name, value) in extension_fields(token) {
+        payload.push(0);
+        payload.extend_from_slice(name.as_bytes());
+        payload.push(b'=');
+        payload.extend_from_slice(value.as_bytes());
+    }
+    payload
+}
+
+/// Check that `token`'s version is known, its fields are ones that
+/// version signs, and no signed string can forge a `\0` separator.
+fn check_envelope(token: &Token) -> Result<(), SplError> {
+    let version = token.envelope_version()?;
+    let fields = extension_fields(token);
+    if let Some((name, _)) = fields.iter().find(|(name, _)| field_since(name) > version) {
+        return Err(SplError(format!("version {} tokens cannot carry {name}", token.version)));
+    }
+    // Policy source may span lines, so only NUL is ruled out there.
+    if token.policy.contains('\0') {
+        return Err(SplError("policy may not contain NUL bytes".into()));
+    }
+    let core = [
+        ("merkle_root", token.merkle_root.as_deref()),
+        ("hash_chain_commitment", token.hash_chain_commitment.as_deref()),
+        ("expires", token.expires.as_deref()),
+    ];
+    let signed = core.into_iter().filter_map(|(name, value)| Some((name, value?)));
+    if let Some((name, _)) = signed
+        .chain(fields.iter().map(|(name, value)| (*name, value.as_str())))
+        .find(|(_, value)| value.chars().any(char::is_control))
+    {
+        return Err(SplError(format!("{name} may not contain control characters")));
+    }
+    if token.refreshed_from.is_some() != (token.refresh_depth > 0) {
+        return Err(SplError("refreshed_from and refresh_depth must be set together".into()));
+    }
+    Ok(())
+}
+
+/// The first envelope version whose payload signs extension field `name`.
+fn field_since(name: &str) -> EnvelopeVersion {
+    match name {
+        "issuer" | "scope" | "signers" | "threshold" | "refreshed_from" | "refresh_depth" | "not_before" | "epoch"
+        | "pop_key" => EnvelopeVersion::V0_3,
+        _ => EnvelopeVersion::V0_2,
+    }
+}
+
+/// Signed envelope fields beyond the original five, in canonical order.
+fn extension_fields(token: &Token) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+    if !token.alg.is_ed25519() {
+        fields.push(("alg", token.alg.name().to_string()));
+    }
+    if let Some(issued_at) = &token.issued_at {
+        fields.push(("issued_at", issued_at.clone()));
+    }
+    if let Some(encrypted_policy) = &token.encrypted_policy {
+        fields.push(("encrypted_policy", encrypted_policy.clone()));
+    }
+    if let Some(delegation_key) = &token.delegation_key {
+        fields.push(("delegation_key", delegation_key.clone()));
+    }
+    if let Some(hash) = &token.resolved_policy_hash {
+        fields.push(("resolved_policy_hash", hash.clone()));
+    }
+    if !token.vars.is_empty() {
+        // Keys are sorted, so compact JSON is canonical.
+        fields.push(("vars", serde_json::to_string(&token.vars).unwrap_or_default()));
+    }
+    if let Some(token_id) = &token.token_id {
+        fields.push(("token_id", token_id.clone()));
+    }
+    if let Some(kid) = &token.kid {
+        fields.push(("kid", kid.clone()));
+    }
+    if let Some(issuer) = &token.issuer {
+        fields.push(("issuer", issuer.clone()));
+    }
+    if !token.scope.is_empty() {
+        fields.push(("scope", serde_json::to_string(&token.scope).unwrap_or_default()));
+    }
+    if !token.signers.is_empty() {
+        fields.push(("signers", serde_json::to_string(&token.signers).unwrap_or_default()));
+    }
+    if let Some(threshold) = token.threshold {
+        fields.push(("threshold", threshold.to_string()));
+    }
+    if let Some(parent) = &token.refreshed_from {
+        fields.push(("refreshed_from", parent.clone()));
+    }
+    if token.refresh_depth != 0 {
+        fields.push(("refresh_depth", token.refresh_depth.to_string()));
+    }
+    if let Some(not_before) = &token.not_before {
+        fields.push(("not_before", not_before.clone()));
+    }
+    if let Some(epoch) = token.epoch {
+        fields.push(("epoch", epoch.to_string()));
+    }
+    if let Some(pop_key) = &token.pop_key {
+        if token.envelope_version().is_ok_and(|v| v >= EnvelopeVersion::V0_3) {
+            fields.push(("pop_key", pop_key.clone()));
+        }
+    }
+    fields
+}
+
 /// Mint a signed capability token.
 pub fn mint(policy: &str, private_key_hex: &str, opts: MintOptions) -> Result<Token, SplError> {
-    let seed_bytes = hex::decode(private_key_hex)
-        .map_err(|e| SplError(format!("invalid private key hex: {e}")))?;
-    let seed: [u8; 32] = seed_bytes
-        .try_into()
-        .map_err(|_| SplError("private key must be 32 bytes".to_string()))?;
+    let alg = opts.alg;
+    let public_key = alg.public_key(private_key_hex)?;
+    mint_signed(policy, public_key, opts, None, |payload| alg.sign(private_key_hex, payload))
+}
 
-    let signing_key = SigningKey::from_bytes(&seed);
-    let verifying_key = signing_key.verifying_key();
+/// Mint a token that verifiers accept only with Ed25519 signatures from
+/// `threshold` distinct issuers. Each of `signers` (private keys) signs the
+/// same payload; the first is the token's `public_key`, the rest become
+/// [`Token::cosignatures`]. The declared issuer keys are the signers' own
+/// unless [`MintOptions::signers`] names a larger set.
+pub fn mint_multisig(policy: &str, signers: &[&str], threshold: u32, mut opts: MintOptions) -> Result<Token, SplError> {
+    if !opts.alg.is_ed25519() {
+        return Err(SplError("multi-signature tokens are Ed25519 only".into()));
+    }
+    let keys = signers.iter().map(|k| opts.alg.public_key(k)).collect::<Result<Vec<_>, _>>()?;
+    let Some((lead, _)) = keys.split_first() else {
+        return Err(SplError("a multi-signature token needs at least one signer".into()));
+    };
+    let mut declared = std::mem::take(&mut opts.signers);
+    if declared.is_empty() {
+        declared = keys.clone();
+    }
+    let mut token = unsigned_token(policy, lead.clone(), opts, None)?;
+    token.signers = declared;
+    token.threshold = Some(threshold);
+    check_did_key(&token).map_err(|e| SplError(e.message))?;
+    let payload = envelope_payload(&token);
+    for (key, private_key) in keys.iter().zip(signers) {
+        let signature = sign_hex(&key_from_hex(private_key, "signer private key")?, &payload)?;
+        if key == lead {
+            token.signature = signature;
+        } else {
+            token.cosignatures.push(CoSignature { public_key: key.clone(), signature });
+        }
+    }
+    check_multisig(&token).map_err(|e| SplError(e.message))?;
+    Ok(token)
+}
 
-    let payload = signing_payload(
-        policy, &opts.merkle_root, &opts.hash_chain_commitment, opts.sealed, &opts.expires,
-    );
-    let signature = signing_key.sign(&payload);
+/// Check a multi-signature token's declared signers and threshold, and
+/// that its co-signatures are valid and bring the count of distinct
+/// signers to the threshold. The lead signature is checked separately.
+fn check_multisig(token: &Token) -> Result<(), VerifyError> {
+    let malformed = |message: String| VerifyError::new(VerifyErrorCode::MalformedToken, message);
+    let Some(threshold) = token.threshold else {
+        if !token.signers.is_empty() || !token.cosignatures.is_empty() {
+            return Err(malformed("signers and cosignatures require a threshold".into()));
+        }
+        return Ok(());
+    };
+    let canonical = |key: &String| key.len() == 64 && key.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !token.signers.iter().all(canonical) {
+        return Err(malformed("signers must be lowercase hex Ed25519 keys".into()));
+    }
+    if threshold == 0 || threshold as usize > token.signers.len() {
+        return Err(malformed(format!("threshold {threshold} is not between 1 and the {} signers", token.signers.len())));
+    }
+    if !token.alg.is_ed25519() {
+        return Err(malformed("multi-signature tokens are Ed25519 only".into()));
+    }
+    let mut signed = vec![token.public_key.as_str()];
+    if !token.signers.contains(&token.public_key) {
+        return Err(malformed("public_key is not a declared signer".into()));
+    }
+    let payload = envelope_payload(token);
+    for cosignature in &token.cosignatures {
+        let key = cosignature.public_key.as_str();
+        if !token.signers.iter().any(|s| s == key) || signed.contains(&key) {
+            return Err(malformed(format!("cosignature from {key} is undeclared or repeated")));
+        }
+        if !verify_ed25519(&payload, &cosignature.signature, key) {
+            return Err(VerifyError::new(VerifyErrorCode::InvalidSignature, format!("invalid cosignature from {key}")));
+        }
+        signed.push(key);
+    }
+    if signed.len() < threshold as usize {
+        return Err(VerifyError::new(
+            VerifyErrorCode::InvalidSignature,
+            format!("{} of the required {threshold} issuer signatures", signed.len()),
+        ));
+    }
+    Ok(())
+}
 
-    Ok(Token {
-        version: "0.2.0".to_string(),
-        policy: policy.trim().to_string(),
+/// Mint a token whose policy uses `(include "name")` fragments. The policy
+/// text is signed as written, together with a hash of its expansion through
+/// `resolver`, so verifiers reject the token if a fragment later changes.
+pub fn mint_with_fragments(
+    policy: &str,
+    private_key_hex: &str,
+    opts: MintOptions,
+    resolver: &dyn FragmentResolver,
+) -> Result<Token, SplError> {
+    let expanded = parse_all(policy)?.iter().map(|e| expand(e, resolver)).collect::<Result<Vec<_>, _>>()?;
+    let alg = opts.alg;
+    let public_key = alg.public_key(private_key_hex)?;
+    mint_signed(policy, public_key, opts, Some(resolved_hash(&expanded)), |payload| {
+        alg.sign(private_key_hex, payload)
+    })
+}
+
+/// Mint a token signed by the active key of a [`KeyStore`], so the issuer's
+/// private key never leaves the store.
+pub fn mint_with_key_store(policy: &str, store: &dyn KeyStore, mut opts: MintOptions) -> Result<Token, SplError> {
+    if !opts.alg.is_ed25519() {
+        return Err(SplError("key stores hold Ed25519 keys only".into()));
+    }
+    let key = store
+        .active()?
+        .ok_or_else(|| SplError("key store has no active key".into()))?;
+    opts.kid.get_or_insert_with(|| key.id.clone());
+    mint_signed(policy, key.public_key, opts, None, |payload| store.sign(&key.id, payload))
+}
+
+/// Mint a token signed by a [`Signer`], such as an HSM or KMS key. The
+/// token's `alg` is the signer's scheme, and the signature is checked
+/// before the token is returned.
+pub fn mint_with_signer(policy: &str, signer: &dyn Signer, mut opts: MintOptions) -> Result<Token, SplError> {
+    opts.alg = signer.scheme();
+    let unsigned = UnsignedToken { token: unsigned_token(policy, String::new(), opts, None)? };
+    let signature = signer.sign(&unsigned.payload())?;
+    unsigned.attach_signature(&hex::encode(signature), &signer.public_key())
+}
+
+/// A token built for signing outside the crate, e.g. in an HSM that
+/// returns raw signatures. Sign [`UnsignedToken::payload`] with the issuer
+/// key under the token's `alg`, then [`UnsignedToken::attach_signature`].
+#[derive(Debug, Clone)]
+pub struct UnsignedToken {
+    token: Token,
+}
+
+impl UnsignedToken {
+    /// The canonical bytes the issuer signs ([`envelope_payload`]).
+    pub fn payload(&self) -> Vec<u8> {
+        envelope_payload(&self.token)
+    }
+
+    /// The signed token. Fails unless `signature_hex` verifies over the
+    /// payload with `public_key_hex`, so a wrong key or a signer that
+    /// hashed or re-encoded the payload is caught before the token ships.
+    pub fn attach_signature(self, signature_hex: &str, public_key_hex: &str) -> Result<Token, SplError> {
+        let mut token = self.token;
+        token.public_key = public_key_hex.to_string();
+        token.signature = signature_hex.to_string();
+        if !token.alg.verify(&envelope_payload(&token), &token.signature, &token.public_key) {
+            return Err(SplError("signature does not verify over the token payload".into()));
+        }
+        check_did_key(&token).map_err(|e| SplError(e.message))?;
+        Ok(token)
+    }
+}
+
+/// Build a token to be signed elsewhere, returning it with the payload to
+/// sign. The issuer key is only needed when attaching the signature.
+pub fn mint_unsigned(policy: &str, opts: MintOptions) -> Result<(UnsignedToken, Vec<u8>), SplError> {
+    let token = UnsignedToken { token: unsigned_token(policy, String::new(), opts, None)? };
+    let payload = token.payload();
+    Ok((token, payload))
+}
+
+fn mint_signed(
+    policy: &str,
+    public_key: String,
+    opts: MintOptions,
+    resolved_policy_hash: Option<String>,
+    sign: impl FnOnce(&[u8]) -> Result<String, SplError>,
+) -> Result<Token, SplError> {
+    let mut token = unsigned_token(policy, public_key, opts, resolved_policy_hash)?;
+    check_did_key(&token).map_err(|e| SplError(e.message))?;
+    token.signature = sign(&envelope_payload(&token))?;
+    Ok(token)
+}
+
+/// The token [`mint`] would sign, with an empty signature.
+fn unsigned_token(
+    policy: &str,
+    public_key: String,
+    opts: MintOptions,
+    resolved_policy_hash: Option<String>,
+) -> Result<Token, SplError> {
+    if !opts.signers.is_empty() {
+        return Err(SplError("MintOptions::signers requires mint_multisig".into()));
+    }
+    check_token_vars(&opts.vars)?;
+    check_ids(&opts.token_id, &opts.kid, &opts.issuer)?;
+    check_scope_globs(&opts.scope)?;
+    if let Some(not_before) = &opts.not_before {
+        let not_before = parse_rfc3339(not_before)?;
+        if opts.expires.as_deref().map(parse_rfc3339).transpose()?.is_some_and(|expires| expires < not_before) {
+            return Err(SplError("not_before is later than expires".into()));
+        }
+    }
+    let (policy, encrypted_policy) = match &opts.encrypt_policy_to {
+        Some(recipient) => (String::new(), Some(encrypt_policy(policy.trim(), recipient)?)),
+        None => (policy.trim().to_string(), None),
+    };
+    let (delegation_key, caveat_proof) = if opts.attenuable {
+        let (public, private) = generate_keypair();
+        (Some(public), Some(private))
+    } else {
+        (None, None)
+    };
+    let token = Token {
+        version: EnvelopeVersion::CURRENT.as_str().to_string(),
+        alg: opts.alg,
+        policy,
         merkle_root: opts.merkle_root,
         hash_chain_commitment: opts.hash_chain_commitment,
         sealed: opts.sealed,
         expires: opts.expires,
-        public_key: hex::encode(verifying_key.as_bytes()),
-        signature: hex::encode(signature.to_bytes()),
+        issued_at: opts.issued_at,
+        public_key,
+        signature: String::new(),
         pop_key: opts.pop_key,
-    })
+        encrypted_policy,
+        delegation_key,
+        caveats: Vec::new(),
+        caveat_proof,
+        vars: opts.vars,
+        resolved_policy_hash,
+        token_id: opts.token_id,
+        kid: opts.kid,
+        issuer: opts.issuer,
+        scope: opts.scope,
+        signers: Vec::new(),
+        threshold: None,
+        cosignatures: Vec::new(),
+        refreshed_from: None,
+        refresh_depth: 0,
+        not_before: opts.not_before,
+        epoch: opts.epoch,
+    };
+    check_envelope(&token)?;
+    Ok(token)
+}
+
+/// Ids are bounded and free of control characters, so they are safe in log
+/// lines and the `\0`-separated signing payload. Token ids are further
+/// limited to a charset that is safe in URLs, and issuers must be DIDs.
+fn check_ids(token_id: &Option<String>, kid: &Option<String>, issuer: &Option<String>) -> Result<(), SplError> {
+    let bounded = |id: &str| (1..=128).contains(&id.len()) && !id.chars().any(char::is_control);
+    if let Some(token_id) = token_id {
+        if !bounded(token_id) || !token_id.bytes().all(|b| b.is_ascii_alphanumeric() || b".:_-".contains(&b)) {
+            return Err(SplError(format!("invalid token_id: {token_id:?}")));
+        }
+    }
+    if let Some(kid) = kid {
+        if !bounded(kid) {
+            return Err(SplError(format!("invalid kid: {kid:?}")));
+        }
+    }
+    if let Some(issuer) = issuer {
+        if !bounded(issuer) || !issuer.starts_with("did:") {
+            return Err(SplError(format!("invalid issuer: {issuer:?}")));
+        }
+    }
+    Ok(())
+}
+
+/// Signed vars may not shadow a standard var: an issuer-fixed `now` would
+/// stop the clock for every time check in the policy.
+fn check_token_vars(vars: &BTreeMap<String, Node>) -> Result<(), SplError> {
+    match vars.keys().find(|name| StandardVar::from_name(name).is_some()) {
+        Some(name) => Err(SplError(format!("token vars may not set standard var {name:?}"))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn encrypt_policy(policy: &str, recipient_public_hex: &str) -> Result<String, SplError> {
+    crate::crypto::seal(recipient_public_hex, policy.as_bytes())
+}
+
+#[cfg(not(feature = "encryption"))]
+fn encrypt_policy(_policy: &str, _recipient_public_hex: &str) -> Result<String, SplError> {
+    Err(SplError("policy encryption requires the `encryption` feature".into()))
+}
+
+/// The token's policy source, decrypting `encrypted_policy` with the
+/// recipient's X25519 private key when present.
+pub fn policy_source<'a>(token: &'a Token, decryption_key: Option<&str>) -> Result<Cow<'a, str>, SplError> {
+    let Some(sealed) = &token.encrypted_policy else {
+        return Ok(Cow::Borrowed(&token.policy));
+    };
+    let key = decryption_key.ok_or_else(|| SplError("policy is encrypted and no decryption key was given".into()))?;
+    decrypt_policy(sealed, key).map(Cow::Owned)
+}
+
+#[cfg(feature = "encryption")]
+fn decrypt_policy(sealed: &str, key: &str) -> Result<String, SplError> {
+    let bytes = crate::crypto::open_sealed(key, sealed)?;
+    String::from_utf8(bytes).map_err(|_| SplError("decrypted policy is not UTF-8".into()))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt_policy(_sealed: &str, _key: &str) -> Result<String, SplError> {
+    Err(SplError("policy decryption requires the `encryption` feature".into()))
+}
+
+/// Verifier-issued challenge that a PoP presentation signature must cover.
+/// Binding the nonce and timestamp into the signature stops a captured
+/// presentation from being replayed against a different challenge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub nonce: String,
+    /// Unix timestamp (seconds) at which the challenge was answered.
+    pub timestamp: i64,
+    /// Hex SHA-256 of evidence sent with the presentation, such as the
+    /// request body, which the signature then binds it to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<String>,
+}
+
+/// A PoP presentation: the agent's signature plus the challenge it signed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Presentation {
+    pub signature: String,
+    pub challenge: Challenge,
+}
+
+/// Build the bytes signed for a PoP presentation:
+/// SHA-256(envelope_payload \0 nonce \0 timestamp), followed inside the
+/// hash by `\0 evidence` when the challenge carries evidence.
+pub fn presentation_payload(token: &Token, challenge: &Challenge) -> Vec<u8> {
+    let payload = envelope_payload(token);
+    let mut hasher = Sha256::new();
+    hasher.update(&payload);
+    hasher.update(b"\0");
+    hasher.update(challenge.nonce.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(challenge.timestamp.to_string().as_bytes());
+    if let Some(evidence) = &challenge.evidence {
+        hasher.update(b"\0");
+        hasher.update(evidence.as_bytes());
+    }
+    hasher.finalize().to_vec()
 }
 
 /// Create a PoP presentation signature for a token.
-/// The agent signs SHA-256(signing_payload) with its own Ed25519 key.
+/// The agent signs `presentation_payload(token, challenge)` with its own Ed25519 key.
 pub fn create_presentation_signature(
     token: &Token,
     agent_private_key_hex: &str,
+    challenge: &Challenge,
 ) -> Result<String, SplError> {
-    let seed_bytes = hex::decode(agent_private_key_hex)
-        .map_err(|e| SplError(format!("invalid agent private key hex: {e}")))?;
-    let seed: [u8; 32] = seed_bytes
-        .try_into()
-        .map_err(|_| SplError("agent private key must be 32 bytes".to_string()))?;
-
-    let signing_key = SigningKey::from_bytes(&seed);
-    let payload = signing_payload(
-        &token.policy, &token.merkle_root, &token.hash_chain_commitment,
-        token.sealed, &token.expires,
-    );
-    let mut hasher = Sha256::new();
-    hasher.update(&payload);
-    let pop_payload = hasher.finalize();
+    let seed = key_from_hex(agent_private_key_hex, "agent private key")?;
+    sign_hex(&seed, &presentation_payload(token, challenge))
+}
 
-    let sig = signing_key.sign(&pop_payload);
-    Ok(hex::encode(sig.to_bytes()))
+/// Extract `(policy "name" expr)` clauses from a multi-policy source.
+/// Returns `None` when the source is a single ordinary policy.
+pub fn named_clauses(exprs: &[Node]) -> Result<Option<Vec<(String, Node)>>, SplError> {
+    let is_clause = |n: &Node| matches!(n, Node::List(items) if items.first() == Some(&Node::Symbol("policy".into())));
+    if exprs.len() == 1 && !is_clause(&exprs[0]) {
+        return Ok(None);
+    }
+    let mut clauses = Vec::with_capacity(exprs.len());
+    for expr in exprs {
+        match expr {
+            Node::List(items) if is_clause(expr) => match items.as_slice() {
+                [_, Node::Str(name), body] => {
+                    if clauses.iter().any(|(n, _)| n == name) {
+                        return Err(SplError(format!("duplicate policy clause: {name}")));
+                    }
+                    clauses.push((name.clone(), body.clone()));
+                }
+                _ => return Err(SplError("policy clause must be (policy \"name\" expr)".into())),
+            },
+            _ => return Err(SplError("multi-policy source may only contain policy clauses".into())),
+        }
+    }
+    Ok(Some(clauses))
+}
+
+/// Pick the policy that governs `req`. Single policies apply to every request;
+/// named clauses match `req["action"]` exactly, or else its namespace (the
+/// part before the first `.`), so `"payments.create"` selects `"payments"`.
+pub(crate) fn select_clause(exprs: &[Node], req: &HashMap<String, Node>) -> Result<Node, SplError> {
+    let Some(clauses) = named_clauses(exprs)? else {
+        return Ok(exprs.first().cloned().unwrap_or(Node::Nil));
+    };
+    let action = match req.get("action") {
+        Some(Node::Str(a)) => a.as_str(),
+        _ => return Err(SplError("multi-policy token requires req[\"action\"]".into())),
+    };
+    let namespace = action.split('.').next().unwrap_or(action);
+    clauses
+        .iter()
+        .find(|(name, _)| name == action)
+        .or_else(|| clauses.iter().find(|(name, _)| name == namespace))
+        .map(|(_, body)| body.clone())
+        .ok_or_else(|| SplError(format!("no policy clause for action: {action}")))
+}
+
+/// Why a token was rejected, stable across releases for callers that branch
+/// on or log the failure. Serializes as [`VerifyErrorCode::as_str`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyErrorCode {
+    /// The token's envelope version is unknown to this verifier.
+    UnsupportedVersion,
+    InvalidSignature,
+    /// The issuer key is not trusted, or not for when the token was issued.
+    UntrustedIssuer,
+    /// The token's signature scheme is not enabled or not accepted.
+    UnsupportedAlgorithm,
+    /// An envelope field (such as a timestamp) could not be parsed.
+    MalformedToken,
+    /// The token's lifetime violates the verifier profile.
+    TokenLifetime,
+    /// The token, or a presentation of it, predates its `issued_at`.
+    PresentedBeforeIssuance,
+    PresentationRequired,
+    InvalidPresentation,
+    /// The presentation challenge is older than the profile allows.
+    PresentationExpired,
+    NonceReused,
+    /// The caveat chain or its proof does not verify.
+    InvalidCaveat,
+    /// A use-limited token came without the preimage for its next use, or
+    /// its uses are spent (see [`crate::uses`]).
+    UseLimit,
+    /// The policy is encrypted and could not be decrypted.
+    PolicyDecryption,
+    PolicyParse,
+    /// No `(policy ...)` clause governs the request.
+    ClauseSelection,
+    /// An `(include ...)` could not be expanded, or its fragments no longer
+    /// match the token's `resolved_policy_hash`.
+    Fragment,
+    Evaluation,
+    /// The policy uses an operator the verifier profile does not permit.
+    OperatorNotPermitted,
+    /// The caveat chain exceeds the profile's [`crate::caveat::ChainLimits`],
+    /// or the refresh lineage its [`VerifierProfile::max_refresh_depth`].
+    ChainLimit,
+    /// The request's action is outside the token's signed scope.
+    OutOfScope,
+    /// The token's `not_before` is still in the future.
+    NotYetValid,
+    /// The token was minted in an issuer epoch the profile has revoked.
+    RevokedEpoch,
+}
+
+impl VerifyErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VerifyErrorCode::UnsupportedVersion => "unsupported_version",
+            VerifyErrorCode::InvalidSignature => "invalid_signature",
+            VerifyErrorCode::UntrustedIssuer => "untrusted_issuer",
+            VerifyErrorCode::UnsupportedAlgorithm => "unsupported_algorithm",
+            VerifyErrorCode::MalformedToken => "malformed_token",
+            VerifyErrorCode::TokenLifetime => "token_lifetime",
+            VerifyErrorCode::PresentedBeforeIssuance => "presented_before_issuance",
+            VerifyErrorCode::PresentationRequired => "presentation_required",
+            VerifyErrorCode::InvalidPresentation => "invalid_presentation",
+            VerifyErrorCode::PresentationExpired => "presentation_expired",
+            VerifyErrorCode::NonceReused => "nonce_reused",
+            VerifyErrorCode::InvalidCaveat => "invalid_caveat",
+            VerifyErrorCode::UseLimit => "use_limit",
+            VerifyErrorCode::PolicyDecryption => "policy_decryption",
+            VerifyErrorCode::PolicyParse => "policy_parse",
+            VerifyErrorCode::ClauseSelection => "clause_selection",
+            VerifyErrorCode::Fragment => "fragment",
+            VerifyErrorCode::Evaluation => "evaluation",
+            VerifyErrorCode::OperatorNotPermitted => "operator_not_permitted",
+            VerifyErrorCode::ChainLimit => "chain_limit",
+            VerifyErrorCode::OutOfScope => "out_of_scope",
+            VerifyErrorCode::NotYetValid => "not_yet_valid",
+            VerifyErrorCode::RevokedEpoch => "revoked_epoch",
+        }
+    }
+}
+
+/// A verification failure: its code plus a human-readable message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub code: VerifyErrorCode,
+    pub message: String,
 }
 
+impl VerifyError {
+    pub fn new(code: VerifyErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code.as_str(), self.message)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
 /// Result of token verification.
+#[derive(Debug, Clone)]
 pub struct VerifyTokenResult {
+    /// [`Token::id`] of the token checked. Unauthenticated when `code` is
+    /// a signature failure.
+    pub token_id: String,
     pub allow: bool,
     pub sealed: bool,
     pub error: Option<String>,
+    /// Set whenever `error` is.
+    pub code: Option<VerifyErrorCode>,
+    /// Obligations attached to an allow; empty otherwise.
+    pub obligations: Vec<Obligation>,
+    /// Machine-readable code from the policy's `deny-with`; `None` on allow.
+    pub reason_code: Option<String>,
+    /// Gas the policy evaluation consumed; 0 when rejected before evaluating.
+    pub gas_used: i64,
+    /// Deny-list versions `denylist-absent?` consulted, in evaluation order.
+    pub denylists: Vec<DenyListVersion>,
 }
 
-/// Verify a token's signature and evaluate its policy.
+impl VerifyTokenResult {
+    fn rejected(token: &Token, err: VerifyError) -> Self {
+        Self {
+            token_id: token.id().into_owned(),
+            allow: false,
+            sealed: token.sealed,
+            error: Some(err.message),
+            code: Some(err.code),
+            obligations: Vec::new(),
+            reason_code: None,
+            gas_used: 0,
+            denylists: Vec::new(),
+        }
+    }
+}
+
+/// Verify a token signed by one of `issuers`, then evaluate its policy,
+/// under the default [`VerifierProfile`] and the system clock. Tokens whose
+/// key `issuers` does not trust are rejected as
+/// [`VerifyErrorCode::UntrustedIssuer`].
 pub fn verify_token(
     token: &Token,
     req: HashMap<String, Node>,
     vars: HashMap<String, Node>,
+    issuers: &dyn IssuerKeys,
 ) -> VerifyTokenResult {
-    verify_token_with_pop(token, req, vars, None)
+    let profile = VerifierProfile::default();
+    let ctx = VerifyContext { issuers: Some(issuers), ..VerifyContext::new(&profile, SystemClock.now_unix()) };
+    verify_token_at(token, req, vars, &ctx)
 }
 
-/// Verify a token with optional PoP presentation signature.
+/// Verify a token's signature against its own `public_key` and evaluate its
+/// policy. Anyone can mint a token with their own key, so use this only
+/// where trust in the issuer is established some other way; otherwise call
+/// [`verify_token`].
+pub fn verify_token_untrusted(
+    token: &Token,
+    req: HashMap<String, Node>,
+    vars: HashMap<String, Node>,
+) -> VerifyTokenResult {
+    verify_token_with_pop(token, req, vars, None, None)
+}
+
+/// Verify use `preimage_hex` of a use-limited token against `store`, then
+/// evaluate its policy, under the default [`VerifierProfile`] and the
+/// system clock. The use is recorded only when the policy allows; see
+/// [`crate::uses`].
+pub fn verify_token_use(
+    token: &Token,
+    req: HashMap<String, Node>,
+    vars: HashMap<String, Node>,
+    store: &dyn UsageStore,
+    preimage_hex: &str,
+) -> VerifyTokenResult {
+    let profile = VerifierProfile::default();
+    let ctx = VerifyContext {
+        usage_store: Some(store),
+        use_preimage: Some(preimage_hex),
+        ..VerifyContext::new(&profile, SystemClock.now_unix())
+    };
+    verify_token_at(token, req, vars, &ctx)
+}
+
+/// Verify a token with optional PoP presentation under the default
+/// [`VerifierProfile`] and the system clock. Use [`crate::profile::Verifier`]
+/// to configure either.
+///
+/// When `replay_cache` is supplied, the presentation nonce is recorded after
+/// the signature checks out and any previously seen nonce is rejected.
 pub fn verify_token_with_pop(
     token: &Token,
     req: HashMap<String, Node>,
     vars: HashMap<String, Node>,
-    presentation_signature: Option<&str>,
+    presentation: Option<&Presentation>,
+    replay_cache: Option<&dyn ReplayCache>,
+) -> VerifyTokenResult {
+    let profile = VerifierProfile::default();
+    let ctx = VerifyContext { presentation, replay_cache, ..VerifyContext::new(&profile, SystemClock.now_unix()) };
+    verify_token_at(token, req, vars, &ctx)
+}
+
+/// Verify a token whose policy is sealed to `recipient_private_key_hex`
+/// (an X25519 key), under the default profile and the system clock.
+pub fn verify_encrypted_token(
+    token: &Token,
+    req: HashMap<String, Node>,
+    vars: HashMap<String, Node>,
+    recipient_private_key_hex: &str,
+) -> VerifyTokenResult {
+    let profile = VerifierProfile::default();
+    let ctx = VerifyContext {
+        decryption_key: Some(recipient_private_key_hex),
+        ..VerifyContext::new(&profile, SystemClock.now_unix())
+    };
+    verify_token_at(token, req, vars, &ctx)
+}
+
+/// Outcome of checking one request against several tokens.
+#[derive(Debug, Clone)]
+pub struct VerifyAnyResult {
+    pub allow: bool,
+    /// Index in the input slice of the token that allowed the request.
+    pub token_index: Option<usize>,
+    /// Per-token results in input order, ending at the allowing token.
+    pub results: Vec<VerifyTokenResult>,
+}
+
+impl VerifyAnyResult {
+    /// Result of the allowing token, carrying its obligations.
+    pub fn decision(&self) -> Option<&VerifyTokenResult> {
+        self.token_index.map(|i| &self.results[i])
+    }
+}
+
+/// Check whether any of `tokens` allows the request, under the default
+/// [`VerifierProfile`] and the system clock.
+///
+/// Tokens are tried in input order and the first allow wins, so callers
+/// express preference by ordering. Tokens sharing a policy parse it once.
+/// PoP-bound tokens are rejected here; verify them with their presentation.
+pub fn verify_any(
+    tokens: &[Token],
+    req: HashMap<String, Node>,
+    vars: HashMap<String, Node>,
+) -> VerifyAnyResult {
+    let profile = VerifierProfile::default();
+    let ctx = VerifyContext::new(&profile, SystemClock.now_unix());
+    verify_any_at(tokens, &req, &vars, &ctx)
+}
+
+pub(crate) fn verify_any_at(
+    tokens: &[Token],
+    req: &HashMap<String, Node>,
+    vars: &HashMap<String, Node>,
+    ctx: &VerifyContext<'_>,
+) -> VerifyAnyResult {
+    let cache = ParseCache::default();
+    let ctx = VerifyContext { parse_cache: ctx.parse_cache.or(Some(&cache)), ..*ctx };
+    let mut results = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let result = verify_token_at(token, req.clone(), vars.clone(), &ctx);
+        let allow = result.allow;
+        results.push(result);
+        if allow {
+            return VerifyAnyResult { allow, token_index: Some(i), results };
+        }
+    }
+    VerifyAnyResult { allow: false, token_index: None, results }
+}
+
+/// Ed25519 signatures checked per batch in [`verify_tokens_batch`].
+const SIGNATURE_BATCH: usize = 64;
+
+/// A token's input index, Ed25519 key, signed payload and signature.
+type SignedPayload = (usize, [u8; 32], Vec<u8>, [u8; 64]);
+
+/// Verify many tokens, each against its own request, under the default
+/// [`VerifierProfile`] and the system clock. Results are in input order.
+///
+/// Ed25519 signatures are checked together (`batch` feature), in parallel
+/// with the `rayon` feature, and a batch that fails falls back to checking
+/// its tokens one by one. The batch equation is cofactored, so a signature
+/// whose `R` an issuer deliberately gave a small-order component can pass
+/// here yet fail [`verify_token_untrusted`]'s strict check; results
+/// otherwise match verifying each token with [`verify_token_untrusted`].
+/// Tokens sharing a policy parse it once.
+/// PoP-bound tokens are rejected here; verify them with their presentation.
+pub fn verify_tokens_batch(items: &[(Token, HashMap<String, Node>)]) -> Vec<VerifyTokenResult> {
+    let profile = VerifierProfile::default();
+    let ctx = VerifyContext::new(&profile, SystemClock.now_unix());
+    verify_batch_at(items, &HashMap::new(), &ctx)
+}
+
+pub(crate) fn verify_batch_at(
+    items: &[(Token, HashMap<String, Node>)],
+    vars: &HashMap<String, Node>,
+    ctx: &VerifyContext<'_>,
+) -> Vec<VerifyTokenResult> {
+    let cache = ParseCache::default();
+    let ctx = VerifyContext { parse_cache: ctx.parse_cache.or(Some(&cache)), ..*ctx };
+    let verified = batch_signatures(items.iter().map(|(token, _)| token));
+    items
+        .iter()
+        .zip(verified)
+        .map(|((token, req), signature_verified)| {
+            let ctx = VerifyContext { signature_verified, ..ctx };
+            verify_token_at(token, req.clone(), vars.clone(), &ctx)
+        })
+        .collect()
+}
+
+/// Which tokens' Ed25519 signatures verified as part of a passing batch.
+/// `false` means unchecked, not invalid.
+fn batch_signatures<'t>(tokens: impl Iterator<Item = &'t Token>) -> Vec<bool> {
+    let mut verified = Vec::new();
+    let mut signed: Vec<SignedPayload> = Vec::new();
+    for (i, token) in tokens.enumerate() {
+        verified.push(false);
+        if !token.alg.is_ed25519() {
+            continue;
+        }
+        let key = hex::decode(&token.public_key).ok().and_then(|k| <[u8; 32]>::try_from(k).ok());
+        let signature = hex::decode(&token.signature).ok().and_then(|s| <[u8; 64]>::try_from(s).ok());
+        if let (Some(key), Some(signature)) = (key, signature) {
+            signed.push((i, key, envelope_payload(token), signature));
+        }
+    }
+
+    let check = |chunk: &[SignedPayload]| {
+        let batch: Vec<_> = chunk.iter().map(|(_, key, payload, sig)| (key, payload.as_slice(), sig)).collect();
+        crate::backend::backend().ed25519_verify_batch(&batch)
+    };
+    #[cfg(feature = "rayon")]
+    let passed: Vec<bool> = {
+        use rayon::prelude::*;
+        signed.par_chunks(SIGNATURE_BATCH).map(check).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let passed: Vec<bool> = signed.chunks(SIGNATURE_BATCH).map(check).collect();
+
+    for (chunk, passed) in signed.chunks(SIGNATURE_BATCH).zip(passed) {
+        if passed {
+            for (i, ..) in chunk {
+                verified[*i] = true;
+            }
+        }
+    }
+    verified
+}
+
+/// Inputs to a verification beyond the token and request.
+pub(crate) struct VerifyContext<'a> {
+    pub presentation: Option<&'a Presentation>,
+    pub replay_cache: Option<&'a dyn ReplayCache>,
+    pub profile: &'a VerifierProfile,
+    pub now: i64,
+    pub decryption_key: Option<&'a str>,
+    /// Parsed policies shared across the tokens of one [`verify_any`] call,
+    /// or across calls by a [`crate::cache::PolicyCache`].
+    pub parse_cache: Option<&'a dyn PolicyParser>,
+    /// Expands `(include ...)` forms; policies with includes fail without one.
+    pub fragments: Option<&'a dyn FragmentResolver>,
+    /// Backs `cumulative-spend` and records each allowed spend.
+    pub spend_tracker: Option<&'a Arc<dyn SpendTracker>>,
+    /// Issuer keys to accept; `None` trusts the token's own key.
+    pub issuers: Option<&'a dyn IssuerKeys>,
+    /// Counts uses of tokens with a `hash_chain_commitment`; `None` leaves
+    /// their uses unlimited.
+    pub usage_store: Option<&'a dyn UsageStore>,
+    /// Trusted device attestation roots, backing `attested?`.
+    pub attestation: Option<&'a Arc<AttestationVerifier>>,
+    /// Deny lists backing `denylist-absent?`.
+    pub denylists: Option<&'a Arc<dyn DenyListProvider>>,
+    /// Oldest deny-list snapshot (in seconds) a decision may rely on.
+    pub denylist_max_staleness_secs: u64,
+    /// Exchange rates backing `convert`.
+    pub rates: Option<&'a Arc<dyn RateProvider>>,
+    /// Hash-chain preimage presented for this use.
+    pub use_preimage: Option<&'a str>,
+    /// The token's signature was already checked, as part of a batch.
+    pub signature_verified: bool,
+}
+
+impl<'a> VerifyContext<'a> {
+    /// A context verifying under `profile` at `now`, with no presentation,
+    /// stores, or trusted issuers.
+    pub fn new(profile: &'a VerifierProfile, now: i64) -> Self {
+        Self {
+            presentation: None,
+            replay_cache: None,
+            profile,
+            now,
+            decryption_key: None,
+            parse_cache: None,
+            fragments: None,
+            spend_tracker: None,
+            issuers: None,
+            usage_store: None,
+            attestation: None,
+            denylists: None,
+            denylist_max_staleness_secs: DEFAULT_MAX_STALENESS_SECS,
+            rates: None,
+            use_preimage: None,
+            signature_verified: false,
+        }
+    }
+}
+
+pub(crate) type Parsed = Result<Arc<Vec<Node>>, SplError>;
+
+/// Where [`verify_token_at`] gets its parsed policies when not parsing afresh.
+pub(crate) trait PolicyParser {
+    fn parse(&self, policy: &str, limits: &Limits, syntax: Syntax) -> Parsed;
+}
+
+/// Parse results keyed by policy source and syntax, so tokens sharing a
+/// policy parse it once.
+#[derive(Default)]
+pub(crate) struct ParseCache {
+    parsed: RefCell<HashMap<(String, Syntax), Parsed>>,
+}
+
+impl PolicyParser for ParseCache {
+    fn parse(&self, policy: &str, limits: &Limits, syntax: Syntax) -> Parsed {
+        let key = (policy.to_string(), syntax);
+        if let Some(cached) = self.parsed.borrow().get(&key) {
+            return cached.clone();
+        }
+        let parsed = parse_all_with_syntax(policy, limits, syntax).map(Arc::new);
+        self.parsed.borrow_mut().insert(key, parsed.clone());
+        parsed
+    }
+}
+
+/// Verification core shared by the free functions and [`crate::profile::Verifier`].
+pub(crate) fn verify_token_at(
+    token: &Token,
+    req: HashMap<String, Node>,
+    vars: HashMap<String, Node>,
+    ctx: &VerifyContext<'_>,
 ) -> VerifyTokenResult {
+    let VerifyContext {
+        presentation,
+        replay_cache,
+        profile,
+        now,
+        decryption_key,
+        parse_cache,
+        fragments,
+        spend_tracker,
+        issuers,
+        usage_store,
+        attestation,
+        denylists,
+        denylist_max_staleness_secs,
+        rates,
+        use_preimage,
+        signature_verified,
+    } = *ctx;
+    let reject = |code, message: String| VerifyTokenResult::rejected(token, VerifyError::new(code, message));
+
+    let version = match token.envelope_version() {
+        Ok(version) => version,
+        Err(e) => return reject(VerifyErrorCode::UnsupportedVersion, e.to_string()),
+    };
+    if let Err(e) = check_envelope(token) {
+        return reject(VerifyErrorCode::MalformedToken, e.to_string());
+    }
+    // Bound ids before anything decodes or looks them up.
+    if let Err(e) = check_ids(&token.token_id, &token.kid, &token.issuer) {
+        return reject(VerifyErrorCode::MalformedToken, e.to_string());
+    }
+
+    if !token.alg.is_supported() || !profile.accepts_alg(token.alg) {
+        return reject(
+            VerifyErrorCode::UnsupportedAlgorithm,
+            format!("signature scheme {} not accepted", token.alg.name()),
+        );
+    }
+
+    if let Some(issuers) = issuers {
+        if let Err(e) = check_issuer(issuers, token, now) {
+            return VerifyTokenResult::rejected(token, e);
+        }
+    }
+
     // Verify signature over full token envelope
-    let payload = signing_payload(
-        &token.policy, &token.merkle_root, &token.hash_chain_commitment,
-        token.sealed, &token.expires,
-    );
-    if !verify_ed25519(
-        &payload,
-        &token.signature,
-        &token.public_key,
-    ) {
-        return VerifyTokenResult {
-            allow: false,
-            sealed: token.sealed,
-            error: Some("invalid signature".to_string()),
+    if !signature_verified && !token.alg.verify(&envelope_payload(token), &token.signature, &token.public_key) {
+        return reject(VerifyErrorCode::InvalidSignature, "invalid signature".into());
+    }
+    if let Err(e) = check_multisig(token) {
+        return VerifyTokenResult::rejected(token, e);
+    }
+    if token.threshold.is_some() {
+        // The lead key signs the declared signers, so co-signatures prove
+        // nothing unless each co-signer is independently trusted.
+        let Some(issuers) = issuers else {
+            return reject(
+                VerifyErrorCode::UntrustedIssuer,
+                "multi-signature tokens require a trusted issuer set".into(),
+            );
         };
+        for cosignature in &token.cosignatures {
+            if let Err(e) = check_cosigner(issuers, token, &cosignature.public_key, now) {
+                return VerifyTokenResult::rejected(token, e);
+            }
+        }
+    }
+    if let Err(e) = check_did_key(token) {
+        return VerifyTokenResult::rejected(token, e);
+    }
+
+    if let Err(e) = profile.check_token(token) {
+        return VerifyTokenResult::rejected(token, e);
+    }
+    if let Err(e) = check_token_vars(&token.vars) {
+        return reject(VerifyErrorCode::MalformedToken, e.to_string());
+    }
+    if let Err(e) = check_scope_globs(&token.scope) {
+        return reject(VerifyErrorCode::MalformedToken, e.to_string());
+    }
+    if let Err(e) = check_scope(token, &req, profile.string_match) {
+        return VerifyTokenResult::rejected(token, e);
     }
 
     // PoP binding: if token has pop_key, require and verify presentation signature
-    if let Some(pop_key) = &token.pop_key {
-        match presentation_signature {
-            None => {
-                return VerifyTokenResult {
-                    allow: false,
-                    sealed: token.sealed,
-                    error: Some("PoP binding requires presentation signature".to_string()),
-                };
-            }
-            Some(pres_sig) => {
-                let mut hasher = Sha256::new();
-                hasher.update(&payload);
-                let pop_payload = hasher.finalize();
-                if !verify_ed25519(&pop_payload, pres_sig, pop_key) {
-                    return VerifyTokenResult {
-                        allow: false,
-                        sealed: token.sealed,
-                        error: Some("invalid presentation signature".to_string()),
-                    };
-                }
+    let challenge = match (&token.pop_key, presentation) {
+        (Some(_), None) => {
+            return reject(
+                VerifyErrorCode::PresentationRequired,
+                "PoP binding requires presentation signature".into(),
+            );
+        }
+        (Some(pop_key), Some(pres)) => {
+            let pop_payload = presentation_payload(token, &pres.challenge);
+            if !verify_ed25519(&pop_payload, &pres.signature, pop_key) {
+                return reject(VerifyErrorCode::InvalidPresentation, "invalid presentation signature".into());
             }
+            Some(&pres.challenge)
         }
+        (None, _) => None,
+    };
+
+    if let Err(e) = profile.check_freshness(token, challenge, now) {
+        return VerifyTokenResult::rejected(token, e);
     }
 
+    if let (Some(challenge), Some(cache)) = (challenge, replay_cache) {
+        if !cache.check_and_record(&challenge.nonce) {
+            return reject(VerifyErrorCode::NonceReused, "presentation nonce already used".into());
+        }
+    }
+
+    let chain_use = match (&token.hash_chain_commitment, usage_store) {
+        (Some(commitment), Some(store)) => {
+            let Some(preimage) = use_preimage else {
+                return reject(VerifyErrorCode::UseLimit, "token is use-limited; present its next hash-chain preimage".into());
+            };
+            let index = match store.uses(commitment) {
+                Ok(index) => index,
+                Err(e) => return reject(VerifyErrorCode::UseLimit, format!("usage store failed: {e}")),
+            };
+            if !check_use(commitment, preimage, index) {
+                return reject(
+                    VerifyErrorCode::UseLimit,
+                    format!("preimage is not for use {index} of the token's hash chain, or its uses are spent"),
+                );
+            }
+            Some((store, commitment, index))
+        }
+        _ => None,
+    };
+
+    let policy = match policy_source(token, decryption_key) {
+        Ok(policy) => policy,
+        Err(e) => return reject(VerifyErrorCode::PolicyDecryption, e.to_string()),
+    };
+
     // Parse policy
-    let ast = match parse(&token.policy) {
+    let parsed = match parse_cache {
+        Some(cache) => cache.parse(&policy, &profile.limits, version.syntax()),
+        None => parse_all_with_syntax(&policy, &profile.limits, version.syntax()).map(Arc::new),
+    };
+    let exprs = match parsed {
+        Ok(exprs) => exprs,
+        Err(e) => return reject(VerifyErrorCode::PolicyParse, format!("parse error: {e}")),
+    };
+    let exprs = match resolve_fragments(token, &exprs, fragments) {
+        Ok(Some(expanded)) => Arc::new(expanded),
+        Ok(None) => exprs,
+        Err(e) => return reject(VerifyErrorCode::Fragment, e.to_string()),
+    };
+
+    // Select the clause governing this request
+    let ast = match select_clause(&exprs, &req) {
         Ok(ast) => ast,
-        Err(e) => {
-            return VerifyTokenResult {
-                allow: false,
-                sealed: token.sealed,
-                error: Some(format!("parse error: {e}")),
-            }
+        Err(e) => return reject(VerifyErrorCode::ClauseSelection, e.to_string()),
+    };
+
+    // Every caveat must hold alongside the selected clause
+    let ast = match verify_caveat_chain(token) {
+        Ok(caveats) if caveats.is_empty() => ast,
+        Ok(caveats) => {
+            let mut conjuncts = vec![Node::Symbol("and".into()), ast];
+            conjuncts.extend(caveats);
+            Node::List(conjuncts)
         }
+        Err(e) => return reject(VerifyErrorCode::InvalidCaveat, e.to_string()),
     };
 
+    if let Some(allowed) = &profile.allowed_ops {
+        if let Some(op) = forbidden_ops(&ast, allowed).first() {
+            return reject(VerifyErrorCode::OperatorNotPermitted, format!("operator not permitted: {op}"));
+        }
+    }
+    if profile.strict {
+        let disabled = disabled_references(&ast, &profile.disabled_vars);
+        if let Some(var) = disabled.iter().find(|v| !vars.contains_key(v.name())) {
+            return reject(
+                VerifyErrorCode::Evaluation,
+                format!("policy references disabled standard var: {}", var.name()),
+            );
+        }
+    }
+    let mut vars = vars;
+    vars.extend(token.vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+    // `now` resolves through the verifier clock, not vars.
+    let mut not_injected = profile.disabled_vars.clone();
+    not_injected.push(StandardVar::Now);
+    inject_standard_vars(&mut vars, token, now, &not_injected);
+
     // Evaluate
     let env = Env {
         req,
         vars,
-        per_day_count: Box::new(|_, _| 0),
-        crypto: CryptoCallbacks::default(),
-        max_gas: 10_000,
-        sealed: false,
-        strict: false,
+        clock: Arc::new(FixedClock(now)),
+        strict: profile.strict,
+        allowed_ops: profile.allowed_ops.clone(),
+        spend_tracker: spend_tracker.cloned(),
+        attestation: attestation.cloned(),
+        denylists: denylists.cloned(),
+        denylist_max_staleness_secs,
+        rates: rates.cloned(),
+        // Evaluation is reached only after the presentation checked out.
+        pop_key: token.pop_key.clone(),
+        limits: profile.limits.clone(),
+        string_match: profile.string_match,
+        ..Env::default()
     };
 
-    match eval_policy(&ast, &env) {
-        Ok(result) => VerifyTokenResult {
-            allow: result.is_truthy(),
-            sealed: token.sealed,
-            error: None,
-        },
-        Err(e) => VerifyTokenResult {
-            allow: false,
-            sealed: token.sealed,
-            error: Some(e.to_string()),
-        },
+    match eval_policy_detailed(&ast, &env) {
+        Ok(outcome) => {
+            let allow = outcome.value.is_truthy();
+            if let (true, Some((store, commitment, index))) = (allow, chain_use) {
+                match store.record_use(commitment, index) {
+                    Ok(true) => {}
+                    Ok(false) => return reject(VerifyErrorCode::UseLimit, format!("use {index} was already consumed")),
+                    Err(e) => return reject(VerifyErrorCode::UseLimit, format!("usage store failed: {e}")),
+                }
+            }
+            if let (true, Some(tracker)) = (allow, spend_tracker) {
+                if let Err(e) = record_allowed(tracker.as_ref(), &env.req) {
+                    return reject(VerifyErrorCode::Evaluation, e.to_string());
+                }
+            }
+            VerifyTokenResult {
+                token_id: token.id().into_owned(),
+                allow,
+                sealed: token.sealed,
+                error: None,
+                code: None,
+                obligations: if allow { outcome.obligations } else { Vec::new() },
+                reason_code: if allow { None } else { outcome.reason_code },
+                gas_used: outcome.gas_used,
+                denylists: outcome.denylists,
+            }
+        }
+        Err(e) => reject(VerifyErrorCode::Evaluation, e.to_string()),
+    }
+}
+
+/// Expand a policy's includes and check the result against the token's
+/// signed `resolved_policy_hash`. `None` when the policy includes nothing.
+fn resolve_fragments(
+    token: &Token,
+    exprs: &[Node],
+    resolver: Option<&dyn FragmentResolver>,
+) -> Result<Option<Vec<Node>>, SplError> {
+    if !exprs.iter().any(has_includes) {
+        return Ok(None);
+    }
+    let expected = token
+        .resolved_policy_hash
+        .as_deref()
+        .ok_or_else(|| SplError("policy includes fragments but the token does not pin them".into()))?;
+    let resolver = resolver.ok_or_else(|| SplError("policy includes fragments and no resolver was given".into()))?;
+    let expanded = exprs.iter().map(|e| expand(e, resolver)).collect::<Result<Vec<_>, _>>()?;
+    if !ct_eq_hex(&resolved_hash(&expanded), expected) {
+        return Err(SplError("included fragments changed since the token was minted".into()));
     }
+    Ok(Some(expanded))
 }
@@ -0,0 +1,620 @@
+//! Migration of Cedar policies onto SPL.
+//!
+//! [`from_cedar`] translates a policy set written in a subset of Cedar into
+//! one SPL expression: the request is allowed when some `permit` matches and
+//! no `forbid` does, as in Cedar.
+//!
+//! The request shape follows the SPL conventions: `principal`, `action`,
+//! and `resource` are request fields holding entity ids (so
+//! `Action::"payments.create"` becomes `"payments.create"`), and
+//! `context.amount` reads `req["amount"]`. Supported conditions are
+//! `&&`, `||`, `!`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `in` against a set
+//! literal, `.contains`, `.containsAll`, `.containsAny`, and string,
+//! integer, boolean, and set literals.
+//!
+//! Anything else (entity hierarchies, entity attributes, `has`, `like`,
+//! `is`, `if`, arithmetic, records, extension functions) is listed in
+//! [`Translation::untranslated`] and fails closed: the condition becomes
+//! whichever of `#t` or `#f` narrows what the policy allows, so a
+//! translation never permits more than the Cedar original.
+
+use crate::types::{Node, SplError};
+
+/// Result of [`from_cedar`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Translation {
+    pub policy: Node,
+    /// Conditions replaced by a fail-closed constant, in source order.
+    pub untranslated: Vec<Untranslated>,
+}
+
+impl Translation {
+    /// Whether every condition translated, so the policy is equivalent to
+    /// the Cedar original rather than only at most as permissive.
+    pub fn is_complete(&self) -> bool {
+        self.untranslated.is_empty()
+    }
+}
+
+/// A Cedar construct with no SPL translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Untranslated {
+    /// Index of the Cedar policy in the source, from 0.
+    pub policy: usize,
+    /// What could not be translated, e.g. ``entity hierarchy (`in` Group::"admins")``.
+    pub construct: String,
+}
+
+/// Translate a Cedar policy set into SPL. Syntax errors fail the whole
+/// translation; unsupported constructs are reported instead.
+pub fn from_cedar(src: &str) -> Result<Translation, SplError> {
+    let mut parser = Parser { tokens: lex(src)?, pos: 0 };
+    let mut permits = Vec::new();
+    let mut forbids = Vec::new();
+    let mut untranslated = Vec::new();
+    while !parser.at_end() {
+        let policy = parser.policy()?;
+        let mut tr = Translator { policy: permits.len() + forbids.len(), untranslated: &mut untranslated };
+        // A forbid's condition is negated, so its fail-closed constant is #t.
+        let condition = tr.policy(&policy, policy.permit);
+        if policy.permit {
+            permits.push(condition);
+        } else {
+            forbids.push(negate(condition));
+        }
+    }
+    let mut clauses = vec![any(permits)];
+    clauses.extend(forbids);
+    Ok(Translation { policy: all(clauses), untranslated })
+}
+
+// --- Lexer ---
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Punct(&'static str),
+}
+
+const PUNCT: &[&str] = &[
+    "::", "==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", "[", "]", ",", ";", ".", ":", "<", ">", "!", "-",
+    "+", "*", "@",
+];
+
+fn lex(src: &str) -> Result<Vec<(usize, Tok)>, SplError> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if src[i..].starts_with("//") {
+            i = src[i..].find('\n').map_or(bytes.len(), |n| i + n);
+        } else if c == b'"' {
+            let (s, end) = lex_string(src, i)?;
+            tokens.push((i, Tok::Str(s)));
+            i = end;
+        } else if c.is_ascii_digit() {
+            let end = src[i..].find(|c: char| !c.is_ascii_digit()).map_or(bytes.len(), |n| i + n);
+            let n = src[i..end].parse().map_err(|_| syntax(i, "integer out of range"))?;
+            tokens.push((i, Tok::Int(n)));
+            i = end;
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let end = src[i..].find(|c: char| !c.is_ascii_alphanumeric() && c != '_').map_or(bytes.len(), |n| i + n);
+            tokens.push((i, Tok::Ident(src[i..end].to_string())));
+            i = end;
+        } else {
+            let p = PUNCT.iter().find(|p| src[i..].starts_with(**p)).ok_or_else(|| syntax(i, "unexpected character"))?;
+            tokens.push((i, Tok::Punct(p)));
+            i += p.len();
+        }
+    }
+    Ok(tokens)
+}
+
+fn lex_string(src: &str, start: usize) -> Result<(String, usize), SplError> {
+    let mut out = String::new();
+    let mut chars = src[start + 1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((out, start + 1 + i + 1)),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => out.push('\n'),
+                Some((_, 't')) => out.push('\t'),
+                Some((_, 'r')) => out.push('\r'),
+                Some((_, '0')) => out.push('\0'),
+                Some((_, c @ ('"' | '\'' | '\\' | '*'))) => out.push(c),
+                _ => return Err(syntax(start + 1 + i, "unsupported string escape")),
+            },
+            c => out.push(c),
+        }
+    }
+    Err(syntax(start, "unterminated string"))
+}
+
+fn syntax(pos: usize, message: &str) -> SplError {
+    SplError(format!("Cedar syntax error at byte {pos}: {message}"))
+}
+
+// --- Parser ---
+
+struct Policy {
+    permit: bool,
+    scope: Vec<Expr>,
+    /// `when` conditions, and `unless` conditions wrapped in `Not`.
+    conditions: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Var(String),
+    Entity(String, String),
+    Set(Vec<Expr>),
+    Attr(Box<Expr>, String),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Method(Box<Expr>, String, Vec<Expr>),
+    /// A construct parsed only to be reported as untranslatable.
+    Unsupported(String),
+}
+
+struct Parser {
+    tokens: Vec<(usize, Tok)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn error(&self, message: &str) -> SplError {
+        match self.tokens.get(self.pos) {
+            Some((pos, _)) => syntax(*pos, message),
+            None => SplError(format!("Cedar syntax error at end of input: {message}")),
+        }
+    }
+
+    fn next(&mut self) -> Result<Tok, SplError> {
+        let tok = self.peek().cloned().ok_or_else(|| self.error("incomplete policy"))?;
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Tok::Punct(p)) if *p == punct);
+        self.pos += found as usize;
+        found
+    }
+
+    fn eat_keyword(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Tok::Ident(w)) if w == word);
+        self.pos += found as usize;
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), SplError> {
+        if self.eat(punct) { Ok(()) } else { Err(self.error(&format!("expected `{punct}`"))) }
+    }
+
+    fn expect_keyword(&mut self, word: &str) -> Result<(), SplError> {
+        if self.eat_keyword(word) { Ok(()) } else { Err(self.error(&format!("expected `{word}`"))) }
+    }
+
+    fn ident(&mut self) -> Result<String, SplError> {
+        match self.next()? {
+            Tok::Ident(name) => Ok(name),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected an identifier"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, SplError> {
+        match self.next()? {
+            Tok::Str(s) => Ok(s),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a string"))
+            }
+        }
+    }
+
+    fn policy(&mut self) -> Result<Policy, SplError> {
+        while self.eat("@") {
+            self.ident()?;
+            self.expect("(")?;
+            self.string()?;
+            self.expect(")")?;
+        }
+        let permit = match self.ident()?.as_str() {
+            "permit" => true,
+            "forbid" => false,
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected `permit` or `forbid`"));
+            }
+        };
+        self.expect("(")?;
+        let mut scope = Vec::new();
+        for (i, var) in ["principal", "action", "resource"].into_iter().enumerate() {
+            if i > 0 {
+                self.expect(",")?;
+            }
+            self.expect_keyword(var)?;
+            scope.extend(self.scope_constraint(var)?);
+        }
+        self.expect(")")?;
+        let mut conditions = Vec::new();
+        loop {
+            let negate = if self.eat_keyword("when") {
+                false
+            } else if self.eat_keyword("unless") {
+                true
+            } else {
+                break;
+            };
+            self.expect("{")?;
+            let expr = self.expr()?;
+            self.expect("}")?;
+            conditions.push(if negate { Expr::Not(Box::new(expr)) } else { expr });
+        }
+        self.expect(";")?;
+        Ok(Policy { permit, scope, conditions })
+    }
+
+    fn scope_constraint(&mut self, var: &str) -> Result<Option<Expr>, SplError> {
+        let subject = Box::new(Expr::Var(var.to_string()));
+        if self.eat("==") {
+            return Ok(Some(Expr::Binary("==", subject, Box::new(self.entity()?))));
+        }
+        if self.eat_keyword("in") {
+            let target = if var == "action" && matches!(self.peek(), Some(Tok::Punct("["))) {
+                self.primary()?
+            } else {
+                self.entity()?
+            };
+            return Ok(Some(Expr::Binary("in", subject, Box::new(target))));
+        }
+        if self.eat_keyword("is") {
+            let ty = self.path()?;
+            if self.eat_keyword("in") {
+                self.entity()?;
+            }
+            return Ok(Some(Expr::Unsupported(format!("type test (`{var} is {ty}`)"))));
+        }
+        Ok(None)
+    }
+
+    fn path(&mut self) -> Result<String, SplError> {
+        let mut path = self.ident()?;
+        while matches!(self.tokens.get(self.pos..self.pos + 2), Some([(_, Tok::Punct("::")), (_, Tok::Ident(_))])) {
+            self.pos += 1;
+            path = format!("{path}::{}", self.ident()?);
+        }
+        Ok(path)
+    }
+
+    fn entity(&mut self) -> Result<Expr, SplError> {
+        let ty = self.path()?;
+        self.expect("::")?;
+        Ok(Expr::Entity(ty, self.string()?))
+    }
+
+    fn expr(&mut self) -> Result<Expr, SplError> {
+        if self.eat_keyword("if") {
+            self.expr()?;
+            self.expect_keyword("then")?;
+            self.expr()?;
+            self.expect_keyword("else")?;
+            self.expr()?;
+            return Ok(Expr::Unsupported("if-then-else".into()));
+        }
+        let mut lhs = self.and()?;
+        while self.eat("||") {
+            lhs = Expr::Binary("||", Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, SplError> {
+        let mut lhs = self.relation()?;
+        while self.eat("&&") {
+            lhs = Expr::Binary("&&", Box::new(lhs), Box::new(self.relation()?));
+        }
+        Ok(lhs)
+    }
+
+    fn relation(&mut self) -> Result<Expr, SplError> {
+        let lhs = self.sum()?;
+        for op in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat(op) {
+                return Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?)));
+            }
+        }
+        if self.eat_keyword("in") {
+            return Ok(Expr::Binary("in", Box::new(lhs), Box::new(self.sum()?)));
+        }
+        if self.eat_keyword("has") {
+            let field = match self.next()? {
+                Tok::Ident(s) | Tok::Str(s) => s,
+                _ => return Err(self.error("expected an attribute name")),
+            };
+            return Ok(Expr::Unsupported(format!("attribute test (`has {field}`)")));
+        }
+        if self.eat_keyword("like") {
+            let pattern = self.string()?;
+            return Ok(Expr::Unsupported(format!("pattern match (`like \"{pattern}\"`)")));
+        }
+        if self.eat_keyword("is") {
+            let ty = self.path()?;
+            if self.eat_keyword("in") {
+                self.sum()?;
+            }
+            return Ok(Expr::Unsupported(format!("type test (`is {ty}`)")));
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expr, SplError> {
+        let mut lhs = self.product()?;
+        while let Some(op) = ["+", "-"].into_iter().find(|op| self.eat(op)) {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expr, SplError> {
+        let mut lhs = self.unary()?;
+        while self.eat("*") {
+            lhs = Expr::Binary("*", Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, SplError> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.member()
+    }
+
+    fn member(&mut self) -> Result<Expr, SplError> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(".") {
+                let name = self.ident()?;
+                if self.eat("(") {
+                    let args = self.list(")")?;
+                    expr = Expr::Method(Box::new(expr), name, args);
+                } else {
+                    expr = Expr::Attr(Box::new(expr), name);
+                }
+            } else if self.eat("[") {
+                let key = self.string()?;
+                self.expect("]")?;
+                expr = Expr::Attr(Box::new(expr), key);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Comma-separated expressions up to the closing `close`.
+    fn list(&mut self, close: &str) -> Result<Vec<Expr>, SplError> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            if !items.is_empty() {
+                self.expect(",")?;
+            }
+            items.push(self.expr()?);
+        }
+        Ok(items)
+    }
+
+    fn primary(&mut self) -> Result<Expr, SplError> {
+        match self.next()? {
+            Tok::Int(n) => Ok(Expr::Int(n)),
+            Tok::Str(s) => Ok(Expr::Str(s)),
+            Tok::Punct("(") => {
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Tok::Punct("[") => Ok(Expr::Set(self.list("]")?)),
+            Tok::Punct("{") => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match self.next()? {
+                        Tok::Punct("{") => depth += 1,
+                        Tok::Punct("}") => depth -= 1,
+                        _ => {}
+                    }
+                }
+                Ok(Expr::Unsupported("record literal".into()))
+            }
+            Tok::Ident(word) => match word.as_str() {
+                "true" => Ok(Expr::Bool(true)),
+                "false" => Ok(Expr::Bool(false)),
+                "principal" | "action" | "resource" | "context" => Ok(Expr::Var(word)),
+                _ => {
+                    self.pos -= 1;
+                    let path = self.path()?;
+                    if self.eat("::") {
+                        return Ok(Expr::Entity(path, self.string()?));
+                    }
+                    self.expect("(")?;
+                    self.list(")")?;
+                    Ok(Expr::Unsupported(format!("extension function `{path}`")))
+                }
+            },
+            Tok::Punct(_) => {
+                self.pos -= 1;
+                Err(self.error("expected an expression"))
+            }
+        }
+    }
+}
+
+// --- Translation ---
+
+struct Translator<'a> {
+    policy: usize,
+    untranslated: &'a mut Vec<Untranslated>,
+}
+
+impl Translator<'_> {
+    /// The policy's scope and conditions as one SPL condition.
+    fn policy(&mut self, policy: &Policy, permits: bool) -> Node {
+        let terms = policy.scope.iter().chain(&policy.conditions).map(|e| self.condition(e, permits)).collect();
+        all(terms)
+    }
+
+    /// Translate a boolean expression. `permits` says whether the expression
+    /// being true widens what is allowed; an untranslatable condition then
+    /// becomes `#f`, and otherwise `#t`.
+    fn condition(&mut self, expr: &Expr, permits: bool) -> Node {
+        let result = match expr {
+            Expr::Bool(b) => Ok(Node::Bool(*b)),
+            Expr::Not(inner) => Ok(negate(self.condition(inner, !permits))),
+            Expr::Binary("&&", ..) => Ok(all(self.flatten(expr, "&&", permits))),
+            Expr::Binary("||", ..) => Ok(any(self.flatten(expr, "||", permits))),
+            Expr::Binary("in", a, b) => match b.as_ref() {
+                Expr::Set(_) => value(a).and_then(|a| Ok(list("member", vec![a, value(b)?]))),
+                Expr::Entity(ty, id) => Err(format!("entity hierarchy (`in` {ty}::\"{id}\")")),
+                _ => Err("entity hierarchy (`in`)".into()),
+            },
+            Expr::Binary(op @ ("==" | "!=" | "<" | "<=" | ">" | ">="), a, b) => value(a).and_then(|a| {
+                let b = value(b)?;
+                Ok(match *op {
+                    "==" => list("=", vec![a, b]),
+                    "!=" => list("not", vec![list("=", vec![a, b])]),
+                    op => list(op, vec![a, b]),
+                })
+            }),
+            Expr::Method(recv, method, args) => match (method.as_str(), args.as_slice()) {
+                ("contains", [x]) => value(recv).and_then(|r| Ok(list("member", vec![value(x)?, r]))),
+                ("containsAll", [x]) => value(recv).and_then(|r| Ok(list("subset?", vec![value(x)?, r]))),
+                ("containsAny", [x]) => {
+                    value(recv).and_then(|r| Ok(list("not", vec![list("disjoint?", vec![value(x)?, r])])))
+                }
+                _ => Err(format!("method `.{method}`")),
+            },
+            // A bare attribute must hold `true`, not merely be truthy.
+            Expr::Attr(..) => value(expr).map(|v| list("=", vec![v, Node::Bool(true)])),
+            other => value(other),
+        };
+        result.unwrap_or_else(|construct| {
+            self.untranslated.push(Untranslated { policy: self.policy, construct });
+            Node::Bool(!permits)
+        })
+    }
+
+    fn flatten(&mut self, expr: &Expr, op: &str, permits: bool) -> Vec<Node> {
+        match expr {
+            Expr::Binary(o, a, b) if *o == op => {
+                let mut terms = self.flatten(a, op, permits);
+                terms.extend(self.flatten(b, op, permits));
+                terms
+            }
+            other => vec![self.condition(other, permits)],
+        }
+    }
+}
+
+/// Translate a value expression, or name the construct that has no SPL form.
+fn value(expr: &Expr) -> Result<Node, String> {
+    match expr {
+        Expr::Bool(b) => Ok(Node::Bool(*b)),
+        Expr::Int(n) => Ok(Node::Int(*n)),
+        Expr::Str(s) => Ok(Node::Str(s.clone())),
+        Expr::Neg(inner) => match inner.as_ref() {
+            Expr::Int(n) => Ok(Node::Int(-n)),
+            _ => Err("arithmetic".into()),
+        },
+        Expr::Entity(_, id) => Ok(Node::Str(id.clone())),
+        Expr::Var(var) if var == "context" => Err("the whole `context` record".into()),
+        Expr::Var(var) => Ok(get(Node::Symbol("req".into()), var)),
+        Expr::Attr(base, field) => match base.as_ref() {
+            Expr::Var(var) if var == "context" => Ok(get(Node::Symbol("req".into()), field)),
+            Expr::Var(var) => Err(format!("entity attribute `{var}.{field}`")),
+            _ => Ok(get(value(base)?, field)),
+        },
+        Expr::Set(items) => Ok(list("tuple", items.iter().map(value).collect::<Result<_, _>>()?)),
+        Expr::Binary(op @ ("+" | "-" | "*"), ..) => Err(format!("arithmetic (`{op}`)")),
+        Expr::Unsupported(construct) => Err(construct.clone()),
+        Expr::Not(_) | Expr::Binary(..) | Expr::Method(..) => Err("boolean used as a value".into()),
+    }
+}
+
+fn list(op: &str, args: Vec<Node>) -> Node {
+    let mut items = vec![Node::Symbol(op.into())];
+    items.extend(args);
+    Node::List(items)
+}
+
+fn negate(node: Node) -> Node {
+    match node {
+        Node::Bool(b) => Node::Bool(!b),
+        other => list("not", vec![other]),
+    }
+}
+
+fn get(obj: Node, field: &str) -> Node {
+    list("get", vec![obj, Node::Str(field.to_string())])
+}
+
+/// Conjunction of `terms`, without the `and` when there is only one.
+fn all(terms: Vec<Node>) -> Node {
+    let mut terms = splice("and", terms);
+    if terms.contains(&Node::Bool(false)) {
+        return Node::Bool(false);
+    }
+    terms.retain(|t| *t != Node::Bool(true));
+    match terms.len() {
+        0 => Node::Bool(true),
+        1 => terms.remove(0),
+        _ => list("and", terms),
+    }
+}
+
+/// Disjunction of `terms`; `#f` when there are none, as Cedar denies by default.
+fn any(terms: Vec<Node>) -> Node {
+    let mut terms = splice("or", terms);
+    if terms.contains(&Node::Bool(true)) {
+        return Node::Bool(true);
+    }
+    terms.retain(|t| *t != Node::Bool(false));
+    match terms.len() {
+        0 => Node::Bool(false),
+        1 => terms.remove(0),
+        _ => list("or", terms),
+    }
+}
+
+/// `terms` with nested `(op ...)` lists replaced by their arguments.
+fn splice(op: &str, terms: Vec<Node>) -> Vec<Node> {
+    let mut out = Vec::with_capacity(terms.len());
+    for term in terms {
+        match term {
+            Node::List(items) if items.first() == Some(&Node::Symbol(op.into())) => out.extend(items.into_iter().skip(1)),
+            other => out.push(other),
+        }
+    }
+    out
+}
@@ -1,14 +1,35 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::sync::Arc;
+
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::attestation::AttestationVerifier;
+use crate::denylist::{DenyListProvider, DEFAULT_MAX_STALENESS_SECS};
+use crate::money::RateProvider;
+use crate::ops::{OpHandler, OpRegistry};
+use crate::spend::SpendTracker;
+use crate::time::{Clock, SystemClock};
 
 /// AST node for SPL S-expressions.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     Bool(bool),
+    /// Whole number held exactly; integer literals and JSON integers.
+    Int(i64),
     Number(f64),
     Str(String),
     Symbol(String),
     List(Vec<Node>),
+    /// String-keyed map, e.g. per-recipient limits `{"niece@example.com" 75}`.
+    Map(BTreeMap<String, Node>),
+    /// Currency amount in integer minor units (cents for USD), built by
+    /// `(money ...)`; see [`crate::money`].
+    Money { minor_units: i64, currency: String },
     Nil,
 }
 
@@ -17,8 +38,11 @@ impl fmt::Display for Node {
         match self {
             Node::Bool(true) => write!(f, "#t"),
             Node::Bool(false) => write!(f, "#f"),
+            Node::Int(i) => write!(f, "{i}"),
+            // Keep a decimal point so whole floats read back as floats.
+            Node::Number(n) if n.is_finite() && n.fract() == 0.0 => write!(f, "{n:.1}"),
             Node::Number(n) => write!(f, "{n}"),
-            Node::Str(s) => write!(f, "\"{s}\""),
+            Node::Str(s) => write!(f, "{}", crate::parser::escape_string(s)),
             Node::Symbol(s) => write!(f, "{s}"),
             Node::List(items) => {
                 write!(f, "(")?;
@@ -28,25 +52,74 @@ impl fmt::Display for Node {
                 }
                 write!(f, ")")
             }
+            Node::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 { write!(f, " ")?; }
+                    write!(f, "{} {v}", crate::parser::escape_string(k))?;
+                }
+                write!(f, "}}")
+            }
+            Node::Money { minor_units, currency } => {
+                write!(f, "(money \"{}\" \"{currency}\")", crate::money::format_amount(*minor_units, currency))
+            }
             Node::Nil => write!(f, "nil"),
         }
     }
 }
 
+impl From<bool> for Node {
+    fn from(b: bool) -> Self {
+        Node::Bool(b)
+    }
+}
+
+impl From<f64> for Node {
+    fn from(n: f64) -> Self {
+        Node::Number(n)
+    }
+}
+
+impl From<&str> for Node {
+    fn from(s: &str) -> Self {
+        Node::Str(s.to_string())
+    }
+}
+
+impl From<String> for Node {
+    fn from(s: String) -> Self {
+        Node::Str(s)
+    }
+}
+
+impl From<Vec<Node>> for Node {
+    fn from(items: Vec<Node>) -> Self {
+        Node::List(items)
+    }
+}
+
 impl Node {
     pub fn is_truthy(&self) -> bool {
         match self {
             Node::Bool(b) => *b,
             Node::Nil => false,
+            Node::Int(i) => *i != 0,
             Node::Number(n) => *n != 0.0,
             _ => true,
         }
     }
 
+    /// Numeric value, or 0 for non-numbers. Ints beyond 2^53 round.
     pub fn as_f64(&self) -> f64 {
+        self.as_number().unwrap_or(0.0)
+    }
+
+    /// Numeric value of an `Int` or `Number`.
+    pub fn as_number(&self) -> Option<f64> {
         match self {
-            Node::Number(n) => *n,
-            _ => 0.0,
+            Node::Int(i) => Some(*i as f64),
+            Node::Number(n) => Some(*n),
+            _ => None,
         }
     }
 
@@ -56,6 +129,160 @@ impl Node {
             _ => None,
         }
     }
+
+    /// Convert JSON to a node: objects become maps (or money, see
+    /// [`Node::to_json_value`]), arrays lists, null nil.
+    pub fn from_json_value(value: &Value) -> Node {
+        match value {
+            Value::Null => Node::Nil,
+            Value::Bool(b) => Node::Bool(*b),
+            Value::Number(n) => n.as_i64().map_or_else(|| Node::Number(n.as_f64().unwrap_or(f64::NAN)), Node::Int),
+            Value::String(s) => Node::Str(s.clone()),
+            Value::Array(items) => Node::List(items.iter().map(Node::from_json_value).collect()),
+            Value::Object(entries) => {
+                money_or_map(entries.iter().map(|(k, v)| (k.clone(), Node::from_json_value(v))).collect())
+            }
+        }
+    }
+
+    /// Convert to JSON. Symbols become strings; non-finite numbers become
+    /// null; money becomes `{"currency": "USD", "minor_units": 1999}`.
+    pub fn to_json_value(&self) -> Value {
+        match self {
+            Node::Nil => Value::Null,
+            Node::Bool(b) => Value::Bool(*b),
+            Node::Int(i) => Value::from(*i),
+            Node::Number(n) => serde_json::Number::from_f64(*n).map_or(Value::Null, Value::Number),
+            Node::Str(s) | Node::Symbol(s) => Value::String(s.clone()),
+            Node::List(items) => Value::Array(items.iter().map(Node::to_json_value).collect()),
+            Node::Map(entries) => Value::Object(entries.iter().map(|(k, v)| (k.clone(), v.to_json_value())).collect()),
+            Node::Money { minor_units, currency } => {
+                serde_json::json!({ "currency": currency, "minor_units": minor_units })
+            }
+        }
+    }
+}
+
+/// An object of exactly `currency` (an ISO 4217 code) and integral
+/// `minor_units` is money; any other object stays a map.
+fn money_or_map(entries: BTreeMap<String, Node>) -> Node {
+    if entries.len() == 2 {
+        if let (Some(Node::Str(currency)), Some(Node::Int(units))) = (entries.get("currency"), entries.get("minor_units")) {
+            if crate::money::is_currency_code(currency) {
+                return Node::Money { minor_units: *units, currency: currency.clone() };
+            }
+        }
+    }
+    Node::Map(entries)
+}
+
+/// Convert a JSON object (a request or vars document) to a field map.
+pub fn map_from_json(value: &Value) -> Result<HashMap<String, Node>, SplError> {
+    let entries = value
+        .as_object()
+        .ok_or_else(|| SplError("expected a JSON object".into()))?;
+    Ok(entries.iter().map(|(k, v)| (k.clone(), Node::from_json_value(v))).collect())
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Node::Nil => serializer.serialize_unit(),
+            Node::Bool(b) => serializer.serialize_bool(*b),
+            Node::Int(i) => serializer.serialize_i64(*i),
+            Node::Number(n) if n.is_finite() => serializer.serialize_f64(*n),
+            Node::Number(_) => serializer.serialize_unit(),
+            Node::Str(s) | Node::Symbol(s) => serializer.serialize_str(s),
+            Node::List(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Node::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Node::Money { minor_units, currency } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("currency", currency)?;
+                map.serialize_entry("minor_units", minor_units)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Node, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Node, E> {
+        Ok(Node::Nil)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Node, E> {
+        Ok(Node::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        Node::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Node, E> {
+        Ok(Node::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Node, E> {
+        Ok(Node::Int(n))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Node, E> {
+        Ok(i64::try_from(n).map_or(Node::Number(n as f64), Node::Int))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Node, E> {
+        Ok(Node::Number(n))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Node, E> {
+        Ok(Node::Str(s.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Node, E> {
+        Ok(Node::Str(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::List(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = BTreeMap::new();
+        while let Some((k, v)) = map.next_entry::<String, Node>()? {
+            entries.insert(k, v);
+        }
+        Ok(money_or_map(entries))
+    }
 }
 
 /// SPL evaluation error.
@@ -72,43 +299,311 @@ impl std::error::Error for SplError {}
 
 pub type SplResult = Result<Node, SplError>;
 
-type BoolCallback = Box<dyn Fn() -> bool>;
-type MerkleCallback = Box<dyn Fn(&[Node]) -> bool>;
-type VrfCallback = Box<dyn Fn(&str, f64) -> bool>;
-type CountCallback = Box<dyn Fn(&str, &str) -> i64>;
+type BoolCallback = dyn Fn() -> bool + Send + Sync;
+type MerkleCallback = Arc<dyn Fn(&[Node]) -> bool + Send + Sync>;
+type VrfCallback = dyn Fn(&str, f64) -> bool + Send + Sync;
+type CountCallback = Arc<dyn Fn(&CounterKey, &str) -> i64 + Send + Sync>;
+type SpentCallback = Arc<dyn Fn(&str) -> f64 + Send + Sync>;
+
+/// Key of a usage counter: the parts of a `usage-count` tuple, rendered as
+/// strings, joined by `|` with `|` and `\` backslash-escaped. A one-part key
+/// is its part, so `per-day-count` keys are plain action names.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CounterKey(String);
+
+impl CounterKey {
+    pub fn new<S: AsRef<str>>(parts: &[S]) -> Self {
+        let escaped: Vec<String> = parts.iter().map(|p| p.as_ref().replace('\\', "\\\\").replace('|', "\\|")).collect();
+        CounterKey(escaped.join("|"))
+    }
+
+    /// The key `per-day-count` uses for `action`.
+    pub fn action(action: &str) -> Self {
+        Self::new(&[action])
+    }
+
+    /// The canonical form, for hosts storing counters by string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for CounterKey {
+    fn from(action: &str) -> Self {
+        Self::action(action)
+    }
+}
+
+impl fmt::Display for CounterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A host callback, or none. Keeping "not configured" apart from a callback
+/// that answers `false` lets strict mode reject policies that lean on a
+/// predicate the host never wired up.
+#[derive(Default)]
+pub enum CallbackState<F: ?Sized> {
+    #[default]
+    Unconfigured,
+    Configured(Arc<F>),
+}
+
+impl<F: ?Sized> CallbackState<F> {
+    pub fn is_configured(&self) -> bool {
+        matches!(self, CallbackState::Configured(_))
+    }
+
+    /// The callback, if configured.
+    pub fn get(&self) -> Option<&F> {
+        match self {
+            CallbackState::Configured(f) => Some(f),
+            CallbackState::Unconfigured => None,
+        }
+    }
+}
+
+impl<F: ?Sized> Clone for CallbackState<F> {
+    fn clone(&self) -> Self {
+        match self {
+            CallbackState::Configured(f) => CallbackState::Configured(f.clone()),
+            CallbackState::Unconfigured => CallbackState::Unconfigured,
+        }
+    }
+}
 
 /// Crypto callback functions provided by the host.
+///
+/// An unconfigured predicate evaluates to `#f`, or is an error when
+/// [`Env::strict`] is set.
+#[derive(Clone, Default)]
 pub struct CryptoCallbacks {
-    pub dpop_ok: BoolCallback,
-    pub merkle_ok: MerkleCallback,
-    pub vrf_ok: VrfCallback,
+    pub dpop_ok: CallbackState<BoolCallback>,
+    /// Override for `merkle_ok?`. When unset, the evaluator checks
+    /// `(merkle_ok? leaf proof_var root)` itself with
+    /// [`crate::crypto::verify_merkle_proof`]; any other form denies.
+    pub merkle_ok: Option<MerkleCallback>,
+    pub vrf_ok: CallbackState<VrfCallback>,
     /// thresh_ok — Threshold co-signature verification.
     /// Expected protocol: k-of-n co-signatures where the verifier checks each
     /// signature against its corresponding public key and confirms count >= threshold.
     /// Not implemented in v0.1 — remains an interface stub.
-    pub thresh_ok: BoolCallback,
+    pub thresh_ok: CallbackState<BoolCallback>,
 }
 
-impl Default for CryptoCallbacks {
+/// Gas cost per class of operation.
+///
+/// Every evaluated node costs `node`; operators then add cost for the work
+/// they do beyond visiting their arguments, so a `merkle_ok?` over a large
+/// proof or a `member` over a long list is charged accordingly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasSchedule {
+    pub node: i64,
+    /// Crypto predicates (`dpop_ok?`, `merkle_ok?`, `vrf_ok?`, `thresh_ok?`)
+    /// and `=hash`.
+    pub crypto: i64,
+    /// Other host lookups (counters, spend, deny lists).
+    pub host_call: i64,
+    /// Per list element scanned or built by list operators.
+    pub list_item: i64,
+    /// Per KiB of string data compared, rounded up.
+    pub string_per_kb: i64,
+}
+
+impl Default for GasSchedule {
     fn default() -> Self {
         Self {
-            dpop_ok: Box::new(|| false),
-            merkle_ok: Box::new(|_| false),
-            vrf_ok: Box::new(|_, _| false),
-            thresh_ok: Box::new(|| false),
+            node: 1,
+            crypto: 10,
+            host_call: 5,
+            list_item: 1,
+            string_per_kb: 16,
         }
     }
 }
 
+/// Size limits on policies and the values they evaluate over. The
+/// defaults suit untrusted policies and requests; constrained embedders can
+/// tighten them, and deployments with large policies can raise them.
+///
+/// Raising `max_depth` past 256 also lets the parser recurse that deep, so
+/// leave stack headroom for it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Deepest expression nesting evaluated or compiled.
+    pub max_depth: usize,
+    /// Largest policy source accepted, in bytes.
+    pub max_policy_bytes: usize,
+    /// Most elements in a list, whether written in the policy, read from the
+    /// request or vars, or built during evaluation.
+    pub max_list_len: usize,
+    /// Longest string, in bytes, from the same sources.
+    pub max_string_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_policy_bytes: 65536,
+            max_list_len: 4096,
+            max_string_len: 65536,
+        }
+    }
+}
+
+impl Limits {
+    /// Check `node`, and every value nested in it, against the list and
+    /// string limits.
+    pub fn check_value(&self, node: &Node) -> Result<(), SplError> {
+        match node {
+            Node::Str(s) | Node::Symbol(s) => self.check_string(s),
+            Node::List(items) => {
+                self.check_list(items.len())?;
+                items.iter().try_for_each(|item| self.check_value(item))
+            }
+            Node::Map(entries) => {
+                self.check_list(entries.len())?;
+                entries.iter().try_for_each(|(key, value)| {
+                    self.check_string(key)?;
+                    self.check_value(value)
+                })
+            }
+            Node::Money { currency, .. } => self.check_string(currency),
+            Node::Bool(_) | Node::Int(_) | Node::Number(_) | Node::Nil => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_string(&self, s: &str) -> Result<(), SplError> {
+        if s.len() > self.max_string_len {
+            return Err(SplError(format!("string exceeds maximum length of {} bytes", self.max_string_len)));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_list(&self, len: usize) -> Result<(), SplError> {
+        if len > self.max_list_len {
+            return Err(SplError(format!("list exceeds maximum length of {}", self.max_list_len)));
+        }
+        Ok(())
+    }
+}
+
+/// How `=`, `member`, `subset`, the set operators, `action-in`, deny-list
+/// lookups, and token scopes compare strings.
+///
+/// The same text can be encoded more than one way: `é` is one code point
+/// in NFC and two (`e` plus a combining accent) in NFD. Under `Exact`, a
+/// request spelling an allowlisted name or address in another form is not a
+/// member, and a denylist check can be slipped the same way. The other
+/// modes compare Unicode normalized forms; turn one on when request strings
+/// come from sources that do not normalize.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringMatch {
+    /// Compare code points as given.
+    #[default]
+    Exact,
+    /// Compare NFC forms. Requires the `unicode` feature.
+    Nfc,
+    /// Compare NFC forms after Unicode lowercasing, so `Alice@Example.com`
+    /// matches `alice@example.com`. Lowercasing is not full case folding:
+    /// `ß` does not match `SS`. Requires the `unicode` feature.
+    NfcLowercase,
+}
+
+impl StringMatch {
+    /// Fails for the normalizing modes in builds without the `unicode`
+    /// feature, rather than quietly comparing exactly.
+    pub fn check(self) -> Result<(), SplError> {
+        match self {
+            StringMatch::Exact => Ok(()),
+            _ if cfg!(feature = "unicode") => Ok(()),
+            mode => Err(SplError(format!("string match mode {mode:?} requires the unicode feature"))),
+        }
+    }
+
+    /// `s` in the form this mode compares. Fails as [`StringMatch::check`] does.
+    pub fn normalize(self, s: &str) -> Result<Cow<'_, str>, SplError> {
+        self.check()?;
+        #[cfg(feature = "unicode")]
+        {
+            use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+            Ok(match self {
+                StringMatch::Exact => Cow::Borrowed(s),
+                StringMatch::Nfc if is_nfc(s) => Cow::Borrowed(s),
+                StringMatch::Nfc => Cow::Owned(s.nfc().collect()),
+                StringMatch::NfcLowercase => Cow::Owned(s.nfd().collect::<String>().to_lowercase().nfc().collect()),
+            })
+        }
+        #[cfg(not(feature = "unicode"))]
+        {
+            Ok(Cow::Borrowed(s))
+        }
+    }
+
+    /// Whether `a` and `b` match under this mode. Evaluation runs
+    /// [`StringMatch::check`] first; a mode that fails it compares exactly.
+    pub fn eq(self, a: &str, b: &str) -> bool {
+        a == b
+            || (self != StringMatch::Exact
+                && matches!((self.normalize(a), self.normalize(b)), (Ok(a), Ok(b)) if a == b))
+    }
+}
+
 /// Evaluation environment.
+///
+/// `Env` is `Send + Sync` and its callbacks and providers are shared
+/// handles, so one environment can serve concurrent requests through
+/// [`Env::clone_for_request`].
+#[derive(Clone)]
 pub struct Env {
     pub req: HashMap<String, Node>,
     pub vars: HashMap<String, Node>,
-    pub per_day_count: CountCallback,
+    /// Earlier uses counted under a key in a period (usually a day),
+    /// backing `usage-count`, `per-day-count`, and `per_day` limits.
+    pub usage_count: CountCallback,
+    /// Source of the `now` symbol. Callers control `vars`, so a `now` var
+    /// overrides the clock only outside strict mode; in strict mode it is
+    /// an error.
+    pub clock: Arc<dyn Clock + Send + Sync>,
+    /// Amount already spent against a category or counterparty key,
+    /// backing `spent-for` / `remaining-for`.
+    pub spent_for: SpentCallback,
+    /// Running spend per action, backing `cumulative-spend`; unset means
+    /// the operator errors. Shared so verifiers can record allowed spend.
+    pub spend_tracker: Option<Arc<dyn SpendTracker>>,
     pub crypto: CryptoCallbacks,
+    /// Trusted device attestation roots, backing `attested?`. Unset, the
+    /// operator is `#f`, or an error in strict mode.
+    pub attestation: Option<Arc<AttestationVerifier>>,
+    /// Ed25519 key (hex) that proved possession of the token for this
+    /// request. `attested?` holds only for a statement about this key, so
+    /// it is `#f` while unset. Token verification sets it to `pop_key`.
+    pub pop_key: Option<String>,
+    /// Exchange rates backing `convert`. Without a rate for a pair the
+    /// operator errors.
+    pub rates: Option<Arc<dyn RateProvider>>,
+    /// Source of deny lists for `denylist-absent?`; unset means the operator errors.
+    pub denylists: Option<Arc<dyn DenyListProvider>>,
+    /// Oldest deny-list snapshot (in seconds) a decision may rely on.
+    pub denylist_max_staleness_secs: u64,
     pub max_gas: i64,
+    pub gas: GasSchedule,
+    pub limits: Limits,
     pub sealed: bool,
+    /// Unresolved symbols, and crypto predicates without a configured
+    /// callback, are errors rather than `nil` and `#f`.
     pub strict: bool,
+    /// Operators this enforcement point permits, by canonical name; any
+    /// other operator is an "operator not permitted" error. `None` permits all.
+    pub allowed_ops: Option<HashSet<String>>,
+    /// Operator names this environment evaluates, including host operators
+    /// added with [`Env::register_op`].
+    pub ops: OpRegistry,
+    /// How string equality and membership compare; exact unless opted in.
+    pub string_match: StringMatch,
 }
 
 impl Default for Env {
@@ -116,11 +611,59 @@ impl Default for Env {
         Self {
             req: HashMap::new(),
             vars: HashMap::new(),
-            per_day_count: Box::new(|_, _| 0),
+            usage_count: Arc::new(|_, _| 0),
+            clock: Arc::new(SystemClock),
+            spent_for: Arc::new(|_| 0.0),
+            spend_tracker: None,
             crypto: CryptoCallbacks::default(),
+            attestation: None,
+            pop_key: None,
+            rates: None,
+            denylists: None,
+            denylist_max_staleness_secs: DEFAULT_MAX_STALENESS_SECS,
             max_gas: 10_000,
+            gas: GasSchedule::default(),
+            limits: Limits::default(),
             sealed: false,
             strict: false,
+            allowed_ops: None,
+            ops: OpRegistry::default(),
+            string_match: StringMatch::Exact,
         }
     }
 }
+
+impl Env {
+    /// This environment with `req` as the request. Callbacks and providers
+    /// are shared with `self`; vars and settings are copied.
+    pub fn clone_for_request(&self, req: HashMap<String, Node>) -> Env {
+        Env {
+            req,
+            vars: self.vars.clone(),
+            usage_count: self.usage_count.clone(),
+            clock: self.clock.clone(),
+            spent_for: self.spent_for.clone(),
+            spend_tracker: self.spend_tracker.clone(),
+            crypto: self.crypto.clone(),
+            attestation: self.attestation.clone(),
+            pop_key: self.pop_key.clone(),
+            rates: self.rates.clone(),
+            denylists: self.denylists.clone(),
+            denylist_max_staleness_secs: self.denylist_max_staleness_secs,
+            max_gas: self.max_gas,
+            gas: self.gas.clone(),
+            limits: self.limits.clone(),
+            sealed: self.sealed,
+            strict: self.strict,
+            allowed_ops: self.allowed_ops.clone(),
+            ops: self.ops.clone(),
+            string_match: self.string_match,
+        }
+    }
+
+    /// Register a host operator such as `(geo-within? point region)`.
+    /// Overriding a built-in is an error once `strict` is set.
+    pub fn register_op(&mut self, name: &str, handler: OpHandler) -> Result<(), SplError> {
+        self.ops.register(name, handler, self.strict)
+    }
+}
@@ -0,0 +1,252 @@
+//! Per-key usage totals backing `per-day-count`, `spent-for`, and
+//! `cumulative-spend`, with retention.
+//!
+//! A [`UsageLedger`] keeps one row per key and day. Left alone, those rows
+//! grow forever, so [`UsageLedger::compact`] rolls days older than a
+//! retention window into one row per key and month: daily counts for old
+//! days are gone, but monthly and lifetime totals stay exact for audits.
+//! [`UsageLedger::records`] and [`UsageLedger::from_records`] let hosts
+//! persist the ledger. For offline analysis, `UsageLedger::export_csv`
+//! (feature `csv`) and `UsageLedger::export_parquet` (feature `parquet`)
+//! write every row with the columns `key`, `period`, `count`, and `amount`.
+//!
+//! As a [`SpendTracker`], the ledger answers windows that only partly cover
+//! a compacted month with an error rather than a guess.
+
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(any(feature = "csv", feature = "parquet"))]
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::spend::SpendTracker;
+use crate::time::{format_rfc3339, is_date as is_day, parse_rfc3339, period_bounds};
+use crate::types::SplError;
+
+/// Totals for one key over one period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Action, counterparty, or budget category.
+    pub key: String,
+    /// `YYYY-MM-DD` for a day, `YYYY-MM` for a compacted month.
+    pub period: String,
+    pub count: i64,
+    pub amount: f64,
+}
+
+/// What a [`UsageLedger::compact`] call rolled up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompactionReport {
+    pub days_compacted: usize,
+    pub months_written: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Totals {
+    count: i64,
+    amount: f64,
+}
+
+/// In-memory usage ledger keyed by key and period.
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    rows: Mutex<BTreeMap<(String, String), Totals>>,
+}
+
+fn check_day(day: &str) -> Result<(), SplError> {
+    if is_day(day) {
+        Ok(())
+    } else {
+        Err(SplError(format!("expected a YYYY-MM-DD day, got {day:?}")))
+    }
+}
+
+impl UsageLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild a ledger from [`UsageLedger::records`] output.
+    pub fn from_records(records: impl IntoIterator<Item = UsageRecord>) -> Result<Self, SplError> {
+        let ledger = Self::new();
+        {
+            let mut rows = ledger.lock();
+            for r in records {
+                if !is_day(&r.period) && !is_month(&r.period) {
+                    return Err(SplError(format!("invalid usage period: {:?}", r.period)));
+                }
+                let totals = rows.entry((r.key, r.period)).or_default();
+                totals.count += r.count;
+                totals.amount += r.amount;
+            }
+        }
+        Ok(ledger)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(String, String), Totals>> {
+        self.rows.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count one use of `key` on `day`, adding `amount` to its spend.
+    pub fn record(&self, key: &str, day: &str, amount: f64) -> Result<(), SplError> {
+        check_day(day)?;
+        let mut rows = self.lock();
+        let totals = rows.entry((key.to_string(), day.to_string())).or_default();
+        totals.count += 1;
+        totals.amount += amount;
+        Ok(())
+    }
+
+    /// Uses of `key` on `day`, for `per-day-count`. Zero once the day has
+    /// been compacted.
+    pub fn count(&self, key: &str, day: &str) -> i64 {
+        self.lock().get(&(key.to_string(), day.to_string())).map_or(0, |t| t.count)
+    }
+
+    /// Total spend against `key` over every period, for `spent-for`.
+    pub fn spent(&self, key: &str) -> f64 {
+        self.lock().range(range_for(key)).map(|(_, t)| t.amount).sum()
+    }
+
+    /// Count and spend for `key` in `month` (`YYYY-MM`), whether its days
+    /// are still daily rows, already compacted, or both.
+    pub fn month_total(&self, key: &str, month: &str) -> (i64, f64) {
+        self.lock()
+            .range(range_for(key))
+            .filter(|((_, period), _)| period == month || period.strip_prefix(month).is_some_and(|r| r.starts_with('-')))
+            .fold((0, 0.0), |(c, a), (_, t)| (c + t.count, a + t.amount))
+    }
+
+    /// Roll every day more than `retain_days` before `today` into its
+    /// month's row. Days inside the window keep their daily counts.
+    pub fn compact(&self, today: &str, retain_days: u32) -> Result<CompactionReport, SplError> {
+        check_day(today)?;
+        let today = parse_rfc3339(&format!("{today}T00:00:00Z"))?;
+        let cutoff = format_rfc3339(today - i64::from(retain_days) * 86_400)[..10].to_string();
+
+        let mut rows = self.lock();
+        let old: Vec<(String, String)> =
+            rows.keys().filter(|(_, period)| is_day(period) && period.as_str() < cutoff.as_str()).cloned().collect();
+        let mut months = BTreeSet::new();
+        for (key, day) in &old {
+            let totals = rows.remove(&(key.clone(), day.clone())).unwrap_or_default();
+            let month = day[..7].to_string();
+            months.insert((key.clone(), month.clone()));
+            let rolled = rows.entry((key.clone(), month)).or_default();
+            rolled.count += totals.count;
+            rolled.amount += totals.amount;
+        }
+        Ok(CompactionReport { days_compacted: old.len(), months_written: months.len() })
+    }
+
+    /// Every row, ordered by key then period.
+    pub fn records(&self) -> Vec<UsageRecord> {
+        self.lock()
+            .iter()
+            .map(|((key, period), t)| UsageRecord { key: key.clone(), period: period.clone(), count: t.count, amount: t.amount })
+            .collect()
+    }
+
+    /// Write every row as CSV with a `key,period,count,amount` header.
+    #[cfg(feature = "csv")]
+    pub fn export_csv(&self, mut out: impl Write) -> Result<(), SplError> {
+        let io = |e: std::io::Error| SplError(format!("usage export failed: {e}"));
+        writeln!(out, "key,period,count,amount").map_err(io)?;
+        for r in self.records() {
+            writeln!(out, "{},{},{},{}", csv_field(&r.key), r.period, r.count, r.amount).map_err(io)?;
+        }
+        Ok(())
+    }
+
+    /// Write every row as a Parquet file of one row group: `key` and
+    /// `period` as UTF-8 strings, `count` as INT64, `amount` as DOUBLE.
+    #[cfg(feature = "parquet")]
+    pub fn export_parquet(&self, out: impl Write + Send) -> Result<(), SplError> {
+        use std::sync::Arc;
+
+        use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let err = |e: parquet::errors::ParquetError| SplError(format!("usage export failed: {e}"));
+        let records = self.records();
+        let keys: Vec<ByteArray> = records.iter().map(|r| r.key.as_str().into()).collect();
+        let periods: Vec<ByteArray> = records.iter().map(|r| r.period.as_str().into()).collect();
+        let counts: Vec<i64> = records.iter().map(|r| r.count).collect();
+        let amounts: Vec<f64> = records.iter().map(|r| r.amount).collect();
+
+        let schema = parse_message_type(PARQUET_SCHEMA).map_err(err)?;
+        let mut writer = SerializedFileWriter::new(out, Arc::new(schema), Default::default()).map_err(err)?;
+        let mut group = writer.next_row_group().map_err(err)?;
+        let mut index = 0;
+        while let Some(mut column) = group.next_column().map_err(err)? {
+            match index {
+                0 => column.typed::<ByteArrayType>().write_batch(&keys, None, None),
+                1 => column.typed::<ByteArrayType>().write_batch(&periods, None, None),
+                2 => column.typed::<Int64Type>().write_batch(&counts, None, None),
+                _ => column.typed::<DoubleType>().write_batch(&amounts, None, None),
+            }
+            .map_err(err)?;
+            column.close().map_err(err)?;
+            index += 1;
+        }
+        group.close().map_err(err)?;
+        writer.close().map_err(err)?;
+        Ok(())
+    }
+}
+
+impl SpendTracker for UsageLedger {
+    fn spent_between(&self, action: &str, from: &str, to: &str) -> Result<f64, SplError> {
+        let mut total = 0.0;
+        for ((_, period), t) in self.lock().range(range_for(action)) {
+            if is_day(period) {
+                if (from..=to).contains(&period.as_str()) {
+                    total += t.amount;
+                }
+                continue;
+            }
+            let (first, last) = period_bounds(&format!("{period}-01"), "month")?;
+            if first.as_str() > to || last.as_str() < from {
+                continue;
+            }
+            if first.as_str() < from || last.as_str() > to {
+                return Err(SplError(format!("spend for {action:?} in {period} was compacted into a monthly total")));
+            }
+            total += t.amount;
+        }
+        Ok(total)
+    }
+
+    fn record(&self, action: &str, day: &str, amount: f64) -> Result<(), SplError> {
+        UsageLedger::record(self, action, day, amount)
+    }
+}
+
+fn range_for(key: &str) -> std::ops::RangeInclusive<(String, String)> {
+    (key.to_string(), String::new())..=(key.to_string(), "\u{10FFFF}".to_string())
+}
+
+fn is_month(s: &str) -> bool {
+    s.len() == 7 && is_day(&format!("{s}-01"))
+}
+
+/// Schema of [`UsageLedger::export_parquet`] files.
+#[cfg(feature = "parquet")]
+const PARQUET_SCHEMA: &str = "message usage {
+    REQUIRED BYTE_ARRAY key (UTF8);
+    REQUIRED BYTE_ARRAY period (UTF8);
+    REQUIRED INT64 count;
+    REQUIRED DOUBLE amount;
+}";
+
+/// Quote a CSV field when it holds a delimiter, quote, or line break.
+#[cfg(feature = "csv")]
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
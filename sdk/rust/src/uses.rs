@@ -0,0 +1,74 @@
+//! Use-limited tokens, enforced by consuming a hash chain.
+//!
+//! An issuer limits a token to `n` uses by minting it with the commitment
+//! of an `n`-use [`HashChain`] in `hash_chain_commitment`, and handing the
+//! chain's preimages to the agent. Use `k` (counting from 0) presents
+//! [`HashChain::for_use`]`(k)`, the preimage that hashes to the commitment
+//! in `k + 1` SHA-256 steps.
+//!
+//! A verifier with a [`UsageStore`] ([`crate::profile::Verifier::with_usage_store`]
+//! or [`crate::token::verify_token_use`]) looks up how many uses of the
+//! commitment it has recorded, rejects the token unless the presented
+//! preimage is the one for that use, and records the use when the policy
+//! allows. Once the seed, use `n - 1`, has been presented, the next use
+//! needs a SHA-256 preimage of the seed, so the chain is exhausted.
+//! Verifiers without a store ignore the commitment.
+//!
+//! [`HashChain`]: crate::crypto::HashChain
+//! [`HashChain::for_use`]: crate::crypto::HashChain::for_use
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::crypto::{ct_eq_hex, sha256};
+use crate::types::SplError;
+
+/// Counts the uses recorded against each hash-chain commitment. Verifiers
+/// sharing a store share the count.
+pub trait UsageStore: Send + Sync {
+    /// Uses of the chain with `commitment` recorded so far.
+    fn uses(&self, commitment: &str) -> Result<u64, SplError>;
+
+    /// Record use `index` if exactly `index` uses are recorded. Returns
+    /// `false` when another verification recorded it first.
+    fn record_use(&self, commitment: &str, index: u64) -> Result<bool, SplError>;
+}
+
+/// In-memory [`UsageStore`] for tests and single-process verifiers.
+#[derive(Default)]
+pub struct InMemoryUsageStore {
+    uses: Mutex<HashMap<String, u64>>,
+}
+
+impl InMemoryUsageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UsageStore for InMemoryUsageStore {
+    fn uses(&self, commitment: &str) -> Result<u64, SplError> {
+        let uses = self.uses.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(uses.get(commitment).copied().unwrap_or(0))
+    }
+
+    fn record_use(&self, commitment: &str, index: u64) -> Result<bool, SplError> {
+        let mut uses = self.uses.lock().unwrap_or_else(|e| e.into_inner());
+        let recorded = uses.entry(commitment.to_string()).or_insert(0);
+        if *recorded != index {
+            return Ok(false);
+        }
+        *recorded += 1;
+        Ok(true)
+    }
+}
+
+/// Whether `preimage_hex` is the preimage for use `index` of the chain
+/// committed to by `commitment`.
+pub fn check_use(commitment: &str, preimage_hex: &str, index: u64) -> bool {
+    let Ok(mut current) = hex::decode(preimage_hex) else { return false };
+    for _ in 0..=index {
+        current = sha256(&current);
+    }
+    ct_eq_hex(&hex::encode(current), commitment)
+}
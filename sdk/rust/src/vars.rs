@@ -0,0 +1,80 @@
+//! Reserved policy symbols the verifier supplies itself.
+
+use std::collections::HashMap;
+
+use crate::time::format_rfc3339;
+use crate::token::Token;
+use crate::types::Node;
+
+/// A symbol the verifier injects into `vars` so callers cannot forget it.
+/// Values the caller passes explicitly take precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardVar {
+    /// Verifier time as an RFC 3339 UTC timestamp. The verifier resolves it
+    /// through [`Env::clock`](crate::types::Env::clock) rather than `vars`.
+    Now,
+    /// Verifier date as `YYYY-MM-DD` (UTC).
+    Day,
+    /// The agent's PoP public key, when the token is PoP-bound.
+    ActorPub,
+}
+
+impl StandardVar {
+    pub const ALL: [StandardVar; 3] = [StandardVar::Now, StandardVar::Day, StandardVar::ActorPub];
+
+    /// Symbol name as written in policies.
+    pub fn name(self) -> &'static str {
+        match self {
+            StandardVar::Now => "now",
+            StandardVar::Day => "day",
+            StandardVar::ActorPub => "actor_pub",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StandardVar> {
+        StandardVar::ALL.into_iter().find(|v| v.name() == name)
+    }
+
+    /// Value for this var given the token and verifier time, if it has one.
+    pub fn value(self, token: &Token, now: i64) -> Option<Node> {
+        match self {
+            StandardVar::Now => Some(Node::Str(format_rfc3339(now))),
+            StandardVar::Day => Some(Node::Str(format_rfc3339(now)[..10].to_string())),
+            StandardVar::ActorPub => token.pop_key.clone().map(Node::Str),
+        }
+    }
+}
+
+/// Fill in every enabled standard var the caller did not provide.
+pub fn inject_standard_vars(vars: &mut HashMap<String, Node>, token: &Token, now: i64, disabled: &[StandardVar]) {
+    for var in StandardVar::ALL {
+        if disabled.contains(&var) || vars.contains_key(var.name()) {
+            continue;
+        }
+        if let Some(value) = var.value(token, now) {
+            vars.insert(var.name().to_string(), value);
+        }
+    }
+}
+
+/// Disabled standard vars that `ast` references as bare symbols.
+pub fn disabled_references(ast: &Node, disabled: &[StandardVar]) -> Vec<StandardVar> {
+    let mut found = Vec::new();
+    collect(ast, disabled, &mut found);
+    found
+}
+
+fn collect(node: &Node, disabled: &[StandardVar], found: &mut Vec<StandardVar>) {
+    match node {
+        Node::Symbol(name) => {
+            if let Some(var) = StandardVar::from_name(name) {
+                if disabled.contains(&var) && !found.contains(&var) {
+                    found.push(var);
+                }
+            }
+        }
+        Node::List(items) => items.iter().for_each(|n| collect(n, disabled, found)),
+        Node::Map(entries) => entries.values().for_each(|n| collect(n, disabled, found)),
+        _ => {}
+    }
+}
@@ -0,0 +1,251 @@
+//! Deterministic test vectors for the signing and presentation flows, and
+//! for the Ed25519, Merkle, and hash-chain primitives beneath them.
+//!
+//! Every value is derived from fixed seeds, so any SDK implementing the
+//! envelope format must reproduce the output byte for byte. The published
+//! copies live in `examples/crypto/`; regenerate them with
+//! `agent-safe gen-vectors --out examples/crypto`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::crypto::{sha256, sha256_hex, HashChain, MerkleProofStep, MerkleTree};
+use crate::token::{
+    create_presentation_signature, keypair_from_seed, mint, signing_payload,
+    verify_token_with_pop, Challenge, MintOptions, Presentation, Token,
+};
+use crate::types::{Node, SplError};
+
+pub const ISSUER_SEED_HEX: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+pub const AGENT_SEED_HEX: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+const POLICY: &str = r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50))"#;
+const EXPIRES: &str = "2026-04-01T00:00:00Z";
+const MERKLE_ROOT: &str = "0e9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a";
+
+const ED25519_SEED_LABEL: &str = "agent-safe-test-vector-seed-ed25519";
+const ED25519_MESSAGE: &str = r#"(and (= (get req "action") "read") (<= (get req "amount") 100))"#;
+const MERKLE_LEAVES: [&str; 4] = ["alice@example.com", "bob@example.com", "carol@example.com", "dave@example.com"];
+const HASH_CHAIN_SEED_LABEL: &str = "agent-safe-hash-chain-seed";
+const HASH_CHAIN_LENGTH: usize = 5;
+
+/// A named verification case and its expected outcome.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifyCase {
+    pub name: String,
+    pub request: BTreeMap<String, VectorValue>,
+    pub presentation_nonce: Option<String>,
+    pub tamper: Option<String>,
+    pub expected_allow: bool,
+    pub expected_error: Option<String>,
+}
+
+/// Request values used by the vectors: strings or numbers only, so the
+/// JSON stays trivially portable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VectorValue {
+    Number(f64),
+    Str(String),
+}
+
+/// Full vector set for keypair derivation, minting, and PoP presentation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SigningVectors {
+    pub issuer_seed_hex: String,
+    pub issuer_public_key_hex: String,
+    pub agent_seed_hex: String,
+    pub agent_public_key_hex: String,
+    pub policy: String,
+    pub signing_payload_hex: String,
+    pub token: Token,
+    pub challenge: Challenge,
+    pub presentation_payload_hex: String,
+    pub presentation_signature_hex: String,
+    pub verify_cases: Vec<VerifyCase>,
+}
+
+fn case(
+    name: &str,
+    amount: f64,
+    nonce: Option<&str>,
+    tamper: Option<&str>,
+) -> VerifyCase {
+    let mut request = BTreeMap::new();
+    request.insert("action".into(), VectorValue::Str("payments.create".into()));
+    request.insert("amount".into(), VectorValue::Number(amount));
+    VerifyCase {
+        name: name.into(),
+        request,
+        presentation_nonce: nonce.map(String::from),
+        tamper: tamper.map(String::from),
+        expected_allow: false,
+        expected_error: None,
+    }
+}
+
+/// Regenerate the signing vectors from the fixed seeds.
+pub fn signing_vectors() -> Result<SigningVectors, SplError> {
+    let (issuer_pub, issuer_priv) = keypair_from_seed(ISSUER_SEED_HEX)?;
+    let (agent_pub, agent_priv) = keypair_from_seed(AGENT_SEED_HEX)?;
+
+    let opts = MintOptions {
+        merkle_root: Some(MERKLE_ROOT.into()),
+        expires: Some(EXPIRES.into()),
+        pop_key: Some(agent_pub.clone()),
+        ..MintOptions::default()
+    };
+    let payload = signing_payload(POLICY, &opts.merkle_root, &opts.hash_chain_commitment, opts.sealed, &opts.expires);
+    let token = mint(POLICY, &issuer_priv, opts)?;
+
+    let challenge = Challenge { nonce: "vector-nonce-0001".into(), timestamp: 1_775_000_000, evidence: None };
+    let presentation_payload = crate::token::presentation_payload(&token, &challenge);
+    let presentation_signature = create_presentation_signature(&token, &agent_priv, &challenge)?;
+
+    let mut verify_cases = vec![
+        case("allow_within_limit", 50.0, Some("vector-nonce-0001"), None),
+        case("deny_over_limit", 75.0, Some("vector-nonce-0001"), None),
+        case("missing_presentation", 50.0, None, None),
+        case("wrong_nonce", 50.0, Some("vector-nonce-0002"), None),
+        case("tampered_expires", 50.0, Some("vector-nonce-0001"), Some("expires=2099-01-01T00:00:00Z")),
+    ];
+    for c in &mut verify_cases {
+        let (allow, error) = run_case(&token, &agent_priv, &challenge, c)?;
+        c.expected_allow = allow;
+        c.expected_error = error;
+    }
+
+    Ok(SigningVectors {
+        issuer_seed_hex: ISSUER_SEED_HEX.into(),
+        issuer_public_key_hex: issuer_pub,
+        agent_seed_hex: AGENT_SEED_HEX.into(),
+        agent_public_key_hex: agent_pub,
+        policy: POLICY.into(),
+        signing_payload_hex: hex::encode(payload),
+        token,
+        challenge,
+        presentation_payload_hex: hex::encode(presentation_payload),
+        presentation_signature_hex: presentation_signature,
+        verify_cases,
+    })
+}
+
+/// Execute one verify case against this implementation.
+/// Returns (allow, error) as `verify_token_with_pop` reports them.
+pub fn run_case(
+    token: &Token,
+    agent_priv_hex: &str,
+    challenge: &Challenge,
+    case: &VerifyCase,
+) -> Result<(bool, Option<String>), SplError> {
+    let mut token = token.clone();
+    if let Some(tamper) = &case.tamper {
+        if let Some(expires) = tamper.strip_prefix("expires=") {
+            token.expires = Some(expires.into());
+        }
+    }
+    let presentation = match &case.presentation_nonce {
+        Some(nonce) => {
+            // The agent always answers the published challenge; a different
+            // nonce here models the verifier expecting another challenge.
+            let signature = create_presentation_signature(&token, agent_priv_hex, challenge)?;
+            Some(Presentation {
+                signature,
+                challenge: Challenge { nonce: nonce.clone(), timestamp: challenge.timestamp, evidence: None },
+            })
+        }
+        None => None,
+    };
+    let req: HashMap<String, Node> = case
+        .request
+        .iter()
+        .map(|(k, v)| {
+            let node = match v {
+                VectorValue::Number(n) => Node::Number(*n),
+                VectorValue::Str(s) => Node::Str(s.clone()),
+            };
+            (k.clone(), node)
+        })
+        .collect();
+    let result = verify_token_with_pop(&token, req, HashMap::new(), presentation.as_ref(), None);
+    Ok((result.allow, result.error))
+}
+
+/// Ed25519 vectors: a key derived from a fixed label, a signed policy, and
+/// a one-byte tampering of it that must fail to verify.
+pub fn ed25519_vectors() -> Result<Value, SplError> {
+    let seed_hex = sha256_hex(ED25519_SEED_LABEL.as_bytes());
+    let (public_key, private_key) = keypair_from_seed(&seed_hex)?;
+    let signature = crate::signature::SignatureScheme::Ed25519.sign(&private_key, ED25519_MESSAGE.as_bytes())?;
+    let mut tampered = ED25519_MESSAGE.to_string();
+    tampered.replace_range(5..6, "o");
+    Ok(json!({
+        "description": "Ed25519 test vectors for SPL policy signing",
+        "private_key_hex": private_key,
+        "public_key_hex": public_key,
+        "message": ED25519_MESSAGE,
+        "signature_hex": signature,
+        "tampered_message": tampered,
+        "cases": [
+            {"name": "valid_signature", "message": ED25519_MESSAGE, "expected": true},
+            {"name": "tampered_message", "message": tampered, "expected": false},
+        ],
+    }))
+}
+
+fn proof_json(proof: &[MerkleProofStep]) -> Value {
+    proof.iter().map(|s| json!({"hash": s.hash, "position": s.position})).collect()
+}
+
+/// Merkle vectors: a four-leaf tree, two valid proofs, and a proof replayed
+/// for a leaf outside the tree.
+pub fn merkle_vectors() -> Result<Value, SplError> {
+    let tree = MerkleTree::build(&MERKLE_LEAVES)?;
+    let leaf_hashes: Vec<String> = MERKLE_LEAVES.iter().map(|l| sha256_hex(l.as_bytes())).collect();
+    let proof = |index: usize| tree.proof_for_index(index).expect("leaf index in range");
+    let outsider = "eve@example.com";
+    Ok(json!({
+        "description": "SHA-256 Merkle tree test vectors (4 leaves)",
+        "leaves": MERKLE_LEAVES,
+        "leaf_hashes": leaf_hashes,
+        "root": tree.root_hex(),
+        "cases": [
+            {"name": "valid_proof_leaf_0", "leaf": MERKLE_LEAVES[0], "leaf_hash": leaf_hashes[0], "proof": proof_json(&proof(0)), "expected": true},
+            {"name": "valid_proof_leaf_2", "leaf": MERKLE_LEAVES[2], "leaf_hash": leaf_hashes[2], "proof": proof_json(&proof(2)), "expected": true},
+            {"name": "invalid_proof_wrong_leaf", "leaf": outsider, "leaf_hash": sha256_hex(outsider.as_bytes()), "proof": proof_json(&proof(0)), "expected": false},
+        ],
+    }))
+}
+
+/// Hash-chain vectors: a five-step chain from a fixed label, receipts at
+/// both ends and in the middle, and a preimage not on the chain.
+pub fn hash_chain_vectors() -> Result<Value, SplError> {
+    let chain = HashChain::generate(&sha256_hex(HASH_CHAIN_SEED_LABEL.as_bytes()), HASH_CHAIN_LENGTH)?;
+    let links: Vec<String> = (0..=HASH_CHAIN_LENGTH).filter_map(|i| chain.receipt(i)).map(|r| r.preimage).collect();
+    Ok(json!({
+        "description": "SHA-256 hash chain test vectors (5-step chain)",
+        "seed_hex": links[0],
+        "chain": links,
+        "commitment": chain.commitment(),
+        "chain_length": HASH_CHAIN_LENGTH,
+        "cases": [
+            {"name": "valid_receipt_step_3", "preimage": links[3], "index": 3, "expected": true, "note": "Hash preimage (5-3)=2 times to reach commitment"},
+            {"name": "valid_receipt_step_0", "preimage": links[0], "index": 0, "expected": true, "note": "Hash seed 5 times to reach commitment"},
+            {"name": "valid_receipt_step_5", "preimage": links[5], "index": 5, "expected": true, "note": "Preimage IS the commitment (0 hashes)"},
+            {"name": "invalid_receipt_wrong_preimage", "preimage": hex::encode(sha256(b"wrong")), "index": 3, "expected": false},
+        ],
+    }))
+}
+
+/// Every published vector set, keyed by its file name in `examples/crypto/`.
+pub fn all_vectors() -> Result<Vec<(&'static str, Value)>, SplError> {
+    let signing = serde_json::to_value(signing_vectors()?).map_err(|e| SplError(e.to_string()))?;
+    Ok(vec![
+        ("ed25519_vectors.json", ed25519_vectors()?),
+        ("merkle_vectors.json", merkle_vectors()?),
+        ("hashchain_vectors.json", hash_chain_vectors()?),
+        ("signing_vectors.json", signing),
+    ])
+}
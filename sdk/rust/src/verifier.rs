@@ -1,21 +1,38 @@
-use crate::evaluator::eval_policy;
+use crate::denylist::DenyListVersion;
+use crate::evaluator::eval_policy_detailed;
+use crate::obligations::Obligation;
+use crate::spend::record_allowed;
 use crate::types::{Env, Node, SplError};
 
 /// Verify result.
 pub struct VerifyResult {
     pub allow: bool,
-    pub obligations: Vec<String>,
+    /// Obligations the caller must discharge; empty on deny.
+    pub obligations: Vec<Obligation>,
+    /// Gas consumed during evaluation, for capacity planning.
+    pub gas_used: i64,
+    /// Deny-list versions the decision relied on.
+    pub denylists: Vec<DenyListVersion>,
+    /// Machine-readable code from the policy's `deny-with`; `None` on allow.
+    pub reason_code: Option<String>,
 }
 
 /// Evaluate an SPL policy AST against a request within an environment.
+/// An allow is recorded with the environment's spend tracker, if any.
 pub fn verify(ast: &Node, env: &Env) -> Result<VerifyResult, SplError> {
     if env.sealed {
         return Err(SplError("token is sealed and cannot be attenuated".to_string()));
     }
-    let result = eval_policy(ast, env)?;
-    let allow = result.is_truthy();
+    let outcome = eval_policy_detailed(ast, env)?;
+    let allow = outcome.value.is_truthy();
+    if let (true, Some(tracker)) = (allow, &env.spend_tracker) {
+        record_allowed(tracker.as_ref(), &env.req)?;
+    }
     Ok(VerifyResult {
         allow,
-        obligations: Vec::new(),
+        obligations: if allow { outcome.obligations } else { Vec::new() },
+        gas_used: outcome.gas_used,
+        denylists: outcome.denylists,
+        reason_code: if allow { None } else { outcome.reason_code },
     })
 }
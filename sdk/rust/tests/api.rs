@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use agent_safe_spl::api::{decide_json, DecisionErrorCode, DecisionResponse, ProtocolErrorCode};
+use agent_safe_spl::issuers::{TrustedIssuers, TrustedKey};
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, MintOptions, VerifyErrorCode};
+use serde_json::json;
+
+fn decide(verifier: &Verifier, input: serde_json::Value) -> DecisionResponse {
+    serde_json::from_str(&decide_json(verifier, &input.to_string())).unwrap()
+}
+
+#[test]
+fn test_decide_json() {
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let verifier = Verifier::default().with_trusted_issuers(Arc::new(TrustedIssuers::new().with_key(TrustedKey::new(&issuer_pub))));
+    let policy = r#"(policy "read" (and (= (get req "action") "read") (obligate "log-access" 60)))
+        (policy "delete" (deny-with "deletes-disabled"))"#;
+    let token = mint(policy, &issuer_priv, MintOptions::default()).unwrap();
+
+    let allowed = decide(&verifier, json!({ "token": token, "request": { "action": "read" } }));
+    assert!(allowed.allow);
+    assert_eq!(allowed.obligations[0].kind, "log-access");
+    assert!(allowed.gas_used > 0);
+    assert_eq!(allowed.token_id.as_deref(), Some(token.id().as_ref()));
+    assert_eq!(allowed.issuer_key.as_deref(), Some(issuer_pub.as_str()));
+    assert_eq!(allowed.error, None);
+
+    let denied = decide(&verifier, json!({ "token": token, "request": { "action": "delete" }, "vars": {}, "options": {} }));
+    assert!(!denied.allow);
+    assert_eq!(denied.reason_code.as_deref(), Some("deletes-disabled"));
+    assert_eq!(denied.error, None);
+
+    let mut forged = serde_json::to_value(&token).unwrap();
+    forged["policy"] = json!("#t");
+    let rejected = decide(&verifier, json!({ "token": forged, "request": { "action": "read" } }));
+    let error = rejected.error.unwrap();
+    assert_eq!(error.code, DecisionErrorCode::Verify(VerifyErrorCode::InvalidSignature));
+    assert_eq!(rejected.gas_used, 0);
+
+    // Every field is present, and codes are bare strings.
+    let raw: serde_json::Value = serde_json::from_str(&decide_json(&verifier, &json!({ "token": forged }).to_string())).unwrap();
+    assert_eq!(raw["error"]["code"], "invalid_signature");
+    assert_eq!(raw["reason_code"], serde_json::Value::Null);
+    assert_eq!(raw["obligations"], json!([]));
+
+    for input in ["not json", "{}", r#"{"token": 1}"#] {
+        let response: DecisionResponse = serde_json::from_str(&decide_json(&verifier, input)).unwrap();
+        assert!(!response.allow);
+        assert_eq!(response.error.unwrap().code, DecisionErrorCode::Protocol(ProtocolErrorCode::InvalidRequest));
+    }
+}
+
+#[test]
+fn test_decide_json_refuses_self_signed_tokens_and_caller_time() {
+    let (issuer_pub, _) = generate_keypair();
+    let (self_pub, self_priv) = generate_keypair();
+    let self_signed = mint("#t", &self_priv, MintOptions::default()).unwrap();
+
+    // Without a trust set any caller could sign `#t` for itself.
+    let open = decide(&Verifier::default(), json!({ "token": self_signed }));
+    assert!(!open.allow);
+    assert_eq!(open.error.unwrap().code, DecisionErrorCode::Verify(VerifyErrorCode::UntrustedIssuer));
+    assert_eq!(open.issuer_key.as_deref(), Some(self_pub.as_str()));
+
+    let verifier = Verifier::default().with_trusted_issuers(Arc::new(TrustedIssuers::new().with_key(TrustedKey::new(&issuer_pub))));
+    let untrusted = decide(&verifier, json!({ "token": self_signed }));
+    assert_eq!(untrusted.error.unwrap().code, DecisionErrorCode::Verify(VerifyErrorCode::UntrustedIssuer));
+
+    let clock = decide(&verifier, json!({ "token": self_signed, "vars": { "now": "2000-01-01T00:00:00Z" } }));
+    assert_eq!(clock.error.unwrap().code, DecisionErrorCode::Protocol(ProtocolErrorCode::InvalidRequest));
+}
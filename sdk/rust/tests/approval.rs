@@ -0,0 +1,72 @@
+#![cfg(feature = "approval")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::approval::{request_hash, Approval, ApprovalDecision, COMPACT_PREFIX};
+use agent_safe_spl::replay::InMemoryReplayCache;
+use agent_safe_spl::token::generate_keypair;
+use agent_safe_spl::types::Node;
+
+const NOW: i64 = 1_772_800_000;
+
+fn req(amount: i64) -> HashMap<String, Node> {
+    let mut req = HashMap::new();
+    req.insert("action".to_string(), Node::Str("payments.create".into()));
+    req.insert("amount".to_string(), Node::Int(amount));
+    req
+}
+
+#[test]
+fn test_approval_round_trip() {
+    let (owner_pub, owner_priv) = generate_keypair();
+    let approval = Approval::sign(&req(500), ApprovalDecision::Approve, NOW + 300, &owner_priv).unwrap();
+    assert_eq!(approval.owner_key, owner_pub);
+    assert_eq!(approval.request_hash, request_hash(&req(500)));
+    assert_eq!(approval.verify(&req(500), &owner_pub, NOW, None).unwrap(), ApprovalDecision::Approve);
+
+    let denied = Approval::sign(&req(500), ApprovalDecision::Deny, NOW + 300, &owner_priv).unwrap();
+    assert_eq!(denied.verify(&req(500), &owner_pub, NOW, None).unwrap(), ApprovalDecision::Deny);
+
+    let err = |a: &Approval, r: &HashMap<String, Node>, key: &str, now: i64| a.verify(r, key, now, None).unwrap_err().0;
+    assert_eq!(err(&approval, &req(501), &owner_pub, NOW), "approval is for a different request");
+    assert_eq!(err(&approval, &req(500), &owner_pub, NOW + 301), "approval has expired");
+    let (other_pub, _) = generate_keypair();
+    assert_eq!(err(&approval, &req(500), &other_pub, NOW), "approval is not from the expected owner");
+
+    let mut flipped = denied.clone();
+    flipped.decision = ApprovalDecision::Approve;
+    assert_eq!(err(&flipped, &req(500), &owner_pub, NOW), "invalid approval signature");
+
+    let cache = InMemoryReplayCache::default();
+    assert!(approval.verify(&req(500), &owner_pub, NOW, Some(&cache)).is_ok());
+    assert_eq!(approval.verify(&req(500), &owner_pub, NOW, Some(&cache)).unwrap_err().0, "approval already used");
+
+    let json = serde_json::to_value(&approval).unwrap();
+    assert_eq!(json["decision"], "approve");
+    assert_eq!(serde_json::from_value::<Approval>(json).unwrap(), approval);
+}
+
+#[test]
+fn test_compact_encoding() {
+    let (owner_pub, owner_priv) = generate_keypair();
+    let approval = Approval::sign(&req(500), ApprovalDecision::Approve, NOW + 300, &owner_priv).unwrap();
+    let compact = approval.to_compact().unwrap();
+    assert!(compact.starts_with(COMPACT_PREFIX));
+    assert_eq!(compact.len(), COMPACT_PREFIX.len() + 220);
+    // QR alphanumeric mode: digits, uppercase letters, and " $%*+-./:".
+    assert!(compact.chars().all(|c| c.is_ascii_digit() || c.is_ascii_uppercase() || " $%*+-./:".contains(c)));
+
+    let decoded = Approval::from_compact(&compact).unwrap();
+    assert_eq!(decoded, approval);
+    assert!(decoded.verify(&req(500), &owner_pub, NOW, None).is_ok());
+
+    assert!(Approval::from_compact(&compact[COMPACT_PREFIX.len()..]).is_err());
+    assert!(Approval::from_compact(&compact[..compact.len() - 8]).is_err());
+    assert!(Approval::from_compact(&compact.to_lowercase()).is_err());
+
+    // Flipping the decision byte breaks the signature.
+    let mut flipped = approval.clone();
+    flipped.decision = ApprovalDecision::Deny;
+    let flipped = Approval::from_compact(&flipped.to_compact().unwrap()).unwrap();
+    assert!(flipped.verify(&req(500), &owner_pub, NOW, None).is_err());
+}
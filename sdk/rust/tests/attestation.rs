@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use agent_safe_spl::attestation::{Attestation, AttestationVerifier};
+use agent_safe_spl::time::FixedClock;
+use agent_safe_spl::issuers::{TrustedIssuers, TrustedKey};
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{create_presentation_signature, generate_keypair, mint, Challenge, MintOptions, Presentation};
+use agent_safe_spl::types::{Env, Node};
+use agent_safe_spl::{parse, verify};
+
+const NOW: i64 = 1_772_800_000;
+
+/// A verifier that accepts each issued nonce once.
+fn single_use(root: &str, issued: &[&str]) -> AttestationVerifier {
+    let open: Mutex<HashSet<String>> = Mutex::new(issued.iter().map(|n| n.to_string()).collect());
+    AttestationVerifier::new([root.to_string()], move |nonce| open.lock().unwrap().remove(nonce))
+}
+
+fn eval(
+    src: &str,
+    attestation: Option<&Attestation>,
+    verifier: Option<AttestationVerifier>,
+    strict: bool,
+    pop_key: Option<&str>,
+) -> Result<bool, String> {
+    let mut req = HashMap::from([("device_attested".to_string(), Node::Bool(true))]);
+    if let Some(a) = attestation {
+        req.insert("attestation".to_string(), a.to_node());
+    }
+    let env = Env {
+        req,
+        attestation: verifier.map(Arc::new),
+        pop_key: pop_key.map(str::to_string),
+        clock: Arc::new(FixedClock(NOW)),
+        strict,
+        ..Env::default()
+    };
+    verify(&parse(src).unwrap(), &env).map(|r| r.allow).map_err(|e| e.0)
+}
+
+#[test]
+fn attested_checks_root_signature_expiry_nonce_and_claim() {
+    let (root_pub, root_priv) = generate_keypair();
+    let (device_pub, _) = generate_keypair();
+    let statement = Attestation::sign("n1", &device_pub, &["secure-enclave", "os-patched"], NOW + 60, &root_priv).unwrap();
+    assert_eq!(statement.claims, ["os-patched", "secure-enclave"]);
+    assert_eq!(Attestation::from_node(&statement.to_node()).unwrap(), statement);
+
+    // Both claims are checked against one verification, so the single-use nonce suffices.
+    let both = r#"(and (attested? "secure-enclave") (attested? "os-patched"))"#;
+    assert!(eval(both, Some(&statement), Some(single_use(&root_pub, &["n1"])), false, Some(&device_pub)).unwrap());
+    assert!(!eval(r#"(attested? "rooted")"#, Some(&statement), Some(single_use(&root_pub, &["n1"])), false, Some(&device_pub)).unwrap());
+    assert!(!eval(r#"(attested? "secure-enclave")"#, Some(&statement), Some(single_use(&root_pub, &["n2"])), false, Some(&device_pub)).unwrap());
+
+    // Untrusted roots, tampering, expiry, and a missing statement all deny.
+    let (other_pub, other_priv) = generate_keypair();
+    let forged = Attestation::sign("n1", &device_pub, &["secure-enclave"], NOW + 60, &other_priv).unwrap();
+    let mut tampered = statement.clone();
+    tampered.claims.push("unlocked".into());
+    let expired = Attestation::sign("n1", &device_pub, &["secure-enclave"], NOW - 1, &root_priv).unwrap();
+    for bad in [&forged, &tampered, &expired] {
+        assert!(!eval(r#"(attested? "secure-enclave")"#, Some(bad), Some(single_use(&root_pub, &["n1"])), false, Some(&device_pub)).unwrap());
+    }
+    assert!(!eval(r#"(attested? "secure-enclave")"#, None, Some(single_use(&root_pub, &["n1"])), false, Some(&device_pub)).unwrap());
+    let verifier = single_use(&root_pub, &["n1"]);
+    assert_eq!(verifier.verify(&forged, &device_pub, NOW).unwrap_err().0, "attestation is not from a trusted root");
+    assert_eq!(verifier.verify(&tampered, &device_pub, NOW).unwrap_err().0, "invalid attestation signature");
+    assert_eq!(verifier.verify(&expired, &device_pub, NOW).unwrap_err().0, "attestation has expired");
+    assert!(AttestationVerifier::new([other_pub], |_| true).verify(&forged, &device_pub, NOW).is_ok());
+
+    // The statement must attest the key that proved possession of the token.
+    let (agent_pub, _) = generate_keypair();
+    assert!(!eval(r#"(attested? "secure-enclave")"#, Some(&statement), Some(single_use(&root_pub, &["n1"])), false, Some(&agent_pub)).unwrap());
+    assert!(!eval(r#"(attested? "secure-enclave")"#, Some(&statement), Some(single_use(&root_pub, &["n1"])), false, None).unwrap());
+    assert_eq!(
+        single_use(&root_pub, &["n1"]).verify(&statement, &agent_pub, NOW).unwrap_err().0,
+        "attestation is for a different device key"
+    );
+
+    // The caller-set flag is no substitute, and strict mode wants a configured verifier.
+    assert!(!eval(r#"(attested? "secure-enclave")"#, Some(&statement), None, false, Some(&device_pub)).unwrap());
+    let err = eval(r#"(attested? "secure-enclave")"#, Some(&statement), None, true, Some(&device_pub)).unwrap_err();
+    assert_eq!(err, "attested? requires an attestation verifier");
+}
+
+#[test]
+fn attested_in_a_token_policy_binds_the_presenting_key() {
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let (root_pub, root_priv) = generate_keypair();
+    let (device_pub, device_priv) = generate_keypair();
+    let opts = MintOptions { pop_key: Some(device_pub.clone()), ..MintOptions::default() };
+    let token = mint(r#"(attested? "secure-enclave")"#, &issuer_priv, opts).unwrap();
+    let challenge = Challenge { nonce: "p1".into(), timestamp: NOW, evidence: None };
+    let signature = create_presentation_signature(&token, &device_priv, &challenge).unwrap();
+    let presentation = Presentation { signature, challenge };
+
+    let decide = |statement: &Attestation| {
+        let verifier = Verifier::default()
+            .with_clock(FixedClock(NOW))
+            .with_trusted_issuers(Arc::new(TrustedIssuers::new().with_key(TrustedKey::new(&issuer_pub))))
+            .with_attestation(Arc::new(single_use(&root_pub, &["n1"])));
+        let req = HashMap::from([("attestation".to_string(), statement.to_node())]);
+        verifier.verify(&token, req, HashMap::new(), Some(&presentation))
+    };
+    let statement = Attestation::sign("n1", &device_pub, &["secure-enclave"], NOW + 60, &root_priv).unwrap();
+    let result = decide(&statement);
+    assert!(result.allow, "{:?}", result.error);
+
+    // A statement about some other device does not vouch for this agent.
+    let (other_pub, _) = generate_keypair();
+    let other = Attestation::sign("n1", &other_pub, &["secure-enclave"], NOW + 60, &root_priv).unwrap();
+    assert!(!decide(&other).allow);
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "dalek")]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use agent_safe_spl::backend::{install_backend, CryptoBackend, DalekBackend};
+use agent_safe_spl::token::{generate_keypair, mint, verify_token_untrusted, MintOptions};
+use agent_safe_spl::types::SplError;
+
+/// Delegates to dalek while counting calls, standing in for an embedder's
+/// own Ed25519 implementation.
+struct CountingBackend {
+    signs: AtomicUsize,
+    verifies: AtomicUsize,
+}
+
+impl CryptoBackend for CountingBackend {
+    fn ed25519_public_key(&self, seed: &[u8; 32]) -> Result<[u8; 32], SplError> {
+        DalekBackend.ed25519_public_key(seed)
+    }
+
+    fn ed25519_sign(&self, seed: &[u8; 32], message: &[u8]) -> Result<[u8; 64], SplError> {
+        self.signs.fetch_add(1, Ordering::SeqCst);
+        DalekBackend.ed25519_sign(seed, message)
+    }
+
+    fn ed25519_verify(&self, public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+        self.verifies.fetch_add(1, Ordering::SeqCst);
+        DalekBackend.ed25519_verify(public_key, message, signature)
+    }
+}
+
+static BACKEND: CountingBackend = CountingBackend { signs: AtomicUsize::new(0), verifies: AtomicUsize::new(0) };
+
+#[test]
+fn test_installed_backend_is_used() {
+    install_backend(&BACKEND).unwrap();
+    assert!(install_backend(&BACKEND).is_err());
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint("#t", &issuer_priv, MintOptions::default()).unwrap();
+    assert!(verify_token_untrusted(&token, HashMap::new(), HashMap::new()).allow);
+    assert_eq!(BACKEND.signs.load(Ordering::SeqCst), 1);
+    assert_eq!(BACKEND.verifies.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, verify_token_untrusted, verify_tokens_batch, MintOptions, VerifyErrorCode};
+use agent_safe_spl::types::Node;
+
+fn req(amount: f64) -> HashMap<String, Node> {
+    HashMap::from([("amount".to_string(), Node::Number(amount))])
+}
+
+#[test]
+fn test_batch_matches_individual_verification() {
+    let (_, issuer) = generate_keypair();
+    let policy = r#"(<= (get req "amount") 100)"#;
+    let mut items = Vec::new();
+    // Enough tokens to span several signature batches.
+    for i in 0..150 {
+        let mut token = mint(policy, &issuer, MintOptions::default()).unwrap();
+        match i % 5 {
+            // Forged: the policy no longer matches the signature.
+            0 => token.policy = "true".into(),
+            // Malformed signature hex.
+            1 => token.signature.truncate(10),
+            _ => {}
+        }
+        items.push((token, req(if i % 7 == 0 { 500.0 } else { 50.0 })));
+    }
+
+    let results = verify_tokens_batch(&items);
+    assert_eq!(results.len(), items.len());
+    for ((token, req), result) in items.iter().zip(&results) {
+        let expected = verify_token_untrusted(token, req.clone(), HashMap::new());
+        assert_eq!(result.token_id, expected.token_id);
+        assert_eq!(result.allow, expected.allow);
+        assert_eq!(result.code, expected.code);
+    }
+    assert_eq!(results[0].code, Some(VerifyErrorCode::InvalidSignature));
+    assert_eq!(results[1].code, Some(VerifyErrorCode::InvalidSignature));
+    assert!(results[2].allow);
+    assert!(!results[7].allow && results[7].code.is_none());
+}
+
+#[test]
+fn test_verifier_batch_shares_vars() {
+    let (_, issuer) = generate_keypair();
+    let token = mint(r#"(<= (get req "amount") limit)"#, &issuer, MintOptions::default()).unwrap();
+    let items = vec![(token.clone(), req(50.0)), (token, req(150.0))];
+    let vars = HashMap::from([("limit".to_string(), Node::Number(100.0))]);
+
+    let results = Verifier::default().verify_batch(&items, vars);
+    assert!(results[0].allow);
+    assert!(!results[1].allow);
+    assert!(verify_tokens_batch(&[]).is_empty());
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::builder::{EnvBuilder, RequestBuilder};
+use agent_safe_spl::parser::parse;
+use agent_safe_spl::types::{GasSchedule, Node};
+use agent_safe_spl::verify;
+
+#[test]
+fn test_builders_produce_evaluable_env() {
+    let req = RequestBuilder::new()
+        .action("payments.create")
+        .amount(50.0)
+        .recipient("niece@example.com")
+        .day("2025-09-29")
+        .field("purpose", "gift")
+        .field("attested", true)
+        .build()
+        .unwrap();
+    assert_eq!(req.get("amount"), Some(&Node::Number(50.0)));
+    assert_eq!(req.get("purpose"), Some(&Node::Str("gift".into())));
+
+    let env = EnvBuilder::new()
+        .request(req)
+        .var("allowed", vec![Node::from("niece@example.com")])
+        .limits("recipient_limits", [("niece@example.com", 75.0)])
+        .spent_for(|key| if key == "niece@example.com" { 20.0 } else { 0.0 })
+        .usage_count(|_, _| 1)
+        .dpop_ok(|| true)
+        .strict(true)
+        .build()
+        .unwrap();
+
+    let ast = parse(
+        r#"(and (member (get req "recipient") allowed)
+                (<= (get req "amount") (remaining-for (get req "recipient") recipient_limits))
+                (< (per-day-count "payments.create" (get req "day")) 3)
+                (dpop_ok?))"#,
+    )
+    .unwrap();
+    assert!(verify(&ast, &env).unwrap().allow);
+}
+
+#[test]
+fn test_request_builder_validates() {
+    assert!(RequestBuilder::new().amount(f64::NAN).build().is_err());
+    assert!(RequestBuilder::new().field("fee", f64::INFINITY).build().is_err());
+    assert!(RequestBuilder::new().action("").build().is_err());
+    assert!(RequestBuilder::new().field("action", 3.0).build().is_err());
+    assert!(RequestBuilder::new().day("29/09/2025").build().is_err());
+    assert_eq!(RequestBuilder::new().build().unwrap(), HashMap::new());
+}
+
+#[test]
+fn test_env_builder_validates_gas() {
+    assert!(EnvBuilder::new().max_gas(0).build().is_err());
+    let refunding = GasSchedule { node: -1, ..GasSchedule::default() };
+    assert!(EnvBuilder::new().gas_schedule(refunding).build().is_err());
+    assert_eq!(EnvBuilder::new().max_gas(500).build().unwrap().max_gas, 500);
+}
+
+#[test]
+fn test_env_shared_across_threads() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<agent_safe_spl::types::Env>();
+
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let counted = lookups.clone();
+    let env = Arc::new(
+        EnvBuilder::new()
+            .var("cap", 100.0)
+            .spent_for(move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                40.0
+            })
+            .build()
+            .unwrap(),
+    );
+    let ast = Arc::new(parse(r#"(and (<= (get req "amount") cap) (< (spent-for "gifts") 50))"#).unwrap());
+
+    let handles: Vec<_> = [20.0, 150.0, 99.0, 101.0]
+        .into_iter()
+        .map(|amount| {
+            let (env, ast) = (env.clone(), ast.clone());
+            thread::spawn(move || {
+                let req = RequestBuilder::new().amount(amount).build().unwrap();
+                verify(&ast, &env.clone_for_request(req)).unwrap().allow
+            })
+        })
+        .collect();
+    let allowed: Vec<bool> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(allowed, [true, false, true, false]);
+    // Every clone called the one shared callback; the original request is untouched.
+    assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    assert!(env.req.is_empty());
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "cache")]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use agent_safe_spl::builder::RequestBuilder;
+use agent_safe_spl::cache::{CacheEvent, CacheStats, PolicyCache};
+use agent_safe_spl::parser::parse_all;
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, MintOptions, VerifyErrorCode};
+use agent_safe_spl::types::Limits;
+
+#[test]
+fn test_policy_cache_evicts_least_recently_used() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    let cache = PolicyCache::new(2).with_observer(move |e| seen.lock().unwrap().push(e));
+
+    let a = cache.parse("(= 1 1)").unwrap();
+    assert_eq!(*a, parse_all("(= 1 1)").unwrap());
+    cache.parse("(= 2 2)").unwrap();
+    // Touch the first policy so the second is the oldest; whitespace around
+    // a policy does not change its hash.
+    assert!(Arc::ptr_eq(&a, &cache.parse("  (= 1 1)\n").unwrap()));
+    cache.parse("(= 3 3)").unwrap();
+    assert!(Arc::ptr_eq(&a, &cache.parse("(= 1 1)").unwrap()));
+    cache.parse("(= 2 2)").unwrap();
+
+    use CacheEvent::{Evict, Hit, Miss};
+    assert_eq!(*events.lock().unwrap(), [Miss, Miss, Hit, Miss, Evict, Hit, Miss, Evict]);
+    assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 4, evictions: 2, entries: 2 });
+
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.stats().hits, 2);
+}
+
+#[test]
+fn test_policy_cache_skips_failures_and_large_policies() {
+    let cache = PolicyCache::new(8).with_max_policy_bytes(16);
+    assert!(cache.parse("(and").is_err());
+    assert!(cache.parse("(and").is_err());
+    cache.parse(r#"(= (get req "action") "read")"#).unwrap();
+    cache.parse(r#"(= (get req "action") "read")"#).unwrap();
+    assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 4, evictions: 0, entries: 0 });
+
+    let disabled = PolicyCache::new(0);
+    disabled.parse("#t").unwrap();
+    disabled.parse("#t").unwrap();
+    assert_eq!(disabled.stats(), CacheStats { hits: 0, misses: 2, evictions: 0, entries: 0 });
+}
+
+#[test]
+fn test_policy_cache_keys_by_limits() {
+    let cache = PolicyCache::new(8);
+    let policy = r#"(= (get req "action") "a-long-action-name")"#;
+    cache.parse(policy).unwrap();
+    // A parse under the default limits must not satisfy a stricter verifier.
+    let strict = Limits { max_string_len: 8, ..Limits::default() };
+    assert!(cache.parse_with_limits(policy, &strict).is_err());
+    cache.parse(policy).unwrap();
+    assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2, evictions: 0, entries: 1 });
+}
+
+#[test]
+fn test_verifier_reuses_cached_parses() {
+    let (_, issuer) = generate_keypair();
+    let policy = r#"(<= (get req "amount") 100)"#;
+    let token = mint(policy, &issuer, MintOptions::default()).unwrap();
+    let other = mint(policy, &issuer, MintOptions { expires: Some("2099-01-01T00:00:00Z".into()), ..Default::default() }).unwrap();
+    let broken = mint("(<= (get req", &issuer, MintOptions::default()).unwrap();
+
+    let cache = Arc::new(PolicyCache::new(16));
+    let verifier = Verifier::default().with_policy_cache(cache.clone());
+    let req = |amount| RequestBuilder::new().amount(amount).build().unwrap();
+
+    assert!(verifier.verify(&token, req(50.0), HashMap::new(), None).allow);
+    assert!(!verifier.verify(&token, req(150.0), HashMap::new(), None).allow);
+    assert!(verifier.verify(&other, req(50.0), HashMap::new(), None).allow);
+    assert!(verifier.verify_any(&[broken.clone(), token], req(50.0), HashMap::new()).allow);
+    assert_eq!(cache.stats(), CacheStats { hits: 3, misses: 2, evictions: 0, entries: 1 });
+
+    // Parse failures are still reported, and not cached.
+    let result = verifier.verify(&broken, req(50.0), HashMap::new(), None);
+    assert_eq!(result.code, Some(VerifyErrorCode::PolicyParse));
+    assert_eq!(cache.stats().misses, 3);
+}
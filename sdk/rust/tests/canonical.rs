@@ -0,0 +1,60 @@
+use agent_safe_spl::canonical::{canonical_json, format_number};
+use serde_json::json;
+
+// Examples from RFC 8785 sections 3.2.2 and 3.2.3, and appendix B.
+
+#[test]
+fn test_rfc8785_example() {
+    let input = r#"{
+        "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+        "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+        "literals": [null, true, false]
+    }"#;
+    let value: serde_json::Value = serde_json::from_str(input).unwrap();
+    assert_eq!(
+        canonical_json(&value),
+        r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+    );
+}
+
+#[test]
+fn test_members_sort_by_utf16() {
+    let value = json!({
+        "\u{20ac}": "Euro Sign",
+        "\r": "Carriage Return",
+        "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+        "1": "One",
+        "\u{1f600}": "Emoji: Grinning Face",
+        "\u{80}": "Control",
+        "\u{f6}": "Latin Small Letter O With Diaeresis",
+    });
+    assert_eq!(
+        canonical_json(&value),
+        "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+         \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+         \"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+    );
+}
+
+#[test]
+fn test_number_formatting() {
+    let cases: [(f64, &str); 14] = [
+        (0.0, "0"),
+        (-0.0, "0"),
+        (5e-324, "5e-324"),
+        (-5e-324, "-5e-324"),
+        (f64::MAX, "1.7976931348623157e+308"),
+        (9007199254740992.0, "9007199254740992"),
+        (-9007199254740992.0, "-9007199254740992"),
+        (295147905179352830000.0, "295147905179352830000"),
+        (9.999999999999997e22, "9.999999999999997e+22"),
+        (1e23, "1e+23"),
+        (999999999999999700000.0, "999999999999999700000"),
+        (1e21, "1e+21"),
+        (0.000001, "0.000001"),
+        (1e-7, "1e-7"),
+    ];
+    for (n, expected) in cases {
+        assert_eq!(format_number(n), expected, "{n:e}");
+    }
+}
@@ -0,0 +1,237 @@
+#![cfg(all(feature = "tooling", feature = "jws", feature = "analysis"))]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn workdir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("agent-safe-cli-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_agent-safe")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_keygen_mint_verify_inspect() {
+    let dir = workdir("flow");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+    let keygen = run(&["keygen"]);
+    assert!(keygen.status.success());
+    fs::write(path("key.json"), &keygen.stdout).unwrap();
+
+    fs::write(
+        path("policy.spl"),
+        r#"(and (<= (get req "amount") limit) (obligate "notify-owner" 3600))"#,
+    )
+    .unwrap();
+    let minted = run(&[
+        "mint", "--policy", &path("policy.spl"), "--key", &path("key.json"),
+        "--issued-at", "2026-01-01T00:00:00Z", "--sealed",
+    ]);
+    assert!(minted.status.success(), "{}", String::from_utf8_lossy(&minted.stderr));
+    fs::write(path("token.json"), &minted.stdout).unwrap();
+    fs::write(path("vars.json"), r#"{"limit": 100}"#).unwrap();
+
+    fs::write(path("ok.json"), r#"{"amount": 50}"#).unwrap();
+    let allow = run(&["verify", "--token", &path("token.json"), "--request", &path("ok.json"), "--vars", &path("vars.json")]);
+    assert_eq!(allow.status.code(), Some(0));
+    assert_eq!(stdout(&allow), "ALLOW\nobligation: notify-owner within 3600s\n");
+
+    fs::write(path("big.json"), r#"{"amount": 500}"#).unwrap();
+    let deny = run(&["verify", "--token", &path("token.json"), "--request", &path("big.json"), "--vars", &path("vars.json")]);
+    assert_eq!(deny.status.code(), Some(1));
+    assert_eq!(stdout(&deny), "DENY\n");
+
+    let tampered = stdout(&minted).replace("limit", "999");
+    fs::write(path("tampered.json"), tampered).unwrap();
+    let bad = run(&["verify", "--token", &path("tampered.json"), "--request", &path("ok.json")]);
+    assert_eq!(bad.status.code(), Some(1));
+    assert_eq!(stdout(&bad), "DENY (invalid_signature): invalid signature\n");
+
+    // Vars signed into the token beat the relying party's.
+    fs::write(path("signed.json"), r#"{"limit": 10}"#).unwrap();
+    let pinned = run(&["mint", "--policy", &path("policy.spl"), "--key", &path("key.json"), "--vars", &path("signed.json")]);
+    fs::write(path("pinned.json"), &pinned.stdout).unwrap();
+    let capped = run(&["verify", "--token", &path("pinned.json"), "--request", &path("ok.json"), "--vars", &path("vars.json")]);
+    assert_eq!(capped.status.code(), Some(1));
+
+    fs::write(path("reasoned.spl"), r#"(or (<= (get req "amount") 100) (deny-with "AMOUNT_TOO_HIGH"))"#).unwrap();
+    let reasoned = run(&["mint", "--policy", &path("reasoned.spl"), "--key", &path("key.json")]);
+    fs::write(path("reasoned.json"), &reasoned.stdout).unwrap();
+    let explained = run(&["verify", "--token", &path("reasoned.json"), "--request", &path("big.json")]);
+    assert_eq!(explained.status.code(), Some(1));
+    assert_eq!(stdout(&explained), "DENY: AMOUNT_TOO_HIGH\n");
+
+    let inspect = run(&["inspect", "--token", &path("token.json")]);
+    let summary: serde_json::Value = serde_json::from_slice(&inspect.stdout).unwrap();
+    assert_eq!(summary["sealed"], serde_json::json!(true));
+    assert_eq!(summary["issued_at"], serde_json::json!("2026-01-01T00:00:00Z"));
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_fmt_and_usage_errors() {
+    let dir = workdir("fmt");
+    let policy = dir.join("p.spl");
+    fs::write(&policy, "(and   #t\n #t)").unwrap();
+    let policy = policy.to_str().unwrap();
+
+    let formatted = run(&["fmt", "--policy", policy]);
+    assert_eq!(stdout(&formatted), "(and #t #t)\n");
+    assert_eq!(run(&["fmt", "--policy", policy, "--check"]).status.code(), Some(1));
+    fs::write(policy, "(and #t #t)\n").unwrap();
+    assert_eq!(run(&["fmt", "--policy", policy, "--check"]).status.code(), Some(0));
+
+    assert_eq!(run(&[]).status.code(), Some(2));
+    assert_eq!(run(&["mint", "--policy"]).status.code(), Some(2));
+    assert_eq!(run(&["verify", "--bogus", "x"]).status.code(), Some(2));
+    assert_eq!(run(&["keygen", "--alg", "RS256"]).status.code(), Some(2));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_sandbox_scenario() {
+    let dir = workdir("sandbox");
+    let policy = dir.join("p.spl");
+    let scenario = dir.join("s.json");
+    fs::write(&policy, r#"(and (< (per-day-count-self) 1) (obligate "receipt"))"#).unwrap();
+    fs::write(
+        &scenario,
+        r#"{"now": "2026-03-01T12:00:00Z", "record_allowed": true, "requests": [
+            {"name": "first", "request": {"action": "pay"}},
+            {"name": "second", "request": {"action": "pay"}}
+        ]}"#,
+    )
+    .unwrap();
+
+    let output = run(&["sandbox", "--policy", policy.to_str().unwrap(), "--scenario", scenario.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "first: ALLOW\nobligation: receipt\nsecond: DENY\n");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_policy_test_command() {
+    let dir = workdir("test");
+    let policy = dir.join("p.spl");
+    let cases = dir.join("cases.json");
+    fs::write(&policy, r#"(<= (get req "amount") 50)"#).unwrap();
+    fs::write(
+        &cases,
+        r#"{"cases": [
+            {"name": "small", "expect": "allow", "request": {"amount": 10}},
+            {"name": "large", "expect": "allow", "request": {"amount": 90}}
+        ]}"#,
+    )
+    .unwrap();
+
+    let output = run(&["test", "--policy", policy.to_str().unwrap(), "--cases", cases.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "ok    small\nFAIL  large: expected ALLOW, got DENY\n      #f  (<= (get req \"amount\") 50)\n1 passed, 1 failed\n"
+    );
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_gen_vectors() {
+    let dir = workdir("vectors");
+    let output = run(&["gen-vectors", "--out", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).lines().count(), 4);
+    let merkle: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("merkle_vectors.json")).unwrap()).unwrap();
+    assert_eq!(merkle["root"], "0e9a1502e51e4040a918fd445b19cdf672c6d5a95eaad946955871c79584cabb");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_lint_command() {
+    let dir = workdir("lint");
+    let policy = dir.join("p.spl");
+    let vars = dir.join("vars.json");
+    fs::write(&policy, r#"(and (= (get req "role") admin_role) (<= (get req "amount") limit))"#).unwrap();
+    fs::write(&vars, r#"{"limit": 100}"#).unwrap();
+    let policy = policy.to_str().unwrap();
+
+    let output = run(&["lint", "--policy", policy]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("unknown-symbol: `admin_role`"));
+    let with_vars = run(&["lint", "--policy", policy, "--vars", vars.to_str().unwrap()]);
+    assert_eq!(stdout(&with_vars).lines().count(), 1);
+    assert_eq!(run(&["lint", "--policy", policy, "--disable", "unknown-symbol"]).status.code(), Some(0));
+    assert_eq!(run(&["lint", "--policy", policy, "--enable", "bogus"]).status.code(), Some(2));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_report_command() {
+    let dir = workdir("report");
+    let policy = dir.join("p.spl");
+    fs::write(&policy, r#"(<= (get req "amount") 50) (policy "refunds" (= (get req "action") "payments.refund"))"#).unwrap();
+    let output = run(&["report", "--policy", policy.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let out = stdout(&output);
+    assert!(out.starts_with("# Policy report\n") && out.contains("\n# Policy report: refunds\n"), "{out}");
+    assert!(out.contains("| `amount` |  | 50 |  |"), "{out}");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_chain_command() {
+    use agent_safe_spl::Token;
+
+    let dir = workdir("chain");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    fs::write(path("key.json"), run(&["keygen"]).stdout).unwrap();
+    fs::write(path("policy.spl"), r#"(<= (get req "amount") 100)"#).unwrap();
+    let minted = run(&[
+        "mint", "--policy", &path("policy.spl"), "--key", &path("key.json"),
+        "--expires", "2026-12-31T00:00:00Z", "--attenuable",
+    ]);
+    assert!(minted.status.success(), "{}", String::from_utf8_lossy(&minted.stderr));
+    let token: Token = serde_json::from_slice(&minted.stdout).unwrap();
+    let token = token
+        .add_caveat(r#"(<= (get req "amount") 50)"#)
+        .unwrap()
+        .add_caveat(r#"(before now "2026-11-01T00:00:00Z")"#)
+        .unwrap();
+    fs::write(path("token.json"), serde_json::to_string(&token).unwrap()).unwrap();
+
+    let tree = run(&["chain", "--token", &path("token.json")]);
+    assert_eq!(tree.status.code(), Some(0));
+    let out = stdout(&tree);
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines[0].starts_with("token by ") && lines[0].ends_with("(signature ok)"), "{out}");
+    assert_eq!(lines[1], "  expires: 2026-12-31T00:00:00Z");
+    assert!(out.contains("└── caveat 0 by ") && out.contains("    └── caveat 1 by "), "{out}");
+    assert!(out.contains("      adds: (<= (get req \"amount\") 50)\n"), "{out}");
+    assert!(out.contains("tightened: amount <= 50 (parent amount <= 100)"), "{out}");
+    assert!(out.contains("        expires: 2026-11-01T00:00:00Z\n"), "{out}");
+
+    let json = run(&["chain", "--token", &path("token.json"), "--json"]);
+    let links: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(links.as_array().unwrap().len(), 3);
+    assert_eq!(links[1]["caveat"], r#"(<= (get req "amount") 50)"#);
+    assert_eq!(links[2]["expires"], "2026-11-01T00:00:00Z");
+
+    // A rewritten caveat fails its own link without hiding the others.
+    let mut tampered = token.clone();
+    tampered.caveats[0].policy = r#"(<= (get req "amount") 500)"#.into();
+    fs::write(path("tampered.json"), serde_json::to_string(&tampered).unwrap()).unwrap();
+    let bad = run(&["chain", "--token", &path("tampered.json"), "--json"]);
+    assert_eq!(bad.status.code(), Some(1));
+    let links: serde_json::Value = serde_json::from_slice(&bad.stdout).unwrap();
+    let valid: Vec<_> = links.as_array().unwrap().iter().map(|l| l["signature_valid"].as_bool().unwrap()).collect();
+    assert_eq!(valid, [true, false, true]);
+}
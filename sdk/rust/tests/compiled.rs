@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use agent_safe_spl::compiled::{CompiledPolicy, Policy};
+use agent_safe_spl::evaluator::{eval_policy_detailed, EvalOutcome};
+use agent_safe_spl::types::{CallbackState, CryptoCallbacks, Env, GasSchedule, Node, SplError};
+use agent_safe_spl::parse;
+
+fn make_env(amount: f64, recipient: &str) -> Env {
+    let mut req = HashMap::new();
+    req.insert("actor_pub".into(), Node::Str("K_ai".into()));
+    req.insert("action".into(), Node::Str("payments.create".into()));
+    req.insert("recipient".into(), Node::Str(recipient.into()));
+    req.insert("purpose".into(), Node::Str("giftcard".into()));
+    req.insert("amount".into(), Node::Number(amount));
+    req.insert("day".into(), Node::Str("2025-09-29".into()));
+    req.insert("device_attested".into(), Node::Bool(true));
+
+    let mut vars = HashMap::new();
+    vars.insert(
+        "allowed_recipients".into(),
+        Node::List(vec![Node::Str("niece@example.com".into()), Node::Str("mom@example.com".into())]),
+    );
+    vars.insert("now".into(), Node::Str("2025-10-01T00:00:00Z".into()));
+
+    Env {
+        req,
+        vars,
+        usage_count: Arc::new(|_, _| 2),
+        crypto: CryptoCallbacks {
+            dpop_ok: CallbackState::Configured(Arc::new(|| true)),
+            merkle_ok: Some(Arc::new(|_| true)),
+            vrf_ok: CallbackState::Configured(Arc::new(|_, _| true)),
+            thresh_ok: CallbackState::Configured(Arc::new(|| true)),
+        },
+        ..Env::default()
+    }
+}
+
+fn summary(result: Result<EvalOutcome, SplError>) -> String {
+    match result {
+        Ok(o) => format!("{:?} gas={} obligations={:?} reason={:?}", o.value, o.gas_used, o.obligations, o.reason_code),
+        Err(e) => format!("err {}", e.0),
+    }
+}
+
+fn assert_same(src: &str, env: &Env) {
+    let ast = parse(src).unwrap();
+    let compiled = Policy::compile(&ast).unwrap();
+    assert_eq!(summary(compiled.eval_detailed(env)), summary(eval_policy_detailed(&ast, env)), "{src}");
+}
+
+#[test]
+fn compiled_policies_match_the_interpreter() {
+    let family = fs::read_to_string("../../examples/policies/family_gifts.spl").unwrap();
+    let policies = [
+        family.as_str(),
+        r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 60))"#,
+        r#"(or (> (get req "amount") 100) (deny-with "SMALL") (obligate "notify" 60))"#,
+        r#"(and (member (get req "recipient") allowed_recipients) (obligate "receipt"))"#,
+        r#"(limits (amount 0 60) (per_day 3))"#,
+        r#"(<= (per-day-count "payments.create" (get req "day")) 3)"#,
+        r#"(and (dpop_ok?) (thresh_ok?) (merkle_ok? (tuple) "k" (tuple)))"#,
+        r#"(= (get (get req "missing") "x") (get req "nope"))"#,
+        r#"(before now "2026-01-01T00:00:00Z")"#,
+        r#"(and unbound-symbol (not #f))"#,
+        r#"(<= (get req "recipient") 5)"#,
+        r#"(and (<= (length allowed_recipients) 2) (= (sum (tuple 1 (get req "amount"))) 51))"#,
+        r#"(= (count-if (lambda (r) (member r allowed_recipients)) (tuple (get req "recipient") "mom@example.com")) 2)"#,
+        r#"(count-if (lambda (x) (sum (tuple x))) allowed_recipients)"#,
+        r#"(and (disjoint? allowed_recipients (tuple (get req "recipient"))) (union allowed_recipients (tuple "x")))"#,
+        r#"(and (all r allowed_recipients (any s (tuple "x" r) (= s (get req "recipient")))) (any r (tuple) #t))"#,
+    ];
+    for (amount, recipient) in [(50.0, "niece@example.com"), (150.0, "stranger@example.com")] {
+        let env = make_env(amount, recipient);
+        for src in policies {
+            assert_same(src, &env);
+        }
+    }
+}
+
+#[test]
+fn compiled_policies_charge_the_same_gas() {
+    let src = r#"(and (<= (get req "amount") 60) (member (get req "recipient") allowed_recipients))"#;
+    let mut env = make_env(50.0, "niece@example.com");
+    env.gas = GasSchedule { node: 3, list_item: 2, ..GasSchedule::default() };
+    assert_same(src, &env);
+
+    // Running out of gas fails at the same point.
+    let used = eval_policy_detailed(&parse(src).unwrap(), &env).unwrap().gas_used;
+    env.max_gas = used - 1;
+    assert_same(src, &env);
+    assert!(Policy::compile(&parse(src).unwrap()).unwrap().eval(&env).is_err());
+}
+
+#[test]
+fn compile_rejects_unknown_operators_and_bad_arity() {
+    let err = |src: &str| CompiledPolicy::compile(&parse(src).unwrap()).err().unwrap().0;
+    assert_eq!(err(r#"(or #t (frobnicate 1))"#), "Unknown op: frobnicate");
+    assert_eq!(err(r#"(or #t ("and" 1))"#), "operator must be a symbol");
+    assert_eq!(err(r#"(or #t (not))"#), "not expects 1 argument, got 0");
+    assert_eq!(err(r#"(<= 1 2 3)"#), "<= expects 2 arguments, got 3");
+    assert_eq!(err(r#"(obligate "a" 1 2)"#), "obligate expects at most 2 arguments, got 3");
+    assert_eq!(err(r#"(limit-for "a")"#), "limit-for expects at least 2 arguments, got 1");
+    assert_eq!(err(r#"(dpop_ok? 1)"#), "dpop_ok? expects 0 arguments, got 1");
+    assert_eq!(err(r#"(limits (amount "low" 5))"#), r#"invalid limits entry: (amount "low" 5)"#);
+    assert_eq!(err(r#"(count-if (lambda (x y) #t) (tuple))"#), "lambda expects one parameter: (lambda (x) body)");
+
+    let deep = format!("{}#t{}", "(not ".repeat(70), ")".repeat(70));
+    assert_eq!(err(&deep), "max nesting depth exceeded");
+}
+
+#[test]
+fn compiled_policy_is_shared_across_threads() {
+    let policy = Arc::new(Policy::compile(&parse(r#"(<= (get req "amount") 100)"#).unwrap()).unwrap());
+    let handles: Vec<_> = [50.0, 150.0]
+        .into_iter()
+        .map(|amount| {
+            let policy = policy.clone();
+            std::thread::spawn(move || policy.eval(&make_env(amount, "mom@example.com")).unwrap())
+        })
+        .collect();
+    let results: Vec<Node> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(results, [Node::Bool(true), Node::Bool(false)]);
+}
+
+#[test]
+fn host_operators_are_arity_checked_and_charged() {
+    use agent_safe_spl::ops::{OpHandler, OpRegistry};
+    use agent_safe_spl::types::Limits;
+
+    // (geo-within? [lat lon] [south west north east])
+    let geo_within = OpHandler::host(2, Some(2), 20, |args| {
+        let nums = |n: &Node| match n {
+            Node::List(items) => items.iter().map(Node::as_number).collect::<Option<Vec<f64>>>(),
+            _ => None,
+        };
+        match (nums(&args[0]).as_deref(), nums(&args[1]).as_deref()) {
+            (Some([lat, lon]), Some([s, w, n, e])) => Ok(Node::Bool((s..=n).contains(&lat) && (w..=e).contains(&lon))),
+            _ => Err(SplError("geo-within? expects a point and a box".into())),
+        }
+    });
+    let mut env = make_env(50.0, "mom@example.com");
+    env.register_op("geo-within?", geo_within.clone()).unwrap();
+    let mut ops = OpRegistry::default();
+    ops.register("geo-within?", geo_within.clone(), false).unwrap();
+
+    let inside = "(geo-within? (tuple 40.7 -74.0) (tuple 40 -75 41 -73))";
+    let outside = "(geo-within? (tuple 10 10) (tuple 40 -75 41 -73))";
+    for src in [inside, outside, "(geo-within? (tuple 1) 2)"] {
+        let ast = parse(src).unwrap();
+        let compiled = CompiledPolicy::compile_with_ops(&ast, &Limits::default(), &ops).unwrap();
+        assert_eq!(summary(compiled.eval_detailed(&env)), summary(eval_policy_detailed(&ast, &env)), "{src}");
+    }
+    let outcome = eval_policy_detailed(&parse(inside).unwrap(), &env).unwrap();
+    assert_eq!(outcome.value, Node::Bool(true));
+    // Nine nodes and six tuple items, then the declared cost.
+    assert_eq!(outcome.gas_used, 15 + 20);
+    assert_eq!(eval_policy_detailed(&parse(outside).unwrap(), &env).unwrap().value, Node::Bool(false));
+
+    // Arity is checked before any argument is evaluated, for host and built-in operators alike.
+    let err = |src: &str, env: &Env| eval_policy_detailed(&parse(src).unwrap(), env).err().unwrap().0;
+    assert_eq!(err("(geo-within? 1)", &env), "geo-within? expects 2 arguments, got 1");
+    assert_eq!(err("(not #t #f)", &env), "not expects 1 argument, got 2");
+    assert_eq!(err(inside, &Env::default()), "Unknown op: geo-within?");
+    assert_eq!(
+        CompiledPolicy::compile(&parse(inside).unwrap()).err().unwrap().0,
+        "Unknown op: geo-within?"
+    );
+
+    // Overriding a built-in is refused in strict mode, at registration or evaluation.
+    let always = OpHandler::host(0, None, 0, |_| Ok(Node::Bool(true)));
+    env.register_op("not", always.clone()).unwrap();
+    assert_eq!(eval_policy_detailed(&parse("(not #t #t)").unwrap(), &env).unwrap().value, Node::Bool(true));
+    env.strict = true;
+    assert_eq!(err("(not #t)", &env), "cannot override built-in operator not in strict mode");
+    assert!(env.register_op("and", always).is_err());
+    assert!(env.register_op("bad name", geo_within.clone()).is_err());
+
+    env.allowed_ops = Some(["tuple".to_string()].into());
+    assert_eq!(err(inside, &env), "operator not permitted: geo-within?");
+}
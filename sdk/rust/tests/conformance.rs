@@ -0,0 +1,50 @@
+#![cfg(feature = "tooling")]
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use agent_safe_spl::conformance::{run_case, run_dir, run_suite, ConformanceCase, ConformanceSuite, ErrorClass, Outcome};
+
+#[test]
+fn test_shared_suite_passes() {
+    let dir = Path::new("../../examples/conformance");
+    if !dir.exists() {
+        eprintln!("Skipping: conformance suite not found");
+        return;
+    }
+    let report = run_dir(dir).unwrap();
+    let failures: Vec<_> = report.cases.iter().filter(|c| !c.passed).collect();
+    assert!(failures.is_empty(), "{failures:#?}");
+    assert!(report.passed > 0);
+}
+
+#[test]
+fn test_mismatches_are_reported() {
+    let case = |name: &str, policy: &str, expect: Outcome| ConformanceCase {
+        name: name.into(),
+        policy: policy.into(),
+        req: HashMap::new(),
+        vars: HashMap::new(),
+        max_gas: None,
+        expect,
+    };
+    let suite = ConformanceSuite {
+        description: None,
+        cases: vec![
+            case("wrong_decision", "#f", Outcome { allow: Some(true), ..Outcome::default() }),
+            case("unexpected_error", "(frobnicate)", Outcome { allow: Some(false), ..Outcome::default() }),
+            case("wrong_class", "(and", Outcome { error: Some(ErrorClass::Eval), ..Outcome::default() }),
+            case("any_decision", "#f", Outcome::default()),
+        ],
+    };
+    let report = run_suite("inline.json", &suite);
+    assert_eq!((report.passed, report.failed), (1, 3));
+    assert_eq!(report.cases[1].actual.error, Some(ErrorClass::Eval));
+    assert!(report.cases[1].message.as_deref().unwrap().contains("frobnicate"));
+    assert_eq!(report.cases[2].file, "inline.json");
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["cases"][2]["actual"], serde_json::json!({"error": "parse"}));
+    let (outcome, _) = run_case(&suite.cases[3]);
+    assert_eq!(outcome, Outcome { allow: Some(false), ..Outcome::default() });
+}
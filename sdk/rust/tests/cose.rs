@@ -0,0 +1,66 @@
+#![cfg(feature = "cose")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::cose::SPL_CLAIM;
+use agent_safe_spl::token::{generate_keypair, mint, verify_token_untrusted, MintOptions, Token, VerifyErrorCode};
+use agent_safe_spl::types::Node;
+use coset::cwt::{ClaimName, ClaimsSet, Timestamp};
+use coset::{iana, Algorithm, CborSerializable, CoseSign1, TaggedCborSerializable};
+
+#[test]
+fn test_cose_roundtrip() {
+    let (_, issuer_priv) = generate_keypair();
+    let (agent_pub, _) = generate_keypair();
+    let token = mint(
+        r#"(<= (get req "amount") cap)"#,
+        &issuer_priv,
+        MintOptions {
+            expires: Some("2027-01-01T00:00:00Z".into()),
+            issued_at: Some("2026-04-01T00:00:00Z".into()),
+            pop_key: Some(agent_pub.clone()),
+            token_id: Some("tok-0001".into()),
+            kid: Some("issuer-2026".into()),
+            vars: [("cap".to_string(), Node::Number(12.5)), ("n".into(), Node::Int(3))].into(),
+            ..MintOptions::default()
+        },
+    )
+    .unwrap();
+
+    let cose = token.to_cose(&issuer_priv, Some("door-17")).unwrap();
+    let sign1 = CoseSign1::from_tagged_slice(&cose).unwrap();
+    assert_eq!(sign1.protected.header.alg, Some(Algorithm::Assigned(iana::Algorithm::EdDSA)));
+    assert_eq!(sign1.protected.header.key_id, b"issuer-2026");
+    let claims = ClaimsSet::from_slice(sign1.payload.as_deref().unwrap()).unwrap();
+    assert_eq!(claims.issuer.as_deref(), Some(token.public_key.as_str()));
+    assert_eq!(claims.expiration_time, Some(Timestamp::WholeSeconds(1_798_761_600)));
+    assert_eq!(claims.issued_at, Some(Timestamp::WholeSeconds(1_775_001_600)));
+    assert_eq!(claims.cwt_id.as_deref(), Some(&b"tok-0001"[..]));
+    assert_eq!(claims.audience.as_deref(), Some("door-17"));
+    assert!(claims.rest.iter().any(|(name, _)| *name == ClaimName::Assigned(iana::CwtClaimName::Cnf)));
+    assert!(claims.rest.iter().any(|(name, _)| *name == ClaimName::PrivateUse(SPL_CLAIM)));
+
+    let imported = Token::from_cose(&cose, Some("door-17")).unwrap();
+    assert_eq!(imported, token);
+    let result = verify_token_untrusted(&imported, HashMap::new(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::PresentationRequired));
+
+    // A different key cannot export, and a tampered envelope does not import.
+    let (_, other_priv) = generate_keypair();
+    assert!(token.to_cose(&other_priv, None).is_err());
+    let mut forged_claims = claims;
+    forged_claims.expiration_time = Some(Timestamp::WholeSeconds(4_102_444_800));
+    let mut forged = sign1;
+    forged.payload = Some(forged_claims.to_vec().unwrap());
+    let err = Token::from_cose(&forged.to_tagged_vec().unwrap(), Some("door-17")).unwrap_err();
+    assert_eq!(err.0, "invalid COSE signature");
+
+    // The audience is checked, not dropped.
+    for audience in [None, Some("door-18")] {
+        let err = Token::from_cose(&cose, audience).unwrap_err();
+        assert_eq!(err.0, "CWT audience does not match");
+    }
+    let unscoped = token.to_cose(&issuer_priv, None).unwrap();
+    assert_eq!(Token::from_cose(&unscoped, None).unwrap(), token);
+    assert!(Token::from_cose(&unscoped, Some("door-17")).is_err());
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "dev")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::builder::RequestBuilder;
+use agent_safe_spl::dev::{DevProviders, ManualClock};
+use agent_safe_spl::time::{parse_rfc3339, Clock};
+use agent_safe_spl::token::{
+    create_presentation_signature, generate_keypair, mint, Challenge, MintOptions, Presentation, VerifyErrorCode,
+};
+use agent_safe_spl::{parse, verify, Verifier};
+
+#[test]
+fn for_development_verifies_tokens() {
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(r#"(= (get req "action") "read")"#, &issuer_priv, MintOptions::default()).unwrap();
+    let req = RequestBuilder::new().action("read").build().unwrap();
+    assert!(Verifier::for_development().verify(&token, req, HashMap::new(), None).allow);
+}
+
+#[test]
+fn scripted_clock_and_replay_cache() {
+    let dev = DevProviders::new(parse_rfc3339("2026-03-01T12:00:00Z").unwrap());
+    let verifier = dev.verifier();
+    let (_, issuer_priv) = generate_keypair();
+    let (agent_pub, agent_priv) = generate_keypair();
+    let token = mint(
+        r#"(= (get req "action") "read")"#,
+        &issuer_priv,
+        MintOptions { issued_at: Some("2026-03-01T13:00:00Z".into()), pop_key: Some(agent_pub), ..MintOptions::default() },
+    )
+    .unwrap();
+    let req = || RequestBuilder::new().action("read").build().unwrap();
+    let present = |nonce: &str| {
+        let challenge = Challenge { nonce: nonce.into(), timestamp: dev.clock.now_unix(), evidence: None };
+        let signature = create_presentation_signature(&token, &agent_priv, &challenge).unwrap();
+        Presentation { signature, challenge }
+    };
+
+    // The token is not valid until the shared clock reaches its issuance.
+    let early = verifier.verify(&token, req(), HashMap::new(), Some(&present("n0")));
+    assert_eq!(early.code, Some(VerifyErrorCode::PresentedBeforeIssuance));
+    dev.clock.advance(2 * 3600);
+
+    assert!(verifier.verify(&token, req(), HashMap::new(), Some(&present("n1"))).allow);
+    assert_eq!(dev.replay.seen(), vec!["n1".to_string()]);
+    let replay = verifier.verify(&token, req(), HashMap::new(), Some(&present("n1")));
+    assert_eq!(replay.code, Some(VerifyErrorCode::NonceReused));
+    dev.replay.preload("n2");
+    assert!(!verifier.verify(&token, req(), HashMap::new(), Some(&present("n2"))).allow);
+}
+
+#[test]
+fn env_builder_reads_usage_and_deny_lists() {
+    let dev = DevProviders { clock: ManualClock::at("2026-03-01T12:00:00Z").unwrap(), ..DevProviders::default() };
+    let policy = parse(
+        r#"(and (< (per-day-count "payments.create" day) 3)
+                (<= (spent-for "alice") 100)
+                (denylist-absent? (get req "recipient") "recipients"))"#,
+    )
+    .unwrap();
+    let check = || {
+        let req = RequestBuilder::new().action("payments.create").recipient("alice").build().unwrap();
+        verify(&policy, &dev.env_builder(req).build().unwrap()).unwrap().allow
+    };
+
+    dev.denylists.load("recipients", "v1", Vec::<String>::new());
+    assert!(check());
+    dev.usage.set_count("payments.create", "2026-03-01", 3);
+    assert!(!check());
+    dev.usage.set_count("payments.create", "2026-03-01", 0);
+    dev.usage.add_spent("alice", 150.0);
+    assert!(!check());
+    dev.usage.set_spent("alice", 10.0);
+    dev.denylists.revoke("recipients", "alice");
+    assert!(!check());
+
+    dev.denylists.load("recipients", "v2", ["mallory"]);
+    assert!(check());
+    dev.denylists.set_age("recipients", 7200);
+    let req = RequestBuilder::new().action("payments.create").recipient("alice").build().unwrap();
+    assert!(verify(&policy, &dev.env_builder(req).build().unwrap()).is_err());
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "tooling")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::expander::MacroRegistry;
+use agent_safe_spl::types::Limits;
+use agent_safe_spl::{parse, verify, Env, Node};
+
+#[test]
+fn standard_macros_expand_to_core_spl() {
+    let macros = MacroRegistry::standard();
+    let policy = macros
+        .parse(r#"(and (amount<= 50) (recipient-in allowed) (max-uses-per-day 3) (expires-before "2030-01-01T00:00:00Z") (limits (amount<= 1)))"#)
+        .unwrap();
+    let core = parse(
+        r#"(and (<= (get req "amount") 50) (member (get req "recipient") allowed) (< (per-day-count-self) 3) (before now "2030-01-01T00:00:00Z") (limits (amount<= 1)))"#,
+    )
+    .unwrap();
+    assert_eq!(policy, core);
+
+    let expanded = macros.parse(r#"(and (amount<= 50) (recipient-in allowed))"#).unwrap();
+    let req = |amount: f64| {
+        let req = HashMap::from([("amount".to_string(), Node::Number(amount)), ("recipient".to_string(), Node::Str("bob".into()))]);
+        let vars = HashMap::from([("allowed".to_string(), Node::List(vec![Node::Str("bob".into())]))]);
+        Env { req, vars, ..Env::default() }
+    };
+    assert!(verify(&expanded, &req(50.0)).unwrap().allow);
+    assert!(!verify(&expanded, &req(51.0)).unwrap().allow);
+}
+
+#[test]
+fn macros_are_hygienic_and_bounded() {
+    let mut macros = MacroRegistry::new();
+    assert!(macros.define("and", &["x"], "x").is_err());
+    assert!(macros.define("req", &["x"], "x").is_err());
+    assert!(macros.define("two words", &[], "#t").is_err());
+    assert!(macros.define("dup", &["x", "x"], "x").is_err());
+
+    // Arguments are substituted once, even when they mention other parameters.
+    macros.define("swap", &["a", "b"], "(< b a)").unwrap();
+    assert_eq!(macros.parse("(swap b a)").unwrap(), parse("(< a b)").unwrap());
+    assert!(macros.parse("(swap 1)").unwrap_err().0.contains("expects 2 argument(s), got 1"));
+
+    // A template binding may not capture a symbol from the call site.
+    macros.define("every-tag", &["pred"], r#"(all t (get req "tags") pred)"#).unwrap();
+    assert!(macros.parse(r#"(every-tag (= x "ok"))"#).is_ok());
+    let err = macros.parse(r#"(every-tag (= t "ok"))"#).unwrap_err();
+    assert!(err.0.contains("would be captured"), "{}", err.0);
+
+    // Macros may build on each other, but recursion is cut off.
+    macros.define("cap", &["n"], "(swap n (get req \"amount\"))").unwrap();
+    assert_eq!(macros.parse("(cap 5)").unwrap(), parse(r#"(< (get req "amount") 5)"#).unwrap());
+    macros.define("forever", &["x"], "(forever x)").unwrap();
+    assert!(macros.parse("(forever 1)").unwrap_err().0.contains("deeper than"));
+
+    let all = macros.parse_all("(cap 1) (cap 2)").unwrap();
+    assert_eq!(all.len(), 2);
+}
+
+#[test]
+fn macro_expansion_is_bounded_by_limits() {
+    // Each level copies its argument eight times, so eight levels would
+    // build a policy of millions of nodes.
+    let mut macros = MacroRegistry::new();
+    macros.define("w0", &["x"], "x").unwrap();
+    for level in 1..8 {
+        let below = format!("w{}", level - 1);
+        macros.define(&format!("w{level}"), &["x"], &format!("({below} (and x x x x x x x x))")).unwrap();
+    }
+    assert!(macros.parse("(w3 #t)").is_ok());
+    let err = macros.parse("(w7 #t)").unwrap_err();
+    assert!(err.0.contains("exceeds"), "{}", err.0);
+
+    // The expanded policy must also fit the registry's limits.
+    let limits = Limits { max_policy_bytes: 64, ..Limits::default() };
+    let macros = MacroRegistry::standard().with_limits(limits);
+    assert!(macros.parse("(amount<= 5)").is_ok());
+    let err = macros.parse("(and (amount<= 5) (amount<= 6) (amount<= 7))").unwrap_err();
+    assert!(err.0.contains("exceeds maximum size"), "{}", err.0);
+
+    let limits = Limits { max_list_len: 2, ..Limits::default() };
+    let macros = MacroRegistry::standard().with_limits(limits);
+    assert!(macros.parse("(amount<= 5)").unwrap_err().0.contains("list exceeds"));
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::fragments::{expand, parse_with_fragments, MAX_INCLUDE_DEPTH};
+use agent_safe_spl::parser::parse;
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, mint_with_fragments, verify_token_untrusted, MintOptions, VerifyErrorCode};
+use agent_safe_spl::types::Node;
+
+fn fragments(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn kyc() -> HashMap<String, String> {
+    fragments(&[
+        ("standard-kyc-checks", r#"(and (include "verified-device") (= (get req "kyc") "passed"))"#),
+        ("verified-device", r#"(= (get req "device_attested") #t)"#),
+    ])
+}
+
+fn req(kyc: &str) -> HashMap<String, Node> {
+    let mut req = HashMap::new();
+    req.insert("action".to_string(), Node::Str("payments.create".into()));
+    req.insert("kyc".to_string(), Node::Str(kyc.into()));
+    req.insert("device_attested".to_string(), Node::Bool(true));
+    req
+}
+
+const POLICY: &str = r#"(and (= (get req "action") "payments.create") (include "standard-kyc-checks"))"#;
+
+#[test]
+fn test_expand_nested_includes() {
+    let ast = parse_with_fragments(POLICY, &kyc()).unwrap();
+    let expected = parse(
+        r#"(and (= (get req "action") "payments.create")
+                (and (= (get req "device_attested") #t) (= (get req "kyc") "passed")))"#,
+    )
+    .unwrap();
+    assert_eq!(ast, expected);
+}
+
+#[test]
+fn test_expand_rejects_bad_includes() {
+    let err = |src: &str, frags: &HashMap<String, String>| expand(&parse(src).unwrap(), frags).unwrap_err().0;
+
+    let cyclic = fragments(&[("a", r#"(include "b")"#), ("b", r#"(not (include "a"))"#)]);
+    assert_eq!(err(r#"(include "a")"#, &cyclic), "include cycle: a -> b -> a");
+    assert_eq!(err(r#"(include "missing")"#, &kyc()), "unknown fragment: \"missing\"");
+    assert!(err(r#"(include a)"#, &kyc()).starts_with("include expects one fragment name"));
+    assert!(err(r#"(include "bad")"#, &fragments(&[("bad", "(and")])).starts_with("fragment \"bad\":"));
+
+    let chain: HashMap<String, String> = (0..=MAX_INCLUDE_DEPTH)
+        .map(|i| (format!("f{i}"), format!(r#"(not (include "f{}"))"#, i + 1)))
+        .chain([(format!("f{}", MAX_INCLUDE_DEPTH + 1), "#t".to_string())])
+        .collect();
+    assert_eq!(err(r#"(include "f0")"#, &chain), format!("includes nested deeper than {MAX_INCLUDE_DEPTH}"));
+
+    // A diamond that doubles at every level stays bounded.
+    let diamond: HashMap<String, String> = (0..7)
+        .map(|i| (format!("d{i}"), format!(r#"(and (include "d{0}") (include "d{0}"))"#, i + 1)))
+        .chain([("d7".to_string(), "#t".to_string())])
+        .collect();
+    assert!(err(r#"(include "d0")"#, &diamond).starts_with("policy expands more than"));
+}
+
+#[test]
+fn test_tokens_pin_resolved_fragments() {
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint_with_fragments(POLICY, &issuer_priv, MintOptions::default(), &kyc()).unwrap();
+    assert!(token.policy.contains("(include \"standard-kyc-checks\")"));
+
+    let verifier = Verifier::default().with_fragments(kyc());
+    let result = verifier.verify(&token, req("passed"), HashMap::new(), None);
+    assert!(result.allow, "{:?}", result.error);
+    assert!(!verifier.verify(&token, req("pending"), HashMap::new(), None).allow);
+
+    // Loosening a fragment after minting invalidates the token.
+    let mut loosened = kyc();
+    loosened.insert("verified-device".into(), "#t".into());
+    let result = Verifier::default().with_fragments(loosened).verify(&token, req("passed"), HashMap::new(), None);
+    assert_eq!(result.code, Some(VerifyErrorCode::Fragment));
+    assert_eq!(result.error.as_deref(), Some("included fragments changed since the token was minted"));
+
+    // So does stripping the pinned hash, and verifying without a resolver fails closed.
+    let mut stripped = token.clone();
+    stripped.resolved_policy_hash = None;
+    assert_eq!(verify_token_untrusted(&stripped, req("passed"), HashMap::new()).code, Some(VerifyErrorCode::InvalidSignature));
+    assert_eq!(verify_token_untrusted(&token, req("passed"), HashMap::new()).code, Some(VerifyErrorCode::Fragment));
+
+    // A token minted without pinning cannot use includes.
+    let unpinned = mint(POLICY, &issuer_priv, MintOptions::default()).unwrap();
+    let result = verifier.verify(&unpinned, req("passed"), HashMap::new(), None);
+    assert_eq!(result.error.as_deref(), Some("policy includes fragments but the token does not pin them"));
+}
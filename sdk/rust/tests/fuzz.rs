@@ -0,0 +1,67 @@
+#![cfg(feature = "tooling")]
+
+use agent_safe_spl::fuzz::{check_eval, check_parse, fuzz_one, AstGen};
+use agent_safe_spl::parse;
+use proptest::prelude::*;
+
+/// Source-like text: mostly SPL tokens so parses succeed often.
+fn spl_source() -> impl Strategy<Value = String> {
+    let token = prop_oneof![
+        Just("(".to_string()),
+        Just(")".to_string()),
+        Just(" ".to_string()),
+        Just("\"".to_string()),
+        "[a-z<>=!?#-]{1,8}",
+        "-?[0-9]{1,4}(\\.[0-9]{1,3})?",
+        "\"[a-z .]{0,6}\"",
+    ];
+    proptest::collection::vec(token, 0..40).prop_map(|tokens| tokens.concat())
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_text_never_panics(src in "\\PC{0,200}") {
+        check_parse(&src);
+    }
+
+    #[test]
+    fn spl_like_source_parses_consistently(src in spl_source()) {
+        check_parse(&src);
+        if let Ok(ast) = parse(&src) {
+            check_eval(&ast, 10_000);
+        }
+    }
+
+    #[test]
+    fn generated_asts_respect_invariants(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+        fuzz_one(&data);
+    }
+
+    #[test]
+    fn gas_budget_is_never_exceeded(data in proptest::collection::vec(any::<u8>(), 0..512), gas in 0i64..200) {
+        check_eval(&AstGen::new(&data).node(), gas);
+    }
+}
+
+#[test]
+fn zero_argument_operators_error_instead_of_panicking() {
+    for op in ["not", "=", "<=", "in-range", "get", "member", "before", "obligate", "limit-for", "tuple"] {
+        let ast = parse(&format!("({op})")).unwrap();
+        check_eval(&ast, 10_000);
+    }
+}
+
+#[test]
+fn generator_is_deterministic_and_bounded() {
+    let data: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    let a = AstGen::new(&data).max_depth(4).node();
+    assert_eq!(a, AstGen::new(&data).max_depth(4).node());
+    fn depth(node: &agent_safe_spl::Node) -> usize {
+        match node {
+            agent_safe_spl::Node::List(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+    assert!(depth(&a) <= 5);
+    assert_eq!(AstGen::new(&[]).node(), agent_safe_spl::Node::Bool(false));
+}
@@ -0,0 +1,210 @@
+#![cfg(feature = "gateway")]
+
+//! Drives the reference gateway in `examples/gateway` over real HTTP.
+
+#[path = "../examples/gateway/app.rs"]
+#[allow(dead_code)]
+mod app;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+
+use agent_safe_spl::audit::{verify_chain, AuditEntry};
+use agent_safe_spl::builder::RequestBuilder;
+use agent_safe_spl::http::{evidence_digest, ChallengeHeader, PresentationHeader, CHALLENGE_HEADER, PRESENTATION_HEADER};
+use agent_safe_spl::obligations::Obligation;
+use agent_safe_spl::request::request_hash;
+use agent_safe_spl::time::FixedClock;
+use agent_safe_spl::token::{create_presentation_signature, generate_keypair, mint, Challenge, MintOptions, Presentation, Token};
+
+use app::{Gateway, PaymentBody, Receipt};
+
+// 2026-10-14T12:00:00Z, a Wednesday.
+const NOW: i64 = 1_791_979_200;
+
+const POLICY: &str = r#"(and (= (get req "action") "payments.create")
+                             (member (get req "recipient") (tuple "niece@example.com" "mom@example.com"))
+                             (<= (get req "amount") 100)
+                             (or (<= (cumulative-spend "payments.create" "week") 150) (deny-with "WEEKLY_BUDGET"))
+                             (obligate "notify-owner" 3600))"#;
+
+struct Reply {
+    status: u16,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json<T: serde::de::DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+/// Minimal HTTP/1.1 client: one request per connection.
+fn send(addr: SocketAddr, method: &str, path: &str, headers: &[(&str, String)], body: &[u8]) -> Reply {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut head = format!("{method} {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\nContent-Length: {}\r\n", body.len());
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("Content-Type: application/json\r\n\r\n");
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).unwrap();
+    let split = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8(raw[..split].to_vec()).unwrap();
+    let mut lines = head.lines();
+    let status = lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+    let headers = lines
+        .filter_map(|l| l.split_once(": "))
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.to_string()))
+        .collect();
+    Reply { status, headers, body: raw[split + 4..].to_vec() }
+}
+
+struct Agent {
+    addr: SocketAddr,
+    token: Token,
+    key: String,
+}
+
+impl Agent {
+    fn body(&self, recipient: &str, amount: f64) -> Vec<u8> {
+        let payment = PaymentBody { token: self.token.clone(), recipient: recipient.into(), amount, currency: "USD".into() };
+        serde_json::to_vec(&payment).unwrap()
+    }
+
+    /// Post without a presentation and return the challenge issued.
+    fn challenge(&self, body: &[u8]) -> ChallengeHeader {
+        let reply = send(self.addr, "POST", "/payments", &[], body);
+        assert_eq!(reply.status, 401);
+        ChallengeHeader::decode(&reply.headers[&CHALLENGE_HEADER.to_ascii_lowercase()]).unwrap()
+    }
+
+    fn presentation(&self, challenge: &ChallengeHeader, evidence: &[u8]) -> String {
+        let signed = Challenge { nonce: challenge.nonce.clone(), timestamp: NOW, evidence: Some(evidence_digest(evidence)) };
+        let signature = create_presentation_signature(&self.token, &self.key, &signed).unwrap();
+        let presentation = Presentation { signature, challenge: signed };
+        PresentationHeader::new(&self.token, &presentation).encode()
+    }
+
+    fn pay(&self, recipient: &str, amount: f64) -> Reply {
+        let body = self.body(recipient, amount);
+        let header = self.presentation(&self.challenge(&body), &body);
+        send(self.addr, "POST", "/payments", &[(PRESENTATION_HEADER, header)], &body)
+    }
+}
+
+fn start(issuers: Vec<String>) -> (tokio::runtime::Runtime, SocketAddr, Arc<Gateway>) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let gateway = Arc::new(Gateway::new(issuers, "payments.example").with_clock(FixedClock(NOW)));
+    let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = app::router(gateway.clone());
+    runtime.spawn(async move { axum::serve(listener, router).await });
+    (runtime, addr, gateway)
+}
+
+fn bound_agent(addr: SocketAddr, issuer_priv: &str) -> Agent {
+    let (agent_pub, agent_priv) = generate_keypair();
+    let opts = MintOptions { pop_key: Some(agent_pub), ..MintOptions::default() };
+    Agent { addr, token: mint(POLICY, issuer_priv, opts).unwrap(), key: agent_priv }
+}
+
+#[test]
+fn test_gateway_allows_tracks_and_audits_payments() {
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let (_runtime, addr, gateway) = start(vec![issuer_pub]);
+    let agent = bound_agent(addr, &issuer_priv);
+
+    let reply = agent.pay("niece@example.com", 100.0);
+    assert_eq!(reply.status, 200, "{}", String::from_utf8_lossy(&reply.body));
+    let receipt: Receipt = reply.json();
+    assert_eq!(receipt.day, "2026-10-14");
+    assert_eq!(receipt.obligations, vec![Obligation { kind: "notify-owner".into(), deadline_secs: Some(3600) }]);
+    assert_eq!(gateway.usage().month_total("payments.create", "2026-10"), (1, 100.0));
+
+    // The weekly budget counts the earlier payment; the policy says why it denied.
+    let reply = agent.pay("mom@example.com", 60.0);
+    assert_eq!(reply.status, 403);
+    let denied: serde_json::Value = reply.json();
+    assert_eq!(denied["reason_code"], "WEEKLY_BUDGET");
+    assert_eq!(agent.pay("mom@example.com", 50.0).status, 200);
+
+    // Each issued obligation is discharged once.
+    let path = format!("/obligations/{}/notify-owner", receipt.token);
+    assert_eq!(send(addr, "POST", &path, &[], b"").status, 200);
+    assert_eq!(send(addr, "POST", &path, &[], b"").status, 200);
+    assert_eq!(send(addr, "POST", &path, &[], b"").status, 404);
+
+    let entries: Vec<AuditEntry> = send(addr, "GET", "/audit", &[], b"").json();
+    verify_chain(&entries).unwrap();
+    let events: Vec<&str> = entries.iter().map(|e| e.event.as_str()).collect();
+    assert_eq!(
+        events,
+        [
+            "payment.allowed",
+            "obligation.issued",
+            "payment.denied",
+            "payment.allowed",
+            "obligation.issued",
+            "obligation.discharged",
+            "obligation.discharged",
+        ]
+    );
+    assert_eq!(entries[receipt.audit_seq as usize].hash, receipt.audit_hash);
+    // The receipt and its entry commit to the request the gateway decided.
+    let req = RequestBuilder::new()
+        .action("payments.create")
+        .recipient("niece@example.com")
+        .amount(100.0)
+        .field("currency", "USD")
+        .day("2026-10-14")
+        .build()
+        .unwrap();
+    assert_eq!(receipt.request_hash, request_hash(&req));
+    assert_eq!(entries[receipt.audit_seq as usize].request_hash.as_ref(), Some(&receipt.request_hash));
+    assert!(entries[2].request_hash.is_some() && entries[1].request_hash.is_none());
+}
+
+#[test]
+fn test_gateway_rejects_bad_presentations() {
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let (_runtime, addr, gateway) = start(vec![issuer_pub]);
+    let agent = bound_agent(addr, &issuer_priv);
+    let code = |reply: &Reply| reply.json::<serde_json::Value>()["code"].as_str().unwrap_or_default().to_string();
+
+    // A presentation answers its challenge once.
+    let body = agent.body("niece@example.com", 10.0);
+    let header = agent.presentation(&agent.challenge(&body), &body);
+    assert_eq!(send(addr, "POST", "/payments", &[(PRESENTATION_HEADER, header.clone())], &body).status, 200);
+    let replayed = send(addr, "POST", "/payments", &[(PRESENTATION_HEADER, header)], &body);
+    assert_eq!(replayed.status, 401);
+    assert!(replayed.headers.contains_key(&CHALLENGE_HEADER.to_ascii_lowercase()));
+
+    // The presentation must cover the body it arrives with.
+    let signed = agent.body("niece@example.com", 10.0);
+    let header = agent.presentation(&agent.challenge(&signed), &signed);
+    let swapped = send(addr, "POST", "/payments", &[(PRESENTATION_HEADER, header)], &agent.body("niece@example.com", 99.0));
+    assert_eq!((swapped.status, code(&swapped)), (400, "evidence_mismatch".into()));
+
+    // Another agent's key cannot answer for this token.
+    let (_, stranger) = generate_keypair();
+    let impostor = Agent { addr, token: agent.token.clone(), key: stranger };
+    let reply = impostor.pay("niece@example.com", 10.0);
+    assert_eq!((reply.status, code(&reply)), (403, "invalid_presentation".into()));
+
+    // Tokens from unknown issuers and without PoP binding are refused outright.
+    let (_, other_issuer) = generate_keypair();
+    let outsider = send(addr, "POST", "/payments", &[], &bound_agent(addr, &other_issuer).body("niece@example.com", 1.0));
+    assert_eq!((outsider.status, code(&outsider)), (403, "untrusted_issuer".into()));
+    let unbound = Agent { addr, token: mint(POLICY, &issuer_priv, MintOptions::default()).unwrap(), key: String::new() };
+    let reply = send(addr, "POST", "/payments", &[], &unbound.body("niece@example.com", 1.0));
+    assert_eq!((reply.status, code(&reply)), (403, "pop_required".into()));
+
+    assert_eq!(gateway.usage().month_total("payments.create", "2026-10"), (1, 10.0));
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "grpc")]
+
+use tonic::service::Interceptor;
+use tonic::{Code, Request};
+
+use agent_safe_spl::grpc::AgentSafeInterceptor;
+use agent_safe_spl::middleware::REASON_CODE_METADATA;
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, MintOptions, VerifyTokenResult};
+
+fn request(auth: Option<&str>, merchant: &str) -> Request<()> {
+    let mut request = Request::new(());
+    if let Some(auth) = auth {
+        request.metadata_mut().insert("authorization", auth.parse().unwrap());
+    }
+    request.metadata_mut().insert("x-merchant", merchant.parse().unwrap());
+    request
+}
+
+#[test]
+fn test_interceptor_verifies_calls() {
+    let (_, issuer) = generate_keypair();
+    let policy = r#"(and (= (get req "action") "payments.Payments")
+        (or (= (get req "merchant") "shop.example.com") (deny-with "UNKNOWN_MERCHANT")) (obligate "receipt"))"#;
+    let token = mint(policy, &issuer, MintOptions::default()).unwrap();
+    let auth = format!("AgentSafe {}", token.to_jws(&issuer, None).unwrap());
+    let mut interceptor =
+        AgentSafeInterceptor::new(Verifier::default).set("action", "payments.Payments").metadata("merchant", "X-Merchant");
+
+    let allowed = interceptor.call(request(Some(&auth), "shop.example.com")).unwrap();
+    let result = allowed.extensions().get::<VerifyTokenResult>().expect("verified result");
+    assert_eq!(result.obligations[0].kind, "receipt");
+
+    let denied = interceptor.call(request(Some(&auth), "evil.example.com")).unwrap_err();
+    assert_eq!(denied.code(), Code::PermissionDenied);
+    assert!(denied.message().starts_with("policy_denied: policy denied"), "{}", denied.message());
+    assert_eq!(denied.metadata().get(REASON_CODE_METADATA).unwrap(), "UNKNOWN_MERCHANT");
+
+    let anonymous = interceptor.call(request(None, "shop.example.com")).unwrap_err();
+    assert_eq!(anonymous.code(), Code::Unauthenticated);
+    assert_eq!(anonymous.message(), "missing_token: missing AgentSafe authorization");
+    let malformed = interceptor.call(request(Some("AgentSafe nonsense"), "shop.example.com")).unwrap_err();
+    assert_eq!(malformed.code(), Code::Unauthenticated);
+    assert!(malformed.message().starts_with("malformed_token"));
+}
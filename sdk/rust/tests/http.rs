@@ -0,0 +1,81 @@
+#![cfg(feature = "http")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::http::{evidence_digest, ChallengeHeader, PresentationHeader};
+use agent_safe_spl::token::{
+    create_presentation_signature, generate_keypair, mint, verify_token_with_pop, Challenge, MintOptions,
+    Presentation, VerifyErrorCode,
+};
+use agent_safe_spl::types::Node;
+
+fn pop_token() -> (agent_safe_spl::Token, String) {
+    let (_, issuer_priv) = generate_keypair();
+    let (agent_pub, agent_priv) = generate_keypair();
+    let token = mint("#t", &issuer_priv, MintOptions { pop_key: Some(agent_pub), ..MintOptions::default() }).unwrap();
+    (token, agent_priv)
+}
+
+fn answer(token: &agent_safe_spl::Token, agent_priv: &str, challenge: &ChallengeHeader, now: i64) -> String {
+    let challenge = Challenge { nonce: challenge.nonce.clone(), timestamp: now, evidence: Some(evidence_digest(b"{}")) };
+    let signature = create_presentation_signature(token, agent_priv, &challenge).unwrap();
+    let presentation = Presentation { signature, challenge };
+    PresentationHeader::new(token, &presentation).encode()
+}
+
+#[test]
+fn test_challenge_round_trip() {
+    let now = 1_760_000_000;
+    let challenge = ChallengeHeader::generate(Some("pay \"east\""), now, 300);
+    assert_eq!(challenge.nonce.len(), 32);
+    let encoded = challenge.encode();
+    assert_eq!(ChallengeHeader::decode(&encoded).unwrap(), challenge);
+    assert!(!challenge.is_expired(now + 300));
+    assert!(challenge.is_expired(now + 301));
+
+    let bare = ChallengeHeader::decode(r#"NONCE="n1",exp=5, future="x""#).unwrap();
+    assert_eq!(bare, ChallengeHeader { nonce: "n1".into(), audience: None, expires: 5 });
+}
+
+#[test]
+fn test_malformed_headers_rejected() {
+    for value in [
+        r#"nonce="n1""#,
+        r#"nonce=n1, exp=5"#,
+        r#"nonce="n1, exp=5"#,
+        r#"nonce="n1", exp="5""#,
+        r#"nonce="n1", nonce="n2", exp=5"#,
+        r#"nonce="n1" exp=5"#,
+    ] {
+        assert!(ChallengeHeader::decode(value).is_err(), "{value}");
+    }
+    assert!(PresentationHeader::decode(r#"tok="t", nonce="n", ts=1"#).is_err());
+}
+
+#[test]
+fn test_presentation_header_verifies_token() {
+    let (token, agent_priv) = pop_token();
+    let now = 1_760_000_000;
+    let challenge = ChallengeHeader::generate(None, now, 60);
+    let header = PresentationHeader::decode(&answer(&token, &agent_priv, &challenge, now + 5)).unwrap();
+    assert_eq!(header.evidence_digest.as_deref(), Some(evidence_digest(b"{}").as_str()));
+
+    let presentation = header.answer_to(&token, &challenge, now + 5).unwrap();
+    let result = verify_token_with_pop(&token, HashMap::<String, Node>::new(), HashMap::new(), Some(&presentation), None);
+    assert!(result.allow, "{:?}", result.error);
+
+    // The signature covers the evidence digest, so it cannot be swapped.
+    let swapped = PresentationHeader { evidence_digest: Some(evidence_digest(b"[]")), ..header.clone() };
+    let presentation = swapped.answer_to(&token, &challenge, now + 5).unwrap();
+    let result = verify_token_with_pop(&token, HashMap::<String, Node>::new(), HashMap::new(), Some(&presentation), None);
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidPresentation));
+
+    let expired = header.answer_to(&token, &challenge, now + 61).unwrap_err();
+    assert_eq!(expired.code, VerifyErrorCode::PresentationExpired);
+
+    let other = ChallengeHeader::generate(None, now, 60);
+    assert_eq!(header.answer_to(&token, &other, now).unwrap_err().code, VerifyErrorCode::InvalidPresentation);
+
+    let (other_token, _) = pop_token();
+    assert_eq!(header.answer_to(&other_token, &challenge, now).unwrap_err().code, VerifyErrorCode::InvalidPresentation);
+}
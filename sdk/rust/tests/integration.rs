@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
-use agent_safe_spl::types::{CryptoCallbacks, Env, Node};
-use agent_safe_spl::parser::parse;
+use agent_safe_spl::types::{CallbackState, CryptoCallbacks, Env, Limits, Node};
+use agent_safe_spl::parser::{parse, parse_with_limits};
 use agent_safe_spl::verifier::verify;
 use agent_safe_spl::crypto;
 
@@ -30,16 +31,13 @@ fn make_env() -> Env {
     Env {
         req,
         vars,
-        per_day_count: Box::new(|_, _| 0),
         crypto: CryptoCallbacks {
-            dpop_ok: Box::new(|| true),
-            merkle_ok: Box::new(|_| true),
-            vrf_ok: Box::new(|_, _| true),
-            thresh_ok: Box::new(|| true),
+            dpop_ok: CallbackState::Configured(Arc::new(|| true)),
+            merkle_ok: Some(Arc::new(|_| true)),
+            vrf_ok: CallbackState::Configured(Arc::new(|_, _| true)),
+            thresh_ok: CallbackState::Configured(Arc::new(|| true)),
         },
-        max_gas: 10_000,
-        sealed: false,
-        strict: false,
+        ..Env::default()
     }
 }
 
@@ -108,6 +106,20 @@ fn test_greater_than() {
     assert!(!eval_expr("(> 5 5)", make_env()).unwrap());
 }
 
+#[test]
+fn test_int_number_comparisons() {
+    assert!(eval_expr("(= 5 5.0)", make_env()).unwrap());
+    assert!(eval_expr("(< 5 5.5)", make_env()).unwrap());
+    assert!(eval_expr("(> 6 5.5)", make_env()).unwrap());
+    assert!(!eval_expr("(<= 6 5.5)", make_env()).unwrap());
+    // 2^53 + 1 has no f64 of its own; as Ints these still differ.
+    assert!(!eval_expr("(= 9007199254740993 9007199254740992)", make_env()).unwrap());
+    assert!(eval_expr("(> 9007199254740993 9007199254740992)", make_env()).unwrap());
+    assert!(eval_expr("(> 9007199254740993 9007199254740992.0)", make_env()).unwrap());
+    assert!(eval_expr("(< 9223372036854775807 9223372036854775808.0)", make_env()).unwrap());
+    assert_eq!(parse("(= 5 5.0)").unwrap().to_string(), "(= 5 5.0)");
+}
+
 #[test]
 fn test_member() {
     assert!(eval_expr(
@@ -120,6 +132,61 @@ fn test_member() {
     ).unwrap());
 }
 
+#[test]
+fn test_member_quoted_list() {
+    let src = r#"(member (get req "recipient") '("mom@example.com" "niece@example.com"))"#;
+    assert!(eval_expr(src, make_env()).unwrap());
+    assert!(!eval_expr(r#"(member (get req "purpose") '("rent" "bills"))"#, make_env()).unwrap());
+    assert!(eval_expr(r#"(= '(1 ("a")) (tuple 1 (tuple "a")))"#, make_env()).unwrap());
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn test_string_match_normalization() {
+    use agent_safe_spl::evaluator::fold_constants;
+    use agent_safe_spl::types::StringMatch;
+
+    // "José" spelled with a combining accent (NFD) against the NFC allowlist.
+    let env_with = |mode: StringMatch| {
+        let mut env = make_env();
+        env.req.insert("recipient".into(), Node::Str("Jose\u{301}@example.com".into()));
+        env.string_match = mode;
+        env
+    };
+    let src = "(member (get req \"recipient\") '(\"jos\u{e9}@example.com\"))";
+    assert!(!eval_expr(src, env_with(StringMatch::Exact)).unwrap());
+    assert!(!eval_expr(src, env_with(StringMatch::Nfc)).unwrap());
+    assert!(eval_expr(src, env_with(StringMatch::NfcLowercase)).unwrap());
+    assert!(eval_expr(r#"(= (get req "recipient") "Jos\u{e9}@example.com")"#, env_with(StringMatch::Nfc)).unwrap());
+    let set = r#"(= (length (intersect (tuple "e\u{301}" "E") (tuple "\u{e9}"))) 1)"#;
+    assert!(eval_expr(set, env_with(StringMatch::Nfc)).unwrap());
+
+    // Lowercasing is not case folding.
+    assert!(!eval_expr(r#"(= "STRASSE" "stra\u{df}e")"#, env_with(StringMatch::NfcLowercase)).unwrap());
+
+    // Deny lists and action globs see the normalized request too.
+    let mut lists = agent_safe_spl::denylist::InMemoryDenyLists::new();
+    lists.load("recipients", "v1", ["jos\u{e9}@example.com"]);
+    let mut env = env_with(StringMatch::NfcLowercase);
+    env.denylists = Some(Arc::new(lists));
+    env.req.insert("action".into(), Node::Str("Caf\u{e9}.Order".into()));
+    assert!(!eval_expr(r#"(denylist-absent? (get req "recipient") "recipients")"#, env.clone()).unwrap());
+    assert!(eval_expr(r#"(action-in "cafe\u{301}.*")"#, env).unwrap());
+
+    // Constant folding leaves comparisons the mode could change for evaluation.
+    let literal = parse(r#"(= "\u{e9}" "e\u{301}")"#).unwrap();
+    assert_eq!(fold_constants(&literal), literal);
+    assert_eq!(fold_constants(&parse(r#"(= "a" "b")"#).unwrap()), Node::Bool(false));
+}
+
+#[cfg(not(feature = "unicode"))]
+#[test]
+fn test_string_match_requires_unicode() {
+    let mut env = make_env();
+    env.string_match = agent_safe_spl::types::StringMatch::Nfc;
+    assert!(eval_expr("#t", env).unwrap_err().contains("requires the unicode feature"));
+}
+
 #[test]
 fn test_in_alias() {
     assert!(eval_expr(
@@ -146,6 +213,75 @@ fn test_subset() {
     assert!(eval_expr("(subset? small big)", env).unwrap());
 }
 
+#[test]
+fn test_set_operators() {
+    use agent_safe_spl::evaluator::eval_policy;
+
+    let mut env = make_env();
+    let strs = |items: &[&str]| Node::List(items.iter().map(|s| Node::Str(s.to_string())).collect());
+    env.req.insert("scopes".into(), strs(&["read", "write", "read"]));
+    env.vars.insert("granted".into(), strs(&["read", "list"]));
+    let value = |src: &str| eval_policy(&parse(src).unwrap(), &env).unwrap();
+
+    assert_eq!(value(r#"(intersect (get req "scopes") granted)"#), strs(&["read"]));
+    assert_eq!(value(r#"(union (get req "scopes") granted)"#), strs(&["read", "write", "list"]));
+    assert_eq!(value(r#"(difference (get req "scopes") granted)"#), strs(&["write"]));
+    assert_eq!(value(r#"(disjoint? (get req "scopes") (tuple "admin"))"#), Node::Bool(true));
+    // Requested scopes minus granted scopes must be empty.
+    assert!(!eval_expr(r#"(= (length (difference (get req "scopes") granted)) 0)"#, env.clone()).unwrap());
+    // Numbers compare by value, as in member.
+    assert_eq!(value("(intersect (tuple 1 2.0) (tuple 2 3))"), Node::List(vec![Node::Number(2.0)]));
+    assert_eq!(value(r#"(union (get req "missing") (tuple))"#), Node::List(vec![]));
+    assert!(eval_expr(r#"(intersect "read" granted)"#, env.clone()).unwrap_err().contains("intersect expects a list"));
+}
+
+#[test]
+fn test_ip_in_cidr() {
+    let with_ip = |ip: &str| {
+        let mut env = make_env();
+        env.req.insert("ip".into(), Node::Str(ip.into()));
+        env
+    };
+    let in_cidr = |ip: &str, cidrs: &str| eval_expr(&format!(r#"(ip-in-cidr (get req "ip") {cidrs})"#), with_ip(ip));
+    assert!(in_cidr("10.2.3.4", r#""10.0.0.0/8""#).unwrap());
+    assert!(!in_cidr("11.0.0.1", r#""10.0.0.0/8""#).unwrap());
+    assert!(in_cidr("192.168.1.255", r#""192.168.1.0/24""#).unwrap());
+    assert!(!in_cidr("192.168.2.0", r#""192.168.1.0/24""#).unwrap());
+    assert!(in_cidr("203.0.113.7", r#""0.0.0.0/0""#).unwrap());
+    assert!(in_cidr("203.0.113.7", r#""203.0.113.7/32""#).unwrap());
+    assert!(in_cidr("2001:db8::1", r#""2001:db8::/32""#).unwrap());
+    assert!(!in_cidr("2001:db9::1", r#""2001:db8::/32""#).unwrap());
+    assert!(in_cidr("::ffff:10.0.0.1", r#""10.0.0.0/8""#).unwrap());
+    assert!(!in_cidr("10.0.0.1", r#""::/0""#).unwrap());
+    assert!(in_cidr("172.16.5.4", r#"(tuple "10.0.0.0/8" "172.16.0.0/12")"#).unwrap());
+    // Unparseable request addresses match nothing.
+    for ip in ["", "10.0.0", "010.0.0.1", "fe80::1%eth0", "10.0.0.1/32"] {
+        assert!(!in_cidr(ip, r#""0.0.0.0/0""#).unwrap(), "{ip}");
+    }
+    assert!(!eval_expr(r#"(ip-in-cidr (get req "missing") "0.0.0.0/0")"#, make_env()).unwrap());
+    // Malformed blocks in the policy are errors.
+    for cidr in ["10.0.0.0", "10.0.0.0/33", "10.0.0.0/-1", "10.0.0.0/+8", "10.1.0.0/8", "2001:db8::1/32", "::/129"] {
+        assert!(in_cidr("10.0.0.1", &format!("{cidr:?}")).is_err(), "{cidr}");
+    }
+    assert!(in_cidr("10.0.0.1", "8").is_err());
+    assert!(in_cidr("10.0.0.1", r#""10.0.0.0/8" "extra""#).unwrap_err().contains("expects 2 arguments"));
+}
+
+#[test]
+fn test_country_in() {
+    let mut env = make_env();
+    env.req.insert("country".into(), Node::Str("us".into()));
+    env.vars.insert("allowed_countries".into(), Node::List(vec![Node::Str("US".into()), Node::Str("CA".into())]));
+    assert!(eval_expr(r#"(country-in (get req "country") allowed_countries)"#, env.clone()).unwrap());
+    assert!(eval_expr(r#"(country-in (get req "country") "US")"#, env.clone()).unwrap());
+    assert!(!eval_expr(r#"(country-in (get req "country") (tuple "GB" "FR"))"#, env.clone()).unwrap());
+    assert!(!eval_expr(r#"(country-in (get req "missing") allowed_countries)"#, env.clone()).unwrap());
+    env.req.insert("country".into(), Node::Str("USA".into()));
+    assert!(!eval_expr(r#"(country-in (get req "country") allowed_countries)"#, env.clone()).unwrap());
+    let err = eval_expr(r#"(country-in (get req "country") (tuple "US" "Canada"))"#, env).unwrap_err();
+    assert!(err.contains("ISO 3166-1 alpha-2"), "{err}");
+}
+
 #[test]
 fn test_before() {
     assert!(eval_expr(
@@ -158,6 +294,64 @@ fn test_before() {
     ).unwrap());
 }
 
+#[test]
+fn test_weekday() {
+    // 2026-03-06 is a Friday.
+    assert!(eval_expr(r#"(weekday? "2026-03-06T12:00:00Z")"#, make_env()).unwrap());
+    assert!(!eval_expr(r#"(weekday? "2026-03-07T12:00:00Z")"#, make_env()).unwrap());
+    // Read in the timestamp's own offset: Friday evening in New York is Saturday in UTC.
+    assert!(eval_expr(r#"(weekday? "2026-03-06T23:30:00-05:00")"#, make_env()).unwrap());
+    assert!(!eval_expr(r#"(weekday? "2026-03-07T04:30:00Z")"#, make_env()).unwrap());
+    assert!(eval_expr("(weekday? now)", make_env()).is_ok());
+    assert!(eval_expr(r#"(weekday? "2026-03-06")"#, make_env()).is_err());
+    assert!(eval_expr("(weekday? 5)", make_env()).is_err());
+}
+
+#[test]
+fn test_hour_between() {
+    let at = |t: &str, window: &str| eval_expr(&format!(r#"(hour-between? "{t}" {window})"#), make_env());
+    assert!(at("2026-03-06T09:00:00Z", "9 17").unwrap());
+    assert!(at("2026-03-06T16:59:00Z", "9 17").unwrap());
+    assert!(!at("2026-03-06T17:00:00Z", "9 17").unwrap());
+    assert!(!at("2026-03-06T08:59:00Z", "9 17").unwrap());
+    assert!(!at("2026-03-06T09:15:00Z", "9.5 17").unwrap());
+    // Local hour: 08:00 in Tokyo is 23:00 UTC the day before.
+    assert!(at("2026-03-06T08:00:00+09:00", "8 9").unwrap());
+    assert!(!at("2026-03-05T23:00:00Z", "8 9").unwrap());
+    // Overnight windows wrap.
+    assert!(at("2026-03-06T23:00:00Z", "22 6").unwrap());
+    assert!(at("2026-03-06T03:00:00Z", "22 6").unwrap());
+    assert!(!at("2026-03-06T12:00:00Z", "22 6").unwrap());
+    assert!(at("2026-03-06T12:00:00Z", "25 6").is_err());
+    assert!(at("2026-03-06T12:00:00Z", r#""9" 17"#).is_err());
+}
+
+#[test]
+fn test_during() {
+    let during = |t: &str| {
+        eval_expr(&format!(r#"(during "{t}" "2025-12-01" "2025-12-31")"#), make_env()).unwrap()
+    };
+    assert!(during("2025-12-01T00:00:00Z"));
+    assert!(during("2025-12-31T23:59:59Z"));
+    assert!(!during("2026-01-01T00:00:00Z"));
+    assert!(!during("2025-11-30T23:59:59Z"));
+    // Dates are local to the timestamp's offset.
+    assert!(during("2025-12-31T20:00:00-08:00"));
+    assert!(!during("2026-01-01T01:00:00+02:00"));
+
+    // RFC 3339 bounds compare instants, end exclusive.
+    let instant = |t: &str| {
+        eval_expr(
+            &format!(r#"(during "{t}" "2025-12-01T09:00:00Z" "2025-12-01T17:00:00Z")"#),
+            make_env(),
+        )
+        .unwrap()
+    };
+    assert!(instant("2025-12-01T10:00:00+01:00"));
+    assert!(!instant("2025-12-01T17:00:00Z"));
+    assert!(eval_expr(r#"(during now "2025-12-01" "bogus")"#, make_env()).is_err());
+}
+
 #[test]
 fn test_get() {
     assert!(eval_expr(
@@ -181,6 +375,80 @@ fn test_crypto_defaults_fail_closed() {
     assert!(!result.allow, "default crypto should be fail-closed");
 }
 
+#[test]
+fn test_unconfigured_crypto_strict() {
+    let strict = || Env { strict: true, ..Env::default() };
+    for src in ["(dpop_ok?)", "(thresh_ok?)", r#"(vrf_ok? "2025-10-01" 5)"#] {
+        let err = eval_expr(src, strict()).unwrap_err();
+        assert!(err.contains("callback is not configured"), "{src}: {err}");
+    }
+
+    // A configured callback answering false is a deny, not an error.
+    let mut env = Env { strict: true, ..Env::default() };
+    env.crypto.dpop_ok = CallbackState::Configured(Arc::new(|| false));
+    assert!(!verify(&parse("(dpop_ok?)").unwrap(), &env).unwrap().allow);
+    assert!(env.crypto.dpop_ok.is_configured() && !env.crypto.thresh_ok.is_configured());
+
+    // merkle_ok? falls back to the built-in proof check instead.
+    assert!(!eval_expr(r#"(merkle_ok? "leaf" proof "root")"#, strict()).unwrap());
+}
+
+#[test]
+fn test_allowed_ops() {
+    let mut env = make_env();
+    env.allowed_ops = Some(["and", "=", "get", "member", "tuple"].map(String::from).into());
+    assert!(eval_expr(r#"(and (= (get req "action") "payments.create") (in "x" (tuple "x")))"#, env.clone_for_request(env.req.clone())).unwrap());
+    let err = eval_expr(r#"(and (= (get req "action") "payments.create") (vrf_ok? "2025-10-01" 5))"#, env).unwrap_err();
+    assert_eq!(err, "operator not permitted: vrf_ok?");
+}
+
+#[test]
+fn test_hashed_claims() {
+    use agent_safe_spl::disclosure::{commit, commitment_salt, disclose, disclose_request};
+
+    let salt = "000102030405060708090a0b0c0d0e0f";
+    let commitment = disclose(&Node::Str("niece@example.com".into()), salt).unwrap();
+    assert_eq!(commitment_salt(&commitment).unwrap(), salt);
+    assert_eq!(commitment, disclose(&Node::Str("niece@example.com".into()), salt).unwrap());
+    assert_ne!(disclose(&Node::Int(5), salt).unwrap(), disclose(&Node::Number(5.0), salt).unwrap());
+    assert!(disclose(&Node::Int(5), "0001").is_err());
+    assert_ne!(commit(&Node::Int(5)), commit(&Node::Int(5)));
+
+    let src = format!(r#"(and (= (get req "action") "payments.create") (=hash (get req "recipient") "{commitment}"))"#);
+    let ast = parse(&src).unwrap();
+    let env = make_env();
+    // The verifier can check a clear value, or the agent's disclosed commitment.
+    assert!(verify(&ast, &env).unwrap().allow);
+    let disclosed = disclose_request(&ast, &env.req).unwrap();
+    assert_eq!(disclosed["recipient"], Node::Str(commitment.clone()));
+    assert_eq!(disclosed["action"], env.req["action"]);
+    assert!(verify(&ast, &env.clone_for_request(disclosed)).unwrap().allow);
+
+    let mut other = env.req.clone();
+    other.insert("recipient".into(), Node::Str("mallory@example.com".into()));
+    assert!(!verify(&ast, &env.clone_for_request(disclose_request(&ast, &other).unwrap())).unwrap().allow);
+    other.remove("recipient");
+    assert!(!verify(&ast, &env.clone_for_request(other)).unwrap().allow);
+
+    assert!(eval_expr(r#"(=hash (get req "recipient") "not-a-commitment")"#, make_env()).is_err());
+    let twice = format!(
+        r#"(or (=hash (get req "recipient") "{commitment}") (=hash (get req "recipient") "{}"))"#,
+        commit(&Node::Str("mom@example.com".into()))
+    );
+    assert!(disclose_request(&parse(&twice).unwrap(), &env.req).is_err());
+}
+
+#[test]
+fn test_action_in() {
+    let env = make_env();
+    assert!(eval_expr(r#"(action-in "payments.*")"#, env.clone_for_request(env.req.clone())).unwrap());
+    assert!(eval_expr(r#"(action-in "reads.*" "payments.create")"#, env.clone_for_request(env.req.clone())).unwrap());
+    assert!(!eval_expr(r#"(action-in "reads.*")"#, env.clone_for_request(env.req.clone())).unwrap());
+    assert!(eval_expr(r#"(action-in (tuple "reads.*" "*.create"))"#, env.clone_for_request(env.req.clone())).unwrap());
+    assert!(!eval_expr(r#"(action-in "*")"#, env.clone_for_request(HashMap::new())).unwrap());
+    assert!(eval_expr("(action-in 5)", env).is_err());
+}
+
 #[test]
 fn test_unknown_op() {
     assert!(eval_expr("(bogus 1 2)", make_env()).is_err());
@@ -202,6 +470,97 @@ fn test_gas_budget_sufficient() {
     assert!(eval_expr("(and #t #t)", env).unwrap());
 }
 
+#[test]
+fn test_list_aggregates() {
+    let mut env = make_env();
+    let item = |amount: Node| Node::Map([("amount".to_string(), amount)].into_iter().collect());
+    env.req.insert("items".into(), Node::List(vec![item(Node::Int(30)), item(Node::Number(45.5))]));
+    env.req.insert(
+        "charges".into(),
+        Node::List(vec![Node::Money { minor_units: 1999, currency: "USD".into() }; 3]),
+    );
+
+    assert!(eval_expr("(= (length allowed_recipients) 2)", env.clone()).unwrap());
+    assert!(eval_expr(r#"(= (length (get req "missing")) 0)"#, env.clone()).unwrap());
+    assert!(eval_expr(r#"(<= (sum (get req "items") "amount") 100)"#, env.clone()).unwrap());
+    assert!(eval_expr(r#"(= (sum (tuple 1 2 3)) 6)"#, env.clone()).unwrap());
+    assert!(eval_expr(r#"(= (sum (get req "charges")) (money "59.97" "USD"))"#, env.clone()).unwrap());
+    assert!(eval_expr(r#"(= (sum (tuple)) 0)"#, env.clone()).unwrap());
+    // No more than one itemized amount over 40.
+    let over_40 = r#"(<= (count-if (lambda (i) (> (get i "amount") 40)) (get req "items")) 1)"#;
+    assert!(eval_expr(over_40, env.clone()).unwrap());
+    // The parameter shadows a var of the same name.
+    assert!(eval_expr(r#"(= (count-if (lambda (now) (= now 2)) (tuple 1 2 2)) 2)"#, env.clone()).unwrap());
+
+    let err = |src: &str| eval_expr(src, env.clone()).unwrap_err();
+    assert_eq!(err(r#"(sum (tuple 1 "two"))"#), r#"sum expects numbers, got "two""#);
+    assert!(err(r#"(sum (tuple (money 1 "USD") (money 1 "EUR")))"#).contains("cannot add USD to EUR"));
+    assert!(err(r#"(sum (tuple (money 1 "USD") 1))"#).contains("cannot add USD money"));
+    assert_eq!(err(r#"(length (get req "amount"))"#), "length expects a list, got 50.0");
+    assert_eq!(err("(count-if (not #f) allowed_recipients)"), "count-if expects a (lambda (x) ...) predicate");
+    assert_eq!(err("(lambda (x) #t)"), "lambda is only valid as the predicate of count-if");
+}
+
+#[test]
+fn test_quantifiers() {
+    use agent_safe_spl::evaluator::eval_policy_detailed;
+
+    let mut env = make_env();
+    let item = |category: &str| Node::Map([("category".to_string(), Node::Str(category.into()))].into_iter().collect());
+    env.req.insert("items".into(), Node::List(vec![item("books"), item("toys")]));
+    env.vars.insert("allowed_categories".into(), Node::List(vec![Node::Str("books".into()), Node::Str("toys".into())]));
+
+    let every = r#"(all i (get req "items") (member (get i "category") allowed_categories))"#;
+    assert!(eval_expr(every, env.clone()).unwrap());
+    assert!(eval_expr(r#"(any r allowed_recipients (= r "mom@example.com"))"#, env.clone()).unwrap());
+    assert!(!eval_expr(r#"(any r allowed_recipients (= r "stranger@example.com"))"#, env.clone()).unwrap());
+    // Vacuous over an empty or missing list.
+    assert!(eval_expr(r#"(all i (get req "missing") #f)"#, env.clone()).unwrap());
+    assert!(!eval_expr("(any i (tuple) #t)", env.clone()).unwrap());
+
+    let mut toys = env.clone();
+    toys.req.insert("items".into(), Node::List(vec![item("books"), item("knives")]));
+    assert!(!eval_expr(every, toys).unwrap());
+
+    assert_eq!(eval_expr(r#"(all "i" (tuple 1) #t)"#, env.clone()).unwrap_err(), "all expects a symbol to bind");
+    assert_eq!(eval_expr("(any req (tuple 1) #t)", env.clone()).unwrap_err(), "any expects a symbol to bind");
+
+    // Gas scales with the list, so a long list cannot slip past max_gas.
+    let gas = |n: usize| {
+        let mut env = env.clone();
+        env.vars.insert("xs".into(), Node::List(vec![Node::Int(1); n]));
+        eval_policy_detailed(&parse("(all x xs (= x 1))").unwrap(), &env).map(|o| o.gas_used)
+    };
+    assert!(gas(20).unwrap() > gas(10).unwrap());
+    env.vars.insert("xs".into(), Node::List(vec![Node::Int(1); 4000]));
+    env.max_gas = 1000;
+    assert!(eval_expr("(all x xs (= x 1))", env).unwrap_err().contains("gas budget exceeded"));
+}
+
+#[test]
+fn test_size_limits() {
+    let deep = format!("{}#t{}", "(not (not ".repeat(34), "))".repeat(34));
+    assert!(eval_expr(&deep, make_env()).unwrap_err().contains("max nesting depth exceeded"));
+    let mut env = make_env();
+    env.limits.max_depth = 100;
+    assert!(eval_expr(&deep, env).unwrap());
+
+    let mut env = make_env();
+    env.limits = Limits { max_list_len: 1, max_string_len: 32, ..Limits::default() };
+    env.vars.insert("long".into(), Node::Str("x".repeat(33)));
+    // Vars and request fields are checked before evaluation, read or not.
+    assert!(eval_expr(r#"(= (get req "amount") 50)"#, env.clone()).unwrap_err().contains("exceeds maximum length"));
+    env.vars.remove("allowed_recipients");
+    assert!(eval_expr("(= long \"x\")", env.clone()).unwrap_err().contains("string exceeds maximum length of 32 bytes"));
+    env.vars.remove("long");
+    assert!(eval_expr(r#"(= (get req "amount") 50)"#, env.clone()).unwrap());
+    assert!(eval_expr("(member 1 (tuple 1 2))", env).unwrap_err().contains("list exceeds maximum length of 1"));
+
+    let tight = Limits { max_policy_bytes: 8, ..Limits::default() };
+    assert!(parse_with_limits("(and #t #f)", &tight).unwrap_err().0.contains("maximum size of 8 bytes"));
+    assert!(parse_with_limits("#t", &tight).is_ok());
+}
+
 // --- Integration tests ---
 
 #[test]
@@ -329,3 +688,770 @@ fn test_hash_chain() {
         assert_eq!(result, expected, "case: {}", case["name"]);
     }
 }
+
+// --- Range and limits tests ---
+
+#[test]
+fn test_in_range() {
+    assert!(eval_expr(r#"(in-range (get req "amount") 0 75)"#, make_env()).unwrap());
+    assert!(eval_expr(r#"(in-range (get req "amount") 50 50)"#, make_env()).unwrap());
+    assert!(!eval_expr(r#"(in-range (get req "amount") 0 49)"#, make_env()).unwrap());
+    assert!(eval_expr("(in-range 1 2)", make_env()).is_err());
+}
+
+#[test]
+fn test_limits_block() {
+    assert!(eval_expr("(limits (amount 0 75) (per_day 3))", make_env()).unwrap());
+    assert!(!eval_expr("(limits (amount 0 40))", make_env()).unwrap());
+    // Missing fields fail closed
+    assert!(!eval_expr("(limits (tip 0 10))", make_env()).unwrap());
+
+    let mut env = make_env();
+    env.usage_count = Arc::new(|key, day| {
+        assert_eq!(key.as_str(), "payments.create");
+        assert_eq!(day, "2025-09-29");
+        4
+    });
+    assert!(!eval_expr("(limits (amount 0 75) (per_day 3))", env).unwrap());
+
+    assert!(eval_expr("(limits (amount 75))", make_env()).is_err());
+}
+
+#[test]
+fn test_find_limits() {
+    use agent_safe_spl::limits::{find_limits, RangeLimit};
+
+    let ast = parse(r#"(and (= (get req "action") "pay") (limits (amount 0 75) (per_day 3)))"#).unwrap();
+    let limits = find_limits(&ast).unwrap().unwrap();
+    assert_eq!(
+        limits.ranges,
+        vec![RangeLimit { field: "amount".into(), min: 0.0, max: 75.0 }]
+    );
+    assert_eq!(limits.per_day, Some(3.0));
+    assert_eq!(limits.max_for("amount"), Some(75.0));
+
+    assert!(find_limits(&parse("(<= (get req \"amount\") 5)").unwrap()).unwrap().is_none());
+}
+
+// --- Limit matrix tests ---
+
+fn recipient_limits() -> Node {
+    let mut limits = std::collections::BTreeMap::new();
+    limits.insert("niece@example.com".to_string(), Node::Number(75.0));
+    limits.insert("mom@example.com".to_string(), Node::Number(150.0));
+    Node::Map(limits)
+}
+
+#[test]
+fn test_limit_for() {
+    let mut env = make_env();
+    env.vars.insert("recipient_limits".into(), recipient_limits());
+    assert!(eval_expr(
+        r#"(<= (get req "amount") (limit-for (get req "recipient") recipient_limits))"#,
+        env
+    ).unwrap());
+
+    let mut env = make_env();
+    env.vars.insert("recipient_limits".into(), recipient_limits());
+    env.req.insert("recipient".into(), Node::Str("stranger@example.com".into()));
+    assert!(!eval_expr(
+        r#"(<= (get req "amount") (limit-for (get req "recipient") recipient_limits))"#,
+        env
+    ).unwrap());
+
+    let mut env = make_env();
+    env.vars.insert("recipient_limits".into(), recipient_limits());
+    env.req.insert("recipient".into(), Node::Str("stranger@example.com".into()));
+    assert!(eval_expr(
+        r#"(<= (get req "amount") (limit-for (get req "recipient") recipient_limits 60))"#,
+        env
+    ).unwrap());
+}
+
+#[test]
+fn test_get_from_map() {
+    let mut env = make_env();
+    env.vars.insert("recipient_limits".into(), recipient_limits());
+    assert!(eval_expr(r#"(= (get recipient_limits "mom@example.com") 150)"#, env).unwrap());
+}
+
+#[test]
+fn test_remaining_for_uses_spend() {
+    let mut env = make_env();
+    env.vars.insert("recipient_limits".into(), recipient_limits());
+    env.spent_for = Arc::new(|key| if key == "niece@example.com" { 40.0 } else { 0.0 });
+    // 75 - 40 = 35 remaining, request is 50
+    assert!(!eval_expr(
+        r#"(<= (get req "amount") (remaining-for (get req "recipient") recipient_limits))"#,
+        env
+    ).unwrap());
+
+    let mut env = make_env();
+    env.vars.insert("recipient_limits".into(), recipient_limits());
+    env.spent_for = Arc::new(|_| 20.0);
+    assert!(eval_expr(
+        r#"(<= (get req "amount") (remaining-for (get req "recipient") recipient_limits))"#,
+        env
+    ).unwrap());
+}
+
+// --- Constant folding tests ---
+
+#[test]
+fn test_fold_constants() {
+    use agent_safe_spl::evaluator::{eval_policy, fold_constants};
+
+    assert_eq!(fold_constants(&parse("(<= 5 10)").unwrap()), Node::Bool(true));
+    assert_eq!(
+        fold_constants(&parse(r#"(and #f (get req "amount"))"#).unwrap()),
+        Node::Bool(false)
+    );
+    assert_eq!(
+        fold_constants(&parse(r#"(and (= "a" "a") (<= (get req "amount") 50))"#).unwrap()),
+        parse(r#"(and #t (<= (get req "amount") 50))"#).unwrap()
+    );
+    // Erroring subtrees are preserved so evaluation still reports them
+    assert_eq!(fold_constants(&parse("(not)").unwrap()), parse("(not)").unwrap());
+    // Limits entries are data and are never rewritten
+    let limits = parse(r#"(limits ("amount" 0 (<= 1 2)))"#).unwrap();
+    assert_eq!(fold_constants(&limits), limits);
+
+    let ast = parse(r#"(and (>= 10 1) (member (get req "recipient") allowed_recipients))"#).unwrap();
+    let folded = fold_constants(&ast);
+    let env = make_env();
+    assert_eq!(eval_policy(&ast, &env).unwrap(), eval_policy(&folded, &env).unwrap());
+}
+
+#[test]
+fn test_missing_argument_is_error() {
+    assert!(eval_expr("(not)", make_env()).is_err());
+    assert!(eval_expr("(= 1)", make_env()).is_err());
+}
+
+// --- Deny-list tests ---
+
+#[test]
+fn test_denylist_absent() {
+    use agent_safe_spl::denylist::{DenyListVersion, InMemoryDenyLists};
+
+    let mut lists = InMemoryDenyLists::new();
+    lists.load("merchants", "v42", ["evil.example.com"]);
+    let mut env = make_env();
+    env.denylists = Some(Arc::new(lists));
+
+    let ast = parse(r#"(denylist-absent? (get req "merchant") "merchants")"#).unwrap();
+    env.req.insert("merchant".into(), Node::Str("shop.example.com".into()));
+    let result = verify(&ast, &env).unwrap();
+    assert!(result.allow);
+    assert_eq!(
+        result.denylists,
+        vec![DenyListVersion { list: "merchants".into(), version: "v42".into() }]
+    );
+
+    env.req.insert("merchant".into(), Node::Str("evil.example.com".into()));
+    assert!(!verify(&ast, &env).unwrap().allow);
+}
+
+#[test]
+fn test_denylist_fails_closed() {
+    use agent_safe_spl::denylist::{DenyListProvider, DenyListStatus};
+
+    struct Stale;
+    impl DenyListProvider for Stale {
+        fn status(&self, _: &str) -> Option<DenyListStatus> {
+            Some(DenyListStatus { version: "v1".into(), age_secs: 7200 })
+        }
+        fn contains(&self, _: &str, _: &str) -> bool {
+            false
+        }
+    }
+
+    let src = r#"(denylist-absent? "shop.example.com" "merchants")"#;
+    // No provider configured
+    assert!(eval_expr(src, make_env()).is_err());
+
+    let mut env = make_env();
+    env.denylists = Some(Arc::new(Stale));
+    let err = eval_expr(src, env).unwrap_err();
+    assert!(err.contains("stale"), "{err}");
+
+    let mut env = make_env();
+    env.denylists = Some(Arc::new(Stale));
+    env.denylist_max_staleness_secs = 86_400;
+    assert!(eval_expr(src, env).unwrap());
+}
+
+// --- Gas schedule tests ---
+
+#[test]
+fn test_gas_used_reported() {
+    let env = make_env();
+    let ast = parse("(and #t #t)").unwrap();
+    assert_eq!(verify(&ast, &env).unwrap().gas_used, 3);
+}
+
+#[test]
+fn test_gas_schedule_charges_crypto_and_lists() {
+    let env = make_env();
+    let crypto = verify(&parse("(dpop_ok?)").unwrap(), &env).unwrap();
+    assert_eq!(crypto.gas_used, 1 + env.gas.crypto);
+
+    let member = verify(&parse(r#"(member "x" allowed_recipients)"#).unwrap(), &env).unwrap();
+    // 3 nodes + 2 list items scanned
+    assert_eq!(member.gas_used, 3 + 2 * env.gas.list_item);
+
+    let mut env = make_env();
+    env.gas.crypto = 500;
+    env.max_gas = 100;
+    let err = eval_expr("(dpop_ok?)", env).unwrap_err();
+    assert!(err.contains("gas budget exceeded"));
+}
+
+#[test]
+fn test_gas_schedule_charges_long_strings() {
+    let mut env = make_env();
+    let long = "a".repeat(4096);
+    env.vars.insert("long".into(), Node::Str(long.clone()));
+    let ast = parse(r#"(= long long)"#).unwrap();
+    let used = verify(&ast, &env).unwrap().gas_used;
+    // 3 nodes + 8 KiB compared at 16 gas per KiB
+    assert_eq!(used, 3 + 8 * env.gas.string_per_kb);
+}
+
+#[test]
+fn test_merkle_tree_matches_vectors() {
+    let vectors_path = Path::new("../../examples/crypto/merkle_vectors.json");
+    if !vectors_path.exists() {
+        return;
+    }
+    let data: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(vectors_path).unwrap()).unwrap();
+    let leaves: Vec<&str> = data["leaves"].as_array().unwrap().iter().map(|l| l.as_str().unwrap()).collect();
+    let tree = crypto::MerkleTree::build(&leaves).unwrap();
+    assert_eq!(tree.root_hex(), data["root"].as_str().unwrap());
+
+    for case in data["cases"].as_array().unwrap() {
+        if !case["expected"].as_bool().unwrap() {
+            continue;
+        }
+        let leaf = case["leaf"].as_str().unwrap();
+        let expected: Vec<crypto::MerkleProofStep> = case["proof"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| crypto::MerkleProofStep {
+                hash: p["hash"].as_str().unwrap().to_string(),
+                position: p["position"].as_str().unwrap().to_string(),
+            })
+            .collect();
+        assert_eq!(tree.proof_for(leaf).unwrap(), expected, "case: {}", case["name"]);
+    }
+}
+
+#[test]
+fn test_merkle_tree_odd_leaves_roundtrip() {
+    let leaves = ["a", "b", "c", "d", "e"];
+    let tree = crypto::MerkleTree::build(&leaves).unwrap();
+    let root = tree.root_hex();
+    for leaf in leaves {
+        let proof = tree.proof_for(leaf).unwrap();
+        assert!(crypto::verify_merkle_proof(leaf, &proof, &root), "leaf {leaf}");
+    }
+    assert!(tree.proof_for("z").is_none());
+    assert!(crypto::MerkleTree::build::<&str>(&[]).is_err());
+
+    let single = crypto::MerkleTree::build(&["only"]).unwrap();
+    assert_eq!(single.root_hex(), crypto::sha256_hex(b"only"));
+    assert!(single.proof_for("only").unwrap().is_empty());
+}
+
+#[test]
+fn test_hash_chain_generate_matches_vectors() {
+    let vectors_path = Path::new("../../examples/crypto/hashchain_vectors.json");
+    if !vectors_path.exists() {
+        return;
+    }
+    let data: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(vectors_path).unwrap()).unwrap();
+    let chain_length = data["chain_length"].as_u64().unwrap() as usize;
+    let chain = crypto::HashChain::generate(data["seed_hex"].as_str().unwrap(), chain_length).unwrap();
+    assert_eq!(chain.commitment(), data["commitment"].as_str().unwrap());
+    for (i, expected) in data["chain"].as_array().unwrap().iter().enumerate() {
+        assert_eq!(chain.receipt(i).unwrap().preimage, expected.as_str().unwrap());
+    }
+}
+
+#[test]
+fn test_hash_chain_receipts_verify() {
+    let chain = crypto::HashChain::generate_random(4).unwrap();
+    let receipts: Vec<_> = chain.preimages().collect();
+    assert_eq!(receipts.iter().map(|r| r.index).collect::<Vec<_>>(), vec![3, 2, 1, 0]);
+    for r in &receipts {
+        assert!(crypto::verify_hash_chain(&chain.commitment(), &r.preimage, r.index, chain.len()));
+    }
+    assert!(chain.receipt(5).is_none());
+    assert!(!crypto::verify_hash_chain(&chain.commitment(), &receipts[0].preimage, 9, chain.len()));
+    assert!(crypto::HashChain::generate("00", 0).is_err());
+}
+
+// --- per-day-count-self tests ---
+
+#[test]
+fn test_per_day_count_self() {
+    let mut env = make_env();
+    env.usage_count = Arc::new(|key, day| {
+        if key.as_str() == "payments.create" && day == "2025-09-29" { 2 } else { 0 }
+    });
+    assert!(eval_expr("(= (per-day-count-self) 2)", env).unwrap());
+
+    let mut env = make_env();
+    env.req.remove("day");
+    assert!(eval_expr("(<= (per-day-count-self) 1)", env).is_err());
+}
+
+#[test]
+fn test_usage_count_keys() {
+    use agent_safe_spl::types::CounterKey;
+
+    assert_eq!(CounterKey::new(&["payments.create", "niece@example.com"]).as_str(), "payments.create|niece@example.com");
+    assert_eq!(CounterKey::new(&["a|b", "c\\"]).as_str(), "a\\|b|c\\\\");
+    assert_ne!(CounterKey::new(&["a|b"]), CounterKey::new(&["a", "b"]));
+    assert_eq!(CounterKey::action("payments.create").as_str(), "payments.create");
+
+    let mut env = make_env();
+    env.usage_count = Arc::new(|key, day| match (key.as_str(), day) {
+        ("payments.create|niece@example.com", "2025-09-29") => 3,
+        ("payments.create", "2025-09-29") => 1,
+        _ => 0,
+    });
+    let per_recipient = r#"(< (usage-count (tuple (get req "action") (get req "recipient")) (get req "day")) 3)"#;
+    assert!(!eval_expr(per_recipient, env.clone_for_request(env.req.clone())).unwrap());
+    let mut other = env.req.clone();
+    other.insert("recipient".into(), Node::Str("mom@example.com".into()));
+    assert!(eval_expr(per_recipient, env.clone_for_request(other)).unwrap());
+    // per-day-count is usage-count with a one-part key.
+    assert!(eval_expr(r#"(= (usage-count (tuple "payments.create") "2025-09-29") (per-day-count "payments.create" "2025-09-29"))"#, env.clone_for_request(env.req.clone())).unwrap());
+    assert!(eval_expr(r#"(= (usage-count "payments.create" (get req "day")) 1)"#, env).unwrap());
+}
+
+#[test]
+fn test_now_from_clock() {
+    use agent_safe_spl::time::FixedClock;
+
+    let policy = r#"(and (before now "2026-04-08T00:00:01Z") (not (before now "2026-04-07T23:59:59Z")))"#;
+    let clocked = || Env { clock: Arc::new(FixedClock(1_775_606_400)), ..Env::default() };
+    assert!(eval_expr(policy, clocked()).unwrap());
+    assert!(eval_expr(policy, Env { strict: true, ..clocked() }).unwrap());
+
+    // A `now` var overrides the clock only outside strict mode.
+    let mut env = clocked();
+    env.vars.insert("now".into(), Node::Str("2020-01-01T00:00:00Z".into()));
+    assert!(!eval_expr(policy, env.clone_for_request(HashMap::new())).unwrap());
+    env.strict = true;
+    let err = eval_expr(policy, env).unwrap_err();
+    assert!(err.contains("evaluation clock"), "{err}");
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_markdown_report() {
+    use agent_safe_spl::analysis::to_markdown;
+
+    let policy = parse(
+        r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50)
+                (member (get req "recipient") allowed_recipients) (before now "2026-12-31T00:00:00Z") (dpop_ok?))"#,
+    )
+    .unwrap();
+    assert_eq!(
+        to_markdown(&policy),
+        "# Policy report\n\n## Requirements\n\n\
+         - action must be payments.create\n- amount must be ≤ 50\n\
+         - recipient must be one of: allowed_recipients\n- only before 2026-12-31T00:00:00Z\n- dpop_ok?() must hold\n\n\
+         ## Constraints\n\n| Field | Minimum | Maximum | Required value |\n|---|---|---|---|\n\
+         | `amount` |  | 50 |  |\n| `action` |  |  | `payments.create` |\n\n\
+         ## Referenced fields\n\n- Request fields: `action`, `amount`, `recipient`\n\
+         - Caller vars: `allowed_recipients`\n- Verifier vars: `now`\n\n\
+         ## Crypto requirements\n\n- `dpop_ok?`: the caller must prove possession of its key (DPoP)\n\n\
+         ## Expiry\n\nRequests are allowed only before 2026-12-31T00:00:00Z.\n"
+    );
+
+    let clause = parse(r#"(policy "open" (= (get req "note") "a|b"))"#).unwrap();
+    let report = to_markdown(&clause);
+    assert!(report.starts_with("# Policy report: open\n"));
+    assert!(report.contains("| `note` |  |  | `a\\|b` |\n"), "{report}");
+    assert!(report.contains("## Crypto requirements\n\nNone.\n"));
+    assert!(report.ends_with("The policy sets no expiry; the token's `expires` field governs.\n"));
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_lint_per_day_count_action_mismatch() {
+    use agent_safe_spl::analysis::lint;
+
+    let ok = parse(r#"(and (= (get req "action") "payments.create") (<= (per-day-count "payments.create" (get req "day")) 1))"#).unwrap();
+    assert!(lint(&ok).is_empty());
+
+    let typo = parse(r#"(and (= (get req "action") "payments.create") (<= (per-day-count "payment.create" (get req "day")) 1))"#).unwrap();
+    let warnings = lint(&typo);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, "per-day-count-action-mismatch");
+
+    let self_form = parse(r#"(and (= (get req "action") "payments.create") (<= (per-day-count-self) 1))"#).unwrap();
+    assert!(lint(&self_form).is_empty());
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_complexity_score() {
+    use agent_safe_spl::analysis::{score, ExposureBound};
+
+    let trivial = score(&parse(r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50))"#).unwrap());
+    assert!(trivial.is_trivial());
+    assert_eq!(trivial.actions, vec!["payments.create"]);
+    assert_eq!(trivial.exposure, vec![ExposureBound { field: "amount".into(), max: 50.0, currency: None }]);
+    assert_eq!(trivial.score, 0);
+
+    // Each branch caps the amount, so the looser cap is the exposure.
+    let branched = score(&parse(r#"(or
+        (and (= (get req "action") "a") (<= (get req "amount") (money "19.99" "USD")))
+        (and (= (get req "action") "b") (in-range (get req "amount") 0 (money 100 "USD"))))"#).unwrap());
+    assert_eq!(branched.branches, 1);
+    assert_eq!(branched.actions, vec!["a", "b"]);
+    assert_eq!(
+        branched.exposure,
+        vec![ExposureBound { field: "amount".into(), max: 100.0, currency: Some("USD".into()) }]
+    );
+    assert!(branched.unbounded.is_empty());
+    assert!(!branched.is_trivial());
+
+    // One branch leaves the amount open; custom and host-delegated ops are flagged.
+    let risky = score(&parse(r#"(and
+        (member (get req "action") (tuple "x" "y"))
+        (or (<= (get req "amount") 10) (>= (get req "amount") 1000))
+        (vrf_ok? (get req "nonce") 5)
+        (custom-check (get req "recipient")))"#).unwrap());
+    assert_eq!(risky.actions, vec!["x", "y"]);
+    assert!(risky.exposure.is_empty());
+    assert_eq!(risky.unbounded, vec!["amount"]);
+    assert_eq!(risky.escape_hatches, vec!["custom-check", "vrf_ok?"]);
+    assert_eq!(risky.nodes / 25, 1);
+    assert_eq!(risky.score, 2 + 1 + 5 + 10 + 1);
+
+    // A limits block caps fields too.
+    let limited = score(&parse(r#"(and (limits (amount 0 25)) (> (get req "amount") 1))"#).unwrap());
+    assert_eq!(limited.exposure[0].max, 25.0);
+    assert!(limited.unbounded.is_empty());
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_referenced_fields() {
+    use agent_safe_spl::analysis::referenced_fields;
+
+    let policy = parse(r#"(and
+        (= (get req "action") "payments.create")
+        (<= (get req "amount") max_amount)
+        (member (get req "recipient") allowed)
+        (limits (amount 0 500) (per_day 3))
+        (<= (cumulative-spend "payments.create" "month") 1000)
+        (vrf_ok? (get req "day") (get req "amount"))
+        (dpop_ok?)
+        (get config (get req "region"))
+        (<= (count-if (lambda (i) (> (get i "amount") limit)) (get req "items")) 1)
+        (all t (get req "tags") (member t tags))
+        #t)"#).unwrap();
+    let fields = referenced_fields(&policy);
+    assert_eq!(fields.req_keys, ["action", "amount", "day", "items", "recipient", "region", "tags"]);
+    assert_eq!(fields.vars, ["allowed", "config", "limit", "max_amount", "tags"]);
+    assert_eq!(fields.ops_used, ["dpop_ok?", "vrf_ok?"]);
+
+    assert_eq!(referenced_fields(&parse("#t").unwrap()), Default::default());
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_implies() {
+    use agent_safe_spl::analysis::{implies, Implication};
+    use agent_safe_spl::evaluator::eval_policy;
+
+    let parent = parse(r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 100))"#).unwrap();
+    let child = parse(r#"(and
+        (= (get req "action") "payments.create")
+        (in-range (get req "amount") 1 50)
+        (member (get req "recipient") (tuple "niece@example.com" "mom@example.com")))"#).unwrap();
+    assert_eq!(implies(&child, &parent), Implication::Holds);
+
+    let Implication::Refuted(req) = implies(&parent, &child) else { panic!("parent is broader") };
+    let env = Env { req, ..Env::default() };
+    assert!(eval_policy(&parent, &env).unwrap().is_truthy());
+    assert!(!eval_policy(&child, &env).unwrap().is_truthy());
+
+    // Boundaries are cases of their own.
+    let lt = parse(r#"(< (get req "amount") 50)"#).unwrap();
+    let le = parse(r#"(<= (get req "amount") 50)"#).unwrap();
+    assert_eq!(implies(&lt, &le), Implication::Holds);
+    let Implication::Refuted(req) = implies(&le, &lt) else { panic!("50 is allowed by only one") };
+    assert_eq!(req["amount"], Node::Int(50));
+    let limited = parse(r#"(limits (amount 0 50))"#).unwrap();
+    assert_eq!(implies(&limited, &le), Implication::Holds);
+
+    // What the search cannot enumerate leaves the answer open.
+    let with_vars = parse(r#"(member (get req "recipient") allowed_recipients)"#).unwrap();
+    assert!(matches!(implies(&with_vars, &parent), Implication::Unknown(_)));
+    let with_host = parse(r#"(and (dpop_ok?) (<= (get req "amount") 100))"#).unwrap();
+    assert!(matches!(implies(&with_host, &parent), Implication::Unknown(_)));
+    let wide = parse(r#"(and (= (get req "a") 1) (= (get req "b") 1) (= (get req "c") 1) (= (get req "d") 1) (= (get req "e") 1))"#).unwrap();
+    assert!(matches!(implies(&wide, &wide), Implication::Unknown(_)));
+}
+
+#[test]
+fn test_partial_eval_specializes_policy() {
+    use agent_safe_spl::evaluator::{eval_policy, partial_eval, KnownBindings};
+
+    let org = parse(r#"(and
+        (= (get req "action") "payments.create")
+        (member (get req "recipient") allowed_recipients)
+        (<= (get req "amount") (get caps (get req "recipient")))
+        (or (= (get req "purpose") "giftcard") (= (get req "purpose") "refund")))"#).unwrap();
+    let mut known = KnownBindings::default();
+    known.req.insert("recipient".into(), Node::Str("niece@example.com".into()));
+    known.vars.insert("allowed_recipients".into(), make_env().vars["allowed_recipients"].clone());
+    known.vars.insert(
+        "caps".into(),
+        Node::Map([("niece@example.com".to_string(), Node::Int(75)), ("mom@example.com".to_string(), Node::Int(500))].into()),
+    );
+
+    let residual = partial_eval(&org, &known);
+    assert_eq!(
+        residual,
+        parse(r#"(and
+            (= (get req "recipient") "niece@example.com")
+            (= (get req "action") "payments.create")
+            (<= (get req "amount") 75)
+            (or (= (get req "purpose") "giftcard") (= (get req "purpose") "refund")))"#).unwrap()
+    );
+    // The residual reads back from its printed form, so it can be minted.
+    assert_eq!(parse(&residual.to_string()).unwrap(), residual);
+
+    // A count-if parameter shadows a known var of the same name.
+    let shadowed = parse(r#"(count-if (lambda (caps) (= caps 1)) (get req "tags"))"#).unwrap();
+    assert_eq!(partial_eval(&shadowed, &known), shadowed);
+
+    let mut env = make_env();
+    env.vars.insert("caps".into(), known.vars["caps"].clone());
+    for (field, value) in [("amount", Node::Number(50.0)), ("amount", Node::Number(80.0)), ("purpose", Node::Str("rent".into()))] {
+        let mut env = make_env();
+        env.vars.insert("caps".into(), known.vars["caps"].clone());
+        env.req.insert(field.into(), value);
+        assert_eq!(eval_policy(&org, &env).unwrap(), eval_policy(&residual, &env).unwrap());
+    }
+    // Another recipient the org policy allows is outside the specialization.
+    env.req.insert("recipient".into(), Node::Str("mom@example.com".into()));
+    assert_eq!(eval_policy(&org, &env).unwrap(), Node::Bool(true));
+    assert_eq!(eval_policy(&residual, &env).unwrap(), Node::Bool(false));
+
+    // Decided policies collapse; unknown fields are left alone.
+    known.req.insert("purpose".into(), Node::Str("rent".into()));
+    let gift = parse(r#"(and (= (get req "purpose") "giftcard") (<= (get req "amount") 10))"#).unwrap();
+    assert_eq!(partial_eval(&gift, &known), Node::Bool(false));
+    let open = parse(r#"(or (= (get req "purpose") "rent") (<= (get req "amount") 10))"#).unwrap();
+    assert_eq!(partial_eval(&open, &known), parse(r#"(= (get req "purpose") "rent")"#).unwrap());
+    assert_eq!(partial_eval(&open, &KnownBindings::default()), open);
+}
+
+// --- merkle_ok? built-in tests ---
+
+fn proof_node(proof: &[crypto::MerkleProofStep]) -> Node {
+    Node::List(
+        proof
+            .iter()
+            .map(|s| Node::List(vec![Node::Str(s.hash.clone()), Node::Str(s.position.clone())]))
+            .collect(),
+    )
+}
+
+fn make_env_without_merkle() -> Env {
+    let mut env = make_env();
+    env.crypto.merkle_ok = None;
+    env
+}
+
+#[test]
+fn test_merkle_ok_builtin() {
+    let tree = crypto::MerkleTree::build(&["mom@example.com", "niece@example.com", "dad@example.com"]).unwrap();
+    let proof = tree.proof_for("niece@example.com").unwrap();
+
+    let mut env = make_env_without_merkle();
+    env.vars.insert("root".into(), Node::Str(tree.root_hex()));
+    env.req.insert("recipient_proof".into(), proof_node(&proof));
+    let src = r#"(merkle_ok? (get req "recipient") recipient_proof root)"#;
+    assert!(eval_expr(src, env).unwrap());
+
+    // Map-shaped steps from vars work too; a different leaf fails.
+    let mut env = make_env_without_merkle();
+    env.vars.insert("root".into(), Node::Str(tree.root_hex()));
+    let steps = proof
+        .iter()
+        .map(|s| {
+            Node::Map(
+                [("hash".to_string(), Node::Str(s.hash.clone())), ("position".to_string(), Node::Str(s.position.clone()))]
+                    .into_iter()
+                    .collect(),
+            )
+        })
+        .collect();
+    env.vars.insert("recipient_proof".into(), Node::List(steps));
+    assert!(!eval_expr(r#"(merkle_ok? "mom@example.com" recipient_proof root)"#, env).unwrap());
+}
+
+#[test]
+fn test_constant_time_equality() {
+    assert!(crypto::ct_eq(b"nonce-1", b"nonce-1"));
+    assert!(!crypto::ct_eq(b"nonce-1", b"nonce-2"));
+    assert!(!crypto::ct_eq(b"nonce", b"nonce-1"));
+    assert!(crypto::ct_eq_hex("00ff", "00FF"));
+    assert!(!crypto::ct_eq_hex("00ff", "00fe"));
+    assert!(!crypto::ct_eq_hex("00ff", "00ff00"));
+    // Non-hex strings never match, even themselves.
+    assert!(!crypto::ct_eq_hex("zz", "zz"));
+    assert!(!crypto::ct_eq_hex("0g", "0G"));
+
+    let chain = crypto::HashChain::generate(&"ab".repeat(32), 3).unwrap();
+    let receipt = chain.receipt(1).unwrap();
+    assert!(crypto::verify_hash_chain(&chain.commitment().to_uppercase(), &receipt.preimage, receipt.index, 3));
+    assert!(!crypto::verify_hash_chain(&chain.commitment(), &receipt.preimage, receipt.index + 1, 3));
+}
+
+#[test]
+fn test_merkle_ok_fails_closed() {
+    let mut env = make_env_without_merkle();
+    env.vars.insert("bad_proof".into(), Node::List(vec![Node::Str("not-a-step".into())]));
+    assert!(!eval_expr(r#"(merkle_ok? "x" missing_proof "00")"#, make_env_without_merkle()).unwrap());
+    assert!(!eval_expr(r#"(merkle_ok? "x" bad_proof "00")"#, env).unwrap());
+    // The legacy one-argument form needs a host override.
+    assert!(!eval_expr(r#"(merkle_ok? (tuple "a" "b"))"#, make_env_without_merkle()).unwrap());
+    assert!(eval_expr(r#"(merkle_ok? (tuple "a" "b"))"#, make_env()).unwrap());
+}
+
+#[test]
+fn test_node_json_round_trip() {
+    use agent_safe_spl::types::map_from_json;
+
+    let json = serde_json::json!({
+        "action": "payments.create",
+        "amount": 50,
+        "attested": true,
+        "tags": ["a", 1.5, null],
+        "limits": { "niece@example.com": 75 }
+    });
+    let req = map_from_json(&json).unwrap();
+    assert_eq!(req["amount"], Node::Int(50));
+    assert_eq!(req["tags"], Node::List(vec![Node::Str("a".into()), Node::Number(1.5), Node::Nil]));
+    let Node::Map(limits) = &req["limits"] else { panic!("expected map") };
+    assert_eq!(limits["niece@example.com"], Node::Int(75));
+    assert!(map_from_json(&serde_json::json!([1])).is_err());
+
+    // serde impls agree with the Value helpers.
+    let node: Node = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(node, Node::from_json_value(&json));
+    assert_eq!(serde_json::to_value(&node).unwrap(), node.to_json_value());
+    assert_eq!(node.to_json_value()["amount"], serde_json::json!(50));
+
+    // Integers round-trip losslessly; past i64 they fall back to Number.
+    let big: Node = serde_json::from_str("9007199254740993").unwrap();
+    assert_eq!(big, Node::Int(9_007_199_254_740_993));
+    assert_eq!(serde_json::to_string(&big).unwrap(), "9007199254740993");
+    assert_eq!(serde_json::from_str::<Node>("18446744073709551615").unwrap(), Node::Number(u64::MAX as f64));
+
+    let vars: HashMap<String, Node> = serde_json::from_str(r#"{"allowed": ["mom@example.com"]}"#).unwrap();
+    assert_eq!(vars["allowed"], Node::List(vec![Node::Str("mom@example.com".into())]));
+
+    assert_eq!(Node::Symbol("now".into()).to_json_value(), serde_json::json!("now"));
+    assert_eq!(Node::Number(f64::NAN).to_json_value(), serde_json::Value::Null);
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_lint_rules() {
+    use agent_safe_spl::analysis::{lint, lint_with, LintConfig, LintRule};
+
+    let codes = |src: &str, config: &LintConfig| -> Vec<String> {
+        lint_with(&parse(src).unwrap(), config).into_iter().map(|w| w.code).collect()
+    };
+    let defaults = LintConfig::default();
+
+    // Unbound symbols compare as their own name.
+    let typo = r#"(= (get req "role") admin_role)"#;
+    assert_eq!(codes(typo, &defaults), ["unknown-symbol"]);
+    let known = LintConfig { known_vars: Some(["admin_role".to_string()].into()), ..LintConfig::default() };
+    assert!(codes(typo, &known).is_empty());
+    let quantified = r#"(all x (get req "items") (= (get req "owner") x))"#;
+    assert!(codes(quantified, &defaults).is_empty());
+    assert_eq!(codes(r#"(<= (get req "amount") limit)"#, &known), ["unknown-symbol"]);
+    assert!(codes(r#"(before now "2027-01-01T00:00:00Z")"#, &known).is_empty());
+
+    // An alternative that drops the amount cap.
+    let bypass = r#"(or (<= (get req "amount") 100) (= (get req "recipient") "mom"))"#;
+    assert_eq!(codes(bypass, &defaults), ["or-bypasses-limit"]);
+    let capped = r#"(and (<= (get req "amount") 500) (or (<= (get req "amount") 100) (= (get req "recipient") "mom")))"#;
+    assert!(codes(capped, &defaults).is_empty());
+    let explained = r#"(or (<= (get req "amount") 100) (deny-with "AMOUNT_TOO_HIGH"))"#;
+    assert!(lint(&parse(explained).unwrap()).is_empty());
+
+    // Expiry is opt-in.
+    let timeless = r#"(= (get req "action") "read")"#;
+    assert!(codes(timeless, &defaults).is_empty());
+    let strict = LintConfig::default().enable(LintRule::MissingExpiry);
+    assert_eq!(codes(timeless, &strict), ["missing-expiry"]);
+    assert!(codes(r#"(and (before now "2027-01-01T00:00:00Z") (= (get req "action") "read"))"#, &strict).is_empty());
+
+    assert_eq!(codes(r#"(and #f (= (get req "action") "read"))"#, &defaults), ["unreachable"]);
+    assert_eq!(codes(r#"(or #t (= (get req "action") "read"))"#, &defaults), ["unreachable"]);
+    let contradiction = r#"(and (= (get req "action") "read") (= "write" (get req "action")))"#;
+    assert_eq!(codes(contradiction, &defaults), ["unreachable"]);
+    assert_eq!(codes(r#"(policy "payments" (deny-with "CLOSED"))"#, &defaults), ["unreachable"]);
+
+    let deprecated = lint(&parse(r#"(in (get req "recipient") (tuple "a" "b"))"#).unwrap());
+    assert_eq!(deprecated.len(), 1);
+    assert_eq!(deprecated[0].message, "`in` is deprecated; use `member`");
+    assert!(codes(r#"(in (get req "recipient") (tuple "a"))"#, &defaults.clone().disable(LintRule::DeprecatedOp)).is_empty());
+
+    for rule in LintRule::ALL {
+        assert_eq!(LintRule::from_code(rule.code()), Some(rule));
+    }
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_describe_policy() {
+    use agent_safe_spl::analysis::{describe, PolicyDescription};
+    use agent_safe_spl::parser::parse_all;
+
+    let src = r#"(and (= (get req "action") "gift")
+                      (<= (get req "amount") 50)
+                      (member (get req "recipient") (tuple "niece@example.com" "nephew@example.com"))
+                      (not (= (get req "category") "gambling"))
+                      (> 10 (per-day-count-self))
+                      (or (before now "2026-12-31") (deny-with "EXPIRED"))
+                      (limits (per_day 3))
+                      (obligate "send_receipt" 3600))"#;
+    assert_eq!(
+        describe(&parse(src).unwrap()),
+        "- action must be gift\n\
+         - amount must be ≤ 50\n\
+         - recipient must be one of: niece@example.com, nephew@example.com\n\
+         - category must not be gambling\n\
+         - the number of earlier requests for this action today must be < 10\n\
+         - only before 2026-12-31\n\
+         - at most 3 requests per day\n\
+         - afterwards, the service must send_receipt within 3600 seconds\n"
+    );
+    assert_eq!(describe(&parse("#t").unwrap()), "- any request is allowed\n");
+    assert_eq!(describe(&parse(r#"(<= (get req "amount") (money 12.5 "USD"))"#).unwrap()), "- amount must be ≤ 12.50 USD\n");
+
+    let clauses = parse_all(r#"(policy "read" #t) (policy "pay" (in-range (get req "amount") 1 100))"#).unwrap();
+    let description = PolicyDescription::of(&clauses).unwrap();
+    assert_eq!(description.clauses[1].name.as_deref(), Some("pay"));
+    assert_eq!(description.clauses[1].requirements, ["amount must be between 1 and 100"]);
+    assert_eq!(description.to_string(), "read:\n  - any request is allowed\n\npay:\n  - amount must be between 1 and 100\n");
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use agent_safe_spl::did::{base58_decode, base58_encode, did_key, did_key_public_key, did_web_url, DidKeyResolver, Resolver};
+use agent_safe_spl::issuers::{TrustedIssuers, TrustedKey};
+use agent_safe_spl::keys::{InMemoryKeyStore, KeyStore};
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::time::{format_rfc3339, FixedClock};
+use agent_safe_spl::token::{
+    envelope_payload, generate_keypair, mint, mint_with_key_store, verify_token_untrusted, verify_token, MintOptions, Token,
+    VerifyErrorCode,
+};
+use agent_safe_spl::signature::SignatureScheme;
+use agent_safe_spl::types::{Node, SplError};
+
+const POLICY: &str = r#"(= (get req "action") "read")"#;
+// 2026-10-01T00:00:00Z
+const ROTATED_AT: i64 = 1_790_812_800;
+
+fn read_req() -> HashMap<String, Node> {
+    HashMap::from([("action".to_string(), Node::Str("read".into()))])
+}
+
+fn minted(private_key: &str, issued_at: Option<i64>, kid: Option<&str>) -> Token {
+    let opts = MintOptions {
+        issued_at: issued_at.map(format_rfc3339),
+        kid: kid.map(Into::into),
+        ..MintOptions::default()
+    };
+    mint(POLICY, private_key, opts).unwrap()
+}
+
+#[test]
+fn test_untrusted_issuers_are_rejected() {
+    let (trusted_pub, trusted_priv) = generate_keypair();
+    let (_, attacker_priv) = generate_keypair();
+    let issuers = TrustedIssuers::new().with_key(TrustedKey::new(&trusted_pub));
+
+    assert!(verify_token(&minted(&trusted_priv, None, None), read_req(), HashMap::new(), &issuers).allow);
+
+    // A forged token verifies against its own key, but that key is not trusted.
+    let forged = minted(&attacker_priv, None, None);
+    let result = verify_token(&forged, read_req(), HashMap::new(), &issuers);
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+
+    // Naming a trusted kid does not help a token signed by another key.
+    let kid = issuers.keys()[0].kid.clone();
+    let borrowed = minted(&attacker_priv, None, Some(&kid));
+    let result = verify_token(&borrowed, read_req(), HashMap::new(), &issuers);
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+    assert!(result.error.unwrap().contains("does not match"));
+    let unknown = minted(&trusted_priv, None, Some("no-such-key"));
+    let result = verify_token(&unknown, read_req(), HashMap::new(), &issuers);
+    assert_eq!(result.error.as_deref(), Some("unknown issuer key id: no-such-key"));
+}
+
+#[test]
+fn test_rotation_overlaps_old_and_new_keys() {
+    let (old_pub, old_priv) = generate_keypair();
+    let (new_pub, new_priv) = generate_keypair();
+    let issuers = TrustedIssuers::new()
+        .with_key(TrustedKey::new(&old_pub).kid("2026-q3").valid(None, Some(ROTATED_AT)))
+        .with_key(TrustedKey::new(&new_pub).kid("2026-q4").valid(Some(ROTATED_AT - 3600), None));
+    let verifier = Verifier::default()
+        .with_clock(FixedClock(ROTATED_AT + 86_400))
+        .with_trusted_issuers(Arc::new(issuers));
+    let check = |token: &Token| verifier.verify(token, read_req(), HashMap::new(), None);
+
+    // Tokens minted before the cutover still verify after it; both keys
+    // sign during the overlap.
+    assert!(check(&minted(&old_priv, Some(ROTATED_AT - 86_400), Some("2026-q3"))).allow);
+    assert!(check(&minted(&old_priv, Some(ROTATED_AT - 60), Some("2026-q3"))).allow);
+    assert!(check(&minted(&new_priv, Some(ROTATED_AT - 60), Some("2026-q4"))).allow);
+    assert!(check(&minted(&new_priv, Some(ROTATED_AT + 60), None)).allow);
+
+    // The retired key cannot mint after its window, nor can the new key before its own.
+    let late = check(&minted(&old_priv, Some(ROTATED_AT + 60), Some("2026-q3")));
+    assert_eq!(late.code, Some(VerifyErrorCode::UntrustedIssuer));
+    let early = check(&minted(&new_priv, Some(ROTATED_AT - 7200), Some("2026-q4")));
+    assert_eq!(early.code, Some(VerifyErrorCode::UntrustedIssuer));
+    // Without issued_at the token is judged at verification time.
+    let undated = check(&minted(&old_priv, None, Some("2026-q3")));
+    assert_eq!(undated.code, Some(VerifyErrorCode::UntrustedIssuer));
+}
+
+#[test]
+fn test_trusted_issuers_from_key_store() {
+    let store = InMemoryKeyStore::new();
+    let first = store.rotate().unwrap();
+    let opts = || MintOptions { issued_at: Some(format_rfc3339(first.created_at)), ..MintOptions::default() };
+    let token = mint_with_key_store(POLICY, &store, opts()).unwrap();
+    assert_eq!(token.kid.as_deref(), Some(first.id.as_str()));
+
+    let second = store.rotate().unwrap();
+    let rotated = mint_with_key_store(POLICY, &store, opts()).unwrap();
+    assert_eq!(rotated.kid.as_deref(), Some(second.id.as_str()));
+
+    let issuers = TrustedIssuers::from_key_store(&store).unwrap();
+    assert_eq!(issuers.keys().len(), 2);
+    assert!(verify_token(&token, read_req(), HashMap::new(), &issuers).allow);
+
+    // The kid is signed: relabelling the token breaks it.
+    let mut relabelled = token.clone();
+    relabelled.kid = Some(second.id.clone());
+    let result = verify_token(&relabelled, read_req(), HashMap::new(), &issuers);
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+    relabelled.public_key = second.public_key.clone();
+    let result = verify_token(&relabelled, read_req(), HashMap::new(), &issuers);
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidSignature));
+
+    let mut trimmed = issuers.clone();
+    assert!(trimmed.remove(&first.id).is_some());
+    let result = verify_token(&token, read_req(), HashMap::new(), &trimmed);
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+}
+
+#[test]
+fn test_did_key_encoding() {
+    // From the did:key method specification.
+    let did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+    let public_key = "3b6a27bcceb6a42d62a3a8d02a6f0d73653215771de243a63ac048a18b59da29";
+    assert_eq!(did_key(public_key).unwrap(), did);
+    assert_eq!(did_key_public_key(did).unwrap(), public_key);
+    assert_eq!(did_key_public_key(&format!("{did}#z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp")).unwrap(), public_key);
+    assert_eq!(DidKeyResolver.resolve(did).unwrap(), public_key);
+
+    assert_eq!(base58_encode(&[0, 0, 1, 2]), "115T");
+    assert_eq!(base58_decode("115T").unwrap(), vec![0, 0, 1, 2]);
+    assert!(base58_decode("0OIl").is_err());
+    // An X25519 key (multicodec 0xec) is not an issuer key.
+    assert!(did_key_public_key("did:key:z6LSeu9HkTHSfLLeUs2nnzUSNedgDUevfNQgQjQC23ZCit6F").is_err());
+    assert!(did_key_public_key("did:web:example.com").is_err());
+    // Oversized keys are refused before the quadratic base58 decode.
+    assert!(did_key_public_key(&format!("did:key:z6Mk{}", "z".repeat(100_000))).is_err());
+
+    assert_eq!(did_web_url("did:web:example.com").unwrap(), "https://example.com/.well-known/did.json");
+    assert_eq!(did_web_url("did:web:localhost%3A8443:agents:7").unwrap(), "https://localhost:8443/agents/7/did.json");
+}
+
+/// A `did:web` resolver backed by documents fetched ahead of time.
+struct StaticResolver(HashMap<String, String>);
+
+impl Resolver for StaticResolver {
+    fn resolve(&self, did: &str) -> Result<String, SplError> {
+        self.0.get(did).cloned().ok_or_else(|| SplError(format!("cannot resolve {did}")))
+    }
+}
+
+#[test]
+fn test_did_issuers() {
+    let (public_key, private_key) = generate_keypair();
+    let did = did_key(&public_key).unwrap();
+    let opts = || MintOptions { issuer: Some(did.clone()), ..MintOptions::default() };
+    let token = mint(POLICY, &private_key, opts()).unwrap();
+    assert!(verify_token_untrusted(&token, read_req(), HashMap::new()).allow);
+
+    // A DID in the trust set admits tokens naming it as issuer.
+    let issuers = TrustedIssuers::new().with_key(TrustedKey::from_did(&did, &DidKeyResolver).unwrap());
+    assert!(verify_token(&token, read_req(), HashMap::new(), &issuers).allow);
+    let (other_public, other_private) = generate_keypair();
+    let other_did = did_key(&other_public).unwrap();
+    let other = mint(POLICY, &other_private, MintOptions { issuer: Some(other_did), ..MintOptions::default() }).unwrap();
+    let result = verify_token(&other, read_req(), HashMap::new(), &issuers);
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+
+    let web = "did:web:issuer.example.com";
+    let resolver = StaticResolver(HashMap::from([(web.to_string(), public_key.clone())]));
+    let issuers = TrustedIssuers::new().with_key(TrustedKey::from_did(web, &resolver).unwrap());
+    let token = mint(POLICY, &private_key, MintOptions { issuer: Some(web.into()), ..MintOptions::default() }).unwrap();
+    assert!(verify_token(&token, read_req(), HashMap::new(), &issuers).allow);
+    assert!(TrustedKey::from_did("did:web:unknown.example.com", &resolver).is_err());
+
+    // A did:key issuer is the signing key: minting under another is refused,
+    // and a token signed over a mismatched DID is rejected.
+    assert!(mint(POLICY, &other_private, opts()).is_err());
+    let mut forged = mint(POLICY, &other_private, MintOptions::default()).unwrap();
+    forged.issuer = Some(did.clone());
+    forged.signature = SignatureScheme::Ed25519.sign(&other_private, &envelope_payload(&forged)).unwrap();
+    let result = verify_token_untrusted(&forged, read_req(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+    assert_eq!(result.error.as_deref(), Some("issuer DID does not match the signing key"));
+
+    assert!(mint(POLICY, &private_key, MintOptions { issuer: Some("alice".into()), ..MintOptions::default() }).is_err());
+}
+
+#[cfg(feature = "jws")]
+#[test]
+fn test_did_document_key() {
+    use agent_safe_spl::did::document_key;
+
+    let did = "did:web:issuer.example.com";
+    let multibase = "z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+    let document = serde_json::json!({
+        "id": did,
+        "verificationMethod": [
+            { "id": format!("{did}#enc"), "type": "X25519KeyAgreementKey2020", "publicKeyMultibase": "z6LSeu9HkTHSfLLeUs2nnzUSNedgDUevfNQgQjQC23ZCit6F" },
+            { "id": format!("{did}#sig"), "type": "Ed25519VerificationKey2020", "publicKeyMultibase": multibase },
+        ],
+        "assertionMethod": [format!("{did}#sig")],
+    });
+    let public_key = "3b6a27bcceb6a42d62a3a8d02a6f0d73653215771de243a63ac048a18b59da29";
+    assert_eq!(document_key(&document, did).unwrap(), public_key);
+    assert!(document_key(&document, "did:web:other.example.com").is_err());
+
+    let jwk = serde_json::json!({
+        "id": did,
+        "verificationMethod": [{ "id": format!("{did}#sig"), "publicKeyJwk": {
+            "kty": "OKP", "crv": "Ed25519", "x": "O2onvM62pC1io6jQKm8Nc2UyFXcd4kOmOsBIoYtZ2ik",
+        } }],
+    });
+    assert_eq!(document_key(&jwk, did).unwrap(), public_key);
+}
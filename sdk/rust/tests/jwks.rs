@@ -0,0 +1,142 @@
+#![cfg(feature = "jwks")]
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use agent_safe_spl::issuers::IssuerKeys;
+use agent_safe_spl::jwks::{load_jwks_file, parse_jwks, JwksFile, JwksKeySet};
+use agent_safe_spl::jws::base64url_encode;
+use agent_safe_spl::keys::key_id;
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::remote::{Fetch, Fetched, RemoteConfig};
+use agent_safe_spl::time::Clock;
+use agent_safe_spl::token::{generate_keypair, mint, MintOptions, Token, VerifyErrorCode};
+use agent_safe_spl::types::{Node, SplError};
+
+#[derive(Clone, Default)]
+struct TestClock(Arc<AtomicI64>);
+
+impl Clock for TestClock {
+    fn now_unix(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Serves whatever document is currently published and counts fetches.
+#[derive(Clone, Default)]
+struct Published {
+    body: Arc<Mutex<String>>,
+    fetches: Arc<AtomicUsize>,
+}
+
+impl Fetch for Published {
+    fn fetch(&self) -> Result<Fetched, SplError> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        Ok(Fetched { body: self.body.lock().unwrap().as_bytes().to_vec(), signature: None })
+    }
+}
+
+fn jwk(kid: &str, public_key: &str) -> serde_json::Value {
+    let x = base64url_encode(&hex::decode(public_key).unwrap());
+    serde_json::json!({ "kty": "OKP", "crv": "Ed25519", "use": "sig", "alg": "EdDSA", "kid": kid, "x": x })
+}
+
+fn jwks(keys: &[serde_json::Value]) -> String {
+    serde_json::json!({ "keys": keys }).to_string()
+}
+
+fn read(token: &Token, verifier: &Verifier) -> agent_safe_spl::token::VerifyTokenResult {
+    let req = HashMap::from([("action".to_string(), Node::Str("read".into()))]);
+    verifier.verify(token, req, HashMap::new(), None)
+}
+
+fn minted(private_key: &str, kid: &str) -> Token {
+    let opts = MintOptions { kid: Some(kid.into()), ..MintOptions::default() };
+    mint(r#"(= (get req "action") "read")"#, private_key, opts).unwrap()
+}
+
+#[test]
+fn test_parse_jwks_converts_ed25519_signing_keys() {
+    let (a, _) = generate_keypair();
+    let (b, _) = generate_keypair();
+    let mut windowed = jwk("b", &b);
+    windowed["nbf"] = serde_json::json!(100);
+    windowed["exp"] = serde_json::json!(200);
+    let mut unnamed = jwk("", &a);
+    unnamed.as_object_mut().unwrap().remove("kid");
+    let doc = jwks(&[
+        jwk("a", &a),
+        windowed,
+        unnamed,
+        serde_json::json!({ "kty": "EC", "crv": "P-256", "kid": "ec", "x": "AA", "y": "AA" }),
+        serde_json::json!({ "kty": "OKP", "crv": "X25519", "kid": "dh", "x": "AA" }),
+        serde_json::json!({ "kty": "OKP", "crv": "Ed25519", "use": "enc", "kid": "enc", "x": "AA" }),
+    ]);
+
+    let issuers = parse_jwks(doc.as_bytes()).unwrap();
+    let kids: Vec<&str> = issuers.keys().iter().map(|k| k.kid.as_str()).collect();
+    assert_eq!(kids, ["a", "b", key_id(&a).as_str()]);
+    let b_key = issuers.find(Some("b"), "").unwrap().unwrap();
+    assert_eq!((b_key.public_key.as_str(), b_key.not_before, b_key.not_after), (b.as_str(), Some(100), Some(200)));
+
+    assert!(parse_jwks(b"{}").is_err());
+    assert!(parse_jwks(b"not json").is_err());
+    let mut short = jwk("short", &a);
+    short["x"] = serde_json::json!("AAAA");
+    assert_eq!(parse_jwks(jwks(&[short]).as_bytes()).err().unwrap().0, "JWK short: x must be 32 bytes");
+    let mut wrong_alg = jwk("rsa", &a);
+    wrong_alg["alg"] = serde_json::json!("RS256");
+    assert!(parse_jwks(jwks(&[wrong_alg]).as_bytes()).is_err());
+}
+
+#[test]
+fn test_jwks_key_set_follows_rotation() {
+    let (old_pub, old_priv) = generate_keypair();
+    let (new_pub, new_priv) = generate_keypair();
+    let published = Published::default();
+    *published.body.lock().unwrap() = jwks(&[jwk("k1", &old_pub)]);
+    let clock = TestClock::default();
+    clock.0.store(1_000, Ordering::SeqCst);
+    let config = RemoteConfig { fresh_for_secs: 3600, jitter: 0.0, ..RemoteConfig::default() };
+    let keys = Arc::new(JwksKeySet::new(published.clone(), config).with_clock(clock.clone()));
+    let verifier = Verifier::default().with_trusted_issuers(keys.clone());
+
+    assert!(read(&minted(&old_priv, "k1"), &verifier).allow);
+    assert!(read(&minted(&old_priv, "k1"), &verifier).allow);
+    assert_eq!(published.fetches.load(Ordering::SeqCst), 1);
+
+    // The issuer publishes a new key. A token naming it arriving right
+    // after the last fetch does not trigger another.
+    *published.body.lock().unwrap() = jwks(&[jwk("k1", &old_pub), jwk("k2", &new_pub)]);
+    let fresh = minted(&new_priv, "k2");
+    assert_eq!(read(&fresh, &verifier).code, Some(VerifyErrorCode::UntrustedIssuer));
+    assert_eq!(published.fetches.load(Ordering::SeqCst), 1);
+
+    // Once the cached copy has settled, an unknown kid refetches early.
+    clock.0.store(1_060, Ordering::SeqCst);
+    assert!(read(&fresh, &verifier).allow);
+    assert_eq!(published.fetches.load(Ordering::SeqCst), 2);
+
+    // Made-up kids are rejected without refetching again.
+    let (_, stranger) = generate_keypair();
+    assert_eq!(read(&minted(&stranger, "k3"), &verifier).code, Some(VerifyErrorCode::UntrustedIssuer));
+    assert_eq!(published.fetches.load(Ordering::SeqCst), 2);
+    assert_eq!(keys.keys().unwrap().keys().len(), 2);
+}
+
+#[test]
+fn test_jwks_from_file() {
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let path = std::env::temp_dir().join(format!("agent-safe-jwks-{}.json", std::process::id()));
+    fs::write(&path, jwks(&[jwk("file-key", &issuer_pub)])).unwrap();
+
+    assert_eq!(load_jwks_file(&path).unwrap().keys()[0].public_key, issuer_pub);
+    let keys = JwksKeySet::new(JwksFile::new(&path), RemoteConfig::default());
+    let verifier = Verifier::default().with_trusted_issuers(Arc::new(keys));
+    assert!(read(&minted(&issuer_priv, "file-key"), &verifier).allow);
+
+    fs::remove_file(&path).unwrap();
+    assert!(load_jwks_file(&path).is_err());
+}
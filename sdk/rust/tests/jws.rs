@@ -0,0 +1,67 @@
+#![cfg(feature = "jws")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::jws::{base64url_decode, base64url_encode};
+use agent_safe_spl::token::{generate_keypair, mint, verify_token_untrusted, MintOptions, Token, VerifyErrorCode};
+
+#[test]
+fn test_base64url_roundtrip() {
+    assert_eq!(base64url_encode(b""), "");
+    assert_eq!(base64url_encode(b"f"), "Zg");
+    assert_eq!(base64url_encode(b"fo"), "Zm8");
+    assert_eq!(base64url_encode(b"foo"), "Zm9v");
+    assert_eq!(base64url_encode(&[0xfb, 0xff]), "-_8");
+    for data in [&b""[..], b"a", b"ab", b"abc", b"abcd", &[0u8, 255, 128, 7, 9]] {
+        assert_eq!(base64url_decode(&base64url_encode(data)).unwrap(), data);
+    }
+    assert!(base64url_decode("Zg==").is_err());
+    assert!(base64url_decode("Zh").is_err());
+    assert!(base64url_decode("Z").is_err());
+}
+
+#[test]
+fn test_jws_roundtrip() {
+    let (_, issuer_priv) = generate_keypair();
+    let (agent_pub, _) = generate_keypair();
+    let token = mint(
+        "#t",
+        &issuer_priv,
+        MintOptions {
+            expires: Some("2027-01-01T00:00:00Z".into()),
+            issued_at: Some("2026-04-01T00:00:00Z".into()),
+            pop_key: Some(agent_pub),
+            token_id: Some("tok-0001".into()),
+            kid: Some("issuer-2026".into()),
+            ..MintOptions::default()
+        },
+    )
+    .unwrap();
+
+    let jws = token.to_jws(&issuer_priv, Some("https://verifier.example")).unwrap();
+    let parts: Vec<&str> = jws.split('.').collect();
+    let claims: serde_json::Value = serde_json::from_slice(&base64url_decode(parts[1]).unwrap()).unwrap();
+    assert_eq!(claims["exp"], 1_798_761_600);
+    assert_eq!(claims["iat"], 1_775_001_600);
+    assert_eq!(claims["iss"], token.public_key.as_str());
+    assert_eq!(claims["jti"], "tok-0001");
+    assert_eq!(claims["aud"], "https://verifier.example");
+    assert_eq!(claims["cnf"]["jwk"]["crv"], "Ed25519");
+    let header: serde_json::Value = serde_json::from_slice(&base64url_decode(parts[0]).unwrap()).unwrap();
+    assert_eq!(header["alg"], "EdDSA");
+    assert_eq!(header["kid"], "issuer-2026");
+
+    let imported = Token::from_jws(&jws).unwrap();
+    assert_eq!(imported, token);
+    // The native signature survives the trip; only the PoP presentation is missing.
+    let result = verify_token_untrusted(&imported, HashMap::new(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::PresentationRequired));
+
+    // A different key cannot export, and a tampered JWS does not import.
+    let (_, other_priv) = generate_keypair();
+    assert!(token.to_jws(&other_priv, None).is_err());
+    let mut forged = claims.clone();
+    forged["exp"] = serde_json::json!(4_102_444_800i64);
+    let forged_jws = format!("{}.{}.{}", parts[0], base64url_encode(forged.to_string().as_bytes()), parts[2]);
+    assert!(Token::from_jws(&forged_jws).is_err());
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::keys::{InMemoryKeyStore, KeyStore};
+use agent_safe_spl::token::{mint_with_key_store, verify_token_untrusted, MintOptions};
+use agent_safe_spl::types::Node;
+
+fn exercise(store: &dyn KeyStore) {
+    assert!(store.active().unwrap().is_none());
+    assert!(mint_with_key_store("#t", store, MintOptions::default()).is_err());
+
+    let first = store.rotate().unwrap();
+    assert_eq!(store.active().unwrap().as_ref(), Some(&first));
+
+    let token = mint_with_key_store(r#"(= (get req "action") "read")"#, store, MintOptions::default()).unwrap();
+    assert_eq!(token.public_key, first.public_key);
+    let mut req = HashMap::new();
+    req.insert("action".to_string(), Node::Str("read".into()));
+    let result = verify_token_untrusted(&token, req, HashMap::new());
+    assert!(result.allow, "{:?}", result.error);
+
+    let second = store.rotate().unwrap();
+    assert_ne!(first.id, second.id);
+    assert_eq!(store.active().unwrap().as_ref(), Some(&second));
+    let keys = store.keys().unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0].successor.as_deref(), Some(second.id.as_str()));
+    assert!(keys[0].retired_at.is_some());
+
+    assert!(store.sign(&first.id, b"msg").is_err());
+    assert!(store.sign("0000000000000000", b"msg").is_err());
+    assert!(store.sign(&second.id, b"msg").is_ok());
+}
+
+#[test]
+fn test_in_memory_key_store() {
+    exercise(&InMemoryKeyStore::new());
+}
+
+#[cfg(feature = "keystore")]
+#[test]
+fn test_file_key_store() {
+    use agent_safe_spl::keys::{Argon2Params, FileKeyStore};
+
+    let dir = std::env::temp_dir().join(format!("agent-safe-keys-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let params = Argon2Params::new(256, 1, 1, Some(32)).unwrap();
+    let store = FileKeyStore::open_with_params(&dir, "correct horse", params.clone()).unwrap();
+    exercise(&store);
+
+    // Seeds are not stored in the clear, and the wrong passphrase cannot sign.
+    let active = store.active().unwrap().unwrap();
+    let raw = std::fs::read_to_string(dir.join(format!("{}.json", active.id))).unwrap();
+    assert!(!raw.contains("private"));
+    let wrong = FileKeyStore::open_with_params(&dir, "battery staple", params).unwrap();
+    assert_eq!(wrong.active().unwrap(), Some(active.clone()));
+    assert!(wrong.sign(&active.id, b"msg").is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
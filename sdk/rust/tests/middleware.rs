@@ -0,0 +1,128 @@
+#![cfg(feature = "tower")]
+
+use std::convert::Infallible;
+use std::future::{ready, Future, Ready};
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use bytes::Bytes;
+use http::{Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use agent_safe_spl::middleware::{AgentSafeLayer, Extract, REASON_CODE_METADATA};
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, MintOptions, VerifyTokenResult};
+
+/// Polls a future that never waits on I/O.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future should complete without waiting"),
+    }
+}
+
+/// Inner service answering with the obligations of the verified token.
+#[derive(Clone)]
+struct Handler;
+
+impl Service<Request<Full<Bytes>>> for Handler {
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Full<Bytes>>) -> Self::Future {
+        let result = request.extensions().get::<VerifyTokenResult>().expect("verified result");
+        let kinds: Vec<_> = result.obligations.iter().map(|o| o.kind.clone()).collect();
+        ready(Ok(Response::new(Full::from(kinds.join(",")))))
+    }
+}
+
+fn send<S>(service: &mut S, auth: Option<&str>, body: &str) -> (StatusCode, String)
+where
+    S: Service<Request<Full<Bytes>>, Response = Response<Full<Bytes>>, Error = Infallible>,
+{
+    let mut request = Request::post("/payments").body(Full::from(body.to_string())).unwrap();
+    if let Some(auth) = auth {
+        request.headers_mut().insert("authorization", auth.parse().unwrap());
+    }
+    let response = block_on(service.call(request)).unwrap();
+    let status = response.status();
+    let body = block_on(response.into_body().collect()).unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[test]
+fn test_layer_verifies_requests() {
+    let (_, issuer) = generate_keypair();
+    let policy = r#"(and (= (get req "method") "POST") (= (get req "path") "/payments")
+        (or (<= (get req "amount") 100) (deny-with "AMOUNT_TOO_HIGH")) (obligate "receipt"))"#;
+    let token = mint(policy, &issuer, MintOptions::default()).unwrap();
+    let auth = format!("AgentSafe {}", token.to_jws(&issuer, None).unwrap());
+
+    let layer = AgentSafeLayer::new(Verifier::default)
+        .extract("method", Extract::Method)
+        .extract("path", Extract::Path)
+        .extract("amount", Extract::Body("/payment/amount".into()));
+    let mut service = layer.layer(Handler);
+
+    let (status, body) = send(&mut service, Some(&auth), r#"{"payment": {"amount": 50}}"#);
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "receipt"));
+
+    let (status, body) = send(&mut service, Some(&auth), r#"{"payment": {"amount": 500}}"#);
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let denial: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(denial["code"], "policy_denied");
+    assert_eq!(denial["reason_code"], "AMOUNT_TOO_HIGH");
+
+    let (status, body) = send(&mut service, None, "{}");
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.contains("missing_token"));
+    assert!(send(&mut service, Some("AgentSafe nonsense"), "{}").1.contains("malformed_token"));
+    assert!(send(&mut service, Some("Bearer abc"), "{}").1.contains("missing_token"));
+
+    let mut capped = layer.max_body_bytes(16).layer(Handler);
+    let (status, body) = send(&mut capped, Some(&auth), r#"{"payment": {"amount": 50}}"#);
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(body.contains("body_too_large"));
+}
+
+#[test]
+fn test_grpc_layer() {
+    let (_, issuer) = generate_keypair();
+    let policy = r#"(and (or (= (get req "action") "payments.Payments/Create") (deny-with "WRONG_METHOD")) (obligate "receipt"))"#;
+    let token = mint(policy, &issuer, MintOptions::default()).unwrap();
+    let auth = format!("AgentSafe {}", token.to_jws(&issuer, None).unwrap());
+    let mut service = AgentSafeLayer::grpc(Verifier::default).layer(Handler);
+
+    fn call<S>(service: &mut S, path: &str, auth: Option<&str>) -> Response<Full<Bytes>>
+    where
+        S: Service<Request<Full<Bytes>>, Response = Response<Full<Bytes>>, Error = Infallible>,
+    {
+        // An empty protobuf message in gRPC framing.
+        let mut request = Request::post(path).body(Full::from(Bytes::from_static(b"\0\0\0\0\0"))).unwrap();
+        if let Some(auth) = auth {
+            request.headers_mut().insert("authorization", auth.parse().unwrap());
+        }
+        block_on(service.call(request)).unwrap()
+    }
+
+    let allowed = call(&mut service, "/payments.Payments/Create", Some(&auth));
+    assert_eq!(block_on(allowed.into_body().collect()).unwrap().to_bytes(), "receipt");
+
+    let denied = call(&mut service, "/payments.Payments/Refund", Some(&auth));
+    assert_eq!(denied.status(), StatusCode::OK);
+    assert_eq!(denied.headers()["grpc-status"], "7");
+    assert_eq!(denied.headers()[REASON_CODE_METADATA], "WRONG_METHOD");
+    assert!(denied.headers()["grpc-message"].to_str().unwrap().starts_with("policy_denied: policy denied"));
+
+    let anonymous = call(&mut service, "/payments.Payments/Create", None);
+    assert_eq!(anonymous.headers()["grpc-status"], "16");
+    assert_eq!(anonymous.headers()["grpc-message"], "missing_token: missing AgentSafe authorization");
+}
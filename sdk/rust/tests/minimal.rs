@@ -0,0 +1,72 @@
+//! The `minimal` profile (parser, evaluator, Ed25519 tokens) must stay
+//! dependency-light: new subsystems go behind their own features.
+//!
+//! Checked against the manifest itself, so the test needs neither the
+//! network nor a `cargo` subprocess.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+const MANIFEST: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+
+/// Direct dependencies the minimal profile may pull in.
+const ALLOWED: &[&str] = &["ed25519-dalek", "getrandom", "hex", "serde", "serde_json", "sha2"];
+
+/// `name = value` lines of manifest section `[section]`.
+fn section(name: &str) -> Vec<(&'static str, &'static str)> {
+    let header = format!("[{name}]");
+    MANIFEST
+        .lines()
+        .skip_while(|line| line.trim() != header)
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once(" = "))
+        .collect()
+}
+
+/// Direct dependencies enabled by `features` with default features off.
+fn dependencies(features: &[&'static str]) -> BTreeSet<&'static str> {
+    let declared = section("dependencies");
+    let table: BTreeMap<&str, Vec<&str>> = section("features")
+        .into_iter()
+        .map(|(name, list)| {
+            let items = list.trim_matches(['[', ']']).split(',').map(|item| item.trim().trim_matches('"'));
+            (name, items.filter(|item| !item.is_empty()).collect())
+        })
+        .collect();
+
+    let mut deps: BTreeSet<&str> =
+        declared.iter().filter(|(_, spec)| !spec.contains("optional = true")).map(|(name, _)| *name).collect();
+    let mut pending: Vec<&str> = features.to_vec();
+    let mut seen = BTreeSet::new();
+    while let Some(item) = pending.pop() {
+        if !seen.insert(item) {
+            continue;
+        }
+        let item = item.strip_prefix("dep:").unwrap_or(item);
+        let name = item.split_once('/').map_or(item, |(name, _)| name);
+        match table.get(name) {
+            Some(enabled) if !item.contains('/') => pending.extend(enabled),
+            _ => {
+                assert!(declared.iter().any(|(dep, _)| *dep == name), "unknown feature or dependency {name}");
+                deps.insert(name);
+            }
+        }
+    }
+    deps
+}
+
+#[test]
+fn test_minimal_profile_direct_dependencies() {
+    let deps = dependencies(&["minimal"]);
+    assert!(deps.contains("ed25519-dalek") && deps.contains("sha2"), "{deps:?}");
+    let extra: Vec<&&str> = deps.iter().filter(|d| !ALLOWED.contains(d)).collect();
+    assert!(extra.is_empty(), "minimal profile gained dependencies: {extra:?}");
+}
+
+#[test]
+fn test_full_profile_builds_on_minimal() {
+    assert!(dependencies(&["full"]).is_superset(&dependencies(&["minimal"])));
+    assert!(dependencies(&["full"]).contains("unicode-normalization"));
+    assert!(!dependencies(&["minimal"]).contains("unicode-normalization"));
+}
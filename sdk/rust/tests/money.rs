@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::money::{format_amount, minor_units_from_decimal, minor_units_from_f64};
+use agent_safe_spl::types::map_from_json;
+use agent_safe_spl::{parse, verify, Env, Node};
+
+fn usd(minor_units: i64) -> Node {
+    Node::Money { minor_units, currency: "USD".into() }
+}
+
+fn eval(src: &str, req: HashMap<String, Node>) -> Result<bool, String> {
+    let env = Env { req, ..Env::default() };
+    verify(&parse(src).map_err(|e| e.0)?, &env).map(|r| r.allow).map_err(|e| e.0)
+}
+
+fn with_amount(amount: Node) -> HashMap<String, Node> {
+    HashMap::from([("amount".to_string(), amount)])
+}
+
+#[test]
+fn minor_units_follow_currency_exponent() {
+    assert_eq!(minor_units_from_decimal("19.99", "USD").unwrap(), 1999);
+    assert_eq!(minor_units_from_decimal("19.9", "USD").unwrap(), 1990);
+    assert_eq!(minor_units_from_decimal("-5", "EUR").unwrap(), -500);
+    assert_eq!(minor_units_from_decimal("500", "JPY").unwrap(), 500);
+    assert_eq!(minor_units_from_decimal("1.234", "KWD").unwrap(), 1234);
+    assert!(minor_units_from_decimal("19.999", "USD").is_err());
+    assert!(minor_units_from_decimal("5.5", "JPY").is_err());
+    for bad in ["", "1.", ".5", "1e3", "1,000", "--1"] {
+        assert!(minor_units_from_decimal(bad, "USD").is_err(), "{bad}");
+    }
+    assert!(minor_units_from_decimal("1", "usd").is_err());
+
+    // 0.1 + 0.2 style float noise lands on the nearest cent.
+    assert_eq!(minor_units_from_f64(19.99, "USD").unwrap(), 1999);
+    assert_eq!(minor_units_from_f64(0.1 + 0.2, "USD").unwrap(), 30);
+    assert!(minor_units_from_f64(0.125, "USD").is_err());
+    assert!(minor_units_from_f64(f64::NAN, "USD").is_err());
+
+    assert_eq!(format_amount(1999, "USD"), "19.99");
+    assert_eq!(format_amount(-5, "USD"), "-0.05");
+    assert_eq!(format_amount(500, "JPY"), "500");
+    assert_eq!(usd(1999).to_string(), r#"(money "19.99" "USD")"#);
+}
+
+#[test]
+fn money_comparisons_are_exact_and_currency_checked() {
+    let src = r#"(<= (get req "amount") (money 100 "USD"))"#;
+    assert!(eval(src, with_amount(usd(10_000))).unwrap());
+    assert!(!eval(src, with_amount(usd(10_001))).unwrap());
+
+    let eur = Node::Money { minor_units: 100, currency: "EUR".into() };
+    let err = eval(src, with_amount(eur.clone())).unwrap_err();
+    assert!(err.contains("cannot compare EUR with USD"), "{err}");
+    assert!(eval(r#"(= (get req "amount") (money 1 "USD"))"#, with_amount(eur)).is_err());
+    // A bare number is not silently compared with money.
+    assert!(eval(src, with_amount(Node::Number(50.0))).is_err());
+
+    assert!(eval(r#"(= (money "0.30" "USD") (money (get req "amount") "USD"))"#, with_amount(Node::Number(0.1 + 0.2))).unwrap());
+    assert!(eval(r#"(in-range (get req "amount") (money 10 "USD") (money 20 "USD"))"#, with_amount(usd(1500))).unwrap());
+    assert!(!eval(r#"(in-range (get req "amount") (money 10 "USD") (money 20 "USD"))"#, with_amount(usd(2001))).unwrap());
+    assert!(eval(r#"(> (money 1 "JPY") (money 0 "JPY"))"#, HashMap::new()).unwrap());
+
+    assert!(eval(r#"(money 1 "dollars")"#, HashMap::new()).is_err());
+    assert!(eval(r#"(money #t "USD")"#, HashMap::new()).is_err());
+    assert!(eval(r#"(money 1)"#, HashMap::new()).is_err());
+}
+
+#[test]
+fn money_round_trips_through_json() {
+    let req = map_from_json(&serde_json::json!({
+        "amount": { "currency": "USD", "minor_units": 1999 },
+        "meta": { "currency": "USD", "minor_units": 19.5 },
+    }))
+    .unwrap();
+    assert_eq!(req["amount"], usd(1999));
+    assert!(matches!(req["meta"], Node::Map(_)));
+
+    let json = serde_json::to_string(&usd(1999)).unwrap();
+    assert_eq!(json, r#"{"currency":"USD","minor_units":1999}"#);
+    assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), usd(1999));
+    assert_eq!(Node::from_json_value(&usd(-3).to_json_value()), usd(-3));
+}
+
+#[test]
+fn convert_normalizes_currencies_through_the_rate_provider() {
+    use std::sync::Arc;
+
+    let rates = |from: &str, to: &str| match (from, to) {
+        ("EUR", "USD") => Some(1.08),
+        ("USD", "JPY") => Some(150.0),
+        ("USD", "GBP") => Some(f64::NAN),
+        _ => None,
+    };
+    let env = |req, strict| Env { req, rates: Some(Arc::new(rates)), strict, ..Env::default() };
+    let eval_in = |src: &str, env: Env| verify(&parse(src).unwrap(), &env).map(|r| r.allow).map_err(|e| e.0);
+
+    let eur = |minor_units| Node::Money { minor_units, currency: "EUR".into() };
+    let cap = r#"(<= (convert (get req "amount") "EUR" "USD") (money 100 "USD"))"#;
+    assert!(eval_in(cap, env(with_amount(eur(9_259)), true)).unwrap());
+    assert!(!eval_in(cap, env(with_amount(eur(9_260)), true)).unwrap());
+    assert!(eval_in(r#"(= (convert 2 "EUR" "USD") 2.16)"#, env(HashMap::new(), true)).unwrap());
+    assert!(eval_in(r#"(= (convert (money "1.01" "USD") "USD" "JPY") (money 152 "JPY"))"#, env(HashMap::new(), true)).unwrap());
+    // Same-currency conversion needs no rate.
+    assert!(eval(r#"(= (convert (money 5 "USD") "USD" "USD") (money 5 "USD"))"#, HashMap::new()).unwrap());
+
+    // An unknown or unusable rate is an error in every mode, so negating the
+    // comparison cannot turn it into an allow.
+    let gbp = r#"(<= (convert 1 "USD" "GBP") 100)"#;
+    for strict in [false, true] {
+        assert_eq!(eval_in(gbp, env(HashMap::new(), strict)).unwrap_err(), "no exchange rate from USD to GBP");
+    }
+    assert!(eval_in(r#"(not (> (convert 1 "USD" "GBP") 100))"#, env(HashMap::new(), false)).is_err());
+    assert!(eval(r#"(<= (convert 1 "EUR" "USD") 100)"#, HashMap::new()).is_err());
+
+    assert!(eval_in(cap, env(with_amount(Node::Money { minor_units: 1, currency: "USD".into() }), false)).is_err());
+    assert!(eval_in(r#"(convert "ten" "EUR" "USD")"#, env(HashMap::new(), false)).is_err());
+    assert!(eval_in(r#"(convert 1 "eur" "USD")"#, env(HashMap::new(), false)).is_err());
+}
+
+#[test]
+fn verifier_rates_back_convert_in_token_policies() {
+    use std::sync::Arc;
+
+    use agent_safe_spl::issuers::{TrustedIssuers, TrustedKey};
+    use agent_safe_spl::profile::Verifier;
+    use agent_safe_spl::token::{MintOptions, VerifyErrorCode};
+    use agent_safe_spl::{generate_keypair, mint};
+
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let cap = r#"(<= (convert (get req "amount") "EUR" "USD") (money 100 "USD"))"#;
+    let token = mint(cap, &issuer_priv, MintOptions::default()).unwrap();
+    let issuers = Arc::new(TrustedIssuers::new().with_key(TrustedKey::new(&issuer_pub)));
+    let eur = |minor_units| with_amount(Node::Money { minor_units, currency: "EUR".into() });
+
+    let verifier = Verifier::default()
+        .with_trusted_issuers(issuers.clone())
+        .with_rates(Arc::new(|from: &str, to: &str| (from == "EUR" && to == "USD").then_some(1.08)));
+    let result = verifier.verify(&token, eur(9_259), HashMap::new(), None);
+    assert!(result.allow, "{:?}", result.error);
+    assert!(!verifier.verify(&token, eur(9_260), HashMap::new(), None).allow);
+
+    // Without rates the conversion fails closed.
+    let bare = Verifier::default().with_trusted_issuers(issuers).verify(&token, eur(1), HashMap::new(), None);
+    assert_eq!(bare.code, Some(VerifyErrorCode::Evaluation));
+}
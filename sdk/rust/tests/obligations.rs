@@ -0,0 +1,58 @@
+use agent_safe_spl::audit::verify_chain;
+use agent_safe_spl::obligations::{Obligation, ObligationTracker};
+
+fn notify(deadline_secs: Option<i64>) -> Obligation {
+    Obligation { kind: "notify-owner".into(), deadline_secs }
+}
+
+#[test]
+fn test_outstanding_and_acknowledge() {
+    let tracker = ObligationTracker::new();
+    tracker.record("tok-1", &[notify(Some(3600)), Obligation { kind: "log".into(), deadline_secs: None }], 1_000);
+    tracker.record("tok-2", &[notify(None)], 1_000);
+
+    let outstanding = tracker.outstanding("tok-1");
+    assert_eq!(outstanding.len(), 2);
+    assert_eq!(outstanding[0].due_at, Some(4_600));
+    assert!(tracker.overdue("tok-1", 4_600).is_empty());
+    assert_eq!(tracker.overdue("tok-1", 4_601).len(), 1);
+
+    let entry = tracker.acknowledge("tok-1", "notify-owner", 2_000).unwrap();
+    assert_eq!(entry.event, "obligation.discharged");
+    assert!(entry.detail.contains("status=on_time"));
+    assert_eq!(tracker.outstanding("tok-1").len(), 1);
+    assert_eq!(tracker.outstanding("tok-2").len(), 1);
+
+    // Nothing left of that kind on tok-1; other tokens are unaffected.
+    assert!(tracker.acknowledge("tok-1", "notify-owner", 2_000).is_err());
+    assert!(tracker.acknowledge("tok-3", "log", 2_000).is_err());
+}
+
+#[test]
+fn test_late_discharge_is_recorded() {
+    let tracker = ObligationTracker::new();
+    tracker.record("tok", &[notify(Some(60))], 100);
+    let entry = tracker.acknowledge("tok", "notify-owner", 500).unwrap();
+    assert!(entry.detail.contains("status=late"));
+    assert!(tracker.outstanding("tok").is_empty());
+}
+
+#[test]
+fn test_audit_chain_detects_tampering() {
+    let tracker = ObligationTracker::new();
+    tracker.record("tok", &[notify(Some(60)), notify(None)], 100);
+    tracker.acknowledge("tok", "notify-owner", 120).unwrap();
+
+    let entries = tracker.audit().entries();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(tracker.audit().head(), entries[2].hash);
+    verify_chain(&entries).unwrap();
+
+    let mut edited = entries.clone();
+    edited[2].detail = edited[2].detail.replace("on_time", "late");
+    assert!(verify_chain(&edited).is_err());
+
+    let mut dropped = entries;
+    dropped.remove(1);
+    assert!(verify_chain(&dropped).is_err());
+}
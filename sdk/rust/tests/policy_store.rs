@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use agent_safe_spl::policy_store::{policy_hash, FsPolicyStore, InMemoryPolicyStore, PolicyStore};
+
+const POLICY_A: &str = r#"(<= (get req "amount") 50)"#;
+const POLICY_B: &str = r#"(= (get req "action") "read")"#;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("agent-safe-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn exercise(store: &dyn PolicyStore) {
+    let a = store.put(POLICY_A).unwrap();
+    let b = store.put(&format!("  {POLICY_B}\n")).unwrap();
+    assert_eq!(a, policy_hash(POLICY_A));
+    assert_eq!(b, policy_hash(POLICY_B));
+    assert_eq!(store.put(POLICY_A).unwrap(), a);
+    assert_eq!(store.get(&b).unwrap().as_deref(), Some(POLICY_B));
+
+    store.retain(&a, "token-1").unwrap();
+    store.retain(&a, "token-2").unwrap();
+    assert!(store.retain(&policy_hash("(unknown)"), "token-1").is_err());
+
+    // B has no holders and is collected; A survives.
+    assert_eq!(store.gc().unwrap(), 1);
+    assert!(store.get(&b).unwrap().is_none());
+    assert!(store.get(&a).unwrap().is_some());
+
+    store.release(&a, "token-1").unwrap();
+    assert_eq!(store.gc().unwrap(), 0);
+    store.release(&a, "token-2").unwrap();
+    store.release(&a, "token-2").unwrap();
+    assert_eq!(store.gc().unwrap(), 1);
+    assert!(store.get(&a).unwrap().is_none());
+}
+
+#[test]
+fn test_in_memory_policy_store() {
+    exercise(&InMemoryPolicyStore::new());
+}
+
+#[test]
+fn test_fs_policy_store() {
+    let dir = scratch_dir("fs-store");
+    exercise(&FsPolicyStore::open(&dir).unwrap());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_fs_policy_store_detects_corruption() {
+    let dir = scratch_dir("fs-corrupt");
+    let store = FsPolicyStore::open(&dir).unwrap();
+    let hash = store.put(POLICY_A).unwrap();
+    std::fs::write(dir.join("objects").join(&hash), "(or #t)").unwrap();
+    assert!(store.get(&hash).is_err());
+    assert!(store.get("../../etc/passwd").is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_fs_policy_store_rejects_mixed_case_hashes() {
+    let dir = scratch_dir("fs-case");
+    let store = FsPolicyStore::open(&dir).unwrap();
+    let hash = store.put(POLICY_A).unwrap();
+    let mixed: String = hash.char_indices().map(|(i, c)| if i % 2 == 0 { c.to_ascii_uppercase() } else { c }).collect();
+    assert_ne!(mixed, hash);
+    assert!(store.get(&mixed).unwrap_err().0.contains("invalid policy hash"));
+    assert!(store.retain(&mixed, "token-1").is_err());
+    assert!(store.release(&mixed, "token-1").is_err());
+    assert!(store.get(&hash.to_ascii_uppercase()).is_err());
+    assert_eq!(store.get(&hash).unwrap().as_deref(), Some(POLICY_A));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_policy_store() {
+    use agent_safe_spl::policy_store::SqlitePolicyStore;
+    exercise(&SqlitePolicyStore::open_in_memory().unwrap());
+}
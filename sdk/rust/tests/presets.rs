@@ -0,0 +1,128 @@
+#![cfg(feature = "presets")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::parser::parse;
+use agent_safe_spl::presets::{CalendarBookingPolicy, EmailPolicy, GiftPolicy, Preset, SubscriptionAction, SubscriptionPolicy};
+use agent_safe_spl::token::{generate_keypair, verify_token_untrusted, MintOptions};
+use agent_safe_spl::types::{map_from_json, Node};
+
+fn req(json: serde_json::Value) -> HashMap<String, Node> {
+    map_from_json(&json).unwrap()
+}
+
+fn allows(preset: &impl Preset, request: serde_json::Value) -> bool {
+    let (_, issuer_priv) = generate_keypair();
+    let token = preset.mint(&issuer_priv, MintOptions::default()).unwrap();
+    let result = verify_token_untrusted(&token, req(request), HashMap::new());
+    assert!(result.error.is_none(), "{:?}", result.error);
+    result.allow
+}
+
+fn gift() -> GiftPolicy {
+    GiftPolicy {
+        recipients: vec!["niece@example.com".into(), "mom@example.com".into()],
+        per_txn_limit: 50.0,
+        per_day: 2,
+        currency: Some("USD".into()),
+    }
+}
+
+#[test]
+fn test_gift_policy() {
+    let policy = gift().policy().unwrap();
+    assert_eq!(parse(&policy.to_string()).unwrap(), policy);
+
+    let purchase = |recipient: &str, amount: f64, currency: &str| {
+        serde_json::json!({
+            "action": "payments.create", "purpose": "gift", "recipient": recipient,
+            "amount": amount, "currency": currency, "day": "2026-03-06",
+        })
+    };
+    assert!(allows(&gift(), purchase("niece@example.com", 50.0, "USD")));
+    assert!(!allows(&gift(), purchase("niece@example.com", 50.01, "USD")));
+    assert!(!allows(&gift(), purchase("stranger@example.com", 10.0, "USD")));
+    assert!(!allows(&gift(), purchase("niece@example.com", 10.0, "EUR")));
+
+    let bad = |f: fn(&mut GiftPolicy)| {
+        let mut g = gift();
+        f(&mut g);
+        g.policy().unwrap_err().0
+    };
+    assert_eq!(bad(|g| g.recipients.clear()), "recipients must not be empty");
+    assert_eq!(bad(|g| g.per_txn_limit = f64::NAN), "invalid amount limit: NaN");
+    assert_eq!(bad(|g| g.currency = Some("usd".into())), "invalid currency code: \"usd\"");
+    assert!(bad(|g| g.recipients = vec![r#"a") #t ("#.into()]).contains("quote"));
+}
+
+#[test]
+fn test_subscription_policy() {
+    let preset = SubscriptionPolicy {
+        services: vec!["streamflix".into()],
+        actions: vec![SubscriptionAction::Pause, SubscriptionAction::Cancel],
+        max_monthly_price: Some(20.0),
+    };
+    let request = |action: &str, service: &str| serde_json::json!({ "action": action, "service": service });
+    assert!(allows(&preset, request("subscriptions.cancel", "streamflix")));
+    assert!(!allows(&preset, request("subscriptions.subscribe", "streamflix")));
+    assert!(!allows(&preset, request("subscriptions.cancel", "musicbox")));
+
+    let renew = SubscriptionPolicy { actions: vec![SubscriptionAction::Renew], ..preset };
+    let priced = |price: f64| {
+        serde_json::json!({ "action": "subscriptions.renew", "service": "streamflix", "monthly_price": price })
+    };
+    assert!(allows(&renew, priced(19.99)));
+    assert!(!allows(&renew, priced(24.99)));
+}
+
+#[test]
+fn test_calendar_booking_policy() {
+    let preset = CalendarBookingPolicy {
+        calendars: vec!["work".into()],
+        hours: (9, 17),
+        weekdays_only: true,
+        max_duration_mins: 60,
+    };
+    let booking = |start: &str, mins: i64| {
+        serde_json::json!({ "action": "calendar.book", "calendar": "work", "start": start, "duration_mins": mins })
+    };
+    // 2026-03-06 is a Friday.
+    assert!(allows(&preset, booking("2026-03-06T10:00:00-05:00", 30)));
+    assert!(!allows(&preset, booking("2026-03-06T18:00:00-05:00", 30)));
+    assert!(!allows(&preset, booking("2026-03-07T10:00:00-05:00", 30)));
+    assert!(!allows(&preset, booking("2026-03-06T10:00:00-05:00", 90)));
+
+    let bad = CalendarBookingPolicy { hours: (9, 25), ..preset.clone() };
+    assert_eq!(bad.policy().unwrap_err().0, "invalid booking hours: 9..25");
+}
+
+#[test]
+fn test_email_policy() {
+    let preset = EmailPolicy { recipients: vec!["boss@example.com".into()], per_day: 5 };
+    let email = |to: &str| serde_json::json!({ "action": "email.send", "recipient": to, "day": "2026-03-06" });
+    assert!(allows(&preset, email("boss@example.com")));
+    assert!(!allows(&preset, email("everyone@example.com")));
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_presets_lint_clean() {
+    use agent_safe_spl::analysis::lint;
+
+    let subscription = SubscriptionPolicy {
+        services: vec!["streamflix".into()],
+        actions: vec![SubscriptionAction::Renew, SubscriptionAction::Cancel],
+        max_monthly_price: Some(20.0),
+    };
+    let calendar = CalendarBookingPolicy {
+        calendars: vec!["work".into()],
+        hours: (9, 17),
+        weekdays_only: true,
+        max_duration_mins: 60,
+    };
+    let email = EmailPolicy { recipients: vec!["boss@example.com".into()], per_day: 5 };
+    for policy in [gift().policy(), subscription.policy(), calendar.policy(), email.policy()] {
+        let policy = policy.unwrap();
+        assert_eq!(lint(&policy), Vec::new(), "{policy}");
+    }
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "remote")]
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use agent_safe_spl::remote::{Fetch, Fetched, RemoteConfig, RemoteResource};
+use agent_safe_spl::signature::SignatureScheme;
+use agent_safe_spl::time::Clock;
+use agent_safe_spl::token::generate_keypair;
+use agent_safe_spl::types::SplError;
+
+#[derive(Clone, Default)]
+struct TestClock(Arc<AtomicI64>);
+
+impl TestClock {
+    fn set(&self, now: i64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+}
+
+impl Clock for TestClock {
+    fn now_unix(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Replays scripted responses and counts calls.
+#[derive(Clone, Default)]
+struct Script {
+    responses: Arc<Mutex<VecDeque<Result<Fetched, SplError>>>>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Script {
+    fn push(&self, response: Result<&str, &str>) {
+        let response = response
+            .map(|body| Fetched { body: body.as_bytes().to_vec(), signature: None })
+            .map_err(|e| SplError(e.into()));
+        self.responses.lock().unwrap().push_back(response);
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl Fetch for Script {
+    fn fetch(&self) -> Result<Fetched, SplError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.responses.lock().unwrap().pop_front().unwrap_or_else(|| Err(SplError("script exhausted".into())))
+    }
+}
+
+fn config() -> RemoteConfig {
+    RemoteConfig { fresh_for_secs: 100, stale_for_secs: 50, jitter: 0.0, ..RemoteConfig::default() }
+}
+
+fn text(body: &[u8]) -> Result<String, SplError> {
+    String::from_utf8(body.to_vec()).map_err(|e| SplError(e.to_string()))
+}
+
+fn wait_for(mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn test_stale_while_revalidate() {
+    let clock = TestClock::default();
+    let script = Script::default();
+    script.push(Ok("v1"));
+    script.push(Ok("v2"));
+    let resource = RemoteResource::new(script.clone(), text, config()).with_clock(clock.clone());
+
+    assert_eq!(*resource.get().unwrap(), "v1");
+    clock.set(99);
+    assert_eq!(*resource.get().unwrap(), "v1");
+    assert_eq!(script.calls(), 1);
+
+    // Stale: served immediately, revalidated in the background.
+    clock.set(120);
+    assert_eq!(*resource.get().unwrap(), "v1");
+    wait_for(|| !resource.status().stale);
+    assert_eq!(*resource.get().unwrap(), "v2");
+    assert_eq!(script.calls(), 2);
+
+    // Past the stale window a failed fetch is an error, not an old value.
+    clock.set(400);
+    assert!(resource.get().is_err());
+}
+
+#[test]
+fn test_failed_revalidation_keeps_value_and_opens_circuit() {
+    let clock = TestClock::default();
+    let script = Script::default();
+    script.push(Ok("v1"));
+    for _ in 0..3 {
+        script.push(Err("unreachable"));
+    }
+    script.push(Ok("v2"));
+    let resource = RemoteResource::new(script.clone(), text, config()).with_clock(clock.clone());
+    resource.get().unwrap();
+
+    clock.set(110);
+    for _ in 0..3 {
+        assert!(resource.refresh().is_err());
+    }
+    assert!(resource.status().circuit_open);
+    assert!(resource.refresh().is_err());
+    // Stale value still served, and the open circuit suppresses revalidation.
+    assert_eq!(*resource.get().unwrap(), "v1");
+    assert_eq!(script.calls(), 4, "open circuit must not fetch");
+
+    // After the cooldown one attempt goes through and closes the circuit.
+    clock.set(110 + 60);
+    assert_eq!(*resource.refresh().unwrap(), "v2");
+    assert!(!resource.status().circuit_open);
+    assert_eq!(resource.status().consecutive_failures, 0);
+}
+
+#[test]
+fn test_signature_required_when_signer_configured() {
+    let (signer_pub, signer_priv) = generate_keypair();
+    let script = Script::default();
+    script.push(Ok("unsigned"));
+    let signed = |body: &str, key: &str| Fetched {
+        body: body.as_bytes().to_vec(),
+        signature: Some(SignatureScheme::Ed25519.sign(key, body.as_bytes()).unwrap()),
+    };
+    let (_, other_priv) = generate_keypair();
+    script.responses.lock().unwrap().push_back(Ok(signed("forged", &other_priv)));
+    script.responses.lock().unwrap().push_back(Ok(signed("genuine", &signer_priv)));
+
+    let config = RemoteConfig { signer_public_key: Some(signer_pub), failure_threshold: 10, ..config() };
+    let resource = RemoteResource::new(script, text, config).with_clock(TestClock::default());
+    assert_eq!(resource.get().unwrap_err().0, "remote resource is unsigned");
+    assert_eq!(resource.get().unwrap_err().0, "remote resource signature is invalid");
+    assert_eq!(*resource.get().unwrap(), "genuine");
+}
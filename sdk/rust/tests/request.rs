@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::audit::{entry_hash, verify_chain, AuditLog};
+use agent_safe_spl::builder::RequestBuilder;
+use agent_safe_spl::request::{canonical_request, request_hash};
+use agent_safe_spl::types::Node;
+
+fn payment(amount: f64) -> HashMap<String, Node> {
+    RequestBuilder::new().action("payments.create").recipient("niece@example.com").amount(amount).build().unwrap()
+}
+
+#[test]
+fn test_canonical_request_encoding() {
+    let req = HashMap::from([("b".to_string(), Node::Int(1)), ("a".to_string(), Node::Str("x".into()))]);
+    let mut expected = b"agent-safe-request-v1\0".to_vec();
+    expected.extend(2u64.to_be_bytes());
+    expected.extend(1u64.to_be_bytes());
+    expected.extend(b"as");
+    expected.extend(1u64.to_be_bytes());
+    expected.extend(b"x");
+    expected.extend(1u64.to_be_bytes());
+    expected.extend(b"bi");
+    expected.extend(1i64.to_be_bytes());
+    assert_eq!(canonical_request(&req), expected);
+
+    assert_eq!(request_hash(&payment(50.0)), "8b6074db1ce5a06c792a6b6f7723dabd0cd0acc1aad8ad52dd96e6fa883be532");
+    assert_eq!(request_hash(&payment(50.0)), request_hash(&payment(50.0)));
+    assert_ne!(request_hash(&payment(50.0)), request_hash(&payment(50.01)));
+
+    // Types are part of the encoding.
+    let typed = |v: Node| request_hash(&HashMap::from([("amount".to_string(), v)]));
+    assert_ne!(typed(Node::Int(5)), typed(Node::Number(5.0)));
+    assert_ne!(typed(Node::Str("x".into())), typed(Node::Symbol("x".into())));
+    assert_ne!(typed(Node::List(vec![])), typed(Node::Nil));
+    assert_eq!(typed(Node::Number(0.0)), typed(Node::Number(-0.0)));
+    // Length prefixes keep field boundaries unambiguous.
+    let split = |k: &str, v: &str| request_hash(&HashMap::from([(k.to_string(), Node::Str(v.into()))]));
+    assert_ne!(split("ab", "c"), split("a", "bc"));
+}
+
+#[test]
+fn test_audit_entries_commit_to_requests() {
+    let log = AuditLog::new();
+    log.append("tok", "obligation.issued", "notify-owner", 10);
+    let entry = log.append_for_request("tok", "payment.allowed", &payment(50.0), "amount=50", 11);
+    assert_eq!(entry.request_hash, Some(request_hash(&payment(50.0))));
+    let mut entries = log.entries();
+    assert!(entries[0].request_hash.is_none());
+    verify_chain(&entries).unwrap();
+    assert_eq!(
+        entries[0].hash,
+        entry_hash(0, 10, "tok", "obligation.issued", "notify-owner", None, &entries[0].prev_hash)
+    );
+
+    // Swapping in another request breaks the chain.
+    entries[1].request_hash = Some(request_hash(&payment(5000.0)));
+    assert!(verify_chain(&entries).is_err());
+}
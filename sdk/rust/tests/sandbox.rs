@@ -0,0 +1,76 @@
+#![cfg(feature = "tooling")]
+
+use std::collections::HashMap;
+
+use agent_safe_spl::builder::RequestBuilder;
+use agent_safe_spl::sandbox::{Sandbox, Scenario};
+use agent_safe_spl::types::Node;
+
+const POLICY: &str = r#"
+(and (<= (get req "amount") (remaining-for (get req "recipient") limits))
+     (< (per-day-count-self) 2)
+     (before now "2026-03-02T00:00:00Z")
+     (dpop_ok?))
+"#;
+
+fn pay(amount: f64) -> HashMap<String, Node> {
+    RequestBuilder::new().action("payments.create").amount(amount).recipient("niece").build().unwrap()
+}
+
+fn sandbox() -> Sandbox {
+    let mut sandbox = Sandbox::new(1_772_366_400); // 2026-03-01T12:00:00Z
+    sandbox.vars.insert("limits".into(), Node::Map([("niece".to_string(), Node::Number(100.0))].into()));
+    sandbox
+}
+
+#[test]
+fn test_scripted_values_and_virtual_clock() {
+    let mut sandbox = sandbox();
+    assert!(sandbox.evaluate(POLICY, pay(60.0)).allow);
+
+    sandbox.set_spent("niece", 50.0);
+    assert!(!sandbox.evaluate(POLICY, pay(60.0)).allow);
+    assert!(sandbox.evaluate(POLICY, pay(40.0)).allow);
+
+    sandbox.set_count("payments.create", "2026-03-01", 2);
+    assert!(!sandbox.evaluate(POLICY, pay(10.0)).allow);
+
+    // Next day: fresh counter, but past the policy's cutoff.
+    sandbox.advance(86_400);
+    assert!(!sandbox.evaluate(POLICY, pay(10.0)).allow);
+
+    let mut no_evidence = self::sandbox();
+    no_evidence.evidence = false;
+    assert!(!no_evidence.evaluate(POLICY, pay(10.0)).allow);
+
+    // Evaluation never records unless asked to.
+    assert!(!sandbox.counters["payments.create"].contains_key("2026-03-02"));
+}
+
+#[test]
+fn test_scenario_records_allowed_requests() {
+    let scenario: Scenario = serde_json::from_value(serde_json::json!({
+        "now": "2026-03-01T12:00:00Z",
+        "vars": { "limits": { "niece": 100 } },
+        "record_allowed": true,
+        "requests": [
+            { "name": "first", "request": { "action": "payments.create", "amount": 60, "recipient": "niece" } },
+            { "name": "over budget", "request": { "action": "payments.create", "amount": 60, "recipient": "niece" } },
+            { "name": "second", "request": { "action": "payments.create", "amount": 40, "recipient": "niece" } },
+            { "name": "third today", "request": { "action": "payments.create", "amount": 0, "recipient": "niece" } },
+            { "name": "past cutoff", "advance_secs": 86400,
+              "request": { "action": "payments.create", "amount": 0, "recipient": "niece" } }
+        ]
+    }))
+    .unwrap();
+
+    let results = Sandbox::run_scenario(POLICY, &scenario).unwrap();
+    let allows: Vec<(&str, bool)> = results.iter().map(|r| (r.name.as_str(), r.outcome.allow)).collect();
+    assert_eq!(
+        allows,
+        [("first", true), ("over budget", false), ("second", true), ("third today", false), ("past cutoff", false)]
+    );
+
+    let error = Sandbox::run_scenario("(and", &scenario).unwrap();
+    assert!(error[0].outcome.error.as_deref().unwrap().contains("unterminated"));
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use agent_safe_spl::builder::{EnvBuilder, RequestBuilder};
+use agent_safe_spl::parser::parse;
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::spend::{InMemorySpendTracker, SpendTracker};
+use agent_safe_spl::time::{period_bounds, FixedClock};
+use agent_safe_spl::token::{generate_keypair, mint, MintOptions, VerifyErrorCode};
+use agent_safe_spl::types::Node;
+use agent_safe_spl::verify;
+
+const WEEKLY_CAP: &str = r#"(and (= (get req "action") "payments.create")
+                                 (<= (cumulative-spend "payments.create" "week") 200))"#;
+
+fn pay(day: &str, amount: f64) -> HashMap<String, Node> {
+    RequestBuilder::new().action("payments.create").amount(amount).day(day).build().unwrap()
+}
+
+#[test]
+fn test_period_bounds() {
+    let bounds = |day, period| period_bounds(day, period).unwrap();
+    assert_eq!(bounds("2026-10-17", "day"), ("2026-10-17".into(), "2026-10-17".into()));
+    // ISO weeks run Monday to Sunday, across month and year ends.
+    assert_eq!(bounds("2026-10-17", "week"), ("2026-10-12".into(), "2026-10-18".into()));
+    assert_eq!(bounds("2027-01-01", "week"), ("2026-12-28".into(), "2027-01-03".into()));
+    assert_eq!(bounds("2028-02-10", "month"), ("2028-02-01".into(), "2028-02-29".into()));
+    assert!(period_bounds("2026-10-17", "fortnight").is_err());
+    assert!(period_bounds("2026-13-01", "day").is_err());
+}
+
+#[test]
+fn test_cumulative_spend_caps_running_total() {
+    let tracker: Arc<dyn SpendTracker> = Arc::new(InMemorySpendTracker::new());
+    let ast = parse(WEEKLY_CAP).unwrap();
+    let check = |day: &str, amount: f64| {
+        let env = EnvBuilder::new().request(pay(day, amount)).spend_tracker(tracker.clone()).build().unwrap();
+        verify(&ast, &env).unwrap().allow
+    };
+
+    assert!(check("2026-10-12", 120.0));
+    assert!(check("2026-10-14", 80.0));
+    // The pending request counts, so this would take the week to 200.01.
+    assert!(!check("2026-10-18", 0.01));
+    assert_eq!(tracker.spent_between("payments.create", "2026-10-12", "2026-10-18").unwrap(), 200.0);
+    // A new week starts from zero.
+    assert!(check("2026-10-19", 150.0));
+
+    let env = EnvBuilder::new().request(pay("2026-10-19", 1.0)).build().unwrap();
+    assert_eq!(verify(&ast, &env).err().unwrap().0, "cumulative-spend requires a spend tracker");
+    let undated = RequestBuilder::new().action("payments.create").amount(1.0).build().unwrap();
+    let env = EnvBuilder::new().request(undated).spend_tracker(tracker.clone()).build().unwrap();
+    assert_eq!(verify(&ast, &env).err().unwrap().0, "cumulative-spend requires req[\"day\"]");
+}
+
+#[test]
+fn test_verifier_records_allowed_spend() {
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(WEEKLY_CAP, &issuer_priv, MintOptions::default()).unwrap();
+    let tracker = Arc::new(InMemorySpendTracker::new());
+    let verifier = Verifier::default().with_clock(FixedClock(1_790_000_000)).with_spend_tracker(tracker.clone());
+
+    assert!(verifier.verify(&token, pay("2026-10-13", 150.0), HashMap::new(), None).allow);
+    let denied = verifier.verify(&token, pay("2026-10-13", 75.0), HashMap::new(), None);
+    assert!(!denied.allow && denied.error.is_none());
+    // Denied requests are not recorded.
+    assert!(verifier.verify(&token, pay("2026-10-15", 50.0), HashMap::new(), None).allow);
+    assert_eq!(tracker.spent_between("payments.create", "2026-10-01", "2026-10-31").unwrap(), 200.0);
+
+    let untracked = Verifier::default().with_clock(FixedClock(1_790_000_000));
+    let result = untracked.verify(&token, pay("2026-10-13", 1.0), HashMap::new(), None);
+    assert_eq!(result.code, Some(VerifyErrorCode::Evaluation));
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "tooling")]
+
+use agent_safe_spl::testing::{run_suite, Expectation, PolicySuite};
+
+const POLICY: &str = r#"
+(policy "payments" (and (member (get req "recipient") allowed) (<= (get req "amount") 50)))
+(policy "email" #t)
+"#;
+
+fn suite(cases: serde_json::Value) -> PolicySuite {
+    serde_json::from_value(serde_json::json!({
+        "now": "2026-03-01T12:00:00Z",
+        "vars": { "allowed": ["mom@example.com"] },
+        "cases": cases,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_passing_suite() {
+    let suite = suite(serde_json::json!([
+        { "name": "mom", "expect": "allow",
+          "request": { "action": "payments.create", "recipient": "mom@example.com", "amount": 20 } },
+        { "name": "stranger", "expect": "DENY",
+          "request": { "action": "payments.create", "recipient": "x@example.com", "amount": 20 } },
+        { "name": "case vars override", "expect": "allow", "vars": { "allowed": ["x@example.com"] },
+          "request": { "action": "payments.create", "recipient": "x@example.com", "amount": 20 } },
+        { "name": "email", "expect": "allow", "request": { "action": "email.send" } }
+    ]));
+    let report = run_suite(POLICY, &suite).unwrap();
+    assert!(report.is_success(), "{:?}", report.failed);
+    assert_eq!(report.passed.len(), 4);
+}
+
+#[test]
+fn test_failures_carry_traces() {
+    let suite = suite(serde_json::json!([
+        { "name": "too much", "expect": "allow",
+          "request": { "action": "payments.create", "recipient": "mom@example.com", "amount": 80 } },
+        { "name": "no clause", "expect": "allow", "request": { "action": "calendar.book" } }
+    ]));
+    let report = run_suite(POLICY, &suite).unwrap();
+    assert_eq!(report.failed.len(), 2);
+
+    let too_much = &report.failed[0];
+    assert_eq!((too_much.expected, too_much.actual), (Expectation::Allow, Expectation::Deny));
+    assert_eq!(
+        too_much.trace,
+        vec![
+            r#"#t  (member (get req "recipient") allowed)"#.to_string(),
+            r#"#f  (<= (get req "amount") 50)"#.to_string(),
+        ]
+    );
+
+    let no_clause = &report.failed[1];
+    assert_eq!(no_clause.error.as_deref(), Some("no policy clause for action: calendar.book"));
+    assert!(no_clause.trace.is_empty());
+}
@@ -0,0 +1,1167 @@
+use std::collections::HashMap;
+
+use agent_safe_spl::replay::InMemoryReplayCache;
+use agent_safe_spl::token::{
+    create_presentation_signature, generate_keypair, mint, verify_token_untrusted, verify_token_with_pop, Challenge,
+    MintOptions, Presentation, Token,
+};
+use agent_safe_spl::types::Node;
+
+fn pop_token() -> (agent_safe_spl::Token, String) {
+    let (_, issuer_priv) = generate_keypair();
+    let (agent_pub, agent_priv) = generate_keypair();
+    let token = mint(
+        "(= (get req \"action\") \"read\")",
+        &issuer_priv,
+        MintOptions { pop_key: Some(agent_pub), ..MintOptions::default() },
+    )
+    .unwrap();
+    (token, agent_priv)
+}
+
+fn read_req() -> HashMap<String, Node> {
+    let mut req = HashMap::new();
+    req.insert("action".into(), Node::Str("read".into()));
+    req
+}
+
+fn present(token: &agent_safe_spl::Token, agent_priv: &str, nonce: &str) -> Presentation {
+    let challenge = Challenge { nonce: nonce.into(), timestamp: 1_760_000_000, evidence: None };
+    Presentation {
+        signature: create_presentation_signature(token, agent_priv, &challenge).unwrap(),
+        challenge,
+    }
+}
+
+#[test]
+fn test_pop_presentation_allows() {
+    let (token, agent_priv) = pop_token();
+    let pres = present(&token, &agent_priv, "n-1");
+    let result = verify_token_with_pop(&token, read_req(), HashMap::new(), Some(&pres), None);
+    assert!(result.allow, "{:?}", result.error);
+}
+
+#[test]
+fn test_pop_signature_bound_to_challenge() {
+    let (token, agent_priv) = pop_token();
+    let mut pres = present(&token, &agent_priv, "n-1");
+    pres.challenge.nonce = "n-2".into();
+    let result = verify_token_with_pop(&token, read_req(), HashMap::new(), Some(&pres), None);
+    assert!(!result.allow);
+    assert_eq!(result.error.as_deref(), Some("invalid presentation signature"));
+
+    let mut pres = present(&token, &agent_priv, "n-1");
+    pres.challenge.timestamp += 1;
+    let result = verify_token_with_pop(&token, read_req(), HashMap::new(), Some(&pres), None);
+    assert!(!result.allow);
+}
+
+#[test]
+fn test_pop_replayed_nonce_rejected() {
+    let (token, agent_priv) = pop_token();
+    let cache = InMemoryReplayCache::new();
+    let pres = present(&token, &agent_priv, "n-1");
+
+    let first = verify_token_with_pop(&token, read_req(), HashMap::new(), Some(&pres), Some(&cache));
+    assert!(first.allow, "{:?}", first.error);
+
+    let replay = verify_token_with_pop(&token, read_req(), HashMap::new(), Some(&pres), Some(&cache));
+    assert!(!replay.allow);
+    assert_eq!(replay.error.as_deref(), Some("presentation nonce already used"));
+
+    let fresh = present(&token, &agent_priv, "n-2");
+    let next = verify_token_with_pop(&token, read_req(), HashMap::new(), Some(&fresh), Some(&cache));
+    assert!(next.allow, "{:?}", next.error);
+}
+
+#[test]
+fn test_pop_missing_presentation() {
+    let (token, _) = pop_token();
+    let result = verify_token_with_pop(&token, read_req(), HashMap::new(), None, None);
+    assert!(!result.allow);
+}
+
+const MULTI_POLICY: &str = r#"
+(policy "payments" (<= (get req "amount") 50))
+(policy "email.send" (= (get req "to") "mom@example.com"))
+"#;
+
+fn action_req(action: &str, extra: &[(&str, Node)]) -> HashMap<String, Node> {
+    let mut req = HashMap::new();
+    req.insert("action".into(), Node::Str(action.into()));
+    for (k, v) in extra {
+        req.insert((*k).into(), v.clone());
+    }
+    req
+}
+
+#[test]
+fn test_multi_policy_selects_by_namespace() {
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(MULTI_POLICY, &issuer_priv, MintOptions::default()).unwrap();
+
+    let ok = verify_token_untrusted(&token, action_req("payments.create", &[("amount", Node::Number(20.0))]), HashMap::new());
+    assert!(ok.allow, "{:?}", ok.error);
+    let over = verify_token_untrusted(&token, action_req("payments.create", &[("amount", Node::Number(80.0))]), HashMap::new());
+    assert!(!over.allow);
+
+    let email = verify_token_untrusted(&token, action_req("email.send", &[("to", Node::Str("mom@example.com".into()))]), HashMap::new());
+    assert!(email.allow, "{:?}", email.error);
+
+    let none = verify_token_untrusted(&token, action_req("calendar.book", &[]), HashMap::new());
+    assert!(!none.allow);
+    assert_eq!(none.error.as_deref(), Some("no policy clause for action: calendar.book"));
+}
+
+#[test]
+fn test_multi_policy_signature_covers_all_clauses() {
+    let (_, issuer_priv) = generate_keypair();
+    let mut token = mint(MULTI_POLICY, &issuer_priv, MintOptions::default()).unwrap();
+    token.policy = token.policy.replace("50", "5000");
+    let result = verify_token_untrusted(&token, action_req("email.send", &[("to", Node::Str("mom@example.com".into()))]), HashMap::new());
+    assert!(!result.allow);
+    assert_eq!(result.error.as_deref(), Some("invalid signature"));
+}
+
+#[test]
+fn test_named_clauses_rejects_mixed_source() {
+    use agent_safe_spl::parser::parse_all;
+    use agent_safe_spl::token::named_clauses;
+
+    let exprs = parse_all(r#"(policy "a" #t) (= 1 1)"#).unwrap();
+    assert!(named_clauses(&exprs).is_err());
+    let exprs = parse_all(r#"(policy "a" #t) (policy "a" #f)"#).unwrap();
+    assert!(named_clauses(&exprs).is_err());
+    let exprs = parse_all("(= 1 1)").unwrap();
+    assert!(named_clauses(&exprs).unwrap().is_none());
+}
+
+fn lifetime_token(issued_at: Option<&str>, expires: Option<&str>) -> agent_safe_spl::Token {
+    let (_, issuer_priv) = generate_keypair();
+    mint(
+        "(= (get req \"action\") \"read\")",
+        &issuer_priv,
+        MintOptions {
+            issued_at: issued_at.map(Into::into),
+            expires: expires.map(Into::into),
+            ..MintOptions::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_profile_max_lifetime() {
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::time::FixedClock;
+
+    let profile = VerifierProfile { max_token_lifetime_secs: Some(30 * 86_400), ..VerifierProfile::default() };
+    let verifier = Verifier::new(profile).with_clock(FixedClock(1_775_606_400)); // 2026-04-08
+    let check = |token: &agent_safe_spl::Token| verifier.verify(token, read_req(), HashMap::new(), None);
+
+    let ok = lifetime_token(Some("2026-04-01T00:00:00Z"), Some("2026-04-20T00:00:00Z"));
+    assert!(check(&ok).allow, "{:?}", check(&ok).error);
+
+    let long = lifetime_token(Some("2026-04-01T00:00:00Z"), Some("2026-06-01T00:00:00Z"));
+    let result = check(&long);
+    assert!(!result.allow);
+    assert!(result.error.unwrap().contains("exceeds maximum"));
+
+    let no_expiry = lifetime_token(Some("2026-04-01T00:00:00Z"), None);
+    assert_eq!(check(&no_expiry).error.as_deref(), Some("token has no expiry"));
+
+    let no_issued = lifetime_token(None, Some("2026-04-20T00:00:00Z"));
+    assert_eq!(check(&no_issued).error.as_deref(), Some("token has no issued_at"));
+
+    // The default profile accepts all of these.
+    assert!(verify_token_untrusted(&long, read_req(), HashMap::new()).allow);
+    assert!(verify_token_untrusted(&no_expiry, read_req(), HashMap::new()).allow);
+}
+
+#[test]
+fn test_issued_at_covered_by_signature() {
+    let mut token = lifetime_token(Some("2026-04-01T00:00:00Z"), Some("2026-06-01T00:00:00Z"));
+    token.issued_at = Some("2026-05-30T00:00:00Z".into());
+    let result = verify_token_untrusted(&token, read_req(), HashMap::new());
+    assert_eq!(result.error.as_deref(), Some("invalid signature"));
+}
+
+#[test]
+fn test_presentation_freshness() {
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::time::FixedClock;
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let (agent_pub, agent_priv) = generate_keypair();
+    let token = mint(
+        "(= (get req \"action\") \"read\")",
+        &issuer_priv,
+        MintOptions {
+            pop_key: Some(agent_pub),
+            issued_at: Some("2026-04-01T00:00:00Z".into()),
+            ..MintOptions::default()
+        },
+    )
+    .unwrap();
+    let issued = 1_775_001_600;
+    let present_at = |nonce: &str, timestamp: i64| {
+        let challenge = Challenge { nonce: nonce.into(), timestamp, evidence: None };
+        Presentation { signature: create_presentation_signature(&token, &agent_priv, &challenge).unwrap(), challenge }
+    };
+    let profile = VerifierProfile { max_presentation_age_secs: Some(300), ..VerifierProfile::default() };
+    let verifier = Verifier::new(profile).with_clock(FixedClock(issued + 3600));
+    let check = |pres: &Presentation| verifier.verify(&token, read_req(), HashMap::new(), Some(pres));
+
+    let fresh = check(&present_at("n-1", issued + 3500));
+    assert!(fresh.allow, "{:?}", fresh.error);
+    assert_eq!(fresh.code, None);
+
+    let stale = check(&present_at("n-2", issued + 60));
+    assert!(!stale.allow);
+    assert_eq!(stale.code, Some(VerifyErrorCode::PresentationExpired));
+
+    let future = check(&present_at("n-3", issued + 7200));
+    assert_eq!(future.code, Some(VerifyErrorCode::PresentationExpired));
+
+    // A presentation signed before the token existed is rejected even when
+    // the verifier's clock is later.
+    let early = Verifier::new(VerifierProfile::default()).with_clock(FixedClock(issued + 3600));
+    let result = early.verify(&token, read_req(), HashMap::new(), Some(&present_at("n-4", issued - 3600)));
+    assert_eq!(result.code, Some(VerifyErrorCode::PresentedBeforeIssuance));
+
+    // So is any use while the verifier's clock predates issuance.
+    let before = Verifier::default().with_clock(FixedClock(issued - 3600));
+    let result = before.verify(&token, read_req(), HashMap::new(), Some(&present_at("n-5", issued - 3600)));
+    assert_eq!(result.code, Some(VerifyErrorCode::PresentedBeforeIssuance));
+}
+
+#[test]
+fn test_token_summary() {
+    use agent_safe_spl::keys::key_id;
+    use agent_safe_spl::summary::{FieldBound, TokenSummary};
+
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let token = mint(
+        r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50) (>= (get req "amount") 1)
+                (member (get req "recipient") allowed) (limits (amount 0 75) (per_day 3)))"#,
+        &issuer_priv,
+        MintOptions { sealed: true, expires: Some("2026-05-01T00:00:00Z".into()), ..MintOptions::default() },
+    )
+    .unwrap();
+
+    let summary = TokenSummary::from(&token);
+    assert_eq!(summary.issuer_fingerprint, key_id(&issuer_pub));
+    assert!(summary.sealed);
+    assert!(!summary.pop_bound);
+    assert_eq!(summary.expires.as_deref(), Some("2026-05-01T00:00:00Z"));
+    assert_eq!(summary.clauses.len(), 1);
+    let clause = &summary.clauses[0];
+    assert_eq!(clause.name, None);
+    assert_eq!(clause.claims.get("action").map(String::as_str), Some("payments.create"));
+    assert_eq!(clause.envelope, vec![FieldBound { field: "amount".into(), min: Some(1.0), max: Some(50.0) }]);
+    assert_eq!(clause.per_day, Some(3.0));
+    assert_eq!(clause.explanation[0], r#"req.action = "payments.create""#);
+    assert_eq!(clause.explanation[3], "req.recipient in allowed");
+
+    // Serializes for UIs.
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["clauses"][0]["envelope"][0]["max"], 50.0);
+
+    let multi = mint(MULTI_POLICY, &issuer_priv, MintOptions::default()).unwrap();
+    let names: Vec<_> = TokenSummary::from(&multi).clauses.into_iter().map(|c| c.name.unwrap()).collect();
+    assert_eq!(names, ["payments", "email.send"]);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_policy() {
+    use agent_safe_spl::crypto::generate_x25519_keypair;
+    use agent_safe_spl::token::{verify_encrypted_token, VerifyErrorCode};
+
+    let (_, issuer_priv) = generate_keypair();
+    let (verifier_pub, verifier_priv) = generate_x25519_keypair();
+    let policy = r#"(member (get req "recipient") (tuple "mom@example.com"))"#;
+    let token = mint(
+        policy,
+        &issuer_priv,
+        MintOptions { encrypt_policy_to: Some(verifier_pub), ..MintOptions::default() },
+    )
+    .unwrap();
+    assert!(token.policy.is_empty());
+    assert!(!serde_json::to_string(&token).unwrap().contains("mom@example.com"));
+
+    let mut req = HashMap::new();
+    req.insert("recipient".to_string(), Node::Str("mom@example.com".into()));
+    let result = verify_encrypted_token(&token, req.clone(), HashMap::new(), &verifier_priv);
+    assert!(result.allow, "{:?}", result.error);
+
+    let no_key = verify_token_untrusted(&token, req.clone(), HashMap::new());
+    assert_eq!(no_key.code, Some(VerifyErrorCode::PolicyDecryption));
+    let (_, other_priv) = generate_x25519_keypair();
+    let wrong_key = verify_encrypted_token(&token, req.clone(), HashMap::new(), &other_priv);
+    assert_eq!(wrong_key.code, Some(VerifyErrorCode::PolicyDecryption));
+
+    // The ciphertext is covered by the issuer signature.
+    let mut swapped = token.clone();
+    let (other_pub, _) = generate_x25519_keypair();
+    swapped.encrypted_policy = Some(agent_safe_spl::crypto::seal(&other_pub, b"#t").unwrap());
+    let result = verify_encrypted_token(&swapped, req, HashMap::new(), &verifier_priv);
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidSignature));
+}
+
+#[test]
+fn test_standard_vars_injected() {
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::time::FixedClock;
+    use agent_safe_spl::vars::StandardVar;
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(
+        r#"(and (before now "2026-05-01T00:00:00Z") (= day "2026-04-08"))"#,
+        &issuer_priv,
+        MintOptions::default(),
+    )
+    .unwrap();
+    let clock = FixedClock(1_775_606_400); // 2026-04-08T00:00:00Z
+
+    let verifier = Verifier::default().with_clock(clock);
+    let result = verifier.verify(&token, read_req(), HashMap::new(), None);
+    assert!(result.allow, "{:?}", result.error);
+
+    // Caller-supplied values win over injected ones.
+    let mut vars = HashMap::new();
+    vars.insert("day".to_string(), Node::Str("2026-04-09".into()));
+    assert!(!verifier.verify(&token, read_req(), vars, None).allow);
+
+    let profile = VerifierProfile { disabled_vars: vec![StandardVar::Day], strict: true, ..VerifierProfile::default() };
+    let strict = Verifier::new(profile).with_clock(clock);
+    let result = strict.verify(&token, read_req(), HashMap::new(), None);
+    assert!(!result.allow);
+    assert_eq!(result.error.as_deref(), Some("policy references disabled standard var: day"));
+
+    let pop = pop_token().0;
+    assert_eq!(StandardVar::ActorPub.value(&pop, 0), pop.pop_key.clone().map(Node::Str));
+    assert_eq!(StandardVar::from_name("now"), Some(StandardVar::Now));
+}
+
+#[test]
+fn test_signed_token_vars() {
+    use std::collections::BTreeMap;
+
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let allowed = Node::List(vec![Node::Str("mom@example.com".into())]);
+    let opts = MintOptions {
+        vars: BTreeMap::from([("allowed_recipients".to_string(), allowed)]),
+        ..MintOptions::default()
+    };
+    let token = mint(
+        r#"(and (member (get req "recipient") allowed_recipients) (<= (get req "amount") max_amount))"#,
+        &issuer_priv,
+        opts,
+    )
+    .unwrap();
+    let mut req = HashMap::new();
+    req.insert("recipient".to_string(), Node::Str("mom@example.com".into()));
+    req.insert("amount".to_string(), Node::Int(10));
+
+    // Relying-party vars can add names the issuer left open...
+    let mut vars = HashMap::new();
+    vars.insert("max_amount".to_string(), Node::Int(50));
+    let result = verify_token_untrusted(&token, req.clone(), vars.clone());
+    assert!(result.allow, "{:?}", result.error);
+
+    // ...but cannot widen the issuer's.
+    vars.insert("allowed_recipients".to_string(), Node::List(vec![Node::Str("eve@example.com".into())]));
+    assert!(verify_token_untrusted(&token, req.clone(), vars.clone()).allow);
+    req.insert("recipient".to_string(), Node::Str("eve@example.com".into()));
+    assert!(!verify_token_untrusted(&token, req.clone(), vars.clone()).allow);
+
+    // The vars survive JSON and are covered by the signature.
+    let json = serde_json::to_string(&token).unwrap();
+    assert!(json.contains("allowed_recipients"));
+    let mut tampered: agent_safe_spl::Token = serde_json::from_str(&json).unwrap();
+    assert_eq!(tampered, token);
+    tampered.vars.insert("allowed_recipients".into(), Node::List(vec![Node::Str("eve@example.com".into())]));
+    let result = verify_token_untrusted(&tampered, req, vars);
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidSignature));
+
+    // Standard vars stay the verifier's.
+    let opts = MintOptions {
+        vars: BTreeMap::from([("now".to_string(), Node::Str("2020-01-01T00:00:00Z".into()))]),
+        ..MintOptions::default()
+    };
+    assert!(mint("#t", &issuer_priv, opts).is_err());
+}
+
+#[test]
+fn test_signature_scheme_negotiation() {
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::signature::SignatureScheme;
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint("(= (get req \"action\") \"read\")", &issuer_priv, MintOptions::default()).unwrap();
+    assert_eq!(token.alg, SignatureScheme::Ed25519);
+    assert!(!serde_json::to_string(&token).unwrap().contains("\"alg\""));
+
+    let profile = VerifierProfile { accepted_algs: Some(vec![SignatureScheme::P256]), ..VerifierProfile::default() };
+    let result = Verifier::new(profile).verify(&token, read_req(), HashMap::new(), None);
+    assert_eq!(result.code, Some(VerifyErrorCode::UnsupportedAlgorithm));
+
+    // Claiming a different scheme does not verify.
+    let mut relabeled = token.clone();
+    relabeled.alg = SignatureScheme::Secp256k1;
+    assert!(!verify_token_untrusted(&relabeled, read_req(), HashMap::new()).allow);
+}
+
+#[cfg(all(feature = "p256", feature = "secp256k1"))]
+#[test]
+fn test_ecdsa_tokens() {
+    use agent_safe_spl::signature::SignatureScheme;
+
+    for alg in [SignatureScheme::P256, SignatureScheme::Secp256k1] {
+        let (public_key, private_key) = alg.generate_keypair().unwrap();
+        let token = mint(
+            "(= (get req \"action\") \"read\")",
+            &private_key,
+            MintOptions { alg, ..MintOptions::default() },
+        )
+        .unwrap();
+        assert_eq!(token.public_key, public_key);
+        let json = serde_json::to_string(&token).unwrap();
+        assert!(json.contains(&format!("\"alg\":\"{}\"", alg.name())));
+        let result = verify_token_untrusted(&token, read_req(), HashMap::new());
+        assert!(result.allow, "{alg:?}: {:?}", result.error);
+
+        let mut tampered = token.clone();
+        tampered.sealed = true;
+        assert!(!verify_token_untrusted(&tampered, read_req(), HashMap::new()).allow);
+    }
+}
+
+#[test]
+fn test_caveats_attenuate() {
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(
+        r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50))"#,
+        &issuer_priv,
+        MintOptions { attenuable: true, ..MintOptions::default() },
+    )
+    .unwrap();
+    let pay = |amount: f64| action_req("payments.create", &[("amount", Node::Number(amount))]);
+    assert!(verify_token_untrusted(&token, pay(40.0), HashMap::new()).allow);
+
+    let narrowed = token.add_caveat(r#"(<= (get req "amount") 20)"#).unwrap();
+    assert!(verify_token_untrusted(&narrowed, pay(15.0), HashMap::new()).allow);
+    assert!(!verify_token_untrusted(&narrowed, pay(40.0), HashMap::new()).allow);
+
+    let twice = narrowed.add_caveat(r#"(>= (get req "amount") 10)"#).unwrap();
+    assert!(!verify_token_untrusted(&twice, pay(5.0), HashMap::new()).allow);
+    assert!(verify_token_untrusted(&twice, pay(15.0), HashMap::new()).allow);
+
+    // Stripping the last caveat leaves a proof that no longer matches.
+    let mut stripped = twice.clone();
+    stripped.caveats.pop();
+    let result = verify_token_untrusted(&stripped, pay(5.0), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidCaveat));
+
+    // Editing a caveat breaks its signature.
+    let mut edited = narrowed.clone();
+    edited.caveats[0].policy = r#"(<= (get req "amount") 2000)"#.into();
+    assert_eq!(verify_token_untrusted(&edited, pay(40.0), HashMap::new()).code, Some(VerifyErrorCode::InvalidCaveat));
+
+    // Non-attenuable tokens refuse caveats; bad caveat source is rejected.
+    let plain = mint("#t", &issuer_priv, MintOptions::default()).unwrap();
+    assert!(plain.add_caveat("#f").is_err());
+    assert!(token.add_caveat("(and").is_err());
+}
+
+#[test]
+fn test_token_scope() {
+    use agent_safe_spl::scope::glob_matches;
+    use agent_safe_spl::summary::TokenSummary;
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    assert!(glob_matches("payments.*", "payments.refund.partial"));
+    assert!(!glob_matches("payments.*", "payments"));
+    assert!(!glob_matches("payments.*", "reports.payments.read"));
+    assert!(glob_matches("*.read", "reports.read"));
+    assert!(glob_matches("a*b*c", "a-b-b-c"));
+    assert!(!glob_matches("a*b*c", "a-c-b"));
+    assert!(glob_matches("read", "read") && !glob_matches("read", "reader"));
+
+    let (_, issuer_priv) = generate_keypair();
+    let scope = vec!["payments.*".to_string(), "read".to_string()];
+    let token = mint(r#"(action-in "payments.*" "read")"#, &issuer_priv, MintOptions { scope: scope.clone(), ..MintOptions::default() })
+        .unwrap();
+    assert!(verify_token_untrusted(&token, read_req(), HashMap::new()).allow);
+    let action = |a: &str| HashMap::from([("action".to_string(), Node::Str(a.into()))]);
+    assert!(verify_token_untrusted(&token, action("payments.create"), HashMap::new()).allow);
+
+    let result = verify_token_untrusted(&token, action("admin.delete"), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::OutOfScope));
+    assert_eq!(result.error.as_deref(), Some("action \"admin.delete\" is outside the token's scope"));
+    let result = verify_token_untrusted(&token, HashMap::new(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::OutOfScope));
+
+    // The scope is signed.
+    let mut widened = token.clone();
+    widened.scope.push("admin.*".into());
+    let result = verify_token_untrusted(&widened, action("admin.delete"), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidSignature));
+
+    assert_eq!(TokenSummary::from(&token).scope, scope);
+    assert!(mint("#t", &issuer_priv, MintOptions { scope: vec![String::new()], ..MintOptions::default() }).is_err());
+
+    // Scopes compare under the profile's string matching.
+    #[cfg(feature = "unicode")]
+    {
+        use agent_safe_spl::profile::{Verifier, VerifierProfile};
+        use agent_safe_spl::types::StringMatch;
+
+        let cafe = mint("#t", &issuer_priv, MintOptions { scope: vec!["caf\u{e9}.*".into()], ..MintOptions::default() })
+            .unwrap();
+        let nfd = action("cafe\u{301}.order");
+        assert_eq!(verify_token_untrusted(&cafe, nfd.clone(), HashMap::new()).code, Some(VerifyErrorCode::OutOfScope));
+        let profile = VerifierProfile { string_match: StringMatch::Nfc, ..VerifierProfile::default() };
+        assert!(Verifier::new(profile).verify(&cafe, nfd, HashMap::new(), None).allow);
+    }
+}
+
+#[test]
+fn test_chain_limits() {
+    use agent_safe_spl::caveat::ChainLimits;
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let mut token = mint(
+        r#"(= (get req "action") "read")"#,
+        &issuer_priv,
+        MintOptions { attenuable: true, ..MintOptions::default() },
+    )
+    .unwrap();
+    for _ in 0..3 {
+        token = token.add_caveat(r#"(= (get req "action") "read")"#).unwrap();
+    }
+    let check = |limits: ChainLimits| {
+        let verifier = Verifier::new(VerifierProfile { chain_limits: limits, ..VerifierProfile::default() });
+        verifier.verify(&token, read_req(), HashMap::new(), None)
+    };
+
+    assert!(check(ChainLimits::default()).allow);
+    let result = check(ChainLimits { max_caveats: 2, ..ChainLimits::default() });
+    assert_eq!(result.code, Some(VerifyErrorCode::ChainLimit));
+    assert_eq!(result.error.as_deref(), Some("token has 3 caveats, maximum 2"));
+    let result = check(ChainLimits { max_chain_depth: 2, ..ChainLimits::default() });
+    assert_eq!(result.error.as_deref(), Some("delegation chain depth 3 exceeds maximum 2"));
+    let result = check(ChainLimits { max_total_policy_bytes: 100, ..ChainLimits::default() });
+    assert_eq!(result.error.as_deref(), Some("chain policies total 116 bytes, maximum 100"));
+}
+
+#[test]
+fn test_caveats_carry_attenuation_proofs() {
+    use agent_safe_spl::caveat::parent_hash;
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(
+        r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50))"#,
+        &issuer_priv,
+        MintOptions { attenuable: true, ..MintOptions::default() },
+    )
+    .unwrap();
+    let pay = |amount: f64| action_req("payments.create", &[("amount", Node::Number(amount))]);
+
+    let narrowed = token.add_caveat(r#"(<= (get req "amount") 20)"#).unwrap();
+    let proof = narrowed.caveats[0].proof.clone().unwrap();
+    assert_eq!(proof.parent_hash, parent_hash(&token, 0));
+    assert_eq!(proof.constraint, narrowed.caveats[0].policy);
+    assert!(proof.strict);
+    assert_eq!(proof.transcript[0].rule, "conjunction");
+    assert!(proof.transcript.iter().any(|s| s.rule == "tightened" && s.detail.contains("amount <= 20")));
+
+    // Restating a parent bound is not a strict narrowing.
+    let restated = token.add_caveat(r#"(<= (get req "amount") 50)"#).unwrap();
+    assert!(!restated.caveats[0].proof.as_ref().unwrap().strict);
+    // A second caveat is compared against the first.
+    let looser = narrowed.add_caveat(r#"(<= (get req "amount") 30)"#).unwrap();
+    assert!(!looser.caveats[1].proof.as_ref().unwrap().strict);
+    let added = narrowed.add_caveat(r#"(= (get req "recipient") "bob")"#).unwrap();
+    let proof = added.caveats[1].proof.as_ref().unwrap();
+    assert!(proof.strict);
+    assert_eq!(proof.parent_hash, parent_hash(&narrowed, 1));
+
+    // Proofs survive JSON and are covered by the caveat signature.
+    let json = serde_json::to_string(&narrowed).unwrap();
+    let decoded: agent_safe_spl::Token = serde_json::from_str(&json).unwrap();
+    assert!(verify_token_untrusted(&decoded, pay(15.0), HashMap::new()).allow);
+
+    let mut tampered = narrowed.clone();
+    tampered.caveats[0].proof.as_mut().unwrap().strict = false;
+    assert_eq!(verify_token_untrusted(&tampered, pay(15.0), HashMap::new()).code, Some(VerifyErrorCode::InvalidCaveat));
+
+    // A proof moved onto another token names the wrong parent.
+    let other = mint(
+        r#"(and (= (get req "action") "payments.create") (<= (get req "amount") 50))"#,
+        &issuer_priv,
+        MintOptions { attenuable: true, ..MintOptions::default() },
+    )
+    .unwrap()
+    .add_caveat(r#"(<= (get req "amount") 20)"#)
+    .unwrap();
+    let mut swapped = other.clone();
+    swapped.caveats[0].proof = narrowed.caveats[0].proof.clone();
+    assert_eq!(verify_token_untrusted(&swapped, pay(15.0), HashMap::new()).code, Some(VerifyErrorCode::InvalidCaveat));
+}
+
+#[test]
+fn test_obligations_surface_on_allow() {
+    use agent_safe_spl::obligations::Obligation;
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(
+        r#"(and (<= (get req "amount") 100) (obligate "notify-owner" 3600) (obligate "log"))"#,
+        &issuer_priv,
+        MintOptions::default(),
+    )
+    .unwrap();
+    let pay = |amount: f64| action_req("payments.create", &[("amount", Node::Number(amount))]);
+
+    let result = verify_token_untrusted(&token, pay(40.0), HashMap::new());
+    assert!(result.allow);
+    assert_eq!(
+        result.obligations,
+        vec![
+            Obligation { kind: "notify-owner".into(), deadline_secs: Some(3600) },
+            Obligation { kind: "log".into(), deadline_secs: None },
+        ]
+    );
+
+    let denied = verify_token_untrusted(&token, pay(400.0), HashMap::new());
+    assert!(!denied.allow);
+    assert!(denied.obligations.is_empty());
+}
+
+#[test]
+fn test_deny_with_reports_reason_code() {
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint(
+        r#"(and (or (= (get req "action") "payments.create") (deny-with "ACTION_NOT_ALLOWED"))
+                (or (<= (get req "amount") 100) (deny-with "AMOUNT_TOO_HIGH"))
+                (or (<= (get req "amount") 50) (deny-with "NEVER_REACHED")))"#,
+        &issuer_priv,
+        MintOptions::default(),
+    )
+    .unwrap();
+    let req = |action: &str, amount: f64| action_req(action, &[("amount", Node::Number(amount))]);
+
+    let denied = verify_token_untrusted(&token, req("payments.create", 400.0), HashMap::new());
+    assert!(!denied.allow && denied.error.is_none());
+    assert_eq!(denied.reason_code.as_deref(), Some("AMOUNT_TOO_HIGH"));
+    let wrong = verify_token_untrusted(&token, req("payments.refund", 400.0), HashMap::new());
+    assert_eq!(wrong.reason_code.as_deref(), Some("ACTION_NOT_ALLOWED"));
+
+    // A branch that denied but was rescued by another is not reported.
+    let rescued = mint(r#"(or (deny-with "FIRST") #t)"#, &issuer_priv, MintOptions::default()).unwrap();
+    let allowed = verify_token_untrusted(&rescued, req("payments.create", 1.0), HashMap::new());
+    assert!(allowed.allow);
+    assert_eq!(allowed.reason_code, None);
+
+    let bad = mint(r#"(deny-with "not a code")"#, &issuer_priv, MintOptions::default()).unwrap();
+    let result = verify_token_untrusted(&bad, req("payments.create", 1.0), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::Evaluation));
+    assert_eq!(result.reason_code, None);
+}
+
+#[test]
+fn test_verify_any_picks_first_allowing_token() {
+    use agent_safe_spl::token::{verify_any, VerifyErrorCode};
+
+    let (_, owner_a) = generate_keypair();
+    let (_, owner_b) = generate_keypair();
+    let small = mint(r#"(<= (get req "amount") 10)"#, &owner_a, MintOptions::default()).unwrap();
+    let large = mint(r#"(<= (get req "amount") 100)"#, &owner_b, MintOptions::default()).unwrap();
+    let also_large = mint(r#"(<= (get req "amount") 100)"#, &owner_a, MintOptions::default()).unwrap();
+    let mut forged = small.clone();
+    forged.policy = "#t".into();
+    let tokens = [forged, small, large, also_large];
+    let pay = |amount: f64| action_req("payments.create", &[("amount", Node::Number(amount))]);
+
+    let result = verify_any(&tokens, pay(50.0), HashMap::new());
+    assert!(result.allow);
+    assert_eq!(result.token_index, Some(2));
+    assert_eq!(result.results.len(), 3);
+    assert_eq!(result.results[0].code, Some(VerifyErrorCode::InvalidSignature));
+    assert!(result.results[1].error.is_none() && !result.results[1].allow);
+    assert!(result.decision().unwrap().allow);
+
+    let denied = verify_any(&tokens, pay(500.0), HashMap::new());
+    assert!(!denied.allow);
+    assert_eq!(denied.token_index, None);
+    assert!(denied.decision().is_none());
+    assert_eq!(denied.results.len(), tokens.len());
+
+    assert!(!verify_any(&[], pay(1.0), HashMap::new()).allow);
+}
+
+#[test]
+fn test_token_id_is_signed_and_reported() {
+    use agent_safe_spl::token::{new_token_id, VerifyErrorCode};
+
+    let (_, issuer_priv) = generate_keypair();
+    let id = new_token_id();
+    assert_eq!(id.len(), 36);
+    assert_eq!(id.as_bytes()[14], b'4');
+    assert_ne!(id, new_token_id());
+
+    let opts = MintOptions { token_id: Some(id.clone()), ..MintOptions::default() };
+    let token = mint("(= (get req \"action\") \"read\")", &issuer_priv, opts).unwrap();
+    assert_eq!(token.id(), id);
+    let result = verify_token_untrusted(&token, read_req(), HashMap::new());
+    assert!(result.allow);
+    assert_eq!(result.token_id, id);
+
+    // The id is part of the signed envelope.
+    let mut renamed = token.clone();
+    renamed.token_id = Some(new_token_id());
+    let result = verify_token_untrusted(&renamed, read_req(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::InvalidSignature));
+    assert_eq!(result.token_id, renamed.token_id.unwrap());
+
+    // Tokens minted without an id fall back to a hash of the signed token.
+    let (plain, _) = pop_token();
+    assert!(plain.token_id.is_none());
+    assert_eq!(plain.id().len(), 64);
+    assert_eq!(plain.id(), id_after_roundtrip(&plain));
+    let (other, _) = pop_token();
+    assert_ne!(plain.id(), other.id());
+
+    for bad in ["", "has space", "nul\0byte", &"x".repeat(129)] {
+        let opts = MintOptions { token_id: Some(bad.into()), ..MintOptions::default() };
+        assert!(mint("#t", &issuer_priv, opts).is_err(), "{bad:?}");
+    }
+}
+
+fn id_after_roundtrip(token: &agent_safe_spl::Token) -> String {
+    let json = serde_json::to_string(token).unwrap();
+    serde_json::from_str::<agent_safe_spl::Token>(&json).unwrap().id().into_owned()
+}
+
+#[test]
+fn test_envelope_versions() {
+    use agent_safe_spl::signature::SignatureScheme;
+    use agent_safe_spl::token::{envelope_payload, EnvelopeVersion, VerifyErrorCode};
+
+    let (_, issuer_priv) = generate_keypair();
+    let token = mint("(= (get req \"action\") \"read\")", &issuer_priv, MintOptions::default()).unwrap();
+    assert_eq!(token.envelope_version().unwrap(), EnvelopeVersion::CURRENT);
+    assert_eq!(EnvelopeVersion::parse("0.1.7").unwrap(), EnvelopeVersion::V0_1);
+
+    // A 0.1 token signs the same five fields, so it verifies and migrates.
+    let legacy = agent_safe_spl::Token { version: "0.1.0".into(), ..token.clone() };
+    assert!(verify_token_untrusted(&legacy, read_req(), HashMap::new()).allow);
+    let migrated = legacy.migrate().unwrap();
+    assert_eq!(migrated.version, "0.3.0");
+    assert!(verify_token_untrusted(&migrated, read_req(), HashMap::new()).allow);
+
+    // Unknown versions are rejected rather than verified under a guessed payload.
+    for version in ["1.0.0", "0.4.0", "0.2", "v0.2.0", ""] {
+        let future = agent_safe_spl::Token { version: version.into(), ..token.clone() };
+        let result = verify_token_untrusted(&future, read_req(), HashMap::new());
+        assert_eq!(result.code, Some(VerifyErrorCode::UnsupportedVersion), "{version:?}");
+        assert!(future.migrate().is_err());
+    }
+
+    // 0.1 signatures never covered extension fields.
+    let opts = MintOptions { issued_at: Some("2026-01-01T00:00:00Z".into()), ..MintOptions::default() };
+    let extended = mint("#t", &issuer_priv, opts).unwrap();
+    let downgraded = agent_safe_spl::Token { version: "0.1.0".into(), ..extended };
+    let result = verify_token_untrusted(&downgraded, read_req(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::MalformedToken));
+    assert_eq!(downgraded.migrate().err().unwrap().0, "version 0.1.0 tokens cannot carry issued_at");
+
+    // From 0.3 the signature covers pop_key, so it cannot be stripped; a
+    // 0.2 PoP token signed without it cannot be relabelled 0.3.
+    let (agent_pub, _) = generate_keypair();
+    let bound = mint("#t", &issuer_priv, MintOptions { pop_key: Some(agent_pub), ..MintOptions::default() }).unwrap();
+    let stripped = agent_safe_spl::Token { pop_key: None, ..bound.clone() };
+    assert_eq!(verify_token_untrusted(&stripped, read_req(), HashMap::new()).code, Some(VerifyErrorCode::InvalidSignature));
+
+    let mut legacy = agent_safe_spl::Token { version: "0.2.0".into(), ..bound };
+    legacy.signature = SignatureScheme::Ed25519.sign(&issuer_priv, &envelope_payload(&legacy)).unwrap();
+    assert_eq!(verify_token_untrusted(&legacy, read_req(), HashMap::new()).code, Some(VerifyErrorCode::PresentationRequired));
+    assert!(legacy.migrate().err().unwrap().0.contains("mint a new token"));
+
+    // Fields added after 0.2 shipped are not part of its payload.
+    let opts = MintOptions { epoch: Some(3), ..MintOptions::default() };
+    let mut epoch = agent_safe_spl::Token { version: "0.2.0".into(), ..mint("#t", &issuer_priv, opts).unwrap() };
+    epoch.signature = SignatureScheme::Ed25519.sign(&issuer_priv, &envelope_payload(&epoch)).unwrap();
+    assert_eq!(verify_token_untrusted(&epoch, read_req(), HashMap::new()).code, Some(VerifyErrorCode::MalformedToken));
+    assert_eq!(epoch.migrate().err().unwrap().0, "version 0.2.0 tokens cannot carry epoch");
+
+    // Signed strings cannot forge a separator, so no two envelopes sign
+    // the same payload.
+    let opts = MintOptions {
+        expires: Some("2030-01-01T00:00:00Z".into()),
+        issued_at: Some("2026-01-01T00:00:00Z".into()),
+        ..MintOptions::default()
+    };
+    let honest = mint("#t", &issuer_priv, opts).unwrap();
+    let forged = agent_safe_spl::Token {
+        expires: Some("2030-01-01T00:00:00Z\0issued_at=2026-01-01T00:00:00Z".into()),
+        issued_at: None,
+        ..honest.clone()
+    };
+    assert_eq!(envelope_payload(&forged), envelope_payload(&honest));
+    assert_eq!(verify_token_untrusted(&forged, read_req(), HashMap::new()).code, Some(VerifyErrorCode::MalformedToken));
+    let opts = MintOptions { expires: forged.expires.clone(), ..MintOptions::default() };
+    assert_eq!(mint("#t", &issuer_priv, opts).unwrap_err().0, "expires may not contain control characters");
+    assert!(mint("(and #t\0)", &issuer_priv, MintOptions::default()).unwrap_err().0.contains("NUL"));
+    assert!(mint("(and #t\n     #t)", &issuer_priv, MintOptions::default()).is_ok());
+}
+
+#[test]
+fn test_detached_signature() {
+    use agent_safe_spl::signature::SignatureScheme;
+    use agent_safe_spl::token::mint_unsigned;
+
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let (unsigned, payload) = mint_unsigned("(= (get req \"action\") \"read\")", MintOptions::default()).unwrap();
+    assert_eq!(payload, unsigned.payload());
+
+    // The "HSM" only ever sees the payload bytes.
+    let sig = SignatureScheme::Ed25519.sign(&issuer_priv, &payload).unwrap();
+    let token = unsigned.clone().attach_signature(&sig, &issuer_pub).unwrap();
+    assert_eq!(token.public_key, issuer_pub);
+    assert!(verify_token_untrusted(&token, read_req(), HashMap::new()).allow);
+
+    let (other_pub, _) = generate_keypair();
+    assert!(unsigned.clone().attach_signature(&sig, &other_pub).is_err());
+    let wrong = SignatureScheme::Ed25519.sign(&issuer_priv, b"not the payload").unwrap();
+    assert!(unsigned.attach_signature(&wrong, &issuer_pub).is_err());
+}
+
+#[test]
+fn test_mint_with_signer() {
+    use agent_safe_spl::keys::{Ed25519Signer, Signer};
+    use agent_safe_spl::token::mint_with_signer;
+    use agent_safe_spl::types::SplError;
+
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let signer = Ed25519Signer::from_hex(&issuer_priv).unwrap();
+    assert_eq!(signer.public_key(), issuer_pub);
+    let token = mint_with_signer("(= (get req \"action\") \"read\")", &signer, MintOptions::default()).unwrap();
+    assert_eq!(token.public_key, issuer_pub);
+    assert!(verify_token_untrusted(&token, read_req(), HashMap::new()).allow);
+
+    // A backend that signs with a different key than it reports is caught.
+    struct Mismatched(Ed25519Signer, String);
+    impl Signer for Mismatched {
+        fn public_key(&self) -> String {
+            self.1.clone()
+        }
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SplError> {
+            self.0.sign(message)
+        }
+    }
+    let (other_pub, _) = generate_keypair();
+    assert!(mint_with_signer("#t", &Mismatched(signer, other_pub), MintOptions::default()).is_err());
+    assert!(Ed25519Signer::from_hex("zz").is_err());
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn test_consent_bundle() {
+    use agent_safe_spl::consent::{mint_with_consent, verify_consent_bundle};
+
+    let (_, issuer_priv) = generate_keypair();
+    let policy = "(and (= (get req \"action\") \"read\") (<= (get req \"amount\") 50))";
+    let bundle = mint_with_consent(policy, &issuer_priv, MintOptions::default()).unwrap();
+    assert_eq!(bundle.policy_description, "- action must be read\n- amount must be ≤ 50\n");
+    assert_eq!(bundle.token.issued_at.as_deref(), Some(bundle.minted_at.as_str()));
+    verify_consent_bundle(&bundle, None).unwrap();
+
+    let mut shown_other = bundle.clone();
+    shown_other.policy_description = "- amount must be ≤ 5000\n".into();
+    assert!(verify_consent_bundle(&shown_other, None).unwrap_err().0.contains("description"));
+
+    let mut swapped = bundle.clone();
+    swapped.token = mint("#t", &issuer_priv, MintOptions { issued_at: Some(bundle.minted_at.clone()), ..MintOptions::default() })
+        .unwrap();
+    assert!(verify_consent_bundle(&swapped, None).unwrap_err().0.contains("hash"));
+
+    let mut backdated = bundle.clone();
+    backdated.minted_at = "2020-01-01T00:00:00Z".into();
+    assert!(verify_consent_bundle(&backdated, None).is_err());
+
+    let mut tampered = bundle;
+    tampered.token.policy = "#t".into();
+    assert!(verify_consent_bundle(&tampered, None).unwrap_err().0.contains("signature"));
+}
+
+#[test]
+fn test_profile_allowed_ops() {
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let allowed = ["and", "or", "=", "get"].map(String::from).into();
+    let verifier = Verifier::new(VerifierProfile { allowed_ops: Some(allowed), ..VerifierProfile::default() });
+    let check = |policy: &str| {
+        let token = mint(policy, &issuer_priv, MintOptions::default()).unwrap();
+        verifier.verify(&token, read_req(), HashMap::new(), None)
+    };
+
+    assert!(check(r#"(and (= (get req "action") "read"))"#).allow);
+
+    // Rejected up front, even where evaluation would short-circuit past it.
+    let result = check(r#"(or (= (get req "action") "read") (vrf_ok? "2026-01-01" 5))"#);
+    assert!(!result.allow);
+    assert_eq!(result.code, Some(VerifyErrorCode::OperatorNotPermitted));
+    assert_eq!(result.error.as_deref(), Some("operator not permitted: vrf_ok?"));
+}
+
+#[test]
+fn test_canonical_json_vector() {
+    use agent_safe_spl::token::keypair_from_seed;
+
+    let (_, private) = keypair_from_seed(&"01".repeat(32)).unwrap();
+    let opts = MintOptions {
+        expires: Some("2026-12-31T00:00:00Z".into()),
+        vars: [("cap".to_string(), Node::Number(2.5e-7)), ("label".into(), Node::Str("caf\u{e9}\n".into()))].into(),
+        ..MintOptions::default()
+    };
+    let token = mint("(<= (get req \"amount\") cap)", &private, opts).unwrap();
+    assert_eq!(
+        token.to_canonical_json(),
+        concat!(
+            r#"{"expires":"2026-12-31T00:00:00Z","policy":"(<= (get req \"amount\") cap)","#,
+            r#""public_key":"8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c","sealed":false,"#,
+            r#""signature":"bcbee3bb80f0e399e7b88f4720a5cd3ddbef859956acb7aed9c3fd57f4cc2b9d"#,
+            r#"6d627784d515383510fedf4b455d3d00f3cdf27431448fde6af1481a11518e05","#,
+            r#""vars":{"cap":2.5e-7,"label":"café\n"},"version":"0.3.0"}"#,
+        )
+    );
+    assert_eq!(token.id(), "badebb4954d78390ebda868f10f0f8b6c04d1ff156d413830a2911a2cbb597ab");
+
+    // The id covers only what the issuer signed.
+    let reformatted = Token { policy: format!("  {}\n", token.policy), version: "0.2.9".into(), ..token.clone() };
+    assert_ne!(reformatted.to_canonical_json(), token.to_canonical_json());
+    assert_eq!(reformatted.id(), token.id());
+
+    let (_, issuer_priv) = generate_keypair();
+    let attenuable = mint("#t", &issuer_priv, MintOptions { attenuable: true, ..MintOptions::default() }).unwrap();
+    assert_eq!(attenuable.add_caveat("#t").unwrap().id(), attenuable.id());
+}
+
+#[test]
+fn test_multisig_threshold() {
+    use agent_safe_spl::issuers::{TrustedIssuers, TrustedKey};
+    use agent_safe_spl::token::{mint_multisig, verify_token, VerifyErrorCode};
+
+    let keys: Vec<(String, String)> = (0..3).map(|_| generate_keypair()).collect();
+    let publics: Vec<String> = keys.iter().map(|(public, _)| public.clone()).collect();
+    let issuers = publics.iter().fold(TrustedIssuers::new(), |set, key| set.with_key(TrustedKey::new(key)));
+    let verify = |t: &agent_safe_spl::Token| verify_token(t, read_req(), HashMap::new(), &issuers);
+    let policy = "(= (get req \"action\") \"read\")";
+    let two_of_three = |signers: &[usize]| {
+        let privates: Vec<&str> = signers.iter().map(|&i| keys[i].1.as_str()).collect();
+        let opts = MintOptions { signers: publics.clone(), ..MintOptions::default() };
+        mint_multisig(policy, &privates, 2, opts)
+    };
+
+    let token = two_of_three(&[0, 2]).unwrap();
+    assert_eq!(token.public_key, publics[0]);
+    assert_eq!(token.signers, publics);
+    assert_eq!(token.cosignatures.len(), 1);
+    assert!(verify(&token).allow);
+    assert!(verify(&two_of_three(&[1, 0, 2]).unwrap()).allow);
+
+    // Without a trust set, nothing shows the co-signers are independent.
+    let result = verify_token_untrusted(&token, read_req(), HashMap::new());
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+
+    // Fewer signatures than the threshold cannot be minted or verified.
+    assert!(two_of_three(&[1]).is_err());
+    let code = |t: &agent_safe_spl::Token| verify(t).code;
+    let stripped = agent_safe_spl::Token { cosignatures: Vec::new(), ..token.clone() };
+    assert_eq!(code(&stripped), Some(VerifyErrorCode::InvalidSignature));
+    let mut forged = token.clone();
+    forged.cosignatures[0].signature = token.signature.clone();
+    assert_eq!(code(&forged), Some(VerifyErrorCode::InvalidSignature));
+    let mut repeated = token.clone();
+    repeated.cosignatures[0].public_key = token.public_key.clone();
+    assert_eq!(code(&repeated), Some(VerifyErrorCode::MalformedToken));
+
+    // The declared signers and threshold are signed, so they cannot be relaxed.
+    let lowered = agent_safe_spl::Token { threshold: Some(1), cosignatures: Vec::new(), ..token.clone() };
+    assert_eq!(code(&lowered), Some(VerifyErrorCode::InvalidSignature));
+    let (outsider_pub, outsider_priv) = generate_keypair();
+    let opts = MintOptions { signers: publics.clone(), ..MintOptions::default() };
+    assert!(mint_multisig(policy, &[&keys[0].1, &outsider_priv], 2, opts).is_err());
+    let mut swapped = token.clone();
+    swapped.signers[1] = outsider_pub;
+    assert_eq!(code(&swapped), Some(VerifyErrorCode::InvalidSignature));
+
+    // Signers default to the keys that sign; the threshold must fit them.
+    let all = mint_multisig(policy, &[&keys[0].1, &keys[1].1], 2, MintOptions::default()).unwrap();
+    assert_eq!(all.signers, publics[..2]);
+    assert!(verify(&all).allow);
+    assert!(mint_multisig(policy, &[&keys[0].1], 2, MintOptions::default()).is_err());
+    assert!(mint_multisig(policy, &[], 1, MintOptions::default()).is_err());
+    let opts = MintOptions { signers: publics.clone(), ..MintOptions::default() };
+    assert!(mint(policy, &keys[0].1, opts).is_err());
+
+    // A trusted lead cannot meet the threshold with keys it generated.
+    let fabricated: Vec<(String, String)> = (0..2).map(|_| generate_keypair()).collect();
+    let opts = MintOptions {
+        signers: vec![publics[0].clone(), fabricated[0].0.clone(), fabricated[1].0.clone()],
+        ..MintOptions::default()
+    };
+    let sybil = mint_multisig(policy, &[&keys[0].1, &fabricated[0].1, &fabricated[1].1], 3, opts).unwrap();
+    let result = verify(&sybil);
+    assert!(!result.allow);
+    assert_eq!(result.code, Some(VerifyErrorCode::UntrustedIssuer));
+    assert!(result.error.unwrap().contains("untrusted co-signer"));
+}
+
+#[test]
+fn test_token_refresh() {
+    use agent_safe_spl::keys::Ed25519Signer;
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let signer = Ed25519Signer::from_hex(&issuer_priv).unwrap();
+    let opts = MintOptions { expires: Some("2099-01-01T00:00:00Z".into()), token_id: Some("grant".into()), ..MintOptions::default() };
+    let grant = mint("(= (get req \"action\") \"read\")", &issuer_priv, opts).unwrap();
+
+    let first = grant.refresh(&signer, "2098-01-01T00:00:00Z").unwrap();
+    assert_eq!(first.policy, grant.policy);
+    assert_eq!(first.expires.as_deref(), Some("2098-01-01T00:00:00Z"));
+    assert_eq!(first.refreshed_from.as_deref(), Some("grant"));
+    assert_eq!(first.refresh_depth, 1);
+    assert_ne!(first.token_id, grant.token_id);
+    assert!(verify_token_untrusted(&first, read_req(), HashMap::new()).allow);
+    let second = first.refresh(&signer, "2097-01-01T00:00:00Z").unwrap();
+    assert_eq!(second.refreshed_from, first.token_id);
+    assert_eq!(second.refresh_depth, 2);
+
+    // A refresh can only shorten, and only the issuer can refresh.
+    assert!(first.refresh(&signer, "2099-01-01T00:00:00Z").is_err());
+    assert!(first.refresh(&signer, "2000-01-01T00:00:00Z").is_err());
+    let (_, other_priv) = generate_keypair();
+    assert!(first.refresh(&Ed25519Signer::from_hex(&other_priv).unwrap(), "2097-01-01T00:00:00Z").is_err());
+
+    // The lineage is signed and capped by the verifier profile.
+    let code = |t: &agent_safe_spl::Token| verify_token_untrusted(t, read_req(), HashMap::new()).code;
+    let relinked = agent_safe_spl::Token { refreshed_from: Some("other".into()), ..first.clone() };
+    assert_eq!(code(&relinked), Some(VerifyErrorCode::InvalidSignature));
+    let unlinked = agent_safe_spl::Token { refreshed_from: None, ..first.clone() };
+    assert_eq!(code(&unlinked), Some(VerifyErrorCode::MalformedToken));
+    let verifier = Verifier::new(VerifierProfile { max_refresh_depth: Some(1), ..VerifierProfile::default() });
+    assert!(verifier.verify(&first, read_req(), HashMap::new(), None).allow);
+    let result = verifier.verify(&second, read_req(), HashMap::new(), None);
+    assert_eq!(result.code, Some(VerifyErrorCode::ChainLimit));
+    assert_eq!(result.error.as_deref(), Some("token refresh depth 2 exceeds maximum 1"));
+}
+
+#[test]
+fn test_not_before_and_issuer_epoch() {
+    use agent_safe_spl::profile::{Verifier, VerifierProfile};
+    use agent_safe_spl::time::FixedClock;
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (_, issuer_priv) = generate_keypair();
+    let policy = "(= (get req \"action\") \"read\")";
+    let mint_with = |opts: MintOptions| mint(policy, &issuer_priv, opts).unwrap();
+    let at = |now: i64, profile: VerifierProfile| Verifier::new(profile).with_clock(FixedClock(now));
+
+    let delayed = mint_with(MintOptions { not_before: Some("2026-04-08T00:00:00Z".into()), ..MintOptions::default() });
+    let check = |verifier: &Verifier, token: &agent_safe_spl::Token| verifier.verify(token, read_req(), HashMap::new(), None);
+    let early = check(&at(1_775_606_400 - 3600, VerifierProfile::default()), &delayed);
+    assert_eq!(early.code, Some(VerifyErrorCode::NotYetValid));
+    assert_eq!(early.error.as_deref(), Some("token is not valid before 2026-04-08T00:00:00Z"));
+    assert!(check(&at(1_775_606_400 - 30, VerifierProfile::default()), &delayed).allow, "within clock skew");
+    let opts = MintOptions {
+        not_before: Some("2026-05-01T00:00:00Z".into()),
+        expires: Some("2026-04-01T00:00:00Z".into()),
+        ..MintOptions::default()
+    };
+    assert!(mint(policy, &issuer_priv, opts).is_err());
+
+    // Raising the minimum epoch revokes tokens from earlier epochs, and
+    // tokens minted without an epoch count as epoch 0.
+    let old = mint_with(MintOptions { epoch: Some(1), ..MintOptions::default() });
+    let new = mint_with(MintOptions { epoch: Some(2), ..MintOptions::default() });
+    let unmarked = mint_with(MintOptions::default());
+    let verifier = at(1_775_606_400, VerifierProfile { min_issuer_epoch: Some(2), ..VerifierProfile::default() });
+    assert!(check(&verifier, &new).allow);
+    let revoked = check(&verifier, &old);
+    assert_eq!(revoked.code, Some(VerifyErrorCode::RevokedEpoch));
+    assert_eq!(revoked.error.as_deref(), Some("token epoch 1 is below minimum issuer epoch 2"));
+    assert_eq!(check(&verifier, &unmarked).code, Some(VerifyErrorCode::RevokedEpoch));
+    assert!(verify_token_untrusted(&old, read_req(), HashMap::new()).allow);
+
+    // Both fields are signed.
+    let bumped = agent_safe_spl::Token { epoch: Some(2), ..old.clone() };
+    assert_eq!(check(&verifier, &bumped).code, Some(VerifyErrorCode::InvalidSignature));
+    let moved = agent_safe_spl::Token { not_before: None, ..delayed.clone() };
+    assert_eq!(verify_token_untrusted(&moved, read_req(), HashMap::new()).code, Some(VerifyErrorCode::InvalidSignature));
+}
+
+#[test]
+fn test_legacy_tokens_keep_their_syntax() {
+    use agent_safe_spl::signature::SignatureScheme;
+    use agent_safe_spl::token::envelope_payload;
+
+    // Before 0.3 a string ends at the first quote, so this literal is `C:\`;
+    // under current syntax the same text is an unterminated string.
+    let (_, issuer_priv) = generate_keypair();
+    let policy = r#"(= (get req "path") "C:\")"#;
+    let current = mint(policy, &issuer_priv, MintOptions::default()).unwrap();
+    let req: HashMap<String, Node> = [("path".to_string(), Node::Str("C:\\".into()))].into();
+    assert!(!verify_token_untrusted(&current, req.clone(), HashMap::new()).allow);
+
+    let mut legacy = Token { version: "0.2.0".into(), ..current };
+    legacy.signature = SignatureScheme::Ed25519.sign(&issuer_priv, &envelope_payload(&legacy)).unwrap();
+    assert!(verify_token_untrusted(&legacy, req, HashMap::new()).allow);
+}
+
+#[test]
+fn test_verifier_reports_denylist_versions() {
+    use std::sync::Arc;
+
+    use agent_safe_spl::denylist::{DenyListVersion, InMemoryDenyLists};
+    use agent_safe_spl::issuers::{TrustedIssuers, TrustedKey};
+    use agent_safe_spl::profile::Verifier;
+    use agent_safe_spl::token::VerifyErrorCode;
+
+    let (issuer_pub, issuer_priv) = generate_keypair();
+    let token = mint(r#"(denylist-absent? (get req "merchant") "merchants")"#, &issuer_priv, MintOptions::default()).unwrap();
+    let mut lists = InMemoryDenyLists::new();
+    lists.load("merchants", "v42", ["evil.example.com"]);
+    let verifier = Verifier::default()
+        .with_trusted_issuers(Arc::new(TrustedIssuers::new().with_key(TrustedKey::new(&issuer_pub))))
+        .with_denylists(Arc::new(lists), 60);
+    let req = |merchant: &str| HashMap::from([("merchant".to_string(), Node::from(merchant))]);
+
+    let result = verifier.verify(&token, req("shop.example.com"), HashMap::new(), None);
+    assert!(result.allow, "{:?}", result.error);
+    assert_eq!(result.denylists, vec![DenyListVersion { list: "merchants".into(), version: "v42".into() }]);
+    let blocked = verifier.verify(&token, req("evil.example.com"), HashMap::new(), None);
+    assert!(!blocked.allow);
+    assert_eq!(blocked.denylists, result.denylists);
+
+    // Without a provider the operator fails closed.
+    let bare = Verifier::default().verify(&token, req("shop.example.com"), HashMap::new(), None);
+    assert_eq!(bare.code, Some(VerifyErrorCode::Evaluation));
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "tooling")]
+
+use agent_safe_spl::translate::from_cedar;
+use agent_safe_spl::types::{map_from_json, Node};
+use agent_safe_spl::{verify, Env};
+
+fn allows(policy: &Node, req: serde_json::Value) -> bool {
+    let env = Env { req: map_from_json(&req).unwrap(), strict: true, ..Env::default() };
+    verify(policy, &env).unwrap().allow
+}
+
+#[test]
+fn test_cedar_translation() {
+    let cedar = r#"
+        // Small gifts to family.
+        @id("gifts")
+        permit (
+            principal == Agent::"shopper",
+            action in [Action::"payments.create", Action::"payments.quote"],
+            resource
+        ) when {
+            context.amount <= 100 && ["mom@example.com", "niece@example.com"].contains(context.recipient)
+        } unless { context.flagged };
+
+        forbid (principal, action, resource) when { context.country == "XX" || context.amount > 5000 };
+    "#;
+    let translation = from_cedar(cedar).unwrap();
+    assert!(translation.is_complete(), "{:?}", translation.untranslated);
+    assert_eq!(
+        translation.policy.to_string(),
+        concat!(
+            r#"(and (= (get req "principal") "shopper") (member (get req "action") (tuple "payments.create" "payments.quote")) "#,
+            r#"(<= (get req "amount") 100) (member (get req "recipient") (tuple "mom@example.com" "niece@example.com")) "#,
+            r#"(not (= (get req "flagged") #t)) "#,
+            r#"(not (or (= (get req "country") "XX") (> (get req "amount") 5000))))"#,
+        )
+    );
+
+    let req = |amount: i64, country: &str| {
+        serde_json::json!({
+            "principal": "shopper", "action": "payments.create", "amount": amount,
+            "recipient": "mom@example.com", "flagged": false, "country": country,
+        })
+    };
+    assert!(allows(&translation.policy, req(50, "US")));
+    assert!(!allows(&translation.policy, req(150, "US")));
+    assert!(!allows(&translation.policy, req(50, "XX")));
+
+    // No permit means deny, as in Cedar.
+    assert_eq!(from_cedar("forbid (principal, action, resource);").unwrap().policy, Node::Bool(false));
+    assert_eq!(from_cedar("").unwrap().policy, Node::Bool(false));
+}
+
+#[test]
+fn test_cedar_untranslated_fails_closed() {
+    let cedar = r#"
+        permit (principal in Group::"admins", action, resource);
+        permit (principal, action == Action::"read", resource) when { context.tier == "gold" || resource.public };
+        forbid (principal, action, resource) when { context.ip.isInRange(ip("10.0.0.0/8")) };
+    "#;
+    let translation = from_cedar(cedar).unwrap();
+    let reported: Vec<(usize, &str)> =
+        translation.untranslated.iter().map(|u| (u.policy, u.construct.as_str())).collect();
+    assert_eq!(
+        reported,
+        [
+            (0, r#"entity hierarchy (`in` Group::"admins")"#),
+            (1, "entity attribute `resource.public`"),
+            (2, "method `.isInRange`"),
+        ]
+    );
+    // The hierarchy permit is dropped and the attribute cannot widen the
+    // disjunction, but the unknown forbid condition forbids everything.
+    assert_eq!(translation.policy, Node::Bool(false));
+    let permits_only = from_cedar(&cedar.lines().take(3).collect::<Vec<_>>().join("\n")).unwrap();
+    assert_eq!(permits_only.policy.to_string(), r#"(and (= (get req "action") "read") (= (get req "tier") "gold"))"#);
+    assert!(allows(&permits_only.policy, serde_json::json!({ "action": "read", "tier": "gold" })));
+    assert!(!allows(&permits_only.policy, serde_json::json!({ "action": "read", "tier": "silver" })));
+
+    let translation = from_cedar(r#"permit (principal, action, resource) unless { context.name like "bot-*" };"#).unwrap();
+    assert_eq!(translation.policy, Node::Bool(false));
+    assert_eq!(translation.untranslated[0].construct, r#"pattern match (`like "bot-*"`)"#);
+
+    assert!(from_cedar("permit (principal, action, resource) when { context.amount < };").is_err());
+    assert!(from_cedar("allow (principal, action, resource);").unwrap_err().0.contains("expected `permit` or `forbid`"));
+    assert!(from_cedar("permit (principal, action, resource)").unwrap_err().0.contains("end of input"));
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "usage")]
+
+use agent_safe_spl::usage::{CompactionReport, UsageLedger, UsageRecord};
+
+fn ledger() -> UsageLedger {
+    let ledger = UsageLedger::new();
+    for (day, amount) in [("2026-01-05", 10.0), ("2026-01-05", 5.0), ("2026-01-20", 7.5), ("2026-02-27", 1.0), ("2026-03-01", 2.0)] {
+        ledger.record("payments.create", day, amount).unwrap();
+    }
+    ledger.record("gifts, misc", "2026-01-02", 3.0).unwrap();
+    ledger
+}
+
+#[test]
+fn compaction_rolls_old_days_into_months() {
+    let ledger = ledger();
+    assert_eq!(ledger.count("payments.create", "2026-01-05"), 2);
+    let before = (ledger.spent("payments.create"), ledger.month_total("payments.create", "2026-01"));
+
+    let report = ledger.compact("2026-03-02", 7).unwrap();
+    assert_eq!(report, CompactionReport { days_compacted: 3, months_written: 2 });
+    // Days inside the window keep their daily counts; older ones are gone.
+    assert_eq!(ledger.count("payments.create", "2026-02-27"), 1);
+    assert_eq!(ledger.count("payments.create", "2026-01-05"), 0);
+    // Monthly and lifetime totals are unchanged.
+    assert_eq!((ledger.spent("payments.create"), ledger.month_total("payments.create", "2026-01")), before);
+    assert_eq!(ledger.month_total("payments.create", "2026-01"), (3, 22.5));
+    assert_eq!(ledger.month_total("gifts, misc", "2026-01"), (1, 3.0));
+
+    let periods: Vec<String> = ledger.records().into_iter().map(|r| format!("{} {}", r.key, r.period)).collect();
+    assert_eq!(
+        periods,
+        ["gifts, misc 2026-01", "payments.create 2026-01", "payments.create 2026-02-27", "payments.create 2026-03-01"]
+    );
+
+    // Compacting again later merges into the existing month rows.
+    ledger.record("payments.create", "2026-01-31", 1.0).unwrap();
+    assert_eq!(ledger.compact("2026-03-02", 7).unwrap().days_compacted, 1);
+    assert_eq!(ledger.month_total("payments.create", "2026-01"), (4, 23.5));
+
+    assert!(ledger.compact("March 2", 7).is_err());
+    assert!(ledger.record("k", "2026-02-30", 1.0).is_err());
+}
+
+#[test]
+fn records_round_trip() {
+    let ledger = ledger();
+    ledger.compact("2026-03-02", 30).unwrap();
+    let json = serde_json::to_string(&ledger.records()).unwrap();
+    let records: Vec<UsageRecord> = serde_json::from_str(&json).unwrap();
+    let restored = UsageLedger::from_records(records).unwrap();
+    assert_eq!(restored.records(), ledger.records());
+
+    let bad = UsageRecord { key: "k".into(), period: "2026".into(), count: 1, amount: 0.0 };
+    assert!(UsageLedger::from_records([bad]).is_err());
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn export_csv() {
+    let ledger = ledger();
+    ledger.compact("2026-03-02", 30).unwrap();
+    let mut csv = Vec::new();
+    ledger.export_csv(&mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "key,period,count,amount\n\"gifts, misc\",2026-01,1,3\npayments.create,2026-01,3,22.5\npayments.create,2026-02-27,1,1\npayments.create,2026-03-01,1,2\n"
+    );
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn export_parquet() {
+    use parquet::file::reader::SerializedFileReader;
+    use parquet::record::RowAccessor;
+
+    let ledger = ledger();
+    ledger.compact("2026-03-02", 30).unwrap();
+    let path = std::env::temp_dir().join(format!("agent-safe-usage-{}.parquet", std::process::id()));
+    ledger.export_parquet(std::fs::File::create(&path).unwrap()).unwrap();
+
+    let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let rows: Vec<UsageRecord> = reader
+        .into_iter()
+        .map(|row| {
+            let row = row.unwrap();
+            UsageRecord {
+                key: row.get_string(0).unwrap().clone(),
+                period: row.get_string(1).unwrap().clone(),
+                count: row.get_long(2).unwrap(),
+                amount: row.get_double(3).unwrap(),
+            }
+        })
+        .collect();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(rows, ledger.records());
+}
+
+#[test]
+fn ledger_tracks_cumulative_spend() {
+    use agent_safe_spl::spend::SpendTracker;
+
+    let ledger = ledger();
+    assert_eq!(ledger.spent_between("payments.create", "2026-01-01", "2026-01-31").unwrap(), 22.5);
+    SpendTracker::record(&ledger, "payments.create", "2026-03-02", 4.0).unwrap();
+    assert_eq!(ledger.count("payments.create", "2026-03-02"), 1);
+
+    ledger.compact("2026-03-02", 7).unwrap();
+    // Whole compacted months still sum exactly; part of one cannot.
+    assert_eq!(ledger.spent_between("payments.create", "2026-01-01", "2026-01-31").unwrap(), 22.5);
+    assert_eq!(ledger.spent_between("payments.create", "2026-02-23", "2026-03-08").unwrap(), 7.0);
+    assert!(ledger.spent_between("payments.create", "2026-01-12", "2026-01-18").is_err());
+}
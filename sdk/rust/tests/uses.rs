@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use agent_safe_spl::crypto::HashChain;
+use agent_safe_spl::profile::Verifier;
+use agent_safe_spl::token::{generate_keypair, mint, verify_token_use, MintOptions, Token, VerifyErrorCode};
+use agent_safe_spl::types::Node;
+use agent_safe_spl::uses::{check_use, InMemoryUsageStore, UsageStore};
+
+fn limited(uses: usize) -> (Token, HashChain) {
+    let (_, issuer) = generate_keypair();
+    let chain = HashChain::generate_random(uses).unwrap();
+    let opts = MintOptions { hash_chain_commitment: Some(chain.commitment()), ..MintOptions::default() };
+    (mint(r#"(<= (get req "amount") 100)"#, &issuer, opts).unwrap(), chain)
+}
+
+fn req(amount: f64) -> HashMap<String, Node> {
+    HashMap::from([("amount".to_string(), Node::Number(amount))])
+}
+
+#[test]
+fn test_chain_limits_uses() {
+    let (token, chain) = limited(3);
+    let store = Arc::new(InMemoryUsageStore::new());
+    let verifier = Verifier::default().with_usage_store(store.clone());
+    let spend = |preimage: &str| verifier.verify_use(&token, req(10.0), HashMap::new(), None, preimage);
+
+    let first = chain.for_use(0).unwrap();
+    assert!(spend(&first).allow);
+    // A spent preimage does not verify again, nor does one out of order.
+    assert_eq!(spend(&first).code, Some(VerifyErrorCode::UseLimit));
+    assert_eq!(spend(&chain.for_use(2).unwrap()).code, Some(VerifyErrorCode::UseLimit));
+    assert!(spend(&chain.for_use(1).unwrap()).allow);
+
+    // A denied request does not consume its use.
+    let last = chain.for_use(2).unwrap();
+    assert!(!verifier.verify_use(&token, req(500.0), HashMap::new(), None, &last).allow);
+    assert_eq!(store.uses(&token.hash_chain_commitment.clone().unwrap()).unwrap(), 2);
+    assert!(spend(&last).allow);
+
+    // The seed was the last use; nothing the agent holds verifies now.
+    assert_eq!(chain.for_use(3), None);
+    let result = spend(&last);
+    assert_eq!(result.code, Some(VerifyErrorCode::UseLimit));
+    assert!(result.error.unwrap().contains("uses are spent"));
+}
+
+#[test]
+fn test_use_limits_need_a_store_and_a_preimage() {
+    let (token, chain) = limited(1);
+    let store = InMemoryUsageStore::new();
+
+    // A verifier enforcing use limits wants the preimage.
+    let verifier = Verifier::default().with_usage_store(Arc::new(InMemoryUsageStore::new()));
+    assert_eq!(verifier.verify(&token, req(10.0), HashMap::new(), None).code, Some(VerifyErrorCode::UseLimit));
+    // One that does not ignores the commitment.
+    assert!(Verifier::default().verify(&token, req(10.0), HashMap::new(), None).allow);
+
+    let once = chain.for_use(0).unwrap();
+    assert!(verify_token_use(&token, req(10.0), HashMap::new(), &store, &once).allow);
+    assert!(!verify_token_use(&token, req(10.0), HashMap::new(), &store, &once).allow);
+    assert!(!verify_token_use(&token, req(10.0), HashMap::new(), &store, "not hex").allow);
+}
+
+#[test]
+fn test_usage_store_records_each_use_once() {
+    let chain = HashChain::generate("00", 2).unwrap();
+    let commitment = chain.commitment();
+    assert!(check_use(&commitment, &chain.for_use(0).unwrap(), 0));
+    assert!(check_use(&commitment, &chain.for_use(1).unwrap(), 1));
+    assert!(!check_use(&commitment, &chain.for_use(1).unwrap(), 0));
+
+    let store = InMemoryUsageStore::new();
+    assert!(store.record_use(&commitment, 0).unwrap());
+    // A verifier that read the count before this use loses the race.
+    assert!(!store.record_use(&commitment, 0).unwrap());
+    assert!(!store.record_use(&commitment, 2).unwrap());
+    assert_eq!(store.uses(&commitment).unwrap(), 1);
+    assert_eq!(store.uses("other").unwrap(), 0);
+}
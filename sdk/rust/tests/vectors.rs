@@ -0,0 +1,49 @@
+#![cfg(feature = "tooling")]
+
+use std::fs;
+use std::path::Path;
+
+use agent_safe_spl::vectors::{all_vectors, signing_vectors, SigningVectors};
+
+const VECTORS_PATH: &str = "../../examples/crypto/signing_vectors.json";
+
+#[test]
+fn test_signing_vectors_match_published() {
+    let path = Path::new(VECTORS_PATH);
+    if !path.exists() {
+        eprintln!("Skipping: signing vectors not found");
+        return;
+    }
+    let published: SigningVectors =
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let regenerated = signing_vectors().unwrap();
+    assert_eq!(regenerated, published, "regenerate with `cargo run --example gen_vectors`");
+}
+
+#[test]
+fn test_all_vectors_match_published() {
+    let dir = Path::new("../../examples/crypto");
+    for (name, regenerated) in all_vectors().unwrap() {
+        let path = dir.join(name);
+        if !path.exists() {
+            eprintln!("Skipping: {name} not found");
+            continue;
+        }
+        let published: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(regenerated, published, "{name}: regenerate with `agent-safe gen-vectors`");
+    }
+}
+
+#[test]
+fn test_signing_vectors_cover_outcomes() {
+    let v = signing_vectors().unwrap();
+    let outcome = |name: &str| {
+        let c = v.verify_cases.iter().find(|c| c.name == name).unwrap();
+        (c.expected_allow, c.expected_error.clone())
+    };
+    assert_eq!(outcome("allow_within_limit"), (true, None));
+    assert_eq!(outcome("deny_over_limit"), (false, None));
+    assert_eq!(outcome("tampered_expires").1.as_deref(), Some("invalid signature"));
+    assert_eq!(outcome("wrong_nonce").1.as_deref(), Some("invalid presentation signature"));
+    assert!(!outcome("missing_presentation").0);
+}